@@ -3,26 +3,41 @@
 //! This binary implements the ACP Agent trait and communicates over stdin/stdout.
 //! It supports:
 //! - Basic initialization and session creation
-//! - Simple prompts that complete immediately
+//! - Scripted prompts: output, tool calls, permission requests, artifacts,
+//!   failures and crashes, read from a scenario file (see [`scenario`])
 //! - Prompts containing "permission" that trigger permission requests
 //! - Event-driven task processing
 //! - Cancellation handling
 
+mod scenario;
+
+use std::cell::OnceCell;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use agent_client_protocol::{
     Agent, AgentSideConnection, AuthenticateRequest, AuthenticateResponse, CancelNotification,
-    ContentBlock, ExtNotification, ExtRequest, ExtResponse, Implementation, InitializeRequest,
-    InitializeResponse, LoadSessionRequest, LoadSessionResponse, NewSessionRequest,
-    NewSessionResponse, PromptRequest, PromptResponse, SessionId, SetSessionConfigOptionRequest,
-    SetSessionConfigOptionResponse, SetSessionModeRequest, SetSessionModeResponse, StopReason,
+    Client, ContentBlock, ContentChunk, ExtNotification, ExtRequest, ExtResponse, Implementation,
+    InitializeRequest, InitializeResponse, LoadSessionRequest, LoadSessionResponse,
+    NewSessionRequest, NewSessionResponse, PermissionOption, PermissionOptionId,
+    PermissionOptionKind, PromptRequest, PromptResponse, RequestPermissionOutcome,
+    RequestPermissionRequest, SessionId, SessionNotification, SessionUpdate,
+    SetSessionConfigOptionRequest, SetSessionConfigOptionResponse, SetSessionModeRequest,
+    SetSessionModeResponse, StopReason, TextContent, ToolCall, ToolCallId, ToolCallStatus,
+    ToolCallUpdate, ToolCallUpdateFields, ToolKind,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, value::RawValue};
 use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 use tracing::{debug, error, info};
 
+use scenario::{OnDeny, Scenario, Step};
+
+/// Option ID that grants a permission request
+const ALLOW_OPTION: &str = "allow";
+const REJECT_OPTION: &str = "reject";
+
 /// Event structure matching server-side Event
 #[derive(Debug, Clone, Deserialize, Serialize)]
 struct Event {
@@ -40,14 +55,166 @@ struct Event {
 struct MockAgent {
     sessions: Arc<Mutex<Vec<SessionId>>>,
     cancelled: Arc<Mutex<Vec<SessionId>>>,
+    /// Played for every prompt; the built-in one when unset
+    scenario: Option<Scenario>,
+    /// Set once the connection exists, which needs the agent first
+    conn: Rc<OnceCell<Rc<AgentSideConnection>>>,
 }
 
 impl MockAgent {
-    fn new() -> Self {
+    fn new(scenario: Option<Scenario>) -> Self {
         Self {
             sessions: Arc::new(Mutex::new(Vec::new())),
             cancelled: Arc::new(Mutex::new(Vec::new())),
+            scenario,
+            conn: Rc::new(OnceCell::new()),
+        }
+    }
+
+    fn conn(&self) -> agent_client_protocol::Result<Rc<AgentSideConnection>> {
+        self.conn
+            .get()
+            .cloned()
+            .ok_or_else(|| agent_client_protocol::Error::internal_error().data("not connected"))
+    }
+
+    fn is_cancelled(&self, session_id: &SessionId) -> bool {
+        self.cancelled.lock().unwrap().contains(session_id)
+    }
+
+    async fn notify(
+        &self,
+        session_id: &SessionId,
+        update: SessionUpdate,
+    ) -> agent_client_protocol::Result<()> {
+        let notification = SessionNotification::new(session_id.clone(), update);
+        self.conn()?.session_notification(notification).await
+    }
+
+    async fn send_text(
+        &self,
+        session_id: &SessionId,
+        text: &str,
+        thought: bool,
+    ) -> agent_client_protocol::Result<()> {
+        let chunk = ContentChunk::new(ContentBlock::Text(TextContent::new(text.to_string())));
+        let update = if thought {
+            SessionUpdate::AgentThoughtChunk(chunk)
+        } else {
+            SessionUpdate::AgentMessageChunk(chunk)
+        };
+        self.notify(session_id, update).await
+    }
+
+    /// A tool call that completes right away with `output`
+    async fn run_tool_call(
+        &self,
+        session_id: &SessionId,
+        id: ToolCallId,
+        title: &str,
+        input: &serde_json::Value,
+        output: &str,
+    ) -> agent_client_protocol::Result<()> {
+        let call = ToolCall::new(id.clone(), title.to_string())
+            .kind(ToolKind::Execute)
+            .status(ToolCallStatus::InProgress)
+            .raw_input(input.clone());
+        self.notify(session_id, SessionUpdate::ToolCall(call)).await?;
+
+        let fields = ToolCallUpdateFields::new()
+            .status(ToolCallStatus::Completed)
+            .raw_output(json!(output));
+        self.notify(session_id, SessionUpdate::ToolCallUpdate(ToolCallUpdate::new(id, fields)))
+            .await
+    }
+
+    /// Ask the client to allow a tool call; true when an allow option was picked
+    async fn ask_permission(
+        &self,
+        session_id: &SessionId,
+        id: ToolCallId,
+        title: &str,
+        input: &serde_json::Value,
+    ) -> agent_client_protocol::Result<bool> {
+        let tool_call = ToolCallUpdate::new(
+            id,
+            ToolCallUpdateFields::new()
+                .title(title.to_string())
+                .raw_input(input.clone()),
+        );
+        let options = vec![
+            PermissionOption::new(
+                PermissionOptionId::new(ALLOW_OPTION),
+                "Allow",
+                PermissionOptionKind::AllowOnce,
+            ),
+            PermissionOption::new(
+                PermissionOptionId::new(REJECT_OPTION),
+                "Reject",
+                PermissionOptionKind::RejectOnce,
+            ),
+        ];
+        let request = RequestPermissionRequest::new(session_id.clone(), tool_call, options);
+        let response = self.conn()?.request_permission(request).await?;
+        let allowed = match response.outcome {
+            RequestPermissionOutcome::Selected(selected) => {
+                selected.option_id.to_string() == ALLOW_OPTION
+            }
+            _ => false,
+        };
+        info!(title = %title, allowed, "permission answered");
+        Ok(allowed)
+    }
+
+    /// Play `scenario` for one prompt
+    async fn play(
+        &self,
+        session_id: &SessionId,
+        scenario: &Scenario,
+    ) -> agent_client_protocol::Result<StopReason> {
+        for (index, step) in scenario.steps.iter().enumerate() {
+            if self.is_cancelled(session_id) {
+                return Ok(StopReason::Cancelled);
+            }
+            let tool_call_id = ToolCallId::new(format!("mock-tool-{}", index));
+            debug!(index, step = ?step, "playing scenario step");
+            match step {
+                Step::Output { text } => self.send_text(session_id, text, false).await?,
+                Step::Thought { text } => self.send_text(session_id, text, true).await?,
+                Step::ToolCall { title, input, output } => {
+                    self.run_tool_call(session_id, tool_call_id, title, input, output)
+                        .await?
+                }
+                Step::Permission { title, input, on_deny } => {
+                    if self.ask_permission(session_id, tool_call_id, title, input).await? {
+                        continue;
+                    }
+                    match on_deny {
+                        OnDeny::EndTurn => return Ok(StopReason::EndTurn),
+                        OnDeny::Fail => {
+                            return Err(agent_client_protocol::Error::internal_error()
+                                .data(format!("permission denied: {}", title)));
+                        }
+                        OnDeny::Continue => {}
+                    }
+                }
+                Step::Artifact { url } => {
+                    let input = json!({ "command": "gh pr create --fill" });
+                    self.run_tool_call(session_id, tool_call_id, "gh pr create", &input, url)
+                        .await?
+                }
+                Step::Sleep { ms } => tokio::time::sleep(Duration::from_millis(*ms)).await,
+                Step::Fail { message } => {
+                    let error = agent_client_protocol::Error::internal_error();
+                    return Err(error.data(message.clone()));
+                }
+                Step::Exit { code } => {
+                    error!(code, "scenario exit");
+                    std::process::exit(*code);
+                }
+            }
         }
+        Ok(StopReason::EndTurn)
     }
 
     /// Handle task.created event
@@ -172,24 +339,14 @@ impl Agent for MockAgent {
 
         debug!("prompt text: {}", prompt_text);
 
-        // Check if prompt contains "permission" - if so, request permission
-        if prompt_text.to_lowercase().contains("permission") {
-            info!("requesting permission...");
-
-            // We need to get the connection to request permission
-            // This is handled via the Client trait implementation on the connection
-            // For now, we'll just return EndTurn since we can't easily access the connection here
-            // The actual permission request would be done through session_notification
+        let scenario = match &self.scenario {
+            Some(scenario) => scenario.clone(),
+            None => Scenario::builtin(&prompt_text),
+        };
+        let stop_reason = self.play(&args.session_id, &scenario).await?;
 
-            // Return with end turn - in a real implementation, we would:
-            // 1. Send a tool call notification
-            // 2. Request permission
-            // 3. Wait for response
-            // 4. Complete the tool call
-        }
-
-        info!("prompt completed");
-        Ok(PromptResponse::new(StopReason::EndTurn))
+        info!(stop_reason = ?stop_reason, "prompt completed");
+        Ok(PromptResponse::new(stop_reason))
     }
 
     async fn cancel(
@@ -274,15 +431,26 @@ async fn main() {
 
     info!("Mock agent starting...");
 
+    // Unknown arguments (e.g. a real agent's flags) are ignored
+    let args: Vec<String> = std::env::args().collect();
+    let scenario = match Scenario::from_args_or_env(&args) {
+        Ok(scenario) => scenario,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
     let local = tokio::task::LocalSet::new();
     local
         .run_until(async {
-            let agent = MockAgent::new();
+            let agent = MockAgent::new(scenario);
+            let conn_cell = agent.conn.clone();
 
             let stdin = tokio::io::stdin();
             let stdout = tokio::io::stdout();
 
-            let (_conn, io_task) = AgentSideConnection::new(
+            let (conn, io_task) = AgentSideConnection::new(
                 agent,
                 stdout.compat_write(),
                 stdin.compat(),
@@ -291,6 +459,8 @@ async fn main() {
                 },
             );
 
+            let _ = conn_cell.set(Rc::new(conn));
+
             // Spawn IO task
             tokio::task::spawn_local(async move {
                 if let Err(e) = io_task.await {
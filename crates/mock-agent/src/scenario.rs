@@ -0,0 +1,169 @@
+//! Scripted prompt handling.
+//!
+//! A scenario file (JSON) lists the steps the mock agent plays for every
+//! prompt it receives. It is picked with `--scenario <path>` or the
+//! `MOCK_AGENT_SCENARIO` environment variable; without one the agent answers
+//! with a single message, and prompts mentioning "permission" ask for one.
+//!
+//! ```json
+//! {
+//!   "steps": [
+//!     { "type": "output", "text": "Looking at the repository..." },
+//!     { "type": "permission", "title": "Run cargo test" },
+//!     { "type": "artifact", "url": "https://github.com/acme/app/pull/7" },
+//!     { "type": "fail", "message": "out of credits" }
+//!   ]
+//! }
+//! ```
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// Environment variable naming the scenario file
+pub const SCENARIO_ENV: &str = "MOCK_AGENT_SCENARIO";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Scenario {
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Step {
+    /// Assistant message chunk
+    Output { text: String },
+    /// Thinking chunk
+    Thought { text: String },
+    /// Tool call that runs to completion with `output` as its result
+    ToolCall {
+        title: String,
+        #[serde(default)]
+        input: serde_json::Value,
+        #[serde(default)]
+        output: String,
+    },
+    /// Ask for permission to run a tool call
+    Permission {
+        title: String,
+        #[serde(default)]
+        input: serde_json::Value,
+        /// What to do when the answer is not an allow option
+        #[serde(default)]
+        on_deny: OnDeny,
+    },
+    /// Tool call whose output contains `url`, so the relay reports an
+    /// artifact
+    Artifact { url: String },
+    /// Wait before the next step
+    Sleep { ms: u64 },
+    /// Fail the prompt with an error
+    Fail { message: String },
+    /// Exit the process, as a crashing agent would
+    Exit {
+        #[serde(default = "default_exit_code")]
+        code: i32,
+    },
+}
+
+fn default_exit_code() -> i32 {
+    1
+}
+
+/// How a scenario continues after a permission is denied or cancelled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnDeny {
+    /// End the turn without running the remaining steps
+    #[default]
+    EndTurn,
+    /// Fail the prompt
+    Fail,
+    /// Run the remaining steps anyway
+    Continue,
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read scenario {}: {}", path.display(), e))?;
+        Self::parse(&content).map_err(|e| format!("invalid scenario {}: {}", path.display(), e))
+    }
+
+    pub fn parse(content: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(content)
+    }
+
+    /// The scenario from `--scenario <path>` or [`SCENARIO_ENV`], if any
+    pub fn from_args_or_env(args: &[String]) -> Result<Option<Self>, String> {
+        let path = args
+            .iter()
+            .position(|arg| arg == "--scenario")
+            .and_then(|i| args.get(i + 1).cloned())
+            .or_else(|| std::env::var(SCENARIO_ENV).ok().filter(|p| !p.is_empty()));
+        path.map(|path| Self::load(Path::new(&path))).transpose()
+    }
+
+    /// What a prompt gets without a scenario file
+    pub fn builtin(prompt: &str) -> Self {
+        let mut steps = Vec::new();
+        if prompt.to_lowercase().contains("permission") {
+            steps.push(Step::Permission {
+                title: "mock command".to_string(),
+                input: serde_json::json!({ "command": "echo mock" }),
+                on_deny: OnDeny::EndTurn,
+            });
+        }
+        steps.push(Step::Output {
+            text: "mock agent done".to_string(),
+        });
+        Self { steps }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let scenario = Scenario::parse(
+            r#"{"steps": [
+                {"type": "output", "text": "hi"},
+                {"type": "permission", "title": "rm", "on_deny": "fail"},
+                {"type": "tool_call", "title": "ls", "output": "a b"},
+                {"type": "sleep", "ms": 10},
+                {"type": "exit"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(scenario.steps.len(), 5);
+        assert_eq!(
+            scenario.steps[1],
+            Step::Permission {
+                title: "rm".to_string(),
+                input: serde_json::Value::Null,
+                on_deny: OnDeny::Fail,
+            }
+        );
+        assert_eq!(scenario.steps[4], Step::Exit { code: 1 });
+
+        assert!(Scenario::parse(r#"{"steps": [{"type": "dance"}]}"#).is_err());
+    }
+
+    #[test]
+    fn test_builtin() {
+        assert_eq!(Scenario::builtin("hello").steps.len(), 1);
+        let steps = Scenario::builtin("needs Permission").steps;
+        assert!(matches!(steps[0], Step::Permission { .. }));
+    }
+
+    #[test]
+    fn test_example_scenarios_parse() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            Scenario::load(&path).unwrap();
+        }
+    }
+}
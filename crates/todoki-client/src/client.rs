@@ -0,0 +1,360 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use todoki_protocol::event_bus::EventMessage;
+use uuid::Uuid;
+
+use crate::error::{ClientError, Result};
+use crate::models::*;
+use crate::subscriber::{EventSubscriber, SubscribeOptions};
+
+/// Typed async client for the todoki REST API
+#[derive(Clone)]
+pub struct TodokiClient {
+    http_client: reqwest::Client,
+    base_url: String,
+    token: String,
+}
+
+impl TodokiClient {
+    /// Create a client for `base_url` (e.g. `https://todoki.example.com`)
+    pub fn new(base_url: &str, token: &str) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    /// Base URL of the server
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Create a WebSocket event subscriber sharing this client's credentials
+    pub fn subscriber(&self, options: SubscribeOptions) -> EventSubscriber {
+        EventSubscriber::new(&self.base_url, &self.token, options)
+    }
+
+    // ========================================================================
+    // Tasks
+    // ========================================================================
+
+    pub async fn list_tasks(&self) -> Result<Vec<Task>> {
+        self.get("/api/tasks", &()).await
+    }
+
+    pub async fn list_inbox_tasks(&self) -> Result<Vec<Task>> {
+        self.get("/api/tasks/inbox", &()).await
+    }
+
+    pub async fn list_backlog_tasks(&self) -> Result<Vec<Task>> {
+        self.get("/api/tasks/backlog", &()).await
+    }
+
+    pub async fn list_in_progress_tasks(&self) -> Result<Vec<Task>> {
+        self.get("/api/tasks/in-progress", &()).await
+    }
+
+    pub async fn list_done_tasks(&self) -> Result<Vec<Task>> {
+        self.get("/api/tasks/done", &()).await
+    }
+
+    pub async fn list_today_done_tasks(&self) -> Result<Vec<Task>> {
+        self.get("/api/tasks/done/today", &()).await
+    }
+
+    pub async fn get_task(&self, task_id: Uuid) -> Result<Task> {
+        self.get(&format!("/api/tasks/{}", task_id), &()).await
+    }
+
+    pub async fn create_task(&self, req: &CreateTaskRequest) -> Result<Task> {
+        self.post("/api/tasks", req).await
+    }
+
+    pub async fn update_task(&self, task_id: Uuid, req: &UpdateTaskRequest) -> Result<Task> {
+        self.put(&format!("/api/tasks/{}", task_id), req).await
+    }
+
+    pub async fn update_task_status(&self, task_id: Uuid, status: &str) -> Result<Task> {
+        self.post(
+            &format!("/api/tasks/{}/status", task_id),
+            &serde_json::json!({ "status": status }),
+        )
+        .await
+    }
+
+    pub async fn archive_task(&self, task_id: Uuid) -> Result<Task> {
+        self.post(&format!("/api/tasks/{}/archive", task_id), &()).await
+    }
+
+    pub async fn unarchive_task(&self, task_id: Uuid) -> Result<Task> {
+        self.post(&format!("/api/tasks/{}/unarchive", task_id), &())
+            .await
+    }
+
+    pub async fn delete_task(&self, task_id: Uuid) -> Result<()> {
+        self.delete(&format!("/api/tasks/{}", task_id)).await
+    }
+
+    pub async fn add_comment(&self, task_id: Uuid, content: &str) -> Result<TaskComment> {
+        self.post(
+            &format!("/api/tasks/{}/comments", task_id),
+            &serde_json::json!({ "content": content }),
+        )
+        .await
+    }
+
+    pub async fn execute_task(&self, task_id: Uuid) -> Result<ExecuteTaskResponse> {
+        self.post(&format!("/api/tasks/{}/execute", task_id), &()).await
+    }
+
+    pub async fn get_task_execution(&self, task_id: Uuid) -> Result<TaskExecution> {
+        self.get(&format!("/api/tasks/{}/execution", task_id), &())
+            .await
+    }
+
+    // ========================================================================
+    // Projects
+    // ========================================================================
+
+    pub async fn list_projects(&self, include_archived: bool) -> Result<Vec<Project>> {
+        self.get("/api/projects", &[("include_archived", include_archived)])
+            .await
+    }
+
+    pub async fn get_project(&self, project_id: Uuid) -> Result<Project> {
+        self.get(&format!("/api/projects/{}", project_id), &()).await
+    }
+
+    pub async fn get_project_by_name(&self, name: &str) -> Result<Option<Project>> {
+        self.get(&format!("/api/projects/by-name/{}", name), &()).await
+    }
+
+    pub async fn create_project(&self, req: &CreateProjectRequest) -> Result<Project> {
+        self.post("/api/projects", req).await
+    }
+
+    pub async fn update_project(
+        &self,
+        project_id: Uuid,
+        req: &UpdateProjectRequest,
+    ) -> Result<Project> {
+        self.put(&format!("/api/projects/{}", project_id), req).await
+    }
+
+    pub async fn delete_project(&self, project_id: Uuid) -> Result<()> {
+        self.delete(&format!("/api/projects/{}", project_id)).await
+    }
+
+    pub async fn list_project_done_tasks(
+        &self,
+        project_id: Uuid,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<Task>> {
+        self.get(
+            &format!("/api/projects/{}/tasks/done", project_id),
+            &[("offset", offset), ("limit", limit)],
+        )
+        .await
+    }
+
+    pub async fn list_artifacts(
+        &self,
+        project_id: Uuid,
+        artifact_type: Option<&str>,
+    ) -> Result<Vec<Artifact>> {
+        self.get(
+            &format!("/api/projects/{}/artifacts", project_id),
+            &[("type", artifact_type)],
+        )
+        .await
+    }
+
+    pub async fn get_artifact(&self, artifact_id: Uuid) -> Result<Artifact> {
+        self.get(&format!("/api/artifacts/{}", artifact_id), &()).await
+    }
+
+    // ========================================================================
+    // Agents
+    // ========================================================================
+
+    pub async fn list_agents(&self) -> Result<Vec<Agent>> {
+        self.get("/api/agents", &()).await
+    }
+
+    pub async fn get_agent(&self, agent_id: Uuid) -> Result<Agent> {
+        self.get(&format!("/api/agents/{}", agent_id), &()).await
+    }
+
+    pub async fn create_agent(&self, req: &CreateAgentRequest) -> Result<CreateAgentResponse> {
+        self.post("/api/agents", req).await
+    }
+
+    pub async fn delete_agent(&self, agent_id: Uuid) -> Result<()> {
+        self.delete(&format!("/api/agents/{}", agent_id)).await
+    }
+
+    pub async fn start_agent(&self, agent_id: Uuid) -> Result<AgentSession> {
+        self.post(&format!("/api/agents/{}/start", agent_id), &()).await
+    }
+
+    pub async fn stop_agent(&self, agent_id: Uuid) -> Result<()> {
+        let _: Value = self
+            .post(&format!("/api/agents/{}/stop", agent_id), &())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn list_agent_sessions(&self, agent_id: Uuid) -> Result<Vec<AgentSession>> {
+        self.get(&format!("/api/agents/{}/sessions", agent_id), &())
+            .await
+    }
+
+    // ========================================================================
+    // Relays
+    // ========================================================================
+
+    pub async fn list_relays(&self) -> Result<Vec<Relay>> {
+        self.get("/api/relays", &()).await
+    }
+
+    pub async fn get_relay(&self, relay_id: &str) -> Result<Relay> {
+        self.get(&format!("/api/relays/{}", relay_id), &()).await
+    }
+
+    pub async fn list_project_relays(&self, project_id: Uuid) -> Result<Vec<Relay>> {
+        self.get(&format!("/api/projects/{}/relays", project_id), &())
+            .await
+    }
+
+    // ========================================================================
+    // Report
+    // ========================================================================
+
+    pub async fn get_report(&self, period: &str) -> Result<Report> {
+        self.get("/api/report", &[("period", period)]).await
+    }
+
+    // ========================================================================
+    // Event Bus
+    // ========================================================================
+
+    pub async fn query_events(&self, query: &EventQuery) -> Result<EventQueryResponse> {
+        let kinds = query.kinds.as_ref().map(|k| k.join(","));
+        let mut params: Vec<(&str, String)> = vec![("cursor", query.cursor.to_string())];
+        if let Some(kinds) = kinds {
+            params.push(("kinds", kinds));
+        }
+        if let Some(agent_id) = query.agent_id {
+            params.push(("agent_id", agent_id.to_string()));
+        }
+        if let Some(task_id) = query.task_id {
+            params.push(("task_id", task_id.to_string()));
+        }
+        if let Some(limit) = query.limit {
+            params.push(("limit", limit.to_string()));
+        }
+        self.get("/api/event-bus", &params).await
+    }
+
+    pub async fn latest_cursor(&self) -> Result<i64> {
+        self.get("/api/event-bus/latest", &()).await
+    }
+
+    /// Emit a typed protocol event
+    pub async fn emit(&self, message: &EventMessage) -> Result<i64> {
+        self.post("/api/event-bus/emit", message).await
+    }
+
+    /// Emit a raw event by kind
+    pub async fn emit_raw(
+        &self,
+        kind: &str,
+        data: Value,
+        agent_id: Uuid,
+        task_id: Option<Uuid>,
+    ) -> Result<i64> {
+        self.post(
+            "/api/event-bus/emit",
+            &serde_json::json!({
+                "kind": kind,
+                "data": data,
+                "agent_id": agent_id,
+                "task_id": task_id,
+            }),
+        )
+        .await
+    }
+
+    // ========================================================================
+    // Internal helpers
+    // ========================================================================
+
+    async fn get<Q: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &Q,
+    ) -> Result<T> {
+        let req = self
+            .http_client
+            .get(format!("{}{}", self.base_url, path))
+            .query(query);
+        self.send(req).await
+    }
+
+    async fn post<B: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T> {
+        let req = self
+            .http_client
+            .post(format!("{}{}", self.base_url, path))
+            .json(body);
+        self.send(req).await
+    }
+
+    async fn put<B: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T> {
+        let req = self
+            .http_client
+            .put(format!("{}{}", self.base_url, path))
+            .json(body);
+        self.send(req).await
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        let req = self
+            .http_client
+            .delete(format!("{}{}", self.base_url, path));
+        let _: Value = self.send(req).await?;
+        Ok(())
+    }
+
+    async fn send<T: DeserializeOwned>(&self, req: reqwest::RequestBuilder) -> Result<T> {
+        let resp = req
+            .header("Authorization", format!("Bearer {}", self.token))
+            .send()
+            .await
+            .map_err(|e| ClientError::Network(e.to_string()))?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(ClientError::Server(status.as_u16(), body));
+        }
+
+        let bytes = resp
+            .bytes()
+            .await
+            .map_err(|e| ClientError::Network(e.to_string()))?;
+        // Empty bodies decode as null (e.g. delete endpoints)
+        let body: &[u8] = if bytes.is_empty() { b"null" } else { &bytes };
+        serde_json::from_slice(body).map_err(|e| ClientError::Parse(e.to_string()))
+    }
+}
@@ -0,0 +1,14 @@
+/// Errors returned by the todoki client
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("network error: {0}")]
+    Network(String),
+    #[error("server error ({0}): {1}")]
+    Server(u16, String),
+    #[error("parse error: {0}")]
+    Parse(String),
+    #[error("websocket error: {0}")]
+    WebSocket(String),
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
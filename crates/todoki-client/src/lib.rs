@@ -0,0 +1,18 @@
+//! Typed async client for the todoki server
+//!
+//! - [`TodokiClient`]: REST endpoints (tasks, projects, agents, relays, artifacts, event bus)
+//! - [`EventSubscriber`]: WebSocket event stream with auto-reconnect and cursor resume
+//!
+//! Event payloads use the shared `todoki-protocol` types so the client stays in
+//! sync with the server.
+
+pub mod client;
+pub mod error;
+pub mod models;
+pub mod subscriber;
+
+pub use client::TodokiClient;
+pub use error::ClientError;
+pub use models::*;
+pub use subscriber::{EventSubscriber, SubscribeOptions};
+pub use todoki_protocol;
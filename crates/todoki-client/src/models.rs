@@ -0,0 +1,305 @@
+//! Request/response DTOs mirroring the server REST API
+//!
+//! Status fields are kept as strings so the client tolerates new server-side
+//! statuses without a release.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+pub use todoki_protocol::AgentRole;
+
+// ============================================================================
+// Tasks
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskEvent {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub event_type: String,
+    pub datetime: DateTime<Utc>,
+    pub state: Option<String>,
+    pub from_state: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskComment {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub content: String,
+    pub create_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub id: Uuid,
+    pub priority: i32,
+    pub content: String,
+    pub project_id: Uuid,
+    pub status: String,
+    pub create_at: DateTime<Utc>,
+    pub archived: bool,
+    #[serde(default)]
+    pub due_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub estimate_minutes: Option<i32>,
+    #[serde(default)]
+    pub snoozed_until: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub events: Vec<TaskEvent>,
+    #[serde(default)]
+    pub comments: Vec<TaskComment>,
+    #[serde(default)]
+    pub agent: Option<AgentBrief>,
+    #[serde(default)]
+    pub artifacts: Vec<Artifact>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateTaskRequest {
+    pub priority: i32,
+    pub content: String,
+    pub project_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate_minutes: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateTaskRequest {
+    pub priority: i32,
+    pub content: String,
+    pub project_id: Uuid,
+    #[serde(default)]
+    pub due_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub estimate_minutes: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskExecution {
+    pub session_id: String,
+    pub relay_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecuteTaskResponse {
+    /// Absent when the execution was queued for the project's next window
+    #[serde(default)]
+    pub agent: Option<Agent>,
+    #[serde(default)]
+    pub session: Option<AgentSession>,
+    #[serde(default)]
+    pub scheduled_for: Option<DateTime<Utc>>,
+}
+
+// ============================================================================
+// Projects
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Project {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub color: String,
+    pub archived: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub general_template: Option<String>,
+    #[serde(default)]
+    pub business_template: Option<String>,
+    #[serde(default)]
+    pub coding_template: Option<String>,
+    #[serde(default)]
+    pub qa_template: Option<String>,
+    #[serde(default)]
+    pub execution_schedule: Option<Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreateProjectRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateProjectRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub general_template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub business_template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coding_template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qa_template: Option<String>,
+}
+
+// ============================================================================
+// Agents
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Agent {
+    pub id: Uuid,
+    pub name: String,
+    pub workdir: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub execution_mode: String,
+    pub role: AgentRole,
+    pub project_id: Uuid,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentBrief {
+    pub id: Uuid,
+    pub name: String,
+    pub status: String,
+    pub role: AgentRole,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentSession {
+    pub id: Uuid,
+    pub agent_id: Uuid,
+    pub status: String,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateAgentRequest {
+    pub name: String,
+    pub workdir: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub execution_mode: Option<String>,
+    #[serde(default)]
+    pub role: Option<AgentRole>,
+    pub project_id: Uuid,
+    #[serde(default)]
+    pub auto_start: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateAgentResponse {
+    #[serde(flatten)]
+    pub agent: Agent,
+    #[serde(default)]
+    pub session: Option<AgentSession>,
+}
+
+// ============================================================================
+// Relays
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Relay {
+    pub relay_id: String,
+    pub name: String,
+    pub role: String,
+    pub safe_paths: Vec<String>,
+    pub labels: HashMap<String, String>,
+    pub projects: Vec<Uuid>,
+    pub setup_script: Option<String>,
+    pub connected_at: i64,
+    pub active_session_count: usize,
+    #[serde(default)]
+    pub max_sessions: usize,
+}
+
+// ============================================================================
+// Artifacts
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Artifact {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub project_id: Uuid,
+    pub agent_id: Option<Uuid>,
+    pub session_id: Option<Uuid>,
+    pub artifact_type: String,
+    pub data: Value,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// ============================================================================
+// Report
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Report {
+    pub period: String,
+    pub created_count: i64,
+    pub done_count: i64,
+    pub archived_count: i64,
+    pub state_changes_count: i64,
+    pub comments_count: i64,
+}
+
+// ============================================================================
+// Event Bus
+// ============================================================================
+
+/// A persisted event as returned by the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredEvent {
+    pub cursor: i64,
+    pub kind: String,
+    pub time: DateTime<Utc>,
+    pub agent_id: Uuid,
+    pub session_id: Option<Uuid>,
+    pub task_id: Option<Uuid>,
+    pub data: Value,
+}
+
+impl StoredEvent {
+    /// Decode the payload into a typed protocol event (builtin or custom)
+    pub fn to_protocol(&self) -> Option<todoki_protocol::Event> {
+        serde_json::from_value(serde_json::json!({
+            "kind": self.kind,
+            "data": self.data,
+        }))
+        .ok()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventQueryResponse {
+    pub events: Vec<StoredEvent>,
+    pub next_cursor: i64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EventQuery {
+    pub cursor: i64,
+    pub kinds: Option<Vec<String>>,
+    pub agent_id: Option<Uuid>,
+    pub task_id: Option<Uuid>,
+    pub limit: Option<usize>,
+}
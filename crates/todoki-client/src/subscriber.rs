@@ -0,0 +1,237 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use uuid::Uuid;
+
+use crate::error::{ClientError, Result};
+use crate::models::StoredEvent;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+const CHANNEL_SIZE: usize = 1024;
+
+/// Subscription filters for the event stream
+#[derive(Debug, Clone, Default)]
+pub struct SubscribeOptions {
+    /// Event kinds (supports wildcards, e.g. "task.*")
+    pub kinds: Vec<String>,
+    /// Resume from this cursor (events with cursor > this value are replayed)
+    pub cursor: Option<i64>,
+    pub agent_id: Option<Uuid>,
+    pub task_id: Option<Uuid>,
+}
+
+/// Server → Client message format from Event Bus WebSocket
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage {
+    Event {
+        cursor: i64,
+        kind: String,
+        time: String,
+        agent_id: String,
+        session_id: Option<String>,
+        task_id: Option<String>,
+        data: Value,
+    },
+    Subscribed {
+        #[allow(dead_code)]
+        cursor: i64,
+    },
+    ReplayComplete {
+        cursor: i64,
+        #[allow(dead_code)]
+        count: usize,
+    },
+    Error {
+        message: String,
+    },
+    Ping,
+    #[serde(other)]
+    Other,
+}
+
+/// WebSocket event subscriber with auto-reconnect and cursor resume
+///
+/// Tracks the last delivered cursor; after a disconnect it reconnects with
+/// exponential backoff and asks the server to replay from that cursor, so
+/// consumers see every event exactly once and in order.
+pub struct EventSubscriber {
+    ws_url: String,
+    token: String,
+    options: SubscribeOptions,
+}
+
+impl EventSubscriber {
+    pub fn new(base_url: &str, token: &str, options: SubscribeOptions) -> Self {
+        let ws_url = format!(
+            "{}/ws/event-bus",
+            base_url
+                .trim_end_matches('/')
+                .replace("https://", "wss://")
+                .replace("http://", "ws://")
+        );
+        Self {
+            ws_url,
+            token: token.to_string(),
+            options,
+        }
+    }
+
+    /// Start the subscription in the background and return a receiver of events
+    ///
+    /// The background task stops when the receiver is dropped.
+    pub fn spawn(self) -> mpsc::Receiver<StoredEvent> {
+        let (tx, rx) = mpsc::channel(CHANNEL_SIZE);
+        tokio::spawn(async move {
+            self.run(tx).await;
+        });
+        rx
+    }
+
+    async fn run(self, tx: mpsc::Sender<StoredEvent>) {
+        let mut cursor = self.options.cursor;
+        let mut delay = RECONNECT_DELAY;
+
+        loop {
+            match self.connect_once(cursor, &tx).await {
+                Ok(last) => {
+                    cursor = last.or(cursor);
+                    delay = RECONNECT_DELAY;
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "event subscription error");
+                }
+            }
+
+            if tx.is_closed() {
+                tracing::debug!("event receiver dropped, stopping subscriber");
+                return;
+            }
+
+            tracing::info!(
+                delay_secs = delay.as_secs(),
+                cursor = ?cursor,
+                "event subscription disconnected, reconnecting..."
+            );
+            tokio::time::sleep(delay).await;
+            delay = std::cmp::min(delay * 2, MAX_RECONNECT_DELAY);
+        }
+    }
+
+    /// Run one connection; returns the last cursor seen
+    async fn connect_once(
+        &self,
+        cursor: Option<i64>,
+        tx: &mpsc::Sender<StoredEvent>,
+    ) -> Result<Option<i64>> {
+        let url = self.build_url(cursor);
+        let (ws_stream, _) = connect_async(&url)
+            .await
+            .map_err(|e| ClientError::WebSocket(e.to_string()))?;
+        let (_write, mut read) = ws_stream.split();
+
+        let mut last_cursor = cursor;
+        while let Some(msg) = read.next().await {
+            let text = match msg {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Close(_)) => break,
+                Ok(_) => continue,
+                Err(e) => return Err(ClientError::WebSocket(e.to_string())),
+            };
+
+            let Ok(msg) = serde_json::from_str::<ServerMessage>(&text) else {
+                tracing::debug!(message = %text, "ignoring unparseable server message");
+                continue;
+            };
+
+            match msg {
+                ServerMessage::Event {
+                    cursor,
+                    kind,
+                    time,
+                    agent_id,
+                    session_id,
+                    task_id,
+                    data,
+                } => {
+                    // Skip duplicates delivered around a reconnect
+                    if last_cursor.is_some_and(|c| cursor <= c) {
+                        continue;
+                    }
+                    let event = StoredEvent {
+                        cursor,
+                        kind,
+                        time: DateTime::parse_from_rfc3339(&time)
+                            .map(|t| t.with_timezone(&Utc))
+                            .unwrap_or_else(|_| Utc::now()),
+                        agent_id: Uuid::parse_str(&agent_id).unwrap_or_default(),
+                        session_id: session_id.and_then(|s| Uuid::parse_str(&s).ok()),
+                        task_id: task_id.and_then(|s| Uuid::parse_str(&s).ok()),
+                        data,
+                    };
+                    if tx.send(event).await.is_err() {
+                        return Ok(last_cursor);
+                    }
+                    last_cursor = Some(cursor);
+                }
+                ServerMessage::ReplayComplete { cursor, .. } => {
+                    tracing::debug!(cursor = cursor, "event replay complete");
+                }
+                ServerMessage::Error { message } => {
+                    tracing::warn!(error = %message, "event bus error");
+                }
+                ServerMessage::Subscribed { .. } | ServerMessage::Ping | ServerMessage::Other => {}
+            }
+        }
+
+        Ok(last_cursor)
+    }
+
+    fn build_url(&self, cursor: Option<i64>) -> String {
+        let mut params = vec![format!("token={}", self.token)];
+        if !self.options.kinds.is_empty() {
+            params.push(format!("kinds={}", self.options.kinds.join(",")));
+        }
+        if let Some(cursor) = cursor {
+            params.push(format!("cursor={}", cursor));
+        }
+        if let Some(agent_id) = self.options.agent_id {
+            params.push(format!("agent_id={}", agent_id));
+        }
+        if let Some(task_id) = self.options.task_id {
+            params.push(format!("task_id={}", task_id));
+        }
+        format!("{}?{}", self.ws_url, params.join("&"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_url_resumes_from_cursor() {
+        let subscriber = EventSubscriber::new(
+            "https://todoki.example.com/",
+            "secret",
+            SubscribeOptions {
+                kinds: vec!["task.*".to_string(), "agent.started".to_string()],
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            subscriber.build_url(Some(42)),
+            "wss://todoki.example.com/ws/event-bus?token=secret&kinds=task.*,agent.started&cursor=42"
+        );
+        assert_eq!(
+            subscriber.build_url(None),
+            "wss://todoki.example.com/ws/event-bus?token=secret&kinds=task.*,agent.started"
+        );
+    }
+}
@@ -0,0 +1,112 @@
+/// End-to-end flows: server + relay + mock agent against a temp database
+///
+/// Run with: TODOKI_E2E_DATABASE_URL=... cargo test -p todoki-e2e -- --ignored
+use serde_json::{json, Value};
+use todoki_e2e::{id_of, Stack};
+
+/// Start executing a new task and return its session ID
+async fn execute_task(stack: &Stack, content: &str) -> anyhow::Result<String> {
+    let task_id = stack.create_task(content).await?;
+    let execution = stack
+        .post(&format!("/api/tasks/{}/execute", task_id), json!({}))
+        .await?;
+    id_of(&execution["session"])
+}
+
+fn for_session<'a>(session_id: &'a str) -> impl Fn(&Value) -> bool + 'a {
+    move |data| data["session_id"] == session_id
+}
+
+fn cursor(event: &Value) -> i64 {
+    event["cursor"].as_i64().unwrap_or_default()
+}
+
+#[tokio::test]
+#[ignore] // Needs PostgreSQL, see the todoki-e2e crate docs
+async fn test_spawn_permission_completion() -> anyhow::Result<()> {
+    let stack = Stack::start("permission").await?;
+    let session_id = execute_task(&stack, "Fix the flaky test").await?;
+
+    let spawned = stack
+        .wait_for_event("relay.spawn_completed", for_session(&session_id))
+        .await?;
+    let requested = stack
+        .wait_for_event("permission.requested", for_session(&session_id))
+        .await?;
+    assert_eq!(requested["data"]["tool_call"]["title"], "Run cargo test");
+
+    let request_id = requested["data"]["request_id"].as_str().unwrap_or_default();
+    stack
+        .post(
+            &format!("/api/permissions/{}/respond", request_id),
+            json!({ "outcome": { "selected": { "option_id": "allow" } } }),
+        )
+        .await?;
+
+    let artifact = stack
+        .wait_for_event("artifact.created", for_session(&session_id))
+        .await?;
+    assert_eq!(artifact["data"]["artifact_type"], "github_pr");
+    assert_eq!(
+        artifact["data"]["data"]["url"],
+        "https://github.com/acme/app/pull/7"
+    );
+
+    let completed = stack
+        .wait_for_event("relay.prompt_completed", for_session(&session_id))
+        .await?;
+    assert_eq!(completed["data"]["success"], true);
+
+    assert!(cursor(&spawned) < cursor(&requested));
+    assert!(cursor(&requested) < cursor(&artifact));
+    assert!(cursor(&artifact) < cursor(&completed));
+
+    stack.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore] // Needs PostgreSQL, see the todoki-e2e crate docs
+async fn test_denied_permission_ends_turn() -> anyhow::Result<()> {
+    let stack = Stack::start("permission").await?;
+    let session_id = execute_task(&stack, "Clean up the build cache").await?;
+
+    let requested = stack
+        .wait_for_event("permission.requested", for_session(&session_id))
+        .await?;
+    let request_id = requested["data"]["request_id"].as_str().unwrap_or_default();
+    stack
+        .post(
+            &format!("/api/permissions/{}/respond", request_id),
+            json!({ "outcome": { "selected": { "option_id": "reject" } } }),
+        )
+        .await?;
+
+    let completed = stack
+        .wait_for_event("relay.prompt_completed", for_session(&session_id))
+        .await?;
+    assert_eq!(completed["data"]["success"], true);
+
+    // The steps after the denied permission never ran
+    let artifacts = stack.events("artifact.created").await?;
+    assert!(artifacts.iter().all(|e| e["data"]["session_id"] != session_id.as_str()));
+
+    stack.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore] // Needs PostgreSQL, see the todoki-e2e crate docs
+async fn test_agent_failure() -> anyhow::Result<()> {
+    let stack = Stack::start("failure").await?;
+    let session_id = execute_task(&stack, "Summarize the changelog").await?;
+
+    let completed = stack
+        .wait_for_event("relay.prompt_completed", for_session(&session_id))
+        .await?;
+    assert_eq!(completed["data"]["success"], false);
+    assert!(completed["data"]["error"].is_string());
+
+    stack.shutdown().await;
+    Ok(())
+}
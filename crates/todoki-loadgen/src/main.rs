@@ -0,0 +1,435 @@
+//! Synthetic event traffic for load testing a todoki server
+//!
+//! Publishes agent output batches and permission requests through the event
+//! bus API, and churns tasks through the task API, each at its own rate. A
+//! WebSocket subscription receives the published events back, so the report
+//! covers both the publish path (HTTP latency per operation) and the
+//! broadcast path (time from publishing an event to receiving it).
+//!
+//! ```sh
+//! todoki-loadgen --url http://localhost:8201 --token $TOKEN \
+//!     --duration-secs 60 --output-rate 200 --permission-rate 5 --task-rate 2
+//! ```
+//!
+//! The events are real: they are stored, notified on and shown in the UI
+//! like any other, so point it at a staging server. Tasks are created in the
+//! `--project` project and deleted again.
+
+mod report;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use serde_json::{json, Value};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use todoki_client::{
+    CreateProjectRequest, CreateTaskRequest, StoredEvent, SubscribeOptions, TodokiClient,
+};
+use uuid::Uuid;
+
+use report::Recorder;
+
+const OUTPUT_BATCH: &str = "agent.output_batch";
+const PERMISSION_REQUESTED: &str = "permission.requested";
+/// Sent first; its arrival shows the subscription is live
+const STARTED: &str = "loadgen.started";
+
+/// Load-test a todoki server with synthetic event traffic
+#[derive(Debug, Clone, Parser)]
+#[command(name = "todoki-loadgen", version)]
+struct Args {
+    /// Server base URL
+    #[arg(long, env = "TODOKI_API_URL", default_value = "http://localhost:8201")]
+    url: String,
+
+    /// User token
+    #[arg(long, env = "TODOKI_TOKEN")]
+    token: String,
+
+    /// How long to generate traffic
+    #[arg(long, default_value_t = 30)]
+    duration_secs: u64,
+
+    /// Output batches per second
+    #[arg(long, default_value_t = 50.0)]
+    output_rate: f64,
+
+    /// Messages per output batch
+    #[arg(long, default_value_t = 20)]
+    batch_messages: usize,
+
+    /// Bytes per output message
+    #[arg(long, default_value_t = 200)]
+    message_bytes: usize,
+
+    /// Permission requests per second
+    #[arg(long, default_value_t = 1.0)]
+    permission_rate: f64,
+
+    /// Tasks created, completed and deleted per second
+    #[arg(long, default_value_t = 1.0)]
+    task_rate: f64,
+
+    /// Project the churned tasks go to (created if missing)
+    #[arg(long, default_value = "loadgen")]
+    project: String,
+
+    /// Requests in flight at most; the rates drop once the server is this
+    /// far behind
+    #[arg(long, default_value_t = 256)]
+    max_in_flight: usize,
+
+    /// How long to wait for outstanding deliveries after the last publish
+    #[arg(long, default_value_t = 10)]
+    drain_secs: u64,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+/// What one generator publishes per tick
+#[derive(Debug, Clone, Copy)]
+enum Load {
+    OutputBatch,
+    Permission,
+    TaskChurn,
+}
+
+/// State shared by the generators of a run
+struct Run {
+    client: TodokiClient,
+    args: Args,
+    /// Keys of this run's events start with it
+    id: Uuid,
+    /// Sender of the published events
+    agent_id: Uuid,
+    project_id: Option<Uuid>,
+    recorder: Recorder,
+    in_flight: Semaphore,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let client = TodokiClient::new(&args.url, &args.token);
+
+    let project_id = if args.task_rate > 0.0 {
+        Some(ensure_project(&client, &args.project).await?)
+    } else {
+        None
+    };
+
+    let run = Arc::new(Run {
+        client: client.clone(),
+        id: Uuid::new_v4(),
+        agent_id: Uuid::new_v4(),
+        project_id,
+        recorder: Recorder::default(),
+        in_flight: Semaphore::new(args.max_in_flight.max(1)),
+        args,
+    });
+
+    let from = client.latest_cursor().await.context("cannot reach the server")?;
+    let mut events = client
+        .subscriber(SubscribeOptions {
+            kinds: vec![
+                OUTPUT_BATCH.to_string(),
+                PERMISSION_REQUESTED.to_string(),
+                STARTED.to_string(),
+            ],
+            cursor: Some(from),
+            ..Default::default()
+        })
+        .spawn();
+    wait_until_live(&run, &mut events).await?;
+
+    let reader = {
+        let run = run.clone();
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                if let Some(key) = event_key(&event) {
+                    run.recorder.delivered(&key);
+                }
+            }
+        })
+    };
+
+    eprintln!(
+        "run {}: {}s of {}/s output batches, {}/s permission requests, {}/s task churn",
+        run.id,
+        run.args.duration_secs,
+        run.args.output_rate,
+        run.args.permission_rate,
+        run.args.task_rate
+    );
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(run.args.duration_secs);
+    let mut generators = JoinSet::new();
+    for (load, rate) in [
+        (Load::OutputBatch, run.args.output_rate),
+        (Load::Permission, run.args.permission_rate),
+        (Load::TaskChurn, run.args.task_rate),
+    ] {
+        if rate > 0.0 {
+            generators.spawn(generate(run.clone(), load, rate, deadline));
+        }
+    }
+    while generators.join_next().await.is_some() {}
+    let elapsed = started.elapsed();
+
+    let drain_until = Instant::now() + Duration::from_secs(run.args.drain_secs);
+    while run.recorder.pending() > 0 && Instant::now() < drain_until {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    reader.abort();
+
+    let report = run.recorder.report(elapsed);
+    if run.args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{}", report);
+    }
+    Ok(())
+}
+
+async fn ensure_project(client: &TodokiClient, name: &str) -> Result<Uuid> {
+    if let Some(project) = client.get_project_by_name(name).await? {
+        return Ok(project.id);
+    }
+    let project = client
+        .create_project(&CreateProjectRequest {
+            name: name.to_string(),
+            description: Some("Tasks churned by todoki-loadgen".to_string()),
+            color: None,
+        })
+        .await?;
+    Ok(project.id)
+}
+
+/// Publish the start marker and wait until it comes back on the WebSocket
+async fn wait_until_live(
+    run: &Run,
+    events: &mut tokio::sync::mpsc::Receiver<StoredEvent>,
+) -> Result<()> {
+    run.client
+        .emit_raw(STARTED, json!({ "run_id": run.id }), run.agent_id, None)
+        .await?;
+    let started = async {
+        while let Some(event) = events.recv().await {
+            if event.kind == STARTED && event.data["run_id"] == run.id.to_string() {
+                return true;
+            }
+        }
+        false
+    };
+    match tokio::time::timeout(Duration::from_secs(10), started).await {
+        Ok(true) => Ok(()),
+        Ok(false) => bail!("event subscription closed"),
+        Err(_) => bail!("no event came back on the WebSocket within 10s"),
+    }
+}
+
+/// Start one `load` operation every 1/`rate` seconds until `deadline`
+async fn generate(run: Arc<Run>, load: Load, rate: f64, deadline: Instant) {
+    let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / rate));
+    // Falling behind lowers the rate instead of bursting afterwards
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut ops = JoinSet::new();
+    let mut seq = 0u64;
+
+    while Instant::now() < deadline {
+        interval.tick().await;
+        let Ok(permit) = run.in_flight.acquire().await else {
+            break;
+        };
+        permit.forget();
+        seq += 1;
+
+        let run = run.clone();
+        ops.spawn(async move {
+            match load {
+                Load::OutputBatch => publish_output_batch(&run, seq).await,
+                Load::Permission => publish_permission(&run, seq).await,
+                Load::TaskChurn => churn_task(&run, seq).await,
+            }
+            run.in_flight.add_permits(1);
+        });
+    }
+    while ops.join_next().await.is_some() {}
+}
+
+/// Key of the `seq`th event of a kind; unique within and across runs
+fn key(run: &Run, kind: &str, seq: u64) -> String {
+    format!("{}:{}:{}", run.id, kind, seq)
+}
+
+/// The key a published event carries: the first word of an output batch's
+/// first message, or a permission request's ID
+fn event_key(event: &StoredEvent) -> Option<String> {
+    match event.kind.as_str() {
+        OUTPUT_BATCH => {
+            let first: Value = serde_json::from_str(event.data["messages"][0].as_str()?).ok()?;
+            first["text"].as_str()?.split(' ').next().map(str::to_string)
+        }
+        PERMISSION_REQUESTED => event.data["request_id"].as_str().map(str::to_string),
+        _ => None,
+    }
+}
+
+async fn publish_output_batch(run: &Run, seq: u64) {
+    let key = key(run, "output", seq);
+    let data = output_batch(run, &key);
+    publish(run, "emit_output_batch", OUTPUT_BATCH, key, data).await;
+}
+
+fn output_batch(run: &Run, key: &str) -> Value {
+    let filler = "x".repeat(run.args.message_bytes);
+    let messages: Vec<String> = (0..run.args.batch_messages.max(1))
+        .map(|i| {
+            let text = if i == 0 {
+                format!("{} {}", key, filler)
+            } else {
+                filler.clone()
+            };
+            json!({ "type": "agent_message", "text": text, "chunk": true }).to_string()
+        })
+        .collect();
+    json!({
+        "session_id": run.id,
+        "stream": "assistant",
+        "messages": messages,
+        "ts": unix_millis(),
+    })
+}
+
+async fn publish_permission(run: &Run, seq: u64) {
+    let key = key(run, "permission", seq);
+    let data = json!({
+        "session_id": run.id,
+        "request_id": key,
+        "tool_call_id": format!("loadgen-tool-{}", seq),
+        "tool_call": {
+            "title": "Run load test step",
+            "raw_input": { "command": format!("echo loadgen {}", seq) },
+            "tool_call_id": format!("loadgen-tool-{}", seq),
+        },
+        "options": [
+            { "kind": "allow_once", "name": "Allow", "option_id": "allow" },
+            { "kind": "reject_once", "name": "Reject", "option_id": "reject" },
+        ],
+    });
+    publish(run, "emit_permission", PERMISSION_REQUESTED, key, data).await;
+}
+
+async fn publish(run: &Run, op: &'static str, kind: &str, key: String, data: Value) {
+    run.recorder.expect(key.clone());
+    let sent = Instant::now();
+    match run.client.emit_raw(kind, data, run.agent_id, None).await {
+        Ok(_) => run.recorder.published(op, sent.elapsed()),
+        Err(e) => {
+            eprintln!("{} failed: {}", op, e);
+            run.recorder.forget(&key);
+            run.recorder.failed(op);
+        }
+    }
+}
+
+/// Create a task, complete it and delete it, timing each call
+async fn churn_task(run: &Run, seq: u64) {
+    let Some(project_id) = run.project_id else {
+        return;
+    };
+    let sent = Instant::now();
+    let created = run
+        .client
+        .create_task(&CreateTaskRequest {
+            priority: 0,
+            content: format!("loadgen {} task {}", run.id, seq),
+            project_id,
+            status: None,
+            due_at: None,
+            estimate_minutes: None,
+        })
+        .await;
+    let task = match created {
+        Ok(task) => {
+            run.recorder.published("task_create", sent.elapsed());
+            task
+        }
+        Err(e) => {
+            eprintln!("task_create failed: {}", e);
+            run.recorder.failed("task_create");
+            return;
+        }
+    };
+
+    let sent = Instant::now();
+    match run.client.update_task_status(task.id, "done").await {
+        Ok(_) => run.recorder.published("task_status", sent.elapsed()),
+        Err(_) => run.recorder.failed("task_status"),
+    }
+
+    let sent = Instant::now();
+    match run.client.delete_task(task.id).await {
+        Ok(()) => run.recorder.published("task_delete", sent.elapsed()),
+        Err(_) => run.recorder.failed("task_delete"),
+    }
+}
+
+fn unix_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run() -> Run {
+        let args = Args::parse_from(["todoki-loadgen", "--token", "t", "--batch-messages", "3"]);
+        Run {
+            client: TodokiClient::new(&args.url, &args.token),
+            id: Uuid::new_v4(),
+            agent_id: Uuid::new_v4(),
+            project_id: None,
+            recorder: Recorder::default(),
+            in_flight: Semaphore::new(1),
+            args,
+        }
+    }
+
+    fn stored(kind: &str, data: Value) -> StoredEvent {
+        StoredEvent {
+            cursor: 1,
+            kind: kind.to_string(),
+            time: Default::default(),
+            agent_id: Uuid::nil(),
+            session_id: None,
+            task_id: None,
+            data,
+        }
+    }
+
+    #[test]
+    fn test_output_batch_carries_key() {
+        let run = run();
+        let key = key(&run, "output", 7);
+        let data = output_batch(&run, &key);
+        assert_eq!(data["messages"].as_array().map(Vec::len), Some(3));
+        assert_eq!(event_key(&stored(OUTPUT_BATCH, data)), Some(key));
+    }
+
+    #[test]
+    fn test_event_key() {
+        let permission = stored(PERMISSION_REQUESTED, json!({ "request_id": "r1" }));
+        assert_eq!(event_key(&permission).as_deref(), Some("r1"));
+        let garbled = stored(OUTPUT_BATCH, json!({ "messages": ["not json"] }));
+        assert_eq!(event_key(&garbled), None);
+        assert_eq!(event_key(&stored(STARTED, json!({}))), None);
+    }
+}
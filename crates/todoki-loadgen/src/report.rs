@@ -0,0 +1,207 @@
+//! Latency samples and the end-of-run report
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Everything measured during a run, shared by the generators and the
+/// event stream reader
+#[derive(Default)]
+pub struct Recorder {
+    publish: Mutex<BTreeMap<&'static str, Vec<Duration>>>,
+    errors: Mutex<BTreeMap<&'static str, u64>>,
+    /// Published events not seen on the WebSocket yet, by key
+    pending: Mutex<HashMap<String, Instant>>,
+    lag: Mutex<Vec<Duration>>,
+}
+
+impl Recorder {
+    /// Start waiting for the event published under `key`; call before
+    /// publishing, the event can arrive before the publish call returns
+    pub fn expect(&self, key: String) {
+        lock(&self.pending).insert(key, Instant::now());
+    }
+
+    /// Forget an event whose publish failed
+    pub fn forget(&self, key: &str) {
+        lock(&self.pending).remove(key);
+    }
+
+    pub fn published(&self, op: &'static str, latency: Duration) {
+        lock(&self.publish).entry(op).or_default().push(latency);
+    }
+
+    pub fn failed(&self, op: &'static str) {
+        *lock(&self.errors).entry(op).or_default() += 1;
+    }
+
+    /// The event published under `key` arrived; ignores keys of other runs
+    pub fn delivered(&self, key: &str) {
+        let sent = lock(&self.pending).remove(key);
+        if let Some(sent) = sent {
+            lock(&self.lag).push(sent.elapsed());
+        }
+    }
+
+    pub fn pending(&self) -> usize {
+        lock(&self.pending).len()
+    }
+
+    pub fn report(&self, elapsed: Duration) -> Report {
+        let publish = lock(&self.publish);
+        let mut operations: BTreeMap<String, OperationSummary> = publish
+            .iter()
+            .map(|(op, samples)| {
+                let summary = OperationSummary {
+                    count: samples.len(),
+                    per_sec: samples.len() as f64 / elapsed.as_secs_f64().max(0.001),
+                    errors: lock(&self.errors).get(op).copied().unwrap_or_default(),
+                    latency: Percentiles::of(samples),
+                };
+                (op.to_string(), summary)
+            })
+            .collect();
+        // Operations that never succeeded still show their errors
+        for (op, errors) in lock(&self.errors).iter() {
+            operations.entry(op.to_string()).or_insert(OperationSummary {
+                count: 0,
+                per_sec: 0.0,
+                errors: *errors,
+                latency: Percentiles::default(),
+            });
+        }
+
+        let lag = lock(&self.lag);
+        Report {
+            duration_secs: elapsed.as_secs_f64(),
+            operations,
+            delivered: lag.len(),
+            undelivered: self.pending(),
+            delivery_lag: Percentiles::of(&lag),
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub duration_secs: f64,
+    /// Publish calls by operation
+    pub operations: BTreeMap<String, OperationSummary>,
+    /// Published events seen on the WebSocket
+    pub delivered: usize,
+    /// Published events still not seen when the run ended
+    pub undelivered: usize,
+    /// From publishing an event to receiving it on the WebSocket
+    pub delivery_lag: Percentiles,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OperationSummary {
+    pub count: usize,
+    pub per_sec: f64,
+    pub errors: u64,
+    pub latency: Percentiles,
+}
+
+/// Latency percentiles in milliseconds (nearest rank)
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Percentiles {
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Percentiles {
+    pub fn of(samples: &[Duration]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        let at = |p: f64| {
+            let rank = ((p * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+            sorted[rank - 1].as_micros() as f64 / 1000.0
+        };
+        Self {
+            p50: at(0.50),
+            p90: at(0.90),
+            p99: at(0.99),
+            max: at(1.0),
+        }
+    }
+}
+
+impl std::fmt::Display for Percentiles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "p50 {:>8.1}ms  p90 {:>8.1}ms  p99 {:>8.1}ms  max {:>8.1}ms",
+            self.p50, self.p90, self.p99, self.max
+        )
+    }
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "duration: {:.1}s", self.duration_secs)?;
+        writeln!(f, "publish latency:")?;
+        for (op, summary) in &self.operations {
+            writeln!(
+                f,
+                "  {:<20} {:>7} ok {:>5} err {:>8.1}/s  {}",
+                op, summary.count, summary.errors, summary.per_sec, summary.latency
+            )?;
+        }
+        writeln!(
+            f,
+            "ws delivery: {} delivered, {} undelivered",
+            self.delivered, self.undelivered
+        )?;
+        write!(f, "  {:<20} {}", "lag", self.delivery_lag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(values: &[u64]) -> Vec<Duration> {
+        values.iter().map(|v| Duration::from_millis(*v)).collect()
+    }
+
+    #[test]
+    fn test_percentiles() {
+        assert_eq!(Percentiles::of(&[]), Percentiles::default());
+
+        let samples: Vec<u64> = (1..=100).rev().collect();
+        let p = Percentiles::of(&ms(&samples));
+        assert_eq!((p.p50, p.p90, p.p99, p.max), (50.0, 90.0, 99.0, 100.0));
+
+        let p = Percentiles::of(&ms(&[7]));
+        assert_eq!((p.p50, p.max), (7.0, 7.0));
+    }
+
+    #[test]
+    fn test_delivery_tracking() {
+        let recorder = Recorder::default();
+        recorder.expect("a".to_string());
+        recorder.expect("b".to_string());
+        recorder.expect("c".to_string());
+        recorder.delivered("a");
+        recorder.delivered("someone-else");
+        recorder.forget("c");
+        recorder.failed("emit_permission");
+
+        let report = recorder.report(Duration::from_secs(1));
+        assert_eq!(report.delivered, 1);
+        assert_eq!(report.undelivered, 1);
+        assert_eq!(report.operations["emit_permission"].errors, 1);
+    }
+}
@@ -4,13 +4,17 @@
 //! todoki-server and todoki-relay.
 
 use std::collections::HashMap;
+use std::fmt;
 
 #[cfg(feature = "schematic")]
 use gotcha::Schematic;
+use serde::de::value::MapDeserializer;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use uuid::Uuid;
 
+use crate::SessionMode;
+
 // ============================================================================
 // Event Kind Constants
 // ============================================================================
@@ -26,6 +30,15 @@ impl EventKind {
     pub const TASK_COMPLETED: &str = "task.completed";
     pub const TASK_FAILED: &str = "task.failed";
     pub const TASK_ARCHIVED: &str = "task.archived";
+    pub const TASK_SCHEDULED: &str = "task.scheduled";
+    pub const TASK_SNOOZED: &str = "task.snoozed";
+    pub const TASK_UNSNOOZED: &str = "task.unsnoozed";
+    pub const TASK_MENTION: &str = "task.mention";
+    pub const TASK_HANDOFF: &str = "task.handoff";
+
+    // Project lifecycle
+    pub const PROJECT_ARCHIVED: &str = "project.archived";
+    pub const PROJECT_RESTORED: &str = "project.restored";
 
     // Agent lifecycle
     pub const AGENT_REGISTERED: &str = "agent.registered";
@@ -45,11 +58,24 @@ impl EventKind {
     // Agent session
     pub const AGENT_SESSION_STARTED: &str = "agent.session_started";
     pub const AGENT_SESSION_EXITED: &str = "agent.session_exited";
+    pub const AGENT_UNHEALTHY: &str = "agent.unhealthy";
+    pub const AGENT_HEALTHY: &str = "agent.healthy";
+
+    // Agent task tools (emitted by the relay on behalf of an ACP agent)
+    pub const AGENT_TASK_COMMENT: &str = "agent.task_comment";
+    pub const AGENT_SUBTASK_DONE: &str = "agent.subtask_done";
+    pub const AGENT_FOLLOWUP_TASK: &str = "agent.followup_task";
 
     // Artifacts
     pub const ARTIFACT_CREATED: &str = "artifact.created";
+    pub const ARTIFACT_UPDATED: &str = "artifact.updated";
     pub const GITHUB_PR_OPENED: &str = "artifact.github_pr_opened";
     pub const GITHUB_PR_MERGED: &str = "artifact.github_pr_merged";
+    pub const MERGE_REQUEST_OPENED: &str = "artifact.merge_request_opened";
+    pub const MERGE_REQUEST_MERGED: &str = "artifact.merge_request_merged";
+    pub const MERGE_REQUEST_CLOSED: &str = "artifact.merge_request_closed";
+    pub const ARTIFACT_CI_PASSED: &str = "artifact.ci_passed";
+    pub const ARTIFACT_CI_FAILED: &str = "artifact.ci_failed";
 
     // Permission
     pub const PERMISSION_REQUESTED: &str = "permission.requested";
@@ -77,6 +103,8 @@ impl EventKind {
     pub const RELAY_SPAWN_REQUESTED: &str = "relay.spawn_requested";
     pub const RELAY_STOP_REQUESTED: &str = "relay.stop_requested";
     pub const RELAY_INPUT_REQUESTED: &str = "relay.input_requested";
+    pub const RELAY_RESIZE_REQUESTED: &str = "relay.resize_requested";
+    pub const RELAY_LOG_LEVEL_CHANGED: &str = "relay.log_level_changed";
 
     // Relay responses (Relay → Server)
     pub const RELAY_SPAWN_COMPLETED: &str = "relay.spawn_completed";
@@ -86,9 +114,99 @@ impl EventKind {
     // System
     pub const SYSTEM_RELAY_CONNECTED: &str = "system.relay_connected";
     pub const SYSTEM_RELAY_DISCONNECTED: &str = "system.relay_disconnected";
+    pub const SYSTEM_BACKUP_STARTED: &str = "system.backup_started";
+    pub const SYSTEM_BACKUP_PROGRESS: &str = "system.backup_progress";
+    pub const SYSTEM_BACKUP_COMPLETED: &str = "system.backup_completed";
+    pub const SYSTEM_BACKUP_FAILED: &str = "system.backup_failed";
+    pub const SYSTEM_CONFIG_RELOADED: &str = "system.config_reloaded";
 
     // Human interaction
     pub const HUMAN_MESSAGE: &str = "human.message";
+
+    // Notifications
+    pub const NOTIFICATION_UNREAD: &str = "notification.unread";
+
+    /// Every kind with a typed payload in [`BuiltinEvent`]; anything else is a
+    /// custom event with free-form data.
+    pub const BUILTIN: &[&str] = &[
+        Self::TASK_CREATED,
+        Self::TASK_STATUS_CHANGED,
+        Self::TASK_ASSIGNED,
+        Self::TASK_COMPLETED,
+        Self::TASK_FAILED,
+        Self::TASK_ARCHIVED,
+        Self::TASK_SCHEDULED,
+        Self::TASK_SNOOZED,
+        Self::TASK_UNSNOOZED,
+        Self::TASK_MENTION,
+        Self::TASK_HANDOFF,
+        Self::PROJECT_ARCHIVED,
+        Self::PROJECT_RESTORED,
+        Self::AGENT_REGISTERED,
+        Self::AGENT_STARTED,
+        Self::AGENT_STOPPED,
+        Self::AGENT_OUTPUT,
+        Self::AGENT_OUTPUT_BATCH,
+        Self::AGENT_ERROR,
+        Self::REQUIREMENT_ANALYZED,
+        Self::BUSINESS_CONTEXT_READY,
+        Self::CODE_REVIEW_REQUESTED,
+        Self::QA_TEST_PASSED,
+        Self::QA_TEST_FAILED,
+        Self::AGENT_SESSION_STARTED,
+        Self::AGENT_SESSION_EXITED,
+        Self::AGENT_UNHEALTHY,
+        Self::AGENT_HEALTHY,
+        Self::AGENT_TASK_COMMENT,
+        Self::AGENT_SUBTASK_DONE,
+        Self::AGENT_FOLLOWUP_TASK,
+        Self::ARTIFACT_CREATED,
+        Self::ARTIFACT_UPDATED,
+        Self::GITHUB_PR_OPENED,
+        Self::GITHUB_PR_MERGED,
+        Self::MERGE_REQUEST_OPENED,
+        Self::MERGE_REQUEST_MERGED,
+        Self::MERGE_REQUEST_CLOSED,
+        Self::ARTIFACT_CI_PASSED,
+        Self::ARTIFACT_CI_FAILED,
+        Self::PERMISSION_REQUESTED,
+        Self::PERMISSION_RESPONDED,
+        Self::PERMISSION_APPROVED,
+        Self::PERMISSION_DENIED,
+        Self::PERMISSION_REVOKED,
+        Self::PERMISSION_EXPIRED,
+        Self::PERMISSION_CANCELLED,
+        Self::RELAY_UP,
+        Self::RELAY_DOWN,
+        Self::RELAY_AGENT_OUTPUT,
+        Self::RELAY_AGENT_OUTPUT_BATCH,
+        Self::RELAY_SESSION_STATUS,
+        Self::RELAY_PERMISSION_REQUEST,
+        Self::RELAY_ARTIFACT,
+        Self::RELAY_PROMPT_COMPLETED,
+        Self::RELAY_ERROR,
+        Self::RELAY_SPAWN_REQUESTED,
+        Self::RELAY_STOP_REQUESTED,
+        Self::RELAY_INPUT_REQUESTED,
+        Self::RELAY_RESIZE_REQUESTED,
+        Self::RELAY_LOG_LEVEL_CHANGED,
+        Self::RELAY_SPAWN_COMPLETED,
+        Self::RELAY_SPAWN_FAILED,
+        Self::RELAY_STOP_COMPLETED,
+        Self::SYSTEM_RELAY_CONNECTED,
+        Self::SYSTEM_RELAY_DISCONNECTED,
+        Self::SYSTEM_BACKUP_STARTED,
+        Self::SYSTEM_BACKUP_PROGRESS,
+        Self::SYSTEM_BACKUP_COMPLETED,
+        Self::SYSTEM_BACKUP_FAILED,
+        Self::SYSTEM_CONFIG_RELOADED,
+        Self::HUMAN_MESSAGE,
+        Self::NOTIFICATION_UNREAD,
+    ];
+
+    pub fn is_builtin(kind: &str) -> bool {
+        Self::BUILTIN.contains(&kind)
+    }
 }
 
 // ============================================================================
@@ -158,6 +276,42 @@ impl PermissionOutcome {
     }
 }
 
+/// What a relay does with a permission request that got no answer in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionFallbackAction {
+    /// Select the first allow option.
+    Allow,
+    /// Select the first reject option, or cancel when there is none.
+    Deny,
+    /// Resolve the request as cancelled.
+    Cancel,
+    /// Keep waiting; the server expires the request eventually.
+    #[default]
+    KeepWaiting,
+}
+
+/// Relay-side fallback for unanswered permission requests. Relays report
+/// theirs in `relay.up`; the server may override it when confirming the
+/// registration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PermissionFallback {
+    /// Applied once `timeout_secs` passed without an answer.
+    pub action: PermissionFallbackAction,
+    /// Seconds to wait for an answer before applying `action`.
+    pub timeout_secs: u64,
+}
+
+impl Default for PermissionFallback {
+    fn default() -> Self {
+        Self {
+            action: PermissionFallbackAction::KeepWaiting,
+            timeout_secs: 600,
+        }
+    }
+}
+
 // ============================================================================
 // Task Data Structures
 // ============================================================================
@@ -175,6 +329,12 @@ pub struct TaskCreatedData {
     /// If this is a subtask, the task_id of the parent task.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parent_task_id: Option<String>,
+    /// Where the task came from (e.g., "api", "email", "telegram", "triage").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Who sent the task, for sources that have one (email address, chat user).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender: Option<String>,
 }
 
 /// Data for task.status_changed event - emitted when a task's status transitions.
@@ -195,6 +355,44 @@ pub struct TaskStatusChangedData {
 pub struct TaskAssignedData {
     /// The agent_id of the agent that the task is assigned to.
     pub assigned_agent_id: String,
+    /// Why the agent was picked, when the assignment engine picked it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rationale: Option<AssignmentRationale>,
+}
+
+/// How the assignment engine chose among the agents an event triggers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct AssignmentRationale {
+    /// One-line explanation of the choice.
+    pub summary: String,
+    /// Every candidate, best first.
+    pub candidates: Vec<AssignmentCandidate>,
+}
+
+/// One agent the assignment engine considered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct AssignmentCandidate {
+    pub agent_id: String,
+    pub agent_name: String,
+    pub role: String,
+    /// Weighted total of the factors below (0-1).
+    pub score: f64,
+    /// How well the agent's role fits the task's stage (0-1).
+    pub role_fit: f64,
+    /// Smoothed share of the agent's finished sessions in the task's project that completed.
+    pub success_rate: f64,
+    /// Unfinished tasks already assigned to the agent.
+    pub open_tasks: i64,
+    /// Relay the agent would run on, if one has a free slot.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relay_id: Option<String>,
+    /// Share of the relay's session slots in use (0-1).
+    pub relay_load: f64,
+    /// Why the agent could not be picked, if it could not.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excluded: Option<String>,
 }
 
 /// Data for task.completed event - emitted when a task finishes successfully.
@@ -205,6 +403,18 @@ pub struct TaskCompletedData {
     /// Optional result data produced by completing the task.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub result: Option<Value>,
+    /// The task's effort estimate in minutes, if one was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate_minutes: Option<i32>,
+    /// Total spawn-to-completion time of the task's agent sessions, in minutes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_minutes: Option<f64>,
+    /// Number of agent sessions run for the task.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_count: Option<i64>,
+    /// actual_minutes / estimate_minutes (> 1 means the task was underestimated).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate_ratio: Option<f64>,
 }
 
 /// Data for task.failed event - emitted when a task fails.
@@ -222,6 +432,113 @@ pub struct TaskFailedData {
 #[cfg_attr(feature = "schematic", derive(Schematic))]
 pub struct TaskArchivedData {}
 
+/// Data for task.scheduled event - emitted when an execution is queued instead of
+/// spawned, either outside the project's execution windows or because a
+/// concurrency cap is reached.
+/// Note: task_id is provided at EventMessage level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct TaskScheduledData {
+    /// When the execution will be spawned (RFC 3339).
+    pub scheduled_for: String,
+    /// Relay requested for the execution, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relay_id: Option<String>,
+    /// Why the execution was queued ("window" or "capacity").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Data for task.snoozed event - emitted when a task is hidden from the inbox
+/// until a wake time.
+/// Note: task_id is provided at EventMessage level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct TaskSnoozedData {
+    /// When the task returns to the inbox (RFC 3339).
+    pub until: String,
+    /// Whether a notification is sent when the task wakes.
+    #[serde(default)]
+    pub notify: bool,
+}
+
+/// Data for task.unsnoozed event - emitted when a snoozed task returns to the inbox.
+/// Note: task_id is provided at EventMessage level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct TaskUnsnoozedData {
+    /// "timer" when the wake time was reached, "manual" when cancelled early.
+    pub reason: String,
+}
+
+/// Data for task.mention event - a task comment mentions users or agents.
+/// Note: task_id is provided at EventMessage level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct TaskMentionData {
+    /// The comment containing the mentions (UUID format).
+    pub comment_id: String,
+    /// Full comment text.
+    pub content: String,
+    /// Mentioned users by token ID (e.g., "user" or "oidc:<subject>").
+    #[serde(default)]
+    pub users: Vec<String>,
+    /// Mentioned agents (UUID format); those with auto_trigger are prompted.
+    #[serde(default)]
+    pub agent_ids: Vec<String>,
+}
+
+/// Data for task.handoff event - one stage of a task hands over to the next role.
+/// Note: task_id is provided at EventMessage level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct TaskHandoffData {
+    /// Role of the closed stage, if an agent ran it (e.g., "coding").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_role: Option<String>,
+    /// Role of the next stage (e.g., "qa").
+    pub to_role: String,
+    /// Session of the closed stage (UUID format).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_session_id: Option<String>,
+    /// Session started for the next stage (UUID format).
+    pub to_session_id: String,
+    /// The `handoff` artifact holding the context (UUID format).
+    pub artifact_id: String,
+}
+
+// ============================================================================
+// Project Data Structures
+// ============================================================================
+
+/// Data for project.archived event - emitted when a project is archived along
+/// with its open tasks, agents and artifacts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct ProjectArchivedData {
+    /// Archived project (UUID format).
+    pub project_id: String,
+    /// Number of non-done tasks archived with the project.
+    pub archived_tasks: i64,
+    /// Number of agents stopped or detached from their tasks.
+    pub stopped_agents: i64,
+    /// Number of artifacts hidden with the project.
+    pub hidden_artifacts: i64,
+}
+
+/// Data for project.restored event - emitted when an archived project is
+/// restored, bringing back what its archiving cascaded to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct ProjectRestoredData {
+    /// Restored project (UUID format).
+    pub project_id: String,
+    /// Number of tasks unarchived with the project.
+    pub restored_tasks: i64,
+    /// Number of artifacts shown again.
+    pub restored_artifacts: i64,
+}
+
 // ============================================================================
 // Agent Data Structures
 // ============================================================================
@@ -262,6 +579,82 @@ pub struct AgentStoppedData {
     pub reason: Option<String>,
 }
 
+/// Kind of output stream an agent message belongs to.
+///
+/// Serialized as a plain string. Names this version does not know are kept
+/// verbatim in `Other`, so newer relays and older servers (or the reverse)
+/// can still exchange output without losing the stream type.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+#[serde(rename_all = "snake_case")]
+pub enum OutputStreamKind {
+    /// Agent reply text.
+    Assistant,
+    /// Agent reasoning.
+    Thinking,
+    /// A tool call started by the agent.
+    ToolUse,
+    /// Progress or result of a tool call.
+    ToolResult,
+    /// User message echoed back by the agent.
+    User,
+    /// Agent execution plan.
+    Plan,
+    /// Relay or session notices.
+    System,
+    /// Standard output of a plain process.
+    Stdout,
+    /// Standard error of a plain process.
+    Stderr,
+    /// Raw output of a PTY session, ANSI sequences included.
+    Terminal,
+    /// A stream kind unknown to this version.
+    #[serde(untagged)]
+    Other(String),
+}
+
+impl OutputStreamKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            OutputStreamKind::Assistant => "assistant",
+            OutputStreamKind::Thinking => "thinking",
+            OutputStreamKind::ToolUse => "tool_use",
+            OutputStreamKind::ToolResult => "tool_result",
+            OutputStreamKind::User => "user",
+            OutputStreamKind::Plan => "plan",
+            OutputStreamKind::System => "system",
+            OutputStreamKind::Stdout => "stdout",
+            OutputStreamKind::Stderr => "stderr",
+            OutputStreamKind::Terminal => "terminal",
+            OutputStreamKind::Other(other) => other,
+        }
+    }
+}
+
+impl From<&str> for OutputStreamKind {
+    fn from(s: &str) -> Self {
+        match s {
+            "assistant" => OutputStreamKind::Assistant,
+            "thinking" => OutputStreamKind::Thinking,
+            "tool_use" => OutputStreamKind::ToolUse,
+            "tool_result" => OutputStreamKind::ToolResult,
+            "user" => OutputStreamKind::User,
+            "plan" => OutputStreamKind::Plan,
+            "system" => OutputStreamKind::System,
+            "stdout" => OutputStreamKind::Stdout,
+            "stderr" => OutputStreamKind::Stderr,
+            "terminal" => OutputStreamKind::Terminal,
+            other => OutputStreamKind::Other(other.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for OutputStreamKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Data for agent.output event - emitted for each line of agent output.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[cfg_attr(feature = "schematic", derive(Schematic))]
@@ -270,8 +663,8 @@ pub struct AgentOutputData {
     pub agent_id: String,
     /// Session ID for this execution.
     pub session_id: String,
-    /// Output stream type: "stdout" or "stderr".
-    pub stream: String,
+    /// Output stream the message belongs to.
+    pub stream: OutputStreamKind,
     /// The output message content.
     pub message: String,
     /// Unix timestamp in milliseconds when the output was produced.
@@ -285,8 +678,8 @@ pub struct AgentOutputData {
 pub struct AgentOutputBatchData {
     /// Session ID for this execution.
     pub session_id: String,
-    /// Output stream type: "stdout" or "stderr".
-    pub stream: String,
+    /// Output stream the message belongs to.
+    pub stream: OutputStreamKind,
     /// Batch of output messages.
     pub messages: Vec<String>,
     /// Unix timestamp in milliseconds when the batch was created.
@@ -313,6 +706,12 @@ pub struct AgentSessionStartedData {
     pub agent_id: String,
     /// Unique identifier for this session (UUID format).
     pub session_id: String,
+    /// ACP protocol version negotiated with the agent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol_version: Option<u16>,
+    /// Relay features available for this session (e.g. "session_resume").
+    #[serde(default)]
+    pub features: Vec<String>,
 }
 
 /// Data for agent.session_exited event - emitted when an agent session terminates.
@@ -325,6 +724,100 @@ pub struct AgentSessionExitedData {
     pub session_id: String,
     /// Process exit code (0 = success, non-zero = error).
     pub exit_code: Option<i32>,
+    /// Final session status ("completed", "failed" or "cancelled").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// Resource usage of the agent's process tree at its last sample.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<ResourceUsage>,
+}
+
+/// Data for agent.unhealthy event - emitted by the relay when liveness probes
+/// of a running session keep failing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct AgentUnhealthyData {
+    /// The agent whose probes failed.
+    pub agent_id: String,
+    /// Session being probed.
+    pub session_id: String,
+    /// Probes failed in a row, including this one.
+    pub consecutive_failures: u32,
+    /// Why the last probe failed (e.g., "ping timed out after 10s").
+    pub error: String,
+}
+
+/// Data for agent.healthy event - emitted when a session that was reported
+/// unhealthy answers a probe again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct AgentHealthyData {
+    /// The agent that recovered.
+    pub agent_id: String,
+    /// Session being probed.
+    pub session_id: String,
+}
+
+// ============================================================================
+// Agent Task Tool Data Structures
+// ============================================================================
+
+/// Snapshot of the task an agent session is working on.
+/// Sent with relay.spawn_requested so the relay can answer `todoki/get_task` locally.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct AgentTaskContext {
+    /// Task ID (UUID format).
+    pub task_id: String,
+    /// Task content as written by the user.
+    pub content: String,
+    /// Task status at spawn time (e.g., "todo", "in-progress").
+    pub status: String,
+    /// Task priority.
+    pub priority: i32,
+    /// Project the task belongs to (UUID format).
+    pub project_id: String,
+    /// Human-readable project name.
+    pub project_name: String,
+}
+
+/// Data for agent.task_comment event - an agent posts a progress comment on its task.
+/// Note: task_id is provided at EventMessage level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct AgentTaskCommentData {
+    /// Session that produced the comment.
+    pub session_id: String,
+    /// Comment text (markdown).
+    pub content: String,
+}
+
+/// Data for agent.subtask_done event - an agent reports a piece of its task as finished.
+/// Note: task_id is provided at EventMessage level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct AgentSubtaskDoneData {
+    /// Session that completed the subtask.
+    pub session_id: String,
+    /// Short description of the completed subtask.
+    pub subtask: String,
+    /// Optional details about how it was done.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// Data for agent.followup_task event - an agent asks for a new task in the same project.
+/// Note: task_id (the originating task) is provided at EventMessage level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct AgentFollowupTaskData {
+    /// Session that requested the follow-up.
+    pub session_id: String,
+    /// Content of the new task.
+    pub content: String,
+    /// Priority of the new task (defaults to 0).
+    #[serde(default)]
+    pub priority: i32,
 }
 
 // ============================================================================
@@ -398,6 +891,25 @@ pub struct ArtifactCreatedData {
     pub data: Value,
 }
 
+/// Data for artifact.updated event - an artifact was revised, moved to another
+/// task, linked to or unlinked from a task, or deleted.
+/// Note: task_id (the artifact's task) is provided at EventMessage level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct ArtifactUpdatedData {
+    /// The artifact (UUID format).
+    pub artifact_id: String,
+    /// Type of artifact (e.g., "github_pr", "document").
+    pub artifact_type: String,
+    /// "updated", "moved", "linked", "unlinked" or "deleted".
+    pub change: String,
+    /// Artifact data after the change.
+    pub data: Value,
+    /// Task linked or unlinked, for "linked" and "unlinked" (UUID format).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linked_task_id: Option<String>,
+}
+
 /// Data for artifact.github_pr_opened and artifact.github_pr_merged events.
 /// Tracks GitHub pull request lifecycle.
 /// Note: task_id is provided at EventMessage level.
@@ -412,6 +924,44 @@ pub struct GithubPrData {
     pub repo: String,
 }
 
+/// Data for artifact.merge_request_opened, artifact.merge_request_merged and
+/// artifact.merge_request_closed events - a GitLab merge request or Gitea pull
+/// request tracked as an artifact changed state, as reported by a webhook.
+/// Note: task_id is provided at EventMessage level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct MergeRequestData {
+    /// The `gitlab_mr` or `gitea_pr` artifact (UUID format).
+    pub artifact_id: String,
+    /// "gitlab" or "gitea".
+    pub provider: String,
+    /// Full URL to the merge request.
+    pub url: String,
+    /// Merge request number within its repository (GitLab's `iid`).
+    pub number: i64,
+    /// Repository path, e.g. "group/project" or "owner/repo".
+    pub repo: String,
+}
+
+/// Data for artifact.ci_passed and artifact.ci_failed events - the checks of
+/// a branch an agent worked on finished.
+/// Note: task_id is provided at EventMessage level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct ArtifactCiData {
+    /// The `branch` artifact (UUID format).
+    pub artifact_id: String,
+    /// Branch name.
+    pub branch: String,
+    /// Commit the checks ran on.
+    pub head: String,
+    /// Repository in "owner/repo" format.
+    pub repo: String,
+    /// Names of the checks that did not succeed (empty when passed).
+    #[serde(default)]
+    pub failed_checks: Vec<String>,
+}
+
 // ============================================================================
 // Relay Data Structures
 // ============================================================================
@@ -435,11 +985,12 @@ pub struct RelayAgentOutputData {
     /// The relay forwarding the output.
     pub relay_id: String,
     /// The agent producing the output.
+    #[serde(alias = "agent_id")]
     pub target_agent_id: String,
     /// Session ID for this execution.
     pub session_id: String,
-    /// Output stream type: "stdout" or "stderr".
-    pub stream: String,
+    /// Output stream the message belongs to.
+    pub stream: OutputStreamKind,
     /// The output message content.
     pub message: String,
     /// Unix timestamp in milliseconds.
@@ -454,11 +1005,12 @@ pub struct RelayAgentOutputBatchData {
     /// The relay forwarding the output.
     pub relay_id: String,
     /// The agent producing the output.
+    #[serde(alias = "agent_id")]
     pub target_agent_id: String,
     /// Session ID for this execution.
     pub session_id: String,
-    /// Output stream type: "stdout" or "stderr".
-    pub stream: String,
+    /// Output stream the message belongs to.
+    pub stream: OutputStreamKind,
     /// Batch of output messages.
     pub messages: Vec<String>,
     /// Unix timestamp in milliseconds when the batch was created.
@@ -478,6 +1030,26 @@ pub struct RelaySessionStatusData {
     /// Process exit code if the session has exited.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub exit_code: Option<i32>,
+    /// Resource usage of the agent's process tree, sampled periodically
+    /// while running and once more when the session ends.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<ResourceUsage>,
+}
+
+/// Resource usage of an agent process and all processes it started.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct ResourceUsage {
+    /// CPU used since the previous sample, in percent of one core.
+    pub cpu_percent: f64,
+    /// CPU time consumed since the session started, in seconds.
+    pub cpu_seconds: f64,
+    /// Resident memory at the time of the sample, in bytes.
+    pub rss_bytes: u64,
+    /// Highest resident memory sampled during the session, in bytes.
+    pub peak_rss_bytes: u64,
+    /// Processes started by the agent that are still running.
+    pub child_processes: u32,
 }
 
 /// Data for relay.permission_request event - relay forwards permission request from agent.
@@ -488,6 +1060,7 @@ pub struct RelayPermissionRequestData {
     /// The relay forwarding the request.
     pub relay_id: String,
     /// The agent requesting permission.
+    #[serde(default, alias = "agent_id")]
     pub target_agent_id: String,
     /// Session ID where the request originated.
     pub session_id: String,
@@ -508,6 +1081,7 @@ pub struct RelayArtifactData {
     /// The relay reporting the artifact.
     pub relay_id: String,
     /// The agent that created the artifact.
+    #[serde(default, alias = "agent_id")]
     pub target_agent_id: String,
     /// Session ID that produced the artifact.
     pub session_id: String,
@@ -539,8 +1113,15 @@ pub struct RelayPromptCompletedData {
 pub struct RelayErrorData {
     /// The relay reporting the error.
     pub relay_id: String,
-    /// Session ID where the error occurred.
-    pub session_id: String,
+    /// Session ID where the error occurred, if the error is tied to one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Request the error relates to (e.g., a permission response).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Machine-readable error category (e.g., "permission_response_failed").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<String>,
     /// Human-readable error message.
     pub error: String,
 }
@@ -554,6 +1135,7 @@ pub struct RelaySpawnRequestedData {
     /// Unique request ID for response correlation.
     pub request_id: String,
     /// Agent ID for the spawned process.
+    #[serde(rename = "agent_id", alias = "target_agent_id")]
     pub target_agent_id: String,
     /// Session ID to assign to this execution.
     pub session_id: String,
@@ -567,6 +1149,15 @@ pub struct RelaySpawnRequestedData {
     /// Environment variables to set for the process.
     #[serde(default)]
     pub env: HashMap<String, String>,
+    /// Task the session works on (UUID format).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+    /// Task the session works on, exposed to the agent through task tools.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<AgentTaskContext>,
+    /// Backend the relay uses to drive the process.
+    #[serde(default)]
+    pub mode: SessionMode,
 }
 
 /// Data for relay.stop_requested event - server requests relay to stop an agent session.
@@ -575,6 +1166,9 @@ pub struct RelaySpawnRequestedData {
 pub struct RelayStopRequestedData {
     /// Target relay that should stop the session.
     pub relay_id: String,
+    /// Unique request ID for response correlation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
     /// Session ID to stop.
     pub session_id: String,
 }
@@ -585,12 +1179,40 @@ pub struct RelayStopRequestedData {
 pub struct RelayInputRequestedData {
     /// Target relay that should forward the input.
     pub relay_id: String,
+    /// Unique request ID for response correlation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
     /// Session ID to receive the input.
     pub session_id: String,
     /// Input text to send to the agent's stdin.
     pub input: String,
 }
 
+/// Data for relay.resize_requested event - resize the terminal of a PTY session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct RelayResizeRequestedData {
+    /// Target relay running the session.
+    pub relay_id: String,
+    /// PTY session to resize.
+    pub session_id: String,
+    /// Terminal width in columns.
+    pub cols: u16,
+    /// Terminal height in rows.
+    pub rows: u16,
+}
+
+/// Data for relay.log_level_changed event - replace the relay's log filter
+/// until it restarts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct RelayLogLevelChangedData {
+    /// Target relay.
+    pub relay_id: String,
+    /// Filter directives in `RUST_LOG` syntax, e.g. "info,todoki_relay::session=debug".
+    pub directives: String,
+}
+
 /// Data for relay.spawn_completed event - relay confirms successful agent spawn.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[cfg_attr(feature = "schematic", derive(Schematic))]
@@ -615,6 +1237,32 @@ pub struct RelaySpawnFailedData {
     pub session_id: String,
     /// Human-readable error explaining why spawn failed.
     pub error: String,
+    /// What went wrong, for relays that report it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<SpawnErrorCode>,
+}
+
+/// Why a relay could not spawn a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+#[serde(rename_all = "snake_case")]
+pub enum SpawnErrorCode {
+    /// The relay is already running a session.
+    RelayBusy,
+    /// The workdir is outside the relay's safe paths.
+    WorkdirNotAllowed,
+    /// The workdir does not exist or is not a directory.
+    WorkdirMissing,
+    /// The relay cannot write to the workdir.
+    WorkdirNotWritable,
+    /// The workdir's filesystem has less free space than required.
+    InsufficientDiskSpace,
+    /// The agent command is not an executable file or not on `PATH`.
+    CommandNotFound,
+    /// The setup script could not be written or exited with an error.
+    SetupFailed,
+    /// Launching the agent process failed.
+    LaunchFailed,
 }
 
 /// Data for relay.stop_completed event - relay confirms session was stopped.
@@ -641,6 +1289,50 @@ pub struct SystemRelayConnectionData {
     pub relay_id: String,
 }
 
+/// Whether a `system.backup_*` event reports a backup or a restore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+#[serde(rename_all = "snake_case")]
+pub enum BackupOperation {
+    Backup,
+    Restore,
+}
+
+/// Data for system.backup_started, system.backup_progress,
+/// system.backup_completed and system.backup_failed events.
+/// Progress is reported once per table as it is exported or restored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct SystemBackupData {
+    /// Identifies one backup or restore run (UUID format).
+    pub backup_id: String,
+    pub operation: BackupOperation,
+    /// Table just finished, for progress events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table: Option<String>,
+    /// Tables finished so far.
+    pub tables_done: u32,
+    /// Tables in the backup.
+    pub tables_total: u32,
+    /// Rows exported or restored so far.
+    pub rows: i64,
+    /// Why the run failed, for system.backup_failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Data for system.config_reloaded event - the server re-read its
+/// configuration files and applied what can change while it runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct SystemConfigReloadedData {
+    /// Settings sections whose changes were applied, e.g. "auto_review".
+    pub applied: Vec<String>,
+    /// Sections that changed but only take effect after a restart.
+    #[serde(default)]
+    pub restart_required: Vec<String>,
+}
+
 // ============================================================================
 // Human Interaction Data Structures
 // ============================================================================
@@ -655,6 +1347,21 @@ pub struct HumanMessageData {
     pub content: String,
 }
 
+// ============================================================================
+// Notification Data Structures
+// ============================================================================
+
+/// Data for notification.unread event - a user's unread notification count changed.
+/// WebSocket clients only receive the events of their own user.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct NotificationUnreadData {
+    /// The user the count belongs to (e.g., "user" or "oidc:<subject>").
+    pub user_id: String,
+    /// Unread notifications of the user.
+    pub unread: i64,
+}
+
 // ============================================================================
 // Permission Event Data (for permission.requested/responded)
 // ============================================================================
@@ -676,6 +1383,72 @@ pub struct PermissionRequestedData {
     pub tool_call: ToolCall,
     /// Available permission options for the user to choose from.
     pub options: Vec<PermissionOption>,
+    /// The server's automatic review, when one is configured. Requests
+    /// reviewed as allow or deny are answered by the server right away.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review: Option<PermissionReview>,
+    /// What the command would do, for relays with command previews enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview: Option<CommandPreview>,
+}
+
+/// How a command preview was produced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+#[serde(rename_all = "snake_case")]
+pub enum PreviewMode {
+    /// The command's own dry-run option (`git push --dry-run`).
+    DryRun,
+    /// The command itself, in a throwaway container on a copy of the workdir.
+    Sandbox,
+}
+
+/// Output of running a permission request's command without effect.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct CommandPreview {
+    pub mode: PreviewMode,
+    /// The command line that was run.
+    pub command: String,
+    /// `None` when the preview timed out or could not start.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Combined stdout and stderr.
+    pub output: String,
+    /// Whether `output` was cut off.
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+/// Verdict of the automatic permission review.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewDecision {
+    Allow,
+    Deny,
+    /// Left to a human.
+    Manual,
+}
+
+/// How risky the reviewer considers a tool call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+#[serde(rename_all = "snake_case")]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+}
+
+/// Automatic review of a permission request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+pub struct PermissionReview {
+    pub decision: ReviewDecision,
+    /// Why the reviewer decided so, shown to human reviewers.
+    pub reason: String,
+    pub risk_level: RiskLevel,
 }
 
 /// Data for permission.responded event - emitted when a user responds to a permission request.
@@ -701,11 +1474,12 @@ pub struct PermissionRespondedData {
 ///
 /// Events are categorized by domain:
 /// - **Task events**: Task lifecycle (created, assigned, completed, failed, etc.)
+/// - **Project events**: Project archiving and restoring
 /// - **Agent events**: Agent lifecycle and output (started, stopped, output, error)
 /// - **Artifact events**: Agent-produced artifacts (files, PRs, commits)
 /// - **Permission events**: Permission request/response flow
 /// - **Relay events**: Relay-to-server communication (output forwarding, status, commands)
-/// - **System events**: Infrastructure events (relay connections)
+/// - **System events**: Infrastructure events (relay connections, backups)
 /// - **Human events**: Human user interactions (messages to tasks/PM)
 ///
 /// The enum is serialized with `#[serde(tag = "kind", content = "data")]`,
@@ -727,6 +1501,22 @@ pub enum BuiltinEvent {
     TaskFailed(TaskFailedData),
     #[serde(rename = "task.archived")]
     TaskArchived(TaskArchivedData),
+    #[serde(rename = "task.scheduled")]
+    TaskScheduled(TaskScheduledData),
+    #[serde(rename = "task.snoozed")]
+    TaskSnoozed(TaskSnoozedData),
+    #[serde(rename = "task.unsnoozed")]
+    TaskUnsnoozed(TaskUnsnoozedData),
+    #[serde(rename = "task.mention")]
+    TaskMention(TaskMentionData),
+    #[serde(rename = "task.handoff")]
+    TaskHandoff(TaskHandoffData),
+
+    // Project events
+    #[serde(rename = "project.archived")]
+    ProjectArchived(ProjectArchivedData),
+    #[serde(rename = "project.restored")]
+    ProjectRestored(ProjectRestoredData),
 
     // Agent lifecycle events
     #[serde(rename = "agent.registered")]
@@ -745,6 +1535,18 @@ pub enum BuiltinEvent {
     AgentSessionStarted(AgentSessionStartedData),
     #[serde(rename = "agent.session_exited")]
     AgentSessionExited(AgentSessionExitedData),
+    #[serde(rename = "agent.unhealthy")]
+    AgentUnhealthy(AgentUnhealthyData),
+    #[serde(rename = "agent.healthy")]
+    AgentHealthy(AgentHealthyData),
+
+    // Agent task tool events
+    #[serde(rename = "agent.task_comment")]
+    AgentTaskComment(AgentTaskCommentData),
+    #[serde(rename = "agent.subtask_done")]
+    AgentSubtaskDone(AgentSubtaskDoneData),
+    #[serde(rename = "agent.followup_task")]
+    AgentFollowupTask(AgentFollowupTaskData),
 
     // Agent collaboration events
     #[serde(rename = "agent.requirement_analyzed")]
@@ -761,10 +1563,22 @@ pub enum BuiltinEvent {
     // Artifact events
     #[serde(rename = "artifact.created")]
     ArtifactCreated(ArtifactCreatedData),
+    #[serde(rename = "artifact.updated")]
+    ArtifactUpdated(ArtifactUpdatedData),
     #[serde(rename = "artifact.github_pr_opened")]
     GithubPrOpened(GithubPrData),
     #[serde(rename = "artifact.github_pr_merged")]
     GithubPrMerged(GithubPrData),
+    #[serde(rename = "artifact.merge_request_opened")]
+    MergeRequestOpened(MergeRequestData),
+    #[serde(rename = "artifact.merge_request_merged")]
+    MergeRequestMerged(MergeRequestData),
+    #[serde(rename = "artifact.merge_request_closed")]
+    MergeRequestClosed(MergeRequestData),
+    #[serde(rename = "artifact.ci_passed")]
+    ArtifactCiPassed(ArtifactCiData),
+    #[serde(rename = "artifact.ci_failed")]
+    ArtifactCiFailed(ArtifactCiData),
 
     // Permission events
     #[serde(rename = "permission.requested")]
@@ -811,6 +1625,10 @@ pub enum BuiltinEvent {
     RelayStopRequested(RelayStopRequestedData),
     #[serde(rename = "relay.input_requested")]
     RelayInputRequested(RelayInputRequestedData),
+    #[serde(rename = "relay.resize_requested")]
+    RelayResizeRequested(RelayResizeRequestedData),
+    #[serde(rename = "relay.log_level_changed")]
+    RelayLogLevelChanged(RelayLogLevelChangedData),
 
     // Relay response events (Relay → Server)
     #[serde(rename = "relay.spawn_completed")]
@@ -825,10 +1643,24 @@ pub enum BuiltinEvent {
     SystemRelayConnected(SystemRelayConnectionData),
     #[serde(rename = "system.relay_disconnected")]
     SystemRelayDisconnected(SystemRelayConnectionData),
+    #[serde(rename = "system.backup_started")]
+    SystemBackupStarted(SystemBackupData),
+    #[serde(rename = "system.backup_progress")]
+    SystemBackupProgress(SystemBackupData),
+    #[serde(rename = "system.backup_completed")]
+    SystemBackupCompleted(SystemBackupData),
+    #[serde(rename = "system.backup_failed")]
+    SystemBackupFailed(SystemBackupData),
+    #[serde(rename = "system.config_reloaded")]
+    SystemConfigReloaded(SystemConfigReloadedData),
 
     // Human interaction events
     #[serde(rename = "human.message")]
     HumanMessage(HumanMessageData),
+
+    // Notification events
+    #[serde(rename = "notification.unread")]
+    NotificationUnread(NotificationUnreadData),
 }
 
 // ============================================================================
@@ -916,6 +1748,74 @@ impl BuiltinEvent {
     }
 }
 
+// ============================================================================
+// Schema Validation
+// ============================================================================
+
+/// Version of the builtin payload schemas, stored with every event.
+/// Bump it when a payload changes incompatibly so stored events can be migrated.
+pub const EVENT_SCHEMA_VERSION: i32 = 1;
+
+/// A builtin event whose data does not match its protocol struct.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventValidationError {
+    /// Event kind that was being validated.
+    pub kind: String,
+    /// Path of the offending field inside `data` (e.g., "outcome.option_id"),
+    /// or "." when the payload itself is wrong.
+    pub path: String,
+    /// What is wrong with the field.
+    pub message: String,
+}
+
+impl fmt::Display for EventValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {} payload at {}: {}", self.kind, self.path, self.message)
+    }
+}
+
+impl std::error::Error for EventValidationError {}
+
+impl BuiltinEvent {
+    /// Parse a flat (kind, data) pair into a typed event.
+    ///
+    /// Returns `Ok(None)` for custom kinds, which carry free-form data.
+    pub fn parse(kind: &str, data: &Value) -> Result<Option<Self>, EventValidationError> {
+        if !EventKind::is_builtin(kind) {
+            return Ok(None);
+        }
+
+        // Feed "kind" before "data" so serde reads the tag first and the
+        // payload is deserialized in place, keeping field paths intact
+        let entries = [
+            ("kind", Value::String(kind.to_string())),
+            ("data", data.clone()),
+        ];
+        let tagged = MapDeserializer::<_, serde_json::Error>::new(entries.into_iter());
+
+        serde_path_to_error::deserialize(tagged)
+            .map(Some)
+            .map_err(|e| {
+                let path = e.path().to_string();
+                let path = match path.strip_prefix("data") {
+                    Some(rest) => rest.trim_start_matches('.').to_string(),
+                    None => path,
+                };
+                EventValidationError {
+                    kind: kind.to_string(),
+                    path: if path.is_empty() { ".".to_string() } else { path },
+                    message: e.into_inner().to_string(),
+                }
+            })
+    }
+
+    /// Check that `data` matches the protocol struct for `kind`.
+    /// Custom kinds always pass.
+    pub fn validate(kind: &str, data: &Value) -> Result<(), EventValidationError> {
+        Self::parse(kind, data).map(|_| ())
+    }
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -1006,6 +1906,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_relay_spawn_requested_wire_format() {
+        let event = BuiltinEvent::RelaySpawnRequested(RelaySpawnRequestedData {
+            relay_id: "relay_123".to_string(),
+            request_id: "req_123".to_string(),
+            target_agent_id: "agent_456".to_string(),
+            session_id: "session_789".to_string(),
+            workdir: "/tmp".to_string(),
+            command: "echo".to_string(),
+            args: vec![],
+            env: HashMap::new(),
+            task_id: None,
+            task: None,
+            mode: SessionMode::Pty,
+        });
+        let (kind, data) = event.into_parts();
+        assert_eq!(kind, EventKind::RELAY_SPAWN_REQUESTED);
+        // The relay reads these keys directly
+        assert_eq!(data["agent_id"], "agent_456");
+        assert_eq!(data["mode"], "pty");
+        assert!(data.get("target_agent_id").is_none());
+    }
+
     #[test]
     fn test_deserialize_custom_event() {
         let message = r#"
@@ -1030,12 +1953,36 @@ mod tests {
             title: "Test".to_string(),
             description: None,
             parent_task_id: None,
+            source: None,
+            sender: None,
         });
         let json = serde_json::to_string(&event).unwrap();
         assert!(json.contains(r#""kind":"task.created""#));
         assert!(json.contains(r#""title":"Test""#));
     }
 
+    #[test]
+    fn test_deserialize_agent_followup_task() {
+        let message = r#"
+        {
+            "kind": "agent.followup_task",
+            "agent_id": "agent_id",
+            "task_id": "018f1a2b-3c4d-5e6f-7a8b-9c0d1e2f3a4b",
+            "data": {
+                "session_id": "session_id",
+                "content": "Add migration for the new column"
+            }
+        }
+        "#;
+        let msg: EventMessage = serde_json::from_str(message).unwrap();
+        if let Event::Builtin(BuiltinEvent::AgentFollowupTask(data)) = msg.event {
+            assert_eq!(data.content, "Add migration for the new column");
+            assert_eq!(data.priority, 0);
+        } else {
+            panic!("Expected AgentFollowupTask event");
+        }
+    }
+
     #[test]
     fn test_permission_outcome() {
         let selected = PermissionOutcome::selected("allow");
@@ -1047,4 +1994,71 @@ mod tests {
         let json = serde_json::to_string(&cancelled).unwrap();
         assert!(json.contains(r#""cancelled":true"#));
     }
+
+    #[test]
+    fn test_permission_fallback_defaults() {
+        let fallback: PermissionFallback = serde_json::from_str(r#"{"action": "deny"}"#).unwrap();
+        assert_eq!(fallback.action, PermissionFallbackAction::Deny);
+        assert_eq!(fallback.timeout_secs, 600);
+
+        let fallback: PermissionFallback = serde_json::from_str("{}").unwrap();
+        assert_eq!(fallback, PermissionFallback::default());
+        assert!(serde_json::from_str::<PermissionFallback>(r#"{"action": "maybe"}"#).is_err());
+    }
+
+    #[test]
+    fn test_output_stream_kind_round_trip() {
+        for name in ["assistant", "tool_use", "stderr", "terminal"] {
+            let kind: OutputStreamKind = serde_json::from_value(serde_json::json!(name)).unwrap();
+            assert_eq!(kind, OutputStreamKind::from(name));
+            assert_eq!(serde_json::to_value(&kind).unwrap(), serde_json::json!(name));
+        }
+
+        // Unknown kinds survive a round trip
+        let kind: OutputStreamKind = serde_json::from_str(r#""diff""#).unwrap();
+        assert_eq!(kind, OutputStreamKind::Other("diff".to_string()));
+        assert_eq!(serde_json::to_string(&kind).unwrap(), r#""diff""#);
+        assert_eq!(OutputStreamKind::from("diff"), kind);
+    }
+
+    #[test]
+    fn test_validate_reports_field_path() {
+        let err = BuiltinEvent::validate(
+            EventKind::TASK_SNOOZED,
+            &serde_json::json!({ "until": 5, "notify": true }),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind, "task.snoozed");
+        assert_eq!(err.path, "until");
+        assert!(err.message.contains("expected a string"), "{}", err.message);
+
+        let err = BuiltinEvent::validate(
+            EventKind::PERMISSION_RESPONDED,
+            &serde_json::json!({ "relay_id": "r", "request_id": "q", "session_id": "s" }),
+        )
+        .unwrap_err();
+        assert_eq!(err.path, ".");
+        assert!(err.message.contains("outcome"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_validate_accepts_relay_wire_format_and_custom_kinds() {
+        let output = serde_json::json!({
+            "relay_id": "relay_123",
+            "agent_id": "agent_456",
+            "session_id": "session_789",
+            "seq": 1,
+            "ts": 0,
+            "stream": "assistant",
+            "message": "hi",
+        });
+        assert!(BuiltinEvent::validate(EventKind::RELAY_AGENT_OUTPUT, &output).is_ok());
+
+        let error = serde_json::json!({ "relay_id": "relay_123", "error": "boom" });
+        assert!(BuiltinEvent::validate(EventKind::RELAY_ERROR, &error).is_ok());
+
+        assert!(BuiltinEvent::validate("custom.event", &serde_json::json!(42)).is_ok());
+        assert!(EventKind::is_builtin(EventKind::HUMAN_MESSAGE));
+        assert!(!EventKind::is_builtin("custom.event"));
+    }
 }
@@ -2,9 +2,12 @@
 
 use std::collections::HashMap;
 
+#[cfg(feature = "schematic")]
+use gotcha::Schematic;
 use serde::{Deserialize, Serialize};
 
 pub mod event_bus;
+pub mod relay_version;
 
 // Re-export event_bus types for convenience
 pub use event_bus::*;
@@ -48,6 +51,21 @@ impl AgentRole {
 // Relay Session Parameters (used by relay internally)
 // ============================================================================
 
+/// How the relay drives a spawned agent process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schematic", derive(Schematic))]
+#[serde(rename_all = "snake_case")]
+pub enum SessionMode {
+    /// Speak ACP over stdio
+    #[default]
+    Acp,
+    /// Plain process: raw stdout/stderr is streamed, input is written to stdin
+    Process,
+    /// Interactive pseudo-terminal: output is streamed as `terminal`,
+    /// input is raw keystrokes and the terminal can be resized
+    Pty,
+}
+
 /// Parameters for spawn-session
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpawnSessionParams {
@@ -66,6 +84,76 @@ pub struct SpawnSessionParams {
     /// Optional task_id for associating events with a task
     #[serde(default)]
     pub task_id: Option<String>,
+    /// Task snapshot exposed to the agent through the todoki task tools
+    #[serde(default)]
+    pub task: Option<AgentTaskContext>,
+    /// Backend used to drive the process
+    #[serde(default)]
+    pub mode: SessionMode,
+}
+
+// ============================================================================
+// Agent Task Tools (ACP extension methods)
+// ============================================================================
+
+/// Extension method names the relay registers with ACP agents.
+///
+/// Agents call these via ACP `ext_method`; the relay answers `GET_TASK` from the
+/// spawn-time task snapshot and turns the others into `agent.*` events.
+pub struct AgentToolMethod;
+
+impl AgentToolMethod {
+    pub const GET_TASK: &str = "todoki/get_task";
+    pub const POST_COMMENT: &str = "todoki/post_comment";
+    pub const COMPLETE_SUBTASK: &str = "todoki/complete_subtask";
+    pub const CREATE_FOLLOWUP_TASK: &str = "todoki/create_followup_task";
+
+    /// All tool methods, advertised to the agent during initialization
+    pub const ALL: [&str; 4] = [
+        Self::GET_TASK,
+        Self::POST_COMMENT,
+        Self::COMPLETE_SUBTASK,
+        Self::CREATE_FOLLOWUP_TASK,
+    ];
+}
+
+// ============================================================================
+// Relay WebSocket Endpoint
+// ============================================================================
+
+/// Path relays connect to; `/ws/event-bus` is for frontends only.
+pub const RELAY_WS_PATH: &str = "/ws/relay";
+
+/// Kinds the server sends to relays; nothing else on the bus reaches them.
+pub const RELAY_COMMAND_KINDS: [&str; 6] = [
+    EventKind::RELAY_SPAWN_REQUESTED,
+    EventKind::RELAY_STOP_REQUESTED,
+    EventKind::RELAY_INPUT_REQUESTED,
+    EventKind::RELAY_RESIZE_REQUESTED,
+    EventKind::RELAY_LOG_LEVEL_CHANGED,
+    EventKind::PERMISSION_RESPONDED,
+];
+
+/// Whether a relay may emit `kind` over its WebSocket: its own `relay.*`
+/// reports and the `agent.*` events of its sessions, but neither the
+/// server's commands nor `relay.down`.
+pub fn relay_may_emit(kind: &str) -> bool {
+    if RELAY_COMMAND_KINDS.contains(&kind) || kind == EventKind::RELAY_DOWN {
+        return false;
+    }
+    kind.starts_with("relay.") || kind.starts_with("agent.")
+}
+
+/// Codes of the errors the server sends over the relay WebSocket.
+pub struct RelayErrorCode;
+
+impl RelayErrorCode {
+    /// An event other than `relay.up` arrived before registration
+    pub const NOT_REGISTERED: &str = "not_registered";
+    /// The relay emitted a kind it may not emit
+    pub const KIND_NOT_ALLOWED: &str = "kind_not_allowed";
+    /// Another connection registered with the same relay ID
+    pub const SUPERSEDED: &str = "superseded";
 }
 
 /// Parameters for send-input
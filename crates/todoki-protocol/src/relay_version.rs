@@ -0,0 +1,251 @@
+//! Relay WebSocket protocol versions
+//!
+//! Relays report the versions they speak in `relay.up` (`protocol_version`
+//! and `min_protocol_version`); relays from before versioning send neither
+//! and speak version 1. The server picks the newest version both sides
+//! support and confirms it in `registered` together with its own range.
+//! When the ranges do not overlap the registration is refused with an
+//! `incompatible_protocol` error naming both ranges.
+//!
+//! Fields added to server → relay events after version 1 are listed in
+//! [`ADDED_FIELDS`]. Events sent to a relay speaking an older version have
+//! them removed by [`downgrade`], so older relays keep working; events whose
+//! new field changes what the relay has to do cannot be downgraded and are
+//! refused instead. Relays read missing fields of the server's messages as
+//! their defaults, so nothing needs upgrading the other way.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Version of the relay protocol this build speaks.
+pub const RELAY_PROTOCOL_VERSION: u16 = 2;
+
+/// Oldest version this build still speaks.
+pub const MIN_RELAY_PROTOCOL_VERSION: u16 = 1;
+
+/// Version of relays that do not report one.
+pub const UNVERSIONED_RELAY_PROTOCOL: u16 = 1;
+
+/// Error code of a registration refused for incompatible versions.
+pub const INCOMPATIBLE_PROTOCOL: &str = "incompatible_protocol";
+
+/// Versions a peer speaks, both ends included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolRange {
+    pub min: u16,
+    pub max: u16,
+}
+
+impl ProtocolRange {
+    /// The versions this build speaks.
+    pub const fn current() -> Self {
+        Self {
+            min: MIN_RELAY_PROTOCOL_VERSION,
+            max: RELAY_PROTOCOL_VERSION,
+        }
+    }
+
+    /// The range a relay reported in `relay.up`.
+    pub fn from_relay_up(data: &Value) -> Self {
+        let version = |field: &str| {
+            data.get(field)
+                .and_then(Value::as_u64)
+                .and_then(|v| u16::try_from(v).ok())
+        };
+        let max = version("protocol_version").unwrap_or(UNVERSIONED_RELAY_PROTOCOL);
+        let min = version("min_protocol_version").unwrap_or(max).min(max);
+        Self { min, max }
+    }
+
+    /// The newest version both ranges contain.
+    pub fn negotiate(&self, other: &ProtocolRange) -> Result<u16, IncompatibleProtocol> {
+        let version = self.max.min(other.max);
+        if version >= self.min.max(other.min) {
+            Ok(version)
+        } else {
+            Err(IncompatibleProtocol {
+                server: *self,
+                relay: *other,
+            })
+        }
+    }
+}
+
+impl std::fmt::Display for ProtocolRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.min == self.max {
+            write!(f, "v{}", self.max)
+        } else {
+            write!(f, "v{}-v{}", self.min, self.max)
+        }
+    }
+}
+
+/// Server and relay have no protocol version in common.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IncompatibleProtocol {
+    pub server: ProtocolRange,
+    pub relay: ProtocolRange,
+}
+
+impl std::fmt::Display for IncompatibleProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let upgrade = if self.relay.max < self.server.min {
+            "upgrade the relay"
+        } else {
+            "upgrade the server"
+        };
+        write!(
+            f,
+            "relay protocol {} is not supported by the server ({}); {}",
+            self.relay, self.server, upgrade
+        )
+    }
+}
+
+impl std::error::Error for IncompatibleProtocol {}
+
+/// How an older relay copes without an added field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fallback {
+    /// The field is only informational and is removed.
+    Drop,
+    /// The field is removed while it holds this value, the behaviour older
+    /// relays have anyway; any other value makes the event undeliverable.
+    OnlyDefault(&'static str),
+}
+
+/// A field added to a server → relay event in a later protocol version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddedField {
+    pub kind: &'static str,
+    pub field: &'static str,
+    /// First version that knows the field.
+    pub since: u16,
+    pub fallback: Fallback,
+}
+
+/// Fields added after version 1, oldest first; version 2 only added the
+/// handshake itself.
+pub const ADDED_FIELDS: &[AddedField] = &[];
+
+/// An event that a relay speaking an older version cannot act on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Undeliverable {
+    pub kind: String,
+    pub field: &'static str,
+    pub since: u16,
+    pub version: u16,
+}
+
+impl std::fmt::Display for Undeliverable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} needs `{}` from relay protocol v{}, the relay speaks v{}",
+            self.kind, self.field, self.since, self.version
+        )
+    }
+}
+
+impl std::error::Error for Undeliverable {}
+
+/// Make the data of a `kind` event fit a relay speaking `version`.
+pub fn downgrade(kind: &str, data: &mut Value, version: u16) -> Result<(), Undeliverable> {
+    downgrade_with(ADDED_FIELDS, kind, data, version)
+}
+
+fn downgrade_with(
+    added: &[AddedField],
+    kind: &str,
+    data: &mut Value,
+    version: u16,
+) -> Result<(), Undeliverable> {
+    let Some(object) = data.as_object_mut() else {
+        return Ok(());
+    };
+    for added in added.iter().filter(|a| a.kind == kind && a.since > version) {
+        let Some(value) = object.get(added.field) else {
+            continue;
+        };
+        if let Fallback::OnlyDefault(default) = added.fallback
+            && value.as_str() != Some(default)
+        {
+            return Err(Undeliverable {
+                kind: kind.to_string(),
+                field: added.field,
+                since: added.since,
+                version,
+            });
+        }
+        object.remove(added.field);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_negotiate() {
+        let server = ProtocolRange { min: 2, max: 4 };
+        assert_eq!(server.negotiate(&ProtocolRange { min: 1, max: 3 }), Ok(3));
+        assert_eq!(server.negotiate(&ProtocolRange { min: 3, max: 9 }), Ok(4));
+
+        let old = ProtocolRange::from_relay_up(&json!({ "relay_id": "r" }));
+        assert_eq!(old, ProtocolRange { min: 1, max: 1 });
+        let err = server.negotiate(&old).unwrap_err();
+        assert!(err.to_string().contains("upgrade the relay"), "{}", err);
+
+        let newer = ProtocolRange::from_relay_up(&json!({
+            "protocol_version": 7,
+            "min_protocol_version": 5,
+        }));
+        assert_eq!(newer, ProtocolRange { min: 5, max: 7 });
+        let err = server.negotiate(&newer).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "relay protocol v5-v7 is not supported by the server (v2-v4); upgrade the server"
+        );
+
+        let current = ProtocolRange::current();
+        assert_eq!(
+            current.negotiate(&old),
+            Ok(UNVERSIONED_RELAY_PROTOCOL),
+            "unversioned relays must keep working"
+        );
+    }
+
+    #[test]
+    fn test_downgrade() {
+        let added = [
+            AddedField {
+                kind: "relay.spawn_requested",
+                field: "hints",
+                since: 3,
+                fallback: Fallback::Drop,
+            },
+            AddedField {
+                kind: "relay.spawn_requested",
+                field: "sandbox",
+                since: 3,
+                fallback: Fallback::OnlyDefault("none"),
+            },
+        ];
+
+        let mut data = json!({ "session_id": "s", "hints": ["a"], "sandbox": "none" });
+        downgrade_with(&added, "relay.spawn_requested", &mut data, 3).unwrap();
+        assert!(data.get("hints").is_some());
+        downgrade_with(&added, "relay.spawn_requested", &mut data, 2).unwrap();
+        assert_eq!(data, json!({ "session_id": "s" }));
+
+        let mut data = json!({ "session_id": "s", "sandbox": "container" });
+        let err = downgrade_with(&added, "relay.spawn_requested", &mut data, 2).unwrap_err();
+        assert_eq!(err.field, "sandbox");
+
+        let mut data = json!({ "sandbox": "container" });
+        downgrade_with(&added, "relay.stop_requested", &mut data, 1).unwrap();
+    }
+}
@@ -1,34 +1,40 @@
 use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicI64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
 use agent_client_protocol::{
     Agent, CancelNotification, Client, ClientCapabilities, ClientSideConnection, ContentBlock,
-    Implementation, InitializeRequest, NewSessionRequest, PromptRequest, ProtocolVersion,
-    RequestPermissionOutcome, RequestPermissionRequest, RequestPermissionResponse,
-    SelectedPermissionOutcome, SessionNotification, SessionUpdate, ToolCall as AcpToolCall,
-    ToolCallUpdate,
+    ExtRequest, ExtResponse, Implementation, InitializeRequest, NewSessionRequest, PromptRequest, ProtocolVersion,
+    PermissionOptionKind, RequestPermissionOutcome, RequestPermissionRequest,
+    RequestPermissionResponse, SelectedPermissionOutcome, SessionNotification, SessionUpdate,
+    ToolCall as AcpToolCall, ToolCallUpdate,
 };
 // Note: We use AcpToolCall for the full ToolCall type and ToolCallUpdate for permission requests
 use chrono::Utc;
-use once_cell::sync::Lazy;
-use regex::Regex;
 use serde_json::{Map, Value};
 use tokio::process::{ChildStdin, ChildStdout};
 use tokio::sync::{mpsc, oneshot, Mutex};
 use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
+use crate::acp_version::{self, Negotiation, NegotiatedProtocol};
 use crate::event_bus_client::EventBusClient;
+use crate::config::{OutputSettings, PreviewSettings};
+use crate::flow_control::{FlowControl, OutputControl};
+use crate::output_buffer::OutputBuffer;
+use crate::preview;
 use crate::relay::RelayOutput;
+use crate::review_links;
+use crate::task_tools::{TaskToolError, TaskTools};
 use todoki_protocol::event_bus::{
-    AgentOutputBatchData, ArtifactCreatedData, BuiltinEvent, PermissionOption, PermissionRequestedData,
-    ToolCall,
+    AgentOutputBatchData, AgentSessionStartedData, AgentTaskContext, ArtifactCreatedData,
+    BuiltinEvent, OutputStreamKind, PermissionOption, PermissionRequestedData, ToolCall,
 };
+use todoki_protocol::{AgentToolMethod, PermissionFallback, PermissionFallbackAction};
 
-/// Regex for detecting GitHub PR URLs
-static PR_REGEX: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"https://github\.com/([^/]+)/([^/]+)/pull/(\d+)").unwrap());
+/// Ext method used to probe agent liveness
+const PING_METHOD: &str = "todoki/ping";
 
 /// Convert ACP ToolCallUpdate to protocol ToolCall
 fn convert_tool_call_update(acp: &ToolCallUpdate) -> ToolCall {
@@ -52,6 +58,8 @@ fn convert_permission_option(acp: &agent_client_protocol::PermissionOption) -> P
 #[derive(Clone)]
 pub struct AcpHandle {
     pub acp_session_id: String,
+    /// Protocol version and features agreed with the agent
+    pub protocol: NegotiatedProtocol,
     tx: mpsc::Sender<AcpCommand>,
 }
 
@@ -66,6 +74,21 @@ impl AcpHandle {
         Ok(done_rx)
     }
 
+    /// Round-trip a `todoki/ping` ext request
+    ///
+    /// Any answer counts, including a method-not-found error: it shows the
+    /// agent is still reading and answering its input.
+    pub async fn ping(&self) -> anyhow::Result<()> {
+        let (done_tx, done_rx) = oneshot::channel();
+        self.tx
+            .send(AcpCommand::Ping { done_tx })
+            .await
+            .map_err(|_| anyhow::anyhow!("acp channel closed"))?;
+        done_rx
+            .await
+            .map_err(|_| anyhow::anyhow!("acp session ended"))
+    }
+
     pub async fn cancel(&self) -> anyhow::Result<()> {
         self.tx
             .send(AcpCommand::Cancel)
@@ -110,18 +133,16 @@ enum AcpCommand {
         done_tx: oneshot::Sender<()>,
     },
     Cancel,
+    Ping {
+        /// Signalled once the agent answered
+        done_tx: oneshot::Sender<()>,
+    },
     RespondPermission {
         request_id: String,
         outcome: RequestPermissionOutcome,
     },
 }
 
-/// Buffer state for aggregating output
-struct OutputBufferState {
-    current_stream: Option<String>,
-    messages: Vec<String>,
-}
-
 /// Event sink for ACP output
 #[derive(Clone)]
 struct AcpEventSink {
@@ -129,10 +150,16 @@ struct AcpEventSink {
     agent_id: String,
     session_id: String,
     seq_counter: Arc<AtomicI64>,
-    /// Buffer for aggregating output (flush on stream type change)
-    buffer: Arc<Mutex<OutputBufferState>>,
+    /// Buffer for aggregating output (flushed on stream type change, size
+    /// limits or latency)
+    buffer: Arc<Mutex<OutputBuffer>>,
+    batch: OutputSettings,
+    /// Holds back agent output while the server connection is congested
+    flow: Arc<FlowControl>,
     /// Client for emitting events to event-bus
     event_bus: EventBusClient,
+    /// Set once `initialize` has negotiated a version
+    protocol: Arc<OnceLock<NegotiatedProtocol>>,
 }
 
 impl AcpEventSink {
@@ -141,6 +168,8 @@ impl AcpEventSink {
         agent_id: String,
         session_id: String,
         event_bus: EventBusClient,
+        batch: OutputSettings,
+        flow: Arc<FlowControl>,
     ) -> Self {
         // Initialize seq with current timestamp to maintain global ordering across sessions
         let initial_seq = Utc::now().timestamp_nanos_opt().unwrap_or(0);
@@ -149,19 +178,29 @@ impl AcpEventSink {
             agent_id,
             session_id,
             seq_counter: Arc::new(AtomicI64::new(initial_seq)),
-            buffer: Arc::new(Mutex::new(OutputBufferState {
-                current_stream: None,
-                messages: Vec::new(),
-            })),
+            buffer: Arc::new(Mutex::new(OutputBuffer::new(batch.clone()))),
+            batch,
+            flow,
             event_bus,
+            protocol: Arc::new(OnceLock::new()),
         }
     }
 
+    /// Whether session updates can go through the typed mapping layer
+    fn typed_updates(&self) -> bool {
+        self.protocol
+            .get()
+            .map_or(true, NegotiatedProtocol::typed_updates)
+    }
+
     async fn emit_system(&self, message: String) {
-        self.emit_raw("system", message).await;
+        self.emit_raw(OutputStreamKind::System, message).await;
     }
 
-    async fn emit_raw(&self, stream: &str, message: String) {
+    async fn emit_raw(&self, stream: OutputStreamKind, message: String) {
+        // Hold output back while the server connection is congested
+        self.flow.acquire(message.len()).await;
+
         let seq = self.seq_counter.fetch_add(1, Ordering::SeqCst);
         let ts = Utc::now().timestamp_nanos_opt().unwrap_or(0);
 
@@ -173,18 +212,21 @@ impl AcpEventSink {
             "emitting agent output"
         );
 
-        // Check if stream type changed - if so, flush previous buffer first
+        // Batches are flushed while holding the lock so they stay in order
         {
             let mut buffer = self.buffer.lock().await;
-            if let Some(ref current) = buffer.current_stream {
-                if current != stream && !buffer.messages.is_empty() {
-                    // Stream type changed, flush previous
-                    self.flush_buffer_inner(current, &buffer.messages, ts).await;
-                    buffer.messages.clear();
+            if buffer.stream() != Some(&stream) {
+                // Stream type changed, flush previous
+                if let Some((current, messages)) = buffer.take() {
+                    self.flush_buffer_inner(&current, &messages, ts).await;
                 }
+                buffer.set_stream(Some(stream.clone()));
+            }
+            buffer.push(message.clone());
+
+            if buffer.is_full() && let Some((current, messages)) = buffer.take() {
+                self.flush_buffer_inner(&current, &messages, ts).await;
             }
-            buffer.current_stream = Some(stream.to_string());
-            buffer.messages.push(message.clone());
         }
 
         // Emit via Event Bus (streaming, for real-time display)
@@ -204,14 +246,14 @@ impl AcpEventSink {
     }
 
     /// Internal: emit batch event to event-bus
-    async fn flush_buffer_inner(&self, stream: &str, messages: &[String], ts: i64) {
+    async fn flush_buffer_inner(&self, stream: &OutputStreamKind, messages: &[String], ts: i64) {
         if messages.is_empty() {
             return;
         }
 
         let event = BuiltinEvent::AgentOutputBatch(AgentOutputBatchData {
             session_id: self.session_id.clone(),
-            stream: stream.to_string(),
+            stream: stream.clone(),
             messages: messages.to_vec(),
             ts,
         });
@@ -222,16 +264,27 @@ impl AcpEventSink {
     /// Flush remaining buffer (call on prompt completion)
     async fn flush_buffer(&self) {
         let ts = Utc::now().timestamp_nanos_opt().unwrap_or(0);
-        let (stream, messages) = {
-            let mut buffer = self.buffer.lock().await;
-            let stream = buffer.current_stream.take();
-            let messages = std::mem::take(&mut buffer.messages);
-            (stream, messages)
-        };
-
-        if let Some(stream) = stream {
+        let mut buffer = self.buffer.lock().await;
+        if let Some((stream, messages)) = buffer.take() {
             self.flush_buffer_inner(&stream, &messages, ts).await;
         }
+        buffer.set_stream(None);
+    }
+
+    /// Flush batches older than the configured latency; runs for the
+    /// lifetime of the session
+    async fn run_flush_timer(&self) {
+        let max_latency = self.batch.max_batch_latency();
+        let mut interval = tokio::time::interval(max_latency / 2);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let ts = Utc::now().timestamp_nanos_opt().unwrap_or(0);
+            let mut buffer = self.buffer.lock().await;
+            if buffer.is_stale() && let Some((stream, messages)) = buffer.take() {
+                self.flush_buffer_inner(&stream, &messages, ts).await;
+            }
+        }
     }
 
     async fn emit_update(&self, update: SessionUpdate) {
@@ -248,67 +301,63 @@ impl AcpEventSink {
 
         // Determine stream type based on SessionUpdate variant
         let stream = match &update {
-            SessionUpdate::AgentMessageChunk(_) => "assistant",
-            SessionUpdate::AgentThoughtChunk(_) => "thinking",
-            SessionUpdate::ToolCall(_) => "tool_use",
-            SessionUpdate::ToolCallUpdate(_) => "tool_result",
-            SessionUpdate::UserMessageChunk(_) => "user",
-            SessionUpdate::Plan(_) => "plan",
-            _ => "system",
+            SessionUpdate::AgentMessageChunk(_) => OutputStreamKind::Assistant,
+            SessionUpdate::AgentThoughtChunk(_) => OutputStreamKind::Thinking,
+            SessionUpdate::ToolCall(_) => OutputStreamKind::ToolUse,
+            SessionUpdate::ToolCallUpdate(_) => OutputStreamKind::ToolResult,
+            SessionUpdate::UserMessageChunk(_) => OutputStreamKind::User,
+            SessionUpdate::Plan(_) => OutputStreamKind::Plan,
+            _ => OutputStreamKind::System,
         };
 
+        if !self.typed_updates() {
+            let payload = serde_json::to_value(&update).unwrap_or(Value::Null);
+            let message = serde_json::json!({ "type": "session_update", "payload": payload });
+            self.emit_raw(OutputStreamKind::System, message.to_string()).await;
+            return;
+        }
+
         if let Some(value) = update_to_event(update) {
             let message = value.to_string();
             self.emit_raw(stream, message).await;
         }
     }
 
-    /// Detect and emit artifacts from tool call output (e.g., GitHub PR URLs)
+    /// Detect and emit artifacts from tool call output (pull and merge
+    /// request URLs)
     async fn detect_artifacts(&self, update: &ToolCallUpdate) {
-        if let Some(raw_output) = &update.fields.raw_output {
-            if let Some(output_str) = raw_output.as_str() {
-                // Detect GitHub PR URLs
-                for caps in PR_REGEX.captures_iter(output_str) {
-                    let url = caps.get(0).map(|m| m.as_str()).unwrap_or_default();
-                    let owner = caps.get(1).map(|m| m.as_str()).unwrap_or_default();
-                    let repo = caps.get(2).map(|m| m.as_str()).unwrap_or_default();
-                    let number: u32 = caps
-                        .get(3)
-                        .and_then(|m| m.as_str().parse().ok())
-                        .unwrap_or(0);
-
-                    tracing::info!(
-                        session_id = %self.session_id,
-                        url = %url,
-                        owner = %owner,
-                        repo = %repo,
-                        number = number,
-                        "detected GitHub PR artifact"
-                    );
+        let Some(output_str) = update.fields.raw_output.as_ref().and_then(|v| v.as_str()) else {
+            return;
+        };
+        for link in review_links::detect(output_str) {
+            tracing::info!(
+                session_id = %self.session_id,
+                artifact_type = link.artifact_type,
+                url = %link.data["url"],
+                "detected review artifact"
+            );
 
-                    let artifact_data = ArtifactCreatedData {
-                        session_id: self.session_id.clone(),
-                        artifact_type: "github_pr".to_string(),
-                        data: serde_json::json!({
-                            "url": url,
-                            "owner": owner,
-                            "repo": repo,
-                            "number": number,
-                        }),
-                    };
-
-                    // Send to server via WebSocket (for artifacts table)
-                    let msg = RelayOutput::EmitEvent {
-                        kind: "relay.artifact".to_string(),
-                        data: serde_json::to_value(&artifact_data).unwrap_or_default(),
-                    };
-                    let _ = self.output_tx.send(msg).await;
-
-                    // Also emit to event-bus via HTTP for persistence/replay
-                    let event = BuiltinEvent::ArtifactCreated(artifact_data);
-                    self.event_bus.emit_builtin_fire_and_forget(event).await;
-                }
+            let artifact_data = ArtifactCreatedData {
+                session_id: self.session_id.clone(),
+                artifact_type: link.artifact_type.to_string(),
+                data: link.data,
+            };
+
+            // Send to server via WebSocket (for artifacts table); the server
+            // attaches it to the agent's task
+            let mut data = serde_json::to_value(&artifact_data).unwrap_or_default();
+            if let Some(obj) = data.as_object_mut() {
+                obj.insert("agent_id".to_string(), self.agent_id.clone().into());
             }
+            let msg = RelayOutput::EmitEvent {
+                kind: "relay.artifact".to_string(),
+                data,
+            };
+            let _ = self.output_tx.send(msg).await;
+
+            // Also emit to event-bus via HTTP for persistence/replay
+            let event = BuiltinEvent::ArtifactCreated(artifact_data);
+            self.event_bus.emit_builtin_fire_and_forget(event).await;
         }
     }
 }
@@ -326,6 +375,11 @@ struct PermissionManager {
     output_tx: mpsc::Sender<RelayOutput>,
     session_id: String,
     event_bus: EventBusClient,
+    /// Applied when the server does not answer in time
+    fallback: PermissionFallback,
+    /// Shell command previews, run in `workdir`
+    preview: PreviewSettings,
+    workdir: String,
 }
 
 impl PermissionManager {
@@ -333,12 +387,18 @@ impl PermissionManager {
         output_tx: mpsc::Sender<RelayOutput>,
         session_id: String,
         event_bus: EventBusClient,
+        fallback: PermissionFallback,
+        preview: PreviewSettings,
+        workdir: String,
     ) -> Self {
         Self {
             pending: Mutex::new(None),
             output_tx,
             session_id,
             event_bus,
+            fallback,
+            preview,
+            workdir,
         }
     }
 
@@ -355,6 +415,10 @@ impl PermissionManager {
             "PermissionManager::create_request: creating permission request"
         );
 
+        // Run the preview before registering: nothing can answer yet
+        let tool_call = convert_tool_call_update(&args.tool_call);
+        let preview = preview::preview(&self.preview, &self.workdir, &tool_call).await;
+
         // IMPORTANT: Register in pending BEFORE sending to server to avoid race condition
         // where response arrives before we're ready to receive it
         let (tx, rx) = oneshot::channel();
@@ -383,8 +447,10 @@ impl PermissionManager {
             session_id: self.session_id.clone(),
             request_id: request_id.clone(),
             tool_call_id: args.tool_call.tool_call_id.to_string(),
-            tool_call: convert_tool_call_update(&args.tool_call),
+            tool_call,
             options: args.options.iter().map(convert_permission_option).collect(),
+            review: None,
+            preview,
         };
 
         // Send permission request to server via WebSocket (for real-time handling)
@@ -466,11 +532,16 @@ impl PermissionManager {
 struct AcpClient {
     sink: AcpEventSink,
     permissions: Arc<PermissionManager>,
+    tools: TaskTools,
 }
 
 impl AcpClient {
-    fn new(sink: AcpEventSink, permissions: Arc<PermissionManager>) -> Self {
-        Self { sink, permissions }
+    fn new(sink: AcpEventSink, permissions: Arc<PermissionManager>, tools: TaskTools) -> Self {
+        Self {
+            sink,
+            permissions,
+            tools,
+        }
     }
 }
 
@@ -486,24 +557,34 @@ impl Client for AcpClient {
             .await
             .map_err(|e| agent_client_protocol::Error::internal_error().data(e.to_string()))?;
 
-        // Wait for response with timeout (5 minutes)
-        let outcome = match tokio::time::timeout(
-            std::time::Duration::from_secs(300),
-            response_rx,
-        )
-        .await
-        {
+        // The server answers every request: with a human decision, or with a
+        // cancellation once it expires (`permissions.expire_after_secs`). The
+        // fallback only covers a server that cannot answer.
+        let fallback = self.permissions.fallback;
+        let answer = match fallback.action {
+            PermissionFallbackAction::KeepWaiting => Ok(response_rx.await),
+            _ => {
+                let timeout = Duration::from_secs(fallback.timeout_secs);
+                tokio::time::timeout(timeout, response_rx).await
+            }
+        };
+        let outcome = match answer {
             Ok(Ok(outcome)) => outcome,
             Ok(Err(_)) => {
-                // Channel closed
-                pick_allow_option(&args)
+                // Channel closed (request replaced or session shutting down)
+                self.sink
+                    .emit_system(format!("permission request {} was dropped", request_id))
+                    .await;
+                RequestPermissionOutcome::Cancelled
             }
             Err(_) => {
-                // Timeout - auto-select allow option
                 self.sink
-                    .emit_system(format!("permission request {} timed out", request_id))
+                    .emit_system(format!(
+                        "permission request {} timed out, falling back to {:?}",
+                        request_id, fallback.action
+                    ))
                     .await;
-                pick_allow_option(&args)
+                fallback_outcome(&args, fallback.action)
             }
         };
 
@@ -517,28 +598,128 @@ impl Client for AcpClient {
         self.sink.emit_update(args.update).await;
         Ok(())
     }
+
+    async fn ext_method(&self, args: ExtRequest) -> Result<ExtResponse, agent_client_protocol::Error> {
+        let tools_enabled = self
+            .sink
+            .protocol
+            .get()
+            .map_or(true, NegotiatedProtocol::task_tools);
+        if !tools_enabled || !TaskTools::handles(&args.method) {
+            return Err(agent_client_protocol::Error::method_not_found());
+        }
+
+        let params: Value = serde_json::from_str(args.params.get()).unwrap_or(Value::Null);
+        let result = self.tools.call(&args.method, params).await.map_err(|e| {
+            tracing::warn!(method = %args.method, error = %e, "agent task tool failed");
+            match e {
+                TaskToolError::UnknownMethod(_) => agent_client_protocol::Error::method_not_found(),
+                TaskToolError::InvalidParams(msg) => {
+                    agent_client_protocol::Error::invalid_params().data(msg)
+                }
+                other => agent_client_protocol::Error::internal_error().data(other.to_string()),
+            }
+        })?;
+
+        let raw = serde_json::value::to_raw_value(&result)
+            .map_err(|e| agent_client_protocol::Error::internal_error().data(e.to_string()))?;
+        Ok(ExtResponse::new(Arc::from(raw)))
+    }
 }
 
-fn pick_allow_option(args: &RequestPermissionRequest) -> RequestPermissionOutcome {
-    let option_id = args
-        .options
-        .iter()
-        .find(|opt| {
+/// Client capabilities advertising the todoki task tools under `_meta`
+/// (v0 has no `_meta`, so nothing is advertised there)
+fn client_capabilities(version: u16) -> ClientCapabilities {
+    if version == 0 {
+        return ClientCapabilities::default();
+    }
+    let mut meta = Map::new();
+    meta.insert(
+        "todoki".to_string(),
+        serde_json::json!({ "tools": AgentToolMethod::ALL }),
+    );
+    ClientCapabilities::default().meta(meta)
+}
+
+/// Outcome of a permission request resolved by `action` instead of an answer
+fn fallback_outcome(
+    args: &RequestPermissionRequest,
+    action: PermissionFallbackAction,
+) -> RequestPermissionOutcome {
+    let option = match action {
+        PermissionFallbackAction::Allow => args.options.iter().find(|opt| {
+            matches!(
+                opt.kind,
+                PermissionOptionKind::AllowOnce | PermissionOptionKind::AllowAlways
+            )
+        }),
+        PermissionFallbackAction::Deny => args.options.iter().find(|opt| {
             matches!(
                 opt.kind,
-                agent_client_protocol::PermissionOptionKind::AllowAlways
-                    | agent_client_protocol::PermissionOptionKind::AllowOnce
+                PermissionOptionKind::RejectOnce | PermissionOptionKind::RejectAlways
             )
-        })
-        .or_else(|| args.options.first())
-        .map(|opt| opt.option_id.clone());
+        }),
+        PermissionFallbackAction::Cancel | PermissionFallbackAction::KeepWaiting => None,
+    };
 
-    match option_id {
-        Some(id) => RequestPermissionOutcome::Selected(SelectedPermissionOutcome::new(id)),
+    match option {
+        Some(opt) => RequestPermissionOutcome::Selected(SelectedPermissionOutcome::new(
+            opt.option_id.clone(),
+        )),
         None => RequestPermissionOutcome::Cancelled,
     }
 }
 
+fn to_acp_version(version: u16) -> ProtocolVersion {
+    serde_json::from_value(Value::from(version)).unwrap_or(ProtocolVersion::V1)
+}
+
+fn from_acp_version(version: &ProtocolVersion) -> Option<u16> {
+    serde_json::to_value(version)
+        .ok()?
+        .as_u64()
+        .and_then(|v| u16::try_from(v).ok())
+}
+
+/// Run `initialize`, offering older versions to agents that reject newer ones
+async fn negotiate_protocol(conn: &ClientSideConnection) -> Result<NegotiatedProtocol, String> {
+    let mut offered = acp_version::SUPPORTED_VERSIONS[0];
+    loop {
+        tracing::debug!(version = offered, "sending ACP initialize request");
+        let init = InitializeRequest::new(to_acp_version(offered))
+            .client_capabilities(client_capabilities(offered))
+            .client_info(Implementation::new("todoki-relay", env!("CARGO_PKG_VERSION")));
+
+        match conn.initialize(init).await {
+            Ok(response) => {
+                let answered = from_acp_version(&response.protocol_version)
+                    .ok_or_else(|| "agent answered an invalid protocol version".to_string())?;
+                return match acp_version::negotiate(offered, answered) {
+                    Negotiation::Accept(version) => Ok(NegotiatedProtocol {
+                        version,
+                        load_session: response.agent_capabilities.load_session,
+                    }),
+                    Negotiation::Unsupported(version) => Err(format!(
+                        "agent requires unsupported ACP version {}",
+                        version
+                    )),
+                };
+            }
+            Err(e) => match acp_version::next_offer(offered) {
+                Some(next) => {
+                    tracing::warn!(
+                        version = offered,
+                        error = %e,
+                        "ACP initialize rejected, retrying with an older version"
+                    );
+                    offered = next;
+                }
+                None => return Err(format!("acp init failed: {}", e)),
+            },
+        }
+    }
+}
+
 /// Spawn an ACP session
 pub async fn spawn_acp_session(
     output_tx: mpsc::Sender<RelayOutput>,
@@ -549,7 +730,11 @@ pub async fn spawn_acp_session(
     stdin: ChildStdin,
     server_url: String,
     token: String,
+    output: OutputControl,
     task_id: Option<String>,
+    task: Option<AgentTaskContext>,
+    permission_fallback: PermissionFallback,
+    preview: PreviewSettings,
 ) -> anyhow::Result<AcpHandle> {
     tracing::debug!(
         session_id = %session_id,
@@ -560,11 +745,13 @@ pub async fn spawn_acp_session(
     );
 
     let (cmd_tx, mut cmd_rx) = mpsc::channel::<AcpCommand>(64);
-    let (ready_tx, ready_rx) = oneshot::channel::<Result<String, String>>();
+    let (ready_tx, ready_rx) =
+        oneshot::channel::<Result<(String, NegotiatedProtocol), String>>();
 
     let agent_uuid = uuid::Uuid::parse_str(&agent_id).unwrap_or_default();
     let task_uuid = task_id.as_ref().and_then(|id| uuid::Uuid::parse_str(id).ok());
-    let mut event_bus = EventBusClient::new(&server_url, &token, agent_uuid);
+    let mut event_bus =
+        EventBusClient::new(&server_url, &token, agent_uuid).with_offline_buffer(output.offline);
     event_bus.set_task_id(task_uuid);
 
     let sink = AcpEventSink::new(
@@ -572,11 +759,17 @@ pub async fn spawn_acp_session(
         agent_id.clone(),
         session_id.clone(),
         event_bus.clone(),
+        output.settings,
+        output.flow,
     );
+    let tools = TaskTools::new(session_id.clone(), task, event_bus.clone());
     let permissions = Arc::new(PermissionManager::new(
         output_tx.clone(),
         session_id.clone(),
         event_bus,
+        permission_fallback,
+        preview,
+        workdir.clone(),
     ));
     let permissions_for_cmd = permissions.clone();
 
@@ -604,7 +797,7 @@ pub async fn spawn_acp_session(
         let local = tokio::task::LocalSet::new();
         runtime.block_on(local.run_until(async move {
             tracing::debug!("inside LocalSet, creating ACP client");
-            let client = AcpClient::new(sink.clone(), permissions);
+            let client = AcpClient::new(sink.clone(), permissions, tools);
             let outgoing = stdin.compat_write();
             let incoming = stdout.compat();
 
@@ -624,18 +817,21 @@ pub async fn spawn_acp_session(
                 tracing::debug!("io_task ended");
             });
 
-            // Initialize protocol
-            tracing::debug!("sending ACP initialize request");
-            let init = InitializeRequest::new(ProtocolVersion::V1)
-                .client_capabilities(ClientCapabilities::default())
-                .client_info(Implementation::new("todoki-relay", env!("CARGO_PKG_VERSION")));
-
-            if let Err(e) = conn.initialize(init).await {
-                tracing::error!(error = %e, "ACP initialize failed");
-                let _ = ready_tx.send(Err(format!("acp init failed: {}", e)));
-                return;
-            }
-            tracing::debug!("ACP initialize succeeded");
+            // Negotiate protocol version
+            let protocol = match negotiate_protocol(&conn).await {
+                Ok(protocol) => protocol,
+                Err(e) => {
+                    tracing::error!(error = %e, "ACP initialize failed");
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            let _ = sink.protocol.set(protocol);
+            tracing::info!(
+                version = protocol.version,
+                load_session = protocol.load_session,
+                "ACP protocol negotiated"
+            );
 
             // Create new session
             tracing::debug!(workdir = %workdir, "creating new ACP session");
@@ -651,7 +847,24 @@ pub async fn spawn_acp_session(
 
             let acp_session_id = acp_session.session_id.to_string();
             tracing::info!(acp_session_id = %acp_session_id, "ACP session created");
-            let _ = ready_tx.send(Ok(acp_session_id.clone()));
+
+            sink.event_bus
+                .emit_builtin_fire_and_forget(BuiltinEvent::AgentSessionStarted(
+                    AgentSessionStartedData {
+                        agent_id: sink.agent_id.clone(),
+                        session_id: sink.session_id.clone(),
+                        protocol_version: Some(protocol.version),
+                        features: protocol.features(),
+                    },
+                ))
+                .await;
+
+            let _ = ready_tx.send(Ok((acp_session_id.clone(), protocol)));
+
+            // Latency-bound batch flushing; dropped with the LocalSet when the
+            // session ends
+            let timer_sink = sink.clone();
+            tokio::task::spawn_local(async move { timer_sink.run_flush_timer().await });
 
             // Wrap conn in Rc so it can be shared with spawn_local tasks
             let conn = Rc::new(conn);
@@ -734,6 +947,21 @@ pub async fn spawn_acp_session(
                             sink.emit_system(format!("cancel error: {}", e)).await;
                         }
                     }
+                    AcpCommand::Ping { done_tx } => {
+                        // Answered off the command loop so a hung agent does
+                        // not hold up other commands
+                        let conn = conn.clone();
+                        tokio::task::spawn_local(async move {
+                            let Ok(params) = serde_json::value::to_raw_value(&Value::Null) else {
+                                return;
+                            };
+                            let request = ExtRequest::new(PING_METHOD, Arc::from(params));
+                            if let Err(e) = conn.ext_method(request).await {
+                                tracing::trace!(error = %e, "ping answered with an error");
+                            }
+                            let _ = done_tx.send(());
+                        });
+                    }
                     AcpCommand::RespondPermission { request_id, outcome } => {
                         tracing::info!(
                             acp_session_id = %acp_session_id,
@@ -751,14 +979,16 @@ pub async fn spawn_acp_session(
 
     tracing::debug!(session_id = %session_id, "waiting for ACP ready signal");
     match ready_rx.await {
-        Ok(Ok(acp_session_id)) => {
+        Ok(Ok((acp_session_id, protocol))) => {
             tracing::info!(
                 session_id = %session_id,
                 acp_session_id = %acp_session_id,
+                protocol_version = protocol.version,
                 "ACP session ready"
             );
             Ok(AcpHandle {
                 acp_session_id,
+                protocol,
                 tx: cmd_tx,
             })
         }
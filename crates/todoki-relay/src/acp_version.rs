@@ -0,0 +1,116 @@
+//! ACP protocol version negotiation
+//!
+//! The relay offers its newest supported version in `initialize`. The agent
+//! answers with the version it will speak: the offered one when it supports
+//! it, otherwise its own latest. Agents that reject the request outright are
+//! probed again with the next older version. The negotiated version decides
+//! which relay features are enabled for the session.
+
+/// ACP versions the relay can speak, newest first
+pub const SUPPORTED_VERSIONS: &[u16] = &[1, 0];
+
+/// Feature names reported in `agent.session_started`
+pub const FEATURE_SESSION_RESUME: &str = "session_resume";
+pub const FEATURE_TASK_TOOLS: &str = "task_tools";
+pub const FEATURE_TYPED_UPDATES: &str = "typed_updates";
+
+/// The protocol agreed with an agent and what it enables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NegotiatedProtocol {
+    pub version: u16,
+    /// The agent advertised `loadSession`, so sessions can be resumed
+    pub load_session: bool,
+}
+
+impl NegotiatedProtocol {
+    /// `todoki/*` ext methods and capability `_meta` need ACP v1
+    pub fn task_tools(&self) -> bool {
+        self.version >= 1
+    }
+
+    /// v0 agents send session updates the typed mapping layer does not know;
+    /// they are forwarded as opaque system output instead
+    pub fn typed_updates(&self) -> bool {
+        self.version >= 1
+    }
+
+    pub fn features(&self) -> Vec<String> {
+        let mut features = Vec::new();
+        if self.load_session {
+            features.push(FEATURE_SESSION_RESUME.to_string());
+        }
+        if self.task_tools() {
+            features.push(FEATURE_TASK_TOOLS.to_string());
+        }
+        if self.typed_updates() {
+            features.push(FEATURE_TYPED_UPDATES.to_string());
+        }
+        features
+    }
+}
+
+/// What to do after the agent answered an `initialize` offering `offered`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Negotiation {
+    /// Speak this version
+    Accept(u16),
+    /// The agent needs a version the relay does not support
+    Unsupported(u16),
+}
+
+/// Decide on the version after the agent answered `answered` to `offered`
+pub fn negotiate(offered: u16, answered: u16) -> Negotiation {
+    // An agent must not answer with a newer version than it was offered
+    if answered <= offered && SUPPORTED_VERSIONS.contains(&answered) {
+        Negotiation::Accept(answered)
+    } else {
+        Negotiation::Unsupported(answered)
+    }
+}
+
+/// Version to offer after `offered` was rejected, if any is left
+pub fn next_offer(offered: u16) -> Option<u16> {
+    SUPPORTED_VERSIONS.iter().copied().find(|v| *v < offered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_accepts_supported_answer() {
+        assert_eq!(negotiate(1, 1), Negotiation::Accept(1));
+        assert_eq!(negotiate(1, 0), Negotiation::Accept(0));
+    }
+
+    #[test]
+    fn test_negotiate_rejects_unknown_or_newer_answer() {
+        assert_eq!(negotiate(1, 2), Negotiation::Unsupported(2));
+        assert_eq!(negotiate(0, 1), Negotiation::Unsupported(1));
+    }
+
+    #[test]
+    fn test_next_offer_walks_down() {
+        assert_eq!(next_offer(SUPPORTED_VERSIONS[0]), Some(0));
+        assert_eq!(next_offer(0), None);
+    }
+
+    #[test]
+    fn test_features_by_version() {
+        let v1 = NegotiatedProtocol {
+            version: 1,
+            load_session: true,
+        };
+        assert_eq!(
+            v1.features(),
+            vec!["session_resume", "task_tools", "typed_updates"]
+        );
+
+        let v0 = NegotiatedProtocol {
+            version: 0,
+            load_session: false,
+        };
+        assert!(v0.features().is_empty());
+        assert!(!v0.task_tools());
+    }
+}
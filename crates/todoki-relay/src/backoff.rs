@@ -0,0 +1,151 @@
+//! Reconnect backoff for the relay
+//!
+//! Delays grow exponentially from `initial` up to `max`, with "equal jitter"
+//! (half the delay fixed, half random) so a fleet of relays does not hammer
+//! the server in lockstep after an outage. An optional budget makes the relay
+//! give up once it has been disconnected for too long, so a supervisor can
+//! restart or alert on it.
+
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_INITIAL_DELAY: Duration = Duration::from_secs(3);
+pub const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Log a health summary every this many failed attempts
+const HEALTH_LOG_EVERY: u32 = 10;
+
+/// Returned when the relay stayed disconnected longer than its budget
+#[derive(Debug, thiserror::Error)]
+#[error("could not reconnect for {:.1} hours, giving up", .downtime.as_secs_f64() / 3600.0)]
+pub struct ReconnectBudgetExhausted {
+    pub downtime: Duration,
+    pub attempts: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    give_up_after: Option<Duration>,
+    attempts: u32,
+    down_since: Option<Instant>,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration, give_up_after: Option<Duration>) -> Self {
+        Self {
+            initial,
+            max: max.max(initial),
+            give_up_after,
+            attempts: 0,
+            down_since: None,
+        }
+    }
+
+    /// Delay before attempt number `attempt` (0-based), given a random
+    /// `jitter` in [0, 1)
+    pub fn delay_for(&self, attempt: u32, jitter: f64) -> Duration {
+        let exp = self
+            .initial
+            .saturating_mul(2u32.saturating_pow(attempt.min(16)))
+            .min(self.max);
+        let half = exp / 2;
+        half + half.mul_f64(jitter.clamp(0.0, 1.0))
+    }
+
+    /// Record a failed attempt and return how long to wait before the next
+    /// one, or an error once the budget is spent
+    pub fn next_delay(&mut self) -> Result<Duration, ReconnectBudgetExhausted> {
+        let down_since = *self.down_since.get_or_insert_with(Instant::now);
+        let downtime = down_since.elapsed();
+        self.attempts += 1;
+
+        if let Some(budget) = self.give_up_after
+            && downtime >= budget
+        {
+            return Err(ReconnectBudgetExhausted {
+                downtime,
+                attempts: self.attempts,
+            });
+        }
+
+        let delay = self.delay_for(self.attempts - 1, rand::random::<f64>());
+
+        if self.attempts.is_multiple_of(HEALTH_LOG_EVERY) {
+            tracing::warn!(
+                attempts = self.attempts,
+                downtime_secs = downtime.as_secs(),
+                give_up_after_secs = ?self.give_up_after.map(|d| d.as_secs()),
+                "relay still disconnected"
+            );
+        }
+
+        Ok(delay)
+    }
+
+    /// Connection established; start over on the next failure
+    pub fn reset(&mut self) {
+        if let Some(down_since) = self.down_since.take() {
+            tracing::info!(
+                attempts = self.attempts,
+                downtime_secs = down_since.elapsed().as_secs(),
+                "relay reconnected"
+            );
+        }
+        self.attempts = 0;
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(DEFAULT_INITIAL_DELAY, DEFAULT_MAX_DELAY, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_grows_and_caps() {
+        let backoff = Backoff::new(Duration::from_secs(2), Duration::from_secs(30), None);
+        assert_eq!(backoff.delay_for(0, 1.0), Duration::from_secs(2));
+        assert_eq!(backoff.delay_for(2, 1.0), Duration::from_secs(8));
+        assert_eq!(backoff.delay_for(10, 1.0), Duration::from_secs(30));
+        assert_eq!(backoff.delay_for(u32::MAX, 1.0), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_jitter_keeps_half_the_delay() {
+        let backoff = Backoff::new(Duration::from_secs(4), Duration::from_secs(60), None);
+        assert_eq!(backoff.delay_for(0, 0.0), Duration::from_secs(2));
+        assert_eq!(backoff.delay_for(0, 0.5), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_reset_starts_over() {
+        let mut backoff = Backoff::default();
+        backoff.next_delay().unwrap();
+        backoff.next_delay().unwrap();
+        assert_eq!(backoff.attempts(), 2);
+
+        backoff.reset();
+        assert_eq!(backoff.attempts(), 0);
+        assert!(backoff.next_delay().unwrap() <= DEFAULT_INITIAL_DELAY);
+    }
+
+    #[test]
+    fn test_gives_up_after_budget() {
+        let mut backoff = Backoff::new(
+            Duration::from_secs(1),
+            Duration::from_secs(10),
+            Some(Duration::ZERO),
+        );
+        let err = backoff.next_delay().unwrap_err();
+        assert_eq!(err.attempts, 1);
+    }
+}
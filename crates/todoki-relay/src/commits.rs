@@ -0,0 +1,382 @@
+//! Commits made by agents in their session's workdir
+//!
+//! When a session starts the relay notes the workdir's `HEAD`. When the
+//! session ends, every commit reachable from the new `HEAD` but not from the
+//! old one is reported as a `commit` artifact of the session's task, with
+//! its message and changed-file stats. When there are new commits, the
+//! branch they are on is reported too, as a `branch` artifact the server
+//! follows CI status for. Workdirs outside a git repository are left alone.
+
+use std::process::Stdio;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+use todoki_protocol::event_bus::{ArtifactCreatedData, BuiltinEvent};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+
+use crate::config::CommitSettings;
+use crate::event_bus_client::EventBusClient;
+use crate::relay::RelayOutput;
+
+/// Give up on a git command after this long
+const GIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Changed files listed per commit; the totals still count every file
+const MAX_FILES: usize = 100;
+
+/// Separators in the `git log` output, unlikely to appear in messages
+const RECORD_SEPARATOR: char = '\u{1e}';
+const FIELD_SEPARATOR: char = '\u{1f}';
+
+/// Hash, author, commit date and message of each commit, followed by its
+/// `--numstat` lines
+const LOG_FORMAT: &str = "--format=%x1e%H%x1f%an%x1f%cI%x1f%B%x1f";
+
+/// A commit found in the workdir, as stored in the artifact's data
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Commit {
+    pub hash: String,
+    pub short_hash: String,
+    pub message: String,
+    pub author: String,
+    pub committed_at: String,
+    pub files_changed: usize,
+    pub insertions: u64,
+    pub deletions: u64,
+    pub files: Vec<ChangedFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChangedFile {
+    pub path: String,
+    /// `None` for binary files
+    pub insertions: Option<u64>,
+    pub deletions: Option<u64>,
+}
+
+/// The branch new commits were made on, as stored in the artifact's data
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Branch {
+    pub name: String,
+    /// Latest commit on the branch
+    pub head: String,
+    /// URL of the `origin` remote, if there is one
+    pub remote_url: Option<String>,
+    /// Owner and name of the repository, for `origin` remotes on GitHub
+    pub owner: Option<String>,
+    pub repo: Option<String>,
+}
+
+/// Watches one session's workdir for new commits
+pub struct CommitWatcher {
+    settings: CommitSettings,
+    workdir: String,
+    /// `HEAD` when the session started; `None` in a repository without
+    /// commits yet
+    start: Option<String>,
+    agent_id: String,
+    session_id: String,
+    event_bus: EventBusClient,
+}
+
+impl CommitWatcher {
+    /// Note the workdir's `HEAD`; `None` when detection is off or the
+    /// workdir is not in a git repository
+    pub async fn start(
+        settings: &CommitSettings,
+        workdir: &str,
+        agent_id: String,
+        session_id: String,
+        event_bus: EventBusClient,
+    ) -> Option<Self> {
+        if !settings.enabled {
+            return None;
+        }
+        let inside = git(workdir, &["rev-parse", "--is-inside-work-tree"]).await?;
+        if inside.trim() != "true" {
+            return None;
+        }
+        let start = git(workdir, &["rev-parse", "--verify", "-q", "HEAD"])
+            .await
+            .map(|head| head.trim().to_string())
+            .filter(|head| !head.is_empty());
+
+        tracing::debug!(session_id = %session_id, start = ?start, "watching workdir for commits");
+        Some(Self {
+            settings: settings.clone(),
+            workdir: workdir.to_string(),
+            start,
+            agent_id,
+            session_id,
+            event_bus,
+        })
+    }
+
+    /// Commits made since the session started, oldest first
+    pub async fn new_commits(&self) -> Vec<Commit> {
+        let range = match &self.start {
+            Some(start) => format!("{}..HEAD", start),
+            None => "HEAD".to_string(),
+        };
+        let limit = format!("-n{}", self.settings.max_commits.max(1));
+        let args = [
+            "log",
+            "--reverse",
+            "--no-color",
+            "--numstat",
+            &limit,
+            LOG_FORMAT,
+            &range,
+        ];
+        // No commits at all yet, or HEAD was reset to before the start
+        let Some(log) = git(&self.workdir, &args).await else {
+            return Vec::new();
+        };
+        parse_log(&log)
+    }
+
+    /// The checked-out branch; `None` on a detached `HEAD`
+    pub async fn branch(&self) -> Option<Branch> {
+        let name = git(&self.workdir, &["rev-parse", "--abbrev-ref", "HEAD"]).await?;
+        let name = name.trim();
+        if name.is_empty() || name == "HEAD" {
+            return None;
+        }
+        let head = git(&self.workdir, &["rev-parse", "HEAD"]).await?;
+        let remote_url = git(&self.workdir, &["remote", "get-url", "origin"])
+            .await
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty());
+        let (owner, repo) = remote_url.as_deref().and_then(github_repo).unzip();
+
+        Some(Branch {
+            name: name.to_string(),
+            head: head.trim().to_string(),
+            remote_url,
+            owner,
+            repo,
+        })
+    }
+
+    /// Report the new commits as `commit` artifacts of the session's task,
+    /// and the branch they are on as a `branch` artifact
+    pub async fn report(&self, output_tx: &mpsc::Sender<RelayOutput>) {
+        let commits = self.new_commits().await;
+        for commit in &commits {
+            tracing::info!(
+                session_id = %self.session_id,
+                hash = %commit.short_hash,
+                files_changed = commit.files_changed,
+                "detected commit artifact"
+            );
+            self.emit(
+                output_tx,
+                "commit",
+                serde_json::to_value(commit).unwrap_or_default(),
+            )
+            .await;
+        }
+
+        if commits.is_empty() {
+            return;
+        }
+        if let Some(branch) = self.branch().await {
+            tracing::info!(
+                session_id = %self.session_id,
+                branch = %branch.name,
+                "detected branch artifact"
+            );
+            self.emit(
+                output_tx,
+                "branch",
+                serde_json::to_value(&branch).unwrap_or_default(),
+            )
+            .await;
+        }
+    }
+
+    async fn emit(&self, output_tx: &mpsc::Sender<RelayOutput>, artifact_type: &str, data: Value) {
+        let artifact_data = ArtifactCreatedData {
+            session_id: self.session_id.clone(),
+            artifact_type: artifact_type.to_string(),
+            data,
+        };
+
+        // The server attaches it to the task the agent is working on
+        let mut data = serde_json::to_value(&artifact_data).unwrap_or_default();
+        if let Some(obj) = data.as_object_mut() {
+            obj.insert("agent_id".to_string(), self.agent_id.clone().into());
+        }
+        let msg = RelayOutput::EmitEvent {
+            kind: "relay.artifact".to_string(),
+            data,
+        };
+        let _ = output_tx.send(msg).await;
+
+        let event = BuiltinEvent::ArtifactCreated(artifact_data);
+        self.event_bus.emit_builtin_fire_and_forget(event).await;
+    }
+}
+
+/// Run git in `workdir`; its stdout if it succeeded
+async fn git(workdir: &str, args: &[&str]) -> Option<String> {
+    let mut cmd = Command::new("git");
+    cmd.args(args)
+        .current_dir(workdir)
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    match tokio::time::timeout(GIT_TIMEOUT, cmd.output()).await {
+        Ok(Ok(out)) if out.status.success() => {
+            Some(String::from_utf8_lossy(&out.stdout).into_owned())
+        }
+        Ok(Ok(_)) => None,
+        Ok(Err(e)) => {
+            tracing::warn!(workdir = %workdir, error = %e, "failed to run git");
+            None
+        }
+        Err(_) => {
+            tracing::warn!(workdir = %workdir, args = ?args, "git timed out");
+            None
+        }
+    }
+}
+
+/// Commits in `git log` output written with [`LOG_FORMAT`] and `--numstat`
+fn parse_log(log: &str) -> Vec<Commit> {
+    log.split(RECORD_SEPARATOR)
+        .filter_map(|record| {
+            let mut fields = record.splitn(5, FIELD_SEPARATOR);
+            let hash = fields.next()?.trim().to_string();
+            let author = fields.next()?.to_string();
+            let committed_at = fields.next()?.to_string();
+            let message = fields.next()?.trim().to_string();
+            let numstat = fields.next().unwrap_or_default();
+            if hash.is_empty() {
+                return None;
+            }
+
+            let mut files = Vec::new();
+            let (mut files_changed, mut insertions, mut deletions) = (0, 0, 0);
+            for line in numstat.lines() {
+                let mut parts = line.splitn(3, '\t');
+                let (Some(added), Some(removed), Some(path)) =
+                    (parts.next(), parts.next(), parts.next())
+                else {
+                    continue;
+                };
+                // Binary files show `-` for both counts
+                let added = added.parse::<u64>().ok();
+                let removed = removed.parse::<u64>().ok();
+                files_changed += 1;
+                insertions += added.unwrap_or(0);
+                deletions += removed.unwrap_or(0);
+                if files.len() < MAX_FILES {
+                    files.push(ChangedFile {
+                        path: path.to_string(),
+                        insertions: added,
+                        deletions: removed,
+                    });
+                }
+            }
+
+            Some(Commit {
+                short_hash: hash.chars().take(7).collect(),
+                hash,
+                message,
+                author,
+                committed_at,
+                files_changed,
+                insertions,
+                deletions,
+                files,
+            })
+        })
+        .collect()
+}
+
+/// Owner and repository name of a GitHub remote URL (HTTPS or SSH)
+fn github_repo(url: &str) -> Option<(String, String)> {
+    let path = url
+        .strip_prefix("git@github.com:")
+        .or_else(|| url.strip_prefix("ssh://git@github.com/"))
+        .or_else(|| url.strip_prefix("https://github.com/"))
+        .or_else(|| url.strip_prefix("http://github.com/"))?;
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let (owner, repo) = path.split_once('/')?;
+    if owner.is_empty() || repo.is_empty() || repo.contains('/') {
+        return None;
+    }
+    Some((owner.to_string(), repo.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(hash: &str, message: &str, numstat: &str) -> String {
+        let header = format!("{hash}\u{1f}Agent\u{1f}2025-01-01T00:00:00+00:00");
+        format!("\u{1e}{header}\u{1f}{message}\n\u{1f}\n{numstat}")
+    }
+
+    #[test]
+    fn test_parse_log() {
+        let log = [
+            record(
+                "a1b2c3d4e5f6",
+                "Fix login\n\nHandle expired tokens.",
+                "3\t1\tsrc/auth.rs\n",
+            ),
+            record(
+                "0f9e8d7c6b5a",
+                "Add logo",
+                "-\t-\tassets/logo.png\n10\t0\tREADME.md\n",
+            ),
+        ]
+        .concat();
+
+        let commits = parse_log(&log);
+        assert_eq!(commits.len(), 2);
+
+        let first = &commits[0];
+        assert_eq!(first.hash, "a1b2c3d4e5f6");
+        assert_eq!(first.short_hash, "a1b2c3d");
+        assert_eq!(first.message, "Fix login\n\nHandle expired tokens.");
+        assert_eq!(first.author, "Agent");
+        assert_eq!(
+            (first.files_changed, first.insertions, first.deletions),
+            (1, 3, 1)
+        );
+
+        let second = &commits[1];
+        assert_eq!(
+            (second.files_changed, second.insertions, second.deletions),
+            (2, 10, 0)
+        );
+        assert_eq!(second.files[0].path, "assets/logo.png");
+        assert_eq!(second.files[0].insertions, None);
+    }
+
+    #[test]
+    fn test_parse_log_without_changes() {
+        assert!(parse_log("").is_empty());
+        let commits = parse_log(&record("abc", "Empty commit", ""));
+        assert_eq!(commits[0].files_changed, 0);
+        assert!(commits[0].files.is_empty());
+    }
+
+    #[test]
+    fn test_github_repo() {
+        let expected = Some(("Kilerd".to_string(), "todoki".to_string()));
+        assert_eq!(github_repo("git@github.com:Kilerd/todoki.git"), expected);
+        assert_eq!(github_repo("https://github.com/Kilerd/todoki"), expected);
+        assert_eq!(
+            github_repo("ssh://git@github.com/Kilerd/todoki.git"),
+            expected
+        );
+        assert_eq!(github_repo("https://gitlab.com/Kilerd/todoki.git"), None);
+        assert_eq!(github_repo("https://github.com/Kilerd"), None);
+    }
+}
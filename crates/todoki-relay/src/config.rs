@@ -1,22 +1,34 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use clap::Parser;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::backoff::{Backoff, DEFAULT_INITIAL_DELAY, DEFAULT_MAX_DELAY};
+
 // Re-export from shared protocol
 pub use todoki_protocol::AgentRole;
+use todoki_protocol::{PermissionFallback, PermissionFallbackAction};
+
+const DEFAULT_BUFFER_FILE: &str = "~/.todoki-relay/buffer.jsonl";
 
 fn parse_relay_role(s: &str) -> Result<AgentRole, String> {
     Ok(AgentRole::from_str(s))
 }
 
+fn parse_permission_fallback(s: &str) -> Result<PermissionFallbackAction, String> {
+    serde_json::from_value(serde_json::Value::String(s.replace('-', "_"))).map_err(|_| {
+        format!("invalid permission fallback: {s}, expected allow, deny, cancel or keep-waiting")
+    })
+}
+
 /// Todoki relay agent
 #[derive(Debug, Clone, Parser)]
 #[command(name = "todoki-relay", version, about = "Remote agent relay for todoki")]
 pub struct Args {
-    /// WebSocket URL to connect to (e.g., wss://example.com/ws/relays)
+    /// WebSocket URL to connect to (e.g., wss://example.com/ws/relay)
     #[arg(env = "TODOKI_SERVER_URL")]
     pub url: String,
 
@@ -52,6 +64,30 @@ pub struct Args {
     #[arg(short, long, env = "TODOKI_CONFIG", default_value = "~/.todoki-relay/config.toml")]
     pub config: PathBuf,
 
+    /// On-disk buffer for events that could not be delivered while disconnected
+    #[arg(long, env = "TODOKI_RELAY_BUFFER_FILE")]
+    pub buffer_file: Option<PathBuf>,
+
+    /// Upper bound for the reconnect backoff, in seconds
+    #[arg(long, env = "TODOKI_RELAY_RECONNECT_MAX_INTERVAL")]
+    pub reconnect_max_interval: Option<u64>,
+
+    /// Exit with a nonzero status after being disconnected this many hours
+    #[arg(long, env = "TODOKI_RELAY_GIVE_UP_AFTER_HOURS")]
+    pub give_up_after_hours: Option<f64>,
+
+    /// What to do with unanswered permission requests (allow, deny, cancel, keep-waiting)
+    #[arg(long, env = "TODOKI_PERMISSION_FALLBACK", value_parser = parse_permission_fallback)]
+    pub permission_fallback: Option<PermissionFallbackAction>,
+
+    /// Seconds to wait for a permission answer before applying the fallback
+    #[arg(long, env = "TODOKI_PERMISSION_TIMEOUT")]
+    pub permission_timeout_secs: Option<u64>,
+
+    /// Fake agent sessions with canned output instead of running agents
+    #[arg(long, env = "TODOKI_RELAY_SIMULATE")]
+    pub simulate: bool,
+
     /// Run as daemon in background
     #[arg(short = 'D', long)]
     pub daemonize: bool,
@@ -79,6 +115,22 @@ pub struct FileConfig {
     pub server: ServerConfig,
     #[serde(default)]
     pub relay: RelaySettings,
+    #[serde(default)]
+    pub output: OutputSettings,
+    #[serde(default)]
+    pub health: HealthSettings,
+    #[serde(default)]
+    pub usage: UsageSettings,
+    #[serde(default)]
+    pub permissions: PermissionSettings,
+    #[serde(default)]
+    pub preview: PreviewSettings,
+    #[serde(default)]
+    pub preflight: PreflightSettings,
+    #[serde(default)]
+    pub simulate: SimulateSettings,
+    #[serde(default)]
+    pub commits: CommitSettings,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -100,6 +152,244 @@ pub struct RelaySettings {
     pub projects: Vec<Uuid>,
     /// Path to setup script file to run before each session
     pub setup_script_file: Option<PathBuf>,
+    /// Path to the offline event buffer
+    pub buffer_file: Option<PathBuf>,
+    /// Upper bound for the reconnect backoff, in seconds
+    pub reconnect_max_interval: Option<u64>,
+    /// Give up after being disconnected this many hours (unset = never)
+    pub give_up_after_hours: Option<f64>,
+}
+
+/// Output batching and flow control (`[output]` in the config file)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputSettings {
+    /// Flush an output batch once it holds this many messages
+    pub max_batch_messages: usize,
+    /// Flush an output batch once its messages add up to this many bytes
+    pub max_batch_bytes: usize,
+    /// Flush an output batch at the latest this long after its first message
+    pub max_batch_latency_ms: u64,
+    /// Pause reading agent output while this many bytes wait for the server
+    pub max_in_flight_bytes: usize,
+    /// Store consecutive chunks of one message as a single message
+    pub coalesce_chunks: bool,
+    /// Flush a batch holding only a message still being streamed at the
+    /// latest this long after its first chunk
+    pub max_coalesce_latency_ms: u64,
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        Self {
+            max_batch_messages: 200,
+            max_batch_bytes: 64 * 1024,
+            max_batch_latency_ms: 1000,
+            max_in_flight_bytes: 8 * 1024 * 1024,
+            coalesce_chunks: true,
+            max_coalesce_latency_ms: 10_000,
+        }
+    }
+}
+
+impl OutputSettings {
+    pub fn max_batch_latency(&self) -> Duration {
+        Duration::from_millis(self.max_batch_latency_ms.max(1))
+    }
+
+    pub fn max_coalesce_latency(&self) -> Duration {
+        Duration::from_millis(self.max_coalesce_latency_ms).max(self.max_batch_latency())
+    }
+}
+
+/// Liveness probes of ACP sessions (`[health]` in the config file)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthSettings {
+    /// Probe running ACP sessions at all
+    pub enabled: bool,
+    /// Time between two probes of a session
+    pub probe_interval_secs: u64,
+    /// A probe not answered within this long fails
+    pub probe_timeout_secs: u64,
+    /// Report the agent unhealthy after this many failed probes in a row
+    pub unhealthy_after: u32,
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            probe_interval_secs: 30,
+            probe_timeout_secs: 10,
+            unhealthy_after: 2,
+        }
+    }
+}
+
+impl HealthSettings {
+    pub fn probe_interval(&self) -> Duration {
+        Duration::from_secs(self.probe_interval_secs.max(1))
+    }
+
+    pub fn probe_timeout(&self) -> Duration {
+        Duration::from_secs(self.probe_timeout_secs.max(1))
+    }
+}
+
+/// Resource usage sampling of sessions (`[usage]` in the config file)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageSettings {
+    /// Sample CPU and memory of running sessions at all
+    pub enabled: bool,
+    /// Time between two samples, each reported in `relay.session_status`
+    pub sample_interval_secs: u64,
+}
+
+impl Default for UsageSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sample_interval_secs: 60,
+        }
+    }
+}
+
+impl UsageSettings {
+    pub fn sample_interval(&self) -> Duration {
+        Duration::from_secs(self.sample_interval_secs.max(1))
+    }
+}
+
+/// Previews of shell commands awaiting permission (`[preview]` in the
+/// config file)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreviewSettings {
+    /// Attach previews to permission requests at all
+    pub enabled: bool,
+    /// Give up on a preview after this long
+    pub timeout_secs: u64,
+    /// Cut preview output after this many bytes
+    pub max_output_bytes: usize,
+    /// Image for running commands without a dry-run option in a throwaway
+    /// container on a copy of the workdir (unset = dry-run previews only)
+    pub container_image: Option<String>,
+    /// Container CLI used for sandboxed previews
+    pub container_runtime: String,
+}
+
+impl Default for PreviewSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_secs: 30,
+            max_output_bytes: 16 * 1024,
+            container_image: None,
+            container_runtime: "docker".to_string(),
+        }
+    }
+}
+
+impl PreviewSettings {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs.max(1))
+    }
+}
+
+/// Checks before spawning a session (`[preflight]` in the config file)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreflightSettings {
+    /// Refuse to spawn when the workdir's filesystem has less free space
+    /// (0 = no check)
+    pub min_free_disk_mb: u64,
+}
+
+impl Default for PreflightSettings {
+    fn default() -> Self {
+        Self {
+            min_free_disk_mb: 512,
+        }
+    }
+}
+
+/// Simulated agent sessions (`[simulate]` in the config file)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimulateSettings {
+    /// Answer spawn requests with simulated sessions; agents are never run
+    pub enabled: bool,
+    /// Pause between two chunks of output
+    pub chunk_interval_ms: u64,
+    /// Vary each pause by up to this much either way
+    pub jitter_ms: u64,
+    /// Reply chunks streamed per prompt
+    pub message_chunks: usize,
+    /// Tool calls made per prompt
+    pub tool_calls: usize,
+    /// Share of prompts that fail halfway, between 0 and 1
+    pub failure_rate: f64,
+    /// Seed for output and timing, for repeatable runs (unset = random)
+    pub seed: Option<u64>,
+}
+
+impl Default for SimulateSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            chunk_interval_ms: 150,
+            jitter_ms: 100,
+            message_chunks: 12,
+            tool_calls: 3,
+            failure_rate: 0.0,
+            seed: None,
+        }
+    }
+}
+
+/// Commits made in a session's workdir (`[commits]` in the config file)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommitSettings {
+    /// Report new commits as artifacts of the session's task
+    pub enabled: bool,
+    /// Report at most this many commits per session
+    pub max_commits: usize,
+}
+
+impl Default for CommitSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_commits: 50,
+        }
+    }
+}
+
+/// Fallback for unanswered permission requests (`[permissions]` in the
+/// config file). A policy pushed by the server replaces all of it.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct PermissionSettings {
+    /// Applied to every agent without an override
+    #[serde(flatten)]
+    pub fallback: PermissionFallback,
+    /// Overrides keyed by the file name of the agent command
+    /// (`[permissions.agents.claude-code-acp]`)
+    pub agents: HashMap<String, PermissionFallback>,
+}
+
+impl PermissionSettings {
+    /// Fallback for sessions running `command`
+    pub fn for_command(&self, command: &str) -> PermissionFallback {
+        let name = Path::new(command)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(command);
+        self.agents.get(name).copied().unwrap_or(self.fallback)
+    }
 }
 
 /// Merged configuration from CLI, env, and file
@@ -113,6 +403,17 @@ pub struct RelayConfig {
     pub labels: HashMap<String, String>,
     pub projects: Vec<Uuid>,
     pub setup_script: Option<String>,
+    pub buffer_file: PathBuf,
+    pub reconnect_max_interval: Duration,
+    pub give_up_after: Option<Duration>,
+    pub output: OutputSettings,
+    pub health: HealthSettings,
+    pub usage: UsageSettings,
+    pub permissions: PermissionSettings,
+    pub preview: PreviewSettings,
+    pub preflight: PreflightSettings,
+    pub simulate: SimulateSettings,
+    pub commits: CommitSettings,
 }
 
 impl RelayConfig {
@@ -164,6 +465,13 @@ impl RelayConfig {
             labels.insert(k, v);
         }
 
+        let mut simulate = file_config.simulate;
+        simulate.enabled |= args.simulate;
+        if simulate.enabled {
+            // Lets the server and its users tell simulated relays apart
+            labels.entry("simulated".to_string()).or_insert_with(|| "true".to_string());
+        }
+
         // CLI setup_script_file takes precedence over file config
         let setup_script_file = args
             .setup_script_file
@@ -181,6 +489,36 @@ impl RelayConfig {
             None
         };
 
+        let buffer_file = args
+            .buffer_file
+            .or(file_config.relay.buffer_file)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_BUFFER_FILE));
+        let buffer_file = expand_tilde(&buffer_file);
+
+        let reconnect_max_interval = args
+            .reconnect_max_interval
+            .or(file_config.relay.reconnect_max_interval)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_MAX_DELAY);
+        let give_up_after = match args
+            .give_up_after_hours
+            .or(file_config.relay.give_up_after_hours)
+        {
+            Some(hours) if hours.is_finite() && hours > 0.0 => {
+                Some(Duration::from_secs_f64(hours * 3600.0))
+            }
+            Some(hours) => anyhow::bail!("invalid give_up_after_hours: {hours}"),
+            None => None,
+        };
+
+        let mut permissions = file_config.permissions;
+        if let Some(action) = args.permission_fallback {
+            permissions.fallback.action = action;
+        }
+        if let Some(timeout_secs) = args.permission_timeout_secs {
+            permissions.fallback.timeout_secs = timeout_secs;
+        }
+
         Ok(Self {
             url: args.url,
             token: args.token,
@@ -190,6 +528,17 @@ impl RelayConfig {
             labels,
             projects,
             setup_script,
+            buffer_file,
+            reconnect_max_interval,
+            give_up_after,
+            output: file_config.output,
+            health: file_config.health,
+            usage: file_config.usage,
+            permissions,
+            preview: file_config.preview,
+            preflight: file_config.preflight,
+            simulate,
+            commits: file_config.commits,
         })
     }
 
@@ -231,6 +580,60 @@ impl RelayConfig {
     pub fn setup_script(&self) -> Option<&str> {
         self.setup_script.as_deref()
     }
+
+    /// Get offline event buffer path
+    pub fn buffer_file(&self) -> &Path {
+        &self.buffer_file
+    }
+
+    /// Get output batching and flow control settings
+    pub fn output(&self) -> &OutputSettings {
+        &self.output
+    }
+
+    /// Get session liveness probe settings
+    pub fn health(&self) -> &HealthSettings {
+        &self.health
+    }
+
+    /// Get session resource usage sampling settings
+    pub fn usage(&self) -> &UsageSettings {
+        &self.usage
+    }
+
+    /// Get the fallback for unanswered permission requests
+    pub fn permissions(&self) -> &PermissionSettings {
+        &self.permissions
+    }
+
+    /// Get the shell command preview settings
+    pub fn preview(&self) -> &PreviewSettings {
+        &self.preview
+    }
+
+    /// Get the checks run before spawning
+    pub fn preflight(&self) -> &PreflightSettings {
+        &self.preflight
+    }
+
+    /// Get the simulated session settings
+    pub fn simulate(&self) -> &SimulateSettings {
+        &self.simulate
+    }
+
+    /// Get the commit detection settings
+    pub fn commits(&self) -> &CommitSettings {
+        &self.commits
+    }
+
+    /// Build the reconnect backoff from the configured limits
+    pub fn backoff(&self) -> Backoff {
+        Backoff::new(
+            DEFAULT_INITIAL_DELAY,
+            self.reconnect_max_interval,
+            self.give_up_after,
+        )
+    }
 }
 
 fn expand_tilde(path: &PathBuf) -> PathBuf {
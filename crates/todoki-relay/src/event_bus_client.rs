@@ -1,8 +1,23 @@
+use std::sync::Arc;
+
 use serde::Serialize;
 use serde_json::Value;
 use todoki_protocol::event_bus::{BuiltinEvent, Event, EventMessage};
 use uuid::Uuid;
 
+use crate::offline_buffer::{self, BufferedRoute, OfflineBuffer};
+
+/// HTTP base URL of the server behind a relay WebSocket URL
+pub fn http_base_url(server_url: &str) -> String {
+    // Remove the WebSocket path and convert the protocol
+    server_url
+        .trim_end_matches("/ws/relay")
+        .trim_end_matches("/ws/relays")
+        .trim_end_matches("/ws/event-bus")
+        .replace("wss://", "https://")
+        .replace("ws://", "http://")
+}
+
 /// Client for emitting events to event-bus via HTTP API
 #[derive(Clone)]
 pub struct EventBusClient {
@@ -11,27 +26,28 @@ pub struct EventBusClient {
     token: String,
     agent_id: Uuid,
     task_id: Option<Uuid>,
+    /// Durable events that fail to send are kept here for replay
+    offline: Option<Arc<OfflineBuffer>>,
 }
 
 impl EventBusClient {
     pub fn new(server_url: &str, token: &str, agent_id: Uuid) -> Self {
-        // Extract base URL: remove WebSocket path and convert protocol
-        let base_url = server_url
-            .trim_end_matches("/ws/relay")
-            .trim_end_matches("/ws/relays")
-            .trim_end_matches("/ws/event-bus")
-            .replace("wss://", "https://")
-            .replace("ws://", "http://");
-
         Self {
             http_client: reqwest::Client::new(),
-            base_url,
+            base_url: http_base_url(server_url),
             token: token.to_string(),
             agent_id,
             task_id: None,
+            offline: None,
         }
     }
 
+    /// Buffer durable events on disk when the server cannot be reached
+    pub fn with_offline_buffer(mut self, buffer: Option<Arc<OfflineBuffer>>) -> Self {
+        self.offline = buffer;
+        self
+    }
+
     /// Set the task_id to include in all emitted events
     pub fn set_task_id(&mut self, task_id: Option<Uuid>) {
         self.task_id = task_id;
@@ -92,7 +108,48 @@ impl EventBusClient {
             agent_id: self.agent_id.to_string(),
             task_id: self.task_id,
         };
-        self.emit_message(&message).await
+        let result = self.emit_message(&message).await;
+        if let Err(e) = &result {
+            self.buffer_undelivered(&message, e);
+        }
+        result
+    }
+
+    /// Re-send a message stored in the offline buffer
+    pub async fn emit_buffered(&self, message: &Value) -> Result<i64, EventBusError> {
+        self.emit_message(message).await
+    }
+
+    /// Keep a durable event for replay if it failed for a reason that may go
+    /// away (network, server error); rejected events are not retried
+    fn buffer_undelivered(&self, message: &EventMessage, error: &EventBusError) {
+        let Some(buffer) = &self.offline else {
+            return;
+        };
+        let retryable = match error {
+            EventBusError::Network(_) => true,
+            EventBusError::Server(status, _) => *status >= 500,
+            EventBusError::Parse(_) => false,
+        };
+        if !retryable {
+            return;
+        }
+
+        let Ok(message) = serde_json::to_value(message) else {
+            return;
+        };
+        let kind = message
+            .get("kind")
+            .and_then(|k| k.as_str())
+            .unwrap_or_default()
+            .to_string();
+        if !offline_buffer::is_durable(&kind) {
+            return;
+        }
+        match buffer.append(BufferedRoute::Http { message }) {
+            Ok(seq) => tracing::info!(kind = %kind, seq, "buffered undelivered event"),
+            Err(e) => tracing::error!(kind = %kind, error = %e, "failed to buffer event"),
+        }
     }
 
     /// Emit a typed builtin event, logging errors but not propagating them
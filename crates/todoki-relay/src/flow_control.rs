@@ -0,0 +1,177 @@
+//! Flow control between agent output and the server connection
+//!
+//! Streaming output (`relay.agent_output`) is charged against an in-flight
+//! byte budget when a session queues it and released once the forwarder has
+//! handed it to the WebSocket (or the offline buffer). When the connection is
+//! slow or down the budget runs out, sessions stop reading from the agent and
+//! the agent blocks on its own stdout instead of the relay growing without
+//! bound.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use serde_json::Value;
+use tokio::sync::Notify;
+
+use crate::config::OutputSettings;
+use crate::offline_buffer::OfflineBuffer;
+
+const AGENT_OUTPUT_KIND: &str = "relay.agent_output";
+
+pub struct FlowControl {
+    in_flight: AtomicUsize,
+    max_in_flight: usize,
+    paused: AtomicBool,
+    released: Notify,
+}
+
+impl FlowControl {
+    pub fn new(max_in_flight_bytes: usize) -> Self {
+        Self {
+            in_flight: AtomicUsize::new(0),
+            max_in_flight: max_in_flight_bytes,
+            paused: AtomicBool::new(false),
+            released: Notify::new(),
+        }
+    }
+
+    /// Bytes an event costs against the budget; only streaming output is
+    /// charged, everything else is small and must never be held back
+    pub fn weight(kind: &str, data: &Value) -> usize {
+        if kind != AGENT_OUTPUT_KIND {
+            return 0;
+        }
+        data.get("message")
+            .and_then(|m| m.as_str())
+            .map_or(0, str::len)
+    }
+
+    /// Wait until `bytes` fit in the budget, then charge them. A single
+    /// message larger than the budget still goes through once nothing else
+    /// is in flight.
+    pub async fn acquire(&self, bytes: usize) {
+        if bytes == 0 {
+            return;
+        }
+        loop {
+            let released = self.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+
+            let current = self.in_flight.load(Ordering::Acquire);
+            if current == 0 || current + bytes <= self.max_in_flight {
+                if self
+                    .in_flight
+                    .compare_exchange(
+                        current,
+                        current + bytes,
+                        Ordering::AcqRel,
+                        Ordering::Acquire,
+                    )
+                    .is_ok()
+                {
+                    if self.paused.swap(false, Ordering::AcqRel) {
+                        tracing::info!(in_flight = current, "output flow resumed");
+                    }
+                    return;
+                }
+                continue;
+            }
+
+            if !self.paused.swap(true, Ordering::AcqRel) {
+                tracing::warn!(
+                    in_flight = current,
+                    max_in_flight = self.max_in_flight,
+                    "server connection congested, pausing agent output"
+                );
+            }
+            released.await;
+        }
+    }
+
+    /// Blocking variant for output readers running on plain threads
+    pub fn acquire_blocking(&self, runtime: &tokio::runtime::Handle, bytes: usize) {
+        runtime.block_on(self.acquire(bytes));
+    }
+
+    /// Give back bytes once the forwarder is done with an event
+    pub fn release(&self, bytes: usize) {
+        if bytes == 0 {
+            return;
+        }
+        let _ = self
+            .in_flight
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                Some(current.saturating_sub(bytes))
+            });
+        self.released.notify_waiters();
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Acquire)
+    }
+}
+
+/// Output pipeline shared by every session of a relay
+#[derive(Clone)]
+pub struct OutputControl {
+    pub settings: OutputSettings,
+    pub flow: Arc<FlowControl>,
+    /// Durable events that fail to send are kept here for replay
+    pub offline: Option<Arc<OfflineBuffer>>,
+}
+
+impl Default for OutputControl {
+    fn default() -> Self {
+        let settings = OutputSettings::default();
+        let flow = Arc::new(FlowControl::new(settings.max_in_flight_bytes));
+        Self {
+            settings,
+            flow,
+            offline: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_weight_only_counts_streaming_output() {
+        let data = serde_json::json!({ "message": "hello" });
+        assert_eq!(FlowControl::weight("relay.agent_output", &data), 5);
+        assert_eq!(FlowControl::weight("relay.prompt_completed", &data), 0);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_waits_for_release() {
+        let flow = Arc::new(FlowControl::new(10));
+        flow.acquire(8).await;
+
+        let waiter = {
+            let flow = flow.clone();
+            tokio::spawn(async move { flow.acquire(5).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        flow.release(8);
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(flow.in_flight(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_message_passes_when_idle() {
+        let flow = FlowControl::new(4);
+        flow.acquire(100).await;
+        assert_eq!(flow.in_flight(), 100);
+        flow.release(200);
+        assert_eq!(flow.in_flight(), 0);
+    }
+}
@@ -0,0 +1,65 @@
+//! Liveness probes of ACP sessions
+//!
+//! While an ACP session runs, the session manager periodically checks that
+//! the agent process is still alive and answers a `todoki/ping` ext request.
+//! Once `unhealthy_after` probes in a row have failed, every further failure
+//! is reported as `agent.unhealthy`; the first successful probe afterwards
+//! is reported as `agent.healthy`.
+
+/// What a probe result changed about a session's reported health
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthChange {
+    /// Report the session unhealthy with this many failures in a row
+    Unhealthy(u32),
+    /// A session reported unhealthy answered again
+    Recovered,
+}
+
+/// Consecutive probe failures of one session
+#[derive(Debug)]
+pub struct ProbeTracker {
+    unhealthy_after: u32,
+    failures: u32,
+    reported: bool,
+}
+
+impl ProbeTracker {
+    pub fn new(unhealthy_after: u32) -> Self {
+        Self {
+            unhealthy_after: unhealthy_after.max(1),
+            failures: 0,
+            reported: false,
+        }
+    }
+
+    pub fn record_success(&mut self) -> Option<HealthChange> {
+        self.failures = 0;
+        std::mem::take(&mut self.reported).then_some(HealthChange::Recovered)
+    }
+
+    pub fn record_failure(&mut self) -> Option<HealthChange> {
+        self.failures += 1;
+        if self.failures < self.unhealthy_after {
+            return None;
+        }
+        self.reported = true;
+        Some(HealthChange::Unhealthy(self.failures))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_after_threshold_and_recovery() {
+        let mut tracker = ProbeTracker::new(2);
+        assert_eq!(tracker.record_success(), None);
+        assert_eq!(tracker.record_failure(), None);
+        assert_eq!(tracker.record_failure(), Some(HealthChange::Unhealthy(2)));
+        assert_eq!(tracker.record_failure(), Some(HealthChange::Unhealthy(3)));
+        assert_eq!(tracker.record_success(), Some(HealthChange::Recovered));
+        assert_eq!(tracker.record_success(), None);
+        assert_eq!(tracker.record_failure(), None);
+    }
+}
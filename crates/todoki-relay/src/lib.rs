@@ -1,6 +1,22 @@
 pub mod acp;
+pub mod acp_version;
+pub mod backoff;
+pub mod commits;
 pub mod config;
 pub mod event_bus_client;
 pub mod event_poller;
+pub mod flow_control;
+pub mod health;
+pub mod logging;
+pub mod offline_buffer;
+pub mod output_buffer;
+pub mod preflight;
+pub mod process;
+pub mod preview;
+pub mod pty;
 pub mod relay;
+pub mod review_links;
 pub mod session;
+pub mod simulate;
+pub mod task_tools;
+pub mod usage;
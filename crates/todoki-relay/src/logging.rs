@@ -0,0 +1,51 @@
+//! Tracing setup
+//!
+//! The filter starts from `RUST_LOG` (default "info") and sits behind a reload
+//! layer, so `relay.log_level_changed` from the server can replace it until
+//! the relay restarts.
+
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+
+/// Swaps the active log filter
+#[derive(Clone)]
+pub struct LogControl {
+    handle: reload::Handle<EnvFilter, Registry>,
+}
+
+/// Install the global subscriber
+pub fn init() -> LogControl {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let (filter, handle) = reload::Layer::new(filter);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .init();
+    LogControl { handle }
+}
+
+impl LogControl {
+    /// Use `directives` until the relay restarts
+    pub fn set(&self, directives: &str) -> Result<(), String> {
+        let filter = parse(directives)?;
+        self.handle.reload(filter).map_err(|e| e.to_string())
+    }
+}
+
+/// Filter directives in `RUST_LOG` syntax
+pub fn parse(directives: &str) -> Result<EnvFilter, String> {
+    EnvFilter::try_new(directives.trim()).map_err(|e| format!("{:?}: {}", directives, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert!(parse("info").is_ok());
+        assert!(parse("warn,todoki_relay::session=debug").is_ok());
+        assert!(parse("todoki_relay=loud").is_err());
+    }
+}
@@ -1,8 +1,8 @@
 use std::fs::File;
 
-use tracing_subscriber::EnvFilter;
-
+use todoki_relay::backoff::ReconnectBudgetExhausted;
 use todoki_relay::config::{DaemonArgs, RelayConfig};
+use todoki_relay::logging;
 use todoki_relay::relay::Relay;
 
 fn main() -> anyhow::Result<()> {
@@ -31,8 +31,7 @@ fn main() -> anyhow::Result<()> {
 async fn async_main() -> anyhow::Result<()> {
 
     // Initialize logging
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    tracing_subscriber::fmt().with_env_filter(filter).init();
+    let log = logging::init();
 
     // Load config
     let config = RelayConfig::load()?;
@@ -42,23 +41,28 @@ async fn async_main() -> anyhow::Result<()> {
         role = %config.role().as_str(),
         safe_paths = ?config.safe_paths(),
         projects = ?config.projects(),
+        simulate = config.simulate().enabled,
         "todoki-relay starting"
     );
 
     // Run relay with reconnection logic
+    let mut relay = Relay::new(config).with_log_control(log);
     loop {
-        let mut relay = Relay::new(config.clone());
-
         match relay.run().await {
             Ok(()) => {
                 tracing::info!("relay disconnected, reconnecting...");
             }
+            Err(e) if e.is::<ReconnectBudgetExhausted>() => {
+                // Exit nonzero so a supervisor can restart or alert
+                return Err(e);
+            }
             Err(e) => {
                 tracing::error!(error = %e, "relay error, reconnecting...");
             }
         }
 
         // Wait before reconnecting
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+        let delay = relay.next_reconnect_delay()?;
+        tokio::time::sleep(delay).await;
     }
 }
@@ -0,0 +1,260 @@
+//! On-disk buffer for events that could not reach the server
+//!
+//! Durable events (output batches, artifacts, session status) that fail to
+//! send are appended to a JSON-lines log and replayed in order after the
+//! relay reconnects. Every entry gets a buffer-wide `seq`; the highest
+//! delivered seq is kept in a sidecar `.ack` file, so an entry is never
+//! replayed twice, even across relay restarts.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use todoki_protocol::event_bus::EventKind;
+
+/// Event kinds worth keeping across a disconnect; streaming output
+/// (`relay.agent_output`) is covered by its batches
+const DURABLE_KINDS: &[&str] = &[
+    EventKind::AGENT_OUTPUT_BATCH,
+    EventKind::AGENT_SESSION_EXITED,
+    EventKind::ARTIFACT_CREATED,
+    EventKind::RELAY_AGENT_OUTPUT_BATCH,
+    EventKind::RELAY_ARTIFACT,
+    EventKind::RELAY_SESSION_STATUS,
+    EventKind::RELAY_PROMPT_COMPLETED,
+];
+
+pub fn is_durable(kind: &str) -> bool {
+    DURABLE_KINDS.contains(&kind)
+}
+
+/// How a buffered event is delivered on replay
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "via", rename_all = "snake_case")]
+pub enum BufferedRoute {
+    /// `emit_event` over the relay WebSocket
+    WebSocket { kind: String, data: Value },
+    /// Full event message for the HTTP emit API
+    Http { message: Value },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BufferedEvent {
+    pub seq: u64,
+    #[serde(flatten)]
+    pub route: BufferedRoute,
+}
+
+struct BufferState {
+    next_seq: u64,
+    acked: u64,
+}
+
+pub struct OfflineBuffer {
+    path: PathBuf,
+    ack_path: PathBuf,
+    state: Mutex<BufferState>,
+}
+
+impl OfflineBuffer {
+    /// Open (or create) the buffer at `path`
+    pub fn open(path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut ack_path = path.as_os_str().to_owned();
+        ack_path.push(".ack");
+        let ack_path = PathBuf::from(ack_path);
+
+        let acked = match fs::read_to_string(&ack_path) {
+            Ok(content) => content.trim().parse().unwrap_or(0),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e),
+        };
+
+        let buffer = Self {
+            path: path.to_path_buf(),
+            ack_path,
+            state: Mutex::new(BufferState {
+                next_seq: acked + 1,
+                acked,
+            }),
+        };
+
+        let last_seq = buffer
+            .read_entries()?
+            .keys()
+            .next_back()
+            .copied()
+            .unwrap_or(0);
+        {
+            let mut state = buffer.state.lock().unwrap();
+            state.next_seq = state.next_seq.max(last_seq + 1);
+        }
+
+        Ok(buffer)
+    }
+
+    /// Append an event; returns its seq
+    pub fn append(&self, route: BufferedRoute) -> io::Result<u64> {
+        let mut state = self.state.lock().unwrap();
+        let event = BufferedEvent {
+            seq: state.next_seq,
+            route,
+        };
+
+        let mut line = serde_json::to_string(&event).map_err(io::Error::other)?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+
+        state.next_seq += 1;
+        Ok(event.seq)
+    }
+
+    /// Events not yet delivered, in seq order
+    pub fn pending(&self) -> io::Result<Vec<BufferedEvent>> {
+        let acked = self.state.lock().unwrap().acked;
+        Ok(self
+            .read_entries()?
+            .into_values()
+            .filter(|event| event.seq > acked)
+            .collect())
+    }
+
+    /// Mark everything up to `seq` as delivered; the log is truncated once
+    /// nothing is pending
+    pub fn ack(&self, seq: u64) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if seq <= state.acked {
+            return Ok(());
+        }
+        state.acked = seq;
+        fs::write(&self.ack_path, seq.to_string())?;
+
+        if state.acked + 1 >= state.next_seq {
+            File::create(&self.path)?;
+        }
+        Ok(())
+    }
+
+    /// Read the log keyed by seq; a duplicate seq keeps its first entry and
+    /// a torn last line (crash mid-write) is skipped
+    fn read_entries(&self) -> io::Result<BTreeMap<u64, BufferedEvent>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut entries = BTreeMap::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<BufferedEvent>(&line) {
+                Ok(event) => {
+                    entries.entry(event.seq).or_insert(event);
+                }
+                Err(e) => {
+                    tracing::warn!(path = ?self.path, error = %e, "skipping corrupt buffer entry");
+                }
+            }
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ws(kind: &str) -> BufferedRoute {
+        BufferedRoute::WebSocket {
+            kind: kind.to_string(),
+            data: serde_json::json!({ "session_id": "s1" }),
+        }
+    }
+
+    #[test]
+    fn test_append_and_replay_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let buffer = OfflineBuffer::open(&dir.path().join("buffer.jsonl")).unwrap();
+
+        assert_eq!(buffer.append(ws("relay.session_status")).unwrap(), 1);
+        assert_eq!(buffer.append(ws("relay.prompt_completed")).unwrap(), 2);
+
+        let pending = buffer.pending().unwrap();
+        assert_eq!(
+            pending.iter().map(|e| e.seq).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(pending[0].route, ws("relay.session_status"));
+    }
+
+    #[test]
+    fn test_ack_skips_delivered_and_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("buffer.jsonl");
+        let buffer = OfflineBuffer::open(&path).unwrap();
+        buffer.append(ws("a")).unwrap();
+        buffer.append(ws("b")).unwrap();
+        buffer.ack(1).unwrap();
+        drop(buffer);
+
+        let buffer = OfflineBuffer::open(&path).unwrap();
+        let pending = buffer.pending().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].seq, 2);
+        // Numbering continues after the existing entries
+        assert_eq!(buffer.append(ws("c")).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_full_ack_truncates_log_but_keeps_seq() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("buffer.jsonl");
+        let buffer = OfflineBuffer::open(&path).unwrap();
+        buffer.append(ws("a")).unwrap();
+        buffer.ack(1).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        drop(buffer);
+
+        let buffer = OfflineBuffer::open(&path).unwrap();
+        assert!(buffer.pending().unwrap().is_empty());
+        assert_eq!(buffer.append(ws("b")).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_duplicate_and_torn_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("buffer.jsonl");
+        let entry = serde_json::to_string(&BufferedEvent {
+            seq: 1,
+            route: ws("a"),
+        })
+        .unwrap();
+        fs::write(&path, format!("{entry}\n{entry}\n{{\"seq\":2,\"via\"")).unwrap();
+
+        let buffer = OfflineBuffer::open(&path).unwrap();
+        assert_eq!(buffer.pending().unwrap().len(), 1);
+        assert_eq!(buffer.append(ws("b")).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_durable_kinds() {
+        assert!(is_durable("relay.session_status"));
+        assert!(is_durable("agent.output_batch"));
+        assert!(!is_durable("relay.agent_output"));
+    }
+}
@@ -0,0 +1,234 @@
+//! Batching of agent output for storage
+//!
+//! Output of one stream is buffered and stored as one `agent.output_batch`.
+//! Agents stream their replies as many small chunks, so with coalescing on
+//! consecutive text chunks of one message are joined into a single message
+//! before they are stored, and a message repeated verbatim is stored once.
+//! Live display is unaffected: every chunk still goes out as
+//! `relay.agent_output` as soon as it arrives.
+
+use std::time::Instant;
+
+use serde_json::Value;
+use todoki_protocol::OutputStreamKind;
+
+use crate::config::OutputSettings;
+
+/// Message types ACP streams in chunks
+const CHUNKED_TYPES: &[&str] = &["agent_message", "agent_thought", "user_message"];
+
+/// Messages of the current stream waiting to be stored
+pub struct OutputBuffer {
+    settings: OutputSettings,
+    current_stream: Option<OutputStreamKind>,
+    messages: Vec<String>,
+    /// Chunks of the message still being streamed, joined
+    open: Option<OpenText>,
+    bytes: usize,
+    /// When the first message of the current batch arrived
+    started_at: Option<Instant>,
+}
+
+struct OpenText {
+    kind: String,
+    text: String,
+}
+
+impl OpenText {
+    fn into_message(self) -> String {
+        serde_json::json!({ "type": self.kind, "text": self.text, "chunk": true }).to_string()
+    }
+}
+
+impl OutputBuffer {
+    pub fn new(settings: OutputSettings) -> Self {
+        Self {
+            settings,
+            current_stream: None,
+            messages: Vec::new(),
+            open: None,
+            bytes: 0,
+            started_at: None,
+        }
+    }
+
+    pub fn stream(&self) -> Option<&OutputStreamKind> {
+        self.current_stream.as_ref()
+    }
+
+    /// Switch streams; take the batch of the previous stream first
+    pub fn set_stream(&mut self, stream: Option<OutputStreamKind>) {
+        self.current_stream = stream;
+    }
+
+    pub fn push(&mut self, message: String) {
+        self.started_at.get_or_insert_with(Instant::now);
+        self.bytes += message.len();
+        if !self.settings.coalesce_chunks {
+            self.messages.push(message);
+            return;
+        }
+
+        match text_chunk(&message) {
+            Some((kind, text)) => match &mut self.open {
+                Some(open) if open.kind == kind => open.text.push_str(&text),
+                _ => {
+                    self.close_open();
+                    self.open = Some(OpenText { kind, text });
+                }
+            },
+            None => {
+                self.close_open();
+                // Agents resend unchanged tool call updates while they wait
+                if self.messages.last() != Some(&message) {
+                    self.messages.push(message);
+                }
+            }
+        }
+    }
+
+    /// Messages in the batch, a message still being streamed counting once
+    pub fn len(&self) -> usize {
+        self.messages.len() + usize::from(self.open.is_some())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the batch reached its message or size limit
+    pub fn is_full(&self) -> bool {
+        self.len() >= self.settings.max_batch_messages
+            || self.bytes >= self.settings.max_batch_bytes
+    }
+
+    /// Whether the batch waited long enough to be stored; a batch holding
+    /// nothing but a message still being streamed waits for the rest of it,
+    /// up to the coalescing latency
+    pub fn is_stale(&self) -> bool {
+        let Some(started) = self.started_at else {
+            return false;
+        };
+        let max_latency = if self.messages.is_empty() && self.open.is_some() {
+            self.settings.max_coalesce_latency()
+        } else {
+            self.settings.max_batch_latency()
+        };
+        started.elapsed() >= max_latency
+    }
+
+    /// Take the current batch, if any; the stream type is kept
+    pub fn take(&mut self) -> Option<(OutputStreamKind, Vec<String>)> {
+        self.close_open();
+        self.bytes = 0;
+        self.started_at = None;
+        let messages = std::mem::take(&mut self.messages);
+        let stream = self.current_stream.clone()?;
+        (!messages.is_empty()).then_some((stream, messages))
+    }
+
+    fn close_open(&mut self) {
+        if let Some(open) = self.open.take() {
+            self.messages.push(open.into_message());
+        }
+    }
+}
+
+/// Type and text of a streamed text chunk
+fn text_chunk(message: &str) -> Option<(String, String)> {
+    let value: Value = serde_json::from_str(message).ok()?;
+    if value.get("chunk").and_then(Value::as_bool) != Some(true) {
+        return None;
+    }
+    let kind = value.get("type").and_then(Value::as_str)?;
+    let text = value.get("text").and_then(Value::as_str)?;
+    CHUNKED_TYPES
+        .contains(&kind)
+        .then(|| (kind.to_string(), text.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(kind: &str, text: &str) -> String {
+        serde_json::json!({ "type": kind, "text": text, "chunk": true }).to_string()
+    }
+
+    fn buffer(coalesce_chunks: bool) -> OutputBuffer {
+        let mut buffer = OutputBuffer::new(OutputSettings {
+            coalesce_chunks,
+            ..OutputSettings::default()
+        });
+        buffer.set_stream(Some(OutputStreamKind::Assistant));
+        buffer
+    }
+
+    fn texts(messages: &[String]) -> Vec<String> {
+        messages
+            .iter()
+            .map(|m| {
+                let value: Value = serde_json::from_str(m).unwrap();
+                value["text"].as_str().unwrap_or(m).to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_chunks_of_one_message_are_joined() {
+        let mut buffer = buffer(true);
+        for part in ["The fix ", "is in ", "place."] {
+            buffer.push(chunk("agent_message", part));
+        }
+        assert_eq!(buffer.len(), 1);
+
+        let (stream, messages) = buffer.take().unwrap();
+        assert_eq!(stream, OutputStreamKind::Assistant);
+        assert_eq!(messages, vec![chunk("agent_message", "The fix is in place.")]);
+        assert!(buffer.take().is_none());
+    }
+
+    #[test]
+    fn test_other_messages_end_the_joined_message() {
+        let mut buffer = buffer(true);
+        let update = r#"{"type":"tool_call_update","id":"1","status":"in_progress"}"#;
+        buffer.push(chunk("agent_thought", "Let me "));
+        buffer.push(chunk("agent_thought", "check."));
+        buffer.push(update.to_string());
+        buffer.push(update.to_string());
+        buffer.push(chunk("agent_message", "Done"));
+        buffer.push(chunk("agent_message", "."));
+
+        let (_, messages) = buffer.take().unwrap();
+        assert_eq!(
+            texts(&messages),
+            vec!["Let me check.".to_string(), update.to_string(), "Done.".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_coalescing_off_keeps_every_chunk() {
+        let mut buffer = buffer(false);
+        buffer.push(chunk("agent_message", "a"));
+        buffer.push(chunk("agent_message", "b"));
+        buffer.push("plain".to_string());
+        buffer.push("plain".to_string());
+        assert_eq!(buffer.take().unwrap().1.len(), 4);
+    }
+
+    #[test]
+    fn test_open_message_waits_for_coalesce_latency() {
+        let mut buffer = OutputBuffer::new(OutputSettings {
+            max_batch_latency_ms: 1,
+            max_coalesce_latency_ms: 60_000,
+            ..OutputSettings::default()
+        });
+        buffer.set_stream(Some(OutputStreamKind::Assistant));
+        buffer.push(chunk("agent_message", "still going"));
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        assert!(!buffer.is_stale());
+
+        buffer.push("done".to_string());
+        assert!(buffer.is_stale());
+    }
+}
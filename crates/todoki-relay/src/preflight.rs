@@ -0,0 +1,175 @@
+//! Checks run before spawning a session
+//!
+//! A missing workdir or agent binary otherwise surfaces as a bare
+//! "No such file or directory" from the process launch. The relay checks
+//! the workdir (exists, is a directory, is writable, has enough free disk
+//! space) and that the command resolves to an executable, and reports the
+//! first problem as a [`SpawnError`] whose code travels with
+//! `relay.spawn_failed`.
+
+use std::path::{Path, PathBuf};
+
+use todoki_protocol::event_bus::SpawnErrorCode;
+
+use crate::config::PreflightSettings;
+
+/// A spawn that failed for a known reason
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+pub struct SpawnError {
+    pub code: SpawnErrorCode,
+    pub message: String,
+}
+
+impl SpawnError {
+    pub fn new(code: SpawnErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Check `workdir` and `command` before spawning
+///
+/// `path` is the `PATH` the agent will run with; relative commands with a
+/// slash resolve against the workdir.
+pub fn check(
+    settings: &PreflightSettings,
+    workdir: &str,
+    command: &str,
+    path: Option<&str>,
+) -> Result<(), SpawnError> {
+    let dir = Path::new(workdir);
+    if !dir.is_dir() {
+        return Err(SpawnError::new(
+            SpawnErrorCode::WorkdirMissing,
+            format!("workdir does not exist or is not a directory: {}", workdir),
+        ));
+    }
+    check_writable(dir)?;
+    check_disk_space(settings, dir)?;
+
+    if resolve_command(command, dir, path).is_none() {
+        return Err(SpawnError::new(
+            SpawnErrorCode::CommandNotFound,
+            format!("command not found or not executable: {}", command),
+        ));
+    }
+    Ok(())
+}
+
+fn check_writable(dir: &Path) -> Result<(), SpawnError> {
+    let probe = dir.join(format!(".todoki-preflight-{}", uuid::Uuid::new_v4()));
+    match std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+    {
+        Ok(_) => {
+            let _ = std::fs::remove_file(&probe);
+            Ok(())
+        }
+        Err(e) => Err(SpawnError::new(
+            SpawnErrorCode::WorkdirNotWritable,
+            format!("workdir is not writable: {}: {}", dir.display(), e),
+        )),
+    }
+}
+
+fn check_disk_space(settings: &PreflightSettings, dir: &Path) -> Result<(), SpawnError> {
+    if settings.min_free_disk_mb == 0 {
+        return Ok(());
+    }
+    let available = match fs4::available_space(dir) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            // Some filesystems cannot report it; do not block spawns on that
+            tracing::warn!(workdir = %dir.display(), error = %e, "failed to read free disk space");
+            return Ok(());
+        }
+    };
+    let available_mb = available / (1024 * 1024);
+    if available_mb < settings.min_free_disk_mb {
+        return Err(SpawnError::new(
+            SpawnErrorCode::InsufficientDiskSpace,
+            format!(
+                "only {} MB free in {}, {} MB required",
+                available_mb,
+                dir.display(),
+                settings.min_free_disk_mb
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Executable that `command` runs, looked up on `path` unless it has a slash
+fn resolve_command(command: &str, workdir: &Path, path: Option<&str>) -> Option<PathBuf> {
+    if command.is_empty() {
+        return None;
+    }
+    if command.contains('/') {
+        let candidate = workdir.join(command);
+        return is_executable(&candidate).then_some(candidate);
+    }
+    std::env::split_paths(path?)
+        .map(|dir| workdir.join(dir).join(command))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(result: Result<(), SpawnError>) -> Option<SpawnErrorCode> {
+        result.err().map(|e| e.code)
+    }
+
+    #[test]
+    fn test_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let workdir = dir.path().to_str().unwrap();
+        let settings = PreflightSettings {
+            min_free_disk_mb: 0,
+        };
+
+        assert_eq!(code(check(&settings, workdir, "sh", Some("/bin:/usr/bin"))), None);
+        assert_eq!(code(check(&settings, workdir, "/bin/sh", None)), None);
+        assert_eq!(
+            code(check(&settings, "/nonexistent/workdir", "sh", Some("/bin"))),
+            Some(SpawnErrorCode::WorkdirMissing)
+        );
+        assert_eq!(
+            code(check(&settings, workdir, "todoki-no-such-agent", Some("/bin:/usr/bin"))),
+            Some(SpawnErrorCode::CommandNotFound)
+        );
+        // Not executable
+        std::fs::write(dir.path().join("agent"), "").unwrap();
+        assert_eq!(
+            code(check(&settings, workdir, "./agent", None)),
+            Some(SpawnErrorCode::CommandNotFound)
+        );
+
+        let settings = PreflightSettings {
+            min_free_disk_mb: u64::MAX,
+        };
+        assert_eq!(
+            code(check(&settings, workdir, "sh", Some("/bin:/usr/bin"))),
+            Some(SpawnErrorCode::InsufficientDiskSpace)
+        );
+    }
+}
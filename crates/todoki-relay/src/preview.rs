@@ -0,0 +1,215 @@
+//! Previews of shell commands awaiting permission
+//!
+//! With `[preview] enabled`, the relay runs a Bash tool call's command
+//! without effect before asking for permission and attaches the output to
+//! the request, so whoever answers it sees what the command would do.
+//! Commands with a dry-run option (`git push`, `cargo publish`,
+//! `kubectl apply`, ...) run with it in the session's workdir. With
+//! `container_image` set, any other command runs in a throwaway container,
+//! without network, on a copy of the workdir. Everything else gets no
+//! preview.
+
+use std::process::Stdio;
+
+use todoki_protocol::event_bus::{CommandPreview, PreviewMode, ToolCall};
+use tokio::process::Command;
+
+use crate::config::PreviewSettings;
+
+/// Commands with a dry-run option: binary, subcommand and the option,
+/// inserted right after the subcommand (or the binary)
+const DRY_RUN_OPTIONS: &[(&str, Option<&str>, &str)] = &[
+    ("git", Some("push"), "--dry-run"),
+    ("git", Some("clean"), "--dry-run"),
+    ("git", Some("add"), "--dry-run"),
+    ("git", Some("rm"), "--dry-run"),
+    ("git", Some("mv"), "--dry-run"),
+    ("cargo", Some("publish"), "--dry-run"),
+    ("npm", Some("publish"), "--dry-run"),
+    ("npm", Some("install"), "--dry-run"),
+    ("pip", Some("install"), "--dry-run"),
+    ("kubectl", Some("apply"), "--dry-run=client"),
+    ("kubectl", Some("create"), "--dry-run=client"),
+    ("kubectl", Some("delete"), "--dry-run=client"),
+    ("helm", Some("install"), "--dry-run"),
+    ("helm", Some("upgrade"), "--dry-run"),
+    ("rsync", None, "--dry-run"),
+    ("make", None, "-n"),
+];
+
+/// Characters that make a command line more than one simple command
+const SHELL_METACHARACTERS: &[char] = &[';', '&', '|', '<', '>', '$', '`', '(', ')', '\n'];
+
+/// Preview the command of `tool_call`, if it has one that can be previewed
+pub async fn preview(
+    settings: &PreviewSettings,
+    workdir: &str,
+    tool_call: &ToolCall,
+) -> Option<CommandPreview> {
+    if !settings.enabled {
+        return None;
+    }
+    let command = tool_call.raw_input.get("command")?.as_str()?;
+
+    if let Some(dry_run) = dry_run_command(command) {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(&dry_run).current_dir(workdir);
+        return Some(run(settings, PreviewMode::DryRun, dry_run, cmd).await);
+    }
+    let image = settings.container_image.as_deref()?;
+    sandbox(settings, image, workdir, command).await
+}
+
+/// `command` with its dry-run option added; `None` when it has none or is
+/// more than a single simple command
+fn dry_run_command(command: &str) -> Option<String> {
+    let command = command.trim();
+    if command.contains(SHELL_METACHARACTERS) {
+        return None;
+    }
+
+    let mut words = command.split_whitespace();
+    let binary = words.next()?;
+    let binary = binary.rsplit('/').next().unwrap_or(binary);
+    let subcommand = words.next();
+    let (_, expected, option) = DRY_RUN_OPTIONS.iter().find(|(name, expected, _)| {
+        *name == binary && (expected.is_none() || *expected == subcommand)
+    })?;
+
+    // Insert after the binary or subcommand, keeping the rest as written
+    let skip = if expected.is_some() { 2 } else { 1 };
+    let mut end = 0;
+    for word in command.split_whitespace().take(skip) {
+        end = command[end..].find(word)? + end + word.len();
+    }
+    Some(format!("{} {}{}", &command[..end], option, &command[end..]))
+}
+
+/// Run `command` in a throwaway container on a copy of `workdir`
+async fn sandbox(
+    settings: &PreviewSettings,
+    image: &str,
+    workdir: &str,
+    command: &str,
+) -> Option<CommandPreview> {
+    let id = uuid::Uuid::new_v4();
+    let copy = std::env::temp_dir().join(format!("todoki-preview-{}", id));
+    let copied = Command::new("cp")
+        .arg("-a")
+        .arg(format!("{}/.", workdir.trim_end_matches('/')))
+        .arg(&copy)
+        .status()
+        .await;
+    if !matches!(copied, Ok(status) if status.success()) {
+        tracing::warn!(workdir = %workdir, "failed to copy workdir for command preview");
+        let _ = tokio::fs::remove_dir_all(&copy).await;
+        return None;
+    }
+
+    let name = format!("todoki-preview-{}", id);
+    let mut cmd = Command::new(&settings.container_runtime);
+    cmd.args(["run", "--rm", "--network", "none", "--name", &name])
+        .arg("-v")
+        .arg(format!("{}:/work", copy.display()))
+        .args(["-w", "/work", image, "sh", "-c", command]);
+    let preview = run(settings, PreviewMode::Sandbox, command.to_string(), cmd).await;
+
+    // Killing the CLI on timeout leaves the container running
+    if preview.exit_code.is_none() {
+        let _ = Command::new(&settings.container_runtime)
+            .args(["rm", "-f", &name])
+            .status()
+            .await;
+    }
+    let _ = tokio::fs::remove_dir_all(&copy).await;
+    Some(preview)
+}
+
+async fn run(
+    settings: &PreviewSettings,
+    mode: PreviewMode,
+    command: String,
+    mut cmd: Command,
+) -> CommandPreview {
+    cmd.stdin(Stdio::null()).kill_on_drop(true);
+
+    let (exit_code, output) = match tokio::time::timeout(settings.timeout(), cmd.output()).await {
+        Ok(Ok(out)) => {
+            let mut output = String::from_utf8_lossy(&out.stdout).into_owned();
+            output.push_str(&String::from_utf8_lossy(&out.stderr));
+            (out.status.code(), output)
+        }
+        Ok(Err(e)) => (None, format!("failed to run preview: {}", e)),
+        Err(_) => (
+            None,
+            format!("preview timed out after {}s", settings.timeout().as_secs()),
+        ),
+    };
+    let (output, truncated) = truncate(output, settings.max_output_bytes);
+
+    tracing::debug!(command = %command, exit_code = ?exit_code, "command preview finished");
+    CommandPreview {
+        mode,
+        command,
+        exit_code,
+        output,
+        truncated,
+    }
+}
+
+/// Cut `output` to at most `max_bytes`, on a character boundary
+fn truncate(mut output: String, max_bytes: usize) -> (String, bool) {
+    if output.len() <= max_bytes {
+        return (output, false);
+    }
+    let mut end = max_bytes;
+    while !output.is_char_boundary(end) {
+        end -= 1;
+    }
+    output.truncate(end);
+    (output, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dry_run_command() {
+        assert_eq!(
+            dry_run_command("git push origin  main").as_deref(),
+            Some("git push --dry-run origin  main")
+        );
+        assert_eq!(
+            dry_run_command("/usr/bin/kubectl apply -f deploy.yaml").as_deref(),
+            Some("/usr/bin/kubectl apply --dry-run=client -f deploy.yaml")
+        );
+        assert_eq!(
+            dry_run_command("make install").as_deref(),
+            Some("make -n install")
+        );
+        assert_eq!(dry_run_command("rm -rf build"), None);
+        assert_eq!(dry_run_command("git status"), None);
+        // Chains and substitutions are not rewritten
+        assert_eq!(dry_run_command("git push && rm -rf ."), None);
+        assert_eq!(dry_run_command("git push $(cat remote)"), None);
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("short".to_string(), 10), ("short".to_string(), false));
+        // Never splits a character
+        assert_eq!(truncate("héllo".to_string(), 2), ("h".to_string(), true));
+    }
+
+    #[tokio::test]
+    async fn test_disabled_preview() {
+        let tool_call = ToolCall {
+            title: "git push".to_string(),
+            raw_input: serde_json::json!({ "command": "git push" }),
+            tool_call_id: None,
+        };
+        let settings = PreviewSettings::default();
+        assert_eq!(preview(&settings, ".", &tool_call).await, None);
+    }
+}
@@ -0,0 +1,180 @@
+//! Plain-process sessions for agents that do not speak ACP
+//!
+//! Raw stdout/stderr chunks are streamed as `relay.agent_output` with the
+//! `stdout`/`stderr` stream types, and session input is written to stdin.
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+use chrono::Utc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{ChildStderr, ChildStdin, ChildStdout};
+use tokio::sync::{mpsc, Mutex};
+
+use crate::flow_control::FlowControl;
+use crate::relay::RelayOutput;
+use todoki_protocol::OutputStreamKind;
+
+const READ_CHUNK_SIZE: usize = 4096;
+
+/// Handle for writing input to a plain-process session
+#[derive(Clone)]
+pub struct ProcessHandle {
+    stdin: Arc<Mutex<ChildStdin>>,
+}
+
+impl ProcessHandle {
+    /// Start streaming the process output and return a handle for its stdin
+    pub fn start(
+        output_tx: mpsc::Sender<RelayOutput>,
+        flow: Arc<FlowControl>,
+        agent_id: String,
+        session_id: String,
+        stdout: ChildStdout,
+        stderr: Option<ChildStderr>,
+        stdin: ChildStdin,
+    ) -> Self {
+        // Same seq scheme as ACP sessions: start at the current timestamp so
+        // ordering holds across sessions
+        let seq_counter = Arc::new(AtomicI64::new(
+            Utc::now().timestamp_nanos_opt().unwrap_or(0),
+        ));
+
+        tokio::spawn(stream_output(
+            stdout,
+            OutputStreamKind::Stdout,
+            output_tx.clone(),
+            flow.clone(),
+            agent_id.clone(),
+            session_id.clone(),
+            seq_counter.clone(),
+        ));
+        if let Some(stderr) = stderr {
+            tokio::spawn(stream_output(
+                stderr,
+                OutputStreamKind::Stderr,
+                output_tx,
+                flow,
+                agent_id,
+                session_id,
+                seq_counter,
+            ));
+        }
+
+        Self {
+            stdin: Arc::new(Mutex::new(stdin)),
+        }
+    }
+
+    /// Write input to stdin as a line
+    pub async fn write_input(&self, input: &str) -> anyhow::Result<()> {
+        let mut stdin = self.stdin.lock().await;
+        stdin.write_all(input.as_bytes()).await?;
+        if !input.ends_with('\n') {
+            stdin.write_all(b"\n").await?;
+        }
+        stdin.flush().await?;
+        Ok(())
+    }
+}
+
+async fn stream_output<R: AsyncRead + Unpin>(
+    mut reader: R,
+    stream: OutputStreamKind,
+    output_tx: mpsc::Sender<RelayOutput>,
+    flow: Arc<FlowControl>,
+    agent_id: String,
+    session_id: String,
+    seq_counter: Arc<AtomicI64>,
+) {
+    let mut buf = [0u8; READ_CHUNK_SIZE];
+    let mut pending = Vec::new();
+
+    loop {
+        let n = match reader.read(&mut buf).await {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) => {
+                tracing::warn!(
+                    session_id = %session_id,
+                    stream = %stream,
+                    error = %e,
+                    "process output read failed"
+                );
+                break;
+            }
+        };
+        pending.extend_from_slice(&buf[..n]);
+
+        let message = take_utf8(&mut pending);
+        if message.is_empty() {
+            continue;
+        }
+
+        // Stop reading (and let the pipe fill up) while the server is behind
+        flow.acquire(message.len()).await;
+
+        let msg = RelayOutput::EmitEvent {
+            kind: "relay.agent_output".to_string(),
+            data: serde_json::json!({
+                "agent_id": agent_id,
+                "session_id": session_id,
+                "seq": seq_counter.fetch_add(1, Ordering::SeqCst),
+                "ts": Utc::now().timestamp_nanos_opt().unwrap_or(0),
+                "stream": stream,
+                "message": message,
+            }),
+        };
+        if output_tx.send(msg).await.is_err() {
+            break;
+        }
+    }
+
+    tracing::debug!(session_id = %session_id, stream = %stream, "process output closed");
+}
+
+/// Take the longest valid UTF-8 prefix of `pending`, keeping a trailing
+/// partial character for the next read; invalid bytes are replaced
+pub(crate) fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let valid_up_to = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        // error_len() == None means the input ended mid-character
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => pending.len(),
+    };
+    let rest = pending.split_off(valid_up_to);
+    let text = String::from_utf8_lossy(pending).into_owned();
+    *pending = rest;
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_utf8_complete() {
+        let mut pending = b"hello\n".to_vec();
+        assert_eq!(take_utf8(&mut pending), "hello\n");
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn test_take_utf8_keeps_partial_char() {
+        let bytes = "日本".as_bytes();
+        let mut pending = bytes[..4].to_vec();
+        assert_eq!(take_utf8(&mut pending), "日");
+        assert_eq!(pending, bytes[3..4]);
+
+        pending.extend_from_slice(&bytes[4..]);
+        assert_eq!(take_utf8(&mut pending), "本");
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn test_take_utf8_replaces_invalid_bytes() {
+        let mut pending = vec![b'a', 0xff, b'b'];
+        assert_eq!(take_utf8(&mut pending), "a\u{fffd}b");
+        assert!(pending.is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+//! PTY-backed interactive sessions
+//!
+//! The agent runs on a pseudo-terminal so interactive CLIs behave as they
+//! would for a person. Terminal output, ANSI sequences included, is streamed
+//! as `relay.agent_output` with the `terminal` stream type; input is written
+//! to the terminal as raw keystrokes and the terminal can be resized.
+
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
+use tokio::sync::mpsc;
+
+use crate::flow_control::FlowControl;
+use crate::process::take_utf8;
+use crate::relay::RelayOutput;
+use crate::session::RELAY_TOKEN_ENV;
+use todoki_protocol::{OutputStreamKind, SpawnSessionParams};
+
+/// Terminal size until the frontend sends its own
+pub const DEFAULT_COLS: u16 = 120;
+pub const DEFAULT_ROWS: u16 = 40;
+
+const READ_CHUNK_SIZE: usize = 4096;
+
+/// Ctrl-C, sent to interrupt the foreground program on cancel
+const INTERRUPT: &str = "\u{3}";
+
+pub type PtyChild = Box<dyn portable_pty::Child + Send + Sync>;
+
+/// Handle for writing to and resizing a PTY session
+#[derive(Clone)]
+pub struct PtyHandle {
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    master: Arc<Mutex<Box<dyn MasterPty + Send>>>,
+}
+
+impl PtyHandle {
+    /// Spawn the agent command on a new PTY and start streaming its output
+    pub fn spawn(
+        output_tx: mpsc::Sender<RelayOutput>,
+        flow: Arc<FlowControl>,
+        params: &SpawnSessionParams,
+        workdir: &str,
+    ) -> anyhow::Result<(Self, PtyChild)> {
+        let pair = native_pty_system().openpty(PtySize {
+            rows: DEFAULT_ROWS,
+            cols: DEFAULT_COLS,
+            pixel_width: 0,
+            pixel_height: 0,
+        })?;
+
+        // CommandBuilder inherits the relay's environment, minus its token
+        let mut command = CommandBuilder::new(&params.command);
+        command.args(&params.args);
+        command.cwd(workdir);
+        command.env_remove(RELAY_TOKEN_ENV);
+        command.env("TERM", "xterm-256color");
+        for (key, value) in &params.env {
+            command.env(key, value);
+        }
+
+        let child = pair.slave.spawn_command(command)?;
+        // Keep only the master side open so EOF is seen when the child exits
+        drop(pair.slave);
+
+        let reader = pair.master.try_clone_reader()?;
+        let writer = pair.master.take_writer()?;
+
+        let agent_id = params.agent_id.clone();
+        let session_id = params.session_id.clone();
+        let runtime = tokio::runtime::Handle::current();
+        std::thread::spawn(move || {
+            stream_terminal(reader, output_tx, flow, runtime, agent_id, session_id)
+        });
+
+        let handle = Self {
+            writer: Arc::new(Mutex::new(writer)),
+            master: Arc::new(Mutex::new(pair.master)),
+        };
+        Ok((handle, child))
+    }
+
+    /// Write raw input (keystrokes) to the terminal
+    pub async fn write_input(&self, input: String) -> anyhow::Result<()> {
+        let writer = self.writer.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut writer = writer
+                .lock()
+                .map_err(|_| anyhow::anyhow!("pty writer poisoned"))?;
+            writer.write_all(input.as_bytes())?;
+            writer.flush()?;
+            Ok(())
+        })
+        .await?
+    }
+
+    /// Interrupt the foreground program
+    pub async fn interrupt(&self) -> anyhow::Result<()> {
+        self.write_input(INTERRUPT.to_string()).await
+    }
+
+    pub fn resize(&self, cols: u16, rows: u16) -> anyhow::Result<()> {
+        if cols == 0 || rows == 0 {
+            anyhow::bail!("invalid terminal size {}x{}", cols, rows);
+        }
+        let master = self
+            .master
+            .lock()
+            .map_err(|_| anyhow::anyhow!("pty master poisoned"))?;
+        master.resize(PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        })?;
+        Ok(())
+    }
+}
+
+/// Blocking reader loop; runs on its own thread
+fn stream_terminal(
+    mut reader: Box<dyn Read + Send>,
+    output_tx: mpsc::Sender<RelayOutput>,
+    flow: Arc<FlowControl>,
+    runtime: tokio::runtime::Handle,
+    agent_id: String,
+    session_id: String,
+) {
+    let seq_counter = AtomicI64::new(Utc::now().timestamp_nanos_opt().unwrap_or(0));
+    let mut buf = [0u8; READ_CHUNK_SIZE];
+    let mut pending = Vec::new();
+
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) => {
+                // EIO is how Linux reports a closed PTY
+                tracing::debug!(session_id = %session_id, error = %e, "terminal read ended");
+                break;
+            }
+        };
+        pending.extend_from_slice(&buf[..n]);
+
+        let message = take_utf8(&mut pending);
+        if message.is_empty() {
+            continue;
+        }
+
+        // Stop reading (and let the terminal block) while the server is behind
+        flow.acquire_blocking(&runtime, message.len());
+
+        let msg = RelayOutput::EmitEvent {
+            kind: "relay.agent_output".to_string(),
+            data: serde_json::json!({
+                "agent_id": agent_id,
+                "session_id": session_id,
+                "seq": seq_counter.fetch_add(1, Ordering::SeqCst),
+                "ts": Utc::now().timestamp_nanos_opt().unwrap_or(0),
+                "stream": OutputStreamKind::Terminal,
+                "message": message,
+            }),
+        };
+        if output_tx.blocking_send(msg).is_err() {
+            break;
+        }
+    }
+
+    tracing::debug!(session_id = %session_id, "terminal output closed");
+}
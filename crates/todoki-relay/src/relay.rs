@@ -1,18 +1,31 @@
 use std::sync::Arc;
 use std::time::Duration;
 
-use futures_util::{SinkExt, StreamExt};
+use futures_util::{Sink, SinkExt, StreamExt};
 use serde_json::Value;
 use sha2::{Digest, Sha256};
 use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::header::{HeaderValue, AUTHORIZATION};
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 
+use crate::backoff::{Backoff, ReconnectBudgetExhausted};
 use crate::config::RelayConfig;
+use crate::event_bus_client::EventBusClient;
+use crate::flow_control::FlowControl;
+use crate::logging::LogControl;
+use crate::offline_buffer::{self, BufferedRoute, OfflineBuffer};
+use crate::preflight::SpawnError;
 use crate::session::SessionManager;
-use todoki_protocol::{PermissionOutcome, SendInputParams};
+use todoki_protocol::event_bus::SpawnErrorCode;
+use todoki_protocol::relay_version::{
+    ProtocolRange, INCOMPATIBLE_PROTOCOL, MIN_RELAY_PROTOCOL_VERSION, RELAY_PROTOCOL_VERSION,
+    UNVERSIONED_RELAY_PROTOCOL,
+};
+use todoki_protocol::{
+    PermissionFallback, PermissionOutcome, RelayErrorCode, SendInputParams, RELAY_WS_PATH,
+};
 
-const RECONNECT_DELAY: Duration = Duration::from_secs(3);
-const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
 const BUFFER_SIZE: usize = 4096;
 
 /// Client → Server message format for Event Bus WebSocket
@@ -49,10 +62,24 @@ enum ServerMessage {
         #[allow(dead_code)]
         cursor: i64,
     },
-    /// Relay registered confirmation
-    Registered { relay_id: String },
+    /// Relay registered confirmation, with the server's permission fallback
+    /// if it enforces one and the protocol version agreed on; servers from
+    /// before versioning send neither version field
+    Registered {
+        relay_id: String,
+        #[serde(default)]
+        permission_fallback: Option<PermissionFallback>,
+        #[serde(default)]
+        protocol_version: Option<u16>,
+        #[serde(default)]
+        supported_versions: Option<ProtocolRange>,
+    },
     /// Error message
-    Error { message: String },
+    Error {
+        message: String,
+        #[serde(default)]
+        code: Option<String>,
+    },
     /// Heartbeat ping
     Ping,
     /// Heartbeat pong
@@ -77,28 +104,81 @@ pub enum RelayOutput {
 pub struct Relay {
     config: RelayConfig,
     relay_id: String,
+    /// Events that could not be delivered, replayed after reconnecting
+    offline: Option<Arc<OfflineBuffer>>,
+    /// In-flight output budget shared by sessions and the forwarder
+    flow: Arc<FlowControl>,
+    /// Persists across `run` calls so downtime is measured from the first
+    /// failure
+    backoff: Backoff,
+    /// Changes the log filter on `relay.log_level_changed`
+    log: Option<LogControl>,
 }
 
 impl Relay {
     pub fn new(config: RelayConfig) -> Self {
         let relay_id = generate_relay_id();
-        Self { config, relay_id }
+        let backoff = config.backoff();
+        let flow = Arc::new(FlowControl::new(config.output().max_in_flight_bytes));
+        Self {
+            config,
+            relay_id,
+            offline: None,
+            flow,
+            backoff,
+            log: None,
+        }
+    }
+
+    /// Let the server change the log filter
+    pub fn with_log_control(mut self, log: LogControl) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    /// Record a failed connection and return how long to wait before
+    /// retrying; errors once the give-up budget is spent
+    pub fn next_reconnect_delay(&mut self) -> Result<Duration, ReconnectBudgetExhausted> {
+        self.backoff.next_delay()
     }
 
     pub async fn run(&mut self) -> anyhow::Result<()> {
+        // Without the on-disk buffer the relay still works, it just loses
+        // events sent while disconnected
+        self.offline = match OfflineBuffer::open(self.config.buffer_file()) {
+            Ok(buffer) => Some(Arc::new(buffer)),
+            Err(e) => {
+                tracing::warn!(
+                    path = ?self.config.buffer_file(),
+                    error = %e,
+                    "failed to open offline buffer, undelivered events will be lost"
+                );
+                None
+            }
+        };
+
         // Create a persistent buffer channel
         // All session output goes here first, then forwarded to WebSocket
         let (buffer_tx, buffer_rx) = mpsc::channel::<RelayOutput>(BUFFER_SIZE);
 
         // Create session manager once - persists across reconnects
-        let session_manager = Arc::new(SessionManager::new(
-            buffer_tx.clone(),
-            self.config.safe_paths().to_vec(),
-            self.config.server_url().to_string(),
-            self.config.token.clone(),
-        ));
-
-        let mut reconnect_delay = RECONNECT_DELAY;
+        let session_manager = Arc::new(
+            SessionManager::new(
+                buffer_tx.clone(),
+                self.config.safe_paths().to_vec(),
+                self.config.server_url().to_string(),
+                self.config.token.clone(),
+            )
+            .with_offline_buffer(self.offline.clone())
+            .with_flow_control(self.config.output().clone(), self.flow.clone())
+            .with_health_probes(self.config.health().clone())
+            .with_usage_sampling(self.config.usage().clone())
+            .with_permission_fallback(self.config.permissions().clone())
+            .with_command_preview(self.config.preview().clone())
+            .with_preflight(self.config.preflight().clone())
+            .with_simulation(self.config.simulate().clone())
+            .with_commit_detection(self.config.commits().clone()),
+        );
 
         // Wrap buffer_rx in Option so we can take ownership in the loop
         let mut buffer_rx = Some(buffer_rx);
@@ -113,16 +193,27 @@ impl Relay {
                 ConnectionResult::Reconnect(returned_rx) => {
                     buffer_rx = Some(returned_rx);
 
+                    let delay = match self.backoff.next_delay() {
+                        Ok(delay) => delay,
+                        Err(e) => {
+                            tracing::error!(
+                                error = %e,
+                                attempts = e.attempts,
+                                "reconnect budget exhausted"
+                            );
+                            session_manager.stop_all().await;
+                            return Err(e.into());
+                        }
+                    };
                     tracing::info!(
-                        delay_secs = reconnect_delay.as_secs(),
+                        attempt = self.backoff.attempts(),
+                        delay_ms = delay.as_millis() as u64,
                         "connection lost, reconnecting..."
                     );
-                    tokio::time::sleep(reconnect_delay).await;
-                    reconnect_delay = std::cmp::min(reconnect_delay * 2, MAX_RECONNECT_DELAY);
+                    tokio::time::sleep(delay).await;
                 }
                 ConnectionResult::ReconnectImmediate(returned_rx) => {
                     buffer_rx = Some(returned_rx);
-                    reconnect_delay = RECONNECT_DELAY;
                 }
                 ConnectionResult::FatalError(e) => {
                     tracing::error!(error = %e, "fatal error, stopping relay");
@@ -140,26 +231,33 @@ impl Relay {
         buffer_tx: mpsc::Sender<RelayOutput>,
         mut buffer_rx: mpsc::Receiver<RelayOutput>,
     ) -> ConnectionResult {
-        // Build Event Bus WebSocket URL with relay_id
-        let base_url = self.config.server_url();
-        let event_bus_url = base_url
-            .replace("/ws/relay", "/ws/event-bus")
-            .replace("/ws/relays", "/ws/event-bus");
-
-        let url = format!(
-            "{}?relay_id={}&kinds=relay.*,permission.responded&token={}",
-            if event_bus_url.contains("/ws/event-bus") {
-                event_bus_url
-            } else {
-                format!("{}/ws/event-bus", base_url.trim_end_matches('/'))
-            },
-            self.relay_id,
-            self.config.token
-        );
+        // Build the relay WebSocket URL; URLs naming another endpoint are
+        // pointed at /ws/relay
+        let base_url = self
+            .config
+            .server_url()
+            .trim_end_matches('/')
+            .trim_end_matches("/ws/relays")
+            .trim_end_matches("/ws/relay")
+            .trim_end_matches("/ws/event-bus")
+            .to_string();
+        let url = format!("{}{}?relay_id={}", base_url, RELAY_WS_PATH, self.relay_id);
 
         tracing::info!(url = %url, relay_id = %self.relay_id, "connecting to event bus");
 
-        let (ws_stream, _) = match connect_async(&url).await {
+        // The relay token goes in the Authorization header
+        let mut request = match url.as_str().into_client_request() {
+            Ok(request) => request,
+            Err(e) => return ConnectionResult::FatalError(e.into()),
+        };
+        match HeaderValue::from_str(&format!("Bearer {}", self.config.token)) {
+            Ok(value) => {
+                request.headers_mut().insert(AUTHORIZATION, value);
+            }
+            Err(e) => return ConnectionResult::FatalError(e.into()),
+        }
+
+        let (ws_stream, _) = match connect_async(request).await {
             Ok(s) => s,
             Err(e) => {
                 tracing::error!(error = %e, "failed to connect to event bus");
@@ -203,6 +301,9 @@ impl Relay {
             "labels": self.config.labels(),
             "projects": self.config.projects(),
             "setup_script": self.config.setup_script(),
+            "permission_fallback": self.config.permissions().fallback,
+            "protocol_version": RELAY_PROTOCOL_VERSION,
+            "min_protocol_version": MIN_RELAY_PROTOCOL_VERSION,
         });
 
         let register_msg = ClientMessage::EmitEvent {
@@ -223,18 +324,39 @@ impl Relay {
 
         // Wait for registered confirmation
         let mut registered = false;
+        let mut incompatible = false;
         let timeout = tokio::time::timeout(Duration::from_secs(30), async {
             while !registered {
                 match ws_read.next().await {
                     Some(Ok(Message::Text(text))) => {
                         if let Ok(msg) = serde_json::from_str::<ServerMessage>(&text) {
                             match msg {
-                                ServerMessage::Registered { relay_id } => {
-                                    tracing::info!(relay_id = %relay_id, "registered with server");
+                                ServerMessage::Registered {
+                                    relay_id,
+                                    permission_fallback,
+                                    protocol_version,
+                                    supported_versions,
+                                } => {
+                                    tracing::info!(
+                                        relay_id = %relay_id,
+                                        protocol_version =
+                                            protocol_version.unwrap_or(UNVERSIONED_RELAY_PROTOCOL),
+                                        server_versions = ?supported_versions,
+                                        "registered with server"
+                                    );
+                                    if let Some(fallback) = &permission_fallback {
+                                        tracing::info!(
+                                            action = ?fallback.action,
+                                            timeout_secs = fallback.timeout_secs,
+                                            "using permission fallback pushed by server"
+                                        );
+                                    }
+                                    session_manager.set_server_permission_fallback(permission_fallback);
                                     registered = true;
                                 }
-                                ServerMessage::Error { message } => {
+                                ServerMessage::Error { message, code } => {
                                     tracing::error!(error = %message, "registration error");
+                                    incompatible = code.as_deref() == Some(INCOMPATIBLE_PROTOCOL);
                                     return Err(anyhow::anyhow!("registration error: {}", message));
                                 }
                                 _ => {}
@@ -254,7 +376,11 @@ impl Relay {
         });
 
         match timeout.await {
-            Ok(Ok(())) => {}
+            Ok(Ok(())) => self.backoff.reset(),
+            // Reconnecting cannot fix a version mismatch
+            Ok(Err(e)) if incompatible => {
+                return ConnectionResult::FatalError(e);
+            }
             Ok(Err(e)) => {
                 tracing::error!(error = %e, "registration failed");
                 return ConnectionResult::Reconnect(buffer_rx);
@@ -265,10 +391,18 @@ impl Relay {
             }
         }
 
+        // Deliver what was buffered while disconnected before anything newer
+        if let Err(e) = self.replay_offline_buffer(&mut ws_write).await {
+            tracing::error!(error = %e, "failed to replay offline buffer");
+            return ConnectionResult::Reconnect(buffer_rx);
+        }
+
         // Channel to signal shutdown to forwarder
         let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
 
         // Spawn forwarder task: buffer_rx -> WebSocket (via Event Bus emit)
+        let offline = self.offline.clone();
+        let flow = self.flow.clone();
         let forwarder_handle = tokio::spawn(async move {
             loop {
                 tokio::select! {
@@ -279,16 +413,23 @@ impl Relay {
                     msg = buffer_rx.recv() => {
                         match msg {
                             Some(RelayOutput::EmitEvent { kind, data }) => {
+                                // The event leaves memory either way below
+                                let weight = FlowControl::weight(&kind, &data);
                                 let client_msg = ClientMessage::EmitEvent { kind, data };
                                 let msg_text = match serde_json::to_string(&client_msg) {
                                     Ok(text) => text,
                                     Err(e) => {
                                         tracing::error!(error = %e, "failed to serialize message");
+                                        flow.release(weight);
                                         continue;
                                     }
                                 };
-                                if ws_write.send(Message::Text(msg_text)).await.is_err() {
+                                let sent = ws_write.send(Message::Text(msg_text)).await.is_ok();
+                                flow.release(weight);
+                                if !sent {
                                     tracing::warn!("websocket send failed, stopping forwarder");
+                                    let ClientMessage::EmitEvent { kind, data } = client_msg;
+                                    buffer_undelivered(offline.as_deref(), kind, data);
                                     break;
                                 }
                             }
@@ -306,6 +447,8 @@ impl Relay {
         tracing::info!("forwarder task spawned");
         // Process inbound messages from server (events)
 
+        // Set when another relay took over this relay ID
+        let mut superseded = None;
         loop {
             let msg = ws_read.next().await;
             match msg {
@@ -325,6 +468,7 @@ impl Relay {
                                 &self.relay_id,
                                 &buffer_tx,
                                 self.config.setup_script(),
+                                self.log.as_ref(),
                             )
                             .await
                             {
@@ -335,7 +479,12 @@ impl Relay {
                             // Server sends JSON-level ping for keep-alive
                             tracing::debug!("received ping from server");
                         }
-                        ServerMessage::Error { message } => {
+                        ServerMessage::Error { message, code } => {
+                            // Reconnecting would only take the ID back and forth
+                            if code.as_deref() == Some(RelayErrorCode::SUPERSEDED) {
+                                superseded = Some(message);
+                                break;
+                            }
                             tracing::warn!(error = %message, "received error from server");
                         }
                         _ => {}
@@ -376,10 +525,56 @@ impl Relay {
             }
         };
 
+        if let Some(message) = superseded {
+            return ConnectionResult::FatalError(anyhow::anyhow!(
+                "{}; is another relay running with the same ID?",
+                message
+            ));
+        }
+
         tracing::info!("keeping sessions alive, buffered messages will be sent on reconnect");
         ConnectionResult::ReconnectImmediate(returned_rx)
     }
 
+    /// Send buffered events in seq order, acking each once it is out
+    async fn replay_offline_buffer<S>(&self, ws_write: &mut S) -> anyhow::Result<()>
+    where
+        S: Sink<Message> + Unpin,
+        S::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let Some(buffer) = &self.offline else {
+            return Ok(());
+        };
+        let pending = buffer.pending()?;
+        if pending.is_empty() {
+            return Ok(());
+        }
+        tracing::info!(count = pending.len(), "replaying offline buffer");
+
+        // Stored messages carry their own agent/task ids
+        let event_bus = EventBusClient::new(
+            self.config.server_url(),
+            &self.config.token,
+            uuid::Uuid::nil(),
+        );
+
+        for entry in pending {
+            match entry.route {
+                BufferedRoute::WebSocket { kind, data } => {
+                    let msg_text = serde_json::to_string(&ClientMessage::EmitEvent { kind, data })?;
+                    ws_write.send(Message::Text(msg_text)).await?;
+                }
+                BufferedRoute::Http { message } => {
+                    event_bus.emit_buffered(&message).await?;
+                }
+            }
+            buffer.ack(entry.seq)?;
+        }
+
+        tracing::info!("offline buffer replayed");
+        Ok(())
+    }
+
     /// Handle server events (commands from server)
     async fn handle_server_event(
         kind: &str,
@@ -388,6 +583,7 @@ impl Relay {
         relay_id: &str,
         buffer_tx: &mpsc::Sender<RelayOutput>,
         setup_script: Option<&str>,
+        log: Option<&LogControl>,
     ) -> Option<RelayOutput> {
         match kind {
             "relay.spawn_requested" => {
@@ -400,6 +596,13 @@ impl Relay {
                 let env: std::collections::HashMap<String, String> =
                     serde_json::from_value(data.get("env")?.clone()).unwrap_or_default();
                 let task_id = data.get("task_id").and_then(|v| v.as_str()).map(|s| s.to_string());
+                let task = data
+                    .get("task")
+                    .and_then(|v| serde_json::from_value(v.clone()).ok());
+                let mode = data
+                    .get("mode")
+                    .and_then(|v| serde_json::from_value(v.clone()).ok())
+                    .unwrap_or_default();
 
                 let params = todoki_protocol::SpawnSessionParams {
                     agent_id: agent_id.to_string(),
@@ -410,6 +613,8 @@ impl Relay {
                     env,
                     setup_script: setup_script.map(|s| s.to_string()),
                     task_id,
+                    task,
+                    mode,
                 };
 
                 match session_manager.spawn(params).await {
@@ -429,9 +634,13 @@ impl Relay {
                         })
                     }
                     Err(e) => {
+                        let code = e
+                            .downcast_ref::<SpawnError>()
+                            .map_or(SpawnErrorCode::LaunchFailed, |e| e.code);
                         tracing::error!(
                             request_id = %request_id,
                             error = %e,
+                            code = ?code,
                             "spawn failed"
                         );
                         Some(RelayOutput::EmitEvent {
@@ -441,6 +650,7 @@ impl Relay {
                                 "session_id": session_id,
                                 "relay_id": relay_id,
                                 "error": e.to_string(),
+                                "code": code,
                             }),
                         })
                     }
@@ -466,6 +676,46 @@ impl Relay {
                 None
             }
 
+            "relay.resize_requested" => {
+                let session_id = data.get("session_id")?.as_str()?;
+                let cols = u16::try_from(data.get("cols")?.as_u64()?).ok()?;
+                let rows = u16::try_from(data.get("rows")?.as_u64()?).ok()?;
+
+                if let Err(e) = session_manager.resize(session_id, cols, rows).await {
+                    tracing::warn!(session_id = %session_id, error = %e, "resize failed");
+                }
+                None
+            }
+
+            "relay.log_level_changed" => {
+                let directives = data.get("directives")?.as_str()?;
+                let result = match log {
+                    Some(log) => log.set(directives),
+                    None => Err("log filter cannot be changed by this relay".to_string()),
+                };
+                match result {
+                    Ok(()) => {
+                        tracing::info!(directives = %directives, "log filter changed");
+                        None
+                    }
+                    Err(msg) => {
+                        tracing::warn!(
+                            directives = %directives,
+                            error = %msg,
+                            "log filter not changed"
+                        );
+                        Some(RelayOutput::EmitEvent {
+                            kind: "relay.error".to_string(),
+                            data: serde_json::json!({
+                                "relay_id": relay_id,
+                                "error_type": "log_level_failed",
+                                "error": msg,
+                            }),
+                        })
+                    }
+                }
+            }
+
             "permission.responded" => {
                 let request_id = match data.get("request_id").and_then(|v| v.as_str()) {
                     Some(id) => id,
@@ -611,6 +861,24 @@ enum ConnectionResult {
     FatalError(anyhow::Error),
 }
 
+/// Keep a durable event that could not be sent for replay on reconnect;
+/// anything else is dropped as before
+fn buffer_undelivered(offline: Option<&OfflineBuffer>, kind: String, data: Value) {
+    let Some(buffer) = offline else {
+        return;
+    };
+    if !offline_buffer::is_durable(&kind) {
+        return;
+    }
+    match buffer.append(BufferedRoute::WebSocket {
+        kind: kind.clone(),
+        data,
+    }) {
+        Ok(seq) => tracing::info!(kind = %kind, seq, "buffered undelivered event"),
+        Err(e) => tracing::error!(kind = %kind, error = %e, "failed to buffer event"),
+    }
+}
+
 /// Generate a stable relay ID based on machine ID
 fn generate_relay_id() -> String {
     let machine_id = match machine_uid::get() {
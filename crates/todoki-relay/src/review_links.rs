@@ -0,0 +1,110 @@
+//! Pull and merge request links in tool output
+//!
+//! Agents open pull requests with CLIs (`gh`, `glab`, `tea`) or `git push`,
+//! which print the request's URL. Each link found becomes an artifact:
+//! `github_pr` for GitHub, `gitlab_mr` for GitLab merge requests (any host)
+//! and `gitea_pr` for Gitea and Forgejo pull requests (any host).
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{json, Value};
+
+static GITHUB_PR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"https://github\.com/([^/\s]+)/([^/\s]+)/pull/(\d+)").unwrap());
+
+/// Group paths may be nested: `https://gitlab.com/group/sub/repo/-/merge_requests/7`
+static GITLAB_MR: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"https://([^/\s]+)/((?:[^/\s]+/)*[^/\s]+)/-/merge_requests/(\d+)").unwrap()
+});
+
+/// Gitea says `pulls` where GitHub says `pull`
+static GITEA_PR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"https://([^/\s]+)/([^/\s]+)/([^/\s]+)/pulls/(\d+)").unwrap());
+
+/// A pull or merge request found in text
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReviewLink {
+    /// Artifact type: `github_pr`, `gitlab_mr` or `gitea_pr`
+    pub artifact_type: &'static str,
+    /// Artifact data; always has `url` and `number`
+    pub data: Value,
+}
+
+/// Every pull or merge request URL in `text`, in order of appearance
+pub fn detect(text: &str) -> Vec<ReviewLink> {
+    let mut found: Vec<(usize, ReviewLink)> = Vec::new();
+
+    for caps in GITHUB_PR.captures_iter(text) {
+        let data = json!({
+            "url": &caps[0],
+            "owner": &caps[1],
+            "repo": &caps[2],
+            "number": number(&caps[3]),
+        });
+        found.push((caps.get(0).unwrap().start(), link("github_pr", data)));
+    }
+
+    for caps in GITLAB_MR.captures_iter(text) {
+        let data = json!({
+            "url": &caps[0],
+            "host": &caps[1],
+            "project": &caps[2],
+            "number": number(&caps[3]),
+        });
+        found.push((caps.get(0).unwrap().start(), link("gitlab_mr", data)));
+    }
+
+    for caps in GITEA_PR.captures_iter(text) {
+        let data = json!({
+            "url": &caps[0],
+            "host": &caps[1],
+            "owner": &caps[2],
+            "repo": &caps[3],
+            "number": number(&caps[4]),
+        });
+        found.push((caps.get(0).unwrap().start(), link("gitea_pr", data)));
+    }
+
+    found.sort_by_key(|(start, _)| *start);
+    found.into_iter().map(|(_, link)| link).collect()
+}
+
+fn link(artifact_type: &'static str, data: Value) -> ReviewLink {
+    ReviewLink {
+        artifact_type,
+        data,
+    }
+}
+
+fn number(digits: &str) -> u32 {
+    digits.parse().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let output = "Created https://github.com/Kilerd/todoki/pull/12\n\
+            remote: View merge request at https://gitlab.example.com/infra/tools/cli/-/merge_requests/7\n\
+            see https://git.example.org/team/app/pulls/3 and https://github.com/Kilerd/todoki/issues/4";
+
+        let links = detect(output);
+        let types: Vec<&str> = links.iter().map(|l| l.artifact_type).collect();
+        assert_eq!(types, vec!["github_pr", "gitlab_mr", "gitea_pr"]);
+
+        assert_eq!(links[0].data["repo"], "todoki");
+        assert_eq!(links[0].data["number"], 12);
+        assert_eq!(links[1].data["host"], "gitlab.example.com");
+        assert_eq!(links[1].data["project"], "infra/tools/cli");
+        assert_eq!(links[1].data["number"], 7);
+        assert_eq!(links[2].data["owner"], "team");
+        assert_eq!(links[2].data["url"], "https://git.example.org/team/app/pulls/3");
+    }
+
+    #[test]
+    fn test_detect_nothing() {
+        assert!(detect("no links here, only https://github.com/Kilerd/todoki").is_empty());
+    }
+}
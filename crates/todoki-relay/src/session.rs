@@ -1,14 +1,43 @@
-use std::path::Path;
 use std::process::Stdio;
 use std::sync::Arc;
+use std::time::Duration;
 
 use agent_client_protocol::RequestPermissionOutcome;
-use tokio::process::{Child, Command};
+use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
 use tokio::sync::{mpsc, oneshot, Mutex};
 
 use crate::acp::{spawn_acp_session, AcpHandle};
+use crate::commits::CommitWatcher;
+use crate::config::{
+    CommitSettings, HealthSettings, OutputSettings, PermissionSettings, PreflightSettings,
+    PreviewSettings, SimulateSettings, UsageSettings,
+};
+use crate::event_bus_client::{http_base_url, EventBusClient};
+use crate::flow_control::{FlowControl, OutputControl};
+use crate::health::{HealthChange, ProbeTracker};
+use crate::offline_buffer::OfflineBuffer;
+use crate::preflight::{self, SpawnError};
+use crate::process::ProcessHandle;
+use crate::pty::{PtyChild, PtyHandle};
 use crate::relay::RelayOutput;
-use todoki_protocol::{SendInputParams, SpawnSessionParams, SpawnSessionResult};
+use crate::simulate::SimulatedHandle;
+use crate::usage::UsageSampler;
+use todoki_protocol::event_bus::{
+    AgentHealthyData, AgentSessionExitedData, AgentUnhealthyData, BuiltinEvent, SpawnErrorCode,
+};
+use todoki_protocol::{
+    PermissionFallback, SendInputParams, SessionMode, SpawnSessionParams, SpawnSessionResult,
+};
+
+/// How often plain and PTY processes are checked for exiting on their own
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Holds the relay's own token, which agent processes must not inherit;
+/// they get a scoped token with the spawn request instead
+pub(crate) const RELAY_TOKEN_ENV: &str = "TODOKI_RELAY_TOKEN";
+
+/// Server address for agents calling the API with their scoped token
+const API_URL_ENV: &str = "TODOKI_API_URL";
 
 /// Manages a single local agent session (subprocess).
 /// Only one session can be active at a time.
@@ -18,16 +47,96 @@ pub struct SessionManager {
     safe_paths: Vec<String>,
     server_url: String,
     token: String,
+    output: OutputControl,
+    health: HealthSettings,
+    usage: UsageSettings,
+    permissions: PermissionSettings,
+    preview: PreviewSettings,
+    preflight: PreflightSettings,
+    simulate: SimulateSettings,
+    commits: CommitSettings,
+    /// Fallback pushed by the server on registration; replaces `permissions`
+    server_permission_fallback: Arc<std::sync::RwLock<Option<PermissionFallback>>>,
 }
 
 struct ActiveSession {
     session_id: String,
-    child: Child,
-    acp_handle: AcpHandle,
+    agent_id: String,
+    child: AgentProcess,
+    usage: UsageSampler,
+    backend: SessionBackend,
+    /// Reports the commits made in the workdir when the session ends
+    commits: Option<CommitWatcher>,
     /// Sender to signal that the session should be terminated
     kill_tx: Option<oneshot::Sender<()>>,
 }
 
+/// What drives a session's process
+#[derive(Clone)]
+enum SessionBackend {
+    Acp(AcpHandle),
+    Process(ProcessHandle),
+    Pty(PtyHandle),
+    Simulated(SimulatedHandle),
+}
+
+/// The OS process behind a session
+enum AgentProcess {
+    Child(Child),
+    Pty(PtyChild),
+    /// No process; ends when killed
+    Simulated,
+}
+
+/// How a session's process ended
+struct ExitInfo {
+    success: bool,
+    code: Option<i32>,
+}
+
+impl AgentProcess {
+    fn try_wait(&mut self) -> Option<ExitInfo> {
+        match self {
+            AgentProcess::Child(child) => child.try_wait().ok().flatten().map(|s| ExitInfo {
+                success: s.success(),
+                code: s.code(),
+            }),
+            AgentProcess::Pty(child) => child.try_wait().ok().flatten().map(|s| ExitInfo {
+                success: s.success(),
+                code: i32::try_from(s.exit_code()).ok(),
+            }),
+            AgentProcess::Simulated => None,
+        }
+    }
+
+    async fn kill_and_wait(&mut self) -> Option<ExitInfo> {
+        match self {
+            AgentProcess::Child(child) => {
+                let _ = child.kill().await;
+                child.wait().await.ok().map(|s| ExitInfo {
+                    success: s.success(),
+                    code: s.code(),
+                })
+            }
+            AgentProcess::Pty(child) => {
+                let _ = child.kill();
+                // portable-pty only offers a blocking wait; poll instead
+                for _ in 0..100 {
+                    if let Some(exit) = self.try_wait() {
+                        return Some(exit);
+                    }
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+                None
+            }
+            AgentProcess::Simulated => Some(ExitInfo {
+                success: true,
+                code: Some(0),
+            }),
+        }
+    }
+}
+
 impl SessionManager {
     pub fn new(
         output_tx: mpsc::Sender<RelayOutput>,
@@ -41,16 +150,105 @@ impl SessionManager {
             safe_paths,
             server_url,
             token,
+            output: OutputControl::default(),
+            health: HealthSettings::default(),
+            usage: UsageSettings::default(),
+            permissions: PermissionSettings::default(),
+            preview: PreviewSettings::default(),
+            preflight: PreflightSettings::default(),
+            simulate: SimulateSettings::default(),
+            commits: CommitSettings::default(),
+            server_permission_fallback: Arc::new(std::sync::RwLock::new(None)),
         }
     }
 
+    /// Buffer undelivered session events on disk
+    pub fn with_offline_buffer(mut self, buffer: Option<Arc<OfflineBuffer>>) -> Self {
+        self.output.offline = buffer;
+        self
+    }
+
+    /// Batch output with `settings` and charge it against the relay-wide
+    /// `flow` budget
+    pub fn with_flow_control(mut self, settings: OutputSettings, flow: Arc<FlowControl>) -> Self {
+        self.output.settings = settings;
+        self.output.flow = flow;
+        self
+    }
+
+    /// Probe running ACP sessions with `settings`
+    pub fn with_health_probes(mut self, settings: HealthSettings) -> Self {
+        self.health = settings;
+        self
+    }
+
+    /// Sample resource usage of running sessions with `settings`
+    pub fn with_usage_sampling(mut self, settings: UsageSettings) -> Self {
+        self.usage = settings;
+        self
+    }
+
+    /// Resolve unanswered permission requests with `settings`
+    pub fn with_permission_fallback(mut self, settings: PermissionSettings) -> Self {
+        self.permissions = settings;
+        self
+    }
+
+    /// Preview shell commands awaiting permission with `settings`
+    pub fn with_command_preview(mut self, settings: PreviewSettings) -> Self {
+        self.preview = settings;
+        self
+    }
+
+    /// Check workdir and command with `settings` before spawning
+    pub fn with_preflight(mut self, settings: PreflightSettings) -> Self {
+        self.preflight = settings;
+        self
+    }
+
+    /// Fake sessions with `settings` when simulation is enabled
+    pub fn with_simulation(mut self, settings: SimulateSettings) -> Self {
+        self.simulate = settings;
+        self
+    }
+
+    /// Report commits made in session workdirs with `settings`
+    pub fn with_commit_detection(mut self, settings: CommitSettings) -> Self {
+        self.commits = settings;
+        self
+    }
+
+    /// Apply the fallback pushed by the server to sessions spawned from now
+    /// on (`None` = back to the configured one)
+    pub fn set_server_permission_fallback(&self, fallback: Option<PermissionFallback>) {
+        *self
+            .server_permission_fallback
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = fallback;
+    }
+
+    fn permission_fallback(&self, command: &str) -> PermissionFallback {
+        let pushed = *self
+            .server_permission_fallback
+            .read()
+            .unwrap_or_else(|e| e.into_inner());
+        pushed.unwrap_or_else(|| self.permissions.for_command(command))
+    }
+
     /// Spawn a new session
-    pub async fn spawn(&self, params: SpawnSessionParams) -> anyhow::Result<SpawnSessionResult> {
+    pub async fn spawn(
+        &self,
+        mut params: SpawnSessionParams,
+    ) -> anyhow::Result<SpawnSessionResult> {
         // Single-task mode: only one session at a time
         {
             let session = self.active_session.lock().await;
             if session.is_some() {
-                anyhow::bail!("relay busy: already running session");
+                return Err(SpawnError::new(
+                    SpawnErrorCode::RelayBusy,
+                    "relay busy: already running session",
+                )
+                .into());
             }
         }
 
@@ -60,19 +258,57 @@ impl SessionManager {
             command = %params.command,
             workdir = %params.workdir,
             args = ?params.args,
+            mode = ?params.mode,
             "spawn called"
         );
 
         // Validate workdir against safe paths
         if !self.is_path_safe(&params.workdir) {
             tracing::error!(workdir = %params.workdir, "workdir not in safe paths");
-            anyhow::bail!("workdir not in safe paths: {}", params.workdir);
+            return Err(SpawnError::new(
+                SpawnErrorCode::WorkdirNotAllowed,
+                format!("workdir not in safe paths: {}", params.workdir),
+            )
+            .into());
+        }
+
+        // Nothing is installed or run, so preflight and setup do not apply
+        if self.simulate.enabled {
+            tracing::info!(session_id = %params.session_id, "starting simulated session");
+            let handle = SimulatedHandle::new(
+                self.output_tx.clone(),
+                self.output.clone(),
+                self.simulate.clone(),
+                params.agent_id.clone(),
+                params.session_id.clone(),
+            );
+            self.register_session(
+                &params,
+                AgentProcess::Simulated,
+                SessionBackend::Simulated(handle),
+                UsageSampler::new(0),
+            )
+            .await;
+            return Ok(SpawnSessionResult { pid: 0 });
         }
 
         let workdir = expand_tilde(&params.workdir);
-        if !Path::new(&workdir).exists() {
-            tracing::error!(workdir = %workdir, "workdir does not exist");
-            anyhow::bail!("workdir does not exist: {}", workdir);
+        let path = params
+            .env
+            .get("PATH")
+            .cloned()
+            .or_else(|| std::env::var("PATH").ok());
+        let checked = preflight::check(&self.preflight, &workdir, &params.command, path.as_deref());
+        if let Err(e) = checked {
+            tracing::error!(workdir = %workdir, command = %params.command, code = ?e.code, error = %e, "spawn preflight failed");
+            return Err(e.into());
+        }
+
+        if !self.server_url.is_empty() {
+            params
+                .env
+                .entry(API_URL_ENV.to_string())
+                .or_insert_with(|| http_base_url(&self.server_url));
         }
 
         tracing::debug!(
@@ -89,7 +325,11 @@ impl SessionManager {
             tracing::debug!(setup_path = %setup_path, "writing setup script");
 
             if let Err(e) = std::fs::write(&setup_path, setup_script) {
-                anyhow::bail!("failed to write setup script: {}", e);
+                return Err(SpawnError::new(
+                    SpawnErrorCode::SetupFailed,
+                    format!("failed to write setup script: {}", e),
+                )
+                .into());
             }
 
             let status = Command::new("bash")
@@ -107,14 +347,41 @@ impl SessionManager {
                     tracing::debug!("setup script completed successfully");
                 }
                 Ok(s) => {
-                    anyhow::bail!("setup script failed with exit code: {:?}", s.code());
+                    return Err(SpawnError::new(
+                        SpawnErrorCode::SetupFailed,
+                        format!("setup script failed with exit code: {:?}", s.code()),
+                    )
+                    .into());
                 }
                 Err(e) => {
-                    anyhow::bail!("failed to run setup script: {}", e);
+                    return Err(SpawnError::new(
+                        SpawnErrorCode::SetupFailed,
+                        format!("failed to run setup script: {}", e),
+                    )
+                    .into());
                 }
             }
         }
 
+        if params.mode == SessionMode::Pty {
+            let (handle, child) = PtyHandle::spawn(
+                self.output_tx.clone(),
+                self.output.flow.clone(),
+                &params,
+                &workdir,
+            )?;
+            let pid = child.process_id().unwrap_or(0);
+            tracing::info!(pid = pid, command = %params.command, "pty process spawned");
+            self.register_session(
+                &params,
+                AgentProcess::Pty(child),
+                SessionBackend::Pty(handle),
+                UsageSampler::new(pid),
+            )
+            .await;
+            return Ok(SpawnSessionResult { pid });
+        }
+
         let mut command = Command::new(&params.command);
         command
             .args(&params.args)
@@ -123,7 +390,8 @@ impl SessionManager {
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             // Inherit all environment variables from parent process
-            .envs(std::env::vars());
+            .envs(std::env::vars())
+            .env_remove(RELAY_TOKEN_ENV);
 
         // Override with any custom env vars from params
         for (key, value) in &params.env {
@@ -145,15 +413,126 @@ impl SessionManager {
         };
         let pid = child.id().unwrap_or(0);
         tracing::info!(pid = pid, command = %params.command, "child process spawned");
+        // Start measuring before the ACP handshake so its CPU time counts
+        let usage = UsageSampler::new(pid);
 
         let stdout = child.stdout.take();
         let stderr = child.stderr.take();
         let stdin = child.stdin.take();
 
+        let stdout = stdout.ok_or_else(|| anyhow::anyhow!("no stdout for agent process"))?;
+        let stdin = stdin.ok_or_else(|| anyhow::anyhow!("no stdin for agent process"))?;
+
+        let backend = match params.mode {
+            SessionMode::Acp => {
+                SessionBackend::Acp(self.start_acp(&params, &workdir, stdout, stdin, stderr).await?)
+            }
+            SessionMode::Pty => unreachable!("pty sessions are spawned above"),
+            SessionMode::Process => {
+                tracing::info!(session_id = %params.session_id, "starting plain process session");
+                SessionBackend::Process(ProcessHandle::start(
+                    self.output_tx.clone(),
+                    self.output.flow.clone(),
+                    params.agent_id.clone(),
+                    params.session_id.clone(),
+                    stdout,
+                    stderr,
+                    stdin,
+                ))
+            }
+        };
+
+        self.register_session(&params, AgentProcess::Child(child), backend, usage)
+            .await;
+
+        Ok(SpawnSessionResult { pid })
+    }
+
+    /// Store the spawned session and start its exit watcher
+    async fn register_session(
+        &self,
+        params: &SpawnSessionParams,
+        child: AgentProcess,
+        backend: SessionBackend,
+        usage: UsageSampler,
+    ) {
+        // ACP and simulated sessions end when their prompt completes; plain
+        // and PTY processes also end the session by exiting
+        let watch_exit = matches!(backend, SessionBackend::Process(_) | SessionBackend::Pty(_));
+        let probe = match &backend {
+            SessionBackend::Acp(handle) if self.health.enabled => Some(handle.clone()),
+            _ => None,
+        };
+
+        // Simulated sessions never touch their workdir
+        let commits = match &backend {
+            SessionBackend::Simulated(_) => None,
+            _ => self.watch_commits(params).await,
+        };
+
+        // Create kill channel for termination signaling
+        let (kill_tx, kill_rx) = oneshot::channel();
+
+        let session = ActiveSession {
+            session_id: params.session_id.clone(),
+            agent_id: params.agent_id.clone(),
+            child,
+            usage,
+            backend,
+            commits,
+            kill_tx: Some(kill_tx),
+        };
+
+        // Store session
+        {
+            let mut active = self.active_session.lock().await;
+            *active = Some(session);
+        }
+
+        self.spawn_exit_watcher(params.session_id.clone(), kill_rx, watch_exit);
+        if let Some(handle) = probe {
+            self.spawn_health_probe(params.session_id.clone(), handle);
+        }
+        if self.usage.enabled {
+            self.spawn_usage_sampler(params.session_id.clone());
+        }
+
+        tracing::info!(session_id = %params.session_id, "spawn completed successfully");
+    }
+
+    /// Start watching the session's workdir for commits, if it is in a git
+    /// repository
+    async fn watch_commits(&self, params: &SpawnSessionParams) -> Option<CommitWatcher> {
+        let agent_uuid = uuid::Uuid::parse_str(&params.agent_id).unwrap_or_default();
+        let task_uuid = params
+            .task_id
+            .as_ref()
+            .and_then(|id| uuid::Uuid::parse_str(id).ok());
+        let mut event_bus = EventBusClient::new(&self.server_url, &self.token, agent_uuid)
+            .with_offline_buffer(self.output.offline.clone());
+        event_bus.set_task_id(task_uuid);
+
+        CommitWatcher::start(
+            &self.commits,
+            &expand_tilde(&params.workdir),
+            params.agent_id.clone(),
+            params.session_id.clone(),
+            event_bus,
+        )
+        .await
+    }
+
+    /// Initialize ACP over the process's stdio, logging its stderr
+    async fn start_acp(
+        &self,
+        params: &SpawnSessionParams,
+        workdir: &str,
+        stdout: ChildStdout,
+        stdin: ChildStdin,
+        stderr: Option<ChildStderr>,
+    ) -> anyhow::Result<AcpHandle> {
         // Initialize ACP session
         tracing::debug!(session_id = %params.session_id, "initializing ACP session");
-        let stdout = stdout.ok_or_else(|| anyhow::anyhow!("no stdout for ACP"))?;
-        let stdin = stdin.ok_or_else(|| anyhow::anyhow!("no stdin for ACP"))?;
 
         // Spawn stderr reader to capture agent errors
         if let Some(stderr) = stderr {
@@ -178,12 +557,16 @@ impl SessionManager {
             self.output_tx.clone(),
             params.agent_id.clone(),
             params.session_id.clone(),
-            workdir.clone(),
+            workdir.to_string(),
             stdout,
             stdin,
             self.server_url.clone(),
             self.token.clone(),
+            self.output.clone(),
             params.task_id.clone(),
+            params.task.clone(),
+            self.permission_fallback(&params.command),
+            self.preview.clone(),
         )
         .await
         {
@@ -201,34 +584,11 @@ impl SessionManager {
         tracing::info!(
             session_id = %params.session_id,
             acp_session_id = %acp_handle.acp_session_id,
+            protocol_version = acp_handle.protocol.version,
             "ACP session initialized successfully"
         );
 
-        // Create kill channel for termination signaling
-        let (kill_tx, kill_rx) = oneshot::channel();
-
-        let session = ActiveSession {
-            session_id: params.session_id.clone(),
-            child,
-            acp_handle,
-            kill_tx: Some(kill_tx),
-        };
-
-        // Store session
-        {
-            let mut active = self.active_session.lock().await;
-            *active = Some(session);
-        }
-
-        // Spawn exit watcher
-        self.spawn_exit_watcher(params.session_id.clone(), kill_rx);
-
-        tracing::info!(
-            session_id = %params.session_id,
-            pid = pid,
-            "spawn completed successfully"
-        );
-        Ok(SpawnSessionResult { pid })
+        Ok(acp_handle)
     }
 
     /// Send input to a session.
@@ -240,25 +600,40 @@ impl SessionManager {
             "send_input called"
         );
 
-        // Get the acp_handle without removing the session
+        // Get the backend without removing the session
         // Session cleanup is handled by exit_watcher when the process exits
-        let acp_handle = {
+        let backend = {
             let active = self.active_session.lock().await;
             let session = active
                 .as_ref()
                 .filter(|s| s.session_id == params.session_id)
                 .ok_or_else(|| anyhow::anyhow!("session not found: {}", params.session_id))?;
-            session.acp_handle.clone()
+            session.backend.clone()
         };
 
-        tracing::debug!(
-            session_id = %params.session_id,
-            acp_session_id = %acp_handle.acp_session_id,
-            "forwarding input to ACP"
-        );
-
-        let done_rx = acp_handle.prompt(params.input).await?;
-        tracing::debug!(session_id = %params.session_id, "input sent to ACP");
+        let done_rx = match backend {
+            SessionBackend::Acp(acp_handle) => {
+                tracing::debug!(
+                    session_id = %params.session_id,
+                    acp_session_id = %acp_handle.acp_session_id,
+                    "forwarding input to ACP"
+                );
+                acp_handle.prompt(params.input).await?
+            }
+            SessionBackend::Simulated(handle) => handle.prompt(params.input).await?,
+            SessionBackend::Process(handle) => {
+                // Plain processes keep running until they exit on their own
+                handle.write_input(&params.input).await?;
+                tracing::debug!(session_id = %params.session_id, "input written to stdin");
+                return Ok(());
+            }
+            SessionBackend::Pty(handle) => {
+                // Terminal input is raw keystrokes, written as-is
+                handle.write_input(params.input).await?;
+                return Ok(());
+            }
+        };
+        tracing::debug!(session_id = %params.session_id, "input sent to agent");
 
         // Spawn a task to wait for this specific prompt completion and then terminate the process
         let session_id = params.session_id.clone();
@@ -315,7 +690,14 @@ impl SessionManager {
                 .as_ref()
                 .filter(|s| s.session_id == session_id)
                 .ok_or_else(|| anyhow::anyhow!("session not found: {}", session_id))?;
-            session.acp_handle.clone()
+            match &session.backend {
+                SessionBackend::Acp(handle) => handle.clone(),
+                SessionBackend::Process(_)
+                | SessionBackend::Pty(_)
+                | SessionBackend::Simulated(_) => {
+                    anyhow::bail!("session {} has no permission requests", session_id)
+                }
+            }
         };
 
         tracing::debug!(
@@ -336,15 +718,40 @@ impl SessionManager {
     }
 
     /// Cancel current operation in a session
+    /// (a PTY gets Ctrl-C; a plain process has no operation besides itself,
+    /// so it is stopped)
     pub async fn cancel(&self, session_id: &str) -> anyhow::Result<()> {
+        let mut active = self.active_session.lock().await;
+        let session = active
+            .as_mut()
+            .filter(|s| s.session_id == session_id)
+            .ok_or_else(|| anyhow::anyhow!("session not found: {}", session_id))?;
+
+        match &session.backend {
+            SessionBackend::Acp(handle) => handle.cancel().await?,
+            SessionBackend::Pty(handle) => handle.interrupt().await?,
+            SessionBackend::Simulated(handle) => handle.cancel().await?,
+            SessionBackend::Process(_) => {
+                if let Some(kill_tx) = session.kill_tx.take() {
+                    let _ = kill_tx.send(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Resize the terminal of a PTY session
+    pub async fn resize(&self, session_id: &str, cols: u16, rows: u16) -> anyhow::Result<()> {
         let active = self.active_session.lock().await;
         let session = active
             .as_ref()
             .filter(|s| s.session_id == session_id)
             .ok_or_else(|| anyhow::anyhow!("session not found: {}", session_id))?;
 
-        session.acp_handle.cancel().await?;
-        Ok(())
+        match &session.backend {
+            SessionBackend::Pty(handle) => handle.resize(cols, rows),
+            _ => anyhow::bail!("session {} has no terminal", session_id),
+        }
     }
 
     /// Stop a session by signaling the kill channel
@@ -370,7 +777,12 @@ impl SessionManager {
         }
     }
 
-    fn spawn_exit_watcher(&self, session_id: String, kill_rx: oneshot::Receiver<()>) {
+    fn spawn_exit_watcher(
+        &self,
+        session_id: String,
+        mut kill_rx: oneshot::Receiver<()>,
+        watch_exit: bool,
+    ) {
         let output_tx = self.output_tx.clone();
         let active_session = self.active_session.clone();
 
@@ -378,33 +790,45 @@ impl SessionManager {
         tokio::spawn(async move {
             tracing::debug!(session_id = %session_id, "exit watcher waiting for kill signal");
 
-            // Wait for kill signal
-            let _ = kill_rx.await;
-
-            tracing::debug!(session_id = %session_id, "kill signal received, terminating process");
+            // Wait for kill signal, or for the process to exit by itself
+            let exited = if watch_exit {
+                Self::wait_for_exit(&active_session, &session_id, &mut kill_rx).await
+            } else {
+                let _ = kill_rx.await;
+                None
+            };
 
             // Take the session and kill the process
-            let exit_status = {
+            let (exit_status, ended) = if let Some((exit, session)) = exited {
+                (Some(exit), Some(session))
+            } else {
+                tracing::debug!(session_id = %session_id, "kill signal received, terminating process");
                 let mut active = active_session.lock().await;
                 if let Some(mut session) = active.take() {
                     if session.session_id == session_id {
-                        // Kill the process
-                        let _ = session.child.kill().await;
-                        // Wait for it to exit
-                        session.child.wait().await.ok()
+                        // Last sample while the process is still there
+                        session.usage.sample();
+                        // Kill the process and wait for it to exit
+                        (session.child.kill_and_wait().await, Some(session))
                     } else {
                         // Put it back if it's not our session (shouldn't happen)
                         *active = Some(session);
-                        None
+                        (None, None)
                     }
                 } else {
-                    None
+                    (None, None)
                 }
             };
+            let usage = ended.as_ref().and_then(|session| session.usage.latest());
+
+            // Before the exit, so the artifacts are there when the task moves on
+            if let Some(watcher) = ended.as_ref().and_then(|session| session.commits.as_ref()) {
+                watcher.report(&output_tx).await;
+            }
 
             let (status, exit_code) = match &exit_status {
-                Some(s) if s.success() => ("completed", s.code()),
-                Some(s) => ("failed", s.code()),
+                Some(exit) if exit.success => ("completed", exit.code),
+                Some(exit) => ("failed", exit.code),
                 None => ("failed", None),
             };
 
@@ -422,13 +846,162 @@ impl SessionManager {
                     "session_id": session_id,
                     "status": status,
                     "exit_code": exit_code,
+                    "usage": usage,
                 }),
             };
 
             let _ = output_tx.send(msg).await;
+
+            if let Some(session) = ended {
+                let exited = BuiltinEvent::AgentSessionExited(AgentSessionExitedData {
+                    agent_id: session.agent_id,
+                    session_id,
+                    exit_code,
+                    status: Some(status.to_string()),
+                    usage,
+                });
+                let (kind, data) = exited.into_parts();
+                let _ = output_tx.send(RelayOutput::EmitEvent { kind, data }).await;
+            }
+        });
+    }
+
+    /// Report the resource usage of a session periodically until it ends
+    fn spawn_usage_sampler(&self, session_id: String) {
+        let output_tx = self.output_tx.clone();
+        let active_session = self.active_session.clone();
+        let interval = self.usage.sample_interval();
+
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+
+                let usage = {
+                    let mut active = active_session.lock().await;
+                    let Some(session) = active.as_mut().filter(|s| s.session_id == session_id)
+                    else {
+                        return;
+                    };
+                    session.usage.sample()
+                };
+                // The process may be gone before the exit watcher notices
+                let Some(usage) = usage else {
+                    continue;
+                };
+
+                let msg = RelayOutput::EmitEvent {
+                    kind: "relay.session_status".to_string(),
+                    data: serde_json::json!({
+                        "session_id": session_id,
+                        "status": "running",
+                        "usage": usage,
+                    }),
+                };
+                if output_tx.send(msg).await.is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Probe an ACP session until it ends, reporting health changes
+    fn spawn_health_probe(&self, session_id: String, handle: AcpHandle) {
+        let output_tx = self.output_tx.clone();
+        let active_session = self.active_session.clone();
+        let settings = self.health.clone();
+
+        tokio::spawn(async move {
+            let mut tracker = ProbeTracker::new(settings.unhealthy_after);
+            loop {
+                tokio::time::sleep(settings.probe_interval()).await;
+
+                // Stop once the session is gone; a dead process fails the
+                // probe without pinging
+                let (agent_id, exited) = {
+                    let mut active = active_session.lock().await;
+                    let Some(session) = active.as_mut().filter(|s| s.session_id == session_id)
+                    else {
+                        return;
+                    };
+                    (session.agent_id.clone(), session.child.try_wait().is_some())
+                };
+                let result = if exited {
+                    Err("agent process exited".to_string())
+                } else {
+                    match tokio::time::timeout(settings.probe_timeout(), handle.ping()).await {
+                        Ok(Ok(())) => Ok(()),
+                        Ok(Err(e)) => Err(e.to_string()),
+                        Err(_) => Err(format!(
+                            "ping timed out after {}s",
+                            settings.probe_timeout().as_secs()
+                        )),
+                    }
+                };
+
+                let event = match result {
+                    Ok(()) => match tracker.record_success() {
+                        Some(HealthChange::Recovered) => {
+                            tracing::info!(
+                                session_id = %session_id,
+                                "agent answering probes again"
+                            );
+                            BuiltinEvent::AgentHealthy(AgentHealthyData {
+                                agent_id,
+                                session_id: session_id.clone(),
+                            })
+                        }
+                        _ => continue,
+                    },
+                    Err(error) => {
+                        tracing::warn!(
+                            session_id = %session_id,
+                            error = %error,
+                            "agent probe failed"
+                        );
+                        match tracker.record_failure() {
+                            Some(HealthChange::Unhealthy(consecutive_failures)) => {
+                                BuiltinEvent::AgentUnhealthy(AgentUnhealthyData {
+                                    agent_id,
+                                    session_id: session_id.clone(),
+                                    consecutive_failures,
+                                    error,
+                                })
+                            }
+                            _ => continue,
+                        }
+                    }
+                };
+                let (kind, data) = event.into_parts();
+                if output_tx.send(RelayOutput::EmitEvent { kind, data }).await.is_err() {
+                    return;
+                }
+            }
         });
     }
 
+    /// Poll the session's process until it exits or a kill signal arrives.
+    /// Returns the exit status and the removed session on a natural exit.
+    async fn wait_for_exit(
+        active_session: &Mutex<Option<ActiveSession>>,
+        session_id: &str,
+        kill_rx: &mut oneshot::Receiver<()>,
+    ) -> Option<(ExitInfo, ActiveSession)> {
+        loop {
+            tokio::select! {
+                _ = &mut *kill_rx => return None,
+                _ = tokio::time::sleep(EXIT_POLL_INTERVAL) => {
+                    let mut active = active_session.lock().await;
+                    let Some(session) = active.as_mut().filter(|s| s.session_id == session_id) else {
+                        return None;
+                    };
+                    if let Some(exit) = session.child.try_wait() {
+                        return active.take().map(|session| (exit, session));
+                    }
+                }
+            }
+        }
+    }
+
     pub(crate) fn is_path_safe(&self, path: &str) -> bool {
         if self.safe_paths.is_empty() {
             return true; // No restrictions if no safe paths configured
@@ -568,4 +1141,43 @@ mod tests {
         assert!(!manager.is_path_safe("/allowed/../etc/passwd"));
         assert!(!manager.is_path_safe("/allowed/sub/../../etc"));
     }
+
+    #[test]
+    fn test_permission_fallback_resolution() {
+        use todoki_protocol::PermissionFallbackAction;
+
+        let fallback = |action| PermissionFallback {
+            action,
+            timeout_secs: 60,
+        };
+        let (manager, _rx) = test_session_manager(vec![]);
+        let manager = manager.with_permission_fallback(PermissionSettings {
+            fallback: fallback(PermissionFallbackAction::Deny),
+            agents: [(
+                "claude-code-acp".to_string(),
+                fallback(PermissionFallbackAction::Allow),
+            )]
+            .into(),
+        });
+        assert_eq!(
+            manager.permission_fallback("/usr/bin/claude-code-acp").action,
+            PermissionFallbackAction::Allow
+        );
+        assert_eq!(
+            manager.permission_fallback("codex").action,
+            PermissionFallbackAction::Deny
+        );
+
+        // A server-pushed policy wins over per-agent overrides
+        manager.set_server_permission_fallback(Some(fallback(PermissionFallbackAction::Cancel)));
+        assert_eq!(
+            manager.permission_fallback("claude-code-acp").action,
+            PermissionFallbackAction::Cancel
+        );
+        manager.set_server_permission_fallback(None);
+        assert_eq!(
+            manager.permission_fallback("codex").action,
+            PermissionFallbackAction::Deny
+        );
+    }
 }
@@ -0,0 +1,326 @@
+//! Simulated agent sessions
+//!
+//! With `--simulate` the relay answers spawn requests without running an
+//! agent: each prompt gets a canned stream of thinking, tool calls and reply
+//! chunks, paced like a real agent and shaped like the output of an ACP
+//! session, followed by `relay.prompt_completed`. Useful for load testing
+//! the server's event pipeline and for demos on machines without agents.
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde_json::Value;
+use tokio::sync::{mpsc, oneshot, Mutex};
+
+use crate::config::SimulateSettings;
+use crate::flow_control::OutputControl;
+use crate::output_buffer::OutputBuffer;
+use crate::relay::RelayOutput;
+use todoki_protocol::event_bus::{AgentOutputBatchData, BuiltinEvent};
+use todoki_protocol::OutputStreamKind;
+
+const THOUGHTS: &[&str] = &[
+    "Let me look at how the relevant code is organized first.",
+    "The change touches a few call sites; I should check each of them.",
+    "Tests cover most of this, so I can verify the fix by running them.",
+];
+
+const TOOLS: &[(&str, &str, &str)] = &[
+    ("Read src/lib.rs", "read", "pub mod config;\npub mod handler;\n"),
+    ("Search for usages", "search", "src/handler.rs:42\nsrc/config.rs:17\n"),
+    ("Edit src/handler.rs", "edit", "Applied 1 edit"),
+    ("Run cargo test", "execute", "test result: ok. 24 passed; 0 failed\n"),
+];
+
+const REPLY: &str = "I went through the affected code and made the change. The handler now \
+validates its input before touching the database, and the existing tests pass. Let me know \
+if you want the same treatment for the other endpoints.";
+
+/// One piece of simulated output
+#[derive(Debug, Clone, PartialEq)]
+struct Chunk {
+    stream: OutputStreamKind,
+    message: Value,
+}
+
+/// Handle for prompting a simulated session
+#[derive(Clone)]
+pub struct SimulatedHandle {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    output_tx: mpsc::Sender<RelayOutput>,
+    output: OutputControl,
+    settings: SimulateSettings,
+    agent_id: String,
+    session_id: String,
+    seq_counter: AtomicI64,
+    rng: Mutex<StdRng>,
+    /// Cancels the prompt being played, if any
+    cancel_tx: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+impl SimulatedHandle {
+    pub fn new(
+        output_tx: mpsc::Sender<RelayOutput>,
+        output: OutputControl,
+        settings: SimulateSettings,
+        agent_id: String,
+        session_id: String,
+    ) -> Self {
+        let rng = match settings.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Self {
+            inner: Arc::new(Inner {
+                output_tx,
+                output,
+                settings,
+                agent_id,
+                session_id,
+                // Same seq scheme as real sessions
+                seq_counter: AtomicI64::new(Utc::now().timestamp_nanos_opt().unwrap_or(0)),
+                rng: Mutex::new(rng),
+                cancel_tx: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Play the canned output for `input`; the receiver fires once
+    /// `relay.prompt_completed` is sent
+    pub async fn prompt(&self, input: String) -> anyhow::Result<oneshot::Receiver<()>> {
+        let (done_tx, done_rx) = oneshot::channel();
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        *self.inner.cancel_tx.lock().await = Some(cancel_tx);
+
+        let inner = self.inner.clone();
+        tokio::spawn(async move {
+            let result = tokio::select! {
+                result = inner.play(&input) => result,
+                _ = cancel_rx => Ok(()),
+            };
+            let msg = RelayOutput::EmitEvent {
+                kind: "relay.prompt_completed".to_string(),
+                data: serde_json::json!({
+                    "session_id": inner.session_id,
+                    "success": result.is_ok(),
+                    "error": result.err(),
+                }),
+            };
+            let _ = inner.output_tx.send(msg).await;
+            let _ = done_tx.send(());
+        });
+
+        Ok(done_rx)
+    }
+
+    /// Stop the prompt being played; it completes right away
+    pub async fn cancel(&self) -> anyhow::Result<()> {
+        if let Some(cancel_tx) = self.inner.cancel_tx.lock().await.take() {
+            let _ = cancel_tx.send(());
+        }
+        Ok(())
+    }
+}
+
+impl Inner {
+    async fn play(&self, input: &str) -> Result<(), String> {
+        let (chunks, fail) = {
+            let mut rng = self.rng.lock().await;
+            let chunks = script(&self.settings, input, &mut *rng);
+            (chunks, rng.gen_bool(self.settings.failure_rate.clamp(0.0, 1.0)))
+        };
+        // Fail halfway through, as an agent running out of credits would
+        let fail_at = fail.then_some(chunks.len() / 2);
+
+        let mut batch = OutputBuffer::new(self.output.settings.clone());
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            if fail_at == Some(i) {
+                self.flush(&mut batch).await;
+                return Err("simulated agent failure".to_string());
+            }
+            self.pause().await;
+            if batch.stream() != Some(&chunk.stream) {
+                self.flush(&mut batch).await;
+                batch.set_stream(Some(chunk.stream.clone()));
+            }
+            let message = chunk.message.to_string();
+            batch.push(message.clone());
+            self.emit(chunk.stream, message).await;
+        }
+        self.flush(&mut batch).await;
+        Ok(())
+    }
+
+    /// Wait the chunk interval, give or take the configured jitter
+    async fn pause(&self) {
+        let jitter = self.settings.jitter_ms;
+        let offset = if jitter == 0 {
+            0
+        } else {
+            self.rng.lock().await.gen_range(0..=jitter * 2)
+        };
+        let ms = (self.settings.chunk_interval_ms + offset).saturating_sub(jitter);
+        tokio::time::sleep(Duration::from_millis(ms)).await;
+    }
+
+    async fn emit(&self, stream: OutputStreamKind, message: String) {
+        self.output.flow.acquire(message.len()).await;
+        let msg = RelayOutput::EmitEvent {
+            kind: "relay.agent_output".to_string(),
+            data: serde_json::json!({
+                "agent_id": self.agent_id,
+                "session_id": self.session_id,
+                "seq": self.seq_counter.fetch_add(1, Ordering::SeqCst),
+                "ts": Utc::now().timestamp_nanos_opt().unwrap_or(0),
+                "stream": stream,
+                "message": message,
+            }),
+        };
+        let _ = self.output_tx.send(msg).await;
+    }
+
+    /// Send the messages of one stream run as an output batch, like the ACP
+    /// sink does on stream changes
+    async fn flush(&self, batch: &mut OutputBuffer) {
+        let Some((stream, messages)) = batch.take() else {
+            return;
+        };
+        let event = BuiltinEvent::AgentOutputBatch(AgentOutputBatchData {
+            session_id: self.session_id.clone(),
+            stream,
+            messages,
+            ts: Utc::now().timestamp_nanos_opt().unwrap_or(0),
+        });
+        let (kind, data) = event.into_parts();
+        let _ = self.output_tx.send(RelayOutput::EmitEvent { kind, data }).await;
+    }
+}
+
+/// The output of one prompt: the prompt echoed back, a thought, the tool
+/// calls, then the reply split into chunks
+fn script(settings: &SimulateSettings, input: &str, rng: &mut impl Rng) -> Vec<Chunk> {
+    let mut chunks = vec![
+        text_chunk(OutputStreamKind::User, "user_message", input),
+        text_chunk(
+            OutputStreamKind::Thinking,
+            "agent_thought",
+            THOUGHTS[rng.gen_range(0..THOUGHTS.len())],
+        ),
+    ];
+
+    for i in 0..settings.tool_calls {
+        let (title, kind, output) = TOOLS[rng.gen_range(0..TOOLS.len())];
+        let id = format!("sim-tool-{}", i + 1);
+        chunks.push(Chunk {
+            stream: OutputStreamKind::ToolUse,
+            message: serde_json::json!({
+                "type": "tool_call",
+                "id": id,
+                "title": title,
+                "kind": kind,
+                "status": "in_progress",
+                "content": [],
+                "raw_input": { "simulated": true },
+                "raw_output": null,
+                "meta": null,
+            }),
+        });
+        chunks.push(Chunk {
+            stream: OutputStreamKind::ToolResult,
+            message: serde_json::json!({
+                "type": "tool_call_update",
+                "id": id,
+                "status": "completed",
+                "raw_output": output,
+            }),
+        });
+    }
+
+    for part in split_reply(REPLY, settings.message_chunks) {
+        chunks.push(text_chunk(OutputStreamKind::Assistant, "agent_message", &part));
+    }
+    chunks
+}
+
+fn text_chunk(stream: OutputStreamKind, kind: &str, text: &str) -> Chunk {
+    Chunk {
+        stream,
+        message: serde_json::json!({ "type": kind, "text": text, "chunk": true }),
+    }
+}
+
+/// Split `text` into about `parts` pieces at word boundaries
+fn split_reply(text: &str, parts: usize) -> Vec<String> {
+    let words: Vec<&str> = text.split_inclusive(' ').collect();
+    let per_part = words.len().div_ceil(parts.max(1));
+    words.chunks(per_part).map(|chunk| chunk.concat()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> SimulateSettings {
+        SimulateSettings {
+            enabled: true,
+            chunk_interval_ms: 0,
+            jitter_ms: 0,
+            message_chunks: 4,
+            tool_calls: 2,
+            failure_rate: 0.0,
+            seed: Some(7),
+        }
+    }
+
+    #[test]
+    fn test_split_reply() {
+        let parts = split_reply(REPLY, 4);
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts.concat(), REPLY);
+        assert_eq!(split_reply("one two", 10), vec!["one ", "two"]);
+        assert_eq!(split_reply("one two", 0), vec!["one two"]);
+    }
+
+    #[test]
+    fn test_script_is_seeded() {
+        let a = script(&settings(), "fix it", &mut StdRng::seed_from_u64(1));
+        let b = script(&settings(), "fix it", &mut StdRng::seed_from_u64(1));
+        assert_eq!(a, b);
+        // echo + thought + 2 tool calls with results + 4 reply chunks
+        assert_eq!(a.len(), 2 + 4 + 4);
+        assert_eq!(a[0].message["text"], "fix it");
+        assert_eq!(a.last().unwrap().stream, OutputStreamKind::Assistant);
+    }
+
+    #[tokio::test]
+    async fn test_prompt_streams_and_completes() {
+        let (tx, mut rx) = mpsc::channel(100);
+        let handle = SimulatedHandle::new(
+            tx,
+            OutputControl::default(),
+            settings(),
+            "agent-1".to_string(),
+            "session-1".to_string(),
+        );
+        handle.prompt("fix it".to_string()).await.unwrap().await.unwrap();
+
+        let mut kinds = Vec::new();
+        while let Ok(RelayOutput::EmitEvent { kind, data }) = rx.try_recv() {
+            assert_eq!(data["session_id"], "session-1");
+            if kind == "relay.prompt_completed" {
+                assert_eq!(data["success"], true);
+            }
+            kinds.push(kind);
+        }
+        assert_eq!(kinds.iter().filter(|k| *k == "relay.agent_output").count(), 10);
+        assert!(kinds.iter().any(|k| k == "agent.output_batch"));
+        assert_eq!(kinds.last().map(String::as_str), Some("relay.prompt_completed"));
+    }
+}
@@ -0,0 +1,180 @@
+use serde::Deserialize;
+use serde_json::Value;
+use todoki_protocol::event_bus::{
+    AgentFollowupTaskData, AgentSubtaskDoneData, AgentTaskCommentData, AgentTaskContext,
+    BuiltinEvent,
+};
+use todoki_protocol::AgentToolMethod;
+
+use crate::event_bus_client::EventBusClient;
+
+/// Todoki task tools exposed to an ACP agent as extension methods.
+///
+/// `todoki/get_task` is answered from the task snapshot sent with the spawn
+/// request; the remaining tools are forwarded to the server as `agent.*` events
+/// and applied there.
+#[derive(Clone)]
+pub struct TaskTools {
+    session_id: String,
+    task: Option<AgentTaskContext>,
+    event_bus: EventBusClient,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TaskToolError {
+    #[error("unknown method: {0}")]
+    UnknownMethod(String),
+    #[error("invalid params: {0}")]
+    InvalidParams(String),
+    #[error("session is not bound to a task")]
+    NoTask,
+    #[error("failed to reach server: {0}")]
+    Server(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct PostCommentParams {
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompleteSubtaskParams {
+    subtask: String,
+    #[serde(default)]
+    note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateFollowupTaskParams {
+    content: String,
+    #[serde(default)]
+    priority: i32,
+}
+
+impl TaskTools {
+    pub fn new(
+        session_id: String,
+        task: Option<AgentTaskContext>,
+        event_bus: EventBusClient,
+    ) -> Self {
+        Self {
+            session_id,
+            task,
+            event_bus,
+        }
+    }
+
+    /// Whether `method` is one of the todoki task tools
+    pub fn handles(method: &str) -> bool {
+        AgentToolMethod::ALL.contains(&method)
+    }
+
+    /// Execute a tool call and return its JSON result
+    pub async fn call(&self, method: &str, params: Value) -> Result<Value, TaskToolError> {
+        tracing::info!(
+            session_id = %self.session_id,
+            method = %method,
+            "agent task tool called"
+        );
+
+        let task = self.task.as_ref().ok_or(TaskToolError::NoTask)?;
+
+        match method {
+            AgentToolMethod::GET_TASK => {
+                serde_json::to_value(task).map_err(|e| TaskToolError::InvalidParams(e.to_string()))
+            }
+            AgentToolMethod::POST_COMMENT => {
+                let params: PostCommentParams = parse_params(params)?;
+                let event = BuiltinEvent::AgentTaskComment(AgentTaskCommentData {
+                    session_id: self.session_id.clone(),
+                    content: params.content,
+                });
+                self.emit(event).await
+            }
+            AgentToolMethod::COMPLETE_SUBTASK => {
+                let params: CompleteSubtaskParams = parse_params(params)?;
+                let event = BuiltinEvent::AgentSubtaskDone(AgentSubtaskDoneData {
+                    session_id: self.session_id.clone(),
+                    subtask: params.subtask,
+                    note: params.note,
+                });
+                self.emit(event).await
+            }
+            AgentToolMethod::CREATE_FOLLOWUP_TASK => {
+                let params: CreateFollowupTaskParams = parse_params(params)?;
+                let event = BuiltinEvent::AgentFollowupTask(AgentFollowupTaskData {
+                    session_id: self.session_id.clone(),
+                    content: params.content,
+                    priority: params.priority,
+                });
+                self.emit(event).await
+            }
+            other => Err(TaskToolError::UnknownMethod(other.to_string())),
+        }
+    }
+
+    async fn emit(&self, event: BuiltinEvent) -> Result<Value, TaskToolError> {
+        let cursor = self
+            .event_bus
+            .emit_builtin(event)
+            .await
+            .map_err(|e| TaskToolError::Server(e.to_string()))?;
+        Ok(serde_json::json!({ "accepted": true, "cursor": cursor }))
+    }
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, TaskToolError> {
+    serde_json::from_value(params).map_err(|e| TaskToolError::InvalidParams(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task_context() -> AgentTaskContext {
+        AgentTaskContext {
+            task_id: "018f1a2b-3c4d-5e6f-7a8b-9c0d1e2f3a4b".to_string(),
+            content: "Fix login redirect".to_string(),
+            status: "in-progress".to_string(),
+            priority: 2,
+            project_id: "018f1a2b-0000-0000-0000-000000000000".to_string(),
+            project_name: "web".to_string(),
+        }
+    }
+
+    fn tools(task: Option<AgentTaskContext>) -> TaskTools {
+        let event_bus = EventBusClient::new("ws://127.0.0.1:1/ws/relay", "", uuid::Uuid::nil());
+        TaskTools::new("session".to_string(), task, event_bus)
+    }
+
+    #[test]
+    fn test_handles_only_todoki_methods() {
+        assert!(TaskTools::handles("todoki/get_task"));
+        assert!(TaskTools::handles("todoki/create_followup_task"));
+        assert!(!TaskTools::handles("fs/read_text_file"));
+    }
+
+    #[tokio::test]
+    async fn test_get_task_returns_snapshot() {
+        let result = tools(Some(task_context()))
+            .call(AgentToolMethod::GET_TASK, Value::Null)
+            .await
+            .unwrap();
+        assert_eq!(result["content"], "Fix login redirect");
+        assert_eq!(result["project_name"], "web");
+    }
+
+    #[tokio::test]
+    async fn test_tools_require_task() {
+        let result = tools(None).call(AgentToolMethod::GET_TASK, Value::Null).await;
+        assert!(matches!(result, Err(TaskToolError::NoTask)));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_params_rejected() {
+        let result = tools(Some(task_context()))
+            .call(AgentToolMethod::POST_COMMENT, serde_json::json!({ "text": "hi" }))
+            .await;
+        assert!(matches!(result, Err(TaskToolError::InvalidParams(_))));
+    }
+}
@@ -0,0 +1,247 @@
+//! Resource usage of agent processes
+//!
+//! The session manager samples the process tree of a running session (the
+//! agent process and everything it started) from `/proc`: CPU time, resident
+//! memory and the number of live child processes. Samples are attached to
+//! periodic `relay.session_status` updates and to `agent.session_exited`.
+//! On platforms without `/proc` no usage is reported.
+#![cfg_attr(not(target_os = "linux"), allow(dead_code))]
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use todoki_protocol::event_bus::ResourceUsage;
+
+/// `/proc` reports CPU times in USER_HZ ticks, fixed at 100 on every
+/// architecture Linux exposes to userspace
+const USER_HZ: f64 = 100.0;
+
+/// Samples the process tree rooted at one pid
+#[derive(Debug)]
+pub struct UsageSampler {
+    pid: u32,
+    last_cpu_seconds: f64,
+    last_sampled_at: Instant,
+    latest: Option<ResourceUsage>,
+}
+
+/// Totals over a process tree at one point in time
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TreeUsage {
+    cpu_seconds: f64,
+    rss_bytes: u64,
+    child_processes: u32,
+}
+
+impl UsageSampler {
+    pub fn new(pid: u32) -> Self {
+        Self {
+            pid,
+            last_cpu_seconds: 0.0,
+            last_sampled_at: Instant::now(),
+            latest: None,
+        }
+    }
+
+    /// Sample the process tree now; `None` once the process is gone
+    pub fn sample(&mut self) -> Option<ResourceUsage> {
+        let tree = read_tree(self.pid)?;
+        let now = Instant::now();
+        let usage = self.record(tree, now);
+        self.latest = Some(usage);
+        Some(usage)
+    }
+
+    /// The most recent successful sample
+    pub fn latest(&self) -> Option<ResourceUsage> {
+        self.latest
+    }
+
+    fn record(&mut self, tree: TreeUsage, now: Instant) -> ResourceUsage {
+        // Exited children only count once their parent reaped them, so the
+        // total can briefly dip; never report it going backwards
+        let cpu_seconds = tree.cpu_seconds.max(self.last_cpu_seconds);
+        let elapsed = now.duration_since(self.last_sampled_at).as_secs_f64();
+        let cpu_percent = if elapsed > 0.0 {
+            (cpu_seconds - self.last_cpu_seconds) / elapsed * 100.0
+        } else {
+            0.0
+        };
+        self.last_cpu_seconds = cpu_seconds;
+        self.last_sampled_at = now;
+
+        let peak_rss_bytes = self
+            .latest
+            .map_or(0, |usage| usage.peak_rss_bytes)
+            .max(tree.rss_bytes);
+        ResourceUsage {
+            cpu_percent,
+            cpu_seconds,
+            rss_bytes: tree.rss_bytes,
+            peak_rss_bytes,
+            child_processes: tree.child_processes,
+        }
+    }
+}
+
+/// The fields of `/proc/<pid>/stat` the sampler needs
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ProcStat {
+    ppid: u32,
+    /// utime + stime + cutime + cstime, i.e. including reaped children
+    cpu_ticks: u64,
+}
+
+/// Parse a `/proc/<pid>/stat` line
+fn parse_stat(content: &str) -> Option<ProcStat> {
+    // The command name may contain spaces and parentheses; the fields
+    // after it start past the last ')'
+    let (_, rest) = content.rsplit_once(')')?;
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let field = |i: usize| fields.get(i).and_then(|f| f.parse::<u64>().ok());
+    // fields[0] is the state; ppid is field 4 of the line, utime..cstime 14..17
+    let ppid = u32::try_from(field(1)?).ok()?;
+    let cpu_ticks = field(11)? + field(12)? + field(13)? + field(14)?;
+    Some(ProcStat { ppid, cpu_ticks })
+}
+
+/// Resident memory in bytes from a `/proc/<pid>/status` file
+fn parse_rss(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb = line
+        .trim_start_matches("VmRSS:")
+        .split_whitespace()
+        .next()?
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+/// Pids of every process below `root`
+fn descendants(root: u32, stats: &HashMap<u32, ProcStat>) -> Vec<u32> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (&pid, stat) in stats {
+        children.entry(stat.ppid).or_default().push(pid);
+    }
+
+    let mut found = Vec::new();
+    let mut pending = vec![root];
+    while let Some(pid) = pending.pop() {
+        for &child in children.get(&pid).into_iter().flatten() {
+            found.push(child);
+            pending.push(child);
+        }
+    }
+    found
+}
+
+#[cfg(target_os = "linux")]
+fn read_tree(root: u32) -> Option<TreeUsage> {
+    let mut stats = HashMap::new();
+    for entry in std::fs::read_dir("/proc").ok()?.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|n| n.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        // Processes may exit while /proc is being walked
+        if let Some(stat) = std::fs::read_to_string(entry.path().join("stat"))
+            .ok()
+            .as_deref()
+            .and_then(parse_stat)
+        {
+            stats.insert(pid, stat);
+        }
+    }
+
+    if !stats.contains_key(&root) {
+        return None;
+    }
+    let children = descendants(root, &stats);
+    let mut cpu_ticks = 0;
+    let mut rss_bytes = 0;
+    for pid in std::iter::once(root).chain(children.iter().copied()) {
+        cpu_ticks += stats[&pid].cpu_ticks;
+        rss_bytes += std::fs::read_to_string(format!("/proc/{}/status", pid))
+            .ok()
+            .as_deref()
+            .and_then(parse_rss)
+            .unwrap_or(0);
+    }
+
+    Some(TreeUsage {
+        cpu_seconds: cpu_ticks as f64 / USER_HZ,
+        rss_bytes,
+        child_processes: u32::try_from(children.len()).unwrap_or(u32::MAX),
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_tree(_root: u32) -> Option<TreeUsage> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_stat_and_status() {
+        let stat = "4242 (node (agent) x) S 4200 4242 4200 0 -1 4194560 1000 0 0 0 \
+                    150 50 7 3 20 0 11 0 123456 1000000 2500 18446744073709551615";
+        assert_eq!(
+            parse_stat(stat),
+            Some(ProcStat {
+                ppid: 4200,
+                cpu_ticks: 210
+            })
+        );
+        assert_eq!(parse_stat("garbage"), None);
+
+        let status = "Name:\tnode\nVmPeak:\t  900 kB\nVmRSS:\t  2048 kB\nThreads:\t11\n";
+        assert_eq!(parse_rss(status), Some(2048 * 1024));
+        assert_eq!(parse_rss("Name:\tkthreadd\n"), None);
+    }
+
+    #[test]
+    fn test_descendants_follow_the_tree() {
+        let stat = |ppid| ProcStat { ppid, cpu_ticks: 0 };
+        let stats = HashMap::from([
+            (10, stat(1)),
+            (11, stat(10)),
+            (12, stat(11)),
+            (13, stat(10)),
+            (20, stat(1)),
+        ]);
+        let mut found = descendants(10, &stats);
+        found.sort();
+        assert_eq!(found, vec![11, 12, 13]);
+        assert!(descendants(20, &stats).is_empty());
+    }
+
+    #[test]
+    fn test_record_tracks_cpu_rate_and_peak_memory() {
+        let mut sampler = UsageSampler::new(1);
+        let start = sampler.last_sampled_at;
+        let tree = |cpu_seconds, rss_bytes| TreeUsage {
+            cpu_seconds,
+            rss_bytes,
+            child_processes: 2,
+        };
+
+        let first = sampler.record(tree(1.0, 300), start + Duration::from_secs(2));
+        sampler.latest = Some(first);
+        assert_eq!(first.cpu_percent, 50.0);
+        assert_eq!(first.peak_rss_bytes, 300);
+
+        let second = sampler.record(tree(0.5, 100), start + Duration::from_secs(4));
+        assert_eq!(second.cpu_seconds, 1.0);
+        assert_eq!(second.cpu_percent, 0.0);
+        assert_eq!(second.rss_bytes, 100);
+        assert_eq!(second.peak_rss_bytes, 300);
+        assert_eq!(second.child_processes, 2);
+    }
+}
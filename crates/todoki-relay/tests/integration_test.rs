@@ -11,7 +11,7 @@ use std::time::Duration;
 use agent_client_protocol::RequestPermissionOutcome;
 use tokio::sync::mpsc;
 
-use todoki_protocol::{SendInputParams, SpawnSessionParams};
+use todoki_protocol::{SendInputParams, SessionMode, SpawnSessionParams};
 use todoki_relay::relay::RelayOutput;
 use todoki_relay::session::SessionManager;
 
@@ -80,6 +80,8 @@ async fn test_spawn_session() {
         env: std::collections::HashMap::new(),
         setup_script: None,
         task_id: None,
+        task: None,
+        mode: SessionMode::Acp,
     };
 
     let result = manager.spawn(params).await;
@@ -128,6 +130,8 @@ async fn test_send_input_simple() {
         env: std::collections::HashMap::new(),
         setup_script: None,
         task_id: None,
+        task: None,
+        mode: SessionMode::Acp,
     };
 
     manager.spawn(params).await.expect("spawn failed");
@@ -188,6 +192,8 @@ async fn test_permission_flow() {
         env: std::collections::HashMap::new(),
         setup_script: None,
         task_id: None,
+        task: None,
+        mode: SessionMode::Acp,
     };
 
     manager.spawn(params).await.expect("spawn failed");
@@ -230,6 +236,8 @@ async fn test_path_validation() {
         env: std::collections::HashMap::new(),
         setup_script: None,
         task_id: None,
+        task: None,
+        mode: SessionMode::Acp,
     };
 
     let result = manager.spawn(params).await;
@@ -267,6 +275,8 @@ async fn test_spawn_duplicate_session() {
         env: std::collections::HashMap::new(),
         setup_script: None,
         task_id: None,
+        task: None,
+        mode: SessionMode::Acp,
     };
 
     manager.spawn(params1).await.expect("first spawn failed");
@@ -281,6 +291,8 @@ async fn test_spawn_duplicate_session() {
         env: std::collections::HashMap::new(),
         setup_script: None,
         task_id: None,
+        task: None,
+        mode: SessionMode::Acp,
     };
 
     let result = manager.spawn(params2).await;
@@ -0,0 +1,76 @@
+use gotcha::axum::extract::{Path, Query, State};
+use gotcha::axum::Extension;
+use gotcha::Json;
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
+use crate::auth::AuthContext;
+use crate::models::{ActivityCursor, ActivityPage, ActivityQuery};
+use crate::Db;
+
+fn cursor(query: &ActivityQuery) -> Result<Option<ActivityCursor>, ApiError> {
+    query
+        .cursor
+        .as_deref()
+        .map(|c| ActivityCursor::decode(c).ok_or_else(|| ApiError::bad_request("invalid cursor")))
+        .transpose()
+}
+
+/// GET /api/tasks/:task_id/activity - Activity feed of a task
+///
+/// Status changes, comments, artifacts and agent session milestones, newest
+/// first. Pass `next_cursor` back as `cursor` for the next page.
+#[gotcha::api]
+pub async fn task_activity(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(task_id): Path<Uuid>,
+    Query(query): Query<ActivityQuery>,
+) -> Result<Json<ActivityPage>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    db.get_task_by_id(task_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("task not found"))?;
+
+    let limit = query.limit();
+    let items = db.task_activity(task_id, cursor(&query)?.as_ref(), limit).await?;
+    Ok(Json(ActivityPage::new(items, limit)))
+}
+
+/// GET /api/projects/:project_id/activity - Activity feed of every task in a
+/// project
+#[gotcha::api]
+pub async fn project_activity(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(project_id): Path<Uuid>,
+    Query(query): Query<ActivityQuery>,
+) -> Result<Json<ActivityPage>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    db.get_project(project_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Project {} not found", project_id)))?;
+
+    let limit = query.limit();
+    let items = db.project_activity(project_id, cursor(&query)?.as_ref(), limit).await?;
+    Ok(Json(ActivityPage::new(items, limit)))
+}
+
+/// GET /api/watched - Activity feed of every task the calling user watches
+///
+/// Tasks are watched with `POST /api/tasks/:task_id/watch`.
+#[gotcha::api]
+pub async fn watched_activity(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Query(query): Query<ActivityQuery>,
+) -> Result<Json<ActivityPage>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    let user_id = auth.token_id().ok_or_else(ApiError::unauthorized)?;
+
+    let limit = query.limit();
+    let items = db.watched_activity(user_id, cursor(&query)?.as_ref(), limit).await?;
+    Ok(Json(ActivityPage::new(items, limit)))
+}
@@ -0,0 +1,336 @@
+//! Backup and restore of all data
+//!
+//! A backup is newline-delimited JSON (see [`BackupLine`]) holding every
+//! table's rows as read from one consistent snapshot. Restoring one replaces
+//! all data in a single transaction; other requests wait for it to finish.
+//! Both report their progress per table with `system.backup_*` events.
+
+use std::sync::Arc;
+
+use chrono::Utc;
+use futures_util::StreamExt;
+use gotcha::axum::body::Body;
+use gotcha::axum::extract::State;
+use gotcha::axum::http::header;
+use gotcha::axum::response::{IntoResponse, Response};
+use gotcha::axum::Extension;
+use gotcha::Json;
+use serde_json::Value;
+use todoki_protocol::event_bus::{BackupOperation, BuiltinEvent, SystemBackupData};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
+use crate::auth::AuthContext;
+use crate::db::DatabaseService;
+use crate::event_bus::{EventPublisher, EventScope};
+use crate::models::{
+    BackupLine, BackupReader, RestoreBatch, RestoreResponse, RestoredTable, BACKUP_TABLES,
+};
+use crate::{Db, Publisher};
+
+/// Lines buffered between the database and the response body
+const BACKUP_BUFFER: usize = 256;
+
+/// Rows inserted per statement while restoring
+const RESTORE_BATCH_ROWS: usize = 500;
+
+/// GET /api/admin/backup - Stream a backup of all data
+pub async fn backup(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(publisher): State<Publisher>,
+) -> Result<Response, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let backup_id = Uuid::new_v4();
+    let (body_tx, body_rx) = mpsc::channel(BACKUP_BUFFER);
+    tokio::spawn(run_backup(
+        db.0.clone(),
+        publisher.0.clone(),
+        backup_id,
+        body_tx,
+    ));
+
+    let body = futures_util::stream::unfold(body_rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+    let filename = format!(
+        "todoki-backup-{}.jsonl",
+        Utc::now().format("%Y%m%dT%H%M%SZ")
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+/// Export the database into the response body, reporting progress
+///
+/// A failed backup ends the body with an error, so the client sees an
+/// aborted transfer rather than a short but well-formed file.
+async fn run_backup(
+    db: Arc<DatabaseService>,
+    publisher: Arc<EventPublisher>,
+    backup_id: Uuid,
+    body: mpsc::Sender<Result<String, std::io::Error>>,
+) {
+    let tables_total = BACKUP_TABLES.len() as u32;
+    let started = progress(backup_id, BackupOperation::Backup, None, 0, tables_total, 0);
+    emit(&publisher, BuiltinEvent::SystemBackupStarted(started)).await;
+
+    let (lines_tx, mut lines_rx) = mpsc::channel(BACKUP_BUFFER);
+    let export = db.export_backup(backup_id, lines_tx);
+    let forward = async {
+        let (mut tables_done, mut rows) = (0, 0);
+        while let Some(line) = lines_rx.recv().await {
+            let mut chunk = serde_json::to_string(&line).map_err(|e| e.to_string())?;
+            chunk.push('\n');
+            if body.send(Ok(chunk)).await.is_err() {
+                return Err("client disconnected".to_string());
+            }
+            if let BackupLine::TableEnd {
+                table,
+                rows: table_rows,
+            } = line
+            {
+                tables_done += 1;
+                rows += table_rows;
+                let data = progress(
+                    backup_id,
+                    BackupOperation::Backup,
+                    Some(table),
+                    tables_done,
+                    tables_total,
+                    rows,
+                );
+                emit(&publisher, BuiltinEvent::SystemBackupProgress(data)).await;
+            }
+        }
+        Ok((tables_done, rows))
+    };
+
+    let (exported, forwarded) = tokio::join!(export, forward);
+    let error = match (exported, forwarded) {
+        (Ok(()), Ok((tables_done, rows))) => {
+            let data = progress(
+                backup_id,
+                BackupOperation::Backup,
+                None,
+                tables_done,
+                tables_total,
+                rows,
+            );
+            emit(&publisher, BuiltinEvent::SystemBackupCompleted(data)).await;
+            tracing::info!(backup_id = %backup_id, rows, "backup completed");
+            return;
+        }
+        (_, Err(e)) => e,
+        (Err(e), Ok(_)) => e.to_string(),
+    };
+
+    tracing::error!(backup_id = %backup_id, error = %error, "backup failed");
+    let _ = body.send(Err(std::io::Error::other(error.clone()))).await;
+    let mut data = progress(backup_id, BackupOperation::Backup, None, 0, tables_total, 0);
+    data.error = Some(error);
+    emit(&publisher, BuiltinEvent::SystemBackupFailed(data)).await;
+}
+
+/// POST /api/admin/restore - Replace all data with a backup
+///
+/// The request body is a backup as returned by `GET /api/admin/backup`.
+/// Nothing changes unless the whole backup is read and checked.
+pub async fn restore(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(publisher): State<Publisher>,
+    body: Body,
+) -> Result<Json<RestoreResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let (batch_tx, batch_rx) = mpsc::channel(BACKUP_BUFFER);
+    let mut run = RestoreRun {
+        publisher: &publisher,
+        reader: BackupReader::default(),
+        batch_tx: Some(batch_tx),
+        table: String::new(),
+        rows: Vec::new(),
+    };
+    let (restored, read) = tokio::join!(db.restore_backup(batch_rx), run.read(body));
+
+    let backup_id = run.reader.backup_id().unwrap_or_default();
+    let tables_total = run.reader.tables_total();
+    let result = match (restored, read) {
+        (Ok(true), Ok(tables)) => Ok(tables),
+        // The reader stops when the database side fails, so its error wins
+        (Err(e), _) => Err(ApiError::from(e)),
+        (Ok(_), Err(e)) => Err(ApiError::bad_request(e)),
+        (Ok(false), Ok(_)) => Err(ApiError::internal("restore was not committed")),
+    };
+
+    match result {
+        Ok(tables) => {
+            let rows = tables.iter().map(|t| t.rows).sum();
+            let data = progress(
+                backup_id,
+                BackupOperation::Restore,
+                None,
+                tables.len() as u32,
+                tables_total,
+                rows,
+            );
+            emit(&publisher, BuiltinEvent::SystemBackupCompleted(data)).await;
+            tracing::info!(backup_id = %backup_id, rows, "backup restored");
+            Ok(Json(RestoreResponse {
+                backup_id,
+                tables,
+                rows,
+            }))
+        }
+        Err(e) => {
+            tracing::error!(backup_id = %backup_id, error = %e.message, "restore failed");
+            let mut data = progress(
+                backup_id,
+                BackupOperation::Restore,
+                None,
+                0,
+                tables_total,
+                0,
+            );
+            data.error = Some(e.message.clone());
+            emit(&publisher, BuiltinEvent::SystemBackupFailed(data)).await;
+            Err(e)
+        }
+    }
+}
+
+/// Reads a backup from the request body into restore batches
+struct RestoreRun<'a> {
+    publisher: &'a EventPublisher,
+    reader: BackupReader,
+    /// Dropped once reading stops, which rolls back an unfinished restore
+    batch_tx: Option<mpsc::Sender<RestoreBatch>>,
+    /// Table of the rows not yet sent
+    table: String,
+    rows: Vec<Value>,
+}
+
+impl RestoreRun<'_> {
+    async fn read(&mut self, body: Body) -> Result<Vec<RestoredTable>, String> {
+        let result = self.read_lines(body).await;
+        self.batch_tx = None;
+        result
+    }
+
+    async fn read_lines(&mut self, body: Body) -> Result<Vec<RestoredTable>, String> {
+        let mut stream = body.into_data_stream();
+        let mut pending = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| format!("failed to read backup: {}", e))?;
+            pending.extend_from_slice(&chunk);
+            while let Some(end) = pending.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                self.line(&line).await?;
+            }
+        }
+        self.line(&pending).await?;
+
+        let tables = self.reader.finish()?;
+        self.send(RestoreBatch::Commit).await?;
+        Ok(tables)
+    }
+
+    async fn line(&mut self, bytes: &[u8]) -> Result<(), String> {
+        if bytes.trim_ascii().is_empty() {
+            return Ok(());
+        }
+        let line: BackupLine =
+            serde_json::from_slice(bytes).map_err(|e| format!("invalid backup line: {}", e))?;
+        let finished = self.reader.accept(&line)?;
+
+        match line {
+            BackupLine::Header { backup_id, .. } => {
+                let total = self.reader.tables_total();
+                let data = progress(backup_id, BackupOperation::Restore, None, 0, total, 0);
+                emit(self.publisher, BuiltinEvent::SystemBackupStarted(data)).await;
+            }
+            BackupLine::Row { table, row } => {
+                self.table = table;
+                self.rows.push(row);
+                if self.rows.len() >= RESTORE_BATCH_ROWS {
+                    self.flush().await?;
+                }
+            }
+            BackupLine::TableEnd { .. } => {
+                self.flush().await?;
+            }
+            BackupLine::End { .. } => {}
+        }
+
+        if let Some(table) = finished {
+            let data = progress(
+                self.reader.backup_id().unwrap_or_default(),
+                BackupOperation::Restore,
+                Some(table.table),
+                self.reader.tables_done(),
+                self.reader.tables_total(),
+                self.reader.rows(),
+            );
+            emit(self.publisher, BuiltinEvent::SystemBackupProgress(data)).await;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), String> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        let batch = RestoreBatch::Rows {
+            table: self.table.clone(),
+            rows: std::mem::take(&mut self.rows),
+        };
+        self.send(batch).await
+    }
+
+    async fn send(&self, batch: RestoreBatch) -> Result<(), String> {
+        let Some(batch_tx) = &self.batch_tx else {
+            return Err("restore stopped".to_string());
+        };
+        batch_tx
+            .send(batch)
+            .await
+            .map_err(|_| "restore stopped".to_string())
+    }
+}
+
+fn progress(
+    backup_id: Uuid,
+    operation: BackupOperation,
+    table: Option<String>,
+    tables_done: u32,
+    tables_total: u32,
+    rows: i64,
+) -> SystemBackupData {
+    SystemBackupData {
+        backup_id: backup_id.to_string(),
+        operation,
+        table,
+        tables_done,
+        tables_total,
+        rows,
+        error: None,
+    }
+}
+
+async fn emit(publisher: &EventPublisher, event: BuiltinEvent) {
+    if let Err(e) = publisher.emit_builtin(event, EventScope::system()).await {
+        tracing::warn!(error = %e, "failed to emit backup event");
+    }
+}
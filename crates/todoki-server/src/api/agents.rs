@@ -1,23 +1,43 @@
 use gotcha::axum::extract::Extension;
 use gotcha::axum::extract::Path;
+use gotcha::axum::extract::Query;
 use gotcha::axum::extract::State;
+use gotcha::axum::http::header;
+use gotcha::axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
 use gotcha::{Json, Schematic};
 use serde::{Deserialize, Serialize};
+use todoki_protocol::event_bus::{BuiltinEvent, RelaySpawnRequestedData, RelayStopRequestedData};
 use uuid::Uuid;
+use validator::Validate;
 
+use std::collections::HashMap;
 use std::time::Duration;
 
 use crate::api::error::ApiError;
-use crate::auth::AuthContext;
-use crate::event_bus::kinds::EventKind;
+use crate::api::validation;
+use crate::api::watches::watch_as_owner;
+use crate::auth::{share_token, AuthContext};
+use crate::config::Settings;
+use crate::event_bus::{AgentEventFilter, AgentEventPage, EventPage, EventScope};
 use crate::models::agent::{
     AgentResponse, AgentRole, AgentSessionResponse, AgentStatus, CreateAgent, ExecutionMode,
     SessionStatus,
 };
+use crate::models::scoreboard::{
+    rank_leaderboard, stats_days, AgentStats, AgentStatsQuery, LeaderboardQuery,
+    DEFAULT_MIN_SESSIONS,
+};
+use crate::models::WatchTarget;
 use crate::Db;
 use crate::Publisher;
 use crate::Relays;
 use crate::ReqTracker;
+use crate::Subscriber;
+use crate::Triggers;
+use crate::Watches;
+use crate::transcript::{self, TranscriptFormat, TranscriptHeader, TRANSCRIPT_EVENT_LIMIT};
+use crate::trigger::ReplayRange;
 
 // ============================================================================
 // List agents
@@ -59,12 +79,28 @@ pub async fn get_agent(
 // Create agent
 // ============================================================================
 
-#[derive(Debug, Deserialize, Schematic)]
+#[derive(Debug, Deserialize, Schematic, Validate)]
 pub struct CreateAgentRequest {
+    #[validate(
+        length(min = 1, max = "validation::MAX_NAME_LEN"),
+        custom(function = "validation::not_blank")
+    )]
     pub name: String,
+    #[validate(
+        length(min = 1, max = "validation::MAX_PATH_LEN"),
+        custom(function = "validation::not_blank")
+    )]
     pub workdir: String,
+    #[validate(
+        length(min = 1, max = "validation::MAX_PATH_LEN"),
+        custom(function = "validation::not_blank")
+    )]
     pub command: String,
     #[serde(default)]
+    #[validate(
+        length(max = "validation::MAX_ARGS"),
+        custom(function = "validation::command_args")
+    )]
     pub args: Vec<String>,
     #[serde(default)]
     pub execution_mode: ExecutionMode,
@@ -74,6 +110,15 @@ pub struct CreateAgentRequest {
     /// If true, automatically start the agent after creation
     #[serde(default)]
     pub auto_start: bool,
+    /// Event kinds that trigger the agent (wildcards allowed, e.g. "task.*")
+    #[serde(default)]
+    #[validate(length(max = "validation::MAX_SUBSCRIPTIONS"))]
+    pub subscribed_events: Vec<String>,
+    /// Start a session whenever a subscribed event is published
+    #[serde(default)]
+    pub auto_trigger: bool,
+    /// Label selector the relay must match (e.g. `gpu=true,!experimental`)
+    pub relay_selector: Option<String>,
 }
 
 #[derive(Debug, Serialize, Schematic)]
@@ -97,6 +142,11 @@ pub async fn create_agent(
     Json(req): Json<CreateAgentRequest>,
 ) -> Result<Json<CreateAgentResponse>, ApiError> {
     auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    validation::validate(&req)?;
+
+    if let Some(selector) = &req.relay_selector {
+        LabelSelector::parse(selector).map_err(ApiError::bad_request)?;
+    }
 
     let auto_start = req.auto_start;
     let execution_mode = req.execution_mode;
@@ -111,7 +161,9 @@ pub async fn create_agent(
         execution_mode,
         role,
         project_id,
-    );
+    )
+    .with_subscriptions(req.subscribed_events, req.auto_trigger)
+    .with_relay_selector(req.relay_selector);
 
     let agent = db.create_agent(create).await?;
 
@@ -133,6 +185,93 @@ pub async fn create_agent(
     }
 }
 
+// ============================================================================
+// Update subscriptions
+// ============================================================================
+
+#[derive(Debug, Deserialize, Schematic)]
+pub struct UpdateSubscriptionsRequest {
+    /// Event kinds that trigger the agent (wildcards allowed, e.g. "task.*")
+    pub subscribed_events: Vec<String>,
+    pub auto_trigger: bool,
+}
+
+/// PUT /api/agents/:agent_id/subscriptions - Replace event subscriptions
+#[gotcha::api]
+pub async fn update_subscriptions(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(agent_id): Path<Uuid>,
+    Json(req): Json<UpdateSubscriptionsRequest>,
+) -> Result<Json<AgentResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    if db.get_agent(agent_id).await?.is_none() {
+        return Err(ApiError::not_found("agent not found"));
+    }
+
+    let agent = db
+        .update_agent_subscriptions(agent_id, req.subscribed_events, req.auto_trigger)
+        .await?;
+    Ok(Json(AgentResponse::from(agent)))
+}
+
+// ============================================================================
+// Replay events
+// ============================================================================
+
+#[derive(Debug, Deserialize, Schematic)]
+pub struct ReplayRequest {
+    /// Replay events after this cursor (default: the agent's last_cursor)
+    pub from_cursor: Option<i64>,
+    /// Max triggers per minute (default: 6, max: 60)
+    pub events_per_minute: Option<u32>,
+}
+
+/// POST /api/agents/:agent_id/replay - Feed historical matching events to the agent
+#[gotcha::api]
+pub async fn start_replay(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(triggers): State<Triggers>,
+    Path(agent_id): Path<Uuid>,
+    Json(req): Json<ReplayRequest>,
+) -> Result<Json<ReplayRange>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let agent = db
+        .get_agent(agent_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("agent not found"))?;
+    if agent.subscribed_events.is_empty() {
+        return Err(ApiError::bad_request("agent has no event subscriptions"));
+    }
+    if agent.execution_mode.session_mode().is_none() {
+        return Err(ApiError::bad_request("local execution not implemented"));
+    }
+
+    let range = triggers
+        .start_replay(agent_id, req.from_cursor, req.events_per_minute.unwrap_or(6))
+        .await
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+    Ok(Json(range))
+}
+
+/// DELETE /api/agents/:agent_id/replay - Stop a running replay
+#[gotcha::api]
+pub async fn cancel_replay(
+    Extension(auth): Extension<AuthContext>,
+    State(triggers): State<Triggers>,
+    Path(agent_id): Path<Uuid>,
+) -> Result<Json<EmptyResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    if !triggers.cancel_replay(agent_id).await {
+        return Err(ApiError::not_found("no replay running for this agent"));
+    }
+    Ok(Json(EmptyResponse {}))
+}
+
 // ============================================================================
 // Delete agent
 // ============================================================================
@@ -159,9 +298,12 @@ pub async fn delete_agent(
 
 use crate::db::DatabaseService;
 use crate::models::agent::{Agent, AgentSession};
-use crate::relay::RelayManager;
+use crate::relay::{LabelSelector, RelayManager};
 
-async fn start_agent_internal(
+/// Spawn a session of `agent` on a relay and wait for the relay to confirm
+///
+/// Shared by the start endpoints and health-triggered restarts.
+pub async fn start_agent_internal(
     db: &DatabaseService,
     relays: &RelayManager,
     publisher: &crate::event_bus::EventPublisher,
@@ -170,21 +312,35 @@ async fn start_agent_internal(
 ) -> anyhow::Result<AgentSession> {
     let agent_id = agent.id;
 
-    if agent.execution_mode != ExecutionMode::Remote {
+    let Some(mode) = agent.execution_mode.session_mode() else {
         anyhow::bail!("local execution not implemented");
-    }
+    };
 
     // Convert agent role to relay role for selection
     let required_role = Some(agent.role.into());
     let required_project = Some(agent.project_id);
 
-    // Select relay based on role, project and availability
+    // The project may route the agent's role to a relay or label selector;
+    // the agent's own selector wins over the project's
+    let route = db
+        .get_project(agent.project_id)
+        .await?
+        .and_then(|project| project.relay_route(agent.role))
+        .unwrap_or_default();
+    let selector = agent.label_selector().or_else(|| route.label_selector());
+
+    // Select relay based on role, project, routing and availability
     let relay_id = relays
-        .select_relay(None, required_role, required_project)
+        .select_relay_matching(
+            route.relay_id.as_deref(),
+            required_role,
+            required_project,
+            selector.as_ref(),
+        )
         .await
         .ok_or_else(|| {
             anyhow::anyhow!(
-                "no idle relay available for role {:?} and project {}",
+                "no relay with a free session slot for role {:?} and project {}",
                 agent.role,
                 agent.project_id
             )
@@ -198,26 +354,29 @@ async fn start_agent_internal(
 
     // Register active session
     relays
-        .add_active_session(&relay_id, &session.id.to_string())
+        .add_active_session(&relay_id, &session.id.to_string(), Some(agent.project_id))
         .await;
 
     // Emit spawn command event to Event Bus
     let request_id = Uuid::new_v4().to_string();
     let rx = tracker.track_request(request_id.clone()).await;
 
-    let data = serde_json::json!({
-        "agent_id": agent_id.to_string(),
-        "session_id": session.id.to_string(),
-        "workdir": agent.workdir,
-        "command": agent.command,
-        "args": agent.args_vec(),
-        "env": {},
+    let spawn = BuiltinEvent::RelaySpawnRequested(RelaySpawnRequestedData {
+        relay_id: relay_id.clone(),
+        request_id: request_id.clone(),
+        target_agent_id: agent_id.to_string(),
+        session_id: session.id.to_string(),
+        workdir: agent.workdir.clone(),
+        command: agent.command.clone(),
+        args: agent.args_vec(),
+        env: HashMap::new(),
+        task_id: None,
+        task: None,
+        mode,
     });
 
-    if let Err(e) = relays
-        .emit_relay_command(publisher, &relay_id, EventKind::RELAY_SPAWN_REQUESTED, request_id.clone(), data, None)
-        .await
-    {
+    let scope = EventScope::execution(session.id, session.correlation_id);
+    if let Err(e) = relays.emit_relay_command(publisher, &relay_id, spawn, scope).await {
         // Rollback on emit failure
         let _ = db.update_agent_status(agent_id, AgentStatus::Failed).await;
         let _ = db
@@ -271,6 +430,7 @@ pub async fn start_agent(
     State(relays): State<Relays>,
     State(publisher): State<Publisher>,
     State(tracker): State<ReqTracker>,
+    State(watches): State<Watches>,
     Path(agent_id): Path<Uuid>,
 ) -> Result<Json<AgentSessionResponse>, ApiError> {
     auth.require_auth().map_err(|_| ApiError::unauthorized())?;
@@ -283,6 +443,7 @@ pub async fn start_agent(
     let session = start_agent_internal(&db, &relays, &publisher, &tracker, &agent)
         .await
         .map_err(|e| ApiError::internal(e.to_string()))?;
+    watch_as_owner(&db, &watches, &auth, WatchTarget::Session(session.id)).await;
 
     Ok(Json(AgentSessionResponse::from(session)))
 }
@@ -311,6 +472,20 @@ pub async fn stop_agent(
         return Err(ApiError::internal("agent not running"));
     }
 
+    stop_agent_internal(&db, &relays, &publisher, agent_id).await?;
+
+    Ok(Json(EmptyResponse {}))
+}
+
+/// Stop an agent's running session on its relay and mark the agent stopped
+///
+/// Shared by the stop endpoint and project archiving.
+pub async fn stop_agent_internal(
+    db: &DatabaseService,
+    relays: &RelayManager,
+    publisher: &crate::event_bus::EventPublisher,
+    agent_id: Uuid,
+) -> crate::Result<()> {
     // Get running session
     let sessions = db.get_agent_sessions(agent_id).await?;
 
@@ -322,17 +497,13 @@ pub async fn stop_agent(
         // Find relay for this session from RelayManager
         if let Some(relay_id) = relays.get_relay_for_session(&session.id.to_string()).await {
             // Emit stop command event to Event Bus (fire-and-forget)
-            let request_id = Uuid::new_v4().to_string();
-            let _ = relays
-                .emit_relay_command(
-                    &publisher,
-                    &relay_id,
-                    EventKind::RELAY_STOP_REQUESTED,
-                    request_id,
-                    serde_json::json!({"session_id": session.id.to_string()}),
-                    None,
-                )
-                .await;
+            let stop = BuiltinEvent::RelayStopRequested(RelayStopRequestedData {
+                relay_id: relay_id.clone(),
+                request_id: Some(Uuid::new_v4().to_string()),
+                session_id: session.id.to_string(),
+            });
+            let scope = EventScope::execution(session.id, session.correlation_id);
+            let _ = relays.emit_relay_command(publisher, &relay_id, stop, scope).await;
 
             relays
                 .remove_active_session(&relay_id, &session.id.to_string())
@@ -344,9 +515,7 @@ pub async fn stop_agent(
             .await;
     }
 
-    db.update_agent_status(agent_id, AgentStatus::Stopped).await?;
-
-    Ok(Json(EmptyResponse {}))
+    db.update_agent_status(agent_id, AgentStatus::Stopped).await
 }
 
 // ============================================================================
@@ -367,3 +536,308 @@ pub async fn get_agent_sessions(
     Ok(Json(resp))
 }
 
+// ============================================================================
+// Agent stats
+// ============================================================================
+
+/// GET /api/agents/:agent_id/stats - Success rate, session length, first-try
+/// QA pass rate and permission denials over the last `days` days
+#[gotcha::api]
+pub async fn get_agent_stats(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(agent_id): Path<Uuid>,
+    Query(query): Query<AgentStatsQuery>,
+) -> Result<Json<AgentStats>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let since = Utc::now() - chrono::Duration::days(stats_days(query.days).into());
+    let counts = db
+        .list_agent_stats(Some(agent_id), None, since)
+        .await?
+        .pop()
+        .ok_or_else(|| ApiError::not_found("agent not found"))?;
+    Ok(Json(counts.into()))
+}
+
+/// GET /api/agents/leaderboard - Agents ranked by one of their stats
+///
+/// Agents with fewer than `min_sessions` sessions in the window are left out.
+#[gotcha::api]
+pub async fn get_agent_leaderboard(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Query(query): Query<LeaderboardQuery>,
+) -> Result<Json<Vec<AgentStats>>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    if let Some(project_id) = query.project_id {
+        db.get_project(project_id)
+            .await?
+            .ok_or_else(|| ApiError::not_found(format!("Project {} not found", project_id)))?;
+    }
+
+    let since = Utc::now() - chrono::Duration::days(stats_days(query.days).into());
+    let stats = db
+        .list_agent_stats(None, query.project_id, since)
+        .await?
+        .into_iter()
+        .map(AgentStats::from)
+        .collect();
+    Ok(Json(rank_leaderboard(
+        stats,
+        query.sort,
+        query.min_sessions.unwrap_or(DEFAULT_MIN_SESSIONS),
+    )))
+}
+
+// ============================================================================
+// Agent event history
+// ============================================================================
+
+/// Events per page unless the caller asks for fewer or more
+const DEFAULT_AGENT_EVENTS_LIMIT: usize = 100;
+/// Most events per page
+const MAX_AGENT_EVENTS_LIMIT: usize = 1000;
+
+#[derive(Debug, Default, Deserialize, Schematic)]
+pub struct AgentEventsQuery {
+    /// `next_cursor` of a previous page: return events older than it
+    pub before: Option<i64>,
+    /// `prev_cursor` of a previous page: return events newer than it
+    pub after: Option<i64>,
+    /// Event kinds (comma-separated, `*` wildcards allowed, e.g. "agent.*")
+    pub kinds: Option<String>,
+    /// Output stream types (comma-separated, e.g. "assistant,tool_use");
+    /// leaves out events that are not agent output
+    pub stream: Option<String>,
+    /// Only events of this session
+    pub session_id: Option<Uuid>,
+    /// Only events at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only events before this time
+    pub until: Option<DateTime<Utc>>,
+    /// Events per page (default 100, at most 1000)
+    pub limit: Option<usize>,
+}
+
+impl AgentEventsQuery {
+    fn page(&self) -> Result<EventPage, ApiError> {
+        match (self.before, self.after) {
+            (Some(_), Some(_)) => {
+                Err(ApiError::bad_request("pass either before or after, not both"))
+            }
+            (Some(cursor), None) => Ok(EventPage::Before(cursor)),
+            (None, Some(cursor)) => Ok(EventPage::After(cursor)),
+            (None, None) => Ok(EventPage::Latest),
+        }
+    }
+
+    fn filter(&self) -> Result<AgentEventFilter, ApiError> {
+        if let (Some(since), Some(until)) = (self.since, self.until)
+            && since >= until
+        {
+            return Err(ApiError::bad_request("since must be before until"));
+        }
+        let list = |value: &Option<String>| {
+            value.as_ref().map(|s| {
+                s.split(',')
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty())
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+            })
+        };
+        Ok(AgentEventFilter {
+            kinds: list(&self.kinds),
+            streams: list(&self.stream),
+            session_id: self.session_id,
+            since: self.since,
+            until: self.until,
+        })
+    }
+
+    fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(DEFAULT_AGENT_EVENTS_LIMIT)
+            .clamp(1, MAX_AGENT_EVENTS_LIMIT)
+    }
+}
+
+/// GET /api/agents/:agent_id/events - Event history of an agent
+///
+/// Newest first. Pass `next_cursor` back as `before` for older events and
+/// `prev_cursor` as `after` for newer ones; filter by kind, output stream,
+/// session and time range.
+#[gotcha::api]
+pub async fn get_agent_events(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(subscriber): State<Subscriber>,
+    Path(agent_id): Path<Uuid>,
+    Query(query): Query<AgentEventsQuery>,
+) -> Result<Json<AgentEventPage>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    db.get_agent(agent_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("agent not found"))?;
+
+    let page = subscriber
+        .agent_events(agent_id, &query.filter()?, query.page()?, query.limit())
+        .await
+        .map_err(|e| ApiError::internal(format!("failed to load agent events: {}", e)))?;
+    Ok(Json(page))
+}
+
+// ============================================================================
+// Export session transcript
+// ============================================================================
+
+#[derive(Debug, Deserialize, Schematic)]
+pub struct TranscriptQuery {
+    /// `markdown` (default) or `html`
+    #[serde(default)]
+    pub format: TranscriptFormat,
+    /// Include the agent's thoughts
+    #[serde(default)]
+    pub thoughts: bool,
+    /// Also store the document as a `transcript` artifact of the session's task
+    #[serde(default)]
+    pub attach: bool,
+}
+
+/// GET /api/agents/:agent_id/sessions/:session_id/export - Session transcript
+///
+/// Renders the session's messages, tool calls (output folded away),
+/// permission requests and artifacts as a Markdown or HTML document.
+pub async fn export_session(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(subscriber): State<Subscriber>,
+    Path((agent_id, session_id)): Path<(Uuid, Uuid)>,
+    Query(query): Query<TranscriptQuery>,
+) -> Result<Response, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let agent = db
+        .get_agent(agent_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("agent not found"))?;
+    let session = db
+        .get_agent_session(session_id)
+        .await?
+        .filter(|s| s.agent_id == agent_id)
+        .ok_or_else(|| ApiError::not_found("session not found"))?;
+    let task = db.get_task_by_agent_id(agent_id).await?;
+
+    let events = subscriber
+        .session_events(agent_id, &session_id.to_string(), TRANSCRIPT_EVENT_LIMIT)
+        .await
+        .map_err(|e| ApiError::internal(format!("failed to load session events: {}", e)))?;
+    let header = TranscriptHeader {
+        agent_name: agent.name.clone(),
+        session_id: session_id.to_string(),
+        status: serde_json::to_value(session.status)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default(),
+        started_at: session.started_at,
+        ended_at: session.ended_at,
+        task: task.as_ref().map(|t| t.content.clone()),
+    };
+    let document = transcript::render(
+        query.format,
+        &header,
+        &transcript::entries(&events, query.thoughts),
+    );
+
+    if query.attach {
+        let task = task.ok_or_else(|| ApiError::bad_request("session has no task to attach to"))?;
+        db.create_artifact(
+            task.id,
+            task.project_id,
+            Some(agent_id),
+            Some(session_id),
+            "transcript",
+            serde_json::json!({
+                "session_id": session_id,
+                "format": query.format.as_str(),
+                "thoughts": query.thoughts,
+                "content": document,
+            }),
+        )
+        .await?;
+    }
+
+    let disposition = format!(
+        "inline; filename=\"session-{}.{}\"",
+        session_id,
+        query.format.extension()
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, query.format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        document,
+    )
+        .into_response())
+}
+
+// ============================================================================
+// Share session
+// ============================================================================
+
+#[derive(Debug, Default, Deserialize, Validate, Schematic)]
+pub struct ShareSessionRequest {
+    /// Lifetime of the link (seconds); capped at `share_tokens.max_ttl_secs`
+    #[validate(range(min = 1))]
+    pub ttl_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Schematic)]
+pub struct ShareSessionResponse {
+    /// Read-only token for `/ws/event-bus`
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+    /// Event-bus WebSocket path carrying the token, relative to the server
+    pub path: String,
+}
+
+/// POST /api/agents/:agent_id/sessions/:session_id/share - Share a session
+///
+/// Mints a token that lets anyone holding it watch this session's events on
+/// `/ws/event-bus` until it expires, without any other access.
+#[gotcha::api]
+pub async fn share_session(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(settings): State<Settings>,
+    Path((agent_id, session_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<ShareSessionRequest>,
+) -> Result<Json<ShareSessionResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    validation::validate(&payload)?;
+
+    db.get_agent_session(session_id)
+        .await?
+        .filter(|s| s.agent_id == agent_id)
+        .ok_or_else(|| ApiError::not_found("session not found"))?;
+
+    let ttl_secs = share_token::ttl_secs(&settings.share_tokens, payload.ttl_secs);
+    let (token, scope) = share_token::issue(
+        &settings.agent_tokens.secret,
+        agent_id,
+        session_id,
+        auth.token_id().unwrap_or_default(),
+        ttl_secs,
+    );
+    let expires_at = DateTime::from_timestamp(scope.exp, 0)
+        .ok_or_else(|| ApiError::internal("invalid share token expiry"))?;
+    Ok(Json(ShareSessionResponse {
+        path: format!("/ws/event-bus?token={}", token),
+        token,
+        expires_at,
+    }))
+}
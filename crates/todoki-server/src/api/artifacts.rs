@@ -1,13 +1,22 @@
 use gotcha::axum::extract::{Path, Query, State};
 use gotcha::axum::Extension;
 use gotcha::{Json, Schematic};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use todoki_protocol::event_bus::{ArtifactUpdatedData, BuiltinEvent};
 use uuid::Uuid;
 
 use crate::api::error::ApiError;
 use crate::auth::AuthContext;
-use crate::models::ArtifactResponse;
-use crate::Db;
+use crate::config::Settings;
+use crate::event_bus::{EventPublisher, EventScope};
+use crate::models::{
+    merge_artifact_data, Artifact, ArtifactResponse, CreateArtifactRequest, UpdateArtifactRequest,
+};
+use crate::quota;
+use crate::{Db, Publisher};
+
+#[derive(Debug, Serialize, Schematic)]
+pub struct EmptyResponse {}
 
 #[derive(Debug, Deserialize, Schematic)]
 pub struct ListArtifactsQuery {
@@ -48,3 +57,189 @@ pub async fn get_artifact(
         .ok_or_else(|| ApiError::not_found("artifact not found"))?;
     Ok(Json(ArtifactResponse::from(artifact)))
 }
+
+/// POST /api/sessions/:session_id/artifacts - Record an artifact of a session
+///
+/// Agents call this with their scoped token for their own session and task.
+/// Artifacts that would take the project over its storage quota get a 429.
+#[gotcha::api]
+pub async fn create_artifact(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(settings): State<Settings>,
+    Path(session_id): Path<Uuid>,
+    Json(payload): Json<CreateArtifactRequest>,
+) -> Result<Json<ArtifactResponse>, ApiError> {
+    auth.require_session(session_id)?;
+
+    let task_id = payload
+        .task_id
+        .or_else(|| auth.agent_scope().and_then(|scope| scope.task_id))
+        .ok_or_else(|| ApiError::bad_request("task_id is required"))?;
+    auth.require_task(task_id)?;
+
+    let session = db
+        .get_agent_session(session_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("session not found"))?;
+    let task = db
+        .get_task_by_id(task_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("task not found"))?;
+
+    let bytes = payload.data.to_string().len() as u64;
+    if let Some(exceeded) =
+        quota::check_storage(&db, &settings.quotas, task.project_id, bytes).await?
+    {
+        return Err(exceeded.into());
+    }
+
+    let artifact = db
+        .create_artifact(
+            task.id,
+            task.project_id,
+            Some(session.agent_id),
+            Some(session.id),
+            &payload.artifact_type,
+            payload.data,
+        )
+        .await?;
+    Ok(Json(ArtifactResponse::from(artifact)))
+}
+
+/// Emit `artifact.updated` for `artifact` after `change`
+async fn emit_updated(
+    publisher: &EventPublisher,
+    artifact: &Artifact,
+    change: &str,
+    linked_task_id: Option<Uuid>,
+) {
+    let event = BuiltinEvent::ArtifactUpdated(ArtifactUpdatedData {
+        artifact_id: artifact.id.to_string(),
+        artifact_type: artifact.artifact_type.clone(),
+        change: change.to_string(),
+        data: artifact.data.clone(),
+        linked_task_id: linked_task_id.map(|id| id.to_string()),
+    });
+    let scope = EventScope::task(artifact.task_id).with_session(artifact.session_id);
+    if let Err(e) = publisher.emit_builtin(event, scope).await {
+        tracing::warn!(artifact_id = %artifact.id, error = %e, "failed to emit artifact.updated");
+    }
+}
+
+/// PATCH /api/artifacts/:artifact_id - Update an artifact
+///
+/// Merges `data` into the artifact (a PR merged, a document revised) and
+/// moves it to `task_id` when given.
+#[gotcha::api]
+pub async fn update_artifact(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(publisher): State<Publisher>,
+    Path(artifact_id): Path<Uuid>,
+    Json(payload): Json<UpdateArtifactRequest>,
+) -> Result<Json<ArtifactResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let artifact = db
+        .get_artifact(artifact_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("artifact not found"))?;
+
+    let task = match payload.task_id.filter(|id| *id != artifact.task_id) {
+        Some(task_id) => {
+            let task = db
+                .get_task_by_id(task_id)
+                .await?
+                .ok_or_else(|| ApiError::not_found("task not found"))?;
+            Some((task.id, task.project_id))
+        }
+        None => None,
+    };
+    let mut data = artifact.data;
+    if let Some(patch) = payload.data {
+        merge_artifact_data(&mut data, patch);
+    }
+
+    let updated = db
+        .update_artifact(artifact_id, data, task)
+        .await?
+        .ok_or_else(|| ApiError::not_found("artifact not found"))?;
+    let change = if task.is_some() { "moved" } else { "updated" };
+    emit_updated(&publisher, &updated, change, None).await;
+    Ok(Json(ArtifactResponse::from(updated)))
+}
+
+/// DELETE /api/artifacts/:artifact_id - Delete an artifact
+///
+/// The artifact disappears from lists but stays in the database.
+#[gotcha::api]
+pub async fn delete_artifact(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(publisher): State<Publisher>,
+    Path(artifact_id): Path<Uuid>,
+) -> Result<Json<EmptyResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let artifact = db
+        .get_artifact(artifact_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("artifact not found"))?;
+    if db.delete_artifact(artifact_id).await? {
+        emit_updated(&publisher, &artifact, "deleted", None).await;
+    }
+    Ok(Json(EmptyResponse {}))
+}
+
+/// POST /api/artifacts/:artifact_id/links/:task_id - Link an artifact to
+/// another task
+///
+/// The artifact then also shows among that task's artifacts.
+#[gotcha::api]
+pub async fn link_artifact(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(publisher): State<Publisher>,
+    Path((artifact_id, task_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ArtifactResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let artifact = db
+        .get_artifact(artifact_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("artifact not found"))?;
+    if task_id == artifact.task_id {
+        return Err(ApiError::bad_request("artifact already belongs to this task"));
+    }
+    db.get_task_by_id(task_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("task not found"))?;
+
+    if db.link_artifact(artifact_id, task_id).await? {
+        emit_updated(&publisher, &artifact, "linked", Some(task_id)).await;
+    }
+    Ok(Json(ArtifactResponse::from(artifact)))
+}
+
+/// DELETE /api/artifacts/:artifact_id/links/:task_id - Unlink an artifact
+/// from a task it was linked to
+#[gotcha::api]
+pub async fn unlink_artifact(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(publisher): State<Publisher>,
+    Path((artifact_id, task_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ArtifactResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let artifact = db
+        .get_artifact(artifact_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("artifact not found"))?;
+    if !db.unlink_artifact(artifact_id, task_id).await? {
+        return Err(ApiError::not_found("artifact is not linked to this task"));
+    }
+    emit_updated(&publisher, &artifact, "unlinked", Some(task_id)).await;
+    Ok(Json(ArtifactResponse::from(artifact)))
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use gotcha::axum::extract::{Query, State};
+use gotcha::axum::http::header;
+use gotcha::axum::response::{IntoResponse, Response};
+use gotcha::axum::Extension;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
+use crate::auth::AuthContext;
+use crate::config::Settings;
+use crate::models::Task;
+use crate::Db;
+
+const DEFAULT_DONE_DAYS: i64 = 14;
+const SUMMARY_MAX_CHARS: usize = 120;
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarQuery {
+    /// User token; calendar apps cannot send an Authorization header
+    pub token: Option<String>,
+    /// Restrict the feed to one project
+    pub project_id: Option<Uuid>,
+    /// How many days of completed tasks to include (default 14)
+    pub days: Option<i64>,
+}
+
+/// GET /api/calendar.ics - iCalendar feed of due and recently completed tasks
+///
+/// Open tasks with a due date become all-day events on the due date; tasks
+/// completed within `days` become events at their completion time.
+pub async fn calendar_feed(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(settings): State<Settings>,
+    Query(query): Query<CalendarQuery>,
+) -> Result<Response, ApiError> {
+    if query.token.as_deref() != Some(settings.user_token.as_str()) {
+        auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    }
+
+    let now = Utc::now();
+    let days = query.days.unwrap_or(DEFAULT_DONE_DAYS).max(0);
+    let entries = db
+        .get_calendar_tasks(query.project_id, now - Duration::days(days))
+        .await?;
+
+    let projects: HashMap<Uuid, String> = db
+        .list_projects(true)
+        .await?
+        .into_iter()
+        .map(|p| (p.id, p.name))
+        .collect();
+
+    let body = render_calendar(&entries, &projects, now);
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8"),
+            (header::CONTENT_DISPOSITION, "inline; filename=\"todoki.ics\""),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Render tasks as an RFC 5545 calendar
+fn render_calendar(
+    entries: &[(Task, Option<DateTime<Utc>>)],
+    projects: &HashMap<Uuid, String>,
+    now: DateTime<Utc>,
+) -> String {
+    let stamp = format_datetime(now);
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//todoki//calendar//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "X-WR-CALNAME:todoki".to_string(),
+    ];
+
+    for (task, completed_at) in entries {
+        let summary = summarize(&task.content);
+        let timing = match (completed_at, task.due_at) {
+            (Some(done), _) => [
+                format!("UID:{}-done@todoki", task.id),
+                format!("DTSTART:{}", format_datetime(*done)),
+                "DURATION:PT15M".to_string(),
+                format!("SUMMARY:{}", escape_text(&format!("✓ {}", summary))),
+            ],
+            (None, Some(due)) => {
+                let day = due.date_naive();
+                [
+                    format!("UID:{}-due@todoki", task.id),
+                    format!("DTSTART;VALUE=DATE:{}", day.format("%Y%m%d")),
+                    format!("DTEND;VALUE=DATE:{}", (day + Duration::days(1)).format("%Y%m%d")),
+                    format!("SUMMARY:{}", escape_text(&summary)),
+                ]
+            }
+            (None, None) => continue,
+        };
+        let project = projects.get(&task.project_id).map(String::as_str).unwrap_or("");
+
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.extend(timing);
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("DESCRIPTION:{}", escape_text(&task.content)));
+        if !project.is_empty() {
+            lines.push(format!("CATEGORIES:{}", escape_text(project)));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut out = String::new();
+    for line in lines {
+        out.push_str(&fold_line(&line));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn format_datetime(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// First line of the task content, truncated for the event title
+fn summarize(content: &str) -> String {
+    let first_line = content.lines().next().unwrap_or("").trim();
+    if first_line.chars().count() > SUMMARY_MAX_CHARS {
+        let truncated: String = first_line.chars().take(SUMMARY_MAX_CHARS).collect();
+        format!("{}…", truncated)
+    } else {
+        first_line.to_string()
+    }
+}
+
+/// Escape TEXT values per RFC 5545 §3.3.11
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold content lines longer than 75 octets (RFC 5545 §3.1)
+fn fold_line(line: &str) -> String {
+    const LIMIT: usize = 75;
+    let mut out = String::with_capacity(line.len());
+    let mut width = 0;
+    for ch in line.chars() {
+        let len = ch.len_utf8();
+        if width + len > LIMIT {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(ch);
+        width += len;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TaskStatus;
+    use chrono::TimeZone;
+
+    fn task(content: &str, status: TaskStatus, due_at: Option<DateTime<Utc>>) -> Task {
+        Task {
+            id: Uuid::nil(),
+            priority: 0,
+            content: content.to_string(),
+            project_id: Uuid::nil(),
+            status,
+            create_at: Utc::now(),
+            archived: false,
+            agent_id: None,
+            due_at,
+            estimate_minutes: None,
+            workflow_state: None,
+            rank: None,
+        }
+    }
+
+    #[test]
+    fn test_render_due_and_done_events() {
+        let due = Utc.with_ymd_and_hms(2024, 3, 5, 9, 0, 0).unwrap();
+        let done = Utc.with_ymd_and_hms(2024, 3, 1, 12, 30, 0).unwrap();
+        let entries = vec![
+            (task("Ship release, v2", TaskStatus::Todo, Some(due)), None),
+            (task("Fix bug", TaskStatus::Done, None), Some(done)),
+        ];
+        let projects = HashMap::from([(Uuid::nil(), "core".to_string())]);
+
+        let ics = render_calendar(&entries, &projects, done);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20240305\r\n"));
+        assert!(ics.contains("DTEND;VALUE=DATE:20240306\r\n"));
+        assert!(ics.contains("SUMMARY:Ship release\\, v2\r\n"));
+        assert!(ics.contains("DTSTART:20240301T123000Z\r\n"));
+        assert!(ics.contains("SUMMARY:✓ Fix bug\r\n"));
+        assert!(ics.contains("CATEGORIES:core\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+    }
+
+    #[test]
+    fn test_fold_long_lines() {
+        let line = format!("DESCRIPTION:{}", "a".repeat(100));
+        let folded = fold_line(&line);
+        let parts: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].len(), 75);
+        assert!(parts[1].starts_with(' '));
+    }
+}
@@ -0,0 +1,114 @@
+//! Runtime debugging controls
+//!
+//! `PUT /api/debug/log-level` swaps a log filter without a restart: the
+//! server's own, or that of a relay, picked directly or through an agent whose
+//! session it runs. A server filter set this way lasts until the `log`
+//! settings are reloaded; a relay's until the relay restarts.
+
+use gotcha::axum::extract::State;
+use gotcha::axum::Extension;
+use gotcha::{Json, Schematic};
+use serde::{Deserialize, Serialize};
+use todoki_protocol::event_bus::{BuiltinEvent, RelayLogLevelChangedData};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::api::error::{ApiError, FieldError};
+use crate::api::validation;
+use crate::auth::AuthContext;
+use crate::event_bus::EventScope;
+use crate::logging;
+use crate::models::agent::SessionStatus;
+use crate::{Db, Logs, Publisher, Relays};
+
+#[derive(Debug, Deserialize, Schematic, Validate)]
+pub struct LogLevelRequest {
+    /// Filter directives in `RUST_LOG` syntax, e.g. "info,todoki::relay=debug"
+    #[validate(
+        length(min = 1, max = "validation::MAX_NAME_LEN"),
+        custom(function = "validation::not_blank")
+    )]
+    pub directives: String,
+    /// Change this relay's filter instead of the server's
+    pub relay_id: Option<String>,
+    /// Change the filter of the relay running this agent's session
+    pub agent_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Schematic)]
+pub struct LogLevelResponse {
+    /// "server" or "relay"
+    pub target: String,
+    pub relay_id: Option<String>,
+    pub directives: String,
+}
+
+/// PUT /api/debug/log-level - Change the server's or a relay's log filter
+#[gotcha::api]
+pub async fn set_log_level(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(logs): State<Logs>,
+    State(relays): State<Relays>,
+    State(publisher): State<Publisher>,
+    Json(payload): Json<LogLevelRequest>,
+) -> Result<Json<LogLevelResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    validation::validate(&payload)?;
+
+    let directives = payload.directives.trim().to_string();
+    logging::parse(&directives)
+        .map_err(|e| ApiError::validation(vec![FieldError::new("directives", e)]))?;
+
+    let relay_id = match (payload.relay_id, payload.agent_id) {
+        (Some(_), Some(_)) => {
+            return Err(ApiError::bad_request("give either relay_id or agent_id, not both"));
+        }
+        (Some(relay_id), None) => Some(relay_id),
+        (None, Some(agent_id)) => Some(relay_for_agent(&db, &relays, agent_id).await?),
+        (None, None) => None,
+    };
+
+    let Some(relay_id) = relay_id else {
+        logs.set(&directives).map_err(ApiError::internal)?;
+        tracing::info!(directives = %directives, "server log filter changed");
+        return Ok(Json(LogLevelResponse {
+            target: "server".to_string(),
+            relay_id: None,
+            directives,
+        }));
+    };
+
+    let command = BuiltinEvent::RelayLogLevelChanged(RelayLogLevelChangedData {
+        relay_id: relay_id.clone(),
+        directives: directives.clone(),
+    });
+    relays
+        .emit_relay_command(&publisher, &relay_id, command, EventScope::system())
+        .await
+        .map_err(|e| ApiError::not_found(e.to_string()))?;
+    tracing::info!(relay_id = %relay_id, directives = %directives, "relay log filter change sent");
+
+    Ok(Json(LogLevelResponse {
+        target: "relay".to_string(),
+        relay_id: Some(relay_id),
+        directives,
+    }))
+}
+
+/// The relay running `agent_id`'s current session
+async fn relay_for_agent(db: &Db, relays: &Relays, agent_id: Uuid) -> Result<String, ApiError> {
+    if db.get_agent(agent_id).await?.is_none() {
+        return Err(ApiError::not_found("agent not found"));
+    }
+    let running = db
+        .get_agent_sessions(agent_id)
+        .await?
+        .into_iter()
+        .find(|s| s.status == SessionStatus::Running)
+        .ok_or_else(|| ApiError::conflict("agent has no running session"))?;
+    relays
+        .get_relay_for_session(&running.id.to_string())
+        .await
+        .ok_or_else(|| ApiError::conflict("agent's session is not on a connected relay"))
+}
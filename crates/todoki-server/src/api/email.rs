@@ -0,0 +1,204 @@
+use gotcha::axum::Extension;
+use gotcha::axum::extract::{Query, State};
+use gotcha::{Json, Schematic};
+use serde::Deserialize;
+use todoki_protocol::event_bus::{BuiltinEvent, TaskCreatedData};
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
+use crate::auth::AuthContext;
+use crate::config::Settings;
+use crate::event_bus::EventScope;
+use crate::models::{CreateTask, TaskResponse, TaskStatus};
+use crate::quota;
+use crate::{Db, Publisher};
+
+#[derive(Debug, Deserialize, Schematic)]
+pub struct InboundEmailQuery {
+    /// Shared webhook secret (alternative to Bearer auth for mail providers)
+    pub secret: Option<String>,
+}
+
+/// Inbound email payload (Postmark-style field names are accepted as aliases)
+#[derive(Debug, Deserialize, Schematic)]
+pub struct InboundEmail {
+    #[serde(alias = "From")]
+    pub from: String,
+    #[serde(default, alias = "Subject")]
+    pub subject: String,
+    #[serde(default, alias = "TextBody", alias = "body-plain")]
+    pub text_body: String,
+    #[serde(default, alias = "MessageID", alias = "Message-Id")]
+    pub message_id: Option<String>,
+    #[serde(default, alias = "Attachments")]
+    pub attachments: Vec<InboundAttachment>,
+}
+
+#[derive(Debug, Deserialize, Schematic)]
+pub struct InboundAttachment {
+    #[serde(alias = "Name")]
+    pub name: String,
+    #[serde(default, alias = "ContentType")]
+    pub content_type: Option<String>,
+    /// Base64-encoded file content
+    #[serde(alias = "Content")]
+    pub content: String,
+    #[serde(default, alias = "ContentLength")]
+    pub content_length: Option<usize>,
+}
+
+impl InboundAttachment {
+    fn size(&self) -> usize {
+        self.content_length
+            .unwrap_or_else(|| self.content.len() / 4 * 3)
+    }
+}
+
+impl InboundEmail {
+    /// Task content: subject as the first line, followed by the body
+    fn task_content(&self) -> String {
+        let subject = self.subject.trim();
+        let body = self.text_body.trim();
+        match (subject.is_empty(), body.is_empty()) {
+            (true, true) => format!("Email from {}", self.from),
+            (false, true) => subject.to_string(),
+            (true, false) => body.to_string(),
+            (false, false) => format!("{}\n\n{}", subject, body),
+        }
+    }
+}
+
+/// POST /api/integrations/email - Create an inbox task from an inbound email
+///
+/// Authenticated either with the user Bearer token or with `?secret=` matching
+/// `email.webhook_secret`. The email is stored as an `email` artifact (sender,
+/// subject, message id) and attachments as `email_attachment` artifacts;
+/// attachments that do not fit the project's storage quota are dropped.
+#[gotcha::api]
+pub async fn receive_email(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(settings): State<Settings>,
+    State(publisher): State<Publisher>,
+    Query(query): Query<InboundEmailQuery>,
+    Json(email): Json<InboundEmail>,
+) -> Result<Json<TaskResponse>, ApiError> {
+    let email_settings = &settings.email;
+    let secret_ok = !email_settings.webhook_secret.is_empty()
+        && query.secret.as_deref() == Some(email_settings.webhook_secret.as_str());
+    if !secret_ok {
+        auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    }
+
+    let project = db
+        .get_or_create_project(&email_settings.project, "Tasks created from inbound email")
+        .await?;
+
+    let content = email.task_content();
+    let task = db
+        .create_task(CreateTask::new(content, TaskStatus::Todo, 0, project.id))
+        .await?;
+    if let Err(e) = db.flag_possible_duplicates(&task, &settings.dedup).await {
+        tracing::warn!(task_id = %task.id, error = %e, "duplicate detection failed");
+    }
+
+    db.create_artifact(
+        task.id,
+        project.id,
+        None,
+        None,
+        "email",
+        serde_json::json!({
+            "from": email.from,
+            "subject": email.subject,
+            "message_id": email.message_id,
+        }),
+    )
+    .await?;
+
+    for attachment in &email.attachments {
+        if attachment.size() > email_settings.max_attachment_bytes {
+            tracing::warn!(
+                task_id = %task.id,
+                name = %attachment.name,
+                size = attachment.size(),
+                "dropping oversized email attachment"
+            );
+            continue;
+        }
+        let bytes = attachment.content.len() as u64;
+        if let Some(exceeded) =
+            quota::check_storage(&db, &settings.quotas, project.id, bytes).await?
+        {
+            tracing::warn!(
+                task_id = %task.id,
+                name = %attachment.name,
+                error = %exceeded,
+                "dropping email attachment over the storage quota"
+            );
+            continue;
+        }
+        db.create_artifact(
+            task.id,
+            project.id,
+            None,
+            None,
+            "email_attachment",
+            serde_json::json!({
+                "filename": attachment.name,
+                "content_type": attachment.content_type,
+                "size": attachment.size(),
+                "content_base64": attachment.content,
+            }),
+        )
+        .await?;
+    }
+
+    let event = BuiltinEvent::TaskCreated(TaskCreatedData {
+        title: email.subject.clone(),
+        description: Some(email.text_body.clone()),
+        parent_task_id: None,
+        source: Some("email".to_string()),
+        sender: Some(email.from.clone()),
+    });
+    if let Err(e) = publisher.emit_builtin(event, EventScope::task(task.id)).await {
+        tracing::warn!(task_id = %task.id, error = %e, "failed to emit task.created for email");
+    }
+
+    tracing::info!(task_id = %task.id, from = %email.from, "created task from inbound email");
+
+    let response = db.get_task_response(task).await?;
+    Ok(Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_postmark_payload() {
+        let email: InboundEmail = serde_json::from_value(serde_json::json!({
+            "From": "alice@example.com",
+            "Subject": "Printer is broken",
+            "TextBody": "It jams on every page.\n",
+            "MessageID": "abc-123",
+            "Attachments": [
+                {"Name": "photo.jpg", "ContentType": "image/jpeg", "Content": "AAAA", "ContentLength": 3}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(email.from, "alice@example.com");
+        assert_eq!(
+            email.task_content(),
+            "Printer is broken\n\nIt jams on every page."
+        );
+        assert_eq!(email.attachments[0].size(), 3);
+    }
+
+    #[test]
+    fn test_task_content_fallbacks() {
+        let email: InboundEmail =
+            serde_json::from_value(serde_json::json!({ "from": "bob@example.com" })).unwrap();
+        assert_eq!(email.task_content(), "Email from bob@example.com");
+    }
+}
@@ -1,63 +1,229 @@
-use gotcha::axum::http::StatusCode;
+//! API errors, served as RFC 7807 problem details
+//!
+//! Every error response has an `application/problem+json` body with a
+//! machine-readable `code` (also the last segment of `type`), the request ID
+//! found in the server logs and, for validation failures, the problem with
+//! each field. Responses that did not come from an [`ApiError`] (rejected
+//! request bodies, unknown routes) are rewritten into the same shape by
+//! [`problem_middleware`].
+
+use gotcha::axum::body::to_bytes;
+use gotcha::axum::extract::Request;
+use gotcha::axum::http::header::CONTENT_TYPE;
+use gotcha::axum::http::{HeaderValue, StatusCode};
+use gotcha::axum::middleware::Next;
 use gotcha::axum::response::{IntoResponse, Response};
 use gotcha::{Json, Schematic};
 use serde::Serialize;
 use gotcha::oas;
 use std::collections::BTreeMap;
 
+use crate::net::request_id;
+
+pub const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
+/// Largest error body read when rewriting a response into problem details
+const MAX_REWRITTEN_BODY: usize = 16 * 1024;
+
+/// Problem details (RFC 7807)
 #[derive(Debug, Serialize, Schematic)]
-pub struct ErrorResponse {
-    pub error: String,
+pub struct ProblemDetails {
+    /// `urn:todoki:problem:<code>`
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    /// Reason phrase of the status
+    pub title: String,
+    pub status: u16,
+    /// What went wrong with this request
+    pub detail: String,
+    /// Machine-readable error code, e.g. `not_found`
+    pub code: String,
+    /// ID of the request in the server logs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Problems with individual fields of the request
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+/// A problem with one field of the request
+#[derive(Debug, Clone, PartialEq, Serialize, Schematic)]
+pub struct FieldError {
+    /// Path of the field, e.g. `name` or `env.PATH`
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
 }
 
 pub struct ApiError {
     pub status: StatusCode,
+    /// Machine-readable error code
+    pub code: &'static str,
     pub message: String,
+    pub errors: Vec<FieldError>,
 }
 
 impl ApiError {
-    pub fn unauthorized() -> Self {
+    fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
         Self {
-            status: StatusCode::UNAUTHORIZED,
-            message: "Unauthorized".to_string(),
+            status,
+            code,
+            message: message.into(),
+            errors: Vec::new(),
         }
     }
 
+    pub fn unauthorized() -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, "unauthorized", "Unauthorized")
+    }
+
+    pub fn forbidden(msg: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, "forbidden", msg)
+    }
+
     pub fn not_found(msg: impl Into<String>) -> Self {
-        Self {
-            status: StatusCode::NOT_FOUND,
-            message: msg.into(),
-        }
+        Self::new(StatusCode::NOT_FOUND, "not_found", msg)
     }
 
     pub fn internal(msg: impl Into<String>) -> Self {
-        Self {
-            status: StatusCode::INTERNAL_SERVER_ERROR,
-            message: msg.into(),
-        }
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", msg)
     }
 
     pub fn bad_request(msg: impl Into<String>) -> Self {
-        Self {
-            status: StatusCode::BAD_REQUEST,
-            message: msg.into(),
+        Self::new(StatusCode::BAD_REQUEST, "bad_request", msg)
+    }
+
+    pub fn conflict(msg: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, "conflict", msg)
+    }
+
+    /// 429 for a request over one of the project's quotas
+    pub fn quota_exceeded(msg: impl Into<String>) -> Self {
+        Self::new(StatusCode::TOO_MANY_REQUESTS, "quota_exceeded", msg)
+    }
+
+    /// 422 listing the problem with each field
+    pub fn validation(errors: Vec<FieldError>) -> Self {
+        let mut error = Self::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "validation_failed",
+            "Request validation failed",
+        );
+        error.errors = errors;
+        error
+    }
+
+    fn into_problem(self) -> ProblemDetails {
+        ProblemDetails {
+            problem_type: format!("urn:todoki:problem:{}", self.code),
+            title: self
+                .status
+                .canonical_reason()
+                .unwrap_or("Error")
+                .to_string(),
+            status: self.status.as_u16(),
+            detail: self.message,
+            code: self.code.to_string(),
+            request_id: request_id::current(),
+            errors: self.errors,
         }
     }
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        (self.status, Json(ErrorResponse { error: self.message })).into_response()
+        let status = self.status;
+        if status.is_server_error() {
+            tracing::error!(
+                request_id = ?request_id::current(),
+                code = self.code,
+                error = %self.message,
+                "request failed"
+            );
+        }
+        let mut response = (status, Json(self.into_problem())).into_response();
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_CONTENT_TYPE));
+        response
+    }
+}
+
+impl From<crate::models::QuotaExceeded> for ApiError {
+    fn from(exceeded: crate::models::QuotaExceeded) -> Self {
+        Self::quota_exceeded(exceeded.to_string())
     }
 }
 
 impl From<crate::TodokiError> for ApiError {
     fn from(e: crate::TodokiError) -> Self {
-        Self {
-            status: e.to_status_code(),
-            message: e.to_string(),
+        let code = match &e {
+            crate::TodokiError::Auth(_) => "unauthorized",
+            crate::TodokiError::NotFound(_) => "not_found",
+            _ => "internal_error",
+        };
+        Self::new(e.to_status_code(), code, e.to_string())
+    }
+}
+
+/// Error code for a status without a more specific one
+fn status_code_name(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "bad_request",
+        StatusCode::UNAUTHORIZED => "unauthorized",
+        StatusCode::FORBIDDEN => "forbidden",
+        StatusCode::NOT_FOUND => "not_found",
+        StatusCode::METHOD_NOT_ALLOWED => "method_not_allowed",
+        StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+        StatusCode::UNPROCESSABLE_ENTITY => "invalid_body",
+        StatusCode::TOO_MANY_REQUESTS => "too_many_requests",
+        s if s.is_server_error() => "internal_error",
+        _ => "error",
+    }
+}
+
+/// Rewrite error responses that are not problem details yet
+///
+/// Covers what never reaches a handler's [`ApiError`]: extractor
+/// rejections (plain text), unknown routes and methods (empty body).
+/// WebSocket upgrades and successful responses pass through untouched.
+pub async fn problem_middleware(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    let status = response.status();
+    if !(status.is_client_error() || status.is_server_error()) {
+        return response;
+    }
+    let is_problem = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(PROBLEM_CONTENT_TYPE.as_bytes()));
+    if is_problem {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let detail = match to_bytes(body, MAX_REWRITTEN_BODY).await {
+        Ok(bytes) if !bytes.is_empty() => String::from_utf8_lossy(&bytes).trim().to_string(),
+        _ => status.canonical_reason().unwrap_or("Error").to_string(),
+    };
+    let error = ApiError::new(status, status_code_name(status), detail);
+    let mut rewritten = error.into_response();
+    // Keep headers such as `Allow` and `WWW-Authenticate`
+    for (name, value) in parts.headers.iter() {
+        if name != CONTENT_TYPE && name != gotcha::axum::http::header::CONTENT_LENGTH {
+            rewritten.headers_mut().insert(name.clone(), value.clone());
         }
     }
+    rewritten
 }
 
 impl gotcha::Responsible for ApiError {
@@ -66,25 +232,51 @@ impl gotcha::Responsible for ApiError {
             default: None,
             data: BTreeMap::default(),
         };
-        response.data.insert(
-            "4XX".to_string(),
-            oas::Referenceable::Data(oas::Response {
-                description: "Error response".to_string(),
-                headers: None,
-                content: Some(BTreeMap::from([(
-                    "application/json".to_string(),
-                    oas::MediaType {
-                        schema: Some(oas::Referenceable::Data(
-                            ErrorResponse::generate_schema().schema,
-                        )),
-                        example: None,
-                        examples: None,
-                        encoding: None,
-                    },
-                )])),
-                links: None,
-            }),
-        );
+        for (status, description) in [("4XX", "Client error"), ("5XX", "Server error")] {
+            response.data.insert(
+                status.to_string(),
+                oas::Referenceable::Data(oas::Response {
+                    description: description.to_string(),
+                    headers: None,
+                    content: Some(BTreeMap::from([(
+                        PROBLEM_CONTENT_TYPE.to_string(),
+                        oas::MediaType {
+                            schema: Some(oas::Referenceable::Data(
+                                ProblemDetails::generate_schema().schema,
+                            )),
+                            example: None,
+                            examples: None,
+                            encoding: None,
+                        },
+                    )])),
+                    links: None,
+                }),
+            );
+        }
         response
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_problem_details() {
+        let problem = ApiError::validation(vec![FieldError::new("color", "must be a hex color")])
+            .into_problem();
+        let json = serde_json::to_value(&problem).unwrap();
+        assert_eq!(json["type"], "urn:todoki:problem:validation_failed");
+        assert_eq!(json["title"], "Unprocessable Entity");
+        assert_eq!(json["status"], 422);
+        assert_eq!(json["code"], "validation_failed");
+        assert_eq!(json["errors"][0]["field"], "color");
+        // No request is being handled
+        assert!(json.get("request_id").is_none());
+
+        let json = serde_json::to_value(ApiError::not_found("Task 1 not found").into_problem())
+            .unwrap();
+        assert_eq!(json["detail"], "Task 1 not found");
+        assert!(json.get("errors").is_none());
+    }
+}
@@ -1,10 +1,16 @@
 use crate::api::error::ApiError;
-use crate::event_bus::Event;
-use crate::{Publisher, Relays, Subscriber};
-use gotcha::axum::extract::{Query, State};
+use crate::auth::{agent_token, AuthContext};
+use crate::config::Settings;
+use crate::event_bus::{DataFilter, Event, EventCount, EventGroupBy};
+use crate::handlers::EventHandler;
+use crate::models::DeadLetter;
+use crate::quota;
+use crate::{Db, Publisher, Relays, ReqTracker, Reviewer, Subscriber};
+use gotcha::axum::extract::{Path, Query, State};
+use gotcha::axum::Extension;
 use gotcha::{Json, Schematic};
 use serde::{Deserialize, Serialize};
-use todoki_protocol::event_bus::EventMessage;
+use todoki_protocol::event_bus::{BuiltinEvent, EventKind, EventMessage, EVENT_SCHEMA_VERSION};
 use uuid::Uuid;
 
 // ============================================================================
@@ -25,8 +31,21 @@ pub struct EventQueryParams {
     /// Filter by task ID
     pub task_id: Option<Uuid>,
 
+    /// Filter by correlation ID (every event of one execution)
+    pub correlation_id: Option<Uuid>,
+
+    /// Conditions on event data (comma-separated `data.<path>=<value>`,
+    /// e.g., "data.relay_id=r1,data.success=false")
+    pub filter: Option<String>,
+
     /// Max events to return (default: 100, max: 1000)
     pub limit: Option<usize>,
+
+    /// Return event counts grouped by "kind", "agent" or "hour" instead of events
+    pub group_by: Option<EventGroupBy>,
+
+    /// Only count events at or after this time (aggregation only)
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 
@@ -37,6 +56,15 @@ pub struct ReplayParams {
     pub kinds: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Schematic)]
+pub struct DeadLetterQueryParams {
+    /// Include dead letters that have been reprocessed (default: false)
+    pub include_resolved: Option<bool>,
+
+    /// Max dead letters to return (default: 100, max: 1000)
+    pub limit: Option<i64>,
+}
+
 // ============================================================================
 // Request/Response DTOs
 // ============================================================================
@@ -45,6 +73,9 @@ pub struct ReplayParams {
 pub struct EventQueryResponse {
     pub events: Vec<Event>,
     pub next_cursor: i64,
+    /// Grouped counts, present instead of events when `group_by` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counts: Option<Vec<EventCount>>,
 }
 
 /// Request to emit an event via HTTP API
@@ -64,6 +95,11 @@ pub struct EmitEventRequest {
 
 /// GET /api/event-bus
 /// Query events with cursor-based pagination
+///
+/// With `group_by`, returns counts per kind, agent or hour instead of
+/// events, so dashboards can chart volume without pulling raw rows.
+/// `filter` narrows either to events whose data matches, e.g.
+/// `data.success=false`; values are JSON literals or else strings.
 #[gotcha::api]
 pub async fn query_events(
     State(subscriber): State<Subscriber>,
@@ -76,12 +112,42 @@ pub async fn query_events(
 
     let kinds_slice = kinds_vec.as_deref();
 
+    let data = params
+        .filter
+        .as_deref()
+        .map(DataFilter::parse)
+        .transpose()
+        .map_err(ApiError::bad_request)?;
+
+    if let Some(group_by) = params.group_by {
+        let counts = subscriber
+            .aggregate(
+                group_by,
+                params.cursor,
+                params.since,
+                kinds_slice,
+                params.agent_id,
+                params.task_id,
+                data.as_ref(),
+            )
+            .await
+            .map_err(|e| ApiError::internal(e.to_string()))?;
+
+        return Ok(Json(EventQueryResponse {
+            events: Vec::new(),
+            next_cursor: params.cursor,
+            counts: Some(counts),
+        }));
+    }
+
     let events = subscriber
         .poll(
             params.cursor,
             kinds_slice,
             params.agent_id,
             params.task_id,
+            params.correlation_id,
+            data.as_ref(),
             params.limit,
         )
         .await
@@ -89,7 +155,11 @@ pub async fn query_events(
 
     let next_cursor = events.last().map(|e| e.cursor).unwrap_or(params.cursor);
 
-    Ok(Json(EventQueryResponse { events, next_cursor }))
+    Ok(Json(EventQueryResponse {
+        events,
+        next_cursor,
+        counts: None,
+    }))
 }
 
 /// GET /api/event-bus/latest
@@ -136,10 +206,18 @@ pub async fn replay_events(
 /// - Standalone agents that connect via HTTP
 /// - Frontend for user actions (permission responses, etc.)
 /// - External integrations
+///
+/// Payloads of builtin kinds must match their todoki-protocol struct;
+/// malformed ones are rejected with the path of the offending field.
+/// Events about a task over its project's hourly quota get a 429.
 #[gotcha::api]
 pub async fn emit_event(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
     State(publisher): State<Publisher>,
     State(relays): State<Relays>,
+    State(reviewer): State<Reviewer>,
+    State(settings): State<Settings>,
     Json(req): Json<EmitEventRequest>,
 ) -> Result<Json<i64>, ApiError> {
     // Parse agent_id from string to Uuid first (before consuming message)
@@ -150,26 +228,87 @@ pub async fn emit_event(
     // Extract kind and data from typed EventMessage
     let (kind, mut data) = req.message.into_parts();
 
+    // Agent tokens may only emit whitelisted kinds, as their own session
+    let (agent_id, task_id, session_id) = match auth.agent_scope() {
+        Some(scope) => {
+            if !agent_token::kind_allowed(&settings.agent_tokens, &kind) {
+                return Err(ApiError::forbidden(format!(
+                    "agent tokens may not emit {}",
+                    kind
+                )));
+            }
+            (scope.agent_id, scope.task_id, Some(scope.session_id))
+        }
+        None => (agent_id, task_id, req.session_id),
+    };
+
     // For permission.responded events, inject relay_id from pending permissions
     // This fixes the routing bug where frontend sends permission responses without relay_id
-    if kind == "permission.responded" {
-        if let Some(request_id) = data.get("request_id").and_then(|v| v.as_str()) {
-            if let Some((relay_id, _session_id)) = relays.get_pending_permission(request_id).await {
-                if let Some(obj) = data.as_object_mut() {
-                    obj.insert("relay_id".to_string(), serde_json::Value::String(relay_id));
-                }
+    let answered_request_id = if kind == "permission.responded" {
+        data.get("request_id")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    } else {
+        None
+    };
+    if let Some(request_id) = &answered_request_id {
+        // Approvals of two-person requests have to be counted
+        if relays.required_approvals(request_id).await > Some(1) {
+            return Err(ApiError::bad_request(format!(
+                "permission request {} needs two approvals; answer it with \
+                 POST /api/permissions/{}/respond",
+                request_id, request_id
+            )));
+        }
+        if let Some((relay_id, _session_id)) = relays.get_pending_permission(request_id).await {
+            if let Some(obj) = data.as_object_mut() {
+                obj.insert("relay_id".to_string(), serde_json::Value::String(relay_id));
             }
         }
     }
 
+    // Reject payloads that don't match the protocol struct for their kind
+    BuiltinEvent::validate(&kind, &data).map_err(|e| ApiError::bad_request(e.to_string()))?;
+
+    // Events about a task count against its project's hourly quota; answers
+    // to permission requests always go through so agents are not stuck
+    if answered_request_id.is_none()
+        && let Some(task_id) = task_id
+        && let Some(task) = db.get_task_by_id(task_id).await?
+        && let Some(exceeded) = quota::check_event(&db, &settings.quotas, task.project_id).await?
+    {
+        return Err(exceeded.into());
+    }
+
+    // Permission requests carry the server's review, if any
+    let reviewed = if kind == EventKind::PERMISSION_REQUESTED {
+        reviewer.annotate(task_id, &mut data).await
+    } else {
+        None
+    };
+
+    // Events about a session (e.g. permission responses) join its execution
+    let correlation_session_id = session_id.or_else(|| {
+        data.get("session_id")
+            .and_then(|v| v.as_str())
+            .and_then(|s| Uuid::parse_str(s).ok())
+    });
+    let correlation_id = match correlation_session_id {
+        Some(session_id) => db.get_session_correlation(session_id).await?,
+        None => None,
+    };
+
     let event = Event {
         cursor: 0, // Will be assigned by store
         kind,
         time: chrono::Utc::now(),
         agent_id,
-        session_id: req.session_id,
+        session_id,
         task_id,
+        correlation_id,
+        request_id: None, // Filled in by the publisher
         data,
+        schema_version: EVENT_SCHEMA_VERSION,
     };
 
     let cursor = publisher
@@ -181,5 +320,64 @@ pub async fn emit_event(
             ApiError::internal(error_chain.join(": "))
         })?;
 
+    // An answered request no longer expires
+    if let Some(request_id) = &answered_request_id {
+        relays.remove_pending_permission(request_id).await;
+    }
+    if let Some((request, review)) = reviewed {
+        reviewer.apply(&request, &review).await;
+    }
+
     Ok(Json(cursor))
 }
+
+/// GET /api/event-bus/dead-letters
+/// List events whose background handling failed
+#[gotcha::api]
+pub async fn list_dead_letters(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Query(params): Query<DeadLetterQueryParams>,
+) -> Result<Json<Vec<DeadLetter>>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let limit = params.limit.unwrap_or(100).clamp(1, 1000);
+    let dead_letters = db
+        .list_dead_letters(params.include_resolved.unwrap_or(false), limit)
+        .await?;
+
+    Ok(Json(dead_letters))
+}
+
+/// POST /api/event-bus/dead-letters/:dead_letter_id/reprocess
+/// Run the failed handler on the stored event again
+///
+/// On success the dead letter is resolved; on failure its attempt count and
+/// error are updated and the error is returned.
+#[gotcha::api]
+pub async fn reprocess_dead_letter(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(tracker): State<ReqTracker>,
+    Path(dead_letter_id): Path<Uuid>,
+) -> Result<Json<DeadLetter>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let dead_letter = db
+        .get_dead_letter(dead_letter_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Dead letter {} not found", dead_letter_id)))?;
+
+    let handler = EventHandler::parse(&dead_letter.handler).ok_or_else(|| {
+        ApiError::bad_request(format!("Unknown event handler: {}", dead_letter.handler))
+    })?;
+
+    match handler.apply(&db, &tracker, &dead_letter.event).await {
+        Ok(()) => Ok(Json(db.resolve_dead_letter(dead_letter.id).await?)),
+        Err(e) => {
+            db.record_dead_letter(handler.as_str(), &dead_letter.event, &e.to_string())
+                .await?;
+            Err(ApiError::internal(format!("Reprocessing failed: {}", e)))
+        }
+    }
+}
@@ -6,10 +6,15 @@
 //! - Event kind filtering
 //! - Automatic reconnection support
 //!
-//! **Relay Mode**: When relay_id is provided, this endpoint acts as the unified
-//! communication channel for relay connections (replaces the old /ws/relay).
+//! `/ws/event-bus` serves frontends only. Relays connect to `/ws/relay` with
+//! the relay token: they register with `relay.up`, receive only the commands
+//! meant for them ([`RELAY_COMMAND_KINDS`]) and may only emit their own
+//! reports ([`relay_may_emit`]).
+//!
+//! **Share tokens** (see `crate::auth::share_token`) connect in client mode
+//! but only receive the events of the session they were minted for.
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
 
 use axum::{
@@ -17,23 +22,35 @@ use axum::{
         ws::{Message, WebSocket},
         Query, State, WebSocketUpgrade,
     },
-    http::HeaderMap,
-    response::Response,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
 };
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info, warn, Instrument};
 use uuid::Uuid;
+use validator::Validate;
 
-use crate::config::Settings;
+use crate::api::validation;
+use crate::auth::share_token::{self, ShareScope};
+use crate::ci::BRANCH_ARTIFACT_TYPE;
+use crate::config::{QuotaSettings, Settings};
 use crate::db::DatabaseService;
 use crate::event_bus::kinds::EventKind;
-use crate::event_bus::{Event, EventPublisher, EventSubscriber};
+use crate::event_bus::{Event, EventPublisher, EventScope, EventSubscriber};
+use crate::logging::LogSampler;
 use crate::models::{AgentStatus, SessionStatus};
+use crate::quota;
 use crate::relay::RelayManager;
+use crate::watch::WatchRegistry;
+use todoki_protocol::event_bus::{
+    BuiltinEvent, PermissionFallback, PermissionRequestedData, RelayLifecycleData, ResourceUsage,
+};
+use todoki_protocol::relay_version::{self, ProtocolRange};
+use todoki_protocol::{relay_may_emit, RelayErrorCode, RELAY_COMMAND_KINDS};
 use todoki_protocol::AgentRole as ProtocolAgentRole;
-use crate::{Db, Publisher, Relays, Subscriber};
+use crate::{Db, Publisher, Relays, Subscriber, Watches};
 
 /// WebSocket subscription parameters
 #[derive(Debug, Deserialize)]
@@ -53,13 +70,26 @@ pub struct WsSubscribeParams {
     pub task_id: Option<String>,
 
     /// Optional relay ID filter (only events for this relay)
-    /// When provided, enables relay mode - the connection acts as a relay
     pub relay_id: Option<String>,
 
+    /// Only events of the tasks and sessions the token's user watches
+    #[serde(default)]
+    pub watched_only: bool,
+
     /// Optional token for authentication (prefer Authorization header)
     pub token: Option<String>,
 }
 
+/// Relay connection parameters
+#[derive(Debug, Deserialize)]
+pub struct RelayWsParams {
+    /// Stable ID of the relay
+    pub relay_id: String,
+
+    /// Optional relay token (prefer Authorization header)
+    pub token: Option<String>,
+}
+
 /// WebSocket message types (Server → Client)
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -72,6 +102,8 @@ enum WsMessage {
         agent_id: String,
         session_id: Option<String>,
         task_id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        correlation_id: Option<String>,
         data: serde_json::Value,
     },
 
@@ -84,11 +116,25 @@ enum WsMessage {
         cursor: i64,
     },
 
-    /// Relay registered confirmation (relay mode only)
-    Registered { relay_id: String },
+    /// Relay registered confirmation (relay mode only), carrying
+    /// `permissions.relay_fallback` when the server enforces one and the
+    /// protocol version agreed on
+    Registered {
+        relay_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        permission_fallback: Option<PermissionFallback>,
+        protocol_version: u16,
+        /// Versions the server speaks
+        supported_versions: ProtocolRange,
+    },
 
     /// Error message
-    Error { message: String },
+    Error {
+        message: String,
+        /// Machine-readable reason, e.g. `incompatible_protocol`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        code: Option<&'static str>,
+    },
 
     /// Heartbeat ping
     Ping,
@@ -111,58 +157,58 @@ enum ClientMessage {
 }
 
 /// GET /ws/event-bus
-/// Subscribe to real-time events via WebSocket
+/// Subscribe to real-time events via WebSocket (frontends)
 ///
 /// Query Parameters:
 /// - kinds: Comma-separated event kinds (e.g., "task.created,agent.*")
 /// - cursor: Starting cursor for replay (optional)
 /// - agent_id: Filter by agent ID (optional)
 /// - task_id: Filter by task ID (optional)
-/// - relay_id: Relay ID for relay mode (optional)
+/// - relay_id: Filter by relay ID (optional)
 ///
 /// Example:
 /// ```
 /// ws://localhost:3000/ws/event-bus?kinds=task.*&cursor=100
 /// ```
 ///
-/// Relay Mode Example:
-/// ```
-/// ws://localhost:3000/ws/event-bus?relay_id=abc123&kinds=relay.*,permission.responded
-/// ```
+/// The relay token is refused here; relays connect to `/ws/relay`.
 pub async fn event_bus_websocket(
     ws: WebSocketUpgrade,
     headers: HeaderMap,
     State(publisher): State<Publisher>,
     State(subscriber): State<Subscriber>,
+    State(watches): State<Watches>,
     State(settings): State<Settings>,
-    State(relays): State<Relays>,
-    State(db): State<Db>,
     Query(params): Query<WsSubscribeParams>,
 ) -> Response {
     // Authenticate: prefer Bearer token in header, fall back to query parameter
-    let auth_header = headers
-        .get(axum::http::header::AUTHORIZATION)
-        .and_then(|h| h.to_str().ok());
-    let bearer = auth_header.and_then(|auth| auth.strip_prefix("Bearer "));
+    let bearer = bearer_token(&headers);
+
+    // Relays used to connect here; point them at their own endpoint
+    if bearer.or(params.token.as_deref()) == Some(settings.relay_token.as_str()) {
+        warn!(relay_id = ?params.relay_id, "Relay token used on /ws/event-bus");
+        return (
+            StatusCode::BAD_REQUEST,
+            "relays connect to /ws/relay; /ws/event-bus is for frontends",
+        )
+            .into_response();
+    }
 
-    // For relay mode, check relay_token; for client mode, check user_token
-    let is_relay_mode = params.relay_id.is_some();
-    let expected_token = if is_relay_mode {
-        &settings.relay_token
-    } else {
-        &settings.user_token
+    // Clients authenticate with a user token, an OIDC session token or a
+    // session share token
+    let share_scope = |token: &str| share_token::verify(&settings.agent_tokens.secret, token);
+    let token_valid = |token: &str| {
+        crate::auth::user_token_id(&settings, token).is_some() || share_scope(token).is_some()
     };
 
     let is_authenticated = match (bearer, params.token.as_deref()) {
-        (Some(t), _) if t == expected_token => true,
+        (Some(t), _) if token_valid(t) => true,
         (Some(_), _) => {
             warn!("Invalid Bearer token provided for WebSocket event-bus");
             false
         }
-        (None, Some(t)) if t == expected_token => {
-            if !is_relay_mode {
-                warn!("WebSocket authenticated via query token; prefer Authorization header");
-            }
+        (None, Some(t)) if token_valid(t) => {
+            warn!("WebSocket authenticated via query token; prefer Authorization header");
             true
         }
         (None, Some(_)) => {
@@ -172,16 +218,28 @@ pub async fn event_bus_websocket(
         _ => false,
     };
 
+    // Clients only receive the notification events of their own user
+    let user_id = if is_authenticated {
+        bearer
+            .or(params.token.as_deref())
+            .and_then(|t| crate::auth::user_token_id(&settings, t))
+    } else {
+        None
+    };
+    // Share tokens are read-only and only see the session they were minted for
+    let share = if is_authenticated && user_id.is_none() {
+        bearer.or(params.token.as_deref()).and_then(share_scope)
+    } else {
+        None
+    };
+
     if !is_authenticated {
-        warn!(
-            relay_mode = is_relay_mode,
-            "Unauthorized WebSocket connection to event-bus"
-        );
+        warn!("Unauthorized WebSocket connection to event-bus");
     }
 
     info!(
         authenticated = is_authenticated,
-        relay_mode = is_relay_mode,
+        shared_session_id = ?share.as_ref().map(|s| s.shared_session_id),
         relay_id = ?params.relay_id,
         kinds = ?params.kinds,
         cursor = ?params.cursor,
@@ -190,30 +248,100 @@ pub async fn event_bus_websocket(
 
     let publisher = publisher.0.clone();
     let subscriber = subscriber.0.clone();
-    let relays = relays.0.clone();
-    let db = db.0.clone();
+    let watches = watches.0.clone();
+    let log_sample_every = settings.log.sample_every;
 
     ws.on_upgrade(move |socket| {
         handle_event_bus_socket(
             socket,
             publisher,
             subscriber,
+            watches,
             params,
             is_authenticated,
+            user_id,
+            share,
+            log_sample_every,
+        )
+    })
+}
+
+/// GET /ws/relay
+/// Relay connection, authenticated with the relay token
+///
+/// Query Parameters:
+/// - relay_id: Stable ID of the relay
+///
+/// Example:
+/// ```
+/// ws://localhost:3000/ws/relay?relay_id=abc123
+/// ```
+///
+/// The relay has to register with `relay.up` before anything else; a later
+/// connection with the same relay ID replaces this one.
+pub async fn relay_websocket(
+    ws: WebSocketUpgrade,
+    headers: HeaderMap,
+    State(publisher): State<Publisher>,
+    State(settings): State<Settings>,
+    State(relays): State<Relays>,
+    State(db): State<Db>,
+    Query(params): Query<RelayWsParams>,
+) -> Response {
+    let token = bearer_token(&headers).or(params.token.as_deref());
+    if token != Some(settings.relay_token.as_str()) {
+        warn!(relay_id = %params.relay_id, "Unauthorized relay WebSocket connection");
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    if params.relay_id.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, "relay_id must not be empty").into_response();
+    }
+
+    info!(relay_id = %params.relay_id, "WebSocket relay connection");
+
+    let publisher = publisher.0.clone();
+    let relays = relays.0.clone();
+    let db = db.0.clone();
+    let permission_fallback = settings.permissions.relay_fallback;
+    let quotas = settings.quotas.clone();
+    let sampler = LogSampler::new(settings.log.sample_every);
+    let relay_id = params.relay_id;
+
+    ws.on_upgrade(move |socket| {
+        // Everything logged for the connection carries who is on the other end
+        let span = tracing::info_span!("relay", relay_id = %relay_id);
+        handle_relay_mode(
+            socket,
+            publisher,
+            relay_id,
             relays,
             db,
+            permission_fallback,
+            quotas,
+            sampler,
         )
+        .instrument(span)
     })
 }
 
+/// Bearer token of the Authorization header
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|auth| auth.strip_prefix("Bearer "))
+}
+
 async fn handle_event_bus_socket(
     socket: WebSocket,
     publisher: Arc<EventPublisher>,
     subscriber: Arc<EventSubscriber>,
+    watches: Arc<WatchRegistry>,
     params: WsSubscribeParams,
     is_authenticated: bool,
-    relays: Arc<RelayManager>,
-    db: Arc<DatabaseService>,
+    user_id: Option<String>,
+    share: Option<ShareScope>,
+    log_sample_every: u64,
 ) {
     // Close connection if not authenticated
     if !is_authenticated {
@@ -222,43 +350,53 @@ async fn handle_event_bus_socket(
         return;
     }
 
-    // Check if this is relay mode
-    if let Some(relay_id) = params.relay_id.clone() {
-        handle_relay_mode(
-            socket,
-            publisher,
-            subscriber,
-            relay_id,
-            params,
-            relays,
-            db,
-        )
-        .await;
-    } else {
-        handle_client_mode(socket, publisher, subscriber, params).await;
-    }
+    // Everything logged for the connection carries who is on the other end
+    let span = tracing::info_span!(
+        "event_bus_client",
+        user_id = ?user_id,
+        session_id = ?share.as_ref().map(|s| s.shared_session_id),
+    );
+    let sampler = LogSampler::new(log_sample_every);
+    handle_client_mode(
+        socket, publisher, subscriber, watches, params, user_id, share, sampler,
+    )
+    .instrument(span)
+    .await;
+}
+
+/// A relay registered on a `/ws/relay` connection
+#[derive(Debug, Clone, Copy)]
+struct Registration {
+    connection_id: Uuid,
+    /// Protocol version agreed on in `relay.up`
+    protocol_version: u16,
 }
 
 /// Handle relay mode - unified communication channel for relays
 async fn handle_relay_mode(
     socket: WebSocket,
     publisher: Arc<EventPublisher>,
-    _subscriber: Arc<EventSubscriber>,
     relay_id: String,
-    params: WsSubscribeParams,
     relays: Arc<RelayManager>,
     db: Arc<DatabaseService>,
+    permission_fallback: Option<PermissionFallback>,
+    quotas: QuotaSettings,
+    sampler: LogSampler,
 ) {
     let (mut tx, mut rx) = socket.split();
+    // Per-message debug logs, sampled
+    let mut forwarded_log = sampler.clone();
+    let mut received_log = sampler;
+
+    // Relays only ever see the commands meant for them
+    let kinds_filter: Option<Vec<String>> =
+        Some(RELAY_COMMAND_KINDS.iter().map(|k| k.to_string()).collect());
 
-    // Parse event kind filters (relay typically subscribes to relay.* and permission.responded)
-    let kinds_filter: Option<Vec<String>> = params
-        .kinds
-        .as_ref()
-        .map(|s| s.split(',').map(|k| k.trim().to_string()).collect());
+    // None until the relay is registered
+    let mut registration: Option<Registration> = None;
 
-    // Track if relay is registered
-    let mut is_registered = false;
+    // Correlation IDs of the sessions this relay emitted events for
+    let mut correlations: HashMap<String, Option<Uuid>> = HashMap::new();
 
     // Subscribe to real-time events
     let mut event_rx = publisher.subscribe();
@@ -284,9 +422,10 @@ async fn handle_relay_mode(
                 match event {
                     Ok(event) => {
                         // Only forward events to registered relays
-                        if !is_registered {
+                        let Some(Registration { protocol_version: version, .. }) = registration
+                        else {
                             continue;
-                        }
+                        };
 
                         if should_send_event(&event, &kinds_filter) {
                             // Check relay_id filter
@@ -302,6 +441,19 @@ async fn handle_relay_mode(
                                 }
                             }
 
+                            // Older relays get the event without fields they do not know
+                            let mut data = event.data.clone();
+                            if let Err(e) =
+                                relay_version::downgrade(&event.kind, &mut data, version)
+                            {
+                                warn!(
+                                    relay_id = %relay_id,
+                                    error = %e,
+                                    "Event not forwarded to older relay"
+                                );
+                                continue;
+                            }
+
                             let ws_msg = WsMessage::Event {
                                 cursor: event.cursor,
                                 kind: event.kind.clone(),
@@ -309,7 +461,8 @@ async fn handle_relay_mode(
                                 agent_id: event.agent_id.to_string(),
                                 session_id: event.session_id.map(|id| id.to_string()),
                                 task_id: event.task_id.map(|id| id.to_string()),
-                                data: event.data.clone(),
+                                correlation_id: event.correlation_id.map(|id| id.to_string()),
+                                data,
                             };
 
                             if let Ok(json) = serde_json::to_string(&ws_msg) {
@@ -318,6 +471,14 @@ async fn handle_relay_mode(
                                     break;
                                 }
                             }
+                            if let Some(skipped) = forwarded_log.sample() {
+                                debug!(
+                                    kind = %event.kind,
+                                    session_id = ?event.session_id,
+                                    skipped,
+                                    "Event forwarded to relay"
+                                );
+                            }
                         }
                     }
                     Err(broadcast::error::RecvError::Lagged(n)) => {
@@ -338,15 +499,22 @@ async fn handle_relay_mode(
                         if let Ok(client_msg) = serde_json::from_str::<ClientMessage>(&text) {
                             match client_msg {
                                 ClientMessage::EmitEvent { kind, data } => {
+                                    if let Some(skipped) = received_log.sample() {
+                                        let session_id = data.get("session_id").and_then(|v| v.as_str());
+                                        debug!(kind = %kind, session_id, skipped, "Relay event received");
+                                    }
                                     // Handle relay emitted events
                                     let result = handle_relay_event(
                                         &kind,
                                         &data,
                                         &relay_id,
-                                        &mut is_registered,
+                                        &mut registration,
                                         &relays,
                                         &db,
                                         &publisher,
+                                        permission_fallback,
+                                        &quotas,
+                                        &mut correlations,
                                         &mut tx,
                                     ).await;
 
@@ -388,6 +556,21 @@ async fn handle_relay_mode(
 
             // Send periodic heartbeat
             _ = heartbeat_interval.tick() => {
+                // A newer connection registered with the same relay ID
+                if let Some(registered) = registration
+                    && !relays.is_current_connection(&relay_id, registered.connection_id).await
+                {
+                    warn!(relay_id = %relay_id, "Relay connection superseded, closing");
+                    let err_msg = WsMessage::Error {
+                        message: format!("another connection registered as relay {}", relay_id),
+                        code: Some(RelayErrorCode::SUPERSEDED),
+                    };
+                    if let Ok(json) = serde_json::to_string(&err_msg) {
+                        let _ = tx.send(Message::Text(json)).await;
+                    }
+                    break;
+                }
+
                 let ping_msg = WsMessage::Ping;
                 if let Ok(json) = serde_json::to_string(&ping_msg) {
                     if tx.send(Message::Text(json)).await.is_err() {
@@ -399,9 +582,13 @@ async fn handle_relay_mode(
         }
     }
 
-    // Cleanup on disconnect
-    if is_registered {
-        let orphaned_sessions = relays.unregister(&relay_id).await;
+    // Cleanup on disconnect, unless a newer connection took over
+    if let Some(registered) = registration
+        && relays
+            .is_current_connection(&relay_id, registered.connection_id)
+            .await
+    {
+        let orphaned_sessions = relays.unregister(&relay_id, registered.connection_id).await;
         if !orphaned_sessions.is_empty() {
             warn!(
                 relay_id = %relay_id,
@@ -421,46 +608,164 @@ async fn handle_relay_mode(
         }
 
         // Emit relay.down event
-        let event = Event::new(
-            EventKind::RELAY_DOWN,
-            Uuid::nil(),
-            serde_json::json!({ "relay_id": relay_id }),
-        );
-        let _ = publisher.emit(event).await;
+        let event = BuiltinEvent::RelayDown(RelayLifecycleData {
+            relay_id: relay_id.clone(),
+        });
+        let _ = publisher.emit_builtin(event, EventScope::system()).await;
     }
 
     info!(relay_id = %relay_id, "Relay mode connection closed");
 }
 
 /// Handle relay emitted events
+/// Fields of `relay.up` the server stores for the relay
+#[derive(Debug, Deserialize, Validate)]
+struct RelayRegistration {
+    #[serde(default = "unknown_relay_name")]
+    #[validate(
+        length(min = 1, max = "validation::MAX_NAME_LEN"),
+        custom(function = "validation::not_blank")
+    )]
+    name: String,
+    #[serde(default)]
+    #[validate(custom(function = "validation::paths"))]
+    safe_paths: Vec<String>,
+    #[serde(default)]
+    #[validate(custom(function = "validation::label_keys"))]
+    labels: HashMap<String, String>,
+    #[serde(default)]
+    #[validate(custom(function = "validation::uuids"))]
+    projects: Vec<String>,
+    #[validate(length(max = "validation::MAX_TEMPLATE_LEN"))]
+    setup_script: Option<String>,
+}
+
+fn unknown_relay_name() -> String {
+    "unknown".to_string()
+}
+
 async fn handle_relay_event(
     kind: &str,
     data: &serde_json::Value,
     relay_id: &str,
-    is_registered: &mut bool,
+    registration: &mut Option<Registration>,
     relays: &Arc<RelayManager>,
     db: &Arc<DatabaseService>,
     publisher: &Arc<EventPublisher>,
+    permission_fallback: Option<PermissionFallback>,
+    quotas: &QuotaSettings,
+    correlations: &mut HashMap<String, Option<Uuid>>,
     tx: &mut futures_util::stream::SplitSink<WebSocket, Message>,
 ) -> anyhow::Result<()> {
+    // Relays register first and may only emit their own reports
+    let refusal = if !relay_may_emit(kind) {
+        Some((
+            format!("relays may not emit {}", kind),
+            RelayErrorCode::KIND_NOT_ALLOWED,
+        ))
+    } else if registration.is_none() && kind != EventKind::RELAY_UP {
+        Some((
+            format!("{} before relay.up", kind),
+            RelayErrorCode::NOT_REGISTERED,
+        ))
+    } else {
+        None
+    };
+    if let Some((message, code)) = refusal {
+        warn!(relay_id = %relay_id, kind = %kind, code, "Refused relay event");
+        let err_msg = WsMessage::Error {
+            message,
+            code: Some(code),
+        };
+        if let Ok(json) = serde_json::to_string(&err_msg) {
+            tx.send(Message::Text(json)).await?;
+        }
+        return Ok(());
+    }
+
+    // Reject payloads that don't match the protocol; relay_id is injected by
+    // the server, so check with it filled in
+    let mut checked = data.clone();
+    if let Some(obj) = checked.as_object_mut() {
+        obj.entry("relay_id")
+            .or_insert_with(|| serde_json::Value::String(relay_id.to_string()));
+    }
+    if let Err(e) = BuiltinEvent::validate(kind, &checked) {
+        warn!(relay_id = %relay_id, error = %e, "Rejected malformed relay event");
+        let err_msg = WsMessage::Error {
+            message: e.to_string(),
+            code: None,
+        };
+        if let Ok(json) = serde_json::to_string(&err_msg) {
+            tx.send(Message::Text(json)).await?;
+        }
+        return Ok(());
+    }
+
     match kind {
         k if k == EventKind::RELAY_UP => {
-            // Register relay
-            let name = data.get("name").and_then(|v| v.as_str()).unwrap_or("unknown").to_string();
+            // Agree on a protocol version before anything else
+            let supported_versions = ProtocolRange::current();
+            let version = match supported_versions.negotiate(&ProtocolRange::from_relay_up(data)) {
+                Ok(version) => version,
+                Err(e) => {
+                    warn!(
+                        relay_id = %relay_id,
+                        error = %e,
+                        "Rejected relay with incompatible protocol"
+                    );
+                    let err_msg = WsMessage::Error {
+                        message: e.to_string(),
+                        code: Some(relay_version::INCOMPATIBLE_PROTOCOL),
+                    };
+                    if let Ok(json) = serde_json::to_string(&err_msg) {
+                        tx.send(Message::Text(json)).await?;
+                    }
+                    return Ok(());
+                }
+            };
+
+            // Register relay, refusing registrations with invalid fields
+            let registration = serde_json::from_value::<RelayRegistration>(data.clone())
+                .map_err(|e| format!("invalid relay registration: {}", e))
+                .and_then(|r| match r.validate() {
+                    Ok(()) => Ok(r),
+                    Err(errors) => Err(validation::field_errors(&errors)
+                        .iter()
+                        .map(|e| format!("{}: {}", e.field, e.message))
+                        .collect::<Vec<_>>()
+                        .join("; ")),
+                });
+            let registration = match registration {
+                Ok(registration) => registration,
+                Err(message) => {
+                    warn!(relay_id = %relay_id, error = %message, "Rejected relay registration");
+                    let err_msg = WsMessage::Error {
+                        message,
+                        code: None,
+                    };
+                    if let Ok(json) = serde_json::to_string(&err_msg) {
+                        tx.send(Message::Text(json)).await?;
+                    }
+                    return Ok(());
+                }
+            };
+            let name = registration.name;
             let role_str = data.get("role").and_then(|v| v.as_str()).unwrap_or("general");
             let role = ProtocolAgentRole::from_str(role_str);
-            let safe_paths: Vec<String> = data.get("safe_paths")
-                .and_then(|v| serde_json::from_value(v.clone()).ok())
-                .unwrap_or_default();
-            let labels: HashMap<String, String> = data.get("labels")
-                .and_then(|v| serde_json::from_value(v.clone()).ok())
-                .unwrap_or_default();
-            let projects: Vec<Uuid> = data.get("projects")
-                .and_then(|v| serde_json::from_value(v.clone()).ok())
-                .unwrap_or_default();
-            let setup_script = data.get("setup_script").and_then(|v| v.as_str()).map(|s| s.to_string());
-
-            relays.register(
+            let safe_paths = registration.safe_paths;
+            let labels = registration.labels;
+            // Validated above
+            let projects: Vec<Uuid> = registration
+                .projects
+                .iter()
+                .filter_map(|p| Uuid::parse_str(p).ok())
+                .collect();
+            let setup_script = registration.setup_script;
+            let reported_fallback: Option<PermissionFallback> = data.get("permission_fallback")
+                .and_then(|v| serde_json::from_value(v.clone()).ok());
+
+            let connection_id = relays.register(
                 relay_id.to_string(),
                 name.clone(),
                 role,
@@ -469,18 +774,33 @@ async fn handle_relay_event(
                 projects,
                 setup_script,
             ).await;
+            relays.set_protocol_version(relay_id, version).await;
 
-            *is_registered = true;
+            *registration = Some(Registration {
+                connection_id,
+                protocol_version: version,
+            });
 
             // Send registered confirmation
             let registered_msg = WsMessage::Registered {
                 relay_id: relay_id.to_string(),
+                permission_fallback,
+                protocol_version: version,
+                supported_versions,
             };
             if let Ok(json) = serde_json::to_string(&registered_msg) {
                 tx.send(Message::Text(json)).await?;
             }
 
-            info!(relay_id = %relay_id, name = %name, role = ?role, "Relay registered via Event Bus");
+            info!(
+                relay_id = %relay_id,
+                name = %name,
+                role = ?role,
+                permission_fallback = ?reported_fallback,
+                enforced_permission_fallback = ?permission_fallback,
+                protocol_version = version,
+                "Relay registered"
+            );
         }
 
         k if k == EventKind::RELAY_AGENT_OUTPUT => {
@@ -494,6 +814,18 @@ async fn handle_relay_event(
 
             let session_uuid = Uuid::parse_str(session_id_str)?;
 
+            let usage = data
+                .get("usage")
+                .and_then(|v| serde_json::from_value::<ResourceUsage>(v.clone()).ok());
+            if let Some(usage) = &usage {
+                db.update_session_resource_usage(session_uuid, usage).await?;
+                // Periodic samples of a running session change nothing else
+                if status_str == "running" {
+                    debug!(session_id = %session_id_str, usage = ?usage, "Session usage sampled");
+                    return Ok(());
+                }
+            }
+
             let session_status = match status_str {
                 "running" => SessionStatus::Running,
                 "exited" | "completed" => SessionStatus::Completed,
@@ -516,8 +848,13 @@ async fn handle_relay_event(
                     let _ = db.update_agent_status(session.agent_id, agent_status).await;
                 }
 
+                if let Err(e) = db.record_session_duration(session_uuid).await {
+                    warn!(session_id = %session_id_str, error = %e, "Failed to record session duration");
+                }
+
                 // Remove from active sessions
                 relays.remove_active_session(relay_id, session_id_str).await;
+                correlations.remove(session_id_str);
             }
 
             info!(
@@ -530,10 +867,8 @@ async fn handle_relay_event(
         }
 
         k if k == EventKind::RELAY_PERMISSION_REQUEST => {
-            let request_id = data.get("request_id").and_then(|v| v.as_str()).unwrap_or_default();
-            let session_id_str = data.get("session_id").and_then(|v| v.as_str()).unwrap_or_default();
-
-            relays.store_permission_request(relay_id, request_id, session_id_str).await;
+            let request: PermissionRequestedData = serde_json::from_value(data.clone())?;
+            relays.store_permission_request(relay_id, request).await;
         }
 
         k if k == EventKind::RELAY_ARTIFACT => {
@@ -546,6 +881,24 @@ async fn handle_relay_event(
             let session_uuid = Uuid::parse_str(session_id_str)?;
 
             if let Ok(Some(task)) = db.get_task_by_agent_id(agent_uuid).await {
+                // A branch reported again gets its new head checked from scratch
+                if artifact_type == BRANCH_ARTIFACT_TYPE
+                    && let Some(existing) = reported_branch(db, task.id, &artifact_data).await
+                {
+                    let _ = db.update_artifact(existing, artifact_data, None).await;
+                    return Ok(());
+                }
+                let bytes = artifact_data.to_string().len() as u64;
+                if let Some(exceeded) =
+                    quota::check_storage(db, quotas, task.project_id, bytes).await?
+                {
+                    warn!(
+                        task_id = %task.id,
+                        error = %exceeded,
+                        "dropping artifact over the storage quota"
+                    );
+                    return Ok(());
+                }
                 let _ = db.create_artifact(
                     task.id,
                     task.project_id,
@@ -569,70 +922,330 @@ async fn handle_relay_event(
                 error = ?error,
                 "Prompt completed"
             );
+
+            // Forward to Event Bus (session summaries and UIs react to it)
+            let correlation_id = session_correlation(data, db, correlations).await;
+            publisher.emit(relay_event(kind, data, relay_id, correlation_id)).await?;
         }
 
         // Forward spawn_completed/spawn_failed to Event Bus for request tracking
         k if k == EventKind::RELAY_SPAWN_COMPLETED || k == EventKind::RELAY_SPAWN_FAILED => {
-            let mut event_data = data.clone();
-            if let Some(obj) = event_data.as_object_mut() {
-                obj.insert("relay_id".to_string(), serde_json::Value::String(relay_id.to_string()));
-            }
-            let event = Event::new(kind.to_string(), Uuid::nil(), event_data);
-            publisher.emit(event).await?;
+            let correlation_id = session_correlation(data, db, correlations).await;
+            publisher.emit(relay_event(kind, data, relay_id, correlation_id)).await?;
         }
 
         _ => {
             // Forward other events to Event Bus
-            let mut event_data = data.clone();
-            if let Some(obj) = event_data.as_object_mut() {
-                obj.insert("relay_id".to_string(), serde_json::Value::String(relay_id.to_string()));
-            }
-            let event = Event::new(kind.to_string(), Uuid::nil(), event_data);
-            publisher.emit(event).await?;
+            let correlation_id = session_correlation(data, db, correlations).await;
+            publisher.emit(relay_event(kind, data, relay_id, correlation_id)).await?;
         }
     }
 
     Ok(())
 }
 
+/// A `branch` artifact of the task for the same repository and branch as
+/// `data`
+async fn reported_branch(
+    db: &DatabaseService,
+    task_id: Uuid,
+    data: &serde_json::Value,
+) -> Option<Uuid> {
+    let key = |data: &serde_json::Value| {
+        ["remote_url", "name"].map(|field| data.get(field).cloned().unwrap_or_default())
+    };
+    let artifacts = db.list_artifacts_by_task(task_id).await.ok()?;
+    artifacts
+        .into_iter()
+        .find(|a| a.artifact_type == BRANCH_ARTIFACT_TYPE && key(&a.data) == key(data))
+        .map(|a| a.id)
+}
+
+/// Event Bus event for a relay-emitted payload, tagged with the relay
+fn relay_event(
+    kind: &str,
+    data: &serde_json::Value,
+    relay_id: &str,
+    correlation_id: Option<Uuid>,
+) -> Event {
+    let mut event_data = data.clone();
+    if let Some(obj) = event_data.as_object_mut() {
+        obj.insert("relay_id".to_string(), serde_json::Value::String(relay_id.to_string()));
+    }
+    let mut event = Event::new(kind.to_string(), Uuid::nil(), event_data);
+    event.correlation_id = correlation_id;
+    event
+}
+
+/// Correlation ID of the session a relay payload belongs to
+///
+/// Looked up once per session and cached for the connection, since output
+/// events arrive many times a second.
+async fn session_correlation(
+    data: &serde_json::Value,
+    db: &DatabaseService,
+    correlations: &mut HashMap<String, Option<Uuid>>,
+) -> Option<Uuid> {
+    let session_id = data.get("session_id").and_then(|v| v.as_str())?;
+    if let Some(correlation_id) = correlations.get(session_id) {
+        return *correlation_id;
+    }
+
+    let correlation_id = match Uuid::parse_str(session_id) {
+        Ok(session_uuid) => match db.get_session_correlation(session_uuid).await {
+            Ok(correlation_id) => correlation_id,
+            Err(e) => {
+                warn!(session_id = %session_id, error = %e, "Failed to load session correlation");
+                return None;
+            }
+        },
+        Err(_) => None,
+    };
+    correlations.insert(session_id.to_string(), correlation_id);
+    correlation_id
+}
+
+/// Filters of a client-mode subscription
+struct ClientFilters {
+    kinds: Option<Vec<String>>,
+    agent_id: Option<Uuid>,
+    task_id: Option<Uuid>,
+    relay_id: Option<String>,
+    /// User of the connection; `notification.*` events of other users are
+    /// never sent
+    user_id: Option<String>,
+    /// Only events of this session (share tokens)
+    session_id: Option<Uuid>,
+    /// Routes permission requests to the watchers of their task or session
+    watches: Option<Arc<WatchRegistry>>,
+    /// Only events of what `user_id` watches; notifications still pass
+    watched_only: bool,
+}
+
+impl ClientFilters {
+    fn from_params(params: &WsSubscribeParams) -> Self {
+        Self {
+            kinds: params
+                .kinds
+                .as_ref()
+                .map(|s| s.split(',').map(|k| k.trim().to_string()).collect()),
+            agent_id: params
+                .agent_id
+                .as_ref()
+                .and_then(|s| Uuid::parse_str(s).ok()),
+            task_id: params
+                .task_id
+                .as_ref()
+                .and_then(|s| Uuid::parse_str(s).ok()),
+            relay_id: params.relay_id.clone(),
+            user_id: None,
+            session_id: None,
+            watches: None,
+            watched_only: params.watched_only,
+        }
+    }
+
+    /// Narrow the filters to the session a share token was minted for
+    fn shared(self, share: &ShareScope) -> Self {
+        Self {
+            agent_id: Some(share.shared_agent_id),
+            session_id: Some(share.shared_session_id),
+            ..self
+        }
+    }
+
+    fn matches(&self, event: &Event) -> bool {
+        if !should_send_event(event, &self.kinds) {
+            return false;
+        }
+        if event.kind.starts_with("notification.")
+            && event.data.get("user_id").and_then(|v| v.as_str()) != self.user_id.as_deref()
+        {
+            return false;
+        }
+        if self.agent_id.is_some_and(|id| event.agent_id != id) {
+            return false;
+        }
+        if self.task_id.is_some_and(|id| event.task_id != Some(id)) {
+            return false;
+        }
+        if let Some(session_id) = self.session_id
+            && event.session_id != Some(session_id)
+            && event.data.get("session_id").and_then(|v| v.as_str())
+                != Some(session_id.to_string().as_str())
+        {
+            return false;
+        }
+        if self.watched_only
+            && !event.kind.starts_with("notification.")
+            && !self
+                .watches
+                .as_ref()
+                .zip(self.user_id.as_deref())
+                .is_some_and(|(watches, user_id)| watches.watched_by(event, user_id))
+        {
+            return false;
+        }
+        // Share tokens see the permission requests of their own session
+        if self.session_id.is_none()
+            && let Some(watches) = &self.watches
+            && !watches.delivers(event, self.user_id.as_deref())
+        {
+            return false;
+        }
+        match &self.relay_id {
+            // Events without a relay_id in their data are skipped
+            Some(relay_id) => {
+                event.data.get("relay_id").and_then(|v| v.as_str()) == Some(relay_id.as_str())
+            }
+            None => true,
+        }
+    }
+}
+
+/// How far back backfilled cursors are remembered to drop late duplicates
+const BACKFILL_MEMORY: i64 = 10_000;
+
+/// Tracks the cursors seen on a client stream so gaps left by broadcast lag
+/// can be filled from the store
+///
+/// A cursor ahead of `last + 1` means events were dropped (or are still in
+/// flight from a concurrent emitter); the missing range is fetched from the
+/// store. Cursors delivered that way are remembered so the broadcast copy of
+/// an in-flight event isn't sent twice.
+#[derive(Debug, Default)]
+struct CursorTracker {
+    last: Option<i64>,
+    backfilled: BTreeSet<i64>,
+}
+
+impl CursorTracker {
+    fn starting_at(cursor: i64) -> Self {
+        Self {
+            last: (cursor > 0).then_some(cursor),
+            ..Self::default()
+        }
+    }
+
+    /// Missing range (exclusive start, inclusive end) before `cursor`
+    fn gap_before(&self, cursor: i64) -> Option<(i64, i64)> {
+        let last = self.last?;
+        (cursor > last + 1).then_some((last, cursor - 1))
+    }
+
+    /// Record a cursor delivered from the store while filling a gap
+    fn record_backfill(&mut self, cursor: i64) {
+        self.backfilled.insert(cursor);
+    }
+
+    /// Record a broadcast cursor; false when it was already backfilled
+    fn accept(&mut self, cursor: i64) -> bool {
+        match self.last {
+            Some(last) if cursor <= last => !self.backfilled.remove(&cursor),
+            _ => {
+                self.last = Some(cursor);
+                self.backfilled = self.backfilled.split_off(&(cursor - BACKFILL_MEMORY));
+                true
+            }
+        }
+    }
+}
+
+fn event_message(event: &Event) -> WsMessage {
+    WsMessage::Event {
+        cursor: event.cursor,
+        kind: event.kind.clone(),
+        time: event.time.to_rfc3339(),
+        agent_id: event.agent_id.to_string(),
+        session_id: event.session_id.map(|id| id.to_string()),
+        task_id: event.task_id.map(|id| id.to_string()),
+        correlation_id: event.correlation_id.map(|id| id.to_string()),
+        data: event.data.clone(),
+    }
+}
+
+type ClientSink = futures_util::stream::SplitSink<WebSocket, Message>;
+
+/// Send stored events in `(from, to]` that match the filters, in order
+///
+/// Returns how many were sent; `Err` when the client went away.
+async fn send_backfill(
+    tx: &mut ClientSink,
+    subscriber: &EventSubscriber,
+    filters: &ClientFilters,
+    tracker: &mut CursorTracker,
+    from: i64,
+    to: i64,
+) -> Result<usize, ()> {
+    let mut cursor = from;
+    let mut sent = 0;
+
+    while cursor < to {
+        let events = match subscriber.replay(cursor, to, filters.kinds.as_deref()).await {
+            Ok(events) => events,
+            Err(e) => {
+                error!(error = %e, from, to, "Failed to backfill event gap");
+                let err_msg = WsMessage::Error {
+                    message: format!("Failed to fetch missed events {}..{}: {}", from, to, e),
+                    code: None,
+                };
+                if let Ok(json) = serde_json::to_string(&err_msg) {
+                    let _ = tx.send(Message::Text(json)).await;
+                }
+                return Ok(sent);
+            }
+        };
+        let Some(last) = events.last() else {
+            break;
+        };
+        cursor = last.cursor;
+
+        for event in events.iter().filter(|e| filters.matches(e)) {
+            tracker.record_backfill(event.cursor);
+            if let Ok(json) = serde_json::to_string(&event_message(event)) {
+                tx.send(Message::Text(json)).await.map_err(|_| ())?;
+                sent += 1;
+            }
+        }
+    }
+
+    Ok(sent)
+}
+
 /// Handle client mode - standard event subscription
 async fn handle_client_mode(
     socket: WebSocket,
     publisher: Arc<EventPublisher>,
     subscriber: Arc<EventSubscriber>,
+    watches: Arc<WatchRegistry>,
     params: WsSubscribeParams,
+    user_id: Option<String>,
+    share: Option<ShareScope>,
+    mut sent_log: LogSampler,
 ) {
     let (mut tx, mut rx) = socket.split();
 
-    // Parse event kind filters
-    let kinds_filter: Option<Vec<String>> = params
-        .kinds
-        .as_ref()
-        .map(|s| s.split(',').map(|k| k.trim().to_string()).collect());
-
-    // Parse UUIDs
-    let agent_id_filter = params
-        .agent_id
-        .as_ref()
-        .and_then(|s| uuid::Uuid::parse_str(s).ok());
-    let task_id_filter = params
-        .task_id
-        .as_ref()
-        .and_then(|s| uuid::Uuid::parse_str(s).ok());
-
-    let relay_id_filter = params.relay_id.clone();
-
+    let mut filters = ClientFilters {
+        user_id,
+        watches: Some(watches),
+        ..ClientFilters::from_params(&params)
+    };
+    if let Some(share) = &share {
+        filters = filters.shared(share);
+    }
     let starting_cursor = params.cursor.unwrap_or(0);
 
     // Send subscription acknowledgment
     let sub_msg = WsMessage::Subscribed {
-        kinds: kinds_filter.clone(),
+        kinds: filters.kinds.clone(),
         cursor: starting_cursor,
     };
     if let Ok(json) = serde_json::to_string(&sub_msg) {
         let _ = tx.send(Message::Text(json)).await;
     }
 
+    let mut tracker = CursorTracker::starting_at(starting_cursor);
+
     // Step 1: Send historical events if cursor provided
     if starting_cursor > 0 {
         debug!(cursor = starting_cursor, "Replaying historical events");
@@ -640,10 +1253,12 @@ async fn handle_client_mode(
         match subscriber
             .poll(
                 starting_cursor,
-                kinds_filter.as_deref(),
-                agent_id_filter,
-                task_id_filter,
-                Some(1000), // Max 1000 events in replay
+                filters.kinds.as_deref(),
+                filters.agent_id,
+                filters.task_id,
+                None,
+                None,
+                Some(1000), // Max 1000 events in replay; the rest is filled as a gap
             )
             .await
         {
@@ -651,37 +1266,15 @@ async fn handle_client_mode(
                 let count = events.len();
                 let last_cursor = events.last().map(|e| e.cursor).unwrap_or(starting_cursor);
 
-                for event in events {
-                    if should_send_event(&event, &kinds_filter) {
-                        // Apply relay_id filter
-                        if let Some(ref relay_filter) = relay_id_filter {
-                            if let Some(relay_in_data) = event.data.get("relay_id") {
-                                if relay_in_data.as_str() != Some(relay_filter.as_str()) {
-                                    continue;
-                                }
-                            } else {
-                                continue;
-                            }
-                        }
-
-                        let ws_msg = WsMessage::Event {
-                            cursor: event.cursor,
-                            kind: event.kind.clone(),
-                            time: event.time.to_rfc3339(),
-                            agent_id: event.agent_id.to_string(),
-                            session_id: event.session_id.map(|id| id.to_string()),
-                            task_id: event.task_id.map(|id| id.to_string()),
-                            data: event.data.clone(),
-                        };
-
-                        if let Ok(json) = serde_json::to_string(&ws_msg) {
-                            if tx.send(Message::Text(json)).await.is_err() {
-                                error!("Failed to send historical event, connection closed");
-                                return;
-                            }
-                        }
+                for event in events.iter().filter(|e| filters.matches(e)) {
+                    if let Ok(json) = serde_json::to_string(&event_message(event))
+                        && tx.send(Message::Text(json)).await.is_err()
+                    {
+                        error!("Failed to send historical event, connection closed");
+                        return;
                     }
                 }
+                tracker.accept(last_cursor);
 
                 // Send replay complete marker
                 let complete_msg = WsMessage::ReplayComplete {
@@ -698,6 +1291,7 @@ async fn handle_client_mode(
                 error!(error = %e, "Failed to fetch historical events");
                 let err_msg = WsMessage::Error {
                     message: format!("Failed to fetch historical events: {}", e),
+                    code: None,
                 };
                 if let Ok(json) = serde_json::to_string(&err_msg) {
                     let _ = tx.send(Message::Text(json)).await;
@@ -720,55 +1314,40 @@ async fn handle_client_mode(
             event = event_rx.recv() => {
                 match event {
                     Ok(event) => {
-                        if should_send_event(&event, &kinds_filter) {
-                            // Check filters
-                            if let Some(ref agent_filter) = agent_id_filter {
-                                if event.agent_id != *agent_filter {
-                                    continue;
-                                }
-                            }
-                            if let Some(ref task_filter) = task_id_filter {
-                                if event.task_id != Some(*task_filter) {
-                                    continue;
-                                }
-                            }
-                            if let Some(ref relay_filter) = relay_id_filter {
-                                if let Some(relay_in_data) = event.data.get("relay_id") {
-                                    if relay_in_data.as_str() != Some(relay_filter.as_str()) {
-                                        continue;
-                                    }
-                                } else {
-                                    // No relay_id in event data, skip
-                                    continue;
-                                }
-                            }
-
-                            let ws_msg = WsMessage::Event {
-                                cursor: event.cursor,
-                                kind: event.kind.clone(),
-                                time: event.time.to_rfc3339(),
-                                agent_id: event.agent_id.to_string(),
-                                session_id: event.session_id.map(|id| id.to_string()),
-                                task_id: event.task_id.map(|id| id.to_string()),
-                                data: event.data.clone(),
-                            };
-
-                            if let Ok(json) = serde_json::to_string(&ws_msg) {
-                                if tx.send(Message::Text(json)).await.is_err() {
+                        // Fill any gap (lag drops, or the window between the
+                        // historical replay and subscribing) before this event
+                        if let Some((from, to)) = tracker.gap_before(event.cursor) {
+                            let backfill = send_backfill(
+                                &mut tx, &subscriber, &filters, &mut tracker, from, to,
+                            );
+                            match backfill.await {
+                                Ok(0) => {}
+                                Ok(count) => debug!(count, from, to, "Backfilled event gap"),
+                                Err(()) => {
                                     debug!("Client disconnected, closing event stream");
                                     break;
                                 }
                             }
                         }
+                        if !tracker.accept(event.cursor) || !filters.matches(&event) {
+                            continue;
+                        }
+
+                        if let Ok(json) = serde_json::to_string(&event_message(&event))
+                            && tx.send(Message::Text(json)).await.is_err()
+                        {
+                            debug!("Client disconnected, closing event stream");
+                            break;
+                        }
+                        if let Some(skipped) = sent_log.sample() {
+                            let cursor = event.cursor;
+                            debug!(kind = %event.kind, cursor, skipped, "Event sent to client");
+                        }
                     }
                     Err(broadcast::error::RecvError::Lagged(n)) => {
-                        warn!(lagged_events = n, "Event stream lagged, some events may be missed");
-                        let err_msg = WsMessage::Error {
-                            message: format!("Event stream lagged by {} events, consider reconnecting with cursor", n),
-                        };
-                        if let Ok(json) = serde_json::to_string(&err_msg) {
-                            let _ = tx.send(Message::Text(json)).await;
-                        }
+                        // The dropped events are fetched from the store when the
+                        // next event reveals the gap
+                        warn!(lagged_events = n, "Event stream lagged, backfilling from store");
                     }
                     Err(_) => {
                         error!("Event broadcast channel closed");
@@ -861,7 +1440,10 @@ mod tests {
             agent_id: Uuid::new_v4(),
             session_id: None,
             task_id: None,
+            correlation_id: None,
+            request_id: None,
             data: serde_json::json!({}),
+            schema_version: todoki_protocol::EVENT_SCHEMA_VERSION,
         }
     }
 
@@ -904,4 +1486,126 @@ mod tests {
 
         assert!(should_send_event(&event, &kinds));
     }
+
+    #[test]
+    fn test_notification_events_only_reach_their_user() {
+        let mut event = make_test_event("notification.unread");
+        event.data = serde_json::json!({"user_id": "oidc:alice", "unread": 3});
+        let filters = |user_id: Option<&str>| ClientFilters {
+            kinds: None,
+            agent_id: None,
+            task_id: None,
+            relay_id: None,
+            user_id: user_id.map(str::to_string),
+            session_id: None,
+            watches: None,
+            watched_only: false,
+        };
+
+        assert!(filters(Some("oidc:alice")).matches(&event));
+        assert!(!filters(Some("user")).matches(&event));
+        assert!(!filters(None).matches(&event));
+        assert!(filters(Some("user")).matches(&make_test_event("task.created")));
+    }
+
+    #[test]
+    fn test_shared_filters_only_match_their_session() {
+        let share = ShareScope {
+            shared_agent_id: Uuid::new_v4(),
+            shared_session_id: Uuid::new_v4(),
+            shared_by: "user".to_string(),
+            iat: 0,
+            exp: i64::MAX,
+        };
+        let params = WsSubscribeParams {
+            kinds: Some("agent.*".to_string()),
+            cursor: None,
+            agent_id: Some(Uuid::new_v4().to_string()),
+            task_id: None,
+            relay_id: None,
+            watched_only: false,
+            token: None,
+        };
+        let filters = ClientFilters::from_params(&params).shared(&share);
+
+        let mut output = make_test_event("agent.output_batch");
+        output.agent_id = share.shared_agent_id;
+        output.data = serde_json::json!({"session_id": share.shared_session_id.to_string()});
+        assert!(filters.matches(&output));
+
+        let mut scoped = make_test_event("agent.started");
+        scoped.agent_id = share.shared_agent_id;
+        scoped.session_id = Some(share.shared_session_id);
+        assert!(filters.matches(&scoped));
+
+        let mut other_session = output.clone();
+        other_session.data = serde_json::json!({"session_id": Uuid::new_v4().to_string()});
+        assert!(!filters.matches(&other_session));
+
+        let mut other_agent = output.clone();
+        other_agent.agent_id = Uuid::new_v4();
+        assert!(!filters.matches(&other_agent));
+
+        let mut unread = make_test_event("notification.unread");
+        unread.data = serde_json::json!({"user_id": "user"});
+        assert!(!filters.matches(&unread));
+    }
+
+    #[test]
+    fn test_watched_only_filters() {
+        let watches = Arc::new(WatchRegistry::new());
+        let task_id = Uuid::new_v4();
+        watches.insert(&crate::models::Watch {
+            id: Uuid::new_v4(),
+            user_id: "oidc:alice".to_string(),
+            task_id: Some(task_id),
+            session_id: None,
+            created_at: Utc::now(),
+        });
+        let filters = |user_id: Option<&str>| ClientFilters {
+            kinds: None,
+            agent_id: None,
+            task_id: None,
+            relay_id: None,
+            user_id: user_id.map(str::to_string),
+            session_id: None,
+            watches: Some(watches.clone()),
+            watched_only: true,
+        };
+
+        let mut watched = make_test_event("task.status_changed");
+        watched.task_id = Some(task_id);
+        assert!(filters(Some("oidc:alice")).matches(&watched));
+        assert!(!filters(Some("user")).matches(&watched));
+        assert!(!filters(None).matches(&watched));
+        assert!(!filters(Some("oidc:alice")).matches(&make_test_event("task.created")));
+
+        let mut unread = make_test_event("notification.unread");
+        unread.data = serde_json::json!({"user_id": "oidc:alice"});
+        assert!(filters(Some("oidc:alice")).matches(&unread));
+    }
+
+    #[test]
+    fn test_cursor_tracker_detects_gap() {
+        let mut tracker = CursorTracker::starting_at(10);
+        assert_eq!(tracker.gap_before(11), None);
+        assert_eq!(tracker.gap_before(15), Some((10, 14)));
+
+        // Nothing to compare against until the first cursor is seen
+        let tracker = CursorTracker::starting_at(0);
+        assert_eq!(tracker.gap_before(15), None);
+    }
+
+    #[test]
+    fn test_cursor_tracker_drops_backfilled_duplicates() {
+        let mut tracker = CursorTracker::starting_at(10);
+        tracker.record_backfill(12);
+        assert!(tracker.accept(13));
+
+        // 12 arrives late from the broadcast after it was backfilled
+        assert!(!tracker.accept(12));
+        // 11 was still in flight during the backfill and must be delivered
+        assert!(tracker.accept(11));
+        assert_eq!(tracker.gap_before(14), None);
+    }
 }
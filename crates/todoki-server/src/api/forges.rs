@@ -0,0 +1,324 @@
+//! Merge request webhooks from GitLab and Gitea
+//!
+//! Relays store the merge requests agents open as `gitlab_mr` and `gitea_pr`
+//! artifacts. These webhooks keep the artifacts' `state` current and emit
+//! `artifact.merge_request_opened`, `artifact.merge_request_merged` or
+//! `artifact.merge_request_closed` on the artifact's task, the way GitHub
+//! pull requests are tracked. Requests for merge requests todoki does not
+//! know about are accepted and ignored.
+
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::Json;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use todoki_protocol::event_bus::{BuiltinEvent, MergeRequestData};
+
+use crate::api::error::ApiError;
+use crate::config::Settings;
+use crate::event_bus::{EventPublisher, EventScope};
+use crate::models::merge_artifact_data;
+use crate::{Db, Publisher};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum MergeRequestState {
+    Opened,
+    Merged,
+    Closed,
+}
+
+/// A merge request state change, whichever forge reported it
+#[derive(Debug, Clone, PartialEq)]
+struct MergeRequestUpdate {
+    provider: &'static str,
+    artifact_type: &'static str,
+    url: String,
+    number: i64,
+    repo: String,
+    state: MergeRequestState,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookResponse {
+    /// Artifacts whose state changed
+    pub updated: usize,
+}
+
+// GitLab `Merge Request Hook` payload, only the fields used
+#[derive(Debug, Deserialize)]
+struct GitlabEvent {
+    object_kind: String,
+    #[serde(default)]
+    project: Option<GitlabProject>,
+    #[serde(default)]
+    object_attributes: Option<GitlabMergeRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitlabProject {
+    path_with_namespace: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitlabMergeRequest {
+    iid: i64,
+    url: String,
+    /// `opened`, `closed`, `locked` or `merged`
+    state: String,
+}
+
+// Gitea `pull_request` payload, only the fields used
+#[derive(Debug, Deserialize)]
+struct GiteaEvent {
+    #[serde(default)]
+    action: String,
+    #[serde(default)]
+    number: i64,
+    #[serde(default)]
+    pull_request: Option<GiteaPullRequest>,
+    #[serde(default)]
+    repository: Option<GiteaRepository>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GiteaPullRequest {
+    html_url: String,
+    #[serde(default)]
+    merged: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct GiteaRepository {
+    full_name: String,
+}
+
+/// The state change in a GitLab webhook; `None` for other events
+fn gitlab_update(event: GitlabEvent) -> Option<MergeRequestUpdate> {
+    if event.object_kind != "merge_request" {
+        return None;
+    }
+    let mr = event.object_attributes?;
+    let state = match mr.state.as_str() {
+        "opened" => MergeRequestState::Opened,
+        "merged" => MergeRequestState::Merged,
+        "closed" => MergeRequestState::Closed,
+        _ => return None,
+    };
+    Some(MergeRequestUpdate {
+        provider: "gitlab",
+        artifact_type: "gitlab_mr",
+        url: mr.url,
+        number: mr.iid,
+        repo: event
+            .project
+            .map(|p| p.path_with_namespace)
+            .unwrap_or_default(),
+        state,
+    })
+}
+
+/// The state change in a Gitea webhook; `None` for other events and for
+/// actions that leave the state as it was (edits, labels, reviews)
+fn gitea_update(event: GiteaEvent) -> Option<MergeRequestUpdate> {
+    let pr = event.pull_request?;
+    let state = match event.action.as_str() {
+        "opened" | "reopened" => MergeRequestState::Opened,
+        "closed" if pr.merged => MergeRequestState::Merged,
+        "closed" => MergeRequestState::Closed,
+        _ => return None,
+    };
+    Some(MergeRequestUpdate {
+        provider: "gitea",
+        artifact_type: "gitea_pr",
+        url: pr.html_url,
+        number: event.number,
+        repo: event.repository.map(|r| r.full_name).unwrap_or_default(),
+        state,
+    })
+}
+
+/// Whether `signature` (hex) is the HMAC-SHA256 of `body` with `secret`
+pub(crate) fn signature_valid(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Ok(signature) = hex::decode(signature.trim()) else {
+        return false;
+    };
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// POST /api/integrations/gitlab - GitLab merge request webhook
+///
+/// The webhook's secret token must match `gitlab.webhook_secret`.
+pub async fn receive_gitlab_webhook(
+    State(db): State<Db>,
+    State(settings): State<Settings>,
+    State(publisher): State<Publisher>,
+    headers: HeaderMap,
+    Json(event): Json<serde_json::Value>,
+) -> Result<Json<WebhookResponse>, ApiError> {
+    let secret = &settings.gitlab.webhook_secret;
+    let token = headers.get("X-Gitlab-Token").and_then(|v| v.to_str().ok());
+    if secret.is_empty() || token != Some(secret.as_str()) {
+        return Err(ApiError::unauthorized());
+    }
+
+    let event: GitlabEvent = serde_json::from_value(event)
+        .map_err(|e| ApiError::bad_request(format!("invalid GitLab payload: {}", e)))?;
+    let updated = match gitlab_update(event) {
+        Some(update) => apply(&db, &publisher, update).await?,
+        None => 0,
+    };
+    Ok(Json(WebhookResponse { updated }))
+}
+
+/// POST /api/integrations/gitea - Gitea (or Forgejo) pull request webhook
+///
+/// The payload must be signed with `gitea.webhook_secret`.
+pub async fn receive_gitea_webhook(
+    State(db): State<Db>,
+    State(settings): State<Settings>,
+    State(publisher): State<Publisher>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<WebhookResponse>, ApiError> {
+    let secret = &settings.gitea.webhook_secret;
+    let signature = headers
+        .get("X-Gitea-Signature")
+        .or_else(|| headers.get("X-Forgejo-Signature"))
+        .and_then(|v| v.to_str().ok());
+    if secret.is_empty() || !signature.is_some_and(|sig| signature_valid(secret, &body, sig)) {
+        return Err(ApiError::unauthorized());
+    }
+
+    let event: GiteaEvent = serde_json::from_slice(&body)
+        .map_err(|e| ApiError::bad_request(format!("invalid Gitea payload: {}", e)))?;
+    let updated = match gitea_update(event) {
+        Some(update) => apply(&db, &publisher, update).await?,
+        None => 0,
+    };
+    Ok(Json(WebhookResponse { updated }))
+}
+
+/// Record the new state on every artifact of the merge request
+async fn apply(
+    db: &Db,
+    publisher: &EventPublisher,
+    update: MergeRequestUpdate,
+) -> Result<usize, ApiError> {
+    let artifacts = db
+        .list_artifacts_by_url(update.artifact_type, &update.url)
+        .await?;
+
+    let state = serde_json::to_value(update.state).unwrap_or_default();
+    let mut updated = 0;
+    for artifact in artifacts {
+        // Forges resend events; only a change is worth an event
+        if artifact.data.get("state") == Some(&state) {
+            continue;
+        }
+        let mut data = artifact.data.clone();
+        merge_artifact_data(&mut data, serde_json::json!({ "state": &state }));
+        if db.update_artifact(artifact.id, data, None).await?.is_none() {
+            continue;
+        }
+        updated += 1;
+
+        let mr = MergeRequestData {
+            artifact_id: artifact.id.to_string(),
+            provider: update.provider.to_string(),
+            url: update.url.clone(),
+            number: update.number,
+            repo: update.repo.clone(),
+        };
+        let event = match update.state {
+            MergeRequestState::Opened => BuiltinEvent::MergeRequestOpened(mr),
+            MergeRequestState::Merged => BuiltinEvent::MergeRequestMerged(mr),
+            MergeRequestState::Closed => BuiltinEvent::MergeRequestClosed(mr),
+        };
+        let scope = EventScope::task(artifact.task_id);
+        if let Err(e) = publisher.emit_builtin(event, scope).await {
+            tracing::warn!(artifact_id = %artifact.id, error = %e, "failed to emit merge request event");
+        }
+    }
+
+    tracing::info!(
+        provider = update.provider,
+        url = %update.url,
+        state = ?update.state,
+        updated,
+        "merge request webhook"
+    );
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_gitlab_update() {
+        let event: GitlabEvent = serde_json::from_value(json!({
+            "object_kind": "merge_request",
+            "project": {"path_with_namespace": "infra/cli"},
+            "object_attributes": {
+                "iid": 7,
+                "url": "https://gitlab.example.com/infra/cli/-/merge_requests/7",
+                "state": "merged",
+                "action": "merge"
+            }
+        }))
+        .unwrap();
+        let update = gitlab_update(event).unwrap();
+        assert_eq!(update.state, MergeRequestState::Merged);
+        assert_eq!((update.number, update.repo.as_str()), (7, "infra/cli"));
+
+        let push: GitlabEvent = serde_json::from_value(json!({"object_kind": "push"})).unwrap();
+        assert!(gitlab_update(push).is_none());
+    }
+
+    #[test]
+    fn test_gitea_update() {
+        let event = |action: &str, merged: bool| -> GiteaEvent {
+            serde_json::from_value(json!({
+                "action": action,
+                "number": 3,
+                "pull_request": {"html_url": "https://git.example.org/team/app/pulls/3", "merged": merged},
+                "repository": {"full_name": "team/app"}
+            }))
+            .unwrap()
+        };
+        let state = |e: GiteaEvent| gitea_update(e).map(|u| u.state);
+        assert_eq!(
+            state(event("opened", false)),
+            Some(MergeRequestState::Opened)
+        );
+        assert_eq!(
+            state(event("closed", true)),
+            Some(MergeRequestState::Merged)
+        );
+        assert_eq!(
+            state(event("closed", false)),
+            Some(MergeRequestState::Closed)
+        );
+        assert_eq!(state(event("label_updated", false)), None);
+    }
+
+    #[test]
+    fn test_signature_valid() {
+        let body = br#"{"action":"opened"}"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(body);
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        assert!(signature_valid("secret", body, &signature));
+        assert!(!signature_valid("other", body, &signature));
+        assert!(!signature_valid("secret", b"{}", &signature));
+        assert!(!signature_valid("secret", body, "not hex"));
+    }
+}
@@ -0,0 +1,494 @@
+//! GraphQL API surface (optional, `graphql` feature)
+//!
+//! Exposes tasks, projects, agents, sessions, artifacts and events with nested
+//! resolvers, plus an event subscription backed by the Event Bus broadcaster.
+//! Lives alongside the REST API and reuses the same database service.
+
+use std::sync::Arc;
+
+use async_graphql::{
+    Context, EmptyMutation, ID, Json as GqlJson, Object, Result as GqlResult, Schema,
+    Subscription,
+};
+use async_graphql_axum::{GraphQLProtocol, GraphQLRequest, GraphQLResponse, GraphQLWebSocket};
+use axum::{
+    extract::{Query, State, WebSocketUpgrade},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use futures_util::Stream;
+use gotcha::axum::Extension;
+use serde::Deserialize;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
+use crate::auth::AuthContext;
+use crate::config::Settings;
+use crate::db::DatabaseService;
+use crate::event_bus::{Event, EventPublisher, EventSubscriber};
+use crate::models::{Agent, AgentSession, Artifact, Project, Task};
+
+pub type TodokiSchema = Schema<QueryRoot, EmptyMutation, SubscriptionRoot>;
+
+/// Build the GraphQL schema with shared services injected as context data
+pub fn build_schema(
+    db: Arc<DatabaseService>,
+    publisher: Arc<EventPublisher>,
+    subscriber: Arc<EventSubscriber>,
+) -> TodokiSchema {
+    Schema::build(QueryRoot, EmptyMutation, SubscriptionRoot)
+        .data(db)
+        .data(publisher)
+        .data(subscriber)
+        .finish()
+}
+
+fn db<'a>(ctx: &Context<'a>) -> &'a Arc<DatabaseService> {
+    ctx.data_unchecked::<Arc<DatabaseService>>()
+}
+
+fn parse_id(id: &ID) -> GqlResult<Uuid> {
+    Uuid::parse_str(id.as_str()).map_err(|_| "invalid id".into())
+}
+
+/// Serialize a serde enum (e.g. TaskStatus) to its wire string
+fn enum_str<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+// ============================================================================
+// Query root
+// ============================================================================
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Today's tasks (todo, not archived)
+    async fn tasks(&self, ctx: &Context<'_>) -> GqlResult<Vec<TaskNode>> {
+        let tasks = db(ctx).get_today_tasks().await?;
+        Ok(tasks.into_iter().map(TaskNode).collect())
+    }
+
+    /// Inbox tasks
+    async fn inbox_tasks(&self, ctx: &Context<'_>) -> GqlResult<Vec<TaskNode>> {
+        let tasks = db(ctx).get_inbox_tasks().await?;
+        Ok(tasks.into_iter().map(TaskNode).collect())
+    }
+
+    async fn task(&self, ctx: &Context<'_>, id: ID) -> GqlResult<Option<TaskNode>> {
+        let task = db(ctx).get_task_by_id(parse_id(&id)?).await?;
+        Ok(task.map(TaskNode))
+    }
+
+    async fn projects(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = false)] include_archived: bool,
+    ) -> GqlResult<Vec<ProjectNode>> {
+        let projects = db(ctx).list_projects(include_archived).await?;
+        Ok(projects.into_iter().map(ProjectNode).collect())
+    }
+
+    async fn project(&self, ctx: &Context<'_>, id: ID) -> GqlResult<Option<ProjectNode>> {
+        let project = db(ctx).get_project(parse_id(&id)?).await?;
+        Ok(project.map(ProjectNode))
+    }
+
+    async fn agents(&self, ctx: &Context<'_>) -> GqlResult<Vec<AgentNode>> {
+        let agents = db(ctx).list_agents().await?;
+        Ok(agents.into_iter().map(AgentNode).collect())
+    }
+
+    async fn agent(&self, ctx: &Context<'_>, id: ID) -> GqlResult<Option<AgentNode>> {
+        let agent = db(ctx).get_agent(parse_id(&id)?).await?;
+        Ok(agent.map(AgentNode))
+    }
+
+    async fn session(&self, ctx: &Context<'_>, id: ID) -> GqlResult<Option<SessionNode>> {
+        let session = db(ctx).get_agent_session(parse_id(&id)?).await?;
+        Ok(session.map(SessionNode))
+    }
+
+    async fn artifact(&self, ctx: &Context<'_>, id: ID) -> GqlResult<Option<ArtifactNode>> {
+        let artifact = db(ctx).get_artifact(parse_id(&id)?).await?;
+        Ok(artifact.map(ArtifactNode))
+    }
+
+    /// Query persisted events by cursor range
+    async fn events(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 0)] cursor: i64,
+        kinds: Option<Vec<String>>,
+        agent_id: Option<ID>,
+        task_id: Option<ID>,
+        correlation_id: Option<ID>,
+        #[graphql(default = 100)] limit: usize,
+    ) -> GqlResult<Vec<EventNode>> {
+        let subscriber = ctx.data_unchecked::<Arc<EventSubscriber>>();
+        let agent_id = agent_id.as_ref().map(parse_id).transpose()?;
+        let task_id = task_id.as_ref().map(parse_id).transpose()?;
+        let correlation_id = correlation_id.as_ref().map(parse_id).transpose()?;
+        let events = subscriber
+            .poll(
+                cursor,
+                kinds.as_deref(),
+                agent_id,
+                task_id,
+                correlation_id,
+                None,
+                Some(limit),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(events.into_iter().map(EventNode).collect())
+    }
+}
+
+// ============================================================================
+// Subscription root
+// ============================================================================
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    /// Live events from the Event Bus (supports wildcard kinds, e.g. "task.*")
+    async fn events(
+        &self,
+        ctx: &Context<'_>,
+        kinds: Option<Vec<String>>,
+        task_id: Option<ID>,
+    ) -> GqlResult<impl Stream<Item = EventNode>> {
+        let publisher = ctx.data_unchecked::<Arc<EventPublisher>>();
+        let task_id = task_id.as_ref().map(parse_id).transpose()?;
+        let rx = publisher.subscribe();
+
+        Ok(futures_util::stream::unfold(rx, move |mut rx| {
+            let kinds = kinds.clone();
+            async move {
+                loop {
+                    match rx.recv().await {
+                        Ok(event) => {
+                            let kind_ok = kinds
+                                .as_ref()
+                                .is_none_or(|k| k.iter().any(|p| kind_matches(p, &event.kind)));
+                            let task_ok = task_id.is_none() || event.task_id == task_id;
+                            if kind_ok && task_ok {
+                                return Some((EventNode(event), rx));
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(n)) => {
+                            tracing::warn!(lagged = n, "graphql subscription lagged");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            }
+        }))
+    }
+}
+
+fn kind_matches(pattern: &str, kind: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => kind.starts_with(prefix),
+        None => pattern == kind,
+    }
+}
+
+// ============================================================================
+// Nodes
+// ============================================================================
+
+pub struct TaskNode(Task);
+
+#[Object(name = "Task")]
+impl TaskNode {
+    async fn id(&self) -> ID {
+        ID(self.0.id.to_string())
+    }
+    async fn priority(&self) -> i32 {
+        self.0.priority
+    }
+    async fn content(&self) -> &str {
+        &self.0.content
+    }
+    async fn status(&self) -> String {
+        enum_str(&self.0.status)
+    }
+    async fn create_at(&self) -> DateTime<Utc> {
+        self.0.create_at
+    }
+    async fn archived(&self) -> bool {
+        self.0.archived
+    }
+    async fn due_at(&self) -> Option<DateTime<Utc>> {
+        self.0.due_at
+    }
+    async fn estimate_minutes(&self) -> Option<i32> {
+        self.0.estimate_minutes
+    }
+    async fn project(&self, ctx: &Context<'_>) -> GqlResult<Option<ProjectNode>> {
+        Ok(db(ctx).get_project(self.0.project_id).await?.map(ProjectNode))
+    }
+    async fn agent(&self, ctx: &Context<'_>) -> GqlResult<Option<AgentNode>> {
+        match self.0.agent_id {
+            Some(id) => Ok(db(ctx).get_agent(id).await?.map(AgentNode)),
+            None => Ok(None),
+        }
+    }
+    async fn artifacts(&self, ctx: &Context<'_>) -> GqlResult<Vec<ArtifactNode>> {
+        let artifacts = db(ctx).list_artifacts_by_task(self.0.id).await?;
+        Ok(artifacts.into_iter().map(ArtifactNode).collect())
+    }
+    async fn comments(&self, ctx: &Context<'_>) -> GqlResult<Vec<GqlJson<serde_json::Value>>> {
+        let comments = db(ctx).get_task_comments(self.0.id).await?;
+        Ok(comments
+            .into_iter()
+            .map(|c| {
+                GqlJson(serde_json::json!({
+                    "id": c.id,
+                    "content": c.content,
+                    "create_at": c.create_at,
+                }))
+            })
+            .collect())
+    }
+    async fn events(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 100)] limit: usize,
+    ) -> GqlResult<Vec<EventNode>> {
+        let subscriber = ctx.data_unchecked::<Arc<EventSubscriber>>();
+        let events = subscriber
+            .poll(0, None, None, Some(self.0.id), None, None, Some(limit))
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(events.into_iter().map(EventNode).collect())
+    }
+}
+
+pub struct ProjectNode(Project);
+
+#[Object(name = "Project")]
+impl ProjectNode {
+    async fn id(&self) -> ID {
+        ID(self.0.id.to_string())
+    }
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+    async fn description(&self) -> Option<&str> {
+        self.0.description.as_deref()
+    }
+    async fn color(&self) -> &str {
+        &self.0.color
+    }
+    async fn archived(&self) -> bool {
+        self.0.archived
+    }
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+    async fn updated_at(&self) -> DateTime<Utc> {
+        self.0.updated_at
+    }
+    async fn artifacts(&self, ctx: &Context<'_>) -> GqlResult<Vec<ArtifactNode>> {
+        let artifacts = db(ctx).list_artifacts(self.0.id, None).await?;
+        Ok(artifacts.into_iter().map(ArtifactNode).collect())
+    }
+    async fn done_tasks(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 0)] offset: i64,
+        #[graphql(default = 50)] limit: i64,
+    ) -> GqlResult<Vec<TaskNode>> {
+        let tasks = db(ctx).get_project_done_tasks(self.0.id, offset, limit).await?;
+        Ok(tasks.into_iter().map(TaskNode).collect())
+    }
+}
+
+pub struct AgentNode(Agent);
+
+#[Object(name = "Agent")]
+impl AgentNode {
+    async fn id(&self) -> ID {
+        ID(self.0.id.to_string())
+    }
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+    async fn workdir(&self) -> &str {
+        &self.0.workdir
+    }
+    async fn command(&self) -> &str {
+        &self.0.command
+    }
+    async fn args(&self) -> Vec<String> {
+        self.0.args_vec()
+    }
+    async fn role(&self) -> String {
+        enum_str(&self.0.role)
+    }
+    async fn status(&self) -> String {
+        enum_str(&self.0.status)
+    }
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+    async fn project(&self, ctx: &Context<'_>) -> GqlResult<Option<ProjectNode>> {
+        Ok(db(ctx).get_project(self.0.project_id).await?.map(ProjectNode))
+    }
+    async fn sessions(&self, ctx: &Context<'_>) -> GqlResult<Vec<SessionNode>> {
+        let sessions = db(ctx).get_agent_sessions(self.0.id).await?;
+        Ok(sessions.into_iter().map(SessionNode).collect())
+    }
+    async fn task(&self, ctx: &Context<'_>) -> GqlResult<Option<TaskNode>> {
+        Ok(db(ctx).get_task_by_agent_id(self.0.id).await?.map(TaskNode))
+    }
+}
+
+pub struct SessionNode(AgentSession);
+
+#[Object(name = "Session")]
+impl SessionNode {
+    async fn id(&self) -> ID {
+        ID(self.0.id.to_string())
+    }
+    async fn status(&self) -> String {
+        enum_str(&self.0.status)
+    }
+    async fn started_at(&self) -> DateTime<Utc> {
+        self.0.started_at
+    }
+    async fn ended_at(&self) -> Option<DateTime<Utc>> {
+        self.0.ended_at
+    }
+    async fn agent(&self, ctx: &Context<'_>) -> GqlResult<Option<AgentNode>> {
+        Ok(db(ctx).get_agent(self.0.agent_id).await?.map(AgentNode))
+    }
+}
+
+pub struct ArtifactNode(Artifact);
+
+#[Object(name = "Artifact")]
+impl ArtifactNode {
+    async fn id(&self) -> ID {
+        ID(self.0.id.to_string())
+    }
+    async fn artifact_type(&self) -> &str {
+        &self.0.artifact_type
+    }
+    async fn data(&self) -> GqlJson<serde_json::Value> {
+        GqlJson(self.0.data.clone())
+    }
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+    async fn task(&self, ctx: &Context<'_>) -> GqlResult<Option<TaskNode>> {
+        Ok(db(ctx).get_task_by_id(self.0.task_id).await?.map(TaskNode))
+    }
+    async fn project(&self, ctx: &Context<'_>) -> GqlResult<Option<ProjectNode>> {
+        Ok(db(ctx).get_project(self.0.project_id).await?.map(ProjectNode))
+    }
+}
+
+pub struct EventNode(Event);
+
+#[Object(name = "Event")]
+impl EventNode {
+    async fn cursor(&self) -> i64 {
+        self.0.cursor
+    }
+    async fn kind(&self) -> &str {
+        &self.0.kind
+    }
+    async fn time(&self) -> DateTime<Utc> {
+        self.0.time
+    }
+    async fn agent_id(&self) -> ID {
+        ID(self.0.agent_id.to_string())
+    }
+    async fn session_id(&self) -> Option<ID> {
+        self.0.session_id.map(|id| ID(id.to_string()))
+    }
+    async fn task_id(&self) -> Option<ID> {
+        self.0.task_id.map(|id| ID(id.to_string()))
+    }
+    async fn correlation_id(&self) -> Option<ID> {
+        self.0.correlation_id.map(|id| ID(id.to_string()))
+    }
+    async fn data(&self) -> GqlJson<serde_json::Value> {
+        GqlJson(self.0.data.clone())
+    }
+    async fn task(&self, ctx: &Context<'_>) -> GqlResult<Option<TaskNode>> {
+        match self.0.task_id {
+            Some(id) => Ok(db(ctx).get_task_by_id(id).await?.map(TaskNode)),
+            None => Ok(None),
+        }
+    }
+}
+
+// ============================================================================
+// HTTP handlers
+// ============================================================================
+
+/// POST /graphql - Execute a GraphQL query
+pub async fn graphql_handler(
+    Extension(auth): Extension<AuthContext>,
+    State(schema): State<TodokiSchema>,
+    request: GraphQLRequest,
+) -> Response {
+    if auth.require_auth().is_err() {
+        return ApiError::unauthorized().into_response();
+    }
+    GraphQLResponse::from(schema.execute(request.into_inner()).await).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GraphQLWsParams {
+    /// Optional token for authentication (prefer Authorization header)
+    pub token: Option<String>,
+}
+
+/// GET /graphql/ws - GraphQL subscriptions over WebSocket
+pub async fn graphql_ws_handler(
+    ws: WebSocketUpgrade,
+    protocol: GraphQLProtocol,
+    headers: HeaderMap,
+    State(schema): State<TodokiSchema>,
+    State(settings): State<Settings>,
+    Query(params): Query<GraphQLWsParams>,
+) -> Response {
+    let bearer = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|auth| auth.strip_prefix("Bearer "));
+    let token = bearer.or(params.token.as_deref());
+
+    if token.and_then(|t| crate::auth::user_token_id(&settings, t)).is_none() {
+        return ApiError::unauthorized().into_response();
+    }
+
+    ws.protocols(async_graphql::http::ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |socket| GraphQLWebSocket::new(socket, schema, protocol).serve())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_matches() {
+        assert!(kind_matches("task.*", "task.created"));
+        assert!(kind_matches("task.created", "task.created"));
+        assert!(!kind_matches("task.created", "task.updated"));
+        assert!(!kind_matches("agent.*", "task.created"));
+    }
+}
@@ -1,9 +1,31 @@
+pub mod activity;
+pub mod admin;
 pub mod agents;
 pub mod artifacts;
+pub mod calendar;
+pub mod debug;
+pub mod email;
 pub mod error;
 pub mod event_bus;
 pub mod event_bus_ws;
+pub mod forges;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+pub mod notifications;
+pub mod permissions;
 pub mod projects;
+pub mod quotas;
 pub mod relays;
 pub mod report;
+pub mod session_replay;
+pub mod status_page;
+pub mod task_context;
 pub mod tasks;
+pub mod templates;
+pub mod time_entries;
+pub mod trackers;
+pub mod undo;
+pub mod validation;
+pub mod vault;
+pub mod views;
+pub mod watches;
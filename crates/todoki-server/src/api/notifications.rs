@@ -0,0 +1,133 @@
+use gotcha::axum::extract::{Path, Query, State};
+use gotcha::axum::Extension;
+use gotcha::Json;
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
+use crate::auth::AuthContext;
+use crate::config::Settings;
+use crate::models::{
+    muted_kinds, NotificationListQuery, NotificationListResponse, NotificationPreferences,
+    NotificationPreferencesRequest, NotificationPreferencesResponse, NotificationReadResponse,
+    MAX_NOTIFICATION_LIMIT,
+};
+use crate::notification::push_unread;
+use crate::{Db, Publisher};
+
+/// The calling user, e.g. `user` or `oidc:<subject>`
+fn user_id(auth: &AuthContext) -> Result<String, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    auth.token_id()
+        .map(str::to_string)
+        .ok_or_else(ApiError::unauthorized)
+}
+
+fn preferences_response(
+    settings: &Settings,
+    preferences: &NotificationPreferences,
+) -> NotificationPreferencesResponse {
+    let available = settings.notifications.kinds.clone();
+    NotificationPreferencesResponse {
+        enabled: available
+            .iter()
+            .copied()
+            .filter(|kind| preferences.allows(*kind))
+            .collect(),
+        available,
+    }
+}
+
+/// GET /api/notifications - Notifications of the calling user, newest first
+///
+/// The first call registers the user as a recipient with every kind enabled.
+#[gotcha::api]
+pub async fn list_notifications(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Query(query): Query<NotificationListQuery>,
+) -> Result<Json<NotificationListResponse>, ApiError> {
+    let user_id = user_id(&auth)?;
+    db.ensure_notification_preferences(&user_id).await?;
+
+    let limit = query
+        .limit
+        .unwrap_or(MAX_NOTIFICATION_LIMIT)
+        .clamp(1, MAX_NOTIFICATION_LIMIT);
+    let notifications = db.list_notifications(&user_id, query.unread, limit).await?;
+    let unread = db.unread_notification_count(&user_id).await?;
+    Ok(Json(NotificationListResponse {
+        items: notifications.into_iter().map(Into::into).collect(),
+        unread,
+    }))
+}
+
+/// POST /api/notifications/:notification_id/read - Mark a notification read
+#[gotcha::api]
+pub async fn mark_notification_read(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(publisher): State<Publisher>,
+    Path(notification_id): Path<Uuid>,
+) -> Result<Json<NotificationReadResponse>, ApiError> {
+    let user_id = user_id(&auth)?;
+
+    let marked = db
+        .mark_notifications_read(&user_id, Some(notification_id))
+        .await?;
+    read_response(&db, &publisher, &user_id, marked).await
+}
+
+/// POST /api/notifications/read-all - Mark every notification read
+#[gotcha::api]
+pub async fn mark_all_notifications_read(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(publisher): State<Publisher>,
+) -> Result<Json<NotificationReadResponse>, ApiError> {
+    let user_id = user_id(&auth)?;
+
+    let marked = db.mark_notifications_read(&user_id, None).await?;
+    read_response(&db, &publisher, &user_id, marked).await
+}
+
+async fn read_response(
+    db: &Db,
+    publisher: &Publisher,
+    user_id: &str,
+    marked: u64,
+) -> Result<Json<NotificationReadResponse>, ApiError> {
+    if marked > 0 {
+        push_unread(db, publisher, user_id).await;
+    }
+    let unread = db.unread_notification_count(user_id).await?;
+    Ok(Json(NotificationReadResponse { marked, unread }))
+}
+
+/// GET /api/notifications/preferences - Kinds the calling user is notified of
+#[gotcha::api]
+pub async fn get_notification_preferences(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(settings): State<Settings>,
+) -> Result<Json<NotificationPreferencesResponse>, ApiError> {
+    let user_id = user_id(&auth)?;
+
+    let preferences = db.ensure_notification_preferences(&user_id).await?;
+    Ok(Json(preferences_response(&settings, &preferences)))
+}
+
+/// PUT /api/notifications/preferences - Choose the kinds to be notified of
+#[gotcha::api]
+pub async fn update_notification_preferences(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(settings): State<Settings>,
+    Json(payload): Json<NotificationPreferencesRequest>,
+) -> Result<Json<NotificationPreferencesResponse>, ApiError> {
+    let user_id = user_id(&auth)?;
+
+    let preferences = db
+        .set_notification_preferences(&user_id, muted_kinds(&payload.enabled))
+        .await?;
+    Ok(Json(preferences_response(&settings, &preferences)))
+}
@@ -0,0 +1,177 @@
+use gotcha::axum::extract::{Path, State};
+use gotcha::axum::Extension;
+use gotcha::{Json, Schematic};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use todoki_protocol::event_bus::{
+    PermissionOption, PermissionOutcome, PermissionRequestedData, PermissionReview, ToolCall,
+};
+
+use crate::api::error::ApiError;
+use crate::auth::AuthContext;
+use crate::config::Settings;
+use crate::permission::command::{NormalizedToolCall, ParsedCommand};
+use crate::permission::{self, MatchedRule, PendingPermissionInfo, PermissionAnswer};
+use crate::{Db, Publisher, Relays, Reviewer};
+
+#[derive(Debug, Deserialize, Schematic)]
+pub struct RespondPermissionRequest {
+    pub outcome: PermissionOutcome,
+}
+
+#[derive(Debug, Serialize, Schematic)]
+pub struct RespondPermissionResponse {
+    /// Whether the answer was forwarded to the agent
+    pub decided: bool,
+    /// Approvals still needed before it is
+    pub remaining_approvals: usize,
+}
+
+#[derive(Debug, Deserialize, Schematic)]
+pub struct SimulatePermissionRequest {
+    pub tool_call: ToolCall,
+    /// What the agent is working on, as the reviewer would see the task
+    pub task_goal: Option<String>,
+    /// Project whose reviewer overrides apply
+    pub project_id: Option<Uuid>,
+    /// Options offered to the reviewer (default: none)
+    #[serde(default)]
+    pub options: Vec<PermissionOption>,
+    /// Ask the configured model for its review
+    #[serde(default)]
+    pub call_model: bool,
+}
+
+#[derive(Debug, Serialize, Schematic)]
+pub struct SimulatePermissionResponse {
+    /// Shell commands as rules see them
+    pub commands: Vec<ParsedCommand>,
+    pub matched_rules: Vec<MatchedRule>,
+    pub required_approvals: usize,
+    pub reviewer_enabled: bool,
+    /// The model's review, with `call_model`
+    pub review: Option<PermissionReview>,
+}
+
+/// GET /api/permissions/pending - Permission requests waiting for an answer
+#[gotcha::api]
+pub async fn list_pending_permissions(
+    Extension(auth): Extension<AuthContext>,
+    State(relays): State<Relays>,
+) -> Result<Json<Vec<PendingPermissionInfo>>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    Ok(Json(relays.list_pending_permissions().await))
+}
+
+/// POST /api/permissions/:request_id/respond - Answer a permission request
+///
+/// Requests that need two approvals are forwarded once a second person
+/// approved; until then the approval is only recorded.
+#[gotcha::api]
+pub async fn respond_permission(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(publisher): State<Publisher>,
+    State(relays): State<Relays>,
+    Path(request_id): Path<String>,
+    Json(payload): Json<RespondPermissionRequest>,
+) -> Result<Json<RespondPermissionResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    let answered_by = auth.token_id().unwrap_or_default();
+
+    let (relay_id, session_id) = relays
+        .get_pending_permission(&request_id)
+        .await
+        .ok_or_else(|| ApiError::not_found("permission request is not pending"))?;
+    let answer = relays
+        .answer_permission(&request_id, answered_by, payload.outcome)
+        .await
+        .ok_or_else(|| ApiError::not_found("permission request is not pending"))?
+        .map_err(ApiError::bad_request)?;
+
+    match answer {
+        PermissionAnswer::Waiting {
+            approvals,
+            required,
+        } => {
+            tracing::info!(
+                request_id = %request_id,
+                approved_by = %answered_by,
+                approvals = approvals,
+                required = required,
+                "permission request approved, waiting for another approval"
+            );
+            Ok(Json(RespondPermissionResponse {
+                decided: false,
+                remaining_approvals: required - approvals,
+            }))
+        }
+        PermissionAnswer::Decided(outcome) => {
+            permission::send_response(&db, &publisher, relay_id, &request_id, &session_id, outcome)
+                .await
+                .map_err(|e| ApiError::internal(e.to_string()))?;
+            relays.remove_pending_permission(&request_id).await;
+            Ok(Json(RespondPermissionResponse {
+                decided: true,
+                remaining_approvals: 0,
+            }))
+        }
+    }
+}
+
+/// POST /api/permission-policies/simulate - Evaluate a sample tool call
+///
+/// Reports which configured rules match and, with `call_model`, what the
+/// reviewer decides; nothing is sent to any agent.
+#[gotcha::api]
+pub async fn simulate_permission_policy(
+    Extension(auth): Extension<AuthContext>,
+    State(settings): State<Settings>,
+    State(db): State<Db>,
+    State(reviewer): State<Reviewer>,
+    Json(payload): Json<SimulatePermissionRequest>,
+) -> Result<Json<SimulatePermissionResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let tool_call = payload.tool_call;
+    let matched_rules = permission::matched_rules(&settings.permissions, &tool_call);
+    let required_approvals = permission::required_approvals(&settings.permissions, &tool_call);
+    let commands = NormalizedToolCall::new(&tool_call).commands;
+    let config = match payload.project_id {
+        Some(project_id) => db
+            .get_project(project_id)
+            .await?
+            .ok_or_else(|| ApiError::not_found(format!("Project {} not found", project_id)))?
+            .review_config(),
+        None => None,
+    };
+    let reviewer_enabled = reviewer.enabled_for(config.as_ref());
+
+    let review = if payload.call_model && reviewer_enabled {
+        let request = PermissionRequestedData {
+            session_id: String::new(),
+            request_id: String::new(),
+            tool_call_id: String::new(),
+            tool_call,
+            options: payload.options,
+            review: None,
+            preview: None,
+        };
+        let review = reviewer
+            .review_for_goal(payload.task_goal.as_deref(), config.as_ref(), &request)
+            .await
+            .map_err(|e| ApiError::internal(format!("review failed: {}", e)))?;
+        Some(review)
+    } else {
+        None
+    };
+
+    Ok(Json(SimulatePermissionResponse {
+        commands,
+        matched_rules,
+        required_approvals,
+        reviewer_enabled,
+        review,
+    }))
+}
@@ -2,13 +2,23 @@ use gotcha::axum::extract::{Path, Query, State};
 use gotcha::axum::Extension;
 use gotcha::{Json, Schematic};
 use serde::Deserialize;
+use todoki_protocol::event_bus::{BuiltinEvent, ProjectArchivedData, ProjectRestoredData};
 use uuid::Uuid;
 
+use crate::api::agents::stop_agent_internal;
 use crate::api::error::ApiError;
 use crate::api::tasks::tasks_to_responses;
+use crate::api::undo;
+use crate::api::validation;
 use crate::auth::AuthContext;
-use crate::models::{CreateProject, ProjectCreateRequest, ProjectResponse, ProjectUpdateRequest, TaskResponse};
-use crate::Db;
+use crate::db::DatabaseService;
+use crate::event_bus::{EventPublisher, EventScope};
+use crate::models::{
+    CreateProject, ProjectCascadeResponse, ProjectCloneRequest, ProjectCloneResponse,
+    ProjectCreateRequest, ProjectResponse, ProjectUpdateRequest, TaskResponse, UndoAction,
+};
+use crate::relay::RelayManager;
+use crate::{Db, Publisher, Relays};
 
 #[derive(Debug, Deserialize, Schematic)]
 pub struct ListProjectsQuery {
@@ -38,6 +48,7 @@ pub async fn create_project(
     Json(payload): Json<ProjectCreateRequest>,
 ) -> Result<Json<ProjectResponse>, ApiError> {
     auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    validation::validate(&payload)?;
 
     let create = CreateProject::new(payload.name, payload.description, payload.color);
     let project = db.create_project(create).await?;
@@ -75,14 +86,51 @@ pub async fn get_project_by_name(
 }
 
 /// PUT /api/projects/:project_id - Update project
+///
+/// Changing `archived` goes through the same cascade as the archive and
+/// restore endpoints.
 #[gotcha::api]
 pub async fn update_project(
     Extension(auth): Extension<AuthContext>,
     State(db): State<Db>,
+    State(relays): State<Relays>,
+    State(publisher): State<Publisher>,
     Path(project_id): Path<Uuid>,
     Json(payload): Json<ProjectUpdateRequest>,
 ) -> Result<Json<ProjectResponse>, ApiError> {
     auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    validation::validate(&payload)?;
+
+    if let Some(schedule) = &payload.execution_schedule {
+        schedule.validate().map_err(ApiError::bad_request)?;
+    }
+    if let Some(routing) = &payload.relay_routing {
+        routing.validate().map_err(ApiError::bad_request)?;
+    }
+    if let Some(config) = &payload.review_config {
+        config.validate().map_err(ApiError::bad_request)?;
+    }
+    if let Some(workflow) = &payload.workflow {
+        workflow.validate().map_err(ApiError::bad_request)?;
+    }
+    if let Some(sync) = &payload.issue_sync {
+        sync.validate().map_err(ApiError::bad_request)?;
+    }
+
+    let current = db
+        .get_project(project_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Project {} not found", project_id)))?;
+    match payload.archived {
+        Some(true) if !current.archived => {
+            archive_cascade(&db, &relays, &publisher, project_id).await?;
+            undo::record(&db, &auth, UndoAction::ProjectArchive, project_id, None).await;
+        }
+        Some(false) if current.archived => {
+            restore_cascade(&db, &publisher, project_id).await?;
+        }
+        _ => {}
+    }
 
     let project = db
         .update_project(
@@ -90,11 +138,16 @@ pub async fn update_project(
             payload.name,
             payload.description,
             payload.color,
-            payload.archived,
+            None,
             payload.general_template,
             payload.business_template,
             payload.coding_template,
             payload.qa_template,
+            payload.execution_schedule,
+            payload.relay_routing,
+            payload.review_config,
+            payload.workflow,
+            payload.issue_sync,
         )
         .await?;
 
@@ -114,6 +167,161 @@ pub async fn delete_project(
     Ok(Json(()))
 }
 
+/// POST /api/projects/:project_id/clone - Copy a project's automation setup
+///
+/// Copies description, color, role templates (inline and shared), the
+/// execution schedule and relay routing, plus the agent definitions with `include_agents`.
+/// Tasks and artifacts are not copied.
+#[gotcha::api]
+pub async fn clone_project(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(project_id): Path<Uuid>,
+    Json(payload): Json<ProjectCloneRequest>,
+) -> Result<Json<ProjectCloneResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    validation::validate(&payload)?;
+
+    let name = payload.name.trim();
+    if db.get_project_by_name(name).await?.is_some() {
+        return Err(ApiError::bad_request(format!("project {} already exists", name)));
+    }
+
+    let (project, agents) = db
+        .clone_project(
+            project_id,
+            name,
+            payload.description,
+            payload.color,
+            payload.include_agents,
+        )
+        .await?;
+
+    tracing::info!(
+        source_id = %project_id,
+        project_id = %project.id,
+        agents = agents.len(),
+        "project cloned"
+    );
+
+    Ok(Json(ProjectCloneResponse {
+        project: project.into(),
+        agents: agents.into_iter().map(Into::into).collect(),
+    }))
+}
+
+/// POST /api/projects/:project_id/archive - Archive a project with its open work
+///
+/// Archives every non-done task, stops the project's running agents and
+/// hides its artifacts, then emits `project.archived`.
+#[gotcha::api]
+pub async fn archive_project(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(relays): State<Relays>,
+    State(publisher): State<Publisher>,
+    Path(project_id): Path<Uuid>,
+) -> Result<Json<ProjectCascadeResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let project = db
+        .get_project(project_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Project {} not found", project_id)))?;
+    if project.archived {
+        return Err(ApiError::bad_request("project is already archived"));
+    }
+
+    let response = archive_cascade(&db, &relays, &publisher, project_id).await?;
+    undo::record(&db, &auth, UndoAction::ProjectArchive, project_id, None).await;
+    Ok(Json(response))
+}
+
+/// POST /api/projects/:project_id/restore - Restore an archived project
+///
+/// Unarchives the tasks archived with the project and shows its artifacts
+/// again, then emits `project.restored`. Stopped agents are not restarted.
+#[gotcha::api]
+pub async fn restore_project(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(publisher): State<Publisher>,
+    Path(project_id): Path<Uuid>,
+) -> Result<Json<ProjectCascadeResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let project = db
+        .get_project(project_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Project {} not found", project_id)))?;
+    if !project.archived {
+        return Err(ApiError::bad_request("project is not archived"));
+    }
+
+    let response = restore_cascade(&db, &publisher, project_id).await?;
+    Ok(Json(response))
+}
+
+async fn archive_cascade(
+    db: &DatabaseService,
+    relays: &RelayManager,
+    publisher: &EventPublisher,
+    project_id: Uuid,
+) -> Result<ProjectCascadeResponse, ApiError> {
+    let cascade = db.archive_project(project_id).await?;
+
+    let mut stopped_agents = 0;
+    for agent in db.list_running_project_agents(project_id).await? {
+        match stop_agent_internal(db, relays, publisher, agent.id).await {
+            Ok(()) => stopped_agents += 1,
+            Err(e) => {
+                tracing::warn!(agent_id = %agent.id, error = %e, "failed to stop agent of archived project");
+            }
+        }
+    }
+
+    let event = BuiltinEvent::ProjectArchived(ProjectArchivedData {
+        project_id: project_id.to_string(),
+        archived_tasks: cascade.task_ids.len() as i64,
+        stopped_agents,
+        hidden_artifacts: cascade.artifacts,
+    });
+    if let Err(e) = publisher.emit_builtin(event, EventScope::system()).await {
+        tracing::warn!(project_id = %project_id, error = %e, "failed to emit project.archived");
+    }
+
+    Ok(ProjectCascadeResponse {
+        project: cascade.project.into(),
+        tasks: cascade.task_ids.len() as i64,
+        stopped_agents,
+        artifacts: cascade.artifacts,
+    })
+}
+
+pub(crate) async fn restore_cascade(
+    db: &DatabaseService,
+    publisher: &EventPublisher,
+    project_id: Uuid,
+) -> Result<ProjectCascadeResponse, ApiError> {
+    let cascade = db.restore_project(project_id).await?;
+
+    let event = BuiltinEvent::ProjectRestored(ProjectRestoredData {
+        project_id: project_id.to_string(),
+        restored_tasks: cascade.task_ids.len() as i64,
+        restored_artifacts: cascade.artifacts,
+    });
+    if let Err(e) = publisher.emit_builtin(event, EventScope::system()).await {
+        tracing::warn!(project_id = %project_id, error = %e, "failed to emit project.restored");
+    }
+
+    Ok(ProjectCascadeResponse {
+        project: cascade.project.into(),
+        tasks: cascade.task_ids.len() as i64,
+        stopped_agents: 0,
+        artifacts: cascade.artifacts,
+    })
+}
+
 #[derive(Debug, Deserialize, Schematic)]
 pub struct ProjectDoneTasksQuery {
     #[serde(default)]
@@ -0,0 +1,59 @@
+use gotcha::axum::extract::{Path, State};
+use gotcha::axum::Extension;
+use gotcha::Json;
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
+use crate::auth::AuthContext;
+use crate::config::Settings;
+use crate::models::{QuotaLimits, QuotaStatus};
+use crate::quota;
+use crate::{Db, Relays};
+
+/// GET /api/projects/:project_id/quotas - Quota use against the limits in effect
+#[gotcha::api]
+pub async fn get_project_quotas(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(relays): State<Relays>,
+    State(settings): State<Settings>,
+    Path(project_id): Path<Uuid>,
+) -> Result<Json<QuotaStatus>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    db.get_project(project_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Project {} not found", project_id)))?;
+
+    let status = quota::status(&db, &relays, &settings.quotas, project_id).await?;
+    Ok(Json(status))
+}
+
+/// PUT /api/projects/:project_id/quotas - Replace the project's overrides
+///
+/// Limits left out use the configured defaults again.
+#[gotcha::api]
+pub async fn update_project_quotas(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(relays): State<Relays>,
+    State(settings): State<Settings>,
+    Path(project_id): Path<Uuid>,
+    Json(payload): Json<QuotaLimits>,
+) -> Result<Json<QuotaStatus>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    db.get_project(project_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Project {} not found", project_id)))?;
+
+    let quotas = db.set_project_quotas(project_id, &payload).await?;
+    relays.set_session_quota(
+        project_id,
+        quotas
+            .overrides
+            .max_concurrent_sessions
+            .map(|max| max as usize),
+    );
+
+    let status = quota::status(&db, &relays, &settings.quotas, project_id).await?;
+    Ok(Json(status))
+}
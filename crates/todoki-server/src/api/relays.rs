@@ -2,7 +2,7 @@ use gotcha::Json;
 use axum::extract::State;
 use uuid::Uuid;
 
-use crate::relay::RelayInfo;
+use crate::relay::{CapacityInfo, RelayInfo};
 use crate::Relays;
 
 /// List all connected relays
@@ -12,6 +12,12 @@ pub async fn list_relays(State(relays): State<Relays>) -> Json<Vec<RelayInfo>> {
     Json(list)
 }
 
+/// Current session utilization against the concurrency caps
+#[gotcha::api]
+pub async fn get_capacity(State(relays): State<Relays>) -> Json<CapacityInfo> {
+    Json(relays.capacity().await)
+}
+
 /// Get relay by ID
 #[gotcha::api]
 pub async fn get_relay(
@@ -0,0 +1,309 @@
+//! WebSocket playback of stored agent sessions
+//!
+//! Streams the events of one session in their original order and with their
+//! original spacing, scaled by a speed multiplier, so the UI can play a
+//! session back like a screen recording. While playing, the client can send
+//! `{"type": "pause"}`, `{"type": "resume"}` and
+//! `{"type": "speed", "speed": 4.0}`.
+
+use std::time::Duration;
+
+use axum::extract::ws::{Message, WebSocket};
+use axum::extract::{Path, Query, State, WebSocketUpgrade};
+use axum::response::Response;
+use axum::Extension;
+use futures_util::stream::SplitSink;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
+use crate::auth::{share_token, AuthContext};
+use crate::config::Settings;
+use crate::event_bus::Event;
+use crate::transcript::TRANSCRIPT_EVENT_LIMIT;
+use crate::{Db, Subscriber};
+
+/// Slowest and fastest playback
+const MIN_SPEED: f64 = 0.1;
+const MAX_SPEED: f64 = 64.0;
+
+#[derive(Debug, Deserialize)]
+pub struct SessionReplayParams {
+    /// Playback speed multiplier (default 1, from 0.1 to 64)
+    pub speed: Option<f64>,
+    /// Shorten idle stretches longer than this (milliseconds); unset keeps
+    /// the original timing
+    pub max_gap_ms: Option<u64>,
+    /// User or share token, for browsers that cannot set headers
+    pub token: Option<String>,
+}
+
+/// Server → client messages
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ReplayMessage {
+    /// Sent first: what is about to be played
+    Started {
+        session_id: Uuid,
+        count: usize,
+        duration_ms: u64,
+        speed: f64,
+    },
+    /// A stored event, `offset_ms` into the recording
+    Event { offset_ms: u64, event: Box<Event> },
+    /// Playback paused, resumed or changed speed
+    State {
+        paused: bool,
+        speed: f64,
+        position_ms: u64,
+    },
+    /// Every event was played
+    Complete { count: usize },
+    Error { message: String },
+}
+
+/// Client → server messages
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ReplayControl {
+    Pause,
+    Resume,
+    Speed { speed: f64 },
+}
+
+fn check_speed(speed: f64) -> Result<f64, String> {
+    if speed.is_finite() && (MIN_SPEED..=MAX_SPEED).contains(&speed) {
+        Ok(speed)
+    } else {
+        Err(format!("speed must be between {} and {}", MIN_SPEED, MAX_SPEED))
+    }
+}
+
+/// GET /api/agents/:agent_id/sessions/:session_id/replay - Play a session back
+///
+/// Upgrades to a WebSocket that sends the session's stored events spaced as
+/// they were recorded, divided by `speed`, then a `complete` message.
+pub async fn replay_session(
+    ws: WebSocketUpgrade,
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(subscriber): State<Subscriber>,
+    State(settings): State<Settings>,
+    Path((agent_id, session_id)): Path<(Uuid, Uuid)>,
+    Query(params): Query<SessionReplayParams>,
+) -> Result<Response, ApiError> {
+    // Share tokens of this session may watch it too
+    let token_allowed = params.token.as_deref().is_some_and(|token| {
+        crate::auth::user_token_id(&settings, token).is_some()
+            || share_token::verify(&settings.agent_tokens.secret, token).is_some_and(|share| {
+                share.shared_agent_id == agent_id && share.shared_session_id == session_id
+            })
+    });
+    if auth.require_auth().is_err() && !token_allowed {
+        return Err(ApiError::unauthorized());
+    }
+
+    let speed = check_speed(params.speed.unwrap_or(1.0)).map_err(ApiError::bad_request)?;
+    db.get_agent_session(session_id)
+        .await?
+        .filter(|s| s.agent_id == agent_id)
+        .ok_or_else(|| ApiError::not_found("session not found"))?;
+
+    let events = subscriber
+        .session_events(agent_id, &session_id.to_string(), TRANSCRIPT_EVENT_LIMIT)
+        .await
+        .map_err(|e| ApiError::internal(format!("failed to load session events: {}", e)))?;
+    let max_gap = params.max_gap_ms.map(Duration::from_millis);
+
+    info!(
+        agent_id = %agent_id,
+        session_id = %session_id,
+        events = events.len(),
+        speed,
+        "Replaying session"
+    );
+    Ok(ws.on_upgrade(move |socket| play(socket, session_id, events, speed, max_gap)))
+}
+
+/// Playback offset of each event: the time since the first one, with idle
+/// stretches longer than `max_gap` shortened to it
+fn offsets(events: &[Event], max_gap: Option<Duration>) -> Vec<Duration> {
+    let mut offset = Duration::ZERO;
+    let mut previous = None;
+    events
+        .iter()
+        .map(|event| {
+            if let Some(previous) = previous {
+                // Clock skew between relays can put an event before the last
+                let gap = (event.time - previous).to_std().unwrap_or_default();
+                offset += max_gap.map_or(gap, |max| gap.min(max));
+            }
+            previous = Some(event.time);
+            offset
+        })
+        .collect()
+}
+
+/// Position in the recording and how it advances
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Playback {
+    speed: f64,
+    paused: bool,
+    position: Duration,
+}
+
+impl Playback {
+    fn apply(&mut self, control: ReplayControl) -> Result<(), String> {
+        match control {
+            ReplayControl::Pause => self.paused = true,
+            ReplayControl::Resume => self.paused = false,
+            ReplayControl::Speed { speed } => self.speed = check_speed(speed)?,
+        }
+        Ok(())
+    }
+
+    /// Move on by `elapsed` of wall time, stopping at `until`
+    fn advance(&mut self, elapsed: Duration, until: Duration) {
+        if !self.paused {
+            self.position = (self.position + elapsed.mul_f64(self.speed)).min(until);
+        }
+    }
+
+    fn state(&self) -> ReplayMessage {
+        ReplayMessage::State {
+            paused: self.paused,
+            speed: self.speed,
+            position_ms: self.position.as_millis() as u64,
+        }
+    }
+}
+
+type ReplaySink = SplitSink<WebSocket, Message>;
+
+async fn send(tx: &mut ReplaySink, message: &ReplayMessage) -> Result<(), ()> {
+    let json = serde_json::to_string(message).map_err(|_| ())?;
+    tx.send(Message::Text(json)).await.map_err(|_| ())
+}
+
+async fn play(
+    socket: WebSocket,
+    session_id: Uuid,
+    events: Vec<Event>,
+    speed: f64,
+    max_gap: Option<Duration>,
+) {
+    let (mut tx, mut rx) = socket.split();
+    let offsets = offsets(&events, max_gap);
+    let count = events.len();
+
+    let started = ReplayMessage::Started {
+        session_id,
+        count,
+        duration_ms: offsets.last().map_or(0, |d| d.as_millis() as u64),
+        speed,
+    };
+    if send(&mut tx, &started).await.is_err() {
+        return;
+    }
+
+    let mut playback = Playback {
+        speed,
+        paused: false,
+        position: Duration::ZERO,
+    };
+    for (event, offset) in events.into_iter().zip(offsets) {
+        while playback.position < offset {
+            let waiting_since = Instant::now();
+            let wait = (offset - playback.position).div_f64(playback.speed);
+            tokio::select! {
+                _ = tokio::time::sleep(wait), if !playback.paused => playback.position = offset,
+                msg = rx.next() => {
+                    playback.advance(waiting_since.elapsed(), offset);
+                    let reply = match msg {
+                        Some(Ok(Message::Text(text))) => {
+                            match serde_json::from_str::<ReplayControl>(&text)
+                                .map_err(|e| format!("invalid control message: {}", e))
+                                .and_then(|control| playback.apply(control))
+                            {
+                                Ok(()) => playback.state(),
+                                Err(message) => ReplayMessage::Error { message },
+                            }
+                        }
+                        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                            debug!(session_id = %session_id, "Replay client went away");
+                            return;
+                        }
+                        Some(Ok(_)) => continue,
+                    };
+                    if send(&mut tx, &reply).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+
+        let message = ReplayMessage::Event {
+            offset_ms: offset.as_millis() as u64,
+            event: Box::new(event),
+        };
+        if send(&mut tx, &message).await.is_err() {
+            return;
+        }
+    }
+
+    let _ = send(&mut tx, &ReplayMessage::Complete { count }).await;
+    let _ = tx.close().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn event_at(secs: u32) -> Event {
+        Event {
+            time: Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, secs).unwrap(),
+            ..Event::new("agent.output_batch", Uuid::nil(), serde_json::json!({}))
+        }
+    }
+
+    #[test]
+    fn test_offsets_keep_spacing_and_shorten_gaps() {
+        let events = vec![event_at(10), event_at(12), event_at(11), event_at(50)];
+        let secs =
+            |offsets: Vec<Duration>| offsets.iter().map(|d| d.as_secs()).collect::<Vec<_>>();
+
+        assert_eq!(secs(offsets(&events, None)), vec![0, 2, 2, 41]);
+        let max_gap = Some(Duration::from_secs(5));
+        assert_eq!(secs(offsets(&events, max_gap)), vec![0, 2, 2, 7]);
+        assert!(offsets(&[], None).is_empty());
+    }
+
+    #[test]
+    fn test_playback_controls() {
+        let mut playback = Playback {
+            speed: 2.0,
+            paused: false,
+            position: Duration::ZERO,
+        };
+        playback.advance(Duration::from_secs(1), Duration::from_secs(10));
+        assert_eq!(playback.position, Duration::from_secs(2));
+        playback.advance(Duration::from_secs(30), Duration::from_secs(10));
+        assert_eq!(playback.position, Duration::from_secs(10));
+
+        playback.apply(ReplayControl::Pause).unwrap();
+        playback.advance(Duration::from_secs(1), Duration::from_secs(20));
+        assert_eq!(playback.position, Duration::from_secs(10));
+
+        let control: ReplayControl =
+            serde_json::from_str(r#"{"type":"speed","speed":8}"#).unwrap();
+        playback.apply(control).unwrap();
+        assert_eq!(playback.speed, 8.0);
+        assert!(playback.apply(ReplayControl::Speed { speed: 0.0 }).is_err());
+        assert!(playback.apply(ReplayControl::Speed { speed: f64::NAN }).is_err());
+        assert_eq!(playback.speed, 8.0);
+    }
+}
@@ -0,0 +1,92 @@
+use gotcha::axum::extract::{Path, State};
+use gotcha::axum::http::header;
+use gotcha::axum::response::{IntoResponse, Response};
+use gotcha::axum::Extension;
+use gotcha::Json;
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
+use crate::auth::AuthContext;
+use crate::models::{PublicProjectStatus, StatusPageRequest, StatusPageResponse};
+use crate::Db;
+
+/// GET /api/projects/:project_id/status-page - Status page settings and token
+#[gotcha::api]
+pub async fn get_status_page(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(project_id): Path<Uuid>,
+) -> Result<Json<StatusPageResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let page = db
+        .get_status_page(project_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("this project has no status page"))?;
+    Ok(Json(page.into()))
+}
+
+/// PUT /api/projects/:project_id/status-page - Enable or configure the page
+///
+/// The first call issues the token; later calls keep it unless
+/// `rotate_token` is set.
+#[gotcha::api]
+pub async fn update_status_page(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(project_id): Path<Uuid>,
+    Json(payload): Json<StatusPageRequest>,
+) -> Result<Json<StatusPageResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    db.get_project(project_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Project {} not found", project_id)))?;
+
+    let page = db
+        .upsert_status_page(
+            project_id,
+            payload.enabled,
+            &payload.redact,
+            payload.rotate_token,
+        )
+        .await?;
+    Ok(Json(page.into()))
+}
+
+async fn public_status(db: &Db, token: &str) -> Result<PublicProjectStatus, ApiError> {
+    let not_found = || ApiError::not_found("status page not found");
+    let page = db
+        .get_status_page_by_token(token)
+        .await?
+        .ok_or_else(not_found)?;
+    let project = db
+        .get_project(page.project_id)
+        .await?
+        .filter(|p| !p.archived)
+        .ok_or_else(not_found)?;
+
+    let snapshot = db.get_project_snapshot(project.id).await?;
+    Ok(PublicProjectStatus::build(&page, &project, snapshot))
+}
+
+/// GET /api/public/status/:token - A project's progress, no login needed
+#[gotcha::api]
+pub async fn get_public_status(
+    State(db): State<Db>,
+    Path(token): Path<String>,
+) -> Result<Json<PublicProjectStatus>, ApiError> {
+    Ok(Json(public_status(&db, &token).await?))
+}
+
+/// GET /status/:token - The same as an HTML page, for embedding
+pub async fn public_status_page(
+    State(db): State<Db>,
+    Path(token): Path<String>,
+) -> Result<Response, ApiError> {
+    let status = public_status(&db, &token).await?;
+    Ok((
+        [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+        status.render_html(),
+    )
+        .into_response())
+}
@@ -0,0 +1,121 @@
+use gotcha::axum::extract::{Path, Query, State};
+use gotcha::axum::Extension;
+use gotcha::Json;
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
+use crate::api::validation;
+use crate::auth::AuthContext;
+use crate::models::{
+    valid_context_key, ContextWriter, TaskContextEntryResponse, TaskContextListQuery,
+    TaskContextWriteRequest,
+};
+use crate::Db;
+
+async fn require_task(db: &Db, task_id: Uuid) -> Result<(), ApiError> {
+    db.get_task_by_id(task_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("task not found"))?;
+    Ok(())
+}
+
+fn check_key(key: &str) -> Result<(), ApiError> {
+    if valid_context_key(key) {
+        Ok(())
+    } else {
+        Err(ApiError::bad_request(
+            "context keys use letters, digits, '.', '_', '-' and ':' (at most 200)",
+        ))
+    }
+}
+
+/// GET /api/tasks/:task_id/context - Shared context entries of a task
+///
+/// Agents read it with their scoped token for the task.
+#[gotcha::api]
+pub async fn list_task_context(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(task_id): Path<Uuid>,
+    Query(query): Query<TaskContextListQuery>,
+) -> Result<Json<Vec<TaskContextEntryResponse>>, ApiError> {
+    auth.require_task(task_id)?;
+    require_task(&db, task_id).await?;
+
+    let entries = db
+        .list_task_context(task_id, query.prefix.as_deref())
+        .await?;
+    Ok(Json(entries.into_iter().map(Into::into).collect()))
+}
+
+/// GET /api/tasks/:task_id/context/:key - One shared context entry
+#[gotcha::api]
+pub async fn get_task_context(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path((task_id, key)): Path<(Uuid, String)>,
+) -> Result<Json<TaskContextEntryResponse>, ApiError> {
+    auth.require_task(task_id)?;
+    check_key(&key)?;
+
+    let entry = db
+        .get_task_context(task_id, &key)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("context entry {} not found", key)))?;
+    Ok(Json(entry.into()))
+}
+
+/// PUT /api/tasks/:task_id/context/:key - Write a shared context entry
+///
+/// Every write bumps the entry's version. Pass `expected_version` to only
+/// write over the version you read (`0` to only create); a 409 means
+/// someone else wrote in between.
+#[gotcha::api]
+pub async fn put_task_context(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path((task_id, key)): Path<(Uuid, String)>,
+    Json(payload): Json<TaskContextWriteRequest>,
+) -> Result<Json<TaskContextEntryResponse>, ApiError> {
+    auth.require_task(task_id)?;
+    check_key(&key)?;
+    validation::validate(&payload)?;
+    require_task(&db, task_id).await?;
+
+    let scope = auth.agent_scope();
+    let writer = ContextWriter {
+        token_id: auth
+            .token_id()
+            .ok_or_else(ApiError::unauthorized)?
+            .to_string(),
+        agent_id: scope.map(|s| s.agent_id),
+        session_id: scope.map(|s| s.session_id),
+    };
+    let entry = db
+        .put_task_context(task_id, &key, &payload.value, payload.expected_version, &writer)
+        .await?
+        .ok_or_else(|| {
+            ApiError::conflict(format!(
+                "context entry {} is not at version {}",
+                key,
+                payload.expected_version.unwrap_or_default()
+            ))
+        })?;
+    Ok(Json(entry.into()))
+}
+
+/// DELETE /api/tasks/:task_id/context/:key - Remove a shared context entry
+#[gotcha::api]
+pub async fn delete_task_context(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path((task_id, key)): Path<(Uuid, String)>,
+) -> Result<Json<()>, ApiError> {
+    auth.require_task(task_id)?;
+    check_key(&key)?;
+
+    if !db.delete_task_context(task_id, &key).await? {
+        return Err(ApiError::not_found(format!("context entry {} not found", key)));
+    }
+    Ok(Json(()))
+}
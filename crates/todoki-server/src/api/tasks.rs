@@ -1,25 +1,48 @@
-use gotcha::axum::extract::{Path, State};
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use gotcha::axum::extract::{Path, Query, State};
 use gotcha::axum::Extension;
 use gotcha::{Json, Schematic};
 use serde::{Deserialize, Serialize};
+use todoki_protocol::event_bus::{
+    AgentTaskContext, BuiltinEvent, RelayInputRequestedData, RelaySpawnRequestedData,
+    RelayStopRequestedData, TaskCompletedData, TaskCreatedData, TaskHandoffData,
+    TaskScheduledData, TaskSnoozedData, TaskStatusChangedData, TaskUnsnoozedData,
+};
+use todoki_protocol::SessionMode;
 use uuid::Uuid;
 
-use crate::api::error::ApiError;
+use crate::api::error::{ApiError, FieldError};
+use crate::api::undo;
+use crate::api::validation;
+use crate::api::views;
 use crate::auth::AuthContext;
+use crate::ci;
+use crate::config::Settings;
+use crate::db::DatabaseService;
+use crate::event_bus::{Event, EventPublisher, EventScope};
 use crate::models::agent::{
-    AgentResponse, AgentRole, AgentSessionResponse, AgentStatus, CreateAgent, ExecutionMode,
-    SessionStatus,
+    Agent, AgentResponse, AgentRole, AgentSession, AgentSessionResponse, AgentStatus,
+    CreateAgent, ExecutionMode, SessionStatus,
 };
-use crate::models::project::Project;
+use crate::relay::{LabelSelector, RelayManager};
+use crate::models::project::{Project, RelayRoute};
+use crate::models::rank;
 use crate::models::task::{Task, TaskStatus};
+use crate::models::workflow::{status_key, StateTarget};
 use crate::models::{
-    CreateTask, TaskCommentCreateRequest, TaskCommentResponse, TaskCreateRequest, TaskResponse,
-    TaskStatusUpdateRequest, TaskUpdateRequest,
+    handoff_artifact_data, inject_handoff, ArtifactResponse, CreateTask, HandoffRequest,
+    HandoffResponse, MergeTaskRequest, TaskCommentCreateRequest, TaskCommentResponse,
+    TaskCreateRequest, TaskCreateResponse, TaskExecutionHistory, TaskMoveRequest, TaskResponse,
+    TaskSnoozeRequest, TaskStatusUpdateRequest, TaskUpdateRequest, UndoAction, WatchTarget,
+    Workflow, HANDOFF_ARTIFACT_TYPE,
 };
-use crate::event_bus::kinds::EventKind;
+use crate::api::watches::watch_as_owner;
 use crate::Db;
 use crate::Publisher;
 use crate::Relays;
+use crate::Watches;
 
 pub async fn tasks_to_responses(db: &Db, tasks: Vec<crate::models::Task>) -> crate::Result<Vec<TaskResponse>> {
     let mut responses = Vec::with_capacity(tasks.len());
@@ -29,14 +52,40 @@ pub async fn tasks_to_responses(db: &Db, tasks: Vec<crate::models::Task>) -> cra
     Ok(responses)
 }
 
-/// GET /api/tasks - Get today's tasks (todo, not archived)
+/// Workflow of `project_id`; the built-in one when the project is missing
+async fn project_workflow(db: &DatabaseService, project_id: Uuid) -> Result<Workflow, ApiError> {
+    Ok(db
+        .get_project(project_id)
+        .await?
+        .map(|project| project.workflow())
+        .unwrap_or_else(Workflow::builtin))
+}
+
+fn check_priority(workflow: &Workflow, priority: i32) -> Result<(), ApiError> {
+    workflow
+        .check_priority(priority)
+        .map_err(|e| ApiError::validation(vec![FieldError::new("priority", e)]))
+}
+
+#[derive(Debug, Deserialize, Schematic)]
+pub struct TaskListQuery {
+    /// Run this saved view instead of listing today's tasks
+    pub view_id: Option<Uuid>,
+}
+
+/// GET /api/tasks - Get today's tasks (todo, not archived), or the tasks of
+/// a saved view
 #[gotcha::api]
 pub async fn get_tasks(
     Extension(auth): Extension<AuthContext>,
     State(db): State<Db>,
+    Query(query): Query<TaskListQuery>,
 ) -> Result<Json<Vec<TaskResponse>>, ApiError> {
     auth.require_auth().map_err(|_| ApiError::unauthorized())?;
 
+    if let Some(view_id) = query.view_id {
+        return Ok(Json(views::view_tasks(&db, view_id).await?));
+    }
     let tasks = db.get_today_tasks().await?;
     let responses = tasks_to_responses(&db, tasks).await?;
     Ok(Json(responses))
@@ -112,20 +161,64 @@ pub async fn get_today_done_tasks(
 pub async fn create_task(
     Extension(auth): Extension<AuthContext>,
     State(db): State<Db>,
+    State(settings): State<Settings>,
     Json(payload): Json<TaskCreateRequest>,
-) -> Result<Json<TaskResponse>, ApiError> {
+) -> Result<Json<TaskCreateResponse>, ApiError> {
     auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    validation::validate(&payload)?;
+
+    let workflow = project_workflow(&db, payload.project_id).await?;
+    check_priority(&workflow, payload.priority)?;
+    let target = match &payload.state {
+        Some(key) => workflow.state(key).map(|state| workflow.target(state)).ok_or_else(|| {
+            ApiError::validation(vec![FieldError::new(
+                "state",
+                format!("'{}' is not a state of this project's workflow", key),
+            )])
+        })?,
+        None => StateTarget {
+            status: payload.status,
+            workflow_state: None,
+        },
+    };
 
     let create_task = CreateTask::new(
         payload.content,
-        payload.status,
+        target.status,
         payload.priority,
         payload.project_id,
-    );
+    )
+    .with_due_at(payload.due_at)
+    .with_estimate_minutes(payload.estimate_minutes)
+    .with_workflow_state(target.workflow_state);
 
-    let task = db.create_task(create_task).await?;
-    let response = db.get_task_response(task).await?;
-    Ok(Json(response))
+    // task.created goes through the outbox so it can't be lost after the insert
+    let task = db
+        .create_task_with_events(create_task, |task| {
+            let event = BuiltinEvent::TaskCreated(TaskCreatedData {
+                title: task.content.lines().next().unwrap_or_default().to_string(),
+                description: Some(task.content.clone()),
+                parent_task_id: None,
+                source: Some("api".to_string()),
+                sender: None,
+            });
+            vec![Event::builtin(event, EventScope::task(task.id))]
+        })
+        .await?;
+
+    let possible_duplicates = db
+        .flag_possible_duplicates(&task, &settings.dedup)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!(task_id = %task.id, error = %e, "duplicate detection failed");
+            Vec::new()
+        });
+
+    let task = db.get_task_response(task).await?;
+    Ok(Json(TaskCreateResponse {
+        task,
+        possible_duplicates,
+    }))
 }
 
 /// GET /api/tasks/:task_id - Get task by ID
@@ -155,9 +248,18 @@ pub async fn update_task(
     Json(payload): Json<TaskUpdateRequest>,
 ) -> Result<Json<TaskResponse>, ApiError> {
     auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    validation::validate(&payload)?;
+    check_priority(&project_workflow(&db, payload.project_id).await?, payload.priority)?;
 
     let task = db
-        .update_task(task_id, payload.priority, payload.content, payload.project_id)
+        .update_task(
+            task_id,
+            payload.priority,
+            payload.content,
+            payload.project_id,
+            payload.due_at,
+            payload.estimate_minutes,
+        )
         .await?;
 
     let response = db.get_task_response(task).await?;
@@ -165,6 +267,9 @@ pub async fn update_task(
 }
 
 /// POST /api/tasks/:task_id/status - Update task status
+///
+/// Moves follow the project's workflow: the target must be one of its
+/// states and an allowed transition from the task's current state.
 #[gotcha::api]
 pub async fn update_task_status(
     Extension(auth): Extension<AuthContext>,
@@ -174,11 +279,174 @@ pub async fn update_task_status(
 ) -> Result<Json<TaskResponse>, ApiError> {
     auth.require_auth().map_err(|_| ApiError::unauthorized())?;
 
-    let task = db.update_task_status(task_id, payload.status).await?;
+    let to = match (payload.state, payload.status) {
+        (Some(state), _) => state,
+        (None, Some(status)) => status_key(status),
+        (None, None) => {
+            return Err(ApiError::validation(vec![FieldError::new(
+                "state",
+                "either status or state is required",
+            )]));
+        }
+    };
+    let current = db
+        .get_task_by_id(task_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Task {} not found", task_id)))?;
+    let target = project_workflow(&db, current.project_id)
+        .await?
+        .transition(&current.state(), &to)
+        .map_err(|e| ApiError::validation(vec![FieldError::new("state", e)]))?;
+
+    let actuals = if target.status == TaskStatus::Done {
+        load_task_actuals(&db, task_id).await
+    } else {
+        (0, 0)
+    };
+
+    // Status events are queued in the same transaction as the status change
+    let mut changed = false;
+    let mut previous_state = None;
+    let task = db
+        .update_task_status_with_events(
+            task_id,
+            target.status,
+            target.workflow_state,
+            |previous, task| {
+                changed = previous.state() != task.state();
+                previous_state = previous.workflow_state.clone();
+                status_change_events(previous, task, actuals)
+            },
+        )
+        .await?;
+    if changed {
+        // The custom state to return to on undo
+        let snapshot = previous_state.map(|state| serde_json::json!({ "workflow_state": state }));
+        undo::record(&db, &auth, UndoAction::TaskStatus, task_id, snapshot).await;
+    }
+
     let response = db.get_task_response(task).await?;
     Ok(Json(response))
 }
 
+/// POST /api/tasks/:task_id/move - Move a task to a position in a column
+///
+/// Columns are workflow states, ordered by manual rank; tasks never moved
+/// come after the ranked ones, by priority. Changing column follows the
+/// same workflow rules as a status update.
+#[gotcha::api]
+pub async fn move_task(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(task_id): Path<Uuid>,
+    Json(payload): Json<TaskMoveRequest>,
+) -> Result<Json<TaskResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let current = db
+        .get_task_by_id(task_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Task {} not found", task_id)))?;
+    let from = current.state();
+    let to = match (payload.state, payload.status) {
+        (Some(state), _) => state,
+        (None, Some(status)) => status_key(status),
+        (None, None) => from.clone(),
+    };
+    let target = if to == from {
+        StateTarget {
+            status: current.status,
+            workflow_state: current.workflow_state.clone(),
+        }
+    } else {
+        project_workflow(&db, current.project_id)
+            .await?
+            .transition(&from, &to)
+            .map_err(|e| ApiError::validation(vec![FieldError::new("state", e)]))?
+    };
+
+    let column: Vec<(Uuid, Option<String>)> = db
+        .column_ranks(current.project_id, target.status, target.workflow_state.as_deref())
+        .await?
+        .into_iter()
+        .filter(|(id, _)| *id != task_id)
+        .collect();
+    let ranks: Vec<Option<String>> = column.iter().map(|(_, rank)| rank.clone()).collect();
+    let (rank, respread) = rank::place(&ranks, payload.position);
+    let respread = respread
+        .map(|ranks| column.iter().map(|(id, _)| *id).zip(ranks).collect())
+        .unwrap_or_default();
+
+    let actuals = if target.status == TaskStatus::Done && current.status != TaskStatus::Done {
+        load_task_actuals(&db, task_id).await
+    } else {
+        (0, 0)
+    };
+
+    let mut changed = false;
+    let mut previous_state = None;
+    let task = db
+        .move_task_with_events(task_id, target, rank, respread, |previous, task| {
+            changed = previous.state() != task.state();
+            previous_state = previous.workflow_state.clone();
+            status_change_events(previous, task, actuals)
+        })
+        .await?;
+    if changed {
+        let snapshot = previous_state.map(|state| serde_json::json!({ "workflow_state": state }));
+        undo::record(&db, &auth, UndoAction::TaskStatus, task_id, snapshot).await;
+    }
+
+    let response = db.get_task_response(task).await?;
+    Ok(Json(response))
+}
+
+/// Session count and recorded seconds of a task, zero when unavailable
+async fn load_task_actuals(db: &DatabaseService, task_id: Uuid) -> (i64, i64) {
+    match db.get_task_actuals(task_id).await {
+        Ok(actuals) => actuals,
+        Err(e) => {
+            tracing::warn!(task_id = %task_id, error = %e, "failed to load task actuals");
+            (0, 0)
+        }
+    }
+}
+
+/// Events of a status change: `task.status_changed` when the task left its
+/// state and `task.completed` when it became done
+fn status_change_events(previous: &Task, task: &Task, actuals: (i64, i64)) -> Vec<Event> {
+    let mut events = Vec::new();
+    if previous.state() != task.state() {
+        let event = BuiltinEvent::TaskStatusChanged(TaskStatusChangedData {
+            old_status: previous.state(),
+            new_status: task.state(),
+        });
+        events.push(Event::builtin(event, EventScope::task(task.id)));
+    }
+    if task.status == TaskStatus::Done && previous.status != TaskStatus::Done {
+        events.push(task_completed_event(task, actuals));
+    }
+    events
+}
+
+/// `task.completed` enriched with the task's estimate and recorded actuals
+fn task_completed_event(task: &Task, (session_count, actual_secs): (i64, i64)) -> Event {
+    let actual_minutes = (session_count > 0).then(|| actual_secs as f64 / 60.0);
+    let estimate_ratio = match (task.estimate_minutes, actual_minutes) {
+        (Some(estimate), Some(actual)) if estimate > 0 => Some(actual / estimate as f64),
+        _ => None,
+    };
+
+    let event = BuiltinEvent::TaskCompleted(TaskCompletedData {
+        result: None,
+        estimate_minutes: task.estimate_minutes,
+        actual_minutes,
+        session_count: Some(session_count),
+        estimate_ratio,
+    });
+    Event::builtin(event, EventScope::task(task.id))
+}
+
 /// POST /api/tasks/:task_id/archive - Archive task
 #[gotcha::api]
 pub async fn archive_task(
@@ -189,6 +457,42 @@ pub async fn archive_task(
     auth.require_auth().map_err(|_| ApiError::unauthorized())?;
 
     let task = db.archive_task(task_id).await?;
+    undo::record(&db, &auth, UndoAction::TaskArchive, task_id, None).await;
+    let response = db.get_task_response(task).await?;
+    Ok(Json(response))
+}
+
+/// POST /api/tasks/:task_id/merge - Merge a duplicate into this task
+///
+/// The duplicate's comments and artifacts move to this task and the
+/// duplicate is archived.
+#[gotcha::api]
+pub async fn merge_task(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(task_id): Path<Uuid>,
+    Json(payload): Json<MergeTaskRequest>,
+) -> Result<Json<TaskResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    if payload.duplicate_id == task_id {
+        return Err(ApiError::bad_request("a task cannot be merged into itself"));
+    }
+    let task = db
+        .get_task_by_id(task_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Task {} not found", task_id)))?;
+    let duplicate = db
+        .get_task_by_id(payload.duplicate_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Task {} not found", payload.duplicate_id)))?;
+    if duplicate.project_id != task.project_id {
+        return Err(ApiError::bad_request(
+            "only tasks of the same project can be merged",
+        ));
+    }
+
+    let task = db.merge_tasks(task_id, payload.duplicate_id).await?;
     let response = db.get_task_response(task).await?;
     Ok(Json(response))
 }
@@ -203,10 +507,85 @@ pub async fn unarchive_task(
     auth.require_auth().map_err(|_| ApiError::unauthorized())?;
 
     let task = db.unarchive_task(task_id).await?;
+    undo::record(&db, &auth, UndoAction::TaskUnarchive, task_id, None).await;
+    let response = db.get_task_response(task).await?;
+    Ok(Json(response))
+}
+
+/// POST /api/tasks/:task_id/snooze - Hide a task from the inbox until a wake time
+#[gotcha::api]
+pub async fn snooze_task(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(publisher): State<Publisher>,
+    Path(task_id): Path<Uuid>,
+    Json(payload): Json<TaskSnoozeRequest>,
+) -> Result<Json<TaskResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    if payload.until <= Utc::now() {
+        return Err(ApiError::bad_request("snooze time must be in the future"));
+    }
+    let task = db
+        .get_task_by_id(task_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("task not found"))?;
+
+    db.snooze_task(task_id, payload.until, payload.notify).await?;
+
+    let event = BuiltinEvent::TaskSnoozed(TaskSnoozedData {
+        until: payload.until.to_rfc3339(),
+        notify: payload.notify,
+    });
+    if let Err(e) = publisher.emit_builtin(event, EventScope::task(task_id)).await {
+        tracing::warn!(task_id = %task_id, error = %e, "failed to emit task.snoozed");
+    }
+
+    let response = db.get_task_response(task).await?;
+    Ok(Json(response))
+}
+
+/// DELETE /api/tasks/:task_id/snooze - Return a snoozed task to the inbox now
+#[gotcha::api]
+pub async fn unsnooze_task(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(publisher): State<Publisher>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<TaskResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let task = db
+        .get_task_by_id(task_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("task not found"))?;
+
+    if db.unsnooze_task(task_id).await? {
+        let event = BuiltinEvent::TaskUnsnoozed(TaskUnsnoozedData {
+            reason: "manual".to_string(),
+        });
+        if let Err(e) = publisher.emit_builtin(event, EventScope::task(task_id)).await {
+            tracing::warn!(task_id = %task_id, error = %e, "failed to emit task.unsnoozed");
+        }
+    }
+
     let response = db.get_task_response(task).await?;
     Ok(Json(response))
 }
 
+/// GET /api/tasks/snoozed - Get snoozed tasks, soonest wake-up first
+#[gotcha::api]
+pub async fn get_snoozed_tasks(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+) -> Result<Json<Vec<TaskResponse>>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let tasks = db.get_snoozed_tasks().await?;
+    let responses = tasks_to_responses(&db, tasks).await?;
+    Ok(Json(responses))
+}
+
 /// DELETE /api/tasks/:task_id - Delete task
 #[gotcha::api]
 pub async fn delete_task(
@@ -216,7 +595,11 @@ pub async fn delete_task(
 ) -> Result<Json<()>, ApiError> {
     auth.require_auth().map_err(|_| ApiError::unauthorized())?;
 
+    let snapshot = db.snapshot_task(task_id).await?;
     db.delete_task(task_id).await?;
+    if snapshot.is_some() {
+        undo::record(&db, &auth, UndoAction::TaskDelete, task_id, snapshot).await;
+    }
     Ok(Json(()))
 }
 
@@ -225,12 +608,18 @@ pub async fn delete_task(
 pub async fn add_comment(
     Extension(auth): Extension<AuthContext>,
     State(db): State<Db>,
+    State(publisher): State<Publisher>,
     Path(task_id): Path<Uuid>,
     Json(payload): Json<TaskCommentCreateRequest>,
 ) -> Result<Json<TaskCommentResponse>, ApiError> {
-    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    auth.require_task(task_id)?;
+    validation::validate(&payload)?;
 
     let comment = db.add_task_comment(task_id, payload.content).await?;
+    if let Some(task) = db.get_task_by_id(task_id).await? {
+        let author = auth.agent_scope().map(|scope| scope.agent_id);
+        crate::notification::mention::publish(&db, &publisher, &task, &comment, author).await;
+    }
     Ok(Json(comment.into()))
 }
 
@@ -240,14 +629,25 @@ pub async fn add_comment(
 
 #[derive(Debug, Deserialize, Schematic)]
 pub struct ExecuteTaskRequest {
-    /// Optionally specify a relay ID to use
+    /// Optionally specify a relay ID to use; overrides the project's relay
+    /// routing for this execution
     pub relay_id: Option<String>,
+    /// Label selector the relay must match (e.g. `gpu=true,region in (eu,us)`);
+    /// overrides the project's relay routing for this execution
+    pub relay_selector: Option<String>,
 }
 
 #[derive(Debug, Serialize, Schematic)]
 pub struct ExecuteTaskResponse {
-    pub agent: AgentResponse,
-    pub session: AgentSessionResponse,
+    /// Spawned agent (absent when the execution was scheduled)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent: Option<AgentResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<AgentSessionResponse>,
+    /// Set when the project's execution window is closed and the execution
+    /// was queued until it opens
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheduled_for: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Schematic)]
@@ -284,13 +684,7 @@ fn render_task_prompt(template: &str, task: &Task, project: &Project) -> String
 
 /// Get template for the given role from project
 fn get_template_for_role(project: &Project, role: AgentRole) -> &str {
-    match role {
-        AgentRole::General => project.general_template.as_deref(),
-        AgentRole::Business => project.business_template.as_deref(),
-        AgentRole::Coding => project.coding_template.as_deref(),
-        AgentRole::Qa => project.qa_template.as_deref(),
-    }
-    .unwrap_or(DEFAULT_TEMPLATE)
+    project.template_for_role(role).unwrap_or(DEFAULT_TEMPLATE)
 }
 
 /// POST /api/tasks/:task_id/execute - Execute task on a relay
@@ -300,11 +694,16 @@ pub async fn execute_task(
     State(db): State<Db>,
     State(relays): State<Relays>,
     State(publisher): State<Publisher>,
+    State(watches): State<Watches>,
     Path(task_id): Path<Uuid>,
     Json(payload): Json<ExecuteTaskRequest>,
 ) -> Result<Json<ExecuteTaskResponse>, ApiError> {
     auth.require_auth().map_err(|_| ApiError::unauthorized())?;
 
+    if let Some(selector) = &payload.relay_selector {
+        LabelSelector::parse(selector).map_err(ApiError::bad_request)?;
+    }
+
     // 1. Get task and project
     let task = db
         .get_task_by_id(task_id)
@@ -336,14 +735,143 @@ pub async fn execute_task(
         }
     }
 
-    // 4. Select relay based on role and project
-    let required_role = Some(AgentRole::Coding.into()); // Default to coding role for task execution
+    // The caller gets the permission requests of this task
+    watch_as_owner(&db, &watches, &auth, WatchTarget::Task(task.id)).await;
+
+    // 4. Queue the execution if the project's execution window is closed or
+    //    a concurrency cap is reached; the scheduler spawns it later
+    let now = Utc::now();
+    let queued = match project.schedule() {
+        Some(schedule) if !schedule.is_open(now) => {
+            let next = schedule
+                .next_open(now)
+                .ok_or_else(|| ApiError::bad_request("project execution windows never open"))?;
+            Some((next, "window"))
+        }
+        _ if relays
+            .at_capacity(Some(AgentRole::Coding.into()), Some(project.id))
+            .await =>
+        {
+            Some((now, "capacity"))
+        }
+        _ => None,
+    };
+
+    if let Some((scheduled_for, reason)) = queued {
+        let scheduled = db
+            .schedule_execution(
+                task_id,
+                payload.relay_id.clone(),
+                payload.relay_selector.clone(),
+                scheduled_for,
+            )
+            .await?;
+
+        let event = BuiltinEvent::TaskScheduled(TaskScheduledData {
+            scheduled_for: scheduled.scheduled_for.to_rfc3339(),
+            relay_id: scheduled.relay_id.clone(),
+            reason: Some(reason.to_string()),
+        });
+        if let Err(e) = publisher.emit_builtin(event, EventScope::task(task_id)).await {
+            tracing::warn!(task_id = %task_id, error = %e, "failed to emit task.scheduled");
+        }
+
+        return Ok(Json(ExecuteTaskResponse {
+            agent: None,
+            session: None,
+            scheduled_for: Some(scheduled.scheduled_for),
+        }));
+    }
+
+    let (agent, session) = start_task_execution(
+        &db,
+        &relays,
+        &publisher,
+        &task,
+        &project,
+        payload.relay_id.as_deref(),
+        payload.relay_selector.as_deref(),
+    )
+    .await?;
+
+    Ok(Json(ExecuteTaskResponse {
+        agent: Some(AgentResponse::from(agent)),
+        session: Some(AgentSessionResponse::from(session)),
+        scheduled_for: None,
+    }))
+}
+
+/// Spawn an agent for `task` on a relay and send it the task prompt
+///
+/// Shared by the execute endpoint and the execution window scheduler.
+pub async fn start_task_execution(
+    db: &DatabaseService,
+    relays: &RelayManager,
+    publisher: &EventPublisher,
+    task: &Task,
+    project: &Project,
+    relay_id: Option<&str>,
+    relay_selector: Option<&str>,
+) -> Result<(Agent, AgentSession), ApiError> {
+    let stage = TaskStage {
+        role: None,
+        relay_id,
+        relay_selector,
+        handoff: None,
+    };
+    start_task_stage(db, relays, publisher, task, project, stage).await
+}
+
+/// How a stage of a task runs
+pub struct TaskStage<'a> {
+    /// Role of the stage's agent; without one a coding relay is picked and
+    /// the agent takes the relay's role
+    pub role: Option<AgentRole>,
+    pub relay_id: Option<&'a str>,
+    pub relay_selector: Option<&'a str>,
+    /// Context handed over by the previous stage, put into the prompt
+    pub handoff: Option<&'a serde_json::Value>,
+}
+
+/// Spawn an agent for a stage of `task` and send it the stage prompt
+pub async fn start_task_stage(
+    db: &DatabaseService,
+    relays: &RelayManager,
+    publisher: &EventPublisher,
+    task: &Task,
+    project: &Project,
+    stage: TaskStage<'_>,
+) -> Result<(Agent, AgentSession), ApiError> {
+    let task_id = task.id;
+    let stage_role = stage.role.unwrap_or(AgentRole::Coding);
+
+    // 1. Select relay based on role and project; an explicit relay or
+    //    selector overrides the project's routing for the role
+    let route = match (stage.relay_id, stage.relay_selector) {
+        (None, None) => project.relay_route(stage_role).unwrap_or_default(),
+        (relay_id, relay_selector) => RelayRoute {
+            relay_id: relay_id.map(str::to_string),
+            selector: relay_selector.map(str::to_string),
+        },
+    };
+    let selector = route
+        .selector
+        .as_deref()
+        .map(LabelSelector::parse)
+        .transpose()
+        .map_err(ApiError::bad_request)?;
+    let required_role = Some(stage_role.into());
     let relay_id = relays
-        .select_relay(payload.relay_id.as_deref(), required_role, Some(project.id))
+        .select_relay_matching(
+            route.relay_id.as_deref(),
+            required_role,
+            Some(project.id),
+            selector.as_ref(),
+        )
         .await
         .ok_or_else(|| ApiError::bad_request("no available relay for this task"))?;
 
-    // 5. Get relay info for workdir
+    // 2. Get relay info for workdir
     let relay_info = relays
         .get_relay(&relay_id)
         .await
@@ -355,16 +883,16 @@ pub async fn execute_task(
         .cloned()
         .unwrap_or_else(|| "~".to_string());
 
-    // 6. Determine agent role from relay
-    let agent_role = match relay_info.role.as_str() {
+    // 3. Determine agent role from the stage, or else from the relay
+    let agent_role = stage.role.unwrap_or(match relay_info.role.as_str() {
         "general" => AgentRole::General,
         "business" => AgentRole::Business,
         "coding" => AgentRole::Coding,
         "qa" => AgentRole::Qa,
         _ => AgentRole::General,
-    };
+    });
 
-    // 7. Create agent
+    // 4. Create agent
     let agent_name = format!("task-{}", &task_id.to_string()[..8]);
     let create_agent = CreateAgent::new(
         agent_name,
@@ -378,40 +906,45 @@ pub async fn execute_task(
 
     let agent = db.create_agent(create_agent).await?;
 
-    // 8. Create session
+    // 5. Create session
     let session = db.create_agent_session(agent.id).await?;
 
-    // 9. Update agent status to running
+    // 6. Update agent status to running
     db.update_agent_status(agent.id, AgentStatus::Running).await?;
 
-    // 10. Register active session with relay manager
+    // 7. Register active session with relay manager
     relays
-        .add_active_session(&relay_id, &session.id.to_string())
+        .add_active_session(&relay_id, &session.id.to_string(), Some(project.id))
         .await;
 
-    // 11. Emit spawn event to relay via Event Bus (fire-and-forget for task execution)
-    let spawn_data = serde_json::json!({
-        "agent_id": agent.id.to_string(),
-        "session_id": session.id.to_string(),
-        "workdir": workdir,
-        "command": agent.command,
-        "args": agent.args_vec(),
-        "env": {},
-        "task_id": task_id.to_string(),
+    // 8. Emit spawn event to relay via Event Bus (fire-and-forget for task execution)
+    let task_status = serde_json::to_value(task.status)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let spawn = BuiltinEvent::RelaySpawnRequested(RelaySpawnRequestedData {
+        relay_id: relay_id.clone(),
+        request_id: Uuid::new_v4().to_string(),
+        target_agent_id: agent.id.to_string(),
+        session_id: session.id.to_string(),
+        workdir,
+        command: agent.command.clone(),
+        args: agent.args_vec(),
+        env: HashMap::new(),
+        task_id: Some(task_id.to_string()),
+        task: Some(AgentTaskContext {
+            task_id: task_id.to_string(),
+            content: task.content.clone(),
+            status: task_status,
+            priority: task.priority,
+            project_id: project.id.to_string(),
+            project_name: project.name.clone(),
+        }),
+        mode: SessionMode::Acp,
     });
 
-    let spawn_request_id = Uuid::new_v4().to_string();
-    if let Err(e) = relays
-        .emit_relay_command(
-            &publisher,
-            &relay_id,
-            EventKind::RELAY_SPAWN_REQUESTED,
-            spawn_request_id,
-            spawn_data,
-            Some(task_id),
-        )
-        .await
-    {
+    let scope = EventScope::execution(session.id, session.correlation_id).with_task(Some(task_id));
+    if let Err(e) = relays.emit_relay_command(publisher, &relay_id, spawn, scope).await {
         // Rollback on failure
         let _ = db.update_agent_status(agent.id, AgentStatus::Failed).await;
         let _ = db
@@ -423,35 +956,37 @@ pub async fn execute_task(
         return Err(ApiError::internal(format!("failed to emit spawn event: {}", e)));
     }
 
-    // 12. Update task with agent_id
+    // 9. Update task with agent_id
     if let Err(e) = db.update_task_agent_id(task_id, Some(agent.id)).await {
         tracing::warn!(task_id = %task_id, agent_id = %agent.id, error = %e, "failed to update task agent_id");
     }
 
-    // 13. Update task status to in-progress if it was todo
+    // 10. Update task status to in-progress if it was todo
     if task.status == TaskStatus::Todo {
         let _ = db.update_task_status(task_id, TaskStatus::InProgress).await;
     }
 
-    // 14. Send task prompt to agent via Event Bus
-    let template = get_template_for_role(&project, agent_role);
-    let prompt = render_task_prompt(template, &task, &project);
-
-    let input_request_id = Uuid::new_v4().to_string();
-    if let Err(e) = relays
-        .emit_relay_command(
-            &publisher,
-            &relay_id,
-            EventKind::RELAY_INPUT_REQUESTED,
-            input_request_id,
-            serde_json::json!({
-                "session_id": session.id.to_string(),
-                "input": prompt,
-            }),
-            Some(task_id),
-        )
-        .await
-    {
+    // 11. Send task prompt to agent via Event Bus
+    //     A shared template referenced by the project wins over the inline one
+    let shared_template = match db.get_project_template_content(project.id, agent_role).await {
+        Ok(content) => content,
+        Err(e) => {
+            tracing::warn!(project_id = %project.id, error = %e, "failed to load shared prompt template");
+            None
+        }
+    };
+    let template = shared_template
+        .as_deref()
+        .unwrap_or_else(|| get_template_for_role(project, agent_role));
+    let prompt = inject_handoff(&render_task_prompt(template, task, project), stage.handoff);
+
+    let input = BuiltinEvent::RelayInputRequested(RelayInputRequestedData {
+        relay_id: relay_id.clone(),
+        request_id: Some(Uuid::new_v4().to_string()),
+        session_id: session.id.to_string(),
+        input: prompt,
+    });
+    if let Err(e) = relays.emit_relay_command(publisher, &relay_id, input, scope).await {
         tracing::warn!(session_id = %session.id, error = %e, "failed to emit initial prompt event");
     }
 
@@ -461,10 +996,7 @@ pub async fn execute_task(
         .await?
         .ok_or_else(|| ApiError::internal("agent not found after creation"))?;
 
-    Ok(Json(ExecuteTaskResponse {
-        agent: AgentResponse::from(agent),
-        session: AgentSessionResponse::from(session),
-    }))
+    Ok((agent, session))
 }
 
 /// GET /api/tasks/:task_id/execution - Get current execution info (session_id, relay_id)
@@ -505,3 +1037,170 @@ pub async fn get_task_execution(
         relay_id,
     }))
 }
+
+/// GET /api/tasks/:task_id/executions - Every session ever run for the task
+///
+/// Combines each session's relay, spawn parameters (secrets redacted),
+/// duration, exit status, artifact count and permission decisions.
+#[gotcha::api]
+pub async fn get_task_executions(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<TaskExecutionHistory>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    db.get_task_by_id(task_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("task not found"))?;
+
+    let history = db.get_task_execution_history(task_id).await?;
+    Ok(Json(history))
+}
+
+// ============================================================================
+// Handoff
+// ============================================================================
+
+/// POST /api/tasks/:task_id/handoff - Hand a task over to an agent of another role
+///
+/// Agents call this with their scoped token for their own task. An agent of
+/// the requested role is started with the context in its prompt, the context
+/// is stored as a `handoff` artifact and the current stage (the caller's
+/// session, or the task agent's running one) is closed as completed. With CI
+/// tracking on, a handoff to QA waits until the task's branches are green.
+#[gotcha::api]
+pub async fn handoff_task(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(relays): State<Relays>,
+    State(publisher): State<Publisher>,
+    State(settings): State<Settings>,
+    Path(task_id): Path<Uuid>,
+    Json(payload): Json<HandoffRequest>,
+) -> Result<Json<HandoffResponse>, ApiError> {
+    auth.require_task(task_id)?;
+    validation::validate(&payload)?;
+    if let Some(selector) = &payload.relay_selector {
+        LabelSelector::parse(selector).map_err(ApiError::bad_request)?;
+    }
+
+    let task = db
+        .get_task_by_id(task_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("task not found"))?;
+    let project = db
+        .get_project(task.project_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("project not found"))?;
+    if project.archived {
+        return Err(ApiError::bad_request("project is archived"));
+    }
+    if payload.role == AgentRole::Qa && settings.ci.enabled && settings.ci.gate_qa {
+        let artifacts = db.list_artifacts_by_task(task_id).await?;
+        if let Some(branch) = ci::unfinished_branch(&artifacts) {
+            let name = branch.data.get("name").and_then(|v| v.as_str()).unwrap_or_default();
+            let state = match ci::branch_ci_status(branch) {
+                ci::CiStatus::Failed => "failed",
+                _ => "still running",
+            };
+            return Err(ApiError::conflict(format!(
+                "CI of branch {} {}; QA starts once it passes",
+                name, state
+            )));
+        }
+    }
+
+    // 1. Find the current stage before the next one takes over the task
+    let current = match (auth.agent_scope(), task.agent_id) {
+        (Some(scope), _) => db.get_agent_session(scope.session_id).await?,
+        (None, Some(agent_id)) => db.get_agent_running_session(agent_id).await?,
+        (None, None) => None,
+    };
+    let from_agent = match &current {
+        Some(session) => db.get_agent(session.agent_id).await?,
+        None => None,
+    };
+    let from_role = from_agent.as_ref().map(|agent| agent.role);
+
+    // 2. Start the next stage
+    let stage = TaskStage {
+        role: Some(payload.role),
+        relay_id: payload.relay_id.as_deref(),
+        relay_selector: payload.relay_selector.as_deref(),
+        handoff: Some(&payload.context),
+    };
+    let (agent, session) =
+        start_task_stage(&db, &relays, &publisher, &task, &project, stage).await?;
+
+    // 3. Keep the context with the task
+    let artifact = db
+        .create_artifact(
+            task.id,
+            task.project_id,
+            current.as_ref().map(|s| s.agent_id),
+            current.as_ref().map(|s| s.id),
+            HANDOFF_ARTIFACT_TYPE,
+            handoff_artifact_data(from_role, payload.role, &payload.context),
+        )
+        .await?;
+
+    // 4. Close the current stage
+    if let Some(current) = &current
+        && current.status == SessionStatus::Running
+    {
+        close_stage(&db, &relays, &publisher, current).await;
+    }
+
+    let event = BuiltinEvent::TaskHandoff(TaskHandoffData {
+        from_role: from_role.map(|role| role.as_str().to_string()),
+        to_role: payload.role.as_str().to_string(),
+        from_session_id: current.as_ref().map(|s| s.id.to_string()),
+        to_session_id: session.id.to_string(),
+        artifact_id: artifact.id.to_string(),
+    });
+    if let Err(e) = publisher.emit_builtin(event, EventScope::task(task_id)).await {
+        tracing::warn!(task_id = %task_id, error = %e, "failed to emit task.handoff");
+    }
+
+    Ok(Json(HandoffResponse {
+        artifact: ArtifactResponse::from(artifact),
+        agent: AgentResponse::from(agent),
+        session: AgentSessionResponse::from(session),
+    }))
+}
+
+/// Stop the session of a finished stage and mark it completed
+async fn close_stage(
+    db: &DatabaseService,
+    relays: &RelayManager,
+    publisher: &EventPublisher,
+    session: &AgentSession,
+) {
+    let session_id = session.id.to_string();
+    if let Some(relay_id) = relays.get_relay_for_session(&session_id).await {
+        let stop = BuiltinEvent::RelayStopRequested(RelayStopRequestedData {
+            relay_id: relay_id.clone(),
+            request_id: Some(Uuid::new_v4().to_string()),
+            session_id: session_id.clone(),
+        });
+        let scope = EventScope::execution(session.id, session.correlation_id);
+        if let Err(e) = relays.emit_relay_command(publisher, &relay_id, stop, scope).await {
+            tracing::warn!(session_id = %session.id, error = %e, "failed to stop handed-off session");
+        }
+        relays.remove_active_session(&relay_id, &session_id).await;
+    }
+
+    if let Err(e) = db
+        .update_session_status(session.id, SessionStatus::Completed)
+        .await
+    {
+        tracing::warn!(session_id = %session.id, error = %e, "failed to complete handed-off session");
+    }
+    if let Err(e) = db
+        .update_agent_status(session.agent_id, AgentStatus::Exited)
+        .await
+    {
+        tracing::warn!(agent_id = %session.agent_id, error = %e, "failed to update handed-off agent");
+    }
+}
@@ -0,0 +1,186 @@
+use gotcha::axum::extract::{Path, Query, State};
+use gotcha::axum::Extension;
+use gotcha::{Json, Schematic};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
+use crate::auth::AuthContext;
+use crate::models::{
+    AgentRole, ProjectTemplateAssignRequest, ProjectTemplateRef, PromptTemplateCreateRequest,
+    PromptTemplateResponse, PromptTemplateRollbackRequest, PromptTemplateUpdateRequest,
+    PromptTemplateVersionResponse,
+};
+use crate::Db;
+
+#[derive(Debug, Deserialize, Schematic)]
+pub struct ListTemplatesQuery {
+    pub role: Option<AgentRole>,
+}
+
+#[derive(Debug, Serialize, Schematic)]
+pub struct EmptyResponse {}
+
+/// GET /api/templates - List prompt templates
+#[gotcha::api]
+pub async fn list_templates(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Query(query): Query<ListTemplatesQuery>,
+) -> Result<Json<Vec<PromptTemplateResponse>>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let templates = db.list_prompt_templates(query.role).await?;
+    Ok(Json(templates.into_iter().map(Into::into).collect()))
+}
+
+/// POST /api/templates - Create a prompt template (version 1)
+#[gotcha::api]
+pub async fn create_template(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Json(payload): Json<PromptTemplateCreateRequest>,
+) -> Result<Json<PromptTemplateResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    if payload.name.trim().is_empty() {
+        return Err(ApiError::bad_request("template name must not be empty"));
+    }
+
+    let template = db
+        .create_prompt_template(
+            payload.name,
+            payload.description,
+            payload.role,
+            payload.content,
+        )
+        .await?;
+    Ok(Json(template.into()))
+}
+
+/// GET /api/templates/:template_id - Get a template with its latest version
+#[gotcha::api]
+pub async fn get_template(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(template_id): Path<Uuid>,
+) -> Result<Json<PromptTemplateResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let template = db
+        .get_prompt_template(template_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("template not found"))?;
+    Ok(Json(template.into()))
+}
+
+/// PUT /api/templates/:template_id - Update a template; new content creates a version
+#[gotcha::api]
+pub async fn update_template(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(template_id): Path<Uuid>,
+    Json(payload): Json<PromptTemplateUpdateRequest>,
+) -> Result<Json<PromptTemplateResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let template = db
+        .update_prompt_template(
+            template_id,
+            payload.name,
+            payload.description,
+            payload.content,
+        )
+        .await?
+        .ok_or_else(|| ApiError::not_found("template not found"))?;
+    Ok(Json(template.into()))
+}
+
+/// DELETE /api/templates/:template_id - Delete a template and its versions
+#[gotcha::api]
+pub async fn delete_template(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(template_id): Path<Uuid>,
+) -> Result<Json<EmptyResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    db.delete_prompt_template(template_id).await?;
+    Ok(Json(EmptyResponse {}))
+}
+
+/// GET /api/templates/:template_id/versions - List template versions, newest first
+#[gotcha::api]
+pub async fn list_template_versions(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(template_id): Path<Uuid>,
+) -> Result<Json<Vec<PromptTemplateVersionResponse>>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let versions = db.list_prompt_template_versions(template_id).await?;
+    if versions.is_empty() {
+        return Err(ApiError::not_found("template not found"));
+    }
+    Ok(Json(versions.into_iter().map(Into::into).collect()))
+}
+
+/// POST /api/templates/:template_id/rollback - Restore an earlier version as the latest
+#[gotcha::api]
+pub async fn rollback_template(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(template_id): Path<Uuid>,
+    Json(payload): Json<PromptTemplateRollbackRequest>,
+) -> Result<Json<PromptTemplateResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let template = db
+        .rollback_prompt_template(template_id, payload.version)
+        .await?
+        .ok_or_else(|| {
+            ApiError::not_found(format!(
+                "template {} has no version {}",
+                template_id, payload.version
+            ))
+        })?;
+    Ok(Json(template.into()))
+}
+
+/// GET /api/projects/:project_id/templates - List shared templates used by a project
+#[gotcha::api]
+pub async fn list_project_templates(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(project_id): Path<Uuid>,
+) -> Result<Json<Vec<ProjectTemplateRef>>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let refs = db.list_project_templates(project_id).await?;
+    Ok(Json(refs))
+}
+
+/// PUT /api/projects/:project_id/templates/:role - Set or clear a project's role template
+#[gotcha::api]
+pub async fn set_project_template(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path((project_id, role)): Path<(Uuid, AgentRole)>,
+    Json(payload): Json<ProjectTemplateAssignRequest>,
+) -> Result<Json<Vec<ProjectTemplateRef>>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    db.get_project(project_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("project not found"))?;
+    if let Some(template_id) = payload.template_id {
+        db.get_prompt_template(template_id)
+            .await?
+            .ok_or_else(|| ApiError::not_found("template not found"))?;
+    }
+
+    db.set_project_template(project_id, role, payload.template_id)
+        .await?;
+    let refs = db.list_project_templates(project_id).await?;
+    Ok(Json(refs))
+}
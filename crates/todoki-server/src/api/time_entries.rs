@@ -0,0 +1,166 @@
+use chrono::{Duration, Utc};
+use gotcha::axum::extract::{Path, Query, State};
+use gotcha::axum::Extension;
+use gotcha::Json;
+use uuid::Uuid;
+
+use crate::api::error::{ApiError, FieldError};
+use crate::api::validation;
+use crate::auth::AuthContext;
+use crate::models::{
+    check_span, LogTimeRequest, ProjectTimeReport, StartTimerRequest, Task, TaskTimeResponse,
+    TimeEntryResponse, TimeReportQuery,
+};
+use crate::Db;
+
+/// Default window of a time report
+const DEFAULT_REPORT_DAYS: i64 = 30;
+
+/// The person logging time, e.g. `user` or `oidc:<subject>`
+fn user_id(auth: &AuthContext) -> Result<String, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    auth.token_id()
+        .map(str::to_string)
+        .ok_or_else(ApiError::unauthorized)
+}
+
+async fn require_task(db: &Db, task_id: Uuid) -> Result<Task, ApiError> {
+    db.get_task_by_id(task_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("task not found"))
+}
+
+/// GET /api/tasks/:task_id/time-entries - Human and agent time on a task
+///
+/// Agent time is recorded automatically when a session of the task ends.
+#[gotcha::api]
+pub async fn list_time_entries(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<TaskTimeResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    let task = require_task(&db, task_id).await?;
+
+    let human = db.list_time_entries(task_id).await?;
+    let agent = db.list_task_session_times(task_id).await?;
+    Ok(Json(TaskTimeResponse::new(
+        human,
+        agent,
+        task.estimate_minutes,
+    )))
+}
+
+/// POST /api/tasks/:task_id/time-entries - Log a finished span of work
+#[gotcha::api]
+pub async fn log_time(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(task_id): Path<Uuid>,
+    Json(payload): Json<LogTimeRequest>,
+) -> Result<Json<TimeEntryResponse>, ApiError> {
+    let user_id = user_id(&auth)?;
+    validation::validate(&payload)?;
+    check_span(payload.started_at, payload.ended_at, Utc::now())
+        .map_err(|e| ApiError::validation(vec![FieldError::new("ended_at", e)]))?;
+    require_task(&db, task_id).await?;
+
+    let entry = db
+        .create_time_entry(
+            task_id,
+            &user_id,
+            payload.started_at,
+            payload.ended_at,
+            payload.note.trim(),
+        )
+        .await?;
+    Ok(Json(entry.into()))
+}
+
+/// POST /api/tasks/:task_id/time-entries/start - Start a timer on a task
+///
+/// A timer the caller runs on another task is stopped first.
+#[gotcha::api]
+pub async fn start_timer(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(task_id): Path<Uuid>,
+    Json(payload): Json<StartTimerRequest>,
+) -> Result<Json<TimeEntryResponse>, ApiError> {
+    let user_id = user_id(&auth)?;
+    validation::validate(&payload)?;
+    require_task(&db, task_id).await?;
+
+    let entry = db
+        .start_timer(task_id, &user_id, payload.note.trim())
+        .await?;
+    Ok(Json(entry.into()))
+}
+
+/// POST /api/tasks/:task_id/time-entries/stop - Stop the caller's timer
+#[gotcha::api]
+pub async fn stop_timer(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<TimeEntryResponse>, ApiError> {
+    let user_id = user_id(&auth)?;
+
+    let entry = db
+        .stop_timer(task_id, &user_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("no timer is running on this task"))?;
+    Ok(Json(entry.into()))
+}
+
+/// DELETE /api/tasks/:task_id/time-entries/:entry_id - Remove logged time
+///
+/// Agent time cannot be removed; it belongs to the session.
+#[gotcha::api]
+pub async fn delete_time_entry(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path((task_id, entry_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<()>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    if !db.delete_time_entry(task_id, entry_id).await? {
+        return Err(ApiError::not_found(format!(
+            "time entry {} not found",
+            entry_id
+        )));
+    }
+    Ok(Json(()))
+}
+
+/// GET /api/projects/:project_id/time-report - Human vs agent time per task
+///
+/// Counts finished entries and sessions started in `[since, until)`,
+/// by default the last 30 days.
+#[gotcha::api]
+pub async fn project_time_report(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(project_id): Path<Uuid>,
+    Query(query): Query<TimeReportQuery>,
+) -> Result<Json<ProjectTimeReport>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    db.get_project(project_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Project {} not found", project_id)))?;
+
+    let until = query.until.unwrap_or_else(Utc::now);
+    let since = query
+        .since
+        .unwrap_or(until - Duration::days(DEFAULT_REPORT_DAYS));
+    if since >= until {
+        return Err(ApiError::bad_request("since must be before until"));
+    }
+
+    let samples = db
+        .list_project_time_samples(project_id, since, until)
+        .await?;
+    Ok(Json(ProjectTimeReport::from_samples(
+        project_id, since, until, samples,
+    )))
+}
@@ -0,0 +1,200 @@
+//! Issue webhooks from Jira and Linear
+//!
+//! Both report issue changes for the issue sync; changes to issues no task
+//! is synced with are accepted and ignored.
+
+use axum::body::Bytes;
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::api::error::ApiError;
+use crate::api::forges::signature_valid;
+use crate::config::Settings;
+use crate::issue_sync::IssueSyncService;
+use crate::models::{IssueProvider, StatusCategory};
+use crate::Db;
+
+#[derive(Debug, Deserialize)]
+pub struct JiraWebhookQuery {
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrackerWebhookResponse {
+    /// Whether a synced task was checked against the change
+    pub synced: bool,
+}
+
+// Jira `jira:issue_*` payload, only the fields used
+#[derive(Debug, Deserialize)]
+struct JiraEvent {
+    #[serde(default)]
+    issue: Option<JiraIssue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JiraIssue {
+    id: String,
+    fields: JiraFields,
+}
+
+#[derive(Debug, Deserialize)]
+struct JiraFields {
+    status: JiraStatus,
+}
+
+#[derive(Debug, Deserialize)]
+struct JiraStatus {
+    #[serde(rename = "statusCategory")]
+    status_category: JiraStatusCategory,
+}
+
+#[derive(Debug, Deserialize)]
+struct JiraStatusCategory {
+    key: String,
+}
+
+// Linear `Issue` payload, only the fields used
+#[derive(Debug, Deserialize)]
+struct LinearEvent {
+    #[serde(rename = "type")]
+    entity: String,
+    data: LinearIssue,
+}
+
+#[derive(Debug, Deserialize)]
+struct LinearIssue {
+    id: String,
+    #[serde(default)]
+    state: Option<LinearState>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LinearState {
+    #[serde(rename = "type")]
+    state_type: String,
+}
+
+/// Issue id and category in a Jira webhook; `None` for other events
+fn jira_change(event: JiraEvent) -> Option<(String, StatusCategory)> {
+    let issue = event.issue?;
+    let category = StatusCategory::from_jira(&issue.fields.status.status_category.key)?;
+    Some((issue.id, category))
+}
+
+/// Issue id and category in a Linear webhook; `None` for other entities
+fn linear_change(event: LinearEvent) -> Option<(String, StatusCategory)> {
+    if event.entity != "Issue" {
+        return None;
+    }
+    let category = StatusCategory::from_linear(&event.data.state?.state_type)?;
+    Some((event.data.id, category))
+}
+
+/// POST /api/integrations/jira - Jira issue webhook for the issue sync
+///
+/// `?secret=` must match `issue_sync.jira_webhook_secret`.
+pub async fn receive_jira_webhook(
+    State(db): State<Db>,
+    State(settings): State<Settings>,
+    Query(query): Query<JiraWebhookQuery>,
+    body: Bytes,
+) -> Result<Json<TrackerWebhookResponse>, ApiError> {
+    let secret = &settings.issue_sync.jira_webhook_secret;
+    if secret.is_empty() || query.secret.as_deref() != Some(secret.as_str()) {
+        return Err(ApiError::unauthorized());
+    }
+
+    let event: JiraEvent = serde_json::from_slice(&body)
+        .map_err(|e| ApiError::bad_request(format!("invalid Jira payload: {}", e)))?;
+    apply(&db, &settings, IssueProvider::Jira, jira_change(event)).await
+}
+
+/// POST /api/integrations/linear - Linear webhook for the issue sync
+///
+/// The payload must be signed with `issue_sync.linear_webhook_secret`.
+pub async fn receive_linear_webhook(
+    State(db): State<Db>,
+    State(settings): State<Settings>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<TrackerWebhookResponse>, ApiError> {
+    let secret = &settings.issue_sync.linear_webhook_secret;
+    let signature = headers
+        .get("Linear-Signature")
+        .and_then(|v| v.to_str().ok());
+    if secret.is_empty() || !signature.is_some_and(|sig| signature_valid(secret, &body, sig)) {
+        return Err(ApiError::unauthorized());
+    }
+
+    let event: LinearEvent = serde_json::from_slice(&body)
+        .map_err(|e| ApiError::bad_request(format!("invalid Linear payload: {}", e)))?;
+    apply(&db, &settings, IssueProvider::Linear, linear_change(event)).await
+}
+
+async fn apply(
+    db: &Db,
+    settings: &Settings,
+    provider: IssueProvider,
+    change: Option<(String, StatusCategory)>,
+) -> Result<Json<TrackerWebhookResponse>, ApiError> {
+    let Some((remote_id, category)) = change else {
+        return Ok(Json(TrackerWebhookResponse { synced: false }));
+    };
+    if !settings.issue_sync.enabled {
+        return Ok(Json(TrackerWebhookResponse { synced: false }));
+    }
+
+    let service = IssueSyncService::new(settings.issue_sync.clone(), db.0.clone());
+    let synced = service
+        .remote_changed(provider, &remote_id, category)
+        .await
+        .map_err(|e| ApiError::internal(format!("issue sync failed: {}", e)))?;
+    Ok(Json(TrackerWebhookResponse { synced }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_jira_change() {
+        let event: JiraEvent = serde_json::from_value(json!({
+            "webhookEvent": "jira:issue_updated",
+            "issue": {
+                "id": "10042",
+                "key": "OPS-7",
+                "fields": {"status": {"name": "Shipped", "statusCategory": {"key": "done"}}}
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            jira_change(event),
+            Some(("10042".to_string(), StatusCategory::Done))
+        );
+
+        let comment: JiraEvent =
+            serde_json::from_value(json!({"webhookEvent": "comment_created"})).unwrap();
+        assert_eq!(jira_change(comment), None);
+    }
+
+    #[test]
+    fn test_linear_change() {
+        let event = |entity: &str| -> LinearEvent {
+            serde_json::from_value(json!({
+                "action": "update",
+                "type": entity,
+                "data": {"id": "b1c2", "state": {"name": "In Review", "type": "started"}}
+            }))
+            .unwrap()
+        };
+        assert_eq!(
+            linear_change(event("Issue")),
+            Some(("b1c2".to_string(), StatusCategory::InProgress))
+        );
+        assert_eq!(linear_change(event("Comment")), None);
+    }
+}
@@ -0,0 +1,182 @@
+//! Undo of recent destructive mutations
+//!
+//! Task status changes, archiving and deletion and project archiving are
+//! logged per API token. `POST /api/undo` reverts the token's latest ones
+//! within `undo.window_secs`, newest first. A mutation that something else
+//! has built on since (the task moved on, was restored by hand, ...) is
+//! reported rather than forced back.
+
+use chrono::{Duration, Utc};
+use gotcha::axum::extract::State;
+use gotcha::axum::Extension;
+use gotcha::Json;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
+use crate::api::projects::restore_cascade;
+use crate::auth::AuthContext;
+use crate::config::Settings;
+use crate::db::DatabaseService;
+use crate::event_bus::EventPublisher;
+use crate::models::task::TaskEventType;
+use crate::models::{UndoAction, UndoEntry, UndoRequest, UndoResponse, UndoResult};
+use crate::{Db, Publisher};
+
+/// Log a mutation for undo; failing to log never fails the mutation itself
+pub(crate) async fn record(
+    db: &DatabaseService,
+    auth: &AuthContext,
+    action: UndoAction,
+    target_id: Uuid,
+    snapshot: Option<Value>,
+) {
+    let Some(token_id) = auth.token_id() else {
+        return;
+    };
+    if let Err(e) = db.record_undo(token_id, action, target_id, snapshot).await {
+        tracing::warn!(
+            target_id = %target_id,
+            action = ?action,
+            error = %e,
+            "failed to record undo entry"
+        );
+    }
+}
+
+/// POST /api/undo - Revert the caller's most recent mutations
+#[gotcha::api]
+pub async fn undo(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(publisher): State<Publisher>,
+    State(settings): State<Settings>,
+    Json(payload): Json<UndoRequest>,
+) -> Result<Json<UndoResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    let token_id = auth.token_id().ok_or_else(ApiError::unauthorized)?;
+
+    let count = payload.count.unwrap_or(1);
+    if count == 0 || count > settings.undo.max_count {
+        return Err(ApiError::bad_request(format!(
+            "count must be between 1 and {}",
+            settings.undo.max_count
+        )));
+    }
+
+    let since = Utc::now() - Duration::seconds(settings.undo.window_secs as i64);
+    let entries = db
+        .pending_undo_entries(token_id, since, count as i64)
+        .await?;
+
+    let mut results = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let error = match revert(&db, &publisher, &entry).await {
+            Ok(()) => {
+                db.mark_undone(entry.id).await?;
+                None
+            }
+            Err(e) => Some(e),
+        };
+        results.push(UndoResult {
+            id: entry.id,
+            action: entry.action,
+            target_id: entry.target_id,
+            created_at: entry.created_at,
+            undone: error.is_none(),
+            error,
+        });
+    }
+
+    Ok(Json(UndoResponse { results }))
+}
+
+async fn revert(
+    db: &DatabaseService,
+    publisher: &EventPublisher,
+    entry: &UndoEntry,
+) -> Result<(), String> {
+    let target_id = entry.target_id;
+    match entry.action {
+        UndoAction::TaskStatus => {
+            let task = db
+                .get_task_by_id(target_id)
+                .await
+                .map_err(|e| e.to_string())?
+                .ok_or("task no longer exists")?;
+            // Events are newest first
+            let change = db
+                .get_task_events(target_id)
+                .await
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .find(|event| event.event_type == TaskEventType::StatusChange)
+                .ok_or("task has no recorded status change")?;
+            let (Some(from), Some(to)) = (change.from_state, change.state) else {
+                return Err("status change has no recorded states".to_string());
+            };
+            if task.status != to {
+                return Err(format!(
+                    "task status has changed to {:?} since",
+                    task.status
+                ));
+            }
+            let workflow_state = entry
+                .snapshot
+                .as_ref()
+                .and_then(|s| s.get("workflow_state"))
+                .and_then(|v| v.as_str())
+                .map(str::to_string);
+            db.update_task_status_with_events(target_id, from, workflow_state, |_, _| vec![])
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        UndoAction::TaskArchive | UndoAction::TaskUnarchive => {
+            let archive = entry.action == UndoAction::TaskUnarchive;
+            let task = db
+                .get_task_by_id(target_id)
+                .await
+                .map_err(|e| e.to_string())?
+                .ok_or("task no longer exists")?;
+            if task.archived == archive {
+                return Err("task archive state has changed since".to_string());
+            }
+            let result = if archive {
+                db.archive_task(target_id).await
+            } else {
+                db.unarchive_task(target_id).await
+            };
+            result.map_err(|e| e.to_string())?;
+        }
+        UndoAction::TaskDelete => {
+            let snapshot = entry
+                .snapshot
+                .as_ref()
+                .ok_or("no snapshot of the deleted task")?;
+            let existing = db
+                .get_task_by_id(target_id)
+                .await
+                .map_err(|e| e.to_string())?;
+            if existing.is_some() {
+                return Err("task already exists".to_string());
+            }
+            db.restore_task_snapshot(snapshot)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        UndoAction::ProjectArchive => {
+            let project = db
+                .get_project(target_id)
+                .await
+                .map_err(|e| e.to_string())?
+                .ok_or("project no longer exists")?;
+            if !project.archived {
+                return Err("project has been restored since".to_string());
+            }
+            restore_cascade(db, publisher, target_id)
+                .await
+                .map_err(|e| e.message)?;
+        }
+    }
+    Ok(())
+}
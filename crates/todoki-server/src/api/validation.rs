@@ -0,0 +1,228 @@
+//! Validation of create and update payloads
+//!
+//! Request types derive [`Validate`] with the limits below. Handlers call
+//! [`validate`] before touching anything; failures become a 422 problem
+//! listing every offending field, e.g. `name` or `safe_paths`.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use validator::{Validate, ValidationError, ValidationErrors, ValidationErrorsKind};
+
+use super::error::{ApiError, FieldError};
+
+/// Names of projects, agents and relays (characters)
+pub const MAX_NAME_LEN: u64 = 200;
+/// Descriptions (characters)
+pub const MAX_DESCRIPTION_LEN: u64 = 5_000;
+/// Task content and comments (characters)
+pub const MAX_CONTENT_LEN: u64 = 50_000;
+/// Inline execution templates and setup scripts (characters)
+pub const MAX_TEMPLATE_LEN: u64 = 64 * 1024;
+/// Workdirs, commands and other paths (characters)
+pub const MAX_PATH_LEN: u64 = 4_096;
+/// Arguments of an agent command
+pub const MAX_ARGS: u64 = 256;
+/// Event subscriptions of an agent
+pub const MAX_SUBSCRIPTIONS: u64 = 100;
+
+/// Check `payload`, turning failures into a 422 with per-field errors
+pub fn validate<T: Validate>(payload: &T) -> Result<(), ApiError> {
+    payload
+        .validate()
+        .map_err(|errors| ApiError::validation(field_errors(&errors)))
+}
+
+/// Flatten nested validation errors into one entry per problem, by field
+pub fn field_errors(errors: &ValidationErrors) -> Vec<FieldError> {
+    let mut out = Vec::new();
+    collect("", errors, &mut out);
+    out.sort_by(|a, b| a.field.cmp(&b.field));
+    out
+}
+
+fn collect(prefix: &str, errors: &ValidationErrors, out: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", prefix, field)
+        };
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                out.extend(errors.iter().map(|e| FieldError::new(&path, message(e))));
+            }
+            ValidationErrorsKind::Struct(inner) => collect(&path, inner, out),
+            ValidationErrorsKind::List(items) => {
+                for (index, inner) in items {
+                    collect(&format!("{}[{}]", path, index), inner, out);
+                }
+            }
+        }
+    }
+}
+
+fn message(error: &ValidationError) -> String {
+    if let Some(message) = &error.message {
+        return message.to_string();
+    }
+    let param = |name: &str| error.params.get(name).map(|v| v.to_string());
+    match (error.code.as_ref(), param("min"), param("max")) {
+        ("length", Some(min), Some(max)) => format!("length must be between {} and {}", min, max),
+        ("length", Some(min), None) => format!("length must be at least {}", min),
+        ("length", None, Some(max)) => format!("length must be at most {}", max),
+        ("range", Some(min), Some(max)) => format!("must be between {} and {}", min, max),
+        ("range", Some(min), None) => format!("must be at least {}", min),
+        ("range", None, Some(max)) => format!("must be at most {}", max),
+        (code, _, _) => format!("is invalid ({})", code),
+    }
+}
+
+fn invalid(code: &'static str, message: impl Into<Cow<'static, str>>) -> ValidationError {
+    ValidationError::new(code).with_message(message.into())
+}
+
+/// `#RGB` or `#RRGGBB`
+pub fn hex_color(value: &str) -> Result<(), ValidationError> {
+    let valid = value.strip_prefix('#').is_some_and(|hex| {
+        matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit())
+    });
+    if valid {
+        Ok(())
+    } else {
+        Err(invalid("hex_color", "must be a hex color like #3B82F6"))
+    }
+}
+
+/// Not empty once surrounding whitespace is removed
+pub fn not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        Err(invalid("blank", "must not be blank"))
+    } else {
+        Ok(())
+    }
+}
+
+/// A JSON object, e.g. a handoff context
+pub fn json_object(value: &serde_json::Value) -> Result<(), ValidationError> {
+    if value.is_object() {
+        Ok(())
+    } else {
+        Err(invalid("object", "must be a JSON object"))
+    }
+}
+
+/// A UUID in its hyphenated or simple form
+pub fn uuid(value: &str) -> Result<(), ValidationError> {
+    uuid::Uuid::parse_str(value)
+        .map(|_| ())
+        .map_err(|_| invalid("uuid", "must be a UUID"))
+}
+
+/// Command-line arguments: bounded in size and free of NUL bytes
+pub fn command_args(args: &[String]) -> Result<(), ValidationError> {
+    if args.iter().any(|arg| arg.contains('\0')) {
+        return Err(invalid("nul_byte", "arguments must not contain NUL bytes"));
+    }
+    if args.iter().any(|arg| arg.chars().count() as u64 > MAX_PATH_LEN) {
+        return Err(invalid(
+            "length",
+            format!("each argument must be at most {} characters", MAX_PATH_LEN),
+        ));
+    }
+    Ok(())
+}
+
+/// UUIDs, e.g. project IDs a relay is restricted to
+pub fn uuids(values: &[String]) -> Result<(), ValidationError> {
+    match values.iter().find(|v| uuid::Uuid::parse_str(v).is_err()) {
+        Some(value) => Err(invalid("uuid", format!("{} is not a UUID", value))),
+        None => Ok(()),
+    }
+}
+
+/// Non-empty paths no longer than [`MAX_PATH_LEN`]
+pub fn paths(values: &[String]) -> Result<(), ValidationError> {
+    if values.iter().any(|v| v.trim().is_empty()) {
+        return Err(invalid("blank", "paths must not be blank"));
+    }
+    if values.iter().any(|v| v.chars().count() as u64 > MAX_PATH_LEN) {
+        return Err(invalid(
+            "length",
+            format!("each path must be at most {} characters", MAX_PATH_LEN),
+        ));
+    }
+    Ok(())
+}
+
+/// Label keys usable in a relay selector: no whitespace or `!=(),`
+pub fn label_keys(labels: &HashMap<String, String>) -> Result<(), ValidationError> {
+    let bad = labels.keys().find(|key| {
+        key.is_empty()
+            || key.chars().count() as u64 > MAX_NAME_LEN
+            || key.contains(|c: char| c.is_whitespace() || "!=(),".contains(c))
+    });
+    match bad {
+        Some(key) => Err(invalid("label_key", format!("invalid label key '{}'", key))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Validate)]
+    struct Sample {
+        #[validate(length(min = 1, max = 5))]
+        name: String,
+        #[validate(custom(function = "hex_color"))]
+        color: Option<String>,
+    }
+
+    #[test]
+    fn test_field_errors() {
+        let sample = Sample {
+            name: "too long".to_string(),
+            color: Some("red".to_string()),
+        };
+        let errors = field_errors(&sample.validate().unwrap_err());
+        assert_eq!(
+            errors,
+            vec![
+                FieldError::new("color", "must be a hex color like #3B82F6"),
+                FieldError::new("name", "length must be between 1 and 5"),
+            ]
+        );
+
+        let sample = Sample {
+            name: "ok".to_string(),
+            color: None,
+        };
+        assert!(sample.validate().is_ok());
+    }
+
+    #[test]
+    fn test_rules() {
+        assert!(hex_color("#3B82F6").is_ok());
+        assert!(hex_color("#fff").is_ok());
+        assert!(hex_color("3B82F6").is_err());
+        assert!(hex_color("#3B82F").is_err());
+
+        assert!(uuid("3f6c1f0e-8f4b-4a53-9d2e-1b7f2a7c9e10").is_ok());
+        assert!(uuid("project-1").is_err());
+
+        assert!(not_blank("  ").is_err());
+        assert!(command_args(&["--model".to_string(), "opus".to_string()]).is_ok());
+        assert!(command_args(&["a\0b".to_string()]).is_err());
+
+        assert!(uuids(&["3f6c1f0e-8f4b-4a53-9d2e-1b7f2a7c9e10".to_string()]).is_ok());
+        assert!(uuids(&["project-1".to_string()]).is_err());
+        assert!(paths(&["/srv/repos".to_string()]).is_ok());
+        assert!(paths(&[" ".to_string()]).is_err());
+        let labels = HashMap::from([("gpu".to_string(), "true".to_string())]);
+        assert!(label_keys(&labels).is_ok());
+        let labels = HashMap::from([("gpu type".to_string(), "a100".to_string())]);
+        assert!(label_keys(&labels).is_err());
+    }
+}
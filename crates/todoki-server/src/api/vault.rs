@@ -0,0 +1,45 @@
+use gotcha::axum::Extension;
+use gotcha::axum::extract::State;
+use gotcha::{Json, Schematic};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
+use crate::auth::AuthContext;
+use crate::config::Settings;
+use crate::vault::{VaultExportSummary, VaultExporter};
+use crate::Db;
+
+#[derive(Debug, Default, Deserialize, Schematic)]
+pub struct VaultExportRequest {
+    /// Export only this project (default: every project)
+    #[serde(default)]
+    pub project_id: Option<Uuid>,
+}
+
+/// POST /api/export/vault - Write the Markdown vault now
+///
+/// Writes into `vault.dir` on the server, the same folder as scheduled
+/// exports.
+#[gotcha::api]
+pub async fn export_vault(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(settings): State<Settings>,
+    Json(payload): Json<VaultExportRequest>,
+) -> Result<Json<VaultExportSummary>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    if let Some(project_id) = payload.project_id {
+        db.get_project(project_id)
+            .await?
+            .ok_or_else(|| ApiError::not_found(format!("Project {} not found", project_id)))?;
+    }
+
+    let exporter = VaultExporter::new(settings.vault.clone(), db.0.clone());
+    let summary = exporter
+        .export(payload.project_id)
+        .await
+        .map_err(|e| ApiError::internal(format!("vault export failed: {}", e)))?;
+    Ok(Json(summary))
+}
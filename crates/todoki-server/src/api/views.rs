@@ -0,0 +1,115 @@
+use gotcha::axum::extract::{Path, State};
+use gotcha::axum::Extension;
+use gotcha::Json;
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
+use crate::api::tasks::tasks_to_responses;
+use crate::api::templates::EmptyResponse;
+use crate::api::validation;
+use crate::auth::AuthContext;
+use crate::models::{
+    CreateSavedView, SavedViewCreateRequest, SavedViewResponse, SavedViewUpdateRequest,
+    TaskResponse,
+};
+use crate::Db;
+
+/// GET /api/views - List saved views
+#[gotcha::api]
+pub async fn list_views(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+) -> Result<Json<Vec<SavedViewResponse>>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let views = db.list_views().await?;
+    Ok(Json(views.into_iter().map(Into::into).collect()))
+}
+
+/// POST /api/views - Save a named task filter
+#[gotcha::api]
+pub async fn create_view(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Json(payload): Json<SavedViewCreateRequest>,
+) -> Result<Json<SavedViewResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    validation::validate(&payload)?;
+    payload.filter.validate().map_err(ApiError::bad_request)?;
+
+    let create = CreateSavedView::new(payload.name, payload.description, &payload.filter);
+    let view = db.create_view(create).await?;
+    Ok(Json(view.into()))
+}
+
+/// GET /api/views/:view_id - Get a saved view
+#[gotcha::api]
+pub async fn get_view(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(view_id): Path<Uuid>,
+) -> Result<Json<SavedViewResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    let view = db
+        .get_view(view_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("View {} not found", view_id)))?;
+    Ok(Json(view.into()))
+}
+
+/// PUT /api/views/:view_id - Update a saved view
+#[gotcha::api]
+pub async fn update_view(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(view_id): Path<Uuid>,
+    Json(payload): Json<SavedViewUpdateRequest>,
+) -> Result<Json<SavedViewResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    validation::validate(&payload)?;
+    if let Some(filter) = &payload.filter {
+        filter.validate().map_err(ApiError::bad_request)?;
+    }
+
+    let view = db
+        .update_view(view_id, payload.name, payload.description, payload.filter)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("View {} not found", view_id)))?;
+    Ok(Json(view.into()))
+}
+
+/// DELETE /api/views/:view_id - Delete a saved view
+#[gotcha::api]
+pub async fn delete_view(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(view_id): Path<Uuid>,
+) -> Result<Json<EmptyResponse>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    db.delete_view(view_id).await?;
+    Ok(Json(EmptyResponse {}))
+}
+
+/// GET /api/views/:view_id/tasks - Tasks matching a saved view
+#[gotcha::api]
+pub async fn get_view_tasks(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    Path(view_id): Path<Uuid>,
+) -> Result<Json<Vec<TaskResponse>>, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+
+    Ok(Json(view_tasks(&db, view_id).await?))
+}
+
+/// Run the filter of view `view_id`
+pub(crate) async fn view_tasks(db: &Db, view_id: Uuid) -> Result<Vec<TaskResponse>, ApiError> {
+    let view = db
+        .get_view(view_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("View {} not found", view_id)))?;
+    let tasks = db.query_tasks(&view.filter()).await?;
+    Ok(tasks_to_responses(db, tasks).await?)
+}
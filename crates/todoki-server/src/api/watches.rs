@@ -0,0 +1,130 @@
+use gotcha::axum::extract::{Path, State};
+use gotcha::axum::Extension;
+use gotcha::Json;
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
+use crate::api::templates::EmptyResponse;
+use crate::auth::AuthContext;
+use crate::models::{CreateWatchRequest, Watch, WatchTarget};
+use crate::{Db, Watches};
+
+/// The calling user, e.g. `user` or `oidc:<subject>`
+fn user_id(auth: &AuthContext) -> Result<String, ApiError> {
+    auth.require_auth().map_err(|_| ApiError::unauthorized())?;
+    auth.token_id()
+        .map(str::to_string)
+        .ok_or_else(ApiError::unauthorized)
+}
+
+/// Make the caller watch what they just started, so its permission
+/// requests reach them; failures are only logged
+pub async fn watch_as_owner(db: &Db, watches: &Watches, auth: &AuthContext, target: WatchTarget) {
+    let Some(user_id) = auth.token_id() else {
+        return;
+    };
+    match db.create_watch(user_id, target).await {
+        Ok(watch) => watches.insert(&watch),
+        Err(e) => tracing::warn!(?target, error = %e, "failed to watch as owner"),
+    }
+}
+
+/// GET /api/watches - Tasks and sessions the calling user watches
+#[gotcha::api]
+pub async fn list_watches(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+) -> Result<Json<Vec<Watch>>, ApiError> {
+    let user_id = user_id(&auth)?;
+    let watches = db.list_user_watches(&user_id).await?;
+    Ok(Json(watches))
+}
+
+/// POST /api/watches - Watch a task or an agent session
+///
+/// Permission requests of watched tasks and sessions are only pushed to
+/// the event-bus clients of their watchers.
+#[gotcha::api]
+pub async fn create_watch(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(watches): State<Watches>,
+    Json(req): Json<CreateWatchRequest>,
+) -> Result<Json<Watch>, ApiError> {
+    let user_id = user_id(&auth)?;
+    let target = req.target().map_err(ApiError::bad_request)?;
+
+    match target {
+        WatchTarget::Task(task_id) => {
+            db.get_task_by_id(task_id)
+                .await?
+                .ok_or_else(|| ApiError::not_found("Task not found"))?;
+        }
+        WatchTarget::Session(session_id) => {
+            db.get_agent_session(session_id)
+                .await?
+                .ok_or_else(|| ApiError::not_found("Session not found"))?;
+        }
+    }
+
+    let watch = db.create_watch(&user_id, target).await?;
+    watches.insert(&watch);
+    Ok(Json(watch))
+}
+
+/// DELETE /api/watches/:watch_id - Stop watching
+#[gotcha::api]
+pub async fn delete_watch(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(watches): State<Watches>,
+    Path(watch_id): Path<Uuid>,
+) -> Result<Json<EmptyResponse>, ApiError> {
+    let user_id = user_id(&auth)?;
+    let watch = db
+        .delete_watch(watch_id, &user_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Watch not found"))?;
+    watches.remove(&watch);
+    Ok(Json(EmptyResponse {}))
+}
+
+/// POST /api/tasks/:task_id/watch - Watch a task
+///
+/// Watchers are notified when the task changes status, is handed over or
+/// gets an agent comment, and see it in `GET /api/watched`.
+#[gotcha::api]
+pub async fn watch_task(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(watches): State<Watches>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<Watch>, ApiError> {
+    let user_id = user_id(&auth)?;
+    db.get_task_by_id(task_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Task not found"))?;
+
+    let watch = db
+        .create_watch(&user_id, WatchTarget::Task(task_id))
+        .await?;
+    watches.insert(&watch);
+    Ok(Json(watch))
+}
+
+/// DELETE /api/tasks/:task_id/watch - Stop watching a task
+#[gotcha::api]
+pub async fn unwatch_task(
+    Extension(auth): Extension<AuthContext>,
+    State(db): State<Db>,
+    State(watches): State<Watches>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<EmptyResponse>, ApiError> {
+    let user_id = user_id(&auth)?;
+    let watch = db
+        .delete_target_watch(&user_id, WatchTarget::Task(task_id))
+        .await?
+        .ok_or_else(|| ApiError::not_found("Task not watched"))?;
+    watches.remove(&watch);
+    Ok(Json(EmptyResponse {}))
+}
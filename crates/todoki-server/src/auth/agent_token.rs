@@ -0,0 +1,141 @@
+//! Scoped tokens for spawned agents
+//!
+//! Every spawn request carries a token in `TODOKI_AGENT_TOKEN` that only
+//! lets the agent comment on its task, create artifacts for its session and
+//! emit `agent_tokens.allowed_event_kinds` as that session. Tokens expire
+//! after `agent_tokens.ttl_secs`, so agents never need the user or relay
+//! token.
+
+use std::collections::HashMap;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::session;
+use crate::config::AgentTokenSettings;
+
+pub const AGENT_TOKEN_ENV: &str = "TODOKI_AGENT_TOKEN";
+pub const SESSION_ID_ENV: &str = "TODOKI_SESSION_ID";
+pub const TASK_ID_ENV: &str = "TODOKI_TASK_ID";
+
+/// What an agent token grants; also its claims
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentScope {
+    pub agent_id: Uuid,
+    pub session_id: Uuid,
+    /// Task the agent works on; without one it cannot comment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<Uuid>,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+impl AgentScope {
+    pub fn token_id(&self) -> String {
+        format!("agent:{}", self.session_id)
+    }
+}
+
+pub fn issue(
+    settings: &AgentTokenSettings,
+    agent_id: Uuid,
+    session_id: Uuid,
+    task_id: Option<Uuid>,
+) -> String {
+    let now = Utc::now().timestamp();
+    let scope = AgentScope {
+        agent_id,
+        session_id,
+        task_id,
+        iat: now,
+        exp: now + settings.ttl_secs as i64,
+    };
+    session::encode(&scope, &settings.secret)
+}
+
+pub fn verify(settings: &AgentTokenSettings, token: &str) -> Option<AgentScope> {
+    if settings.secret.is_empty() {
+        return None;
+    }
+    session::decode(token, &settings.secret, Utc::now().timestamp()).ok()
+}
+
+/// Environment for a spawned agent: its token and what it is working on
+pub fn spawn_env(
+    settings: &AgentTokenSettings,
+    agent_id: Uuid,
+    session_id: Uuid,
+    task_id: Option<Uuid>,
+) -> HashMap<String, String> {
+    let mut env = HashMap::from([
+        (
+            AGENT_TOKEN_ENV.to_string(),
+            issue(settings, agent_id, session_id, task_id),
+        ),
+        (SESSION_ID_ENV.to_string(), session_id.to_string()),
+    ]);
+    if let Some(task_id) = task_id {
+        env.insert(TASK_ID_ENV.to_string(), task_id.to_string());
+    }
+    env
+}
+
+/// Whether agents may emit events of `kind`
+pub fn kind_allowed(settings: &AgentTokenSettings, kind: &str) -> bool {
+    settings
+        .allowed_event_kinds
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => kind.starts_with(prefix),
+            None => pattern == kind,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> AgentTokenSettings {
+        AgentTokenSettings {
+            secret: "secret".to_string(),
+            allowed_event_kinds: vec!["agent.task_comment".to_string(), "artifact.*".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_issue_and_verify() {
+        let settings = settings();
+        let (agent_id, session_id, task_id) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let token = issue(&settings, agent_id, session_id, Some(task_id));
+
+        let scope = verify(&settings, &token).unwrap();
+        assert_eq!(scope.session_id, session_id);
+        assert_eq!(scope.task_id, Some(task_id));
+
+        let other = AgentTokenSettings {
+            secret: "other".to_string(),
+            ..settings.clone()
+        };
+        assert_eq!(verify(&other, &token), None);
+        // A user session signed with the same key is not an agent token
+        let user = session::SessionClaims {
+            sub: "user-1".to_string(),
+            email: None,
+            name: None,
+            iat: 0,
+            exp: i64::MAX,
+        };
+        assert_eq!(verify(&settings, &session::encode(&user, "secret")), None);
+    }
+
+    #[test]
+    fn test_kind_allowed() {
+        let settings = settings();
+        assert!(kind_allowed(&settings, "agent.task_comment"));
+        assert!(kind_allowed(&settings, "artifact.created"));
+        assert!(!kind_allowed(&settings, "agent.started"));
+        assert!(!kind_allowed(&settings, "task.deleted"));
+    }
+}
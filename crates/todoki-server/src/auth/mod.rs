@@ -1,3 +1,9 @@
+pub mod agent_token;
+pub mod oidc;
+pub mod session;
+pub mod share_token;
+
+use chrono::Utc;
 use gotcha::axum::{
     extract::{Request, State},
     http::{header::AUTHORIZATION, StatusCode},
@@ -6,22 +12,103 @@ use gotcha::axum::{
 };
 use gotcha::tracing::{debug, warn};
 
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
 use crate::config::Settings;
+use crate::net::client_ip::ClientIp;
+use agent_token::AgentScope;
+
+/// Token ID of requests authenticated with `user_token`
+pub const USER_TOKEN_ID: &str = "user";
 
 /// Authentication context extracted from request
 #[derive(Debug, Clone)]
 pub enum AuthContext {
-    Authenticated,
+    /// `token_id` names the token without revealing it
+    Authenticated { token_id: String },
+    /// A spawned agent, limited to what its scope grants
+    Agent { token_id: String, scope: AgentScope },
     None,
 }
 
 impl AuthContext {
+    /// Require full access; agent tokens do not have it
     pub fn require_auth(&self) -> Result<(), StatusCode> {
         match self {
-            AuthContext::Authenticated => Ok(()),
-            AuthContext::None => Err(StatusCode::UNAUTHORIZED),
+            AuthContext::Authenticated { .. } => Ok(()),
+            AuthContext::Agent { .. } | AuthContext::None => Err(StatusCode::UNAUTHORIZED),
+        }
+    }
+
+    /// ID of the token the request authenticated with
+    pub fn token_id(&self) -> Option<&str> {
+        match self {
+            AuthContext::Authenticated { token_id } | AuthContext::Agent { token_id, .. } => {
+                Some(token_id)
+            }
+            AuthContext::None => None,
+        }
+    }
+
+    /// Scope of an agent token
+    pub fn agent_scope(&self) -> Option<&AgentScope> {
+        match self {
+            AuthContext::Agent { scope, .. } => Some(scope),
+            _ => None,
+        }
+    }
+
+    /// Require full access or an agent token for this task
+    pub fn require_task(&self, task_id: Uuid) -> Result<(), ApiError> {
+        match self {
+            AuthContext::Authenticated { .. } => Ok(()),
+            AuthContext::Agent { scope, .. } if scope.task_id == Some(task_id) => Ok(()),
+            AuthContext::Agent { .. } => {
+                Err(ApiError::forbidden("agent token is for another task"))
+            }
+            AuthContext::None => Err(ApiError::unauthorized()),
         }
     }
+
+    /// Require full access or an agent token for this session
+    pub fn require_session(&self, session_id: Uuid) -> Result<(), ApiError> {
+        match self {
+            AuthContext::Authenticated { .. } => Ok(()),
+            AuthContext::Agent { scope, .. } if scope.session_id == session_id => Ok(()),
+            AuthContext::Agent { .. } => {
+                Err(ApiError::forbidden("agent token is for another session"))
+            }
+            AuthContext::None => Err(ApiError::unauthorized()),
+        }
+    }
+}
+
+/// Token ID for a valid `user_token` or OIDC session token
+pub fn user_token_id(settings: &Settings, token: &str) -> Option<String> {
+    if token == settings.user_token {
+        return Some(USER_TOKEN_ID.to_string());
+    }
+    if !settings.oidc.enabled() {
+        return None;
+    }
+    let now = Utc::now().timestamp();
+    session::decode::<session::SessionClaims>(token, &settings.oidc.session_secret, now)
+        .ok()
+        .map(|claims| format!("oidc:{}", claims.sub))
+}
+
+fn authenticate(settings: &Settings, token: &str) -> Option<AuthContext> {
+    if let Some(token_id) = user_token_id(settings, token) {
+        debug!("Token authenticated");
+        return Some(AuthContext::Authenticated { token_id });
+    }
+    let scope = agent_token::verify(&settings.agent_tokens, token)?;
+    debug!(session_id = %scope.session_id, "Agent token authenticated");
+    Some(AuthContext::Agent {
+        token_id: scope.token_id(),
+        scope,
+    })
 }
 
 /// Simple Bearer token authentication middleware
@@ -38,14 +125,11 @@ pub async fn auth_middleware(
     let token = auth_header.and_then(|auth| auth.strip_prefix("Bearer ").map(|s| s.to_string()));
 
     let context = match token {
-        Some(t) if t == settings.user_token => {
-            debug!("Token authenticated");
-            AuthContext::Authenticated
-        }
-        Some(_) => {
-            warn!("Invalid token provided");
+        Some(t) => authenticate(&settings, &t).unwrap_or_else(|| {
+            let client_ip = request.extensions().get::<ClientIp>().and_then(|c| c.0);
+            warn!(client_ip = ?client_ip, "Invalid token provided");
             AuthContext::None
-        }
+        }),
         None => {
             debug!("No token provided");
             AuthContext::None
@@ -0,0 +1,351 @@
+//! OIDC login for the web UI (authorization code flow with PKCE)
+//!
+//! `GET /api/auth/oidc/login` redirects to the identity provider, which
+//! sends the browser back to the callback. The callback exchanges the code,
+//! checks the ID token and the allow-lists, and returns to the web UI with a
+//! todoki session token in the URL fragment. Login state travels in a
+//! short-lived signed cookie, so any instance can handle the callback.
+//!
+//! The ID token comes straight from the token endpoint over TLS, so its
+//! signature is not checked (OIDC Core 3.1.3.7); its issuer, audience,
+//! expiry and nonce are.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use gotcha::axum::extract::{Query, State};
+use gotcha::axum::http::{header, HeaderMap};
+use gotcha::axum::response::{IntoResponse, Redirect, Response};
+use gotcha::Json;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::session::{self, random_token, SessionClaims};
+use crate::api::error::ApiError;
+use crate::config::{OidcSettings, Settings};
+
+const LOGIN_COOKIE: &str = "todoki_oidc";
+const LOGIN_COOKIE_PATH: &str = "/api/auth/oidc";
+
+/// How long the provider may take to send the browser back (seconds)
+const LOGIN_TTL_SECS: i64 = 600;
+
+/// Login in progress, kept in the login cookie
+#[derive(Debug, Serialize, Deserialize)]
+struct LoginState {
+    state: String,
+    nonce: String,
+    code_verifier: String,
+    exp: i64,
+}
+
+/// The parts of the provider's discovery document used here
+#[derive(Debug, Deserialize)]
+struct ProviderMetadata {
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    iss: String,
+    sub: String,
+    aud: Value,
+    exp: i64,
+    #[serde(default)]
+    nonce: Option<String>,
+    #[serde(default)]
+    email: Option<String>,
+    #[serde(default)]
+    email_verified: Option<bool>,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CallbackQuery {
+    #[serde(default)]
+    code: Option<String>,
+    #[serde(default)]
+    state: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+/// GET /api/auth/config - Login methods the web UI can offer
+pub async fn auth_config(State(settings): State<Settings>) -> Response {
+    Json(serde_json::json!({ "oidc_enabled": settings.oidc.enabled() })).into_response()
+}
+
+/// GET /api/auth/oidc/login - Start a login at the identity provider
+pub async fn login(State(settings): State<Settings>) -> Response {
+    match start_login(&settings.oidc).await {
+        Ok(response) => response,
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn start_login(oidc: &OidcSettings) -> Result<Response, ApiError> {
+    if !oidc.enabled() {
+        return Err(ApiError::not_found("OIDC login is not configured"));
+    }
+    let provider = discover(oidc).await?;
+
+    let login = LoginState {
+        state: random_token(),
+        nonce: random_token(),
+        code_verifier: random_token(),
+        exp: Utc::now().timestamp() + LOGIN_TTL_SECS,
+    };
+    let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(login.code_verifier.as_bytes()));
+    let scope = oidc.scopes.join(" ");
+    let url = url::Url::parse_with_params(
+        &provider.authorization_endpoint,
+        [
+            ("response_type", "code"),
+            ("client_id", oidc.client_id.as_str()),
+            ("redirect_uri", oidc.redirect_url.as_str()),
+            ("scope", scope.as_str()),
+            ("state", login.state.as_str()),
+            ("nonce", login.nonce.as_str()),
+            ("code_challenge", code_challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .map_err(|e| ApiError::internal(format!("invalid authorization endpoint: {}", e)))?;
+
+    let cookie = login_cookie(
+        oidc,
+        &session::encode(&login, &oidc.session_secret),
+        LOGIN_TTL_SECS,
+    );
+    Ok(([(header::SET_COOKIE, cookie)], Redirect::to(url.as_str())).into_response())
+}
+
+/// GET /api/auth/oidc/callback - Finish a login and hand the web UI a session
+pub async fn callback(
+    State(settings): State<Settings>,
+    headers: HeaderMap,
+    Query(query): Query<CallbackQuery>,
+) -> Response {
+    let oidc = &settings.oidc;
+    // The login cookie is single-use either way
+    let clear_cookie = login_cookie(oidc, "", 0);
+    match finish_login(oidc, &headers, query).await {
+        Ok(token) => {
+            let location = format!("{}#token={}", oidc.post_login_url, token);
+            (
+                [(header::SET_COOKIE, clear_cookie)],
+                Redirect::to(&location),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            tracing::warn!(error = %e.message, "OIDC login failed");
+            ([(header::SET_COOKIE, clear_cookie)], e).into_response()
+        }
+    }
+}
+
+async fn finish_login(
+    oidc: &OidcSettings,
+    headers: &HeaderMap,
+    query: CallbackQuery,
+) -> Result<String, ApiError> {
+    if !oidc.enabled() {
+        return Err(ApiError::not_found("OIDC login is not configured"));
+    }
+    if let Some(error) = query.error {
+        let description = query.error_description.unwrap_or_default();
+        return Err(ApiError::bad_request(format!(
+            "identity provider refused the login: {} {}",
+            error, description
+        )));
+    }
+
+    let now = Utc::now().timestamp();
+    let login: LoginState = read_cookie(headers, LOGIN_COOKIE)
+        .ok_or("login expired or was started elsewhere".to_string())
+        .and_then(|cookie| session::decode(cookie, &oidc.session_secret, now))
+        .map_err(|e| ApiError::bad_request(format!("invalid login state: {}", e)))?;
+    if query.state.as_deref() != Some(login.state.as_str()) {
+        return Err(ApiError::bad_request("login state does not match"));
+    }
+    let code = query
+        .code
+        .ok_or_else(|| ApiError::bad_request("missing authorization code"))?;
+
+    let provider = discover(oidc).await?;
+    let response = reqwest::Client::new()
+        .post(&provider.token_endpoint)
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code.as_str()),
+            ("redirect_uri", oidc.redirect_url.as_str()),
+            ("client_id", oidc.client_id.as_str()),
+            ("client_secret", oidc.client_secret.as_str()),
+            ("code_verifier", login.code_verifier.as_str()),
+        ])
+        .send()
+        .await
+        .map_err(|e| ApiError::internal(format!("token request failed: {}", e)))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(ApiError::bad_request(format!(
+            "token endpoint returned {}: {}",
+            status, body
+        )));
+    }
+    let tokens: TokenResponse = response
+        .json()
+        .await
+        .map_err(|e| ApiError::internal(format!("invalid token response: {}", e)))?;
+
+    let payload = tokens.id_token.split('.').nth(1).unwrap_or_default();
+    let claims: IdTokenClaims = session::decode_part(payload).map_err(ApiError::bad_request)?;
+    check_id_token(oidc, &claims, &login.nonce, now).map_err(ApiError::bad_request)?;
+
+    let email = claims
+        .email
+        .filter(|_| claims.email_verified != Some(false));
+    if !oidc.allows(email.as_deref()) {
+        return Err(ApiError::forbidden(
+            "this account is not allowed to use todoki",
+        ));
+    }
+
+    tracing::info!(sub = %claims.sub, email = ?email, "OIDC login");
+    let session = SessionClaims {
+        sub: claims.sub,
+        email,
+        name: claims.name,
+        iat: now,
+        exp: now + oidc.session_ttl_secs as i64,
+    };
+    Ok(session::encode(&session, &oidc.session_secret))
+}
+
+fn check_id_token(
+    oidc: &OidcSettings,
+    claims: &IdTokenClaims,
+    nonce: &str,
+    now: i64,
+) -> Result<(), String> {
+    if claims.iss.trim_end_matches('/') != oidc.issuer_url.trim_end_matches('/') {
+        return Err(format!("ID token issued by {}", claims.iss));
+    }
+    let audience_ok = match &claims.aud {
+        Value::String(aud) => *aud == oidc.client_id,
+        Value::Array(auds) => auds.iter().any(|aud| *aud == oidc.client_id.as_str()),
+        _ => false,
+    };
+    if !audience_ok {
+        return Err("ID token is for another client".to_string());
+    }
+    if claims.exp <= now {
+        return Err("ID token expired".to_string());
+    }
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err("ID token nonce does not match".to_string());
+    }
+    Ok(())
+}
+
+async fn discover(oidc: &OidcSettings) -> Result<ProviderMetadata, ApiError> {
+    let url = format!(
+        "{}/.well-known/openid-configuration",
+        oidc.issuer_url.trim_end_matches('/')
+    );
+    let fetch = async {
+        reqwest::get(&url)
+            .await?
+            .error_for_status()?
+            .json::<ProviderMetadata>()
+            .await
+    };
+    fetch
+        .await
+        .map_err(|e| ApiError::internal(format!("OIDC discovery failed: {}", e)))
+}
+
+fn login_cookie(oidc: &OidcSettings, value: &str, max_age_secs: i64) -> String {
+    let secure = if oidc.redirect_url.starts_with("https://") {
+        "; Secure"
+    } else {
+        ""
+    };
+    format!(
+        "{}={}; Path={}; Max-Age={}; HttpOnly; SameSite=Lax{}",
+        LOGIN_COOKIE, value, LOGIN_COOKIE_PATH, max_age_secs, secure
+    )
+}
+
+fn read_cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> OidcSettings {
+        OidcSettings {
+            issuer_url: "https://idp.example.com/".to_string(),
+            client_id: "todoki".to_string(),
+            session_secret: "secret".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn claims() -> IdTokenClaims {
+        IdTokenClaims {
+            iss: "https://idp.example.com".to_string(),
+            sub: "user-1".to_string(),
+            aud: serde_json::json!(["other", "todoki"]),
+            exp: 100,
+            nonce: Some("nonce".to_string()),
+            email: None,
+            email_verified: None,
+            name: None,
+        }
+    }
+
+    #[test]
+    fn test_check_id_token() {
+        let oidc = settings();
+        assert!(check_id_token(&oidc, &claims(), "nonce", 50).is_ok());
+        assert!(check_id_token(&oidc, &claims(), "other", 50).is_err());
+        assert!(check_id_token(&oidc, &claims(), "nonce", 100).is_err());
+
+        let mut wrong_audience = claims();
+        wrong_audience.aud = serde_json::json!("other");
+        assert!(check_id_token(&oidc, &wrong_audience, "nonce", 50).is_err());
+    }
+
+    #[test]
+    fn test_allow_lists() {
+        let mut oidc = settings();
+        assert!(oidc.allows(None));
+
+        oidc.allowed_domains = vec!["example.com".to_string()];
+        assert!(oidc.allows(Some("Alice@Example.com")));
+        assert!(!oidc.allows(Some("bob@example.org")));
+        assert!(!oidc.allows(None));
+    }
+}
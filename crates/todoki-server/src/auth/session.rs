@@ -0,0 +1,127 @@
+//! Signed tokens issued by todoki itself
+//!
+//! HS256 JWTs: session tokens handed out after an OIDC login, the login
+//! state kept in a cookie meanwhile, and scoped agent tokens. Every token
+//! carries an `exp` claim, which is checked on decoding.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+
+const HEADER: &str = r#"{"alg":"HS256","typ":"JWT"}"#;
+
+/// Claims of a session token started by an OIDC login
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionClaims {
+    /// Subject at the identity provider
+    pub sub: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+pub fn encode<T: Serialize>(claims: &T, secret: &str) -> String {
+    let payload = serde_json::to_vec(claims).expect("claims serialize to JSON");
+    let signing_input = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(HEADER),
+        URL_SAFE_NO_PAD.encode(payload)
+    );
+    let mut mac = mac(secret);
+    mac.update(signing_input.as_bytes());
+    let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+    format!("{}.{}", signing_input, signature)
+}
+
+/// Check the signature and expiry (against `now`, a unix timestamp)
+pub fn decode<T: DeserializeOwned>(token: &str, secret: &str, now: i64) -> Result<T, String> {
+    let (signing_input, signature) = token.rsplit_once('.').ok_or("malformed token")?;
+    let (header, payload) = signing_input.split_once('.').ok_or("malformed token")?;
+
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature)
+        .map_err(|_| "malformed token")?;
+    let mut mac = mac(secret);
+    mac.update(signing_input.as_bytes());
+    mac.verify_slice(&signature)
+        .map_err(|_| "invalid token signature")?;
+
+    let header: Value = decode_part(header)?;
+    if header["alg"] != "HS256" {
+        return Err("unsupported token algorithm".to_string());
+    }
+    let payload: Value = decode_part(payload)?;
+    match payload.get("exp").and_then(Value::as_i64) {
+        Some(exp) if exp > now => {}
+        Some(_) => return Err("token expired".to_string()),
+        None => return Err("token has no expiry".to_string()),
+    }
+    serde_json::from_value(payload).map_err(|e| format!("invalid token claims: {}", e))
+}
+
+/// Decode one base64url JSON part of a JWT without checking anything
+pub fn decode_part<T: DeserializeOwned>(part: &str) -> Result<T, String> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(part)
+        .map_err(|_| "malformed token")?;
+    serde_json::from_slice(&bytes).map_err(|e| format!("malformed token: {}", e))
+}
+
+/// 32 random bytes, base64url-encoded
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn mac(secret: &str) -> Hmac<Sha256> {
+    Hmac::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(exp: i64) -> SessionClaims {
+        SessionClaims {
+            sub: "user-1".to_string(),
+            email: Some("a@example.com".to_string()),
+            name: None,
+            iat: 0,
+            exp,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let token = encode(&claims(100), "secret");
+        assert_eq!(
+            decode::<SessionClaims>(&token, "secret", 50),
+            Ok(claims(100))
+        );
+    }
+
+    #[test]
+    fn test_rejects_tampered_and_expired_tokens() {
+        let token = encode(&claims(100), "secret");
+        assert!(decode::<SessionClaims>(&token, "other", 50).is_err());
+        assert_eq!(
+            decode::<SessionClaims>(&token, "secret", 100),
+            Err("token expired".to_string())
+        );
+
+        let forged = encode(&claims(1000), "other");
+        let forged: Vec<&str> = forged.split('.').collect();
+        let parts: Vec<&str> = token.split('.').collect();
+        let tampered = format!("{}.{}.{}", parts[0], forged[1], parts[2]);
+        assert!(decode::<SessionClaims>(&tampered, "secret", 50).is_err());
+    }
+}
@@ -0,0 +1,102 @@
+//! Read-only links to a single agent session
+//!
+//! A share token lets whoever holds it watch one session's output stream on
+//! `/ws/event-bus` and nothing else: it is not accepted by the REST API and
+//! the WebSocket only forwards that session's events to it. Tokens are
+//! signed with the agent token key and expire after at most
+//! `share_tokens.max_ttl_secs`; they cannot be revoked before that.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::session;
+use crate::config::ShareTokenSettings;
+
+/// What a share token grants; also its claims
+///
+/// The field names differ from [`super::agent_token::AgentScope`] and
+/// [`session::SessionClaims`] so neither kind of token decodes as the other.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShareScope {
+    pub shared_agent_id: Uuid,
+    pub shared_session_id: Uuid,
+    /// Token ID of whoever minted the link
+    pub shared_by: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+/// Lifetime of a new token: `ttl_secs` if given, capped at `max_ttl_secs`
+pub fn ttl_secs(settings: &ShareTokenSettings, ttl_secs: Option<u64>) -> u64 {
+    ttl_secs
+        .unwrap_or(settings.default_ttl_secs)
+        .min(settings.max_ttl_secs)
+}
+
+pub fn issue(
+    secret: &str,
+    agent_id: Uuid,
+    session_id: Uuid,
+    shared_by: &str,
+    ttl_secs: u64,
+) -> (String, ShareScope) {
+    let now = Utc::now().timestamp();
+    let scope = ShareScope {
+        shared_agent_id: agent_id,
+        shared_session_id: session_id,
+        shared_by: shared_by.to_string(),
+        iat: now,
+        exp: now + ttl_secs as i64,
+    };
+    (session::encode(&scope, secret), scope)
+}
+
+pub fn verify(secret: &str, token: &str) -> Option<ShareScope> {
+    if secret.is_empty() {
+        return None;
+    }
+    session::decode(token, secret, Utc::now().timestamp()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::agent_token::AgentScope;
+
+    #[test]
+    fn test_issue_and_verify() {
+        let (agent_id, session_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let (token, scope) = issue("secret", agent_id, session_id, "user", 60);
+        assert_eq!(scope.exp - scope.iat, 60);
+
+        let verified = verify("secret", &token).unwrap();
+        assert_eq!(verified.shared_session_id, session_id);
+        assert_eq!(verified.shared_by, "user");
+        assert_eq!(verify("other", &token), None);
+
+        // Agent tokens signed with the same key are not share tokens, and
+        // share tokens are not agent tokens
+        let agent = AgentScope {
+            agent_id,
+            session_id,
+            task_id: None,
+            iat: 0,
+            exp: i64::MAX,
+        };
+        assert_eq!(verify("secret", &session::encode(&agent, "secret")), None);
+        let now = Utc::now().timestamp();
+        assert!(session::decode::<AgentScope>(&token, "secret", now).is_err());
+    }
+
+    #[test]
+    fn test_ttl_secs() {
+        let settings = ShareTokenSettings {
+            default_ttl_secs: 3600,
+            max_ttl_secs: 7200,
+        };
+        assert_eq!(ttl_secs(&settings, None), 3600);
+        assert_eq!(ttl_secs(&settings, Some(60)), 60);
+        assert_eq!(ttl_secs(&settings, Some(86400)), 7200);
+    }
+}
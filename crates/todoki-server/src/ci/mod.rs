@@ -0,0 +1,282 @@
+//! CI status of branches agents worked on
+//!
+//! Relays report the branch an agent committed to as a `branch` artifact.
+//! For branches of GitHub repositories, this worker polls the check runs of
+//! the branch's head until they all finish, stores the outcome in the
+//! artifact's `ci` field and emits `artifact.ci_passed` or
+//! `artifact.ci_failed`. With `gate_qa`, handoffs to the QA role wait until
+//! every tracked branch of the task is green.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use todoki_protocol::event_bus::{ArtifactCiData, BuiltinEvent};
+
+use crate::config::CiSettings;
+use crate::db::DatabaseService;
+use crate::event_bus::{EventPublisher, EventScope};
+use crate::models::{merge_artifact_data, Artifact};
+
+/// Artifact type of branches reported by relays
+pub const BRANCH_ARTIFACT_TYPE: &str = "branch";
+
+/// Branches checked per poll
+const POLL_BATCH: i64 = 50;
+
+/// Conclusions that do not fail a branch
+const OK_CONCLUSIONS: &[&str] = &["success", "neutral", "skipped"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CiStatus {
+    /// Not checked yet, or checks still running
+    Pending,
+    Passed,
+    Failed,
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckRuns {
+    check_runs: Vec<CheckRun>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CheckRun {
+    pub name: String,
+    /// `queued`, `in_progress` or `completed`
+    pub status: String,
+    pub conclusion: Option<String>,
+}
+
+/// Status of a head from its check runs, with the names of failed checks
+fn ci_outcome(runs: &[CheckRun]) -> (CiStatus, Vec<String>) {
+    // No runs yet: the checks may not have been queued
+    if runs.is_empty() || runs.iter().any(|run| run.status != "completed") {
+        return (CiStatus::Pending, Vec::new());
+    }
+    let failed: Vec<String> = runs
+        .iter()
+        .filter(|run| !matches!(run.conclusion.as_deref(), Some(c) if OK_CONCLUSIONS.contains(&c)))
+        .map(|run| run.name.clone())
+        .collect();
+    if failed.is_empty() {
+        (CiStatus::Passed, failed)
+    } else {
+        (CiStatus::Failed, failed)
+    }
+}
+
+/// Repository (`owner/repo`), branch name and head of a `branch` artifact;
+/// `None` for branches CI cannot be tracked for
+fn tracked_branch(artifact: &Artifact) -> Option<(String, &str, &str)> {
+    let field = |key: &str| artifact.data.get(key).and_then(|v| v.as_str());
+    if artifact.artifact_type != BRANCH_ARTIFACT_TYPE {
+        return None;
+    }
+    let repo = format!("{}/{}", field("owner")?, field("repo")?);
+    Some((repo, field("name")?, field("head")?))
+}
+
+/// CI status stored on a `branch` artifact
+pub fn branch_ci_status(artifact: &Artifact) -> CiStatus {
+    artifact
+        .data
+        .get("ci")
+        .and_then(|ci| ci.get("status"))
+        .and_then(|status| serde_json::from_value(status.clone()).ok())
+        .unwrap_or(CiStatus::Pending)
+}
+
+/// A tracked branch among `artifacts` that is not green yet
+pub fn unfinished_branch(artifacts: &[Artifact]) -> Option<&Artifact> {
+    artifacts.iter().find(|artifact| {
+        tracked_branch(artifact).is_some() && branch_ci_status(artifact) != CiStatus::Passed
+    })
+}
+
+pub struct CiWatcher {
+    settings: CiSettings,
+    db: Arc<DatabaseService>,
+    publisher: Arc<EventPublisher>,
+    http_client: reqwest::Client,
+}
+
+impl CiWatcher {
+    pub fn new(
+        settings: CiSettings,
+        db: Arc<DatabaseService>,
+        publisher: Arc<EventPublisher>,
+    ) -> Self {
+        Self {
+            settings,
+            db,
+            publisher,
+            http_client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn run(self) {
+        let period = Duration::from_secs(self.settings.poll_interval_secs.max(10));
+        let mut interval = tokio::time::interval(period);
+
+        loop {
+            interval.tick().await;
+
+            let since = Utc::now() - chrono::Duration::hours(self.settings.max_age_hours);
+            let branches = match self.db.list_pending_branches(since, POLL_BATCH).await {
+                Ok(branches) => branches,
+                Err(e) => {
+                    tracing::error!(error = %e, "failed to list branches awaiting CI");
+                    continue;
+                }
+            };
+
+            for branch in branches {
+                if let Err(e) = self.check(&branch).await {
+                    tracing::warn!(artifact_id = %branch.id, error = %e, "failed to check CI");
+                }
+            }
+        }
+    }
+
+    /// Look up the checks of one branch and record them once finished
+    async fn check(&self, artifact: &Artifact) -> anyhow::Result<()> {
+        let Some((repo, name, head)) = tracked_branch(artifact) else {
+            return Ok(());
+        };
+        let runs = self.check_runs(&repo, head).await?;
+        let (status, failed_checks) = ci_outcome(&runs);
+        if status == CiStatus::Pending {
+            return Ok(());
+        }
+
+        let mut data = artifact.data.clone();
+        merge_artifact_data(
+            &mut data,
+            serde_json::json!({
+                "ci": {
+                    "status": status,
+                    "head": head,
+                    "failed_checks": failed_checks,
+                    "checked_at": Utc::now(),
+                }
+            }),
+        );
+        if self
+            .db
+            .update_artifact(artifact.id, data, None)
+            .await?
+            .is_none()
+        {
+            // Deleted meanwhile
+            return Ok(());
+        }
+
+        tracing::info!(repo = %repo, branch = %name, status = ?status, "branch CI finished");
+        let ci = ArtifactCiData {
+            artifact_id: artifact.id.to_string(),
+            branch: name.to_string(),
+            head: head.to_string(),
+            repo,
+            failed_checks,
+        };
+        let event = match status {
+            CiStatus::Passed => BuiltinEvent::ArtifactCiPassed(ci),
+            _ => BuiltinEvent::ArtifactCiFailed(ci),
+        };
+        let scope = EventScope::task(artifact.task_id);
+        if let Err(e) = self.publisher.emit_builtin(event, scope).await {
+            tracing::warn!(artifact_id = %artifact.id, error = %e, "failed to emit CI event");
+        }
+        Ok(())
+    }
+
+    async fn check_runs(&self, repo: &str, head: &str) -> anyhow::Result<Vec<CheckRun>> {
+        let url = format!(
+            "{}/repos/{}/commits/{}/check-runs?per_page=100",
+            self.settings.github_api_url.trim_end_matches('/'),
+            repo,
+            head
+        );
+        let mut request = self
+            .http_client
+            .get(&url)
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "todoki");
+        if !self.settings.github_token.is_empty() {
+            request = request.bearer_auth(&self.settings.github_token);
+        }
+        let runs: CheckRuns = request.send().await?.error_for_status()?.json().await?;
+        Ok(runs.check_runs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn run(name: &str, status: &str, conclusion: Option<&str>) -> CheckRun {
+        CheckRun {
+            name: name.to_string(),
+            status: status.to_string(),
+            conclusion: conclusion.map(str::to_string),
+        }
+    }
+
+    fn branch(data: serde_json::Value) -> Artifact {
+        Artifact {
+            id: Uuid::new_v4(),
+            task_id: Uuid::nil(),
+            project_id: Uuid::nil(),
+            agent_id: None,
+            session_id: None,
+            artifact_type: BRANCH_ARTIFACT_TYPE.to_string(),
+            data,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_ci_outcome() {
+        assert_eq!(ci_outcome(&[]).0, CiStatus::Pending);
+
+        let mut runs = vec![
+            run("build", "completed", Some("success")),
+            run("lint", "in_progress", None),
+        ];
+        assert_eq!(ci_outcome(&runs).0, CiStatus::Pending);
+
+        runs[1] = run("lint", "completed", Some("skipped"));
+        assert_eq!(ci_outcome(&runs), (CiStatus::Passed, vec![]));
+
+        runs.push(run("test", "completed", Some("timed_out")));
+        assert_eq!(
+            ci_outcome(&runs),
+            (CiStatus::Failed, vec!["test".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_unfinished_branch() {
+        let tracked = json!({"name": "fix", "head": "abc", "owner": "o", "repo": "r"});
+        let untracked = branch(json!({"name": "local", "head": "def"}));
+        let mut passed = tracked.clone();
+        passed["ci"] = json!({"status": "passed"});
+
+        assert!(unfinished_branch(&[untracked.clone(), branch(passed.clone())]).is_none());
+        let pending = branch(tracked);
+        assert_eq!(branch_ci_status(&pending), CiStatus::Pending);
+        assert_eq!(
+            unfinished_branch(&[untracked, pending.clone()]).unwrap().id,
+            pending.id
+        );
+
+        passed["ci"]["status"] = json!("failed");
+        assert!(unfinished_branch(&[branch(passed)]).is_some());
+    }
+}
@@ -0,0 +1,241 @@
+//! `todoki --check-config`: validate the configuration and exit
+//!
+//! Loads the settings the server would start with and reports, without
+//! starting anything, keys in the config files that no setting reads, fields
+//! that fail to load and combinations that are insecure or cannot work.
+//! With `--check-database` the database is connected to as well.
+
+use serde_json::Value;
+
+use super::Settings;
+use crate::db::DatabaseService;
+
+/// Placeholder tokens shipped in `config/default.toml`
+const PLACEHOLDER_TOKEN: &str = "change-me-in-production";
+
+/// Top-level sections of the config files
+const SECTIONS: &[&str] = &["basic", "application"];
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ConfigReport {
+    /// Problems the server must not start with
+    pub errors: Vec<String>,
+    /// Settings that work but are probably not what was meant
+    pub warnings: Vec<String>,
+}
+
+impl ConfigReport {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    fn error(&mut self, message: impl Into<String>) {
+        self.errors.push(message.into());
+    }
+
+    fn warn(&mut self, message: impl Into<String>) {
+        self.warnings.push(message.into());
+    }
+
+    pub fn print(&self) {
+        for error in &self.errors {
+            println!("error: {}", error);
+        }
+        for warning in &self.warnings {
+            println!("warning: {}", warning);
+        }
+        println!(
+            "{}: {} error(s), {} warning(s)",
+            if self.is_ok() { "OK" } else { "FAILED" },
+            self.errors.len(),
+            self.warnings.len()
+        );
+    }
+}
+
+/// Check the configuration; the database only when `check_database` is set
+pub async fn run(check_database: bool) -> ConfigReport {
+    let mut report = ConfigReport::default();
+
+    match Settings::files().and_then(|files| files.try_deserialize::<Value>()) {
+        Ok(files) => {
+            for key in unknown_keys(&files) {
+                report.warn(format!("unknown key `{}` is ignored", key));
+            }
+        }
+        Err(e) => report.error(format!("config files cannot be read: {}", e)),
+    }
+
+    let settings = match Settings::new() {
+        Ok(settings) => settings,
+        Err(e) => {
+            report.error(format!("settings cannot be loaded: {}", e));
+            return report;
+        }
+    };
+    check_settings(&settings.application, &mut report);
+
+    if check_database {
+        let app = &settings.application;
+        let reachable = match DatabaseService::new(&app.database_url, &app.database) {
+            Ok(db) => db.pending_migrations().await.map(|pending| pending.len()),
+            Err(e) => Err(e),
+        };
+        match reachable {
+            Ok(0) => {}
+            Ok(pending) => report.warn(format!("{} database migration(s) pending", pending)),
+            Err(e) => report.error(format!("database is not reachable: {}", e)),
+        }
+    }
+
+    report
+}
+
+/// Keys of the config files that no setting reads, as dotted paths
+pub fn unknown_keys(files: &Value) -> Vec<String> {
+    let mut unknown = Vec::new();
+    let Some(sections) = files.as_object() else {
+        return unknown;
+    };
+    for key in sections.keys().filter(|key| !SECTIONS.contains(&key.as_str())) {
+        unknown.push(key.clone());
+    }
+    if let Some(application) = sections.get("application") {
+        let known = serde_json::to_value(Settings::default()).unwrap_or_default();
+        collect_unknown(&known, application, "application", &mut unknown);
+    }
+    unknown
+}
+
+fn collect_unknown(known: &Value, actual: &Value, path: &str, unknown: &mut Vec<String>) {
+    // Only tables are compared key by key; anything else (lists, optional
+    // tables unset by default) is taken as a whole
+    let (Some(known), Some(actual)) = (known.as_object(), actual.as_object()) else {
+        return;
+    };
+    for (key, value) in actual {
+        let path = format!("{}.{}", path, key);
+        match known.get(key) {
+            Some(known) => collect_unknown(known, value, &path, unknown),
+            None => unknown.push(path),
+        }
+    }
+}
+
+/// Missing values and insecure or unusable combinations
+pub fn check_settings(settings: &Settings, report: &mut ConfigReport) {
+    if settings.database_url.is_empty() {
+        report.error("database_url is empty");
+    }
+    if settings.user_token.is_empty() {
+        report.error("user_token is empty");
+    } else if settings.user_token == PLACEHOLDER_TOKEN {
+        report.warn("user_token is still the default placeholder");
+    }
+    if settings.relay_token.is_empty() {
+        report.error("relay_token is empty; relays could connect without a token");
+    } else if settings.relay_token == PLACEHOLDER_TOKEN {
+        report.warn("relay_token is still the default placeholder");
+    } else if settings.relay_token == settings.user_token {
+        report.warn("relay_token equals user_token; every relay host gets full API access");
+    }
+
+    let has_llm = !settings.auto_review.openai_api_key.is_empty();
+    for (enabled, name) in [
+        (settings.auto_review.enabled, "auto_review"),
+        (settings.session_summary.enabled, "session_summary"),
+        (settings.triage.enabled, "triage"),
+    ] {
+        if enabled && !has_llm {
+            report.error(format!(
+                "{} is enabled but auto_review.openai_api_key is not set",
+                name
+            ));
+        }
+    }
+
+    let tls = &settings.tls;
+    if tls.cert_path.is_some() != tls.key_path.is_some() {
+        report.error("tls needs both cert_path and key_path");
+    }
+    for path in [&tls.cert_path, &tls.key_path].into_iter().flatten() {
+        if !std::path::Path::new(path).is_file() {
+            report.error(format!("tls file {} does not exist", path));
+        }
+    }
+
+    let oidc = &settings.oidc;
+    if !oidc.issuer_url.is_empty() && !oidc.enabled() {
+        report.error("oidc.issuer_url is set but client_id or session_secret is missing");
+    }
+    if let Err(e) = crate::logging::filter(&settings.log) {
+        report.error(e);
+    }
+    if settings.cors.allowed_origins.iter().any(|origin| origin == "*") {
+        report.warn("cors.allowed_origins allows any origin");
+    }
+    if settings.agent_tokens.secret.is_empty() {
+        report.warn("agent_tokens.secret is empty; agent and share tokens die with the process");
+    }
+    let share = &settings.share_tokens;
+    if share.default_ttl_secs > share.max_ttl_secs {
+        report.warn("share_tokens.default_ttl_secs is above max_ttl_secs and gets capped");
+    }
+    if settings.web.enabled
+        && let Some(dir) = &settings.web.dir
+        && !std::path::Path::new(dir).join("index.html").is_file()
+    {
+        report.error(format!("web.dir {} has no index.html", dir));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_unknown_keys() {
+        let files = json!({
+            "basic": {"host": "0.0.0.0", "port": 8201},
+            "application": {
+                "user_token": "t",
+                "relay_tokn": "t",
+                "auto_review": {"enabled": false, "modle": "x"},
+                "permissions": {"relay_fallback": {"action": "deny"}},
+                "cors": {"allowed_origins": ["*"]},
+            },
+            "applicaton": {},
+        });
+        assert_eq!(
+            unknown_keys(&files),
+            vec![
+                "applicaton",
+                "application.auto_review.modle",
+                "application.relay_tokn",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_settings() {
+        let settings = Settings {
+            database_url: "postgres://localhost/todoki".to_string(),
+            user_token: "user-secret".to_string(),
+            relay_token: "relay-secret".to_string(),
+            ..Default::default()
+        };
+        let mut report = ConfigReport::default();
+        check_settings(&settings, &mut report);
+        assert!(report.is_ok(), "{:?}", report.errors);
+
+        let mut insecure = settings.clone();
+        insecure.relay_token = String::new();
+        insecure.auto_review.enabled = true;
+        let mut report = ConfigReport::default();
+        check_settings(&insecure, &mut report);
+        assert_eq!(report.errors.len(), 2);
+        assert!(report.errors[0].starts_with("relay_token is empty"));
+        assert!(report.errors[1].starts_with("auto_review is enabled"));
+    }
+}
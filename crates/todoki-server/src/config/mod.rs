@@ -1,7 +1,16 @@
-use config::{Config, ConfigError, Environment, File};
+use config::builder::DefaultState;
+use config::{Config, ConfigBuilder, ConfigError, Environment, File};
 use gotcha::ConfigWrapper;
 use serde::{Deserialize, Serialize};
 use std::env;
+use todoki_protocol::PermissionFallback;
+
+use crate::event_bus::batch::BatchSettings;
+use crate::logging::LogFormat;
+use crate::models::NotificationKind;
+
+pub mod check;
+pub mod reload;
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Settings {
@@ -10,23 +19,1141 @@ pub struct Settings {
     /// Token for relay authentication (can be same as user_token or separate)
     #[serde(default)]
     pub relay_token: String,
+    /// Connection pool tuning and optional read replica
+    #[serde(default)]
+    pub database: DatabaseSettings,
+    /// Batching of event inserts
+    #[serde(default)]
+    pub events: EventSettings,
+    /// Inbound email-to-task integration
+    #[serde(default)]
+    pub email: EmailSettings,
+    /// Merge request webhooks from GitLab
+    #[serde(default)]
+    pub gitlab: ForgeWebhookSettings,
+    /// Pull request webhooks from Gitea and Forgejo
+    #[serde(default)]
+    pub gitea: ForgeWebhookSettings,
+    /// Telegram bot for task capture and permission responses
+    #[serde(default)]
+    pub telegram: TelegramSettings,
+    /// Daily digest of agent activity
+    #[serde(default)]
+    pub digest: DigestSettings,
+    /// CI status of branches agents worked on
+    #[serde(default)]
+    pub ci: CiSettings,
+    /// Jira / Linear issue sync for projects that turn it on
+    #[serde(default)]
+    pub issue_sync: IssueSyncSettings,
+    /// Markdown vault export of tasks, comments, summaries and artifacts
+    #[serde(default)]
+    pub vault: VaultSettings,
+    /// OpenAI-compatible model used to review permission requests
+    #[serde(default)]
+    pub auto_review: AutoReviewSettings,
+    /// LLM-generated summaries of agent sessions
+    #[serde(default)]
+    pub session_summary: SessionSummarySettings,
+    /// LLM triage of newly created tasks
+    #[serde(default)]
+    pub triage: TriageSettings,
+    /// Caps on concurrently running agent sessions
+    #[serde(default)]
+    pub concurrency: ConcurrencySettings,
+    /// Default per-project quotas
+    #[serde(default)]
+    pub quotas: QuotaSettings,
+    /// Picking one agent for events about unassigned tasks
+    #[serde(default)]
+    pub assignment: AssignmentSettings,
+    /// Flagging new tasks that look like open ones
+    #[serde(default)]
+    pub dedup: DedupSettings,
+    /// Task snooze wake-up notifications
+    #[serde(default)]
+    pub snooze: SnoozeSettings,
+    /// Reactions to failed agent liveness probes
+    #[serde(default)]
+    pub health: HealthSettings,
+    /// How far back `POST /api/undo` reaches
+    #[serde(default)]
+    pub undo: UndoSettings,
+    /// Origins allowed to call the API from a browser
+    #[serde(default)]
+    pub cors: CorsSettings,
+    /// Certificate for serving HTTPS directly
+    #[serde(default)]
+    pub tls: TlsSettings,
+    /// Serving the web UI from this server
+    #[serde(default)]
+    pub web: WebSettings,
+    /// Reverse proxies trusted to report the client address
+    #[serde(default)]
+    pub proxy: ProxySettings,
+    /// Login through an OpenID Connect identity provider
+    #[serde(default)]
+    pub oidc: OidcSettings,
+    /// Scoped API tokens handed to spawned agents
+    #[serde(default)]
+    pub agent_tokens: AgentTokenSettings,
+    /// Read-only links to watch a single agent session
+    #[serde(default)]
+    pub share_tokens: ShareTokenSettings,
+    /// Lifetime of unanswered permission requests
+    #[serde(default)]
+    pub permissions: PermissionSettings,
+    /// Events that become in-app notifications
+    #[serde(default)]
+    pub notifications: NotificationSettings,
+    /// Log output
+    #[serde(default)]
+    pub log: LogSettings,
+    /// Watching the config files for changes
+    #[serde(default)]
+    pub reload: ReloadSettings,
+}
+
+/// Log settings
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LogSettings {
+    /// Filter directives like `RUST_LOG`, e.g. "info,todoki::relay=debug"
+    /// (unset = `RUST_LOG` with everything at debug)
+    #[serde(default)]
+    pub level: Option<String>,
+    /// `text` or `json` (one object per line); only read at startup
+    #[serde(default)]
+    pub format: LogFormat,
+    /// Per-message debug logs of WebSocket connections are written for the
+    /// first message and then one in every this many (1 = all)
+    #[serde(default = "default_log_sample_every")]
+    pub sample_every: u64,
+}
+
+fn default_log_sample_every() -> u64 {
+    100
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            level: None,
+            format: LogFormat::default(),
+            sample_every: default_log_sample_every(),
+        }
+    }
+}
+
+/// Configuration reload settings; a reload is also triggered by SIGHUP
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReloadSettings {
+    /// Check the config files for changes this often (0 = only on SIGHUP)
+    #[serde(default = "default_reload_watch_interval_secs")]
+    pub watch_interval_secs: u64,
+}
+
+fn default_reload_watch_interval_secs() -> u64 {
+    5
+}
+
+impl Default for ReloadSettings {
+    fn default() -> Self {
+        Self {
+            watch_interval_secs: default_reload_watch_interval_secs(),
+        }
+    }
+}
+
+/// Permission request settings
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PermissionSettings {
+    /// Cancel requests nobody answered within this many seconds
+    #[serde(default = "default_permission_expire_after_secs")]
+    pub expire_after_secs: u64,
+    /// Fallback every relay must apply when the server does not answer in
+    /// time (unset = each relay uses its own configuration)
+    #[serde(default)]
+    pub relay_fallback: Option<PermissionFallback>,
+    /// Tool calls containing any of these (case-insensitive) need two
+    /// distinct human approvals
+    #[serde(default)]
+    pub two_person_patterns: Vec<String>,
+}
+
+fn default_permission_expire_after_secs() -> u64 {
+    300
+}
+
+impl Default for PermissionSettings {
+    fn default() -> Self {
+        Self {
+            expire_after_secs: default_permission_expire_after_secs(),
+            relay_fallback: None,
+            two_person_patterns: Vec::new(),
+        }
+    }
+}
+
+/// In-app notification settings; users choose among these kinds in their
+/// preferences
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotificationSettings {
+    /// Kinds of notifications generated at all
+    #[serde(default = "default_notification_kinds")]
+    pub kinds: Vec<NotificationKind>,
+}
+
+fn default_notification_kinds() -> Vec<NotificationKind> {
+    NotificationKind::ALL.to_vec()
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            kinds: default_notification_kinds(),
+        }
+    }
+}
+
+/// Agent token settings
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentTokenSettings {
+    /// Key signing agent tokens (empty = random per process, so tokens do
+    /// not survive a restart or work against another instance)
+    #[serde(default)]
+    pub secret: String,
+    /// Lifetime of an agent token (seconds)
+    #[serde(default = "default_agent_token_ttl_secs")]
+    pub ttl_secs: u64,
+    /// Event kinds agents may emit; a trailing `*` matches any suffix
+    #[serde(default = "default_agent_event_kinds")]
+    pub allowed_event_kinds: Vec<String>,
+}
+
+fn default_agent_token_ttl_secs() -> u64 {
+    6 * 60 * 60
+}
+
+fn default_agent_event_kinds() -> Vec<String> {
+    [
+        "agent.output",
+        "agent.error",
+        "agent.requirement_analyzed",
+        "agent.business_context_ready",
+        "agent.code_review_requested",
+        "agent.qa_test_passed",
+        "agent.qa_test_failed",
+        "agent.task_comment",
+        "agent.subtask_done",
+        "agent.followup_task",
+        "artifact.created",
+    ]
+    .iter()
+    .map(|kind| kind.to_string())
+    .collect()
+}
+
+impl Default for AgentTokenSettings {
+    fn default() -> Self {
+        Self {
+            secret: String::new(),
+            ttl_secs: default_agent_token_ttl_secs(),
+            allowed_event_kinds: default_agent_event_kinds(),
+        }
+    }
+}
+
+/// Share token settings; tokens are signed with `agent_tokens.secret`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShareTokenSettings {
+    /// Lifetime of a share token when the request does not give one (seconds)
+    #[serde(default = "default_share_token_ttl_secs")]
+    pub default_ttl_secs: u64,
+    /// Longest lifetime a share token may be given (seconds)
+    #[serde(default = "default_share_token_max_ttl_secs")]
+    pub max_ttl_secs: u64,
+}
+
+fn default_share_token_ttl_secs() -> u64 {
+    60 * 60
+}
+
+fn default_share_token_max_ttl_secs() -> u64 {
+    24 * 60 * 60
+}
+
+impl Default for ShareTokenSettings {
+    fn default() -> Self {
+        Self {
+            default_ttl_secs: default_share_token_ttl_secs(),
+            max_ttl_secs: default_share_token_max_ttl_secs(),
+        }
+    }
+}
+
+/// OIDC login settings; sessions it starts are signed with `session_secret`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OidcSettings {
+    /// Issuer URL, e.g. "https://accounts.google.com" (empty = disabled)
+    #[serde(default)]
+    pub issuer_url: String,
+    #[serde(default)]
+    pub client_id: String,
+    #[serde(default)]
+    pub client_secret: String,
+    /// Must match the redirect URI registered with the provider, e.g.
+    /// "https://todoki.example.com/api/auth/oidc/callback"
+    #[serde(default)]
+    pub redirect_url: String,
+    #[serde(default = "default_oidc_scopes")]
+    pub scopes: Vec<String>,
+    /// Accounts allowed to log in by email (empty with `allowed_domains`
+    /// also empty = any account of the provider)
+    #[serde(default)]
+    pub allowed_emails: Vec<String>,
+    /// Email domains allowed to log in, e.g. "example.com"
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+    /// Key signing todoki session tokens; keep it long and random
+    #[serde(default)]
+    pub session_secret: String,
+    /// Lifetime of a session token (seconds)
+    #[serde(default = "default_oidc_session_ttl_secs")]
+    pub session_ttl_secs: u64,
+    /// Web UI address to return to; the session token is appended as
+    /// `#token=...`
+    #[serde(default = "default_oidc_post_login_url")]
+    pub post_login_url: String,
+}
+
+fn default_oidc_scopes() -> Vec<String> {
+    vec!["openid".to_string(), "email".to_string(), "profile".to_string()]
+}
+
+fn default_oidc_session_ttl_secs() -> u64 {
+    12 * 60 * 60
+}
+
+fn default_oidc_post_login_url() -> String {
+    "/".to_string()
+}
+
+impl Default for OidcSettings {
+    fn default() -> Self {
+        Self {
+            issuer_url: String::new(),
+            client_id: String::new(),
+            client_secret: String::new(),
+            redirect_url: String::new(),
+            scopes: default_oidc_scopes(),
+            allowed_emails: Vec::new(),
+            allowed_domains: Vec::new(),
+            session_secret: String::new(),
+            session_ttl_secs: default_oidc_session_ttl_secs(),
+            post_login_url: default_oidc_post_login_url(),
+        }
+    }
+}
+
+impl OidcSettings {
+    pub fn enabled(&self) -> bool {
+        !self.issuer_url.is_empty() && !self.client_id.is_empty() && !self.session_secret.is_empty()
+    }
+
+    /// Whether an account with this (verified) email may log in
+    pub fn allows(&self, email: Option<&str>) -> bool {
+        if self.allowed_emails.is_empty() && self.allowed_domains.is_empty() {
+            return true;
+        }
+        let Some(email) = email.map(str::to_ascii_lowercase) else {
+            return false;
+        };
+        let domain = email.rsplit_once('@').map(|(_, domain)| domain);
+        self.allowed_emails.iter().any(|e| e.eq_ignore_ascii_case(&email))
+            || self
+                .allowed_domains
+                .iter()
+                .any(|d| Some(d.to_ascii_lowercase().as_str()) == domain)
+    }
+}
+
+/// CORS settings
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CorsSettings {
+    /// Allowed origins, e.g. "https://todoki.example.com" ("*" = any
+    /// origin, empty = same-origin only)
+    #[serde(default = "default_cors_allowed_origins")]
+    pub allowed_origins: Vec<String>,
+    /// How long browsers may cache a preflight response (seconds)
+    #[serde(default = "default_cors_max_age_secs")]
+    pub max_age_secs: u64,
+}
+
+fn default_cors_allowed_origins() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
+fn default_cors_max_age_secs() -> u64 {
+    3600
+}
+
+impl Default for CorsSettings {
+    fn default() -> Self {
+        Self {
+            allowed_origins: default_cors_allowed_origins(),
+            max_age_secs: default_cors_max_age_secs(),
+        }
+    }
+}
+
+/// TLS settings; HTTPS is served directly when both paths are set
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TlsSettings {
+    /// PEM certificate chain
+    #[serde(default)]
+    pub cert_path: Option<String>,
+    /// PEM private key (PKCS#8)
+    #[serde(default)]
+    pub key_path: Option<String>,
+}
+
+impl TlsSettings {
+    pub fn enabled(&self) -> bool {
+        self.cert_path.is_some() && self.key_path.is_some()
+    }
+}
+
+/// Web UI settings
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebSettings {
+    /// Serve the web UI under `/` (off when it is hosted elsewhere)
+    #[serde(default = "default_web_enabled")]
+    pub enabled: bool,
+    /// Serve the UI from this directory instead of the bundle embedded at
+    /// build time, e.g. `web/dist`
+    #[serde(default)]
+    pub dir: Option<String>,
+}
+
+fn default_web_enabled() -> bool {
+    true
+}
+
+impl Default for WebSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_web_enabled(),
+            dir: None,
+        }
+    }
+}
+
+/// Reverse proxy settings
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProxySettings {
+    /// Proxy addresses or CIDR blocks whose forwarding header is believed
+    /// (empty = the header is ignored)
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+    /// Header carrying the forwarded-for chain
+    #[serde(default = "default_client_ip_header")]
+    pub client_ip_header: String,
+}
+
+fn default_client_ip_header() -> String {
+    "x-forwarded-for".to_string()
+}
+
+impl Default for ProxySettings {
+    fn default() -> Self {
+        Self {
+            trusted_proxies: Vec::new(),
+            client_ip_header: default_client_ip_header(),
+        }
+    }
+}
+
+/// Undo log settings
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UndoSettings {
+    /// Mutations older than this can no longer be undone
+    #[serde(default = "default_undo_window_secs")]
+    pub window_secs: u64,
+    /// Most mutations a single undo request may revert
+    #[serde(default = "default_undo_max_count")]
+    pub max_count: usize,
+}
+
+fn default_undo_window_secs() -> u64 {
+    600
+}
+
+fn default_undo_max_count() -> usize {
+    20
+}
+
+impl Default for UndoSettings {
+    fn default() -> Self {
+        Self {
+            window_secs: default_undo_window_secs(),
+            max_count: default_undo_max_count(),
+        }
+    }
+}
+
+/// Agent health settings; probes themselves run on the relays
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HealthSettings {
+    /// Restart an agent whose session failed this many probes in a row
+    /// (unset = never restart)
+    #[serde(default)]
+    pub restart_after_failures: Option<u32>,
+}
+
+/// Snooze settings; snoozed tasks created with `notify` are announced here
+/// when they wake up
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SnoozeSettings {
+    #[serde(default)]
+    pub notifiers: Vec<NotifierConfig>,
+}
+
+/// Concurrent session caps; spawns over a cap wait in the spawn queue
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConcurrencySettings {
+    /// Sessions a single relay may run at once
+    #[serde(default = "default_max_sessions_per_relay")]
+    pub max_sessions_per_relay: usize,
+    /// Sessions a single project may run at once (unset = unlimited)
+    #[serde(default)]
+    pub max_sessions_per_project: Option<usize>,
+    /// Sessions across all relays (unset = unlimited)
+    #[serde(default)]
+    pub max_sessions_global: Option<usize>,
+}
+
+fn default_max_sessions_per_relay() -> usize {
+    1
+}
+
+impl Default for ConcurrencySettings {
+    fn default() -> Self {
+        Self {
+            max_sessions_per_relay: default_max_sessions_per_relay(),
+            max_sessions_per_project: None,
+            max_sessions_global: None,
+        }
+    }
+}
+
+/// Auto-assignment settings
+///
+/// When enabled, an event about a task without an agent starts only the
+/// best-placed of the auto-trigger agents it would trigger, and the task is
+/// assigned to that agent.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AssignmentSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Days of session history the success rate is computed over
+    #[serde(default = "default_assignment_history_days")]
+    pub history_days: u32,
+}
+
+fn default_assignment_history_days() -> u32 {
+    30
+}
+
+impl Default for AssignmentSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            history_days: default_assignment_history_days(),
+        }
+    }
+}
+
+/// Duplicate detection on task creation
+///
+/// A new task's title is compared with the titles of recent open tasks of
+/// its project; close matches are suggested as possible duplicates.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DedupSettings {
+    #[serde(default = "default_dedup_enabled")]
+    pub enabled: bool,
+    /// Trigram similarity (0-1) a title needs to be suggested
+    #[serde(default = "default_dedup_threshold")]
+    pub threshold: f64,
+    /// Only tasks created in this many days are compared
+    #[serde(default = "default_dedup_lookback_days")]
+    pub lookback_days: u32,
+    /// Most suggestions per new task
+    #[serde(default = "default_dedup_max_suggestions")]
+    pub max_suggestions: usize,
+}
+
+fn default_dedup_enabled() -> bool {
+    true
+}
+
+fn default_dedup_threshold() -> f64 {
+    0.5
+}
+
+fn default_dedup_lookback_days() -> u32 {
+    90
+}
+
+fn default_dedup_max_suggestions() -> usize {
+    3
+}
+
+impl Default for DedupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_dedup_enabled(),
+            threshold: default_dedup_threshold(),
+            lookback_days: default_dedup_lookback_days(),
+            max_suggestions: default_dedup_max_suggestions(),
+        }
+    }
+}
+
+/// Per-project quota defaults; unset means unlimited. Projects may override
+/// each with `PUT /api/projects/:project_id/quotas`. The concurrent session
+/// quota defaults to `concurrency.max_sessions_per_project`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct QuotaSettings {
+    /// Events emitted through the API per rolling hour
+    #[serde(default)]
+    pub events_per_hour: Option<u64>,
+    /// Bytes of artifact data, email attachments included
+    #[serde(default)]
+    pub storage_bytes: Option<u64>,
+    /// Permission auto-reviews per calendar month (UTC)
+    #[serde(default)]
+    pub ai_reviews_per_month: Option<u64>,
+}
+
+/// Task triage settings; the OpenAI client config comes from `auto_review`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TriageSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Model override (empty = `auto_review.model`)
+    #[serde(default)]
+    pub model: String,
+    /// Apply the suggested project/priority and create proposed subtasks
+    /// instead of only commenting
+    #[serde(default)]
+    pub auto_apply: bool,
+}
+
+/// Permission auto-review settings (also the shared OpenAI client config)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AutoReviewSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub openai_api_key: String,
+    #[serde(default = "default_openai_model")]
+    pub model: String,
+    /// Override for OpenAI-compatible endpoints
+    #[serde(default)]
+    pub openai_base_url: Option<String>,
+    /// Request timeout (seconds)
+    #[serde(default = "default_openai_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Second model for permission reviews; requests are only answered
+    /// automatically when both models agree
+    #[serde(default)]
+    pub consensus_model: Option<String>,
+    /// Language reviewers reason in: a built-in prompt (`en`, `zh`) or any
+    /// other language name, which gets the English prompt told to answer in it
+    #[serde(default = "default_review_language")]
+    pub language: String,
+    /// Replaces the built-in reviewer instructions; the reply format is
+    /// still appended
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+}
+
+fn default_openai_model() -> String {
+    "gpt-4o-mini".to_string()
+}
+
+fn default_openai_timeout_secs() -> u64 {
+    30
+}
+
+fn default_review_language() -> String {
+    "en".to_string()
+}
+
+impl Default for AutoReviewSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            openai_api_key: String::new(),
+            model: default_openai_model(),
+            openai_base_url: None,
+            timeout_secs: default_openai_timeout_secs(),
+            consensus_model: None,
+            language: default_review_language(),
+            system_prompt: None,
+        }
+    }
+}
+
+/// Session summary settings; the OpenAI client config comes from `auto_review`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionSummarySettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Model override (empty = `auto_review.model`)
+    #[serde(default)]
+    pub model: String,
+    /// Only the most recent part of the timeline is sent to the model (characters)
+    #[serde(default = "default_summary_max_timeline_chars")]
+    pub max_timeline_chars: usize,
+}
+
+fn default_summary_max_timeline_chars() -> usize {
+    24_000
+}
+
+impl Default for SessionSummarySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model: String::new(),
+            max_timeline_chars: default_summary_max_timeline_chars(),
+        }
+    }
+}
+
+/// Daily digest settings
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DigestSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Local time of day the digest is generated ("HH:MM")
+    #[serde(default = "default_digest_time")]
+    pub time: String,
+    /// Offset of the digest's local time from UTC (hours)
+    #[serde(default = "default_digest_utc_offset_hours")]
+    pub utc_offset_hours: i32,
+    /// Project that holds the digest tasks and artifacts (created if missing)
+    #[serde(default = "default_digest_project")]
+    pub project: String,
+    /// Where the rendered digest is delivered besides the artifact
+    #[serde(default)]
+    pub notifiers: Vec<NotifierConfig>,
+}
+
+/// A delivery channel for digests and snooze wake-ups
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NotifierConfig {
+    /// Send to every allowed chat of the configured Telegram bot
+    Telegram,
+    /// POST `{"title", "markdown"}` as JSON to a URL
+    Webhook { url: String },
+}
+
+fn default_digest_time() -> String {
+    "18:00".to_string()
+}
+
+fn default_digest_utc_offset_hours() -> i32 {
+    8
+}
+
+fn default_digest_project() -> String {
+    "Digests".to_string()
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            time: default_digest_time(),
+            utc_offset_hours: default_digest_utc_offset_hours(),
+            project: default_digest_project(),
+            notifiers: Vec::new(),
+        }
+    }
+}
+
+/// CI tracking settings; `branch` artifacts reported by relays are checked
+/// against GitHub's check runs until their checks finish
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CiSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Token for the GitHub API (empty = anonymous, public repositories only)
+    #[serde(default)]
+    pub github_token: String,
+    #[serde(default = "default_ci_github_api_url")]
+    pub github_api_url: String,
+    /// How often branches with unfinished checks are polled (seconds)
+    #[serde(default = "default_ci_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+    /// Stop polling branches reported longer ago than this (hours)
+    #[serde(default = "default_ci_max_age_hours")]
+    pub max_age_hours: i64,
+    /// Refuse handoffs to the QA role while a branch of the task is not green
+    #[serde(default = "default_ci_gate_qa")]
+    pub gate_qa: bool,
+}
+
+fn default_ci_github_api_url() -> String {
+    "https://api.github.com".to_string()
+}
+
+fn default_ci_poll_interval_secs() -> u64 {
+    60
+}
+
+fn default_ci_max_age_hours() -> i64 {
+    72
+}
+
+fn default_ci_gate_qa() -> bool {
+    true
+}
+
+impl Default for CiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            github_token: String::new(),
+            github_api_url: default_ci_github_api_url(),
+            poll_interval_secs: default_ci_poll_interval_secs(),
+            max_age_hours: default_ci_max_age_hours(),
+            gate_qa: default_ci_gate_qa(),
+        }
+    }
+}
+
+/// Issue tracker sync settings; projects choose the tracker and where
+/// their issues go in their `issue_sync`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IssueSyncSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Jira site, e.g. `https://example.atlassian.net`
+    #[serde(default)]
+    pub jira_url: String,
+    /// Jira account the API token belongs to
+    #[serde(default)]
+    pub jira_email: String,
+    #[serde(default)]
+    pub jira_api_token: String,
+    /// Shared secret Jira webhooks pass as `?secret=` (empty = webhook disabled)
+    #[serde(default)]
+    pub jira_webhook_secret: String,
+    /// Linear personal API key
+    #[serde(default)]
+    pub linear_api_key: String,
+    #[serde(default = "default_linear_api_url")]
+    pub linear_api_url: String,
+    /// Secret Linear signs webhooks with (empty = webhook disabled)
+    #[serde(default)]
+    pub linear_webhook_secret: String,
+    /// How often synced issues are polled for changes (seconds, 0 = webhooks only)
+    #[serde(default = "default_issue_sync_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+}
+
+fn default_linear_api_url() -> String {
+    "https://api.linear.app/graphql".to_string()
+}
+
+fn default_issue_sync_poll_interval_secs() -> u64 {
+    300
+}
+
+impl Default for IssueSyncSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            jira_url: String::new(),
+            jira_email: String::new(),
+            jira_api_token: String::new(),
+            jira_webhook_secret: String::new(),
+            linear_api_key: String::new(),
+            linear_api_url: default_linear_api_url(),
+            linear_webhook_secret: String::new(),
+            poll_interval_secs: default_issue_sync_poll_interval_secs(),
+        }
+    }
+}
+
+/// Markdown vault export settings; exports can also be started with
+/// `POST /api/export/vault` whether or not the schedule is enabled
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VaultSettings {
+    /// Export on a schedule
+    #[serde(default)]
+    pub enabled: bool,
+    /// Folder the vault is written to (created if missing)
+    #[serde(default = "default_vault_dir")]
+    pub dir: String,
+    /// Hours between scheduled exports
+    #[serde(default = "default_vault_interval_hours")]
+    pub interval_hours: u64,
+    /// Also export archived projects and tasks
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+fn default_vault_dir() -> String {
+    "./vault".to_string()
+}
+
+fn default_vault_interval_hours() -> u64 {
+    24
+}
+
+impl Default for VaultSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: default_vault_dir(),
+            interval_hours: default_vault_interval_hours(),
+            include_archived: false,
+        }
+    }
+}
+
+/// Telegram bot settings
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TelegramSettings {
+    /// Bot token from @BotFather (empty = bot disabled)
+    #[serde(default)]
+    pub bot_token: String,
+    /// Chat IDs allowed to talk to the bot; messages from other chats are ignored
+    #[serde(default)]
+    pub allowed_chat_ids: Vec<i64>,
+    /// Project that receives tasks created from Telegram (created if missing)
+    #[serde(default = "default_telegram_project")]
+    pub project: String,
+    /// Long-poll timeout for getUpdates (seconds)
+    #[serde(default = "default_telegram_poll_timeout_secs")]
+    pub poll_timeout_secs: u64,
+}
+
+fn default_telegram_project() -> String {
+    "Inbox".to_string()
+}
+
+fn default_telegram_poll_timeout_secs() -> u64 {
+    30
+}
+
+impl Default for TelegramSettings {
+    fn default() -> Self {
+        Self {
+            bot_token: String::new(),
+            allowed_chat_ids: Vec::new(),
+            project: default_telegram_project(),
+            poll_timeout_secs: default_telegram_poll_timeout_secs(),
+        }
+    }
+}
+
+impl TelegramSettings {
+    pub fn enabled(&self) -> bool {
+        !self.bot_token.is_empty() && !self.allowed_chat_ids.is_empty()
+    }
+}
+
+/// Inbound email webhook settings
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmailSettings {
+    /// Shared secret the mail provider passes as `?secret=` (empty = webhook disabled)
+    #[serde(default)]
+    pub webhook_secret: String,
+    /// Project that receives email tasks (created if missing)
+    #[serde(default = "default_email_project")]
+    pub project: String,
+    /// Attachments larger than this are dropped (bytes)
+    #[serde(default = "default_max_attachment_bytes")]
+    pub max_attachment_bytes: usize,
+}
+
+fn default_email_project() -> String {
+    "Inbox".to_string()
+}
+
+fn default_max_attachment_bytes() -> usize {
+    10 * 1024 * 1024
+}
+
+impl Default for EmailSettings {
+    fn default() -> Self {
+        Self {
+            webhook_secret: String::new(),
+            project: default_email_project(),
+            max_attachment_bytes: default_max_attachment_bytes(),
+        }
+    }
+}
+
+/// Inbound merge request webhook settings (GitLab, Gitea)
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ForgeWebhookSettings {
+    /// Secret set on the webhook; GitLab sends it as a token, Gitea signs
+    /// payloads with it (empty = webhook disabled)
+    #[serde(default)]
+    pub webhook_secret: String,
+}
+
+/// Event store settings
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventSettings {
+    /// Most events stored by one INSERT (1 = one INSERT per event)
+    #[serde(default = "default_event_max_batch")]
+    pub max_batch: usize,
+    /// Longest an event waits for others to share its INSERT (milliseconds)
+    #[serde(default = "default_event_max_latency_ms")]
+    pub max_latency_ms: u64,
+}
+
+fn default_event_max_batch() -> usize {
+    256
+}
+
+fn default_event_max_latency_ms() -> u64 {
+    2
+}
+
+impl Default for EventSettings {
+    fn default() -> Self {
+        Self {
+            max_batch: default_event_max_batch(),
+            max_latency_ms: default_event_max_latency_ms(),
+        }
+    }
+}
+
+impl EventSettings {
+    pub fn batch(&self) -> BatchSettings {
+        BatchSettings {
+            max_batch: self.max_batch,
+            max_latency: std::time::Duration::from_millis(self.max_latency_ms),
+        }
+    }
+}
+
+/// Database connection pool settings
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DatabaseSettings {
+    /// Maximum number of pooled connections per database
+    #[serde(default = "default_max_connections")]
+    pub max_connections: usize,
+    /// Timeout for establishing a new connection (seconds)
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// Server-side statement timeout (seconds, 0 = disabled)
+    #[serde(default)]
+    pub statement_timeout_secs: u64,
+    /// Cache prepared statements per connection
+    #[serde(default = "default_statement_cache")]
+    pub statement_cache: bool,
+    /// Optional read replica URL for heavy read queries (events, reports)
+    #[serde(default)]
+    pub replica_url: Option<String>,
+    /// Apply pending migrations at startup; when off, the server refuses to
+    /// start until they are applied with `todoki --migrate-only`
+    #[serde(default = "default_migrate_on_startup")]
+    pub migrate_on_startup: bool,
+    /// How long to wait for another instance that is migrating (seconds)
+    #[serde(default = "default_migration_lock_timeout_secs")]
+    pub migration_lock_timeout_secs: u64,
+}
+
+fn default_max_connections() -> usize {
+    16
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    5
+}
+
+fn default_statement_cache() -> bool {
+    true
+}
+
+fn default_migrate_on_startup() -> bool {
+    true
+}
+
+fn default_migration_lock_timeout_secs() -> u64 {
+    300
+}
+
+impl Default for DatabaseSettings {
+    fn default() -> Self {
+        Self {
+            max_connections: default_max_connections(),
+            connect_timeout_secs: default_connect_timeout_secs(),
+            statement_timeout_secs: 0,
+            statement_cache: default_statement_cache(),
+            replica_url: None,
+            migrate_on_startup: default_migrate_on_startup(),
+            migration_lock_timeout_secs: default_migration_lock_timeout_secs(),
+        }
+    }
+}
+
+impl DatabaseSettings {
+    /// Append connection-level options (timeouts) to a postgres URL
+    pub fn apply_to_url(&self, url: &str) -> String {
+        let mut params = vec![format!("connect_timeout={}", self.connect_timeout_secs)];
+        if self.statement_timeout_secs > 0 {
+            params.push(format!(
+                "options=-c%20statement_timeout%3D{}s",
+                self.statement_timeout_secs
+            ));
+        }
+        let separator = if url.contains('?') { '&' } else { '?' };
+        format!("{}{}{}", url, separator, params.join("&"))
+    }
 }
 
 impl Settings {
     pub fn new() -> Result<ConfigWrapper<Self>, ConfigError> {
-        let run_mode = env::var("RUN_MODE").unwrap_or_else(|_| "development".into());
-
-        let s = Config::builder()
-            // Start with defaults
-            .add_source(File::with_name("config/default").required(false))
-            // Add environment-specific file
-            .add_source(File::with_name(&format!("config/{}", run_mode)).required(false))
-            // Add local configuration file (not tracked by git)
-            .add_source(File::with_name("config/local").required(false))
+        let s = Self::file_sources()
             // Add in settings from environment variables (with prefix TODOKI)
             .add_source(Environment::with_prefix("TODOKI").separator("_"))
             .build()?;
 
         s.try_deserialize()
     }
+
+    /// The config files merged, without environment variables
+    pub fn files() -> Result<Config, ConfigError> {
+        Self::file_sources().build()
+    }
+
+    fn file_sources() -> ConfigBuilder<DefaultState> {
+        Self::file_names()
+            .iter()
+            .fold(Config::builder(), |builder, name| {
+                builder.add_source(File::with_name(name).required(false))
+            })
+    }
+
+    /// Config files in the order they are merged: defaults, the
+    /// environment-specific file, then local overrides (not tracked by git)
+    pub fn file_names() -> Vec<String> {
+        let run_mode = env::var("RUN_MODE").unwrap_or_else(|_| "development".into());
+        vec![
+            "config/default".to_string(),
+            format!("config/{}", run_mode),
+            "config/local".to_string(),
+        ]
+    }
 }
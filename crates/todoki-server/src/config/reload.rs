@@ -0,0 +1,221 @@
+//! Reloading the configuration while the server runs
+//!
+//! On SIGHUP, or when polling every `reload.watch_interval_secs` notices a
+//! config file changed, the settings are loaded again. Changes to the
+//! reloadable sections are applied right away: `auto_review` (permission
+//! reviews), `notifications` (kinds generated) and `log` (the log filter).
+//! Changes to any other section are logged as needing a restart. A reload
+//! that changed anything emits `system.config_reloaded`.
+
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use serde_json::Value;
+use todoki_protocol::event_bus::{BuiltinEvent, SystemConfigReloadedData};
+
+use super::Settings;
+use crate::event_bus::{EventPublisher, EventScope};
+use crate::logging::LogControl;
+use crate::notification::NotificationCenter;
+use crate::permission::review::PermissionReviewer;
+
+/// Settings shared with request handlers; reloads update it in place
+pub type SharedSettings = Arc<RwLock<Settings>>;
+
+/// Sections applied without a restart
+pub const RELOADABLE: &[&str] = &["auto_review", "notifications", "log"];
+
+/// Extensions the `config` crate finds a config file by
+const CONFIG_EXTENSIONS: &[&str] = &["toml", "json", "yaml", "yml", "ini", "ron", "json5"];
+
+pub struct ConfigReloader {
+    current: Settings,
+    shared: SharedSettings,
+    reviewer: Arc<PermissionReviewer>,
+    notifications: Arc<NotificationCenter>,
+    log: LogControl,
+    publisher: Arc<EventPublisher>,
+}
+
+impl ConfigReloader {
+    pub fn new(
+        shared: SharedSettings,
+        reviewer: Arc<PermissionReviewer>,
+        notifications: Arc<NotificationCenter>,
+        log: LogControl,
+        publisher: Arc<EventPublisher>,
+    ) -> Self {
+        let current = shared.read().unwrap_or_else(|e| e.into_inner()).clone();
+        Self {
+            current,
+            shared,
+            reviewer,
+            notifications,
+            log,
+            publisher,
+        }
+    }
+
+    pub async fn run(mut self) {
+        let watch_secs = self.current.reload.watch_interval_secs;
+        let mut watch = tokio::time::interval(Duration::from_secs(watch_secs.max(1)));
+        let mut files = file_times();
+        let mut hangup = hangup_signal();
+
+        loop {
+            tokio::select! {
+                Some(()) = recv_hangup(&mut hangup) => {
+                    tracing::info!("SIGHUP received, reloading configuration");
+                }
+                _ = watch.tick(), if watch_secs > 0 => {
+                    let now = file_times();
+                    if now == files {
+                        continue;
+                    }
+                    files = now;
+                    tracing::info!("config files changed, reloading configuration");
+                }
+                else => break,
+            }
+            self.reload().await;
+        }
+    }
+
+    async fn reload(&mut self) {
+        let mut next = match Settings::new() {
+            Ok(settings) => settings.application,
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to reload configuration");
+                return;
+            }
+        };
+        // An empty secret was replaced by a random one at startup
+        if next.agent_tokens.secret.is_empty() {
+            next.agent_tokens.secret = self.current.agent_tokens.secret.clone();
+        }
+
+        // The log format is fixed when the subscriber is installed
+        let mut format_changed = false;
+        if next.log.format != self.current.log.format {
+            next.log.format = self.current.log.format;
+            format_changed = true;
+        }
+
+        let (mut applied, mut restart_required) = changed_sections(&self.current, &next);
+        if format_changed {
+            restart_required.push("log.format".to_string());
+        }
+        if applied.is_empty() && restart_required.is_empty() {
+            return;
+        }
+
+        if applied.iter().any(|s| s == "log")
+            && let Err(e) = self.log.apply(&next.log)
+        {
+            tracing::warn!(error = %e, "log settings not reloaded");
+            next.log = self.current.log.clone();
+            applied.retain(|s| s != "log");
+        }
+        if applied.iter().any(|s| s == "auto_review") {
+            self.reviewer.reconfigure(&next.auto_review);
+        }
+        if applied.iter().any(|s| s == "notifications") {
+            self.notifications.reconfigure(&next.notifications);
+        }
+
+        // Only reloadable sections change; the rest waits for a restart
+        let mut current = self.current.clone();
+        current.auto_review = next.auto_review;
+        current.notifications = next.notifications;
+        current.log = next.log;
+        *self.shared.write().unwrap_or_else(|e| e.into_inner()) = current.clone();
+        self.current = current;
+
+        if !restart_required.is_empty() {
+            tracing::warn!(sections = ?restart_required, "configuration changes need a restart");
+        }
+        tracing::info!(sections = ?applied, "configuration reloaded");
+        let event = BuiltinEvent::SystemConfigReloaded(SystemConfigReloadedData {
+            applied,
+            restart_required,
+        });
+        if let Err(e) = self.publisher.emit_builtin(event, EventScope::system()).await {
+            tracing::warn!(error = %e, "failed to emit system.config_reloaded");
+        }
+    }
+}
+
+/// Top-level sections that differ between `old` and `new`: the reloadable
+/// ones, then those needing a restart
+pub fn changed_sections(old: &Settings, new: &Settings) -> (Vec<String>, Vec<String>) {
+    let (Ok(Value::Object(old)), Ok(Value::Object(new))) =
+        (serde_json::to_value(old), serde_json::to_value(new))
+    else {
+        return (Vec::new(), Vec::new());
+    };
+    new.iter()
+        .filter(|(key, value)| old.get(*key) != Some(*value))
+        .map(|(key, _)| key.clone())
+        .partition(|key| RELOADABLE.contains(&key.as_str()))
+}
+
+/// Modification times of the config files that exist
+fn file_times() -> Vec<(PathBuf, Option<SystemTime>)> {
+    Settings::file_names()
+        .iter()
+        .flat_map(|name| CONFIG_EXTENSIONS.iter().map(move |ext| format!("{}.{}", name, ext)))
+        .map(PathBuf::from)
+        .filter_map(|path| {
+            let metadata = std::fs::metadata(&path).ok()?;
+            Some((path, metadata.modified().ok()))
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+type Hangup = Option<tokio::signal::unix::Signal>;
+#[cfg(not(unix))]
+type Hangup = Option<()>;
+
+#[cfg(unix)]
+fn hangup_signal() -> Hangup {
+    use tokio::signal::unix::{signal, SignalKind};
+    signal(SignalKind::hangup())
+        .inspect_err(|e| tracing::warn!(error = %e, "cannot listen for SIGHUP"))
+        .ok()
+}
+
+#[cfg(not(unix))]
+fn hangup_signal() -> Hangup {
+    None
+}
+
+/// Wait for the next SIGHUP; pending forever when there is none to wait for
+async fn recv_hangup(hangup: &mut Hangup) -> Option<()> {
+    match hangup {
+        #[cfg(unix)]
+        Some(signal) => signal.recv().await,
+        _ => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_sections() {
+        let old = Settings::default();
+        let (applied, restart_required) = changed_sections(&old, &old.clone());
+        assert!(applied.is_empty() && restart_required.is_empty());
+
+        let mut new = old.clone();
+        new.auto_review.enabled = true;
+        new.log.level = Some("info".to_string());
+        new.relay_token = "rotated".to_string();
+        let (applied, restart_required) = changed_sections(&old, &new);
+        assert_eq!(applied, vec!["auto_review", "log"]);
+        assert_eq!(restart_required, vec!["relay_token"]);
+    }
+}
@@ -0,0 +1,135 @@
+//! Migration files and pre-flight checks run before migrating
+//!
+//! Applying them is left to conservator's migrator; this only works out
+//! which files are pending and whether the database is safe to migrate.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+pub const MIGRATIONS_DIR: &str = "./migrations";
+
+/// Table where the migrator records applied migrations
+pub const MIGRATIONS_TABLE: &str = "_sqlx_migrations";
+
+/// Key of the advisory lock held while migrating ("todoki" in ASCII)
+pub const MIGRATION_LOCK_KEY: i64 = 0x746f_646f_6b69;
+
+/// A migration file, e.g. `033_undo_log.sql`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationFile {
+    pub version: i64,
+    pub description: String,
+}
+
+/// Parse `<version>_<description>.sql`; other files are not migrations
+pub fn parse_migration_file_name(name: &str) -> Option<MigrationFile> {
+    let stem = name.strip_suffix(".sql")?;
+    let (version, description) = stem.split_once('_')?;
+    Some(MigrationFile {
+        version: version.parse().ok()?,
+        description: description.replace('_', " "),
+    })
+}
+
+/// Migration files in `dir`, oldest first
+pub fn list_migration_files(dir: impl AsRef<Path>) -> crate::Result<Vec<MigrationFile>> {
+    let dir = dir.as_ref();
+    let entries = std::fs::read_dir(dir).map_err(|e| {
+        crate::TodokiError::MigrationCheck(format!("cannot read {}: {}", dir.display(), e))
+    })?;
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| crate::TodokiError::MigrationCheck(e.to_string()))?;
+        if let Some(file) = entry
+            .file_name()
+            .to_str()
+            .and_then(parse_migration_file_name)
+        {
+            files.push(file);
+        }
+    }
+    files.sort_by_key(|f| f.version);
+
+    if let Some(pair) = files
+        .windows(2)
+        .find(|pair| pair[0].version == pair[1].version)
+    {
+        return Err(crate::TodokiError::MigrationCheck(format!(
+            "two migrations share version {}",
+            pair[0].version
+        )));
+    }
+    Ok(files)
+}
+
+/// Files not applied yet, after checking the database against them
+///
+/// `applied` holds the recorded versions and whether each succeeded. A
+/// failed migration has to be repaired by hand, and versions missing on
+/// disk mean this build is older than the schema; both refuse to migrate.
+pub fn pending_migrations(
+    files: &[MigrationFile],
+    applied: &[(i64, bool)],
+) -> crate::Result<Vec<MigrationFile>> {
+    let applied: HashMap<i64, bool> = applied.iter().copied().collect();
+
+    let mut versions: Vec<i64> = applied.keys().copied().collect();
+    versions.sort();
+    for version in versions {
+        if !applied[&version] {
+            return Err(crate::TodokiError::MigrationCheck(format!(
+                "migration {} failed previously; repair the database before migrating",
+                version
+            )));
+        }
+        if !files.iter().any(|f| f.version == version) {
+            return Err(crate::TodokiError::MigrationCheck(format!(
+                "database has migration {} which this build does not know; \
+                 is an older server starting against a newer schema?",
+                version
+            )));
+        }
+    }
+
+    Ok(files
+        .iter()
+        .filter(|f| !applied.contains_key(&f.version))
+        .cloned()
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(version: i64) -> MigrationFile {
+        MigrationFile {
+            version,
+            description: format!("migration {}", version),
+        }
+    }
+
+    #[test]
+    fn test_parse_migration_file_name() {
+        assert_eq!(
+            parse_migration_file_name("033_undo_log.sql"),
+            Some(MigrationFile {
+                version: 33,
+                description: "undo log".to_string(),
+            })
+        );
+        assert_eq!(parse_migration_file_name("README.md"), None);
+        assert_eq!(parse_migration_file_name("draft_undo_log.sql"), None);
+    }
+
+    #[test]
+    fn test_pending_migrations() {
+        let files = [file(1), file(2), file(3)];
+        let pending = pending_migrations(&files, &[(1, true), (2, true)]).unwrap();
+        assert_eq!(pending, vec![file(3)]);
+
+        assert!(pending_migrations(&files, &[(1, true), (2, false)]).is_err());
+        assert!(pending_migrations(&files, &[(1, true), (4, true)]).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+pub mod migrations;
 pub mod service;
 
-pub use service::DatabaseService;
\ No newline at end of file
+pub use service::DatabaseService;
@@ -1,46 +1,146 @@
 use crate::models::{
+    activity::{ActivityCursor, ActivityItem, ActivityKind},
     agent::{
-        Agent, AgentBriefResponse, AgentSession, AgentStatus, CreateAgent, CreateAgentSession,
-        SessionStatus,
+        Agent, AgentBriefResponse, AgentHealth, AgentRole, AgentSession, AgentStatus,
+        AgentTrackRecord, CreateAgent, CreateAgentSession, SessionStatus,
     },
     artifact::{Artifact, CreateArtifact},
-    project::{CreateProject, Project},
-    report::{ReportPeriod, ReportResponse},
+    backup::{BackupLine, RestoreBatch, BACKUP_FORMAT_VERSION, BACKUP_TABLES},
+    dead_letter::DeadLetter,
+    dedup::{find_duplicates, DuplicateSuggestion},
+    execution::{ExecutionPermission, ExecutionSpawnParams, TaskExecution, TaskExecutionHistory},
+    issue_sync::{IssueProvider, ProjectIssueSync, StatusCategory, TaskRemoteIssue},
+    notification::{CreateNotification, Notification, NotificationKind, NotificationPreferences},
+    project::{CreateProject, Project, ProjectCascade, ProjectReviewConfig, RelayRouting},
+    quota::{ProjectQuotas, QuotaLimits, QuotaMetric},
+    schedule::{CreateScheduledExecution, ExecutionSchedule, ScheduledExecution, TaskSnooze},
+    scoreboard::AgentStatCounts,
+    status_page::{ProjectSnapshot, StatusPage, StatusPageField, STATUS_PAGE_COMPLETED_LIMIT},
+    report::{
+        DigestActivity, DigestCompletedTask, DigestFailedSession, DigestPermissionDenial,
+        EstimateSample, EstimationReport, ReportPeriod, ReportResponse,
+    },
     task::{
         CreateTask, CreateTaskComment, CreateTaskEvent, Task, TaskComment, TaskEvent,
         TaskResponse, TaskStatus,
     },
+    task_context::{ContextWriter, TaskContextEntry},
+    template::{ProjectTemplateRef, PromptTemplate, PromptTemplateVersion},
+    time_entry::{AgentSessionTime, TaskTimeEntry, TimeSample, TimeSource},
+    undo::{UndoAction, UndoEntry},
+    view::{CreateSavedView, SavedView, ViewFilter, VIEW_TASK_LIMIT},
+    watch::{Watch, WatchTarget},
+    workflow::{StateTarget, Workflow},
 };
 use serde_json::Value;
-use chrono::Utc;
+use todoki_protocol::event_bus::{RelaySpawnRequestedData, ResourceUsage};
+use chrono::{DateTime, Utc};
+use crate::config::{DatabaseSettings, DedupSettings};
+use crate::db::migrations::{self, MigrationFile};
+use crate::event_bus::{outbox, Event};
+use crate::net::request_id;
 use conservator::{Creatable, Domain, Executor, Migrator, PooledConnection, SqlTypeWrapper};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 use uuid::Uuid;
 
+/// How often a waiting instance retries the migration lock
+const MIGRATION_LOCK_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
 /// Database service for managing all database operations
 pub struct DatabaseService {
     pool: Arc<PooledConnection>,
+    /// Optional read replica; falls back to the primary pool when unset
+    replica: Option<Arc<PooledConnection>>,
 }
 
 impl DatabaseService {
     /// Create a new database service
-    pub fn new(database_url: &str) -> crate::Result<Self> {
-        let pool =
-            PooledConnection::from_url(database_url).map_err(|e| crate::TodokiError::Database(e))?;
+    pub fn new(database_url: &str, settings: &DatabaseSettings) -> crate::Result<Self> {
+        let pool = Self::connect(database_url, settings)?;
+
+        let replica = match &settings.replica_url {
+            Some(url) if !url.is_empty() => {
+                tracing::info!("Read replica configured, routing heavy reads to replica");
+                Some(Arc::new(Self::connect(url, settings)?))
+            }
+            _ => None,
+        };
 
         Ok(Self {
             pool: Arc::new(pool),
+            replica,
         })
     }
 
+    fn connect(url: &str, settings: &DatabaseSettings) -> crate::Result<PooledConnection> {
+        PooledConnection::builder(&settings.apply_to_url(url))
+            .max_size(settings.max_connections)
+            .statement_cache(settings.statement_cache)
+            .build()
+            .map_err(|e| crate::TodokiError::Database(e))
+    }
+
     /// Get the underlying connection pool (for Event Bus)
     pub fn pool(&self) -> Arc<PooledConnection> {
         self.pool.clone()
     }
 
-    /// Run database migrations
-    pub async fn migrate(&self) -> crate::Result<()> {
-        let migrator = Migrator::from_path("./migrations")?;
+    /// Get the pool for read-heavy queries (replica if configured, else primary)
+    pub fn read_pool(&self) -> Arc<PooledConnection> {
+        self.replica.clone().unwrap_or_else(|| self.pool.clone())
+    }
+
+    /// Migrations on disk that the database has not applied yet
+    ///
+    /// Fails when the database is not safe to migrate (see
+    /// [`migrations::pending_migrations`]).
+    pub async fn pending_migrations(&self) -> crate::Result<Vec<MigrationFile>> {
+        let files = migrations::list_migration_files(migrations::MIGRATIONS_DIR)?;
+
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        // Nothing is recorded before the first migration creates the table
+        let row = conn
+            .query_one(
+                "SELECT to_regclass($1) IS NOT NULL AS present",
+                &[&migrations::MIGRATIONS_TABLE],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let applied: Vec<(i64, bool)> = if row.get("present") {
+            conn.query(
+                &format!(
+                    "SELECT version, success FROM {}",
+                    migrations::MIGRATIONS_TABLE
+                ),
+                &[],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?
+            .iter()
+            .map(|row| (row.get("version"), row.get("success")))
+            .collect()
+        } else {
+            Vec::new()
+        };
+
+        migrations::pending_migrations(&files, &applied)
+    }
+
+    /// Apply pending migrations, one server instance at a time
+    ///
+    /// An advisory lock is held while migrating so replicas starting together
+    /// do not race: the others wait up to `lock_timeout` and then find
+    /// nothing left to apply.
+    pub async fn migrate(&self, lock_timeout: Duration) -> crate::Result<()> {
+        let migrator = Migrator::from_path(migrations::MIGRATIONS_DIR)?;
 
         let mut conn = self
             .pool
@@ -48,8 +148,54 @@ impl DatabaseService {
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
-        migrator.run(&mut conn).await?;
+        let waiting_since = Instant::now();
+        loop {
+            let row = conn
+                .query_one(
+                    "SELECT pg_try_advisory_lock($1) AS locked",
+                    &[&migrations::MIGRATION_LOCK_KEY],
+                )
+                .await
+                .map_err(|e| crate::TodokiError::Database(e))?;
+            if row.get("locked") {
+                break;
+            }
+            if waiting_since.elapsed() >= lock_timeout {
+                return Err(crate::TodokiError::MigrationCheck(
+                    "timed out waiting for another instance to finish migrating".to_string(),
+                ));
+            }
+            tracing::info!("Waiting for another instance to finish migrating");
+            tokio::time::sleep(MIGRATION_LOCK_POLL_INTERVAL).await;
+        }
+
+        let result = match self.pending_migrations().await {
+            Ok(pending) if pending.is_empty() => {
+                tracing::info!("Database schema is up to date");
+                Ok(())
+            }
+            Ok(pending) => {
+                for migration in &pending {
+                    tracing::info!(
+                        version = migration.version,
+                        description = %migration.description,
+                        "Applying migration"
+                    );
+                }
+                migrator.run(&mut conn).await.map_err(Into::into)
+            }
+            Err(e) => Err(e),
+        };
+
+        // The lock belongs to the session, which outlives this pooled use
+        conn.execute(
+            "SELECT pg_advisory_unlock($1)",
+            &[&migrations::MIGRATION_LOCK_KEY],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
 
+        result?;
         tracing::info!("Migrations completed successfully");
         Ok(())
     }
@@ -58,8 +204,13 @@ impl DatabaseService {
     // Task operations
     // ========================================================================
 
-    /// Get tasks by status (not archived)
-    async fn get_tasks_by_status(&self, statuses: &[TaskStatus]) -> crate::Result<Vec<Task>> {
+    /// Get tasks by status (not archived) in board order, optionally hiding
+    /// snoozed tasks
+    async fn get_tasks_by_status(
+        &self,
+        statuses: &[TaskStatus],
+        exclude_snoozed: bool,
+    ) -> crate::Result<Vec<Task>> {
         let conn = self
             .pool
             .get()
@@ -74,13 +225,19 @@ impl DatabaseService {
 
         let query = format!(
             r#"
-            SELECT id, priority, content, project_id, status, create_at, archived, agent_id
+            SELECT id, priority, content, project_id, status, create_at, archived, agent_id, due_at, estimate_minutes, workflow_state, rank
             FROM tasks
             WHERE status IN ({})
               AND archived = false
-            ORDER BY priority DESC, create_at DESC
+              {}
+            ORDER BY rank ASC NULLS LAST, priority DESC, create_at DESC
             "#,
-            placeholders.join(", ")
+            placeholders.join(", "),
+            if exclude_snoozed {
+                "AND NOT EXISTS (SELECT 1 FROM task_snoozes s WHERE s.task_id = tasks.id AND s.wake_at > NOW())"
+            } else {
+                ""
+            }
         );
 
         let params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> =
@@ -102,81 +259,100 @@ impl DatabaseService {
                 create_at: row.get("create_at"),
                 archived: row.get("archived"),
                 agent_id: row.get("agent_id"),
+                due_at: row.get("due_at"),
+                estimate_minutes: row.get("estimate_minutes"),
+                workflow_state: row.get("workflow_state"),
+                rank: row.get("rank"),
             })
             .collect())
     }
 
-    /// Get today's tasks (todo, not archived)
+    /// Get today's tasks (todo, not archived, not snoozed)
     pub async fn get_today_tasks(&self) -> crate::Result<Vec<Task>> {
-        self.get_tasks_by_status(&[TaskStatus::Todo]).await
+        self.get_tasks_by_status(&[TaskStatus::Todo], true).await
     }
 
-    /// Get inbox tasks (todo, in-progress, in-review, not archived)
+    /// Get inbox tasks (todo, in-progress, in-review, not archived, not snoozed)
     pub async fn get_inbox_tasks(&self) -> crate::Result<Vec<Task>> {
-        self.get_tasks_by_status(&[TaskStatus::Todo, TaskStatus::InProgress, TaskStatus::InReview])
-            .await
+        self.get_tasks_by_status(
+            &[TaskStatus::Todo, TaskStatus::InProgress, TaskStatus::InReview],
+            true,
+        )
+        .await
     }
 
     /// Get backlog tasks (backlog, not archived)
     pub async fn get_backlog_tasks(&self) -> crate::Result<Vec<Task>> {
-        self.get_tasks_by_status(&[TaskStatus::Backlog]).await
+        self.get_tasks_by_status(&[TaskStatus::Backlog], false).await
     }
 
     /// Get in-progress tasks (any working status, not archived)
     pub async fn get_in_progress_tasks(&self) -> crate::Result<Vec<Task>> {
-        self.get_tasks_by_status(&[
-            TaskStatus::InProgress,
-            TaskStatus::InReview,
-            // Plan phase working states
-            TaskStatus::PlanInProgress,
-            TaskStatus::PlanReview,
-            // Coding phase working states
-            TaskStatus::CodingInProgress,
-            TaskStatus::CodingReview,
-            // Cross-review phase working states
-            TaskStatus::CrossReviewInProgress,
-        ])
+        self.get_tasks_by_status(
+            &[
+                TaskStatus::InProgress,
+                TaskStatus::InReview,
+                // Plan phase working states
+                TaskStatus::PlanInProgress,
+                TaskStatus::PlanReview,
+                // Coding phase working states
+                TaskStatus::CodingInProgress,
+                TaskStatus::CodingReview,
+                // Cross-review phase working states
+                TaskStatus::CrossReviewInProgress,
+            ],
+            false,
+        )
         .await
     }
 
     /// Get tasks in Plan phase (not archived)
     pub async fn get_plan_phase_tasks(&self) -> crate::Result<Vec<Task>> {
-        self.get_tasks_by_status(&[
-            TaskStatus::PlanPending,
-            TaskStatus::PlanInProgress,
-            TaskStatus::PlanReview,
-            TaskStatus::PlanDone,
-        ])
+        self.get_tasks_by_status(
+            &[
+                TaskStatus::PlanPending,
+                TaskStatus::PlanInProgress,
+                TaskStatus::PlanReview,
+                TaskStatus::PlanDone,
+            ],
+            false,
+        )
         .await
     }
 
     /// Get tasks in Coding phase (not archived)
     pub async fn get_coding_phase_tasks(&self) -> crate::Result<Vec<Task>> {
-        self.get_tasks_by_status(&[
-            TaskStatus::CodingPending,
-            TaskStatus::CodingInProgress,
-            TaskStatus::CodingReview,
-            TaskStatus::CodingDone,
-            TaskStatus::InProgress,
-            TaskStatus::InReview,
-        ])
+        self.get_tasks_by_status(
+            &[
+                TaskStatus::CodingPending,
+                TaskStatus::CodingInProgress,
+                TaskStatus::CodingReview,
+                TaskStatus::CodingDone,
+                TaskStatus::InProgress,
+                TaskStatus::InReview,
+            ],
+            false,
+        )
         .await
     }
 
     /// Get tasks in Cross-Review phase (not archived)
     pub async fn get_cross_review_phase_tasks(&self) -> crate::Result<Vec<Task>> {
-        self.get_tasks_by_status(&[
-            TaskStatus::CrossReviewPending,
-            TaskStatus::CrossReviewInProgress,
-            TaskStatus::CrossReviewPass,
-            TaskStatus::CrossReviewFail,
-        ])
+        self.get_tasks_by_status(
+            &[
+                TaskStatus::CrossReviewPending,
+                TaskStatus::CrossReviewInProgress,
+                TaskStatus::CrossReviewPass,
+                TaskStatus::CrossReviewFail,
+            ],
+            false,
+        )
         .await
     }
 
     /// Get done tasks (done, not archived)
     pub async fn get_done_tasks(&self) -> crate::Result<Vec<Task>> {
-        self.get_tasks_by_status(&[TaskStatus::Done]).await
+        self.get_tasks_by_status(&[TaskStatus::Done], false).await
     }
 
     /// Get done tasks for a specific project with pagination
@@ -193,7 +369,7 @@ impl DatabaseService {
             .map_err(|e| crate::TodokiError::Database(e))?;
 
         let query = r#"
-            SELECT id, priority, content, project_id, status, create_at, archived, agent_id
+            SELECT id, priority, content, project_id, status, create_at, archived, agent_id, due_at, estimate_minutes, workflow_state, rank
             FROM tasks
             WHERE project_id = $1
               AND status = 'done'
@@ -219,6 +395,54 @@ impl DatabaseService {
                 create_at: row.get("create_at"),
                 archived: row.get("archived"),
                 agent_id: row.get("agent_id"),
+                due_at: row.get("due_at"),
+                estimate_minutes: row.get("estimate_minutes"),
+                workflow_state: row.get("workflow_state"),
+                rank: row.get("rank"),
+            })
+            .collect())
+    }
+
+    /// All tasks of a project, oldest first
+    pub async fn list_project_tasks(
+        &self,
+        project_id: Uuid,
+        include_archived: bool,
+    ) -> crate::Result<Vec<Task>> {
+        let conn = self
+            .read_pool()
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let query = r#"
+            SELECT id, priority, content, project_id, status, create_at, archived, agent_id, due_at, estimate_minutes, workflow_state, rank
+            FROM tasks
+            WHERE project_id = $1
+              AND ($2 OR archived = false)
+            ORDER BY create_at ASC
+        "#;
+
+        let rows = conn
+            .query(query, &[&project_id, &include_archived])
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| Task {
+                id: row.get("id"),
+                priority: row.get("priority"),
+                content: row.get("content"),
+                project_id: row.get("project_id"),
+                status: row.get::<_, SqlTypeWrapper<TaskStatus>>("status").0,
+                create_at: row.get("create_at"),
+                archived: row.get("archived"),
+                agent_id: row.get("agent_id"),
+                due_at: row.get("due_at"),
+                estimate_minutes: row.get("estimate_minutes"),
+                workflow_state: row.get("workflow_state"),
+                rank: row.get("rank"),
             })
             .collect())
     }
@@ -232,7 +456,7 @@ impl DatabaseService {
             .map_err(|e| crate::TodokiError::Database(e))?;
 
         let query = r#"
-            SELECT DISTINCT ON (t.id) t.id, t.priority, t.content, t.project_id, t.status, t.create_at, t.archived, t.agent_id
+            SELECT DISTINCT ON (t.id) t.id, t.priority, t.content, t.project_id, t.status, t.create_at, t.archived, t.agent_id, t.due_at, t.estimate_minutes, t.workflow_state, t.rank
             FROM tasks t
             JOIN task_events e ON t.id = e.task_id
             WHERE t.status = 'done'
@@ -259,6 +483,70 @@ impl DatabaseService {
                 create_at: row.get("create_at"),
                 archived: row.get("archived"),
                 agent_id: row.get("agent_id"),
+                due_at: row.get("due_at"),
+                estimate_minutes: row.get("estimate_minutes"),
+                workflow_state: row.get("workflow_state"),
+                rank: row.get("rank"),
+            })
+            .collect())
+    }
+
+    /// Get tasks for the calendar feed: open tasks with a due date, plus tasks
+    /// completed since `done_since`. Returns each task with its completion time.
+    pub async fn get_calendar_tasks(
+        &self,
+        project_id: Option<Uuid>,
+        done_since: DateTime<Utc>,
+    ) -> crate::Result<Vec<(Task, Option<DateTime<Utc>>)>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let query = r#"
+            SELECT t.id, t.priority, t.content, t.project_id, t.status, t.create_at, t.archived, t.agent_id, t.due_at, t.estimate_minutes, t.workflow_state, t.rank,
+                   d.completed_at
+            FROM tasks t
+            LEFT JOIN LATERAL (
+                SELECT MAX(e.datetime) AS completed_at
+                FROM task_events e
+                WHERE e.task_id = t.id
+                  AND e.event_type = 'StatusChange'
+                  AND e.state = 'done'
+            ) d ON t.status = 'done'
+            WHERE t.archived = false
+              AND ($1::uuid IS NULL OR t.project_id = $1)
+              AND (
+                (t.due_at IS NOT NULL AND t.status <> 'done')
+                OR d.completed_at >= $2
+              )
+            ORDER BY COALESCE(d.completed_at, t.due_at)
+        "#;
+
+        let rows = conn
+            .query(query, &[&project_id, &done_since])
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let task = Task {
+                    id: row.get("id"),
+                    priority: row.get("priority"),
+                    content: row.get("content"),
+                    project_id: row.get("project_id"),
+                    status: row.get::<_, SqlTypeWrapper<TaskStatus>>("status").0,
+                    create_at: row.get("create_at"),
+                    archived: row.get("archived"),
+                    agent_id: row.get("agent_id"),
+                    due_at: row.get("due_at"),
+                    estimate_minutes: row.get("estimate_minutes"),
+                    workflow_state: row.get("workflow_state"),
+                    rank: row.get("rank"),
+                };
+                (task, row.get("completed_at"))
             })
             .collect())
     }
@@ -312,7 +600,15 @@ impl DatabaseService {
             .map(crate::models::ArtifactResponse::from)
             .collect();
 
-        Ok(TaskResponse::from_task(task, events, comments, agent, artifacts))
+        let snooze = self.get_task_snooze(task.id).await?;
+        let remote_issue = self.get_remote_issue(task.id).await?;
+
+        let mut response = TaskResponse::from_task(task, events, comments, agent, artifacts);
+        response.snoozed_until = snooze
+            .map(|s| s.wake_at)
+            .filter(|wake_at| *wake_at > Utc::now());
+        response.remote_issue = remote_issue.map(Into::into);
+        Ok(response)
     }
 
     /// Create a new task
@@ -336,6 +632,54 @@ impl DatabaseService {
             .map_err(|e| crate::TodokiError::Database(e))
     }
 
+    /// Create a task and queue the events describing it in one transaction
+    ///
+    /// The events go through the outbox, so they are published even if the
+    /// process dies right after the task is written.
+    pub async fn create_task_with_events(
+        &self,
+        create_task: CreateTask,
+        events: impl FnOnce(&Task) -> Vec<Event>,
+    ) -> crate::Result<Task> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let tx = conn
+            .begin()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let task_id = create_task
+            .insert::<Task>()
+            .returning_pk(&tx)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        CreateTaskEvent::create(task_id)
+            .insert::<TaskEvent>()
+            .returning_pk(&tx)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let task = Task::fetch_one_by_pk(&task_id, &tx)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        for event in events(&task) {
+            outbox::enqueue(&tx, &event)
+                .await
+                .map_err(|e| crate::TodokiError::Database(e))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(task)
+    }
+
     /// Update a task
     pub async fn update_task(
         &self,
@@ -343,6 +687,8 @@ impl DatabaseService {
         priority: i32,
         content: String,
         project_id: Uuid,
+        due_at: Option<DateTime<Utc>>,
+        estimate_minutes: Option<i32>,
     ) -> crate::Result<Task> {
         let mut task = Task::fetch_one_by_pk(&task_id, &*self.pool)
             .await
@@ -351,6 +697,8 @@ impl DatabaseService {
         task.priority = priority;
         task.content = content;
         task.project_id = project_id;
+        task.due_at = due_at;
+        task.estimate_minutes = estimate_minutes;
 
         task.save(&*self.pool)
             .await
@@ -371,6 +719,7 @@ impl DatabaseService {
 
         let old_status = task.status;
         task.status = new_status;
+        task.workflow_state = None;
 
         // Create status change event
         let event = CreateTaskEvent::status_change(task_id, old_status, new_status);
@@ -387,6 +736,163 @@ impl DatabaseService {
         Ok(task)
     }
 
+    /// Update task status and queue the events it implies in one transaction
+    ///
+    /// `workflow_state` is the custom state the task moves to, if any.
+    /// `events` gets the task before and after the change.
+    pub async fn update_task_status_with_events(
+        &self,
+        task_id: Uuid,
+        new_status: TaskStatus,
+        workflow_state: Option<String>,
+        events: impl FnOnce(&Task, &Task) -> Vec<Event>,
+    ) -> crate::Result<Task> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let tx = conn
+            .begin()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let previous = Task::fetch_one_by_pk(&task_id, &tx)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let mut task = previous.clone();
+        task.status = new_status;
+        task.workflow_state = workflow_state;
+
+        CreateTaskEvent::status_change(task_id, previous.status, new_status)
+            .insert::<TaskEvent>()
+            .returning_pk(&tx)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        task.save(&tx)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        for event in events(&previous, &task) {
+            outbox::enqueue(&tx, &event)
+                .await
+                .map_err(|e| crate::TodokiError::Database(e))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(task)
+    }
+
+    /// Tasks of a board column with their ranks, in board order
+    ///
+    /// The column holds the unarchived tasks of `project_id` in the custom
+    /// state `workflow_state`, or else in `status` without a custom state.
+    pub async fn column_ranks(
+        &self,
+        project_id: Uuid,
+        status: TaskStatus,
+        workflow_state: Option<&str>,
+    ) -> crate::Result<Vec<(Uuid, Option<String>)>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let rows = conn
+            .query(
+                r#"
+                SELECT id, rank
+                FROM tasks
+                WHERE project_id = $1
+                  AND archived = false
+                  AND CASE WHEN $3::text IS NULL
+                           THEN workflow_state IS NULL AND status = $2
+                           ELSE workflow_state = $3 END
+                ORDER BY rank ASC NULLS LAST, priority DESC, create_at DESC
+                "#,
+                &[&project_id, &SqlTypeWrapper(status), &workflow_state],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows.iter().map(|row| (row.get("id"), row.get("rank"))).collect())
+    }
+
+    /// Move a task within or across board columns in one transaction
+    ///
+    /// Sets the task's status, custom state and rank, rewrites the ranks of
+    /// `respread` tasks, records the status change (if any) and the reorder
+    /// as task events and queues `events`, which gets the task before and
+    /// after the move.
+    pub async fn move_task_with_events(
+        &self,
+        task_id: Uuid,
+        target: StateTarget,
+        rank: String,
+        respread: Vec<(Uuid, String)>,
+        events: impl FnOnce(&Task, &Task) -> Vec<Event>,
+    ) -> crate::Result<Task> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let tx = conn
+            .begin()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let previous = Task::fetch_one_by_pk(&task_id, &tx)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        for (id, rank) in &respread {
+            tx.execute("UPDATE tasks SET rank = $2 WHERE id = $1", &[id, rank])
+                .await
+                .map_err(|e| crate::TodokiError::Database(e))?;
+        }
+
+        let mut task = previous.clone();
+        task.status = target.status;
+        task.workflow_state = target.workflow_state;
+        task.rank = Some(rank.clone());
+
+        if task.state() != previous.state() {
+            CreateTaskEvent::status_change(task_id, previous.status, task.status)
+                .insert::<TaskEvent>()
+                .returning_pk(&tx)
+                .await
+                .map_err(|e| crate::TodokiError::Database(e))?;
+        }
+        CreateTaskEvent::reorder(task_id, task.status, previous.rank.clone(), rank)
+            .insert::<TaskEvent>()
+            .returning_pk(&tx)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        task.save(&tx)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        for event in events(&previous, &task) {
+            outbox::enqueue(&tx, &event)
+                .await
+                .map_err(|e| crate::TodokiError::Database(e))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(task)
+    }
+
     /// Archive a task
     pub async fn archive_task(&self, task_id: Uuid) -> crate::Result<Task> {
         let mut task = Task::fetch_one_by_pk(&task_id, &*self.pool)
@@ -472,7 +978,7 @@ impl DatabaseService {
         let row = conn
             .query_opt(
                 r#"
-                SELECT id, priority, content, project_id, status, create_at, archived, agent_id
+                SELECT id, priority, content, project_id, status, create_at, archived, agent_id, due_at, estimate_minutes, workflow_state, rank
                 FROM tasks
                 WHERE agent_id = $1
                 LIMIT 1
@@ -491,6 +997,10 @@ impl DatabaseService {
             create_at: r.get("create_at"),
             archived: r.get("archived"),
             agent_id: r.get("agent_id"),
+            due_at: r.get("due_at"),
+            estimate_minutes: r.get("estimate_minutes"),
+            workflow_state: r.get("workflow_state"),
+            rank: r.get("rank"),
         }))
     }
 
@@ -521,49 +1031,178 @@ impl DatabaseService {
             .map_err(|e| crate::TodokiError::Database(e))
     }
 
-    // ========================================================================
-    // Project operations
-    // ========================================================================
+    /// Compare a new task with recent open tasks of its project and record a
+    /// `PossibleDuplicate` event for each close match
+    pub async fn flag_possible_duplicates(
+        &self,
+        task: &Task,
+        settings: &DedupSettings,
+    ) -> crate::Result<Vec<DuplicateSuggestion>> {
+        if !settings.enabled {
+            return Ok(Vec::new());
+        }
 
-    /// List all projects (not archived by default)
-    pub async fn list_projects(&self, include_archived: bool) -> crate::Result<Vec<Project>> {
         let conn = self
-            .pool
+            .read_pool()
             .get()
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
-        let query = if include_archived {
-            r#"SELECT id, name, description, color, archived, created_at, updated_at,
-                      general_template, business_template, coding_template, qa_template
-               FROM projects ORDER BY name ASC"#
-        } else {
-            r#"SELECT id, name, description, color, archived, created_at, updated_at,
-                      general_template, business_template, coding_template, qa_template
-               FROM projects WHERE archived = false ORDER BY name ASC"#
-        };
-
+        let lookback_days = settings.lookback_days.min(i32::MAX as u32) as i32;
         let rows = conn
-            .query(query, &[])
+            .query(
+                r#"
+                SELECT id, content, status
+                FROM tasks
+                WHERE project_id = $1 AND id <> $2
+                  AND archived = false AND status <> 'done'
+                  AND create_at >= NOW() - make_interval(days => $3)
+                ORDER BY create_at DESC
+                LIMIT 500
+                "#,
+                &[&task.project_id, &task.id, &lookback_days],
+            )
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
-        Ok(rows
+        let candidates: Vec<(Uuid, String, TaskStatus)> = rows
             .iter()
-            .map(|row| Project {
-                id: row.get("id"),
-                name: row.get("name"),
-                description: row.get("description"),
-                color: row.get("color"),
-                archived: row.get("archived"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-                general_template: row.get("general_template"),
-                business_template: row.get("business_template"),
-                coding_template: row.get("coding_template"),
-                qa_template: row.get("qa_template"),
+            .map(|row| {
+                (
+                    row.get("id"),
+                    row.get("content"),
+                    row.get::<_, SqlTypeWrapper<TaskStatus>>("status").0,
+                )
             })
-            .collect())
+            .collect();
+        let suggestions = find_duplicates(
+            &task.content,
+            &candidates,
+            settings.threshold,
+            settings.max_suggestions,
+        );
+
+        for suggestion in &suggestions {
+            CreateTaskEvent::possible_duplicate(task.id, suggestion.task_id, suggestion.similarity)
+                .insert::<TaskEvent>()
+                .returning_pk(&*self.pool)
+                .await
+                .map_err(|e| crate::TodokiError::Database(e))?;
+        }
+        Ok(suggestions)
+    }
+
+    /// Fold `duplicate_id` into `task_id`: its comments and artifacts move
+    /// over and it is archived
+    pub async fn merge_tasks(&self, task_id: Uuid, duplicate_id: Uuid) -> crate::Result<Task> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let tx = conn
+            .begin()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let task = Task::fetch_one_by_pk(&task_id, &tx)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let mut duplicate = Task::fetch_one_by_pk(&duplicate_id, &tx)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        tx.execute(
+            "UPDATE task_comments SET task_id = $1 WHERE task_id = $2",
+            &[&task_id, &duplicate_id],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+        tx.execute(
+            "UPDATE artifacts SET task_id = $1, project_id = $2, updated_at = NOW() \
+             WHERE task_id = $3",
+            &[&task_id, &task.project_id, &duplicate_id],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let mut events = vec![
+            CreateTaskEvent::merged_from(task_id, duplicate_id),
+            CreateTaskEvent::merged_into(duplicate_id, task_id),
+        ];
+        if !duplicate.archived {
+            events.push(CreateTaskEvent::archived(duplicate_id));
+        }
+        for event in events {
+            event
+                .insert::<TaskEvent>()
+                .returning_pk(&tx)
+                .await
+                .map_err(|e| crate::TodokiError::Database(e))?;
+        }
+
+        duplicate.archived = true;
+        duplicate
+            .save(&tx)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        Ok(task)
+    }
+
+    // ========================================================================
+    // Project operations
+    // ========================================================================
+
+    /// List all projects (not archived by default)
+    pub async fn list_projects(&self, include_archived: bool) -> crate::Result<Vec<Project>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let query = if include_archived {
+            r#"SELECT id, name, description, color, archived, created_at, updated_at,
+                      general_template, business_template, coding_template, qa_template, execution_schedule,
+                      relay_routing, review_config, workflow, issue_sync
+               FROM projects ORDER BY name ASC"#
+        } else {
+            r#"SELECT id, name, description, color, archived, created_at, updated_at,
+                      general_template, business_template, coding_template, qa_template, execution_schedule,
+                      relay_routing, review_config, workflow, issue_sync
+               FROM projects WHERE archived = false ORDER BY name ASC"#
+        };
+
+        let rows = conn
+            .query(query, &[])
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| Project {
+                id: row.get("id"),
+                name: row.get("name"),
+                description: row.get("description"),
+                color: row.get("color"),
+                archived: row.get("archived"),
+                created_at: row.get("created_at"),
+                updated_at: row.get("updated_at"),
+                general_template: row.get("general_template"),
+                business_template: row.get("business_template"),
+                coding_template: row.get("coding_template"),
+                qa_template: row.get("qa_template"),
+                execution_schedule: row.get("execution_schedule"),
+                relay_routing: row.get("relay_routing"),
+                review_config: row.get("review_config"),
+                workflow: row.get("workflow"),
+                issue_sync: row.get("issue_sync"),
+            })
+            .collect())
     }
 
     /// Get project by ID
@@ -586,7 +1225,8 @@ impl DatabaseService {
         let row = conn
             .query_opt(
                 r#"SELECT id, name, description, color, archived, created_at, updated_at,
-                          general_template, business_template, coding_template, qa_template
+                          general_template, business_template, coding_template, qa_template, execution_schedule,
+                          relay_routing, review_config, workflow, issue_sync
                    FROM projects WHERE name = $1"#,
                 &[&name],
             )
@@ -605,9 +1245,33 @@ impl DatabaseService {
             business_template: r.get("business_template"),
             coding_template: r.get("coding_template"),
             qa_template: r.get("qa_template"),
+            execution_schedule: r.get("execution_schedule"),
+            relay_routing: r.get("relay_routing"),
+            review_config: r.get("review_config"),
+            workflow: r.get("workflow"),
+            issue_sync: r.get("issue_sync"),
         }))
     }
 
+    /// Get a project by name, creating it when missing (used by integrations)
+    pub async fn get_or_create_project(
+        &self,
+        name: &str,
+        description: &str,
+    ) -> crate::Result<Project> {
+        match self.get_project_by_name(name).await? {
+            Some(project) => Ok(project),
+            None => {
+                self.create_project(CreateProject::new(
+                    name.to_string(),
+                    Some(description.to_string()),
+                    None,
+                ))
+                .await
+            }
+        }
+    }
+
     /// Create a new project
     pub async fn create_project(&self, create: CreateProject) -> crate::Result<Project> {
         let project_id = create
@@ -633,6 +1297,11 @@ impl DatabaseService {
         business_template: Option<String>,
         coding_template: Option<String>,
         qa_template: Option<String>,
+        execution_schedule: Option<ExecutionSchedule>,
+        relay_routing: Option<RelayRouting>,
+        review_config: Option<ProjectReviewConfig>,
+        workflow: Option<Workflow>,
+        issue_sync: Option<ProjectIssueSync>,
     ) -> crate::Result<Project> {
         let mut project = Project::fetch_one_by_pk(&project_id, &*self.pool)
             .await
@@ -662,6 +1331,41 @@ impl DatabaseService {
         if let Some(t) = qa_template {
             project.qa_template = Some(t);
         }
+        if let Some(schedule) = execution_schedule {
+            project.execution_schedule = if schedule.windows.is_empty() {
+                None
+            } else {
+                Some(serde_json::to_value(schedule).unwrap_or_default())
+            };
+        }
+        if let Some(routing) = relay_routing {
+            project.relay_routing = if routing.is_empty() {
+                None
+            } else {
+                Some(serde_json::to_value(routing).unwrap_or_default())
+            };
+        }
+        if let Some(config) = review_config {
+            project.review_config = if config.is_empty() {
+                None
+            } else {
+                Some(serde_json::to_value(config).unwrap_or_default())
+            };
+        }
+        if let Some(workflow) = workflow {
+            project.workflow = if workflow.is_empty() {
+                None
+            } else {
+                Some(serde_json::to_value(workflow).unwrap_or_default())
+            };
+        }
+        if let Some(sync) = issue_sync {
+            project.issue_sync = if sync.is_empty() {
+                None
+            } else {
+                Some(serde_json::to_value(sync).unwrap_or_default())
+            };
+        }
         project.updated_at = Utc::now();
 
         project
@@ -681,6 +1385,269 @@ impl DatabaseService {
         Ok(())
     }
 
+    /// Copy a project's settings into a new project
+    ///
+    /// The copy gets the source's inline and shared role templates, execution
+    /// schedule, relay routing, reviewer overrides and workflow. With
+    /// `include_agents`, agent definitions are copied too; per-task agents
+    /// spawned for executions are skipped.
+    pub async fn clone_project(
+        &self,
+        source_id: Uuid,
+        name: &str,
+        description: Option<String>,
+        color: Option<String>,
+        include_agents: bool,
+    ) -> crate::Result<(Project, Vec<Agent>)> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let tx = conn
+            .begin()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = tx
+            .query_opt(
+                r#"
+                INSERT INTO projects
+                    (name, description, color, archived, general_template, business_template,
+                     coding_template, qa_template, execution_schedule, relay_routing,
+                     review_config, workflow)
+                SELECT $2, COALESCE($3, description), COALESCE($4, color), false,
+                       general_template, business_template, coding_template, qa_template,
+                       execution_schedule, relay_routing, review_config, workflow
+                FROM projects
+                WHERE id = $1
+                RETURNING id
+                "#,
+                &[&source_id, &name, &description, &color],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let Some(row) = row else {
+            return Err(crate::TodokiError::NotFound(format!(
+                "Project {} not found",
+                source_id
+            )));
+        };
+        let project_id: Uuid = row.get("id");
+
+        tx.execute(
+            r#"
+            INSERT INTO project_prompt_templates (project_id, role, template_id)
+            SELECT $2, role, template_id
+            FROM project_prompt_templates
+            WHERE project_id = $1
+            "#,
+            &[&source_id, &project_id],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        if include_agents {
+            tx.execute(
+                r#"
+                INSERT INTO agents
+                    (name, workdir, command, args, execution_mode, role, project_id,
+                     subscribed_events, auto_trigger, last_cursor, relay_selector)
+                SELECT name, workdir, command, args, execution_mode, role, $2,
+                       subscribed_events, auto_trigger, last_cursor, relay_selector
+                FROM agents a
+                WHERE a.project_id = $1
+                  AND NOT EXISTS (SELECT 1 FROM tasks t WHERE t.agent_id = a.id)
+                "#,
+                &[&source_id, &project_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        }
+
+        let project = Project::fetch_one_by_pk(&project_id, &tx)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let agents = Agent::select()
+            .filter(Agent::COLUMNS.project_id.eq(project_id))
+            .all(&tx)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok((project, agents))
+    }
+
+    /// Archive a project with its non-done tasks and hide its artifacts
+    ///
+    /// Pending scheduled executions of the archived tasks are dropped.
+    /// Running agents are left to the caller, which has the relays.
+    pub async fn archive_project(&self, project_id: Uuid) -> crate::Result<ProjectCascade> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let tx = conn
+            .begin()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let updated = tx
+            .execute(
+                "UPDATE projects SET archived = true, updated_at = NOW() WHERE id = $1",
+                &[&project_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        if updated == 0 {
+            return Err(crate::TodokiError::NotFound(format!(
+                "Project {} not found",
+                project_id
+            )));
+        }
+
+        let rows = tx
+            .query(
+                r#"
+                UPDATE tasks SET archived = true, archived_with_project = true
+                WHERE project_id = $1 AND archived = false AND status <> 'done'
+                RETURNING id
+                "#,
+                &[&project_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let task_ids: Vec<Uuid> = rows.iter().map(|row| row.get("id")).collect();
+
+        for task_id in &task_ids {
+            CreateTaskEvent::archived(*task_id)
+                .insert::<TaskEvent>()
+                .returning_pk(&tx)
+                .await
+                .map_err(|e| crate::TodokiError::Database(e))?;
+        }
+
+        tx.execute(
+            "DELETE FROM scheduled_executions WHERE task_id = ANY($1)",
+            &[&task_ids],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let artifacts = tx
+            .execute(
+                "UPDATE artifacts SET hidden = true WHERE project_id = $1 AND hidden = false",
+                &[&project_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let project = Project::fetch_one_by_pk(&project_id, &tx)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(ProjectCascade {
+            project,
+            task_ids,
+            artifacts: artifacts as i64,
+        })
+    }
+
+    /// Restore an archived project, undoing what archiving cascaded to
+    ///
+    /// Only tasks archived together with the project come back; tasks that
+    /// were archived on their own stay archived.
+    pub async fn restore_project(&self, project_id: Uuid) -> crate::Result<ProjectCascade> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let tx = conn
+            .begin()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let updated = tx
+            .execute(
+                "UPDATE projects SET archived = false, updated_at = NOW() WHERE id = $1",
+                &[&project_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        if updated == 0 {
+            return Err(crate::TodokiError::NotFound(format!(
+                "Project {} not found",
+                project_id
+            )));
+        }
+
+        let rows = tx
+            .query(
+                r#"
+                UPDATE tasks SET archived = false, archived_with_project = false
+                WHERE project_id = $1 AND archived_with_project = true
+                RETURNING id
+                "#,
+                &[&project_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let task_ids: Vec<Uuid> = rows.iter().map(|row| row.get("id")).collect();
+
+        for task_id in &task_ids {
+            CreateTaskEvent::unarchived(*task_id)
+                .insert::<TaskEvent>()
+                .returning_pk(&tx)
+                .await
+                .map_err(|e| crate::TodokiError::Database(e))?;
+        }
+
+        let artifacts = tx
+            .execute(
+                "UPDATE artifacts SET hidden = false WHERE project_id = $1 AND hidden = true",
+                &[&project_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let project = Project::fetch_one_by_pk(&project_id, &tx)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(ProjectCascade {
+            project,
+            task_ids,
+            artifacts: artifacts as i64,
+        })
+    }
+
+    /// Running agents of a project
+    pub async fn list_running_project_agents(&self, project_id: Uuid) -> crate::Result<Vec<Agent>> {
+        let agents = Agent::select()
+            .filter(Agent::COLUMNS.project_id.eq(project_id))
+            .all(&*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(agents
+            .into_iter()
+            .filter(|agent| agent.status == AgentStatus::Running)
+            .collect())
+    }
+
     // ========================================================================
     // Report operations
     // ========================================================================
@@ -688,7 +1655,7 @@ impl DatabaseService {
     /// Get activity report for a given period
     pub async fn get_report(&self, period: ReportPeriod) -> crate::Result<ReportResponse> {
         let conn = self
-            .pool
+            .read_pool()
             .get()
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
@@ -720,6 +1687,42 @@ impl DatabaseService {
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
+        // Tasks completed in the period that have an estimate and recorded session time
+        let estimate_query = format!(
+            r#"
+            WITH done AS (
+                SELECT DISTINCT task_id FROM task_events
+                WHERE event_type = 'StatusChange' AND state = 'done' AND {}
+            ), actuals AS (
+                SELECT d.task_id,
+                       SUM(d.duration_secs)::BIGINT AS actual_secs,
+                       (ARRAY_AGG(d.role ORDER BY d.completed_at DESC))[1] AS role
+                FROM task_session_durations d
+                JOIN done ON done.task_id = d.task_id
+                GROUP BY d.task_id
+            )
+            SELECT p.name AS project_name, a.role, t.estimate_minutes, a.actual_secs
+            FROM actuals a
+            JOIN tasks t ON t.id = a.task_id
+            JOIN projects p ON p.id = t.project_id
+            WHERE t.estimate_minutes IS NOT NULL
+            "#,
+            date_filter
+        );
+
+        let samples: Vec<EstimateSample> = conn
+            .query(&estimate_query, &[])
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?
+            .iter()
+            .map(|row| EstimateSample {
+                project_name: row.get("project_name"),
+                role: row.get("role"),
+                estimate_minutes: row.get("estimate_minutes"),
+                actual_secs: row.get("actual_secs"),
+            })
+            .collect();
+
         Ok(ReportResponse {
             period,
             created_count: row.get::<_, Option<i64>>("created_count").unwrap_or(0),
@@ -727,363 +1730,3986 @@ impl DatabaseService {
             archived_count: row.get::<_, Option<i64>>("archived_count").unwrap_or(0),
             state_changes_count: row.get::<_, Option<i64>>("state_changes_count").unwrap_or(0),
             comments_count: row.get::<_, Option<i64>>("comments_count").unwrap_or(0),
+            estimation: EstimationReport::from_samples(&samples),
         })
     }
 
     // ========================================================================
-    // Agent operations
+    // Task duration operations
     // ========================================================================
 
-    /// Create a new agent
-    pub async fn create_agent(&self, create: CreateAgent) -> crate::Result<Agent> {
-        let agent_id = create
-            .insert::<Agent>()
-            .returning_pk(&*self.pool)
+    /// Record the spawn-to-completion duration of an ended session
+    ///
+    /// Only sessions of agents executing a task are recorded; recording the
+    /// same session twice is a no-op.
+    pub async fn record_session_duration(&self, session_id: Uuid) -> crate::Result<()> {
+        let conn = self
+            .pool
+            .get()
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
-        Agent::fetch_one_by_pk(&agent_id, &*self.pool)
-            .await
-            .map_err(|e| crate::TodokiError::Database(e))
-    }
+        conn.execute(
+            r#"
+            INSERT INTO task_session_durations
+                (session_id, task_id, project_id, role, spawned_at, completed_at, duration_secs)
+            SELECT s.id, t.id, t.project_id, a.role, s.started_at, s.ended_at,
+                   EXTRACT(EPOCH FROM s.ended_at - s.started_at)::BIGINT
+            FROM agent_sessions s
+            JOIN agents a ON a.id = s.agent_id
+            JOIN tasks t ON t.agent_id = a.id
+            WHERE s.id = $1 AND s.ended_at IS NOT NULL
+            ON CONFLICT (session_id) DO NOTHING
+            "#,
+            &[&session_id],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
 
-    /// List all agents
-    pub async fn list_agents(&self) -> crate::Result<Vec<Agent>> {
-        Agent::fetch_all(&*self.pool)
-            .await
-            .map_err(|e| crate::TodokiError::Database(e))
+        Ok(())
     }
 
-    /// Get agent by ID
-    pub async fn get_agent(&self, agent_id: Uuid) -> crate::Result<Option<Agent>> {
-        match Agent::fetch_one_by_pk(&agent_id, &*self.pool).await {
-            Ok(agent) => Ok(Some(agent)),
-            Err(conservator::Error::TooManyRows(0)) => Ok(None),
-            Err(e) => Err(crate::TodokiError::Database(e)),
-        }
-    }
-
-    /// Update agent status
-    pub async fn update_agent_status(
-        &self,
-        agent_id: Uuid,
-        status: AgentStatus,
-    ) -> crate::Result<()> {
-        let mut agent = Agent::fetch_one_by_pk(&agent_id, &*self.pool)
+    /// Number of recorded sessions and their total duration (seconds) for a task
+    pub async fn get_task_actuals(&self, task_id: Uuid) -> crate::Result<(i64, i64)> {
+        let conn = self
+            .pool
+            .get()
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
-        agent.status = status;
-        agent.updated_at = Utc::now();
-        agent
-            .save(&*self.pool)
+        let row = conn
+            .query_one(
+                r#"
+                SELECT COUNT(*) AS session_count,
+                       COALESCE(SUM(duration_secs), 0)::BIGINT AS actual_secs
+                FROM task_session_durations
+                WHERE task_id = $1
+                "#,
+                &[&task_id],
+            )
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
-        Ok(())
+        Ok((row.get("session_count"), row.get("actual_secs")))
     }
 
-    /// Delete agent
-    pub async fn delete_agent(&self, agent_id: Uuid) -> crate::Result<()> {
-        Agent::delete_by_pk(&agent_id, &*self.pool)
+    /// Agent sessions recorded for a task, newest first
+    pub async fn list_task_session_times(
+        &self,
+        task_id: Uuid,
+    ) -> crate::Result<Vec<AgentSessionTime>> {
+        let conn = self
+            .read_pool()
+            .get()
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
-        Ok(())
+        let rows = conn
+            .query(
+                r#"
+                SELECT session_id, task_id, role, spawned_at, completed_at, duration_secs
+                FROM task_session_durations
+                WHERE task_id = $1
+                ORDER BY spawned_at DESC
+                "#,
+                &[&task_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| AgentSessionTime {
+                session_id: row.get("session_id"),
+                task_id: row.get("task_id"),
+                role: row.get("role"),
+                spawned_at: row.get("spawned_at"),
+                completed_at: row.get("completed_at"),
+                duration_secs: row.get("duration_secs"),
+            })
+            .collect())
     }
 
     // ========================================================================
-    // Agent Session operations
+    // Status page operations
     // ========================================================================
 
-    /// Create a new agent session
-    pub async fn create_agent_session(&self, agent_id: Uuid) -> crate::Result<AgentSession> {
-        let create = CreateAgentSession { agent_id };
-
-        let session_id = create
-            .insert::<AgentSession>()
-            .returning_pk(&*self.pool)
+    /// A project's status page, enabled or not
+    pub async fn get_status_page(&self, project_id: Uuid) -> crate::Result<Option<StatusPage>> {
+        let conn = self
+            .read_pool()
+            .get()
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
-        AgentSession::fetch_one_by_pk(&session_id, &*self.pool)
+        let row = conn
+            .query_opt(
+                "SELECT * FROM project_status_pages WHERE project_id = $1",
+                &[&project_id],
+            )
             .await
-            .map_err(|e| crate::TodokiError::Database(e))
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(row.as_ref().map(status_page_from_row))
     }
 
-    /// Update session status
-    pub async fn update_session_status(
-        &self,
-        session_id: Uuid,
-        status: SessionStatus,
-    ) -> crate::Result<()> {
-        let mut session = AgentSession::fetch_one_by_pk(&session_id, &*self.pool)
+    /// The enabled status page `token` opens
+    pub async fn get_status_page_by_token(&self, token: &str) -> crate::Result<Option<StatusPage>> {
+        let conn = self
+            .read_pool()
+            .get()
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
-        session.status = status;
-        if status != SessionStatus::Running {
-            session.ended_at = Some(Utc::now());
-        }
-
-        session
-            .save(&*self.pool)
+        let row = conn
+            .query_opt(
+                "SELECT * FROM project_status_pages WHERE token = $1 AND enabled",
+                &[&token],
+            )
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
-        Ok(())
+        Ok(row.as_ref().map(status_page_from_row))
     }
 
-    /// Get a single session by ID
-    pub async fn get_agent_session(&self, session_id: Uuid) -> crate::Result<Option<AgentSession>> {
+    /// Create or update a project's status page; a new page gets a random
+    /// token, an existing one keeps its token unless `rotate_token`
+    pub async fn upsert_status_page(
+        &self,
+        project_id: Uuid,
+        enabled: bool,
+        redact: &[StatusPageField],
+        rotate_token: bool,
+    ) -> crate::Result<StatusPage> {
         let conn = self
             .pool
             .get()
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
+        let token = crate::auth::session::random_token();
+        let redact = serde_json::to_value(redact).unwrap_or(Value::Null);
         let row = conn
-            .query_opt(
+            .query_one(
                 r#"
-                SELECT id, agent_id, status, started_at, ended_at
-                FROM agent_sessions
-                WHERE id = $1
+                INSERT INTO project_status_pages (project_id, token, enabled, redact)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (project_id) DO UPDATE
+                SET enabled = EXCLUDED.enabled,
+                    redact = EXCLUDED.redact,
+                    token = CASE WHEN $5 THEN EXCLUDED.token
+                                 ELSE project_status_pages.token END,
+                    updated_at = NOW()
+                RETURNING *
                 "#,
-                &[&session_id],
+                &[&project_id, &token, &enabled, &redact, &rotate_token],
             )
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
-        Ok(row.map(|r| AgentSession {
-            id: r.get("id"),
-            agent_id: r.get("agent_id"),
-            status: r.get::<_, SqlTypeWrapper<SessionStatus>>("status").0,
-            started_at: r.get("started_at"),
-            ended_at: r.get("ended_at"),
-        }))
+        Ok(status_page_from_row(&row))
     }
 
-    /// Get running session for an agent (if any)
-    pub async fn get_agent_running_session(&self, agent_id: Uuid) -> crate::Result<Option<AgentSession>> {
+    /// Task counts, latest completed tasks and busy agents of a project
+    pub async fn get_project_snapshot(&self, project_id: Uuid) -> crate::Result<ProjectSnapshot> {
         let conn = self
-            .pool
+            .read_pool()
             .get()
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
-        let row = conn
-            .query_opt(
+        let count_rows = conn
+            .query(
                 r#"
-                SELECT id, agent_id, status, started_at, ended_at
-                FROM agent_sessions
-                WHERE agent_id = $1 AND status = 'running'
-                ORDER BY started_at DESC
-                LIMIT 1
+                SELECT status, workflow_state, COUNT(*) AS count
+                FROM tasks
+                WHERE project_id = $1 AND archived = false
+                GROUP BY status, workflow_state
                 "#,
-                &[&agent_id],
+                &[&project_id],
             )
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
-        Ok(row.map(|r| AgentSession {
-            id: r.get("id"),
-            agent_id: r.get("agent_id"),
-            status: r.get::<_, SqlTypeWrapper<SessionStatus>>("status").0,
-            started_at: r.get("started_at"),
-            ended_at: r.get("ended_at"),
-        }))
+        let completed_rows = conn
+            .query(
+                r#"
+                SELECT t.content, d.completed_at
+                FROM tasks t
+                JOIN LATERAL (
+                    SELECT MAX(e.datetime) AS completed_at
+                    FROM task_events e
+                    WHERE e.task_id = t.id
+                      AND e.event_type = 'StatusChange'
+                      AND e.state = 'done'
+                ) d ON d.completed_at IS NOT NULL
+                WHERE t.project_id = $1 AND t.status = 'done' AND t.archived = false
+                ORDER BY d.completed_at DESC
+                LIMIT $2
+                "#,
+                &[&project_id, &STATUS_PAGE_COMPLETED_LIMIT],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let active_agents: i64 = conn
+            .query_one(
+                r#"
+                SELECT COUNT(DISTINCT a.id) AS count
+                FROM agents a
+                JOIN agent_sessions s ON s.agent_id = a.id
+                WHERE a.project_id = $1 AND s.status = 'running'
+                "#,
+                &[&project_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?
+            .get("count");
+
+        Ok(ProjectSnapshot {
+            counts: count_rows
+                .iter()
+                .map(|row| {
+                    (
+                        row.get::<_, SqlTypeWrapper<TaskStatus>>("status").0,
+                        row.get("workflow_state"),
+                        row.get("count"),
+                    )
+                })
+                .collect(),
+            completed: completed_rows
+                .iter()
+                .map(|row| (row.get("content"), row.get("completed_at")))
+                .collect(),
+            active_agents,
+        })
     }
 
-    /// Get sessions for an agent
-    pub async fn get_agent_sessions(&self, agent_id: Uuid) -> crate::Result<Vec<AgentSession>> {
+    // ========================================================================
+    // Quota operations
+    // ========================================================================
+
+    /// A project's quota overrides, if any were set
+    pub async fn get_project_quotas(
+        &self,
+        project_id: Uuid,
+    ) -> crate::Result<Option<ProjectQuotas>> {
         let conn = self
-            .pool
+            .read_pool()
             .get()
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
-        let rows = conn
-            .query(
-                r#"
-                SELECT id, agent_id, status, started_at, ended_at
-                FROM agent_sessions
-                WHERE agent_id = $1
-                ORDER BY started_at DESC
-                "#,
-                &[&agent_id],
+        let row = conn
+            .query_opt(
+                "SELECT * FROM project_quotas WHERE project_id = $1",
+                &[&project_id],
             )
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
-        Ok(rows
-            .iter()
-            .map(|row| AgentSession {
-                id: row.get("id"),
-                agent_id: row.get("agent_id"),
-                status: row.get::<_, SqlTypeWrapper<SessionStatus>>("status").0,
-                started_at: row.get("started_at"),
-                ended_at: row.get("ended_at"),
-            })
-            .collect())
+        Ok(row.as_ref().map(project_quotas_from_row))
     }
 
-    /// Mark running sessions as exited on startup
-    pub async fn mark_sessions_exited_on_startup(&self) -> crate::Result<()> {
+    /// Quota overrides of every project that has some
+    pub async fn list_project_quotas(&self) -> crate::Result<Vec<ProjectQuotas>> {
         let conn = self
-            .pool
+            .read_pool()
             .get()
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
-        conn.execute(
-            r#"
-            UPDATE agent_sessions
-            SET status = 'exited', ended_at = NOW()
-            WHERE status = 'running'
-            "#,
-            &[],
-        )
-        .await
-        .map_err(|e| crate::TodokiError::Database(e))?;
-
-        conn.execute(
-            r#"
-            UPDATE agents
-            SET status = 'exited', updated_at = NOW()
-            WHERE status = 'running'
-            "#,
-            &[],
-        )
-        .await
-        .map_err(|e| crate::TodokiError::Database(e))?;
+        let rows = conn
+            .query("SELECT * FROM project_quotas", &[])
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
 
-        Ok(())
+        Ok(rows.iter().map(project_quotas_from_row).collect())
     }
 
-    // ========================================================================
-    // Artifact operations
-    // ========================================================================
-
-    /// Create a new artifact
-    pub async fn create_artifact(
+    /// Replace a project's quota overrides
+    pub async fn set_project_quotas(
         &self,
-        task_id: Uuid,
         project_id: Uuid,
-        agent_id: Option<Uuid>,
-        session_id: Option<Uuid>,
-        artifact_type: &str,
-        data: Value,
-    ) -> crate::Result<Artifact> {
-        let create = CreateArtifact::new(task_id, project_id, agent_id, session_id, artifact_type, data);
-
-        let artifact_id = create
-            .insert::<Artifact>()
-            .returning_pk(&*self.pool)
+        overrides: &QuotaLimits,
+    ) -> crate::Result<ProjectQuotas> {
+        let conn = self
+            .pool
+            .get()
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
-        Artifact::fetch_one_by_pk(&artifact_id, &*self.pool)
+        let row = conn
+            .query_one(
+                r#"
+                INSERT INTO project_quotas
+                    (project_id, max_concurrent_sessions, events_per_hour, storage_bytes,
+                     ai_reviews_per_month)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (project_id) DO UPDATE
+                SET max_concurrent_sessions = EXCLUDED.max_concurrent_sessions,
+                    events_per_hour = EXCLUDED.events_per_hour,
+                    storage_bytes = EXCLUDED.storage_bytes,
+                    ai_reviews_per_month = EXCLUDED.ai_reviews_per_month,
+                    updated_at = NOW()
+                RETURNING *
+                "#,
+                &[
+                    &project_id,
+                    &overrides.max_concurrent_sessions.map(quota_to_sql),
+                    &overrides.events_per_hour.map(quota_to_sql),
+                    &overrides.storage_bytes.map(quota_to_sql),
+                    &overrides.ai_reviews_per_month.map(quota_to_sql),
+                ],
+            )
             .await
-            .map_err(|e| crate::TodokiError::Database(e))
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(project_quotas_from_row(&row))
     }
 
-    /// List artifacts for a project
-    pub async fn list_artifacts(
+    /// Events about a project's tasks since `since`
+    pub async fn count_project_events(
         &self,
         project_id: Uuid,
-        artifact_type: Option<&str>,
-    ) -> crate::Result<Vec<Artifact>> {
+        since: DateTime<Utc>,
+    ) -> crate::Result<i64> {
         let conn = self
-            .pool
+            .read_pool()
             .get()
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
-        let rows = if let Some(atype) = artifact_type {
-            conn.query(
+        let row = conn
+            .query_one(
                 r#"
-                SELECT id, task_id, project_id, agent_id, session_id, artifact_type, data, created_at, updated_at
-                FROM artifacts
-                WHERE project_id = $1 AND artifact_type = $2
-                ORDER BY created_at DESC
+                SELECT COUNT(*) AS count
+                FROM events e
+                JOIN tasks t ON t.id = e.task_id
+                WHERE t.project_id = $1 AND e.time >= $2
                 "#,
-                &[&project_id, &atype],
+                &[&project_id, &since],
             )
             .await
-        } else {
-            conn.query(
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(row.get("count"))
+    }
+
+    /// Bytes of artifact data a project stores, email attachments included
+    pub async fn project_storage_bytes(&self, project_id: Uuid) -> crate::Result<i64> {
+        let conn = self
+            .read_pool()
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_one(
                 r#"
-                SELECT id, task_id, project_id, agent_id, session_id, artifact_type, data, created_at, updated_at
+                SELECT COALESCE(SUM(octet_length(data::text)), 0)::BIGINT AS bytes
                 FROM artifacts
-                WHERE project_id = $1
-                ORDER BY created_at DESC
+                WHERE project_id = $1 AND deleted_at IS NULL
                 "#,
                 &[&project_id],
             )
             .await
-        }
-        .map_err(|e| crate::TodokiError::Database(e))?;
+            .map_err(|e| crate::TodokiError::Database(e))?;
 
-        Ok(rows
-            .iter()
-            .map(|row| Artifact {
-                id: row.get("id"),
-                task_id: row.get("task_id"),
-                project_id: row.get("project_id"),
-                agent_id: row.get("agent_id"),
-                session_id: row.get("session_id"),
-                artifact_type: row.get("artifact_type"),
-                data: row.get("data"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-            })
-            .collect())
+        Ok(row.get("bytes"))
     }
 
-    /// Get artifact by ID
-    pub async fn get_artifact(&self, artifact_id: Uuid) -> crate::Result<Option<Artifact>> {
-        match Artifact::fetch_one_by_pk(&artifact_id, &*self.pool).await {
-            Ok(artifact) => Ok(Some(artifact)),
-            Err(conservator::Error::TooManyRows(0)) => Ok(None),
-            Err(e) => Err(crate::TodokiError::Database(e)),
-        }
+    /// Uses of `metric` counted in the period starting at `period_start`
+    pub async fn quota_usage(
+        &self,
+        project_id: Uuid,
+        metric: QuotaMetric,
+        period_start: DateTime<Utc>,
+    ) -> crate::Result<i64> {
+        let conn = self
+            .read_pool()
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_opt(
+                r#"
+                SELECT used FROM quota_usage
+                WHERE project_id = $1 AND metric = $2 AND period_start = $3
+                "#,
+                &[&project_id, &metric.as_str(), &period_start],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(row.map(|row| row.get("used")).unwrap_or(0))
     }
 
-    /// List artifacts by task ID
-    pub async fn list_artifacts_by_task(&self, task_id: Uuid) -> crate::Result<Vec<Artifact>> {
+    /// Count one use of `metric` in the period starting at `period_start`,
+    /// unless the period already used up `limit`; false when it did
+    pub async fn consume_quota(
+        &self,
+        project_id: Uuid,
+        metric: QuotaMetric,
+        period_start: DateTime<Utc>,
+        limit: Option<u64>,
+    ) -> crate::Result<bool> {
+        if limit == Some(0) {
+            return Ok(false);
+        }
         let conn = self
             .pool
             .get()
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
+        let row = conn
+            .query_opt(
+                r#"
+                INSERT INTO quota_usage (project_id, metric, period_start, used)
+                VALUES ($1, $2, $3, 1)
+                ON CONFLICT (project_id, metric, period_start) DO UPDATE
+                SET used = quota_usage.used + 1
+                WHERE $4::BIGINT IS NULL OR quota_usage.used < $4
+                RETURNING used
+                "#,
+                &[
+                    &project_id,
+                    &metric.as_str(),
+                    &period_start,
+                    &limit.map(quota_to_sql),
+                ],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(row.is_some())
+    }
+
+    // ========================================================================
+    // Watch operations
+    // ========================================================================
+
+    /// Every watch of every user
+    pub async fn list_watches(&self) -> crate::Result<Vec<Watch>> {
+        let conn = self
+            .read_pool()
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let rows = conn
+            .query("SELECT * FROM watches", &[])
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows.iter().map(watch_from_row).collect())
+    }
+
+    /// A user's watches, newest first
+    pub async fn list_user_watches(&self, user_id: &str) -> crate::Result<Vec<Watch>> {
+        let conn = self
+            .read_pool()
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
         let rows = conn
             .query(
+                "SELECT * FROM watches WHERE user_id = $1 ORDER BY created_at DESC",
+                &[&user_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows.iter().map(watch_from_row).collect())
+    }
+
+    /// Watch a task or session; an existing watch is returned as is
+    pub async fn create_watch(&self, user_id: &str, target: WatchTarget) -> crate::Result<Watch> {
+        let (task_id, session_id) = target.ids();
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_opt(
                 r#"
-                SELECT id, task_id, project_id, agent_id, session_id, artifact_type, data, created_at, updated_at
-                FROM artifacts
-                WHERE task_id = $1
-                ORDER BY created_at DESC
+                INSERT INTO watches (user_id, task_id, session_id)
+                VALUES ($1, $2, $3)
+                ON CONFLICT DO NOTHING
+                RETURNING *
+                "#,
+                &[&user_id, &task_id, &session_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        if let Some(row) = row {
+            return Ok(watch_from_row(&row));
+        }
+
+        let row = conn
+            .query_one(
+                r#"
+                SELECT * FROM watches
+                WHERE user_id = $1
+                  AND task_id IS NOT DISTINCT FROM $2
+                  AND session_id IS NOT DISTINCT FROM $3
+                "#,
+                &[&user_id, &task_id, &session_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        Ok(watch_from_row(&row))
+    }
+
+    /// Stop a user watching `target`; `None` when they did not watch it
+    pub async fn delete_target_watch(
+        &self,
+        user_id: &str,
+        target: WatchTarget,
+    ) -> crate::Result<Option<Watch>> {
+        let (task_id, session_id) = target.ids();
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_opt(
+                r#"
+                DELETE FROM watches
+                WHERE user_id = $1
+                  AND task_id IS NOT DISTINCT FROM $2
+                  AND session_id IS NOT DISTINCT FROM $3
+                RETURNING *
                 "#,
+                &[&user_id, &task_id, &session_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(row.as_ref().map(watch_from_row))
+    }
+
+    /// Remove one of a user's watches; `None` when they have no such watch
+    pub async fn delete_watch(
+        &self,
+        watch_id: Uuid,
+        user_id: &str,
+    ) -> crate::Result<Option<Watch>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_opt(
+                "DELETE FROM watches WHERE id = $1 AND user_id = $2 RETURNING *",
+                &[&watch_id, &user_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(row.as_ref().map(watch_from_row))
+    }
+
+    // ========================================================================
+    // Time entry operations
+    // ========================================================================
+
+    /// Time people logged on a task, newest first
+    pub async fn list_time_entries(&self, task_id: Uuid) -> crate::Result<Vec<TaskTimeEntry>> {
+        let conn = self
+            .read_pool()
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let rows = conn
+            .query(
+                "SELECT * FROM task_time_entries WHERE task_id = $1 ORDER BY started_at DESC",
                 &[&task_id],
             )
             .await
             .map_err(|e| crate::TodokiError::Database(e))?;
 
+        Ok(rows.iter().map(time_entry_from_row).collect())
+    }
+
+    /// Log a finished span of work on a task
+    pub async fn create_time_entry(
+        &self,
+        task_id: Uuid,
+        user_id: &str,
+        started_at: DateTime<Utc>,
+        ended_at: DateTime<Utc>,
+        note: &str,
+    ) -> crate::Result<TaskTimeEntry> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_one(
+                r#"
+                INSERT INTO task_time_entries
+                    (task_id, project_id, user_id, started_at, ended_at, note)
+                SELECT id, project_id, $2, $3, $4, $5 FROM tasks WHERE id = $1
+                RETURNING *
+                "#,
+                &[&task_id, &user_id, &started_at, &ended_at, &note],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(time_entry_from_row(&row))
+    }
+
+    /// Start the user's timer on a task, stopping the one they run on any
+    /// other task; a timer already running on this task is returned as is
+    pub async fn start_timer(
+        &self,
+        task_id: Uuid,
+        user_id: &str,
+        note: &str,
+    ) -> crate::Result<TaskTimeEntry> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        conn.execute(
+            r#"
+            UPDATE task_time_entries SET ended_at = NOW()
+            WHERE user_id = $1 AND ended_at IS NULL AND task_id <> $2
+            "#,
+            &[&user_id, &task_id],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_opt(
+                r#"
+                INSERT INTO task_time_entries (task_id, project_id, user_id, started_at, note)
+                SELECT id, project_id, $2, NOW(), $3 FROM tasks WHERE id = $1
+                ON CONFLICT (user_id) WHERE ended_at IS NULL DO NOTHING
+                RETURNING *
+                "#,
+                &[&task_id, &user_id, &note],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        if let Some(row) = row {
+            return Ok(time_entry_from_row(&row));
+        }
+
+        let row = conn
+            .query_one(
+                "SELECT * FROM task_time_entries WHERE user_id = $1 AND ended_at IS NULL",
+                &[&user_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        Ok(time_entry_from_row(&row))
+    }
+
+    /// Stop the user's timer on a task; `None` when none runs
+    pub async fn stop_timer(
+        &self,
+        task_id: Uuid,
+        user_id: &str,
+    ) -> crate::Result<Option<TaskTimeEntry>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_opt(
+                r#"
+                UPDATE task_time_entries SET ended_at = NOW()
+                WHERE task_id = $1 AND user_id = $2 AND ended_at IS NULL
+                RETURNING *
+                "#,
+                &[&task_id, &user_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(row.as_ref().map(time_entry_from_row))
+    }
+
+    /// Remove a time entry of a task; returns whether it existed
+    pub async fn delete_time_entry(&self, task_id: Uuid, entry_id: Uuid) -> crate::Result<bool> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let deleted = conn
+            .execute(
+                "DELETE FROM task_time_entries WHERE id = $1 AND task_id = $2",
+                &[&entry_id, &task_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(deleted > 0)
+    }
+
+    /// Finished human entries and agent sessions of a project that started
+    /// in `[since, until)`
+    pub async fn list_project_time_samples(
+        &self,
+        project_id: Uuid,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> crate::Result<Vec<TimeSample>> {
+        let conn = self
+            .read_pool()
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let rows = conn
+            .query(
+                r#"
+                WITH spans AS (
+                    SELECT task_id, 'human' AS source, user_id AS key,
+                           EXTRACT(EPOCH FROM ended_at - started_at)::BIGINT AS secs
+                    FROM task_time_entries
+                    WHERE project_id = $1 AND ended_at IS NOT NULL
+                      AND started_at >= $2 AND started_at < $3
+                    UNION ALL
+                    SELECT task_id, 'agent', role, duration_secs
+                    FROM task_session_durations
+                    WHERE project_id = $1 AND spawned_at >= $2 AND spawned_at < $3
+                )
+                SELECT s.task_id, s.source, s.key, s.secs, t.content, t.estimate_minutes
+                FROM spans s
+                JOIN tasks t ON t.id = s.task_id
+                "#,
+                &[&project_id, &since, &until],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
         Ok(rows
             .iter()
-            .map(|row| Artifact {
-                id: row.get("id"),
+            .map(|row| TimeSample {
                 task_id: row.get("task_id"),
-                project_id: row.get("project_id"),
-                agent_id: row.get("agent_id"),
-                session_id: row.get("session_id"),
-                artifact_type: row.get("artifact_type"),
-                data: row.get("data"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
+                title: row.get("content"),
+                estimate_minutes: row.get("estimate_minutes"),
+                source: match row.get::<_, &str>("source") {
+                    "agent" => TimeSource::Agent,
+                    _ => TimeSource::Human,
+                },
+                key: row.get("key"),
+                secs: row.get("secs"),
             })
             .collect())
     }
 
+    // ========================================================================
+    // Agent operations
+    // ========================================================================
+
+    /// Create a new agent
+    pub async fn create_agent(&self, create: CreateAgent) -> crate::Result<Agent> {
+        let agent_id = create
+            .insert::<Agent>()
+            .returning_pk(&*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Agent::fetch_one_by_pk(&agent_id, &*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))
+    }
+
+    /// List all agents
+    pub async fn list_agents(&self) -> crate::Result<Vec<Agent>> {
+        Agent::fetch_all(&*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))
+    }
+
+    /// Get agent by ID
+    pub async fn get_agent(&self, agent_id: Uuid) -> crate::Result<Option<Agent>> {
+        match Agent::fetch_one_by_pk(&agent_id, &*self.pool).await {
+            Ok(agent) => Ok(Some(agent)),
+            Err(conservator::Error::TooManyRows(0)) => Ok(None),
+            Err(e) => Err(crate::TodokiError::Database(e)),
+        }
+    }
+
+    /// Update agent status
+    pub async fn update_agent_status(
+        &self,
+        agent_id: Uuid,
+        status: AgentStatus,
+    ) -> crate::Result<()> {
+        let mut agent = Agent::fetch_one_by_pk(&agent_id, &*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        agent.status = status;
+        agent.updated_at = Utc::now();
+        agent
+            .save(&*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(())
+    }
+
+    /// Record the latest probe result of an agent's session
+    pub async fn update_agent_health(
+        &self,
+        agent_id: Uuid,
+        health: AgentHealth,
+        probe_failures: i32,
+    ) -> crate::Result<()> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        conn.execute(
+            r#"
+            UPDATE agents
+            SET health = $2, probe_failures = $3, health_checked_at = NOW()
+            WHERE id = $1
+            "#,
+            &[&agent_id, &SqlTypeWrapper(health), &probe_failures],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(())
+    }
+
+    /// Agents with auto-trigger enabled
+    pub async fn list_auto_trigger_agents(&self) -> crate::Result<Vec<Agent>> {
+        Agent::select()
+            .filter(Agent::COLUMNS.auto_trigger.eq(true))
+            .all(&*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))
+    }
+
+    /// Open tasks and recent results in `project_id` of each agent, for
+    /// picking who gets an unassigned task
+    pub async fn list_agent_track_records(
+        &self,
+        agent_ids: &[Uuid],
+        project_id: Uuid,
+        history_days: u32,
+    ) -> crate::Result<HashMap<Uuid, AgentTrackRecord>> {
+        let conn = self
+            .read_pool()
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let history_days = history_days.min(i32::MAX as u32) as i32;
+        let rows = conn
+            .query(
+                r#"
+                WITH finished AS (
+                    SELECT d.agent_id, s.status
+                    FROM agent_dispatches d
+                    JOIN agent_sessions s ON s.id = d.session_id
+                    JOIN events e ON e.cursor = d.event_cursor
+                    JOIN tasks t ON t.id = e.task_id
+                    WHERE d.agent_id = ANY($1) AND t.project_id = $2
+                      AND s.ended_at IS NOT NULL
+                      AND d.dispatched_at >= NOW() - make_interval(days => $3)
+                )
+                SELECT a.id,
+                       (SELECT COUNT(*) FROM tasks t
+                        WHERE t.agent_id = a.id AND t.status <> 'done' AND NOT t.archived
+                       ) AS open_tasks,
+                       (SELECT COUNT(*) FROM finished f WHERE f.agent_id = a.id
+                       ) AS finished_sessions,
+                       (SELECT COUNT(*) FROM finished f
+                        WHERE f.agent_id = a.id AND f.status = 'completed'
+                       ) AS completed_sessions
+                FROM agents a
+                WHERE a.id = ANY($1)
+                "#,
+                &[&agent_ids, &project_id, &history_days],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let record = AgentTrackRecord {
+                    open_tasks: row.get("open_tasks"),
+                    finished_sessions: row.get("finished_sessions"),
+                    completed_sessions: row.get("completed_sessions"),
+                };
+                (row.get("id"), record)
+            })
+            .collect())
+    }
+
+    /// Per-agent counts behind the scoreboard, for one agent or every agent
+    /// (of a project)
+    ///
+    /// Sessions count when they ended at or after `since`; QA verdicts and
+    /// permission requests when they were recorded at or after it. A task's
+    /// QA verdict counts for every agent that emitted events for it.
+    pub async fn list_agent_stats(
+        &self,
+        agent_id: Option<Uuid>,
+        project_id: Option<Uuid>,
+        since: DateTime<Utc>,
+    ) -> crate::Result<Vec<AgentStatCounts>> {
+        let conn = self
+            .read_pool()
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        // A denial is a response that cancelled the request or picked an option
+        // whose kind is reject_*/deny, as in the digest
+        let rows = conn
+            .query(
+                r#"
+                WITH scope AS (
+                    SELECT a.id, a.name, a.role, a.project_id
+                    FROM agents a
+                    WHERE ($1::uuid IS NULL OR a.id = $1)
+                      AND ($2::uuid IS NULL OR a.project_id = $2)
+                ),
+                sessions AS (
+                    SELECT s.agent_id,
+                           COUNT(*) AS sessions,
+                           COUNT(*) FILTER (WHERE s.status = 'completed') AS completed_sessions,
+                           COUNT(*) FILTER (WHERE s.status = 'failed') AS failed_sessions,
+                           AVG(EXTRACT(EPOCH FROM s.ended_at - s.started_at))::float8
+                               AS avg_session_secs
+                    FROM agent_sessions s
+                    JOIN scope ON scope.id = s.agent_id
+                    WHERE s.ended_at IS NOT NULL AND s.ended_at >= $3
+                    GROUP BY s.agent_id
+                ),
+                verdicts AS (
+                    SELECT DISTINCT ON (e.task_id)
+                           e.task_id, e.kind = 'agent.qa_test_passed' AS passed
+                    FROM events e
+                    WHERE e.kind IN ('agent.qa_test_passed', 'agent.qa_test_failed')
+                      AND e.task_id IS NOT NULL AND e.time >= $3
+                    ORDER BY e.task_id, e.cursor
+                ),
+                worked AS (
+                    SELECT DISTINCT e.agent_id, e.task_id
+                    FROM events e
+                    JOIN scope ON scope.id = e.agent_id
+                    WHERE e.task_id IS NOT NULL AND e.time >= $3
+                      AND e.kind NOT IN ('agent.qa_test_passed', 'agent.qa_test_failed')
+                ),
+                qa AS (
+                    SELECT w.agent_id,
+                           COUNT(*) AS qa_tasks,
+                           COUNT(*) FILTER (WHERE v.passed) AS qa_first_pass
+                    FROM worked w
+                    JOIN verdicts v ON v.task_id = w.task_id
+                    GROUP BY w.agent_id
+                ),
+                permissions AS (
+                    SELECT s.agent_id,
+                           COUNT(*) AS permission_requests,
+                           COUNT(*) FILTER (WHERE denied.yes) AS permission_denials
+                    FROM events req
+                    JOIN agent_sessions s ON s.id::text = req.data->>'session_id'
+                    JOIN scope ON scope.id = s.agent_id
+                    LEFT JOIN LATERAL (
+                        SELECT true AS yes
+                        FROM events resp
+                        LEFT JOIN LATERAL (
+                            SELECT o AS opt
+                            FROM jsonb_array_elements(req.data->'options') o
+                            WHERE o->>'option_id' = resp.data->'outcome'->'selected'->>'option_id'
+                            LIMIT 1
+                        ) sel ON true
+                        WHERE resp.kind = 'permission.responded'
+                          AND resp.data->>'request_id' = req.data->>'request_id'
+                          AND resp.time >= req.time
+                          AND (resp.data->'outcome' ? 'cancelled'
+                            OR sel.opt->>'kind' LIKE 'reject%'
+                            OR sel.opt->>'kind' = 'deny')
+                        LIMIT 1
+                    ) denied ON true
+                    WHERE req.kind = 'permission.requested' AND req.time >= $3
+                    GROUP BY s.agent_id
+                )
+                SELECT scope.id, scope.name, scope.role, scope.project_id,
+                       COALESCE(sessions.sessions, 0) AS sessions,
+                       COALESCE(sessions.completed_sessions, 0) AS completed_sessions,
+                       COALESCE(sessions.failed_sessions, 0) AS failed_sessions,
+                       sessions.avg_session_secs,
+                       COALESCE(qa.qa_tasks, 0) AS qa_tasks,
+                       COALESCE(qa.qa_first_pass, 0) AS qa_first_pass,
+                       COALESCE(permissions.permission_requests, 0) AS permission_requests,
+                       COALESCE(permissions.permission_denials, 0) AS permission_denials
+                FROM scope
+                LEFT JOIN sessions ON sessions.agent_id = scope.id
+                LEFT JOIN qa ON qa.agent_id = scope.id
+                LEFT JOIN permissions ON permissions.agent_id = scope.id
+                ORDER BY scope.name ASC
+                "#,
+                &[&agent_id, &project_id, &since],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| AgentStatCounts {
+                agent_id: row.get("id"),
+                name: row.get("name"),
+                role: row.get::<_, SqlTypeWrapper<AgentRole>>("role").0,
+                project_id: row.get("project_id"),
+                sessions: row.get("sessions"),
+                completed_sessions: row.get("completed_sessions"),
+                failed_sessions: row.get("failed_sessions"),
+                avg_session_secs: row.get("avg_session_secs"),
+                qa_tasks: row.get("qa_tasks"),
+                qa_first_pass: row.get("qa_first_pass"),
+                permission_requests: row.get("permission_requests"),
+                permission_denials: row.get("permission_denials"),
+            })
+            .collect())
+    }
+
+    /// Replace an agent's event subscriptions
+    pub async fn update_agent_subscriptions(
+        &self,
+        agent_id: Uuid,
+        subscribed_events: Vec<String>,
+        auto_trigger: bool,
+    ) -> crate::Result<Agent> {
+        let mut agent = Agent::fetch_one_by_pk(&agent_id, &*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        agent.subscribed_events = subscribed_events;
+        agent.auto_trigger = auto_trigger;
+        agent.updated_at = Utc::now();
+        agent
+            .save(&*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(agent)
+    }
+
+    /// Record the cursor of an event that triggered the agent; never moves backwards
+    pub async fn update_agent_last_cursor(&self, agent_id: Uuid, cursor: i64) -> crate::Result<()> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        conn.execute(
+            "UPDATE agents SET last_cursor = GREATEST(last_cursor, $2) WHERE id = $1",
+            &[&agent_id, &cursor],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(())
+    }
+
+    /// Delete agent
+    pub async fn delete_agent(&self, agent_id: Uuid) -> crate::Result<()> {
+        Agent::delete_by_pk(&agent_id, &*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(())
+    }
+
+    // ========================================================================
+    // Agent Session operations
+    // ========================================================================
+
+    /// Create a new agent session
+    pub async fn create_agent_session(&self, agent_id: Uuid) -> crate::Result<AgentSession> {
+        let create = CreateAgentSession { agent_id };
+
+        let session_id = create
+            .insert::<AgentSession>()
+            .returning_pk(&*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        AgentSession::fetch_one_by_pk(&session_id, &*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))
+    }
+
+    /// Update session status
+    pub async fn update_session_status(
+        &self,
+        session_id: Uuid,
+        status: SessionStatus,
+    ) -> crate::Result<()> {
+        let mut session = AgentSession::fetch_one_by_pk(&session_id, &*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        session.status = status;
+        if status != SessionStatus::Running {
+            session.ended_at = Some(Utc::now());
+        }
+
+        session
+            .save(&*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(())
+    }
+
+    /// Store the latest resource usage sample of a session
+    pub async fn update_session_resource_usage(
+        &self,
+        session_id: Uuid,
+        usage: &ResourceUsage,
+    ) -> crate::Result<()> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let usage = serde_json::to_value(usage).unwrap_or(Value::Null);
+        conn.execute(
+            "UPDATE agent_sessions SET resource_usage = $2 WHERE id = $1",
+            &[&session_id, &usage],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(())
+    }
+
+    /// Get a single session by ID
+    pub async fn get_agent_session(&self, session_id: Uuid) -> crate::Result<Option<AgentSession>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_opt(
+                r#"
+                SELECT id, agent_id, status, started_at, ended_at, correlation_id, resource_usage
+                FROM agent_sessions
+                WHERE id = $1
+                "#,
+                &[&session_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(row.map(|r| AgentSession {
+            id: r.get("id"),
+            agent_id: r.get("agent_id"),
+            status: r.get::<_, SqlTypeWrapper<SessionStatus>>("status").0,
+            started_at: r.get("started_at"),
+            ended_at: r.get("ended_at"),
+            correlation_id: r.get("correlation_id"),
+            resource_usage: r.get("resource_usage"),
+        }))
+    }
+
+    /// Get running session for an agent (if any)
+    pub async fn get_agent_running_session(&self, agent_id: Uuid) -> crate::Result<Option<AgentSession>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_opt(
+                r#"
+                SELECT id, agent_id, status, started_at, ended_at, correlation_id, resource_usage
+                FROM agent_sessions
+                WHERE agent_id = $1 AND status = 'running'
+                ORDER BY started_at DESC
+                LIMIT 1
+                "#,
+                &[&agent_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(row.map(|r| AgentSession {
+            id: r.get("id"),
+            agent_id: r.get("agent_id"),
+            status: r.get::<_, SqlTypeWrapper<SessionStatus>>("status").0,
+            started_at: r.get("started_at"),
+            ended_at: r.get("ended_at"),
+            correlation_id: r.get("correlation_id"),
+            resource_usage: r.get("resource_usage"),
+        }))
+    }
+
+    /// Get sessions for an agent
+    pub async fn get_agent_sessions(&self, agent_id: Uuid) -> crate::Result<Vec<AgentSession>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let rows = conn
+            .query(
+                r#"
+                SELECT id, agent_id, status, started_at, ended_at, correlation_id, resource_usage
+                FROM agent_sessions
+                WHERE agent_id = $1
+                ORDER BY started_at DESC
+                "#,
+                &[&agent_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| AgentSession {
+                id: row.get("id"),
+                agent_id: row.get("agent_id"),
+                status: row.get::<_, SqlTypeWrapper<SessionStatus>>("status").0,
+                started_at: row.get("started_at"),
+                ended_at: row.get("ended_at"),
+                correlation_id: row.get("correlation_id"),
+                resource_usage: row.get("resource_usage"),
+            })
+            .collect())
+    }
+
+    /// Correlation ID of a session's execution
+    pub async fn get_session_correlation(&self, session_id: Uuid) -> crate::Result<Option<Uuid>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_opt(
+                "SELECT correlation_id FROM agent_sessions WHERE id = $1",
+                &[&session_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(row.map(|r| r.get("correlation_id")))
+    }
+
+    /// Mark running sessions as exited on startup
+    pub async fn mark_sessions_exited_on_startup(&self) -> crate::Result<()> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        conn.execute(
+            r#"
+            UPDATE agent_sessions
+            SET status = 'exited', ended_at = NOW()
+            WHERE status = 'running'
+            "#,
+            &[],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        conn.execute(
+            r#"
+            UPDATE agents
+            SET status = 'exited', updated_at = NOW()
+            WHERE status = 'running'
+            "#,
+            &[],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(())
+    }
+
+    // ========================================================================
+    // Agent dispatch operations
+    // ========================================================================
+
+    /// Claim the right to trigger `agent_id` for the event at `event_cursor`
+    ///
+    /// Returns false when the agent was already triggered for that event, so
+    /// callers can skip the spawn; concurrent claims are settled by the
+    /// primary key rather than by the caller.
+    pub async fn claim_agent_dispatch(
+        &self,
+        agent_id: Uuid,
+        event_cursor: i64,
+    ) -> crate::Result<bool> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let inserted = conn
+            .execute(
+                r#"
+                INSERT INTO agent_dispatches (agent_id, event_cursor)
+                VALUES ($1, $2)
+                ON CONFLICT (agent_id, event_cursor) DO NOTHING
+                "#,
+                &[&agent_id, &event_cursor],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(inserted > 0)
+    }
+
+    /// Link a claimed dispatch to the session it started
+    pub async fn set_dispatch_session(
+        &self,
+        agent_id: Uuid,
+        event_cursor: i64,
+        session_id: Uuid,
+    ) -> crate::Result<()> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        conn.execute(
+            r#"
+            UPDATE agent_dispatches
+            SET session_id = $3
+            WHERE agent_id = $1 AND event_cursor = $2
+            "#,
+            &[&agent_id, &event_cursor, &session_id],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(())
+    }
+
+    // ========================================================================
+    // Artifact operations
+    // ========================================================================
+
+    /// Create a new artifact
+    pub async fn create_artifact(
+        &self,
+        task_id: Uuid,
+        project_id: Uuid,
+        agent_id: Option<Uuid>,
+        session_id: Option<Uuid>,
+        artifact_type: &str,
+        data: Value,
+    ) -> crate::Result<Artifact> {
+        let create = CreateArtifact::new(task_id, project_id, agent_id, session_id, artifact_type, data);
+
+        let artifact_id = create
+            .insert::<Artifact>()
+            .returning_pk(&*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Artifact::fetch_one_by_pk(&artifact_id, &*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))
+    }
+
+    /// List artifacts for a project
+    pub async fn list_artifacts(
+        &self,
+        project_id: Uuid,
+        artifact_type: Option<&str>,
+    ) -> crate::Result<Vec<Artifact>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let rows = if let Some(atype) = artifact_type {
+            conn.query(
+                r#"
+                SELECT id, task_id, project_id, agent_id, session_id, artifact_type, data, created_at, updated_at
+                FROM artifacts
+                WHERE project_id = $1 AND artifact_type = $2 AND hidden = false
+                  AND deleted_at IS NULL
+                ORDER BY created_at DESC
+                "#,
+                &[&project_id, &atype],
+            )
+            .await
+        } else {
+            conn.query(
+                r#"
+                SELECT id, task_id, project_id, agent_id, session_id, artifact_type, data, created_at, updated_at
+                FROM artifacts
+                WHERE project_id = $1 AND hidden = false AND deleted_at IS NULL
+                ORDER BY created_at DESC
+                "#,
+                &[&project_id],
+            )
+            .await
+        }
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| Artifact {
+                id: row.get("id"),
+                task_id: row.get("task_id"),
+                project_id: row.get("project_id"),
+                agent_id: row.get("agent_id"),
+                session_id: row.get("session_id"),
+                artifact_type: row.get("artifact_type"),
+                data: row.get("data"),
+                created_at: row.get("created_at"),
+                updated_at: row.get("updated_at"),
+            })
+            .collect())
+    }
+
+    /// Get artifact by ID; deleted artifacts are not found
+    pub async fn get_artifact(&self, artifact_id: Uuid) -> crate::Result<Option<Artifact>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_opt(
+                r#"
+                SELECT id, task_id, project_id, agent_id, session_id, artifact_type, data, created_at, updated_at
+                FROM artifacts
+                WHERE id = $1 AND deleted_at IS NULL
+                "#,
+                &[&artifact_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(row.map(|row| artifact_from_row(&row)))
+    }
+
+    /// Replace an artifact's data and move it to `task` (task and project
+    /// ID) if given; `None` when it does not exist or was deleted
+    pub async fn update_artifact(
+        &self,
+        artifact_id: Uuid,
+        data: Value,
+        task: Option<(Uuid, Uuid)>,
+    ) -> crate::Result<Option<Artifact>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let (task_id, project_id) = task.unzip();
+        let row = conn
+            .query_opt(
+                r#"
+                UPDATE artifacts
+                SET data = $2,
+                    task_id = COALESCE($3, task_id),
+                    project_id = COALESCE($4, project_id),
+                    updated_at = NOW()
+                WHERE id = $1 AND deleted_at IS NULL
+                RETURNING id, task_id, project_id, agent_id, session_id, artifact_type, data, created_at, updated_at
+                "#,
+                &[&artifact_id, &data, &task_id, &project_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(row.map(|row| artifact_from_row(&row)))
+    }
+
+    /// Soft-delete an artifact; false when it does not exist or was deleted
+    /// already
+    pub async fn delete_artifact(&self, artifact_id: Uuid) -> crate::Result<bool> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let count = conn
+            .execute(
+                "UPDATE artifacts SET deleted_at = NOW(), updated_at = NOW() \
+                 WHERE id = $1 AND deleted_at IS NULL",
+                &[&artifact_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        Ok(count > 0)
+    }
+
+    /// Link an artifact to another task; false when it was linked already
+    pub async fn link_artifact(&self, artifact_id: Uuid, task_id: Uuid) -> crate::Result<bool> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let count = conn
+            .execute(
+                "INSERT INTO artifact_links (artifact_id, task_id) VALUES ($1, $2) \
+                 ON CONFLICT DO NOTHING",
+                &[&artifact_id, &task_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        Ok(count > 0)
+    }
+
+    /// Remove a link made by `link_artifact`; false when there was none
+    pub async fn unlink_artifact(&self, artifact_id: Uuid, task_id: Uuid) -> crate::Result<bool> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let count = conn
+            .execute(
+                "DELETE FROM artifact_links WHERE artifact_id = $1 AND task_id = $2",
+                &[&artifact_id, &task_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        Ok(count > 0)
+    }
+
+    /// Artifacts of `artifact_type` whose data has `url`
+    pub async fn list_artifacts_by_url(
+        &self,
+        artifact_type: &str,
+        url: &str,
+    ) -> crate::Result<Vec<Artifact>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let rows = conn
+            .query(
+                r#"
+                SELECT id, task_id, project_id, agent_id, session_id, artifact_type, data, created_at, updated_at
+                FROM artifacts
+                WHERE artifact_type = $1 AND data->>'url' = $2 AND deleted_at IS NULL
+                ORDER BY created_at DESC
+                "#,
+                &[&artifact_type, &url],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows.iter().map(artifact_from_row).collect())
+    }
+
+    /// `branch` artifacts of GitHub repositories created since `since` whose
+    /// CI has not finished, newest first
+    pub async fn list_pending_branches(
+        &self,
+        since: DateTime<Utc>,
+        limit: i64,
+    ) -> crate::Result<Vec<Artifact>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let rows = conn
+            .query(
+                r#"
+                SELECT id, task_id, project_id, agent_id, session_id, artifact_type, data, created_at, updated_at
+                FROM artifacts
+                WHERE artifact_type = 'branch'
+                  AND deleted_at IS NULL
+                  AND created_at >= $1
+                  AND data ? 'owner'
+                  AND COALESCE(data->'ci'->>'status', 'pending') = 'pending'
+                ORDER BY created_at DESC
+                LIMIT $2
+                "#,
+                &[&since, &limit],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows.iter().map(artifact_from_row).collect())
+    }
+
+    /// List artifacts of a task, including those linked to it
+    pub async fn list_artifacts_by_task(&self, task_id: Uuid) -> crate::Result<Vec<Artifact>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let rows = conn
+            .query(
+                r#"
+                SELECT id, task_id, project_id, agent_id, session_id, artifact_type, data, created_at, updated_at
+                FROM artifacts
+                WHERE (task_id = $1
+                       OR id IN (SELECT artifact_id FROM artifact_links WHERE task_id = $1))
+                  AND deleted_at IS NULL
+                ORDER BY created_at DESC
+                "#,
+                &[&task_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| Artifact {
+                id: row.get("id"),
+                task_id: row.get("task_id"),
+                project_id: row.get("project_id"),
+                agent_id: row.get("agent_id"),
+                session_id: row.get("session_id"),
+                artifact_type: row.get("artifact_type"),
+                data: row.get("data"),
+                created_at: row.get("created_at"),
+                updated_at: row.get("updated_at"),
+            })
+            .collect())
+    }
+
+    // ========================================================================
+    // Digest operations
+    // ========================================================================
+
+    /// Aggregate task completions, failed sessions, PR artifacts and permission
+    /// denials between `since` and `until` for the daily digest
+    pub async fn get_digest_activity(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> crate::Result<DigestActivity> {
+        let conn = self
+            .read_pool()
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let completed_rows = conn
+            .query(
+                r#"
+                SELECT DISTINCT ON (t.id) t.id, t.content, p.name AS project_name, e.datetime AS completed_at
+                FROM tasks t
+                JOIN projects p ON p.id = t.project_id
+                JOIN task_events e ON t.id = e.task_id
+                WHERE t.status = 'done'
+                  AND e.event_type = 'StatusChange'
+                  AND e.state = 'done'
+                  AND e.datetime >= $1 AND e.datetime < $2
+                ORDER BY t.id, e.datetime DESC
+                "#,
+                &[&since, &until],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let mut completed_tasks: Vec<DigestCompletedTask> = completed_rows
+            .iter()
+            .map(|row| DigestCompletedTask {
+                task_id: row.get("id"),
+                content: row.get("content"),
+                project_name: row.get("project_name"),
+                completed_at: row.get("completed_at"),
+            })
+            .collect();
+        completed_tasks.sort_by_key(|t| t.completed_at);
+
+        let failed_rows = conn
+            .query(
+                r#"
+                SELECT s.id, a.name AS agent_name, s.ended_at
+                FROM agent_sessions s
+                JOIN agents a ON a.id = s.agent_id
+                WHERE s.status = 'failed'
+                  AND COALESCE(s.ended_at, s.started_at) >= $1
+                  AND COALESCE(s.ended_at, s.started_at) < $2
+                ORDER BY s.started_at
+                "#,
+                &[&since, &until],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let failed_sessions = failed_rows
+            .iter()
+            .map(|row| DigestFailedSession {
+                session_id: row.get("id"),
+                agent_name: row.get("agent_name"),
+                ended_at: row.get("ended_at"),
+            })
+            .collect();
+
+        let pr_rows = conn
+            .query(
+                r#"
+                SELECT id, task_id, project_id, agent_id, session_id, artifact_type, data, created_at, updated_at
+                FROM artifacts
+                WHERE artifact_type = 'github_pr'
+                  AND hidden = false
+                  AND deleted_at IS NULL
+                  AND ((created_at >= $1 AND created_at < $2)
+                    OR (data->>'state' = 'merged' AND updated_at >= $1 AND updated_at < $2))
+                ORDER BY created_at
+                "#,
+                &[&since, &until],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let pull_requests = pr_rows
+            .iter()
+            .map(|row| Artifact {
+                id: row.get("id"),
+                task_id: row.get("task_id"),
+                project_id: row.get("project_id"),
+                agent_id: row.get("agent_id"),
+                session_id: row.get("session_id"),
+                artifact_type: row.get("artifact_type"),
+                data: row.get("data"),
+                created_at: row.get("created_at"),
+                updated_at: row.get("updated_at"),
+            })
+            .collect();
+
+        // A denial is a response that cancelled the request or picked an option
+        // whose kind is reject_*/deny on the matching permission.requested event
+        let denial_rows = conn
+            .query(
+                r#"
+                SELECT resp.time,
+                       resp.data->>'session_id' AS session_id,
+                       COALESCE(req.data->'tool_call'->>'title', '') AS tool_title,
+                       COALESCE(sel.opt->>'name', 'cancelled') AS choice
+                FROM events resp
+                JOIN events req
+                  ON req.kind = 'permission.requested'
+                 AND req.data->>'request_id' = resp.data->>'request_id'
+                 AND req.time >= $1 - INTERVAL '1 day'
+                LEFT JOIN LATERAL (
+                    SELECT o AS opt
+                    FROM jsonb_array_elements(req.data->'options') o
+                    WHERE o->>'option_id' = resp.data->'outcome'->'selected'->>'option_id'
+                    LIMIT 1
+                ) sel ON true
+                WHERE resp.kind = 'permission.responded'
+                  AND resp.time >= $1 AND resp.time < $2
+                  AND (resp.data->'outcome' ? 'cancelled'
+                    OR sel.opt->>'kind' LIKE 'reject%'
+                    OR sel.opt->>'kind' = 'deny')
+                ORDER BY resp.time
+                "#,
+                &[&since, &until],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let permission_denials = denial_rows
+            .iter()
+            .map(|row| DigestPermissionDenial {
+                session_id: row.get::<_, Option<String>>("session_id").unwrap_or_default(),
+                tool_title: row.get("tool_title"),
+                choice: row.get("choice"),
+                responded_at: row.get("time"),
+            })
+            .collect();
+
+        Ok(DigestActivity {
+            completed_tasks,
+            failed_sessions,
+            pull_requests,
+            permission_denials,
+        })
+    }
+
+    // ========================================================================
+    // Execution history operations
+    // ========================================================================
+
+    /// Every session spawned for a task with its spawn parameters, outcome,
+    /// artifacts and permission decisions, oldest first
+    ///
+    /// Sessions are found through their `relay.spawn_requested` events, so
+    /// executions older than the event retention window are not listed.
+    pub async fn get_task_execution_history(
+        &self,
+        task_id: Uuid,
+    ) -> crate::Result<TaskExecutionHistory> {
+        let conn = self
+            .read_pool()
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let spawn_rows = conn
+            .query(
+                r#"
+                SELECT time, data
+                FROM events
+                WHERE task_id = $1 AND kind = 'relay.spawn_requested'
+                ORDER BY cursor
+                "#,
+                &[&task_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let mut spawns = Vec::new();
+        for row in &spawn_rows {
+            let time: DateTime<Utc> = row.get("time");
+            let Ok(spawn) = serde_json::from_value::<RelaySpawnRequestedData>(row.get("data"))
+            else {
+                continue;
+            };
+            let Ok(session_id) = Uuid::parse_str(&spawn.session_id) else {
+                continue;
+            };
+            spawns.push((time, session_id, spawn));
+        }
+        let Some(since) = spawns.first().map(|(time, _, _)| *time) else {
+            return Ok(TaskExecutionHistory {
+                task_id,
+                executions: Vec::new(),
+            });
+        };
+
+        let session_ids: Vec<Uuid> = spawns.iter().map(|(_, id, _)| *id).collect();
+        let session_keys: Vec<String> = session_ids.iter().map(Uuid::to_string).collect();
+
+        let session_rows = conn
+            .query(
+                r#"
+                SELECT s.id, s.status, s.started_at, s.ended_at, s.resource_usage,
+                       a.name AS agent_name
+                FROM agent_sessions s
+                LEFT JOIN agents a ON a.id = s.agent_id
+                WHERE s.id = ANY($1)
+                "#,
+                &[&session_ids],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let sessions: HashMap<Uuid, _> = session_rows
+            .iter()
+            .map(|row| (row.get::<_, Uuid>("id"), row))
+            .collect();
+
+        let outcome_rows = conn
+            .query(
+                r#"
+                SELECT kind, data->>'session_id' AS session_id,
+                       (data->>'exit_code')::INTEGER AS exit_code,
+                       data->>'error' AS error
+                FROM events
+                WHERE kind IN ('agent.session_exited', 'relay.spawn_failed')
+                  AND data->>'session_id' = ANY($1)
+                  AND time >= $2
+                ORDER BY cursor
+                "#,
+                &[&session_keys, &since],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        // (exit code, spawn error) by session
+        let mut outcomes: HashMap<String, (Option<i32>, Option<String>)> = HashMap::new();
+        for row in &outcome_rows {
+            let outcome = outcomes.entry(row.get("session_id")).or_default();
+            if row.get::<_, String>("kind") == "relay.spawn_failed" {
+                outcome.1 = row.get("error");
+            } else {
+                outcome.0 = row.get("exit_code");
+            }
+        }
+
+        let artifact_rows = conn
+            .query(
+                r#"
+                SELECT session_id, COUNT(*) AS count
+                FROM artifacts
+                WHERE session_id = ANY($1) AND deleted_at IS NULL
+                GROUP BY session_id
+                "#,
+                &[&session_ids],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let artifact_counts: HashMap<Uuid, i64> = artifact_rows
+            .iter()
+            .map(|row| (row.get("session_id"), row.get("count")))
+            .collect();
+
+        // The first response to a request decides it; the selected option
+        // is looked up on the request to name the decision. Requests that
+        // expired before anyone answered are decided "expired".
+        let permission_rows = conn
+            .query(
+                r#"
+                SELECT req.time AS requested_at,
+                       req.data->>'session_id' AS session_id,
+                       req.data->>'request_id' AS request_id,
+                       COALESCE(req.data->'tool_call'->>'title', '') AS tool_title,
+                       COALESCE(exp.time, resp.time) AS responded_at,
+                       CASE WHEN exp.time IS NOT NULL THEN 'expired'
+                            WHEN resp.data->'outcome' ? 'cancelled' THEN 'cancelled'
+                            ELSE sel.opt->>'name' END AS decision,
+                       CASE WHEN exp.time IS NULL THEN sel.opt->>'kind' END AS decision_kind
+                FROM events req
+                LEFT JOIN LATERAL (
+                    SELECT r.time, r.data
+                    FROM events r
+                    WHERE r.kind = 'permission.responded'
+                      AND r.data->>'request_id' = req.data->>'request_id'
+                      AND r.time >= req.time
+                    ORDER BY r.cursor
+                    LIMIT 1
+                ) resp ON true
+                LEFT JOIN LATERAL (
+                    SELECT e.time
+                    FROM events e
+                    WHERE e.kind = 'permission.expired'
+                      AND e.data->>'request_id' = req.data->>'request_id'
+                      AND e.time >= req.time
+                      AND (resp.time IS NULL OR e.time <= resp.time)
+                    LIMIT 1
+                ) exp ON true
+                LEFT JOIN LATERAL (
+                    SELECT o AS opt
+                    FROM jsonb_array_elements(req.data->'options') o
+                    WHERE o->>'option_id' = resp.data->'outcome'->'selected'->>'option_id'
+                    LIMIT 1
+                ) sel ON true
+                WHERE req.kind = 'permission.requested'
+                  AND req.data->>'session_id' = ANY($1)
+                  AND req.time >= $2
+                ORDER BY req.time
+                "#,
+                &[&session_keys, &since],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let mut permissions: HashMap<String, Vec<ExecutionPermission>> = HashMap::new();
+        for row in &permission_rows {
+            permissions
+                .entry(row.get("session_id"))
+                .or_default()
+                .push(ExecutionPermission {
+                    request_id: row.get::<_, Option<String>>("request_id").unwrap_or_default(),
+                    tool_title: row.get("tool_title"),
+                    requested_at: row.get("requested_at"),
+                    decision: row.get("decision"),
+                    decision_kind: row.get("decision_kind"),
+                    responded_at: row.get("responded_at"),
+                });
+        }
+
+        let executions = spawns
+            .into_iter()
+            .map(|(requested_at, session_id, spawn)| {
+                let session = sessions.get(&session_id);
+                let started_at: Option<DateTime<Utc>> = session.map(|row| row.get("started_at"));
+                let ended_at: Option<DateTime<Utc>> =
+                    session.and_then(|row| row.get("ended_at"));
+                let (exit_code, spawn_error) =
+                    outcomes.remove(&spawn.session_id).unwrap_or_default();
+                TaskExecution {
+                    session_id,
+                    agent_id: Uuid::parse_str(&spawn.target_agent_id).unwrap_or_default(),
+                    agent_name: session.and_then(|row| row.get("agent_name")),
+                    relay_id: spawn.relay_id.clone(),
+                    requested_at,
+                    status: session
+                        .map(|row| row.get::<_, SqlTypeWrapper<SessionStatus>>("status").0),
+                    started_at,
+                    ended_at,
+                    duration_secs: started_at
+                        .zip(ended_at)
+                        .map(|(start, end)| (end - start).num_seconds()),
+                    exit_code,
+                    spawn_error,
+                    spawn: ExecutionSpawnParams::from(&spawn),
+                    artifact_count: artifact_counts.get(&session_id).copied().unwrap_or(0),
+                    permissions: permissions.remove(&spawn.session_id).unwrap_or_default(),
+                    resource_usage: session
+                        .and_then(|row| row.get::<_, Option<Value>>("resource_usage"))
+                        .and_then(|v| serde_json::from_value(v).ok()),
+                }
+            })
+            .collect();
+
+        Ok(TaskExecutionHistory {
+            task_id,
+            executions,
+        })
+    }
+
+    // ========================================================================
+    // Activity feed operations
+    // ========================================================================
+
+    /// Activity of one task, newest first, older than `cursor`
+    pub async fn task_activity(
+        &self,
+        task_id: Uuid,
+        cursor: Option<&ActivityCursor>,
+        limit: i64,
+    ) -> crate::Result<Vec<ActivityItem>> {
+        self.activity("t.id = $1", &task_id, cursor, limit).await
+    }
+
+    /// Activity of every task of a project, newest first, older than `cursor`
+    pub async fn project_activity(
+        &self,
+        project_id: Uuid,
+        cursor: Option<&ActivityCursor>,
+        limit: i64,
+    ) -> crate::Result<Vec<ActivityItem>> {
+        self.activity("t.project_id = $1", &project_id, cursor, limit).await
+    }
+
+    /// Activity of every task a user watches, newest first, older than
+    /// `cursor`
+    pub async fn watched_activity(
+        &self,
+        user_id: &str,
+        cursor: Option<&ActivityCursor>,
+        limit: i64,
+    ) -> crate::Result<Vec<ActivityItem>> {
+        let scope = "t.id IN (SELECT task_id FROM watches WHERE user_id = $1)";
+        self.activity(scope, &user_id, cursor, limit).await
+    }
+
+    /// Task events, comments, artifacts and session milestones of the tasks
+    /// matching `scope`, merged into one feed
+    ///
+    /// Comment events are left out in favour of the comments themselves.
+    /// Sessions are found through their `relay.spawn_requested` events, like
+    /// in [`Self::get_task_execution_history`].
+    async fn activity(
+        &self,
+        scope: &str,
+        id: &(dyn tokio_postgres::types::ToSql + Sync),
+        cursor: Option<&ActivityCursor>,
+        limit: i64,
+    ) -> crate::Result<Vec<ActivityItem>> {
+        let conn = self
+            .read_pool()
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let sql = format!(
+            r#"
+            WITH scope AS (
+                SELECT t.id, t.project_id FROM tasks t WHERE {scope}
+            ),
+            spawns AS (
+                SELECT e.time, e.task_id, e.data
+                FROM events e
+                WHERE e.kind = 'relay.spawn_requested'
+                  AND e.task_id IN (SELECT id FROM scope)
+            ),
+            items AS (
+                SELECT te.id::text AS id, te.task_id, te.datetime AS time,
+                       te.event_type AS source, te.state, te.from_state,
+                       jsonb_strip_nulls(jsonb_build_object(
+                           'rank', te.rank, 'from_rank', te.from_rank,
+                           'related_task_id', te.related_task_id,
+                           'similarity', te.similarity)) AS data
+                FROM task_events te
+                WHERE te.task_id IN (SELECT id FROM scope)
+                  AND te.event_type <> 'CreateComment'
+                UNION ALL
+                SELECT c.id::text, c.task_id, c.create_at, 'comment', NULL, NULL,
+                       jsonb_build_object('content', c.content)
+                FROM task_comments c
+                WHERE c.task_id IN (SELECT id FROM scope)
+                UNION ALL
+                SELECT a.id::text, a.task_id, a.created_at, 'artifact', NULL, NULL,
+                       jsonb_strip_nulls(jsonb_build_object(
+                           'artifact_id', a.id, 'artifact_type', a.artifact_type,
+                           'agent_id', a.agent_id, 'session_id', a.session_id))
+                FROM artifacts a
+                WHERE a.task_id IN (SELECT id FROM scope) AND a.deleted_at IS NULL
+                UNION ALL
+                SELECT 'session_started.' || (s.data->>'session_id'), s.task_id, s.time,
+                       'session_started', NULL, NULL,
+                       jsonb_strip_nulls(jsonb_build_object(
+                           'session_id', s.data->'session_id', 'agent_id', s.data->'agent_id'))
+                FROM spawns s
+                UNION ALL
+                SELECT e.kind || '.' || e.cursor, s.task_id, e.time, e.kind, NULL, NULL,
+                       jsonb_strip_nulls(jsonb_build_object(
+                           'session_id', s.data->'session_id', 'agent_id', s.data->'agent_id',
+                           'exit_code', e.data->'exit_code', 'error', e.data->'error'))
+                FROM events e
+                JOIN spawns s ON s.data->>'session_id' = e.data->>'session_id'
+                             AND e.time >= s.time
+                WHERE e.kind IN ('agent.session_exited', 'relay.spawn_failed')
+            )
+            SELECT i.id, i.task_id, scope.project_id, i.time, i.source,
+                   i.state, i.from_state, i.data
+            FROM items i
+            JOIN scope ON scope.id = i.task_id
+            WHERE $2::timestamptz IS NULL OR (i.time, i.id) < ($2, $3::text)
+            ORDER BY i.time DESC, i.id DESC
+            LIMIT $4
+            "#
+        );
+        let before = cursor.map(|c| c.time);
+        let before_id = cursor.map(|c| c.id.clone());
+        let rows = conn
+            .query(&sql, &[id, &before, &before_id, &limit])
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let items = rows
+            .iter()
+            .filter_map(|row| {
+                let kind = ActivityKind::from_source(row.get("source"))?;
+                let state = row
+                    .get::<_, Option<SqlTypeWrapper<TaskStatus>>>("state")
+                    .map(|s| s.0);
+                let from_state = row
+                    .get::<_, Option<SqlTypeWrapper<TaskStatus>>>("from_state")
+                    .map(|s| s.0);
+                let data: Value = row.get("data");
+                Some(ActivityItem {
+                    id: row.get("id"),
+                    kind,
+                    time: row.get("time"),
+                    task_id: row.get("task_id"),
+                    project_id: row.get("project_id"),
+                    summary: ActivityItem::summarize(kind, state, from_state, &data),
+                    state,
+                    from_state,
+                    data,
+                })
+            })
+            .collect();
+        Ok(items)
+    }
+
+    // ========================================================================
+    // Scheduled execution operations
+    // ========================================================================
+
+    /// Queue a task execution until `scheduled_for`; re-queuing a task moves
+    /// its existing entry
+    pub async fn schedule_execution(
+        &self,
+        task_id: Uuid,
+        relay_id: Option<String>,
+        relay_selector: Option<String>,
+        scheduled_for: DateTime<Utc>,
+    ) -> crate::Result<ScheduledExecution> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_one(
+                r#"
+                INSERT INTO scheduled_executions (task_id, relay_id, relay_selector, scheduled_for)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (task_id) DO UPDATE
+                SET relay_id = EXCLUDED.relay_id,
+                    relay_selector = EXCLUDED.relay_selector,
+                    scheduled_for = EXCLUDED.scheduled_for
+                RETURNING id, task_id, relay_id, scheduled_for, created_at, relay_selector
+                "#,
+                &[&task_id, &relay_id, &relay_selector, &scheduled_for],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(ScheduledExecution {
+            id: row.get("id"),
+            task_id: row.get("task_id"),
+            relay_id: row.get("relay_id"),
+            scheduled_for: row.get("scheduled_for"),
+            created_at: row.get("created_at"),
+            relay_selector: row.get("relay_selector"),
+        })
+    }
+
+    /// Scheduled executions whose time has come
+    pub async fn list_due_executions(
+        &self,
+        now: DateTime<Utc>,
+    ) -> crate::Result<Vec<ScheduledExecution>> {
+        ScheduledExecution::select()
+            .filter(ScheduledExecution::COLUMNS.scheduled_for.lt(now))
+            .order_by(ScheduledExecution::COLUMNS.scheduled_for.asc())
+            .all(&*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))
+    }
+
+    /// Move a scheduled execution to a later time
+    pub async fn reschedule_execution(
+        &self,
+        id: Uuid,
+        scheduled_for: DateTime<Utc>,
+    ) -> crate::Result<()> {
+        let mut execution = ScheduledExecution::fetch_one_by_pk(&id, &*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        execution.scheduled_for = scheduled_for;
+        execution
+            .save(&*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(())
+    }
+
+    /// Remove a scheduled execution (spawned or no longer applicable)
+    pub async fn delete_scheduled_execution(&self, id: Uuid) -> crate::Result<()> {
+        ScheduledExecution::delete_by_pk(&id, &*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(())
+    }
+
+    // ========================================================================
+    // Task snooze operations
+    // ========================================================================
+
+    /// Snooze a task until `wake_at`; snoozing again replaces the wake time
+    pub async fn snooze_task(
+        &self,
+        task_id: Uuid,
+        wake_at: DateTime<Utc>,
+        notify: bool,
+    ) -> crate::Result<TaskSnooze> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_one(
+                r#"
+                INSERT INTO task_snoozes (task_id, wake_at, notify)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (task_id) DO UPDATE
+                SET wake_at = EXCLUDED.wake_at, notify = EXCLUDED.notify, created_at = NOW()
+                RETURNING task_id, wake_at, notify, created_at
+                "#,
+                &[&task_id, &wake_at, &notify],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(TaskSnooze {
+            task_id: row.get("task_id"),
+            wake_at: row.get("wake_at"),
+            notify: row.get("notify"),
+            created_at: row.get("created_at"),
+        })
+    }
+
+    /// Remove a task's snooze; returns false when it was not snoozed
+    pub async fn unsnooze_task(&self, task_id: Uuid) -> crate::Result<bool> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let deleted = conn
+            .execute("DELETE FROM task_snoozes WHERE task_id = $1", &[&task_id])
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(deleted > 0)
+    }
+
+    /// Get a task's snooze, if any
+    pub async fn get_task_snooze(&self, task_id: Uuid) -> crate::Result<Option<TaskSnooze>> {
+        match TaskSnooze::fetch_one_by_pk(&task_id, &*self.read_pool()).await {
+            Ok(snooze) => Ok(Some(snooze)),
+            Err(conservator::Error::TooManyRows(0)) => Ok(None),
+            Err(e) => Err(crate::TodokiError::Database(e)),
+        }
+    }
+
+    /// Remove and return snoozes whose wake time has passed
+    pub async fn take_due_snoozes(&self, now: DateTime<Utc>) -> crate::Result<Vec<TaskSnooze>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        // Deleting and returning in one statement keeps a wake-up from being
+        // delivered twice
+        let rows = conn
+            .query(
+                r#"
+                DELETE FROM task_snoozes
+                WHERE wake_at <= $1
+                RETURNING task_id, wake_at, notify, created_at
+                "#,
+                &[&now],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| TaskSnooze {
+                task_id: row.get("task_id"),
+                wake_at: row.get("wake_at"),
+                notify: row.get("notify"),
+                created_at: row.get("created_at"),
+            })
+            .collect())
+    }
+
+    /// Tasks that are currently snoozed, soonest wake-up first
+    pub async fn get_snoozed_tasks(&self) -> crate::Result<Vec<Task>> {
+        let conn = self
+            .read_pool()
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let rows = conn
+            .query(
+                r#"
+                SELECT t.id, t.priority, t.content, t.project_id, t.status, t.create_at,
+                       t.archived, t.agent_id, t.due_at, t.estimate_minutes, t.workflow_state,
+                       t.rank
+                FROM tasks t
+                JOIN task_snoozes s ON s.task_id = t.id
+                WHERE s.wake_at > NOW()
+                  AND t.archived = false
+                ORDER BY s.wake_at ASC
+                "#,
+                &[],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| Task {
+                id: row.get("id"),
+                priority: row.get("priority"),
+                content: row.get("content"),
+                project_id: row.get("project_id"),
+                status: row.get::<_, SqlTypeWrapper<TaskStatus>>("status").0,
+                create_at: row.get("create_at"),
+                archived: row.get("archived"),
+                agent_id: row.get("agent_id"),
+                due_at: row.get("due_at"),
+                estimate_minutes: row.get("estimate_minutes"),
+                workflow_state: row.get("workflow_state"),
+                rank: row.get("rank"),
+            })
+            .collect())
+    }
+
+    // ========================================================================
+    // Issue tracker sync operations
+    // ========================================================================
+
+    /// Record the tracker issue a task was mirrored to
+    pub async fn create_remote_issue(
+        &self,
+        task_id: Uuid,
+        provider: IssueProvider,
+        remote_id: &str,
+        remote_key: &str,
+        url: &str,
+        category: StatusCategory,
+    ) -> crate::Result<TaskRemoteIssue> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_one(
+                r#"
+                INSERT INTO task_remote_issues
+                    (task_id, provider, remote_id, remote_key, url, synced_category)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING task_id, provider, remote_id, remote_key, url, synced_category,
+                          synced_at, created_at
+                "#,
+                &[
+                    &task_id,
+                    &SqlTypeWrapper(provider),
+                    &remote_id,
+                    &remote_key,
+                    &url,
+                    &SqlTypeWrapper(category),
+                ],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(remote_issue_from_row(&row))
+    }
+
+    /// The tracker issue a task is mirrored to, if any
+    pub async fn get_remote_issue(&self, task_id: Uuid) -> crate::Result<Option<TaskRemoteIssue>> {
+        match TaskRemoteIssue::fetch_one_by_pk(&task_id, &*self.read_pool()).await {
+            Ok(issue) => Ok(Some(issue)),
+            Err(conservator::Error::TooManyRows(0)) => Ok(None),
+            Err(e) => Err(crate::TodokiError::Database(e)),
+        }
+    }
+
+    /// The task mirrored to a tracker issue, by the tracker's id
+    pub async fn find_remote_issue(
+        &self,
+        provider: IssueProvider,
+        remote_id: &str,
+    ) -> crate::Result<Option<TaskRemoteIssue>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_opt(
+                r#"
+                SELECT task_id, provider, remote_id, remote_key, url, synced_category,
+                       synced_at, created_at
+                FROM task_remote_issues
+                WHERE provider = $1 AND remote_id = $2
+                "#,
+                &[&SqlTypeWrapper(provider), &remote_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(row.as_ref().map(remote_issue_from_row))
+    }
+
+    /// Issues of unarchived tasks, least recently synced first
+    pub async fn list_remote_issues(&self, limit: i64) -> crate::Result<Vec<TaskRemoteIssue>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let rows = conn
+            .query(
+                r#"
+                SELECT r.task_id, r.provider, r.remote_id, r.remote_key, r.url,
+                       r.synced_category, r.synced_at, r.created_at
+                FROM task_remote_issues r
+                JOIN tasks t ON t.id = r.task_id
+                WHERE t.archived = false
+                ORDER BY r.synced_at ASC
+                LIMIT $1
+                "#,
+                &[&limit],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows.iter().map(remote_issue_from_row).collect())
+    }
+
+    /// Record that a task and its issue agree on `category`
+    pub async fn set_remote_issue_synced(
+        &self,
+        task_id: Uuid,
+        category: StatusCategory,
+    ) -> crate::Result<()> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        conn.execute(
+            r#"
+            UPDATE task_remote_issues
+            SET synced_category = $2, synced_at = NOW()
+            WHERE task_id = $1
+            "#,
+            &[&task_id, &SqlTypeWrapper(category)],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(())
+    }
+
+    // ========================================================================
+    // Prompt template operations
+    // ========================================================================
+
+    /// List prompt templates with their latest version, optionally for one role
+    pub async fn list_prompt_templates(
+        &self,
+        role: Option<AgentRole>,
+    ) -> crate::Result<Vec<PromptTemplate>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let role = role.map(SqlTypeWrapper);
+        let rows = conn
+            .query(
+                r#"
+                SELECT t.id, t.name, t.description, t.role, t.created_at, t.updated_at,
+                       v.version, v.content
+                FROM prompt_templates t
+                JOIN LATERAL (
+                    SELECT version, content FROM prompt_template_versions
+                    WHERE template_id = t.id ORDER BY version DESC LIMIT 1
+                ) v ON true
+                WHERE $1::VARCHAR IS NULL OR t.role = $1
+                ORDER BY t.name ASC
+                "#,
+                &[&role],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows.iter().map(prompt_template_from_row).collect())
+    }
+
+    /// Get a prompt template with its latest version
+    pub async fn get_prompt_template(&self, id: Uuid) -> crate::Result<Option<PromptTemplate>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_opt(
+                r#"
+                SELECT t.id, t.name, t.description, t.role, t.created_at, t.updated_at,
+                       v.version, v.content
+                FROM prompt_templates t
+                JOIN LATERAL (
+                    SELECT version, content FROM prompt_template_versions
+                    WHERE template_id = t.id ORDER BY version DESC LIMIT 1
+                ) v ON true
+                WHERE t.id = $1
+                "#,
+                &[&id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(row.as_ref().map(prompt_template_from_row))
+    }
+
+    /// Create a prompt template together with its first version
+    pub async fn create_prompt_template(
+        &self,
+        name: String,
+        description: Option<String>,
+        role: Option<AgentRole>,
+        content: String,
+    ) -> crate::Result<PromptTemplate> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let role = role.map(SqlTypeWrapper);
+        let row = conn
+            .query_one(
+                r#"
+                WITH t AS (
+                    INSERT INTO prompt_templates (name, description, role)
+                    VALUES ($1, $2, $3)
+                    RETURNING id, name, description, role, created_at, updated_at
+                ), v AS (
+                    INSERT INTO prompt_template_versions (template_id, version, content)
+                    SELECT id, 1, $4 FROM t
+                    RETURNING version, content
+                )
+                SELECT t.id, t.name, t.description, t.role, t.created_at, t.updated_at,
+                       v.version, v.content
+                FROM t, v
+                "#,
+                &[&name, &description, &role, &content],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(prompt_template_from_row(&row))
+    }
+
+    /// Update template metadata; new content is saved as the next version
+    pub async fn update_prompt_template(
+        &self,
+        id: Uuid,
+        name: Option<String>,
+        description: Option<String>,
+        content: Option<String>,
+    ) -> crate::Result<Option<PromptTemplate>> {
+        let Some(current) = self.get_prompt_template(id).await? else {
+            return Ok(None);
+        };
+
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        conn.execute(
+            r#"
+            UPDATE prompt_templates
+            SET name = COALESCE($2, name),
+                description = COALESCE($3, description),
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+            &[&id, &name, &description],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        if let Some(content) = content.filter(|c| *c != current.content) {
+            conn.execute(
+                r#"
+                INSERT INTO prompt_template_versions (template_id, version, content)
+                SELECT $1, COALESCE(MAX(version), 0) + 1, $2
+                FROM prompt_template_versions WHERE template_id = $1
+                "#,
+                &[&id, &content],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        }
+
+        self.get_prompt_template(id).await
+    }
+
+    /// Save an earlier version's content as the new latest version
+    ///
+    /// Returns None when the template or version does not exist.
+    pub async fn rollback_prompt_template(
+        &self,
+        id: Uuid,
+        version: i32,
+    ) -> crate::Result<Option<PromptTemplate>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let inserted = conn
+            .execute(
+                r#"
+                INSERT INTO prompt_template_versions (template_id, version, content)
+                SELECT template_id,
+                       (SELECT MAX(version) + 1 FROM prompt_template_versions WHERE template_id = $1),
+                       content
+                FROM prompt_template_versions
+                WHERE template_id = $1 AND version = $2
+                "#,
+                &[&id, &version],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        if inserted == 0 {
+            return Ok(None);
+        }
+
+        conn.execute(
+            "UPDATE prompt_templates SET updated_at = NOW() WHERE id = $1",
+            &[&id],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        self.get_prompt_template(id).await
+    }
+
+    /// List all versions of a template, newest first
+    pub async fn list_prompt_template_versions(
+        &self,
+        id: Uuid,
+    ) -> crate::Result<Vec<PromptTemplateVersion>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let rows = conn
+            .query(
+                r#"
+                SELECT id, template_id, version, content, created_at
+                FROM prompt_template_versions
+                WHERE template_id = $1
+                ORDER BY version DESC
+                "#,
+                &[&id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| PromptTemplateVersion {
+                id: row.get("id"),
+                template_id: row.get("template_id"),
+                version: row.get("version"),
+                content: row.get("content"),
+                created_at: row.get("created_at"),
+            })
+            .collect())
+    }
+
+    /// Delete a template; projects referencing it fall back to inline templates
+    pub async fn delete_prompt_template(&self, id: Uuid) -> crate::Result<()> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        conn.execute("DELETE FROM prompt_templates WHERE id = $1", &[&id])
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(())
+    }
+
+    /// Role templates referenced by a project
+    pub async fn list_project_templates(
+        &self,
+        project_id: Uuid,
+    ) -> crate::Result<Vec<ProjectTemplateRef>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let rows = conn
+            .query(
+                r#"
+                SELECT p.role, p.template_id, t.name AS template_name,
+                       (SELECT MAX(version) FROM prompt_template_versions
+                        WHERE template_id = t.id) AS version
+                FROM project_prompt_templates p
+                JOIN prompt_templates t ON t.id = p.template_id
+                WHERE p.project_id = $1
+                ORDER BY p.role ASC
+                "#,
+                &[&project_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| ProjectTemplateRef {
+                role: row.get::<_, SqlTypeWrapper<AgentRole>>("role").0,
+                template_id: row.get("template_id"),
+                template_name: row.get("template_name"),
+                version: row.get::<_, Option<i32>>("version").unwrap_or(0),
+            })
+            .collect())
+    }
+
+    /// Point a project's role at a shared template, or clear the reference
+    pub async fn set_project_template(
+        &self,
+        project_id: Uuid,
+        role: AgentRole,
+        template_id: Option<Uuid>,
+    ) -> crate::Result<()> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let role = SqlTypeWrapper(role);
+        match template_id {
+            Some(template_id) => {
+                conn.execute(
+                    r#"
+                    INSERT INTO project_prompt_templates (project_id, role, template_id)
+                    VALUES ($1, $2, $3)
+                    ON CONFLICT (project_id, role) DO UPDATE SET template_id = EXCLUDED.template_id
+                    "#,
+                    &[&project_id, &role, &template_id],
+                )
+                .await
+                .map_err(|e| crate::TodokiError::Database(e))?;
+            }
+            None => {
+                conn.execute(
+                    "DELETE FROM project_prompt_templates WHERE project_id = $1 AND role = $2",
+                    &[&project_id, &role],
+                )
+                .await
+                .map_err(|e| crate::TodokiError::Database(e))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Latest content of the shared template a project uses for `role`, if any
+    pub async fn get_project_template_content(
+        &self,
+        project_id: Uuid,
+        role: AgentRole,
+    ) -> crate::Result<Option<String>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let role = SqlTypeWrapper(role);
+        let row = conn
+            .query_opt(
+                r#"
+                SELECT v.content
+                FROM project_prompt_templates p
+                JOIN prompt_template_versions v ON v.template_id = p.template_id
+                WHERE p.project_id = $1 AND p.role = $2
+                ORDER BY v.version DESC
+                LIMIT 1
+                "#,
+                &[&project_id, &role],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(row.map(|r| r.get("content")))
+    }
+
+    // ========================================================================
+    // Saved view operations
+    // ========================================================================
+
+    /// List saved views by name
+    pub async fn list_views(&self) -> crate::Result<Vec<SavedView>> {
+        let mut views = SavedView::fetch_all(&*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        views.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(views)
+    }
+
+    /// Get a saved view by ID
+    pub async fn get_view(&self, view_id: Uuid) -> crate::Result<Option<SavedView>> {
+        match SavedView::fetch_one_by_pk(&view_id, &*self.pool).await {
+            Ok(view) => Ok(Some(view)),
+            Err(conservator::Error::TooManyRows(0)) => Ok(None),
+            Err(e) => Err(crate::TodokiError::Database(e)),
+        }
+    }
+
+    /// Create a saved view
+    pub async fn create_view(&self, create: CreateSavedView) -> crate::Result<SavedView> {
+        let view_id = create
+            .insert::<SavedView>()
+            .returning_pk(&*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        SavedView::fetch_one_by_pk(&view_id, &*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))
+    }
+
+    /// Update a saved view; `filter` replaces the stored one
+    pub async fn update_view(
+        &self,
+        view_id: Uuid,
+        name: Option<String>,
+        description: Option<String>,
+        filter: Option<ViewFilter>,
+    ) -> crate::Result<Option<SavedView>> {
+        let Some(mut view) = self.get_view(view_id).await? else {
+            return Ok(None);
+        };
+        if let Some(name) = name {
+            view.name = name;
+        }
+        if description.is_some() {
+            view.description = description;
+        }
+        if let Some(filter) = filter {
+            view.filter = serde_json::to_value(filter).unwrap_or_default();
+        }
+        view.updated_at = Utc::now();
+
+        view.save(&*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        Ok(Some(view))
+    }
+
+    /// Delete a saved view
+    pub async fn delete_view(&self, view_id: Uuid) -> crate::Result<()> {
+        SavedView::delete_by_pk(&view_id, &*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        Ok(())
+    }
+
+    /// Tasks matching a view filter, in the filter's order
+    pub async fn query_tasks(&self, filter: &ViewFilter) -> crate::Result<Vec<Task>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        // Statuses are bound one by one like in get_tasks_by_status; the
+        // fixed parameters come first
+        let statuses: Vec<SqlTypeWrapper<TaskStatus>> =
+            filter.statuses.iter().map(|s| SqlTypeWrapper(*s)).collect();
+        let status_in = (0..statuses.len())
+            .map(|i| format!("${}", i + 7))
+            .collect::<Vec<_>>()
+            .join(", ");
+        // Every branch refers to $2 so its type is known
+        let state_clause = match (statuses.is_empty(), filter.states.is_empty()) {
+            (true, true) => "cardinality($2::varchar[]) = 0".to_string(),
+            (true, false) => "t.workflow_state = ANY($2::varchar[])".to_string(),
+            (false, _) => format!(
+                "(t.status IN ({}) OR t.workflow_state = ANY($2::varchar[]))",
+                status_in
+            ),
+        };
+        let query = format!(
+            r#"
+            SELECT t.id, t.priority, t.content, t.project_id, t.status, t.create_at, t.archived,
+                   t.agent_id, t.due_at, t.estimate_minutes, t.workflow_state, t.rank
+            FROM tasks t
+            WHERE ($1 OR t.archived = false)
+              AND {}
+              AND (cardinality($3::uuid[]) = 0 OR t.project_id = ANY($3))
+              AND ($4::uuid IS NULL OR t.agent_id = $4)
+              AND ($5::text IS NULL OR t.content ILIKE $5)
+            ORDER BY {}
+            LIMIT $6
+            "#,
+            state_clause,
+            filter.sort.order_by()
+        );
+
+        let pattern = filter.like_pattern();
+        let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = vec![
+            &filter.include_archived,
+            &filter.states,
+            &filter.project_ids,
+            &filter.agent_id,
+            &pattern,
+            &VIEW_TASK_LIMIT,
+        ];
+        params.extend(statuses.iter().map(|s| s as &(dyn tokio_postgres::types::ToSql + Sync)));
+
+        let rows = conn
+            .query(&query, &params)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| Task {
+                id: row.get("id"),
+                priority: row.get("priority"),
+                content: row.get("content"),
+                project_id: row.get("project_id"),
+                status: row.get::<_, SqlTypeWrapper<TaskStatus>>("status").0,
+                create_at: row.get("create_at"),
+                archived: row.get("archived"),
+                agent_id: row.get("agent_id"),
+                due_at: row.get("due_at"),
+                estimate_minutes: row.get("estimate_minutes"),
+                workflow_state: row.get("workflow_state"),
+                rank: row.get("rank"),
+            })
+            .collect())
+    }
+
+    // ========================================================================
+    // Notification operations
+    // ========================================================================
+
+    /// Notifications of `user_id`, newest first
+    pub async fn list_notifications(
+        &self,
+        user_id: &str,
+        unread_only: bool,
+        limit: i64,
+    ) -> crate::Result<Vec<Notification>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let rows = conn
+            .query(
+                r#"
+                SELECT id, user_id, kind, title, body, task_id, session_id, event_cursor,
+                       created_at, read_at
+                FROM notifications
+                WHERE user_id = $1 AND (NOT $2 OR read_at IS NULL)
+                ORDER BY created_at DESC
+                LIMIT $3
+                "#,
+                &[&user_id, &unread_only, &limit],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows.iter().map(notification_from_row).collect())
+    }
+
+    /// Unread notifications of `user_id`
+    pub async fn unread_notification_count(&self, user_id: &str) -> crate::Result<i64> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_one(
+                "SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND read_at IS NULL",
+                &[&user_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        Ok(row.get(0))
+    }
+
+    /// Store notifications
+    pub async fn create_notifications(
+        &self,
+        notifications: Vec<CreateNotification>,
+    ) -> crate::Result<()> {
+        for notification in notifications {
+            notification
+                .insert::<Notification>()
+                .returning_pk(&*self.pool)
+                .await
+                .map_err(|e| crate::TodokiError::Database(e))?;
+        }
+        Ok(())
+    }
+
+    /// Mark notifications of `user_id` read: one, or all of them when
+    /// `notification_id` is `None`; returns how many were unread
+    pub async fn mark_notifications_read(
+        &self,
+        user_id: &str,
+        notification_id: Option<Uuid>,
+    ) -> crate::Result<u64> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        conn.execute(
+            r#"
+            UPDATE notifications
+            SET read_at = NOW()
+            WHERE user_id = $1 AND read_at IS NULL AND ($2::uuid IS NULL OR id = $2)
+            "#,
+            &[&user_id, &notification_id],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))
+    }
+
+    /// Preferences of `user_id`, created with every kind enabled if missing
+    ///
+    /// Creating the row is what makes an OIDC user a notification recipient.
+    pub async fn ensure_notification_preferences(
+        &self,
+        user_id: &str,
+    ) -> crate::Result<NotificationPreferences> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        conn.execute(
+            r#"
+            INSERT INTO notification_preferences (user_id)
+            VALUES ($1)
+            ON CONFLICT (user_id) DO NOTHING
+            "#,
+            &[&user_id],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        NotificationPreferences::fetch_one_by_pk(&user_id.to_string(), &*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))
+    }
+
+    /// Replace the muted kinds of `user_id`
+    pub async fn set_notification_preferences(
+        &self,
+        user_id: &str,
+        muted: Value,
+    ) -> crate::Result<NotificationPreferences> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_one(
+                r#"
+                INSERT INTO notification_preferences (user_id, muted, updated_at)
+                VALUES ($1, $2, NOW())
+                ON CONFLICT (user_id) DO UPDATE
+                SET muted = EXCLUDED.muted, updated_at = EXCLUDED.updated_at
+                RETURNING user_id, muted, updated_at
+                "#,
+                &[&user_id, &muted],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(NotificationPreferences {
+            user_id: row.get("user_id"),
+            muted: row.get("muted"),
+            updated_at: row.get("updated_at"),
+        })
+    }
+
+    /// Every stored preference row, i.e. every known recipient
+    pub async fn list_notification_preferences(
+        &self,
+    ) -> crate::Result<Vec<NotificationPreferences>> {
+        NotificationPreferences::fetch_all(&*self.pool)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))
+    }
+
+    // ========================================================================
+    // Task context operations
+    // ========================================================================
+
+    /// Context entries of a task by key, optionally only keys under `prefix`
+    pub async fn list_task_context(
+        &self,
+        task_id: Uuid,
+        prefix: Option<&str>,
+    ) -> crate::Result<Vec<TaskContextEntry>> {
+        let conn = self
+            .read_pool()
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let rows = conn
+            .query(
+                r#"
+                SELECT * FROM task_context
+                WHERE task_id = $1 AND ($2::text IS NULL OR starts_with(key, $2::text))
+                ORDER BY key
+                "#,
+                &[&task_id, &prefix],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows.iter().map(task_context_from_row).collect())
+    }
+
+    pub async fn get_task_context(
+        &self,
+        task_id: Uuid,
+        key: &str,
+    ) -> crate::Result<Option<TaskContextEntry>> {
+        let conn = self
+            .read_pool()
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_opt(
+                "SELECT * FROM task_context WHERE task_id = $1 AND key = $2",
+                &[&task_id, &key],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(row.as_ref().map(task_context_from_row))
+    }
+
+    /// Write a context entry, bumping its version
+    ///
+    /// With `expected_version` the write only happens if the entry is at that
+    /// version (`0` = absent); `None` is returned when it is not.
+    pub async fn put_task_context(
+        &self,
+        task_id: Uuid,
+        key: &str,
+        value: &Value,
+        expected_version: Option<i32>,
+        writer: &ContextWriter,
+    ) -> crate::Result<Option<TaskContextEntry>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = vec![
+            &task_id,
+            &key,
+            value,
+            &writer.token_id,
+            &writer.agent_id,
+            &writer.session_id,
+        ];
+        let sql = match &expected_version {
+            Some(0) => {
+                r#"
+                INSERT INTO task_context (task_id, key, value, updated_by, agent_id, session_id)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (task_id, key) DO NOTHING
+                RETURNING *
+                "#
+            }
+            Some(version) => {
+                params.push(version);
+                r#"
+                UPDATE task_context
+                SET value = $3, version = version + 1, updated_by = $4,
+                    agent_id = $5, session_id = $6, updated_at = NOW()
+                WHERE task_id = $1 AND key = $2 AND version = $7
+                RETURNING *
+                "#
+            }
+            None => {
+                r#"
+                INSERT INTO task_context (task_id, key, value, updated_by, agent_id, session_id)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (task_id, key) DO UPDATE
+                SET value = EXCLUDED.value,
+                    version = task_context.version + 1,
+                    updated_by = EXCLUDED.updated_by,
+                    agent_id = EXCLUDED.agent_id,
+                    session_id = EXCLUDED.session_id,
+                    updated_at = NOW()
+                RETURNING *
+                "#
+            }
+        };
+        let row = conn
+            .query_opt(sql, &params)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(row.as_ref().map(task_context_from_row))
+    }
+
+    /// Remove a context entry; returns whether it existed
+    pub async fn delete_task_context(&self, task_id: Uuid, key: &str) -> crate::Result<bool> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let deleted = conn
+            .execute(
+                "DELETE FROM task_context WHERE task_id = $1 AND key = $2",
+                &[&task_id, &key],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(deleted > 0)
+    }
+
+    // ========================================================================
+    // Dead letter operations
+    // ========================================================================
+
+    /// Record a failed attempt to apply `event` in `handler`; failing again
+    /// on the same event bumps its attempt count and reopens it
+    pub async fn record_dead_letter(
+        &self,
+        handler: &str,
+        event: &Event,
+        error: &str,
+    ) -> crate::Result<DeadLetter> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let snapshot = serde_json::to_value(event).map_err(|_| crate::TodokiError::Internal)?;
+        let row = conn
+            .query_one(
+                r#"
+                INSERT INTO event_dead_letters (event_cursor, event_kind, handler, event, error)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (event_cursor, handler) DO UPDATE
+                SET error = EXCLUDED.error,
+                    attempts = event_dead_letters.attempts + 1,
+                    last_attempt_at = NOW(),
+                    resolved_at = NULL
+                RETURNING *
+                "#,
+                &[&event.cursor, &event.kind, &handler, &snapshot, &error],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        dead_letter_from_row(&row)
+    }
+
+    /// Dead letters, oldest first; resolved ones only when asked for
+    pub async fn list_dead_letters(
+        &self,
+        include_resolved: bool,
+        limit: i64,
+    ) -> crate::Result<Vec<DeadLetter>> {
+        let conn = self
+            .read_pool()
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let rows = conn
+            .query(
+                r#"
+                SELECT * FROM event_dead_letters
+                WHERE $1 OR resolved_at IS NULL
+                ORDER BY created_at ASC
+                LIMIT $2
+                "#,
+                &[&include_resolved, &limit],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        rows.iter().map(dead_letter_from_row).collect()
+    }
+
+    pub async fn get_dead_letter(&self, id: Uuid) -> crate::Result<Option<DeadLetter>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_opt("SELECT * FROM event_dead_letters WHERE id = $1", &[&id])
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        row.as_ref().map(dead_letter_from_row).transpose()
+    }
+
+    /// Mark a dead letter as successfully reprocessed
+    pub async fn resolve_dead_letter(&self, id: Uuid) -> crate::Result<DeadLetter> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_one(
+                r#"
+                UPDATE event_dead_letters
+                SET resolved_at = NOW(), last_attempt_at = NOW()
+                WHERE id = $1
+                RETURNING *
+                "#,
+                &[&id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        dead_letter_from_row(&row)
+    }
+
+    // ========================================================================
+    // Undo log operations
+    // ========================================================================
+
+    /// Log a mutation made with `token_id` so `POST /api/undo` can revert it
+    pub async fn record_undo(
+        &self,
+        token_id: &str,
+        action: UndoAction,
+        target_id: Uuid,
+        snapshot: Option<Value>,
+    ) -> crate::Result<()> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        conn.execute(
+            r#"
+            INSERT INTO undo_log (token_id, action, target_id, snapshot, request_id)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            &[
+                &token_id,
+                &SqlTypeWrapper(action),
+                &target_id,
+                &snapshot,
+                &request_id::current(),
+            ],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(())
+    }
+
+    /// Mutations of `token_id` made since `since` and not yet undone, newest
+    /// first; entries older than `since` are pruned on the way
+    pub async fn pending_undo_entries(
+        &self,
+        token_id: &str,
+        since: DateTime<Utc>,
+        limit: i64,
+    ) -> crate::Result<Vec<UndoEntry>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        conn.execute("DELETE FROM undo_log WHERE created_at < $1", &[&since])
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let rows = conn
+            .query(
+                r#"
+                SELECT * FROM undo_log
+                WHERE token_id = $1 AND undone_at IS NULL AND created_at >= $2
+                ORDER BY created_at DESC
+                LIMIT $3
+                "#,
+                &[&token_id, &since, &limit],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(rows.iter().map(undo_entry_from_row).collect())
+    }
+
+    pub async fn mark_undone(&self, id: Uuid) -> crate::Result<()> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        conn.execute("UPDATE undo_log SET undone_at = NOW() WHERE id = $1", &[&id])
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(())
+    }
+
+    /// The rows of a task with its events and comments, as JSON
+    ///
+    /// Taken before deleting a task so `restore_task_snapshot` can recreate it.
+    pub async fn snapshot_task(&self, task_id: Uuid) -> crate::Result<Option<Value>> {
+        let conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = conn
+            .query_opt(
+                r#"
+                SELECT jsonb_build_object(
+                    'task', to_jsonb(t),
+                    'events', COALESCE(
+                        (SELECT jsonb_agg(to_jsonb(e)) FROM task_events e WHERE e.task_id = t.id),
+                        '[]'::jsonb
+                    ),
+                    'comments', COALESCE(
+                        (SELECT jsonb_agg(to_jsonb(c)) FROM task_comments c WHERE c.task_id = t.id),
+                        '[]'::jsonb
+                    )
+                ) AS snapshot
+                FROM tasks t
+                WHERE t.id = $1
+                "#,
+                &[&task_id],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(row.map(|r| r.get("snapshot")))
+    }
+
+    /// Recreate a deleted task from a `snapshot_task` snapshot
+    pub async fn restore_task_snapshot(&self, snapshot: &Value) -> crate::Result<Task> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let tx = conn
+            .begin()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let row = tx
+            .query_one(
+                r#"
+                INSERT INTO tasks
+                SELECT * FROM jsonb_populate_record(NULL::tasks, $1->'task')
+                RETURNING id
+                "#,
+                &[snapshot],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let task_id: Uuid = row.get("id");
+
+        tx.execute(
+            r#"
+            INSERT INTO task_events
+            SELECT * FROM jsonb_populate_recordset(NULL::task_events, $1->'events')
+            "#,
+            &[snapshot],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        tx.execute(
+            r#"
+            INSERT INTO task_comments
+            SELECT * FROM jsonb_populate_recordset(NULL::task_comments, $1->'comments')
+            "#,
+            &[snapshot],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        let task = Task::fetch_one_by_pk(&task_id, &tx)
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(task)
+    }
+
+    // ========================================================================
+    // Backup operations
+    // ========================================================================
+
+    /// Send a backup of every `BACKUP_TABLES` table to `out`, line by line
+    ///
+    /// Rows are read from one repeatable-read snapshot through a cursor, so
+    /// the backup is consistent without holding whole tables in memory.
+    pub async fn export_backup(
+        &self,
+        backup_id: Uuid,
+        out: mpsc::Sender<BackupLine>,
+    ) -> crate::Result<()> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let tx = conn
+            .begin()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        tx.execute(
+            "SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY",
+            &[],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        // The receiver is only dropped when the backup is abandoned
+        let header = BackupLine::Header {
+            version: BACKUP_FORMAT_VERSION,
+            backup_id,
+            created_at: Utc::now(),
+            tables: BACKUP_TABLES.iter().map(|t| t.to_string()).collect(),
+        };
+        out.send(header)
+            .await
+            .map_err(|_| crate::TodokiError::Internal)?;
+
+        let mut total = 0i64;
+        for table in BACKUP_TABLES {
+            tx.execute(
+                &format!(
+                    "DECLARE backup_rows NO SCROLL CURSOR FOR SELECT to_jsonb(t) AS row FROM {} t",
+                    table
+                ),
+                &[],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+            let mut rows = 0i64;
+            loop {
+                let batch = tx
+                    .query("FETCH 500 FROM backup_rows", &[])
+                    .await
+                    .map_err(|e| crate::TodokiError::Database(e))?;
+                if batch.is_empty() {
+                    break;
+                }
+                rows += batch.len() as i64;
+                for row in batch {
+                    let line = BackupLine::Row {
+                        table: table.to_string(),
+                        row: row.get("row"),
+                    };
+                    out.send(line)
+                        .await
+                        .map_err(|_| crate::TodokiError::Internal)?;
+                }
+            }
+
+            tx.execute("CLOSE backup_rows", &[])
+                .await
+                .map_err(|e| crate::TodokiError::Database(e))?;
+            let end = BackupLine::TableEnd {
+                table: table.to_string(),
+                rows,
+            };
+            out.send(end)
+                .await
+                .map_err(|_| crate::TodokiError::Internal)?;
+            total += rows;
+        }
+
+        out.send(BackupLine::End { rows: total })
+            .await
+            .map_err(|_| crate::TodokiError::Internal)?;
+
+        tx.commit()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(())
+    }
+
+    /// Replace the contents of every `BACKUP_TABLES` table with `batches`
+    ///
+    /// Everything happens in one transaction that is only committed on
+    /// `RestoreBatch::Commit`; returns false, changing nothing, when the
+    /// sender goes away before that. The undo log is cleared as its targets
+    /// no longer exist, and serial sequences move past the restored rows.
+    pub async fn restore_backup(
+        &self,
+        mut batches: mpsc::Receiver<RestoreBatch>,
+    ) -> crate::Result<bool> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        let tx = conn
+            .begin()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        tx.execute(
+            &format!("TRUNCATE {}, undo_log CASCADE", BACKUP_TABLES.join(", ")),
+            &[],
+        )
+        .await
+        .map_err(|e| crate::TodokiError::Database(e))?;
+
+        loop {
+            match batches.recv().await {
+                Some(RestoreBatch::Rows { table, rows }) => {
+                    // Table names are interpolated, so only known ones get through
+                    if !BACKUP_TABLES.contains(&table.as_str()) {
+                        tracing::error!(table = %table, "refusing to restore unknown table");
+                        return Err(crate::TodokiError::Internal);
+                    }
+                    tx.execute(
+                        &format!(
+                            "INSERT INTO {0} SELECT * FROM jsonb_populate_recordset(NULL::{0}, $1)",
+                            table
+                        ),
+                        &[&Value::Array(rows)],
+                    )
+                    .await
+                    .map_err(|e| crate::TodokiError::Database(e))?;
+                }
+                Some(RestoreBatch::Commit) => break,
+                None => return Ok(false),
+            }
+        }
+
+        let serials = [("agent_events", "id"), ("events", "cursor"), ("event_outbox", "id")];
+        for (table, column) in serials {
+            tx.execute(
+                &format!(
+                    "SELECT setval(pg_get_serial_sequence('{0}', '{1}'), \
+                     COALESCE((SELECT MAX({1}) FROM {0}), 0) + 1, false)",
+                    table, column
+                ),
+                &[],
+            )
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| crate::TodokiError::Database(e))?;
+
+        Ok(true)
+    }
+}
+
+fn artifact_from_row(row: &tokio_postgres::Row) -> Artifact {
+    Artifact {
+        id: row.get("id"),
+        task_id: row.get("task_id"),
+        project_id: row.get("project_id"),
+        agent_id: row.get("agent_id"),
+        session_id: row.get("session_id"),
+        artifact_type: row.get("artifact_type"),
+        data: row.get("data"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+fn remote_issue_from_row(row: &tokio_postgres::Row) -> TaskRemoteIssue {
+    TaskRemoteIssue {
+        task_id: row.get("task_id"),
+        provider: row.get::<_, SqlTypeWrapper<IssueProvider>>("provider").0,
+        remote_id: row.get("remote_id"),
+        remote_key: row.get("remote_key"),
+        url: row.get("url"),
+        synced_category: row.get::<_, SqlTypeWrapper<StatusCategory>>("synced_category").0,
+        synced_at: row.get("synced_at"),
+        created_at: row.get("created_at"),
+    }
+}
+
+fn notification_from_row(row: &tokio_postgres::Row) -> Notification {
+    Notification {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        kind: row.get::<_, SqlTypeWrapper<NotificationKind>>("kind").0,
+        title: row.get("title"),
+        body: row.get("body"),
+        task_id: row.get("task_id"),
+        session_id: row.get("session_id"),
+        event_cursor: row.get("event_cursor"),
+        created_at: row.get("created_at"),
+        read_at: row.get("read_at"),
+    }
+}
+
+fn undo_entry_from_row(row: &tokio_postgres::Row) -> UndoEntry {
+    UndoEntry {
+        id: row.get("id"),
+        token_id: row.get("token_id"),
+        action: row.get::<_, SqlTypeWrapper<UndoAction>>("action").0,
+        target_id: row.get("target_id"),
+        snapshot: row.get("snapshot"),
+        created_at: row.get("created_at"),
+    }
+}
+
+fn project_quotas_from_row(row: &tokio_postgres::Row) -> ProjectQuotas {
+    let limit = |column: &str| row.get::<_, Option<i64>>(column).map(|v| v.max(0) as u64);
+    ProjectQuotas {
+        project_id: row.get("project_id"),
+        overrides: QuotaLimits {
+            max_concurrent_sessions: limit("max_concurrent_sessions"),
+            events_per_hour: limit("events_per_hour"),
+            storage_bytes: limit("storage_bytes"),
+            ai_reviews_per_month: limit("ai_reviews_per_month"),
+        },
+        updated_at: row.get("updated_at"),
+    }
+}
+
+fn quota_to_sql(limit: u64) -> i64 {
+    limit.min(i64::MAX as u64) as i64
+}
+
+fn status_page_from_row(row: &tokio_postgres::Row) -> StatusPage {
+    StatusPage {
+        project_id: row.get("project_id"),
+        token: row.get("token"),
+        enabled: row.get("enabled"),
+        redact: serde_json::from_value(row.get("redact")).unwrap_or_default(),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+fn watch_from_row(row: &tokio_postgres::Row) -> Watch {
+    Watch {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        task_id: row.get("task_id"),
+        session_id: row.get("session_id"),
+        created_at: row.get("created_at"),
+    }
+}
+
+fn time_entry_from_row(row: &tokio_postgres::Row) -> TaskTimeEntry {
+    TaskTimeEntry {
+        id: row.get("id"),
+        task_id: row.get("task_id"),
+        project_id: row.get("project_id"),
+        user_id: row.get("user_id"),
+        started_at: row.get("started_at"),
+        ended_at: row.get("ended_at"),
+        note: row.get("note"),
+        created_at: row.get("created_at"),
+    }
+}
+
+fn task_context_from_row(row: &tokio_postgres::Row) -> TaskContextEntry {
+    TaskContextEntry {
+        task_id: row.get("task_id"),
+        key: row.get("key"),
+        value: row.get("value"),
+        version: row.get("version"),
+        updated_by: row.get("updated_by"),
+        agent_id: row.get("agent_id"),
+        session_id: row.get("session_id"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+fn dead_letter_from_row(row: &tokio_postgres::Row) -> crate::Result<DeadLetter> {
+    let event = serde_json::from_value(row.get("event")).map_err(|e| {
+        tracing::error!(error = %e, "invalid event snapshot in dead letter");
+        crate::TodokiError::Internal
+    })?;
+
+    Ok(DeadLetter {
+        id: row.get("id"),
+        event_cursor: row.get("event_cursor"),
+        event_kind: row.get("event_kind"),
+        handler: row.get("handler"),
+        event,
+        error: row.get("error"),
+        attempts: row.get("attempts"),
+        created_at: row.get("created_at"),
+        last_attempt_at: row.get("last_attempt_at"),
+        resolved_at: row.get("resolved_at"),
+    })
+}
+
+fn prompt_template_from_row(row: &tokio_postgres::Row) -> PromptTemplate {
+    PromptTemplate {
+        id: row.get("id"),
+        name: row.get("name"),
+        description: row.get("description"),
+        role: row
+            .get::<_, Option<SqlTypeWrapper<AgentRole>>>("role")
+            .map(|r| r.0),
+        version: row.get("version"),
+        content: row.get("content"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
 }
@@ -0,0 +1,307 @@
+//! Daily digest of agent activity
+//!
+//! Once a day at the configured local time, the worker aggregates the last
+//! 24 hours of task completions, failed sessions, pull request artifacts and
+//! permission denials into a Markdown digest. The digest is stored as a
+//! `digest` artifact on a done task in the digest project and then sent to
+//! every configured notifier.
+
+pub mod notifier;
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveTime, Utc};
+
+use crate::config::{DigestSettings, TelegramSettings};
+use crate::db::DatabaseService;
+use crate::models::{CreateTask, DigestActivity, TaskStatus};
+use notifier::Notifier;
+
+const DIGEST_WINDOW_HOURS: i64 = 24;
+const LINE_MAX_CHARS: usize = 100;
+
+pub struct DigestWorker {
+    settings: DigestSettings,
+    time: NaiveTime,
+    offset: FixedOffset,
+    db: Arc<DatabaseService>,
+    notifiers: Vec<Notifier>,
+}
+
+impl DigestWorker {
+    pub fn new(
+        settings: DigestSettings,
+        telegram: &TelegramSettings,
+        db: Arc<DatabaseService>,
+    ) -> Self {
+        let time = NaiveTime::parse_from_str(&settings.time, "%H:%M").unwrap_or_else(|e| {
+            tracing::warn!(time = %settings.time, error = %e, "invalid digest time, using 18:00");
+            NaiveTime::from_hms_opt(18, 0, 0).unwrap()
+        });
+        let offset = FixedOffset::east_opt(settings.utc_offset_hours * 3600)
+            .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+        let notifiers = settings
+            .notifiers
+            .iter()
+            .filter_map(|config| Notifier::from_config(config, telegram))
+            .collect();
+
+        Self {
+            settings,
+            time,
+            offset,
+            db,
+            notifiers,
+        }
+    }
+
+    /// Sleep until each scheduled time and generate the digest
+    pub async fn run(self) {
+        loop {
+            let now = Utc::now();
+            let next = next_run(now, self.time, self.offset);
+            tracing::debug!(next_run = %next, "next daily digest scheduled");
+            tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+
+            if let Err(e) = self.generate(next).await {
+                tracing::error!(error = %e, "failed to generate daily digest");
+            }
+        }
+    }
+
+    /// Generate, store and deliver the digest for the window ending at `until`
+    pub async fn generate(&self, until: DateTime<Utc>) -> crate::Result<()> {
+        let since = until - Duration::hours(DIGEST_WINDOW_HOURS);
+        let date = until.with_timezone(&self.offset).date_naive();
+
+        let activity = self.db.get_digest_activity(since, until).await?;
+        let title = format!("Daily digest {}", date);
+        let markdown = render_digest(date, &activity, self.offset);
+
+        let project = self
+            .db
+            .get_or_create_project(&self.settings.project, "Daily activity digests")
+            .await?;
+        let task = self
+            .db
+            .create_task(CreateTask::new(
+                title.clone(),
+                TaskStatus::Done,
+                0,
+                project.id,
+            ))
+            .await?;
+        self.db
+            .create_artifact(
+                task.id,
+                project.id,
+                None,
+                None,
+                "digest",
+                serde_json::json!({
+                    "date": date.to_string(),
+                    "since": since,
+                    "until": until,
+                    "markdown": markdown,
+                    "completed_tasks": activity.completed_tasks.len(),
+                    "failed_sessions": activity.failed_sessions.len(),
+                    "pull_requests": activity.pull_requests.len(),
+                    "permission_denials": activity.permission_denials.len(),
+                }),
+            )
+            .await?;
+
+        tracing::info!(task_id = %task.id, date = %date, "daily digest generated");
+
+        for notifier in &self.notifiers {
+            if let Err(e) = notifier.send(&title, &markdown).await {
+                tracing::warn!(notifier = notifier.name(), error = %e, "failed to deliver digest");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Next occurrence of `time` (local to `offset`) strictly after `now`
+fn next_run(now: DateTime<Utc>, time: NaiveTime, offset: FixedOffset) -> DateTime<Utc> {
+    let local_date = now.with_timezone(&offset).date_naive();
+    let candidate = local_date
+        .and_time(time)
+        .and_local_timezone(offset)
+        .unwrap()
+        .with_timezone(&Utc);
+    if candidate > now {
+        candidate
+    } else {
+        candidate + Duration::days(1)
+    }
+}
+
+/// Render the digest as Markdown
+fn render_digest(date: NaiveDate, activity: &DigestActivity, offset: FixedOffset) -> String {
+    let local_time = |t: DateTime<Utc>| t.with_timezone(&offset).format("%H:%M").to_string();
+    let mut out = format!("# Daily digest {}\n\n", date);
+
+    out.push_str(&format!(
+        "{} tasks completed, {} failed sessions, {} pull requests, {} permission denials\n",
+        activity.completed_tasks.len(),
+        activity.failed_sessions.len(),
+        activity.pull_requests.len(),
+        activity.permission_denials.len(),
+    ));
+
+    out.push_str("\n## Completed tasks\n\n");
+    if activity.completed_tasks.is_empty() {
+        out.push_str("_None_\n");
+    }
+    for task in &activity.completed_tasks {
+        out.push_str(&format!(
+            "- [{}] {} ({})\n",
+            task.project_name,
+            one_line(&task.content),
+            local_time(task.completed_at)
+        ));
+    }
+
+    out.push_str("\n## Failed sessions\n\n");
+    if activity.failed_sessions.is_empty() {
+        out.push_str("_None_\n");
+    }
+    for session in &activity.failed_sessions {
+        let ended = session
+            .ended_at
+            .map(|t| format!(" at {}", local_time(t)))
+            .unwrap_or_default();
+        out.push_str(&format!(
+            "- {} (session {}){}\n",
+            session.agent_name,
+            &session.session_id.to_string()[..8],
+            ended
+        ));
+    }
+
+    out.push_str("\n## Pull requests\n\n");
+    if activity.pull_requests.is_empty() {
+        out.push_str("_None_\n");
+    }
+    for pr in &activity.pull_requests {
+        let data = &pr.data;
+        let state = match data.get("state").and_then(|v| v.as_str()) {
+            Some("merged") => "Merged",
+            _ => "Opened",
+        };
+        out.push_str(&format!(
+            "- {}: [{}/{}#{}]({})\n",
+            state,
+            data.get("owner").and_then(|v| v.as_str()).unwrap_or("?"),
+            data.get("repo").and_then(|v| v.as_str()).unwrap_or("?"),
+            data.get("number").and_then(|v| v.as_u64()).unwrap_or(0),
+            data.get("url").and_then(|v| v.as_str()).unwrap_or(""),
+        ));
+    }
+
+    out.push_str("\n## Permission denials\n\n");
+    if activity.permission_denials.is_empty() {
+        out.push_str("_None_\n");
+    }
+    for denial in &activity.permission_denials {
+        out.push_str(&format!(
+            "- {} ({}, {})\n",
+            one_line(&denial.tool_title),
+            denial.choice,
+            local_time(denial.responded_at)
+        ));
+    }
+
+    out
+}
+
+/// First line of `text`, truncated for list items
+fn one_line(text: &str) -> String {
+    let line = text.lines().next().unwrap_or("").trim();
+    if line.chars().count() > LINE_MAX_CHARS {
+        format!("{}…", line.chars().take(LINE_MAX_CHARS).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Artifact, DigestCompletedTask, DigestPermissionDenial};
+    use chrono::TimeZone;
+    use uuid::Uuid;
+
+    fn hk() -> FixedOffset {
+        FixedOffset::east_opt(8 * 3600).unwrap()
+    }
+
+    #[test]
+    fn test_next_run_same_day_and_rollover() {
+        let time = NaiveTime::from_hms_opt(18, 0, 0).unwrap();
+
+        // 09:00 HK -> 18:00 HK the same day (10:00 UTC)
+        let morning = Utc.with_ymd_and_hms(2024, 3, 5, 1, 0, 0).unwrap();
+        assert_eq!(
+            next_run(morning, time, hk()),
+            Utc.with_ymd_and_hms(2024, 3, 5, 10, 0, 0).unwrap()
+        );
+
+        // Exactly at 18:00 HK -> tomorrow
+        let at = Utc.with_ymd_and_hms(2024, 3, 5, 10, 0, 0).unwrap();
+        assert_eq!(
+            next_run(at, time, hk()),
+            Utc.with_ymd_and_hms(2024, 3, 6, 10, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_render_digest_sections() {
+        let at = Utc.with_ymd_and_hms(2024, 3, 5, 4, 30, 0).unwrap();
+        let activity = DigestActivity {
+            completed_tasks: vec![DigestCompletedTask {
+                task_id: Uuid::nil(),
+                content: "Fix login redirect\nmore details".to_string(),
+                project_name: "web".to_string(),
+                completed_at: at,
+            }],
+            failed_sessions: vec![],
+            pull_requests: vec![Artifact {
+                id: Uuid::nil(),
+                task_id: Uuid::nil(),
+                project_id: Uuid::nil(),
+                agent_id: None,
+                session_id: None,
+                artifact_type: "github_pr".to_string(),
+                data: serde_json::json!({
+                    "url": "https://github.com/acme/web/pull/7",
+                    "owner": "acme",
+                    "repo": "web",
+                    "number": 7,
+                }),
+                created_at: at,
+                updated_at: at,
+            }],
+            permission_denials: vec![DigestPermissionDenial {
+                session_id: "s1".to_string(),
+                tool_title: "rm -rf build".to_string(),
+                choice: "cancelled".to_string(),
+                responded_at: at,
+            }],
+        };
+
+        let md = render_digest(
+            NaiveDate::from_ymd_opt(2024, 3, 5).unwrap(),
+            &activity,
+            hk(),
+        );
+        assert!(md.starts_with("# Daily digest 2024-03-05\n"));
+        assert!(md.contains("1 tasks completed, 0 failed sessions, 1 pull requests"));
+        assert!(md.contains("- [web] Fix login redirect (12:30)\n"));
+        assert!(md.contains("## Failed sessions\n\n_None_\n"));
+        assert!(md.contains("- Opened: [acme/web#7](https://github.com/acme/web/pull/7)\n"));
+        assert!(md.contains("- rm -rf build (cancelled, 12:30)\n"));
+    }
+}
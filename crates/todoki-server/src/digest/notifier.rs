@@ -0,0 +1,75 @@
+//! Delivery channels for generated digests
+
+use crate::config::{NotifierConfig, TelegramSettings};
+use crate::telegram::api::BotApi;
+
+/// Telegram rejects messages longer than 4096 characters
+const TELEGRAM_MAX_CHARS: usize = 4000;
+
+pub enum Notifier {
+    Telegram {
+        api: BotApi,
+        chat_ids: Vec<i64>,
+    },
+    Webhook {
+        http_client: reqwest::Client,
+        url: String,
+    },
+}
+
+impl Notifier {
+    /// Build a notifier from config; `None` when its prerequisites are missing
+    pub fn from_config(config: &NotifierConfig, telegram: &TelegramSettings) -> Option<Self> {
+        match config {
+            NotifierConfig::Telegram if telegram.enabled() => Some(Self::Telegram {
+                api: BotApi::new(&telegram.bot_token),
+                chat_ids: telegram.allowed_chat_ids.clone(),
+            }),
+            NotifierConfig::Telegram => {
+                tracing::warn!("telegram digest notifier configured but the bot is disabled");
+                None
+            }
+            NotifierConfig::Webhook { url } => Some(Self::Webhook {
+                http_client: reqwest::Client::new(),
+                url: url.clone(),
+            }),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Telegram { .. } => "telegram",
+            Self::Webhook { .. } => "webhook",
+        }
+    }
+
+    pub async fn send(&self, title: &str, markdown: &str) -> anyhow::Result<()> {
+        match self {
+            Self::Telegram { api, chat_ids } => {
+                let text = truncate(markdown, TELEGRAM_MAX_CHARS);
+                for chat_id in chat_ids {
+                    api.send_message(*chat_id, &text, None).await?;
+                }
+                Ok(())
+            }
+            Self::Webhook { http_client, url } => {
+                http_client
+                    .post(url)
+                    .json(&serde_json::json!({ "title": title, "markdown": markdown }))
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                Ok(())
+            }
+        }
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(max_chars).collect();
+    out.push_str("\n…");
+    out
+}
@@ -0,0 +1,189 @@
+//! Write-behind batching of event inserts
+//!
+//! Appends are queued to a single writer task that stores whatever has
+//! queued up in one statement: while an insert runs, the next appends wait
+//! and go out together with it. A lone append waits at most `max_latency`
+//! for company. Callers still get their cursor back once their row is
+//! stored, so the publisher broadcasts in the same order as before, and as
+//! batches are written one after another a reader never sees a cursor
+//! committed before a lower one.
+//!
+//! Kept free of crate imports so the benchmarks in `benches/` can include
+//! it directly.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+
+/// Reserve `$1` cursors in one round trip
+pub const ALLOCATE_CURSORS_SQL: &str =
+    "SELECT nextval('events_cursor_seq') AS cursor FROM generate_series(1, $1::INT)";
+
+/// Insert a batch of events with pre-allocated cursors, one array per column
+pub const BATCH_INSERT_SQL: &str = r#"
+    INSERT INTO events
+        (cursor, kind, time, agent_id, session_id, task_id, correlation_id, request_id, data,
+         schema_version)
+    SELECT * FROM unnest(
+        $1::BIGINT[], $2::TEXT[], $3::TIMESTAMPTZ[], $4::UUID[], $5::UUID[], $6::UUID[],
+        $7::UUID[], $8::TEXT[], $9::JSONB[], $10::INT[]
+    )
+"#;
+
+/// Stores a batch of items, returning one result per item in order
+#[async_trait]
+pub trait BatchSink<T>: Send + Sync + 'static {
+    type Output: Send + 'static;
+
+    async fn write(&self, items: Vec<T>) -> Vec<Result<Self::Output, String>>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BatchSettings {
+    /// Most items stored by one write
+    pub max_batch: usize,
+    /// Longest a queued item waits for others before it is written
+    pub max_latency: Duration,
+}
+
+struct Pending<T, R> {
+    item: T,
+    reply: oneshot::Sender<Result<R, String>>,
+}
+
+/// Handle to a writer task; dropping every handle stops the task once the
+/// queue is written
+pub struct WriteBehind<T, R> {
+    tx: mpsc::Sender<Pending<T, R>>,
+}
+
+impl<T, R> Clone for WriteBehind<T, R> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+impl<T: Send + 'static, R: Send + 'static> WriteBehind<T, R> {
+    pub fn spawn<S: BatchSink<T, Output = R>>(sink: Arc<S>, settings: BatchSettings) -> Self {
+        let max_batch = settings.max_batch.max(1);
+        // A few batches of backlog; beyond that appends wait, as they would
+        // for a pool connection
+        let (tx, rx) = mpsc::channel(max_batch * 4);
+        tokio::spawn(run_writer(sink, rx, max_batch, settings.max_latency));
+        Self { tx }
+    }
+
+    /// Queue `item` and wait until it is written
+    pub async fn submit(&self, item: T) -> anyhow::Result<R> {
+        let (reply, done) = oneshot::channel();
+        self.tx
+            .send(Pending { item, reply })
+            .await
+            .map_err(|_| anyhow::anyhow!("event writer stopped"))?;
+        done.await
+            .map_err(|_| anyhow::anyhow!("event writer stopped"))?
+            .map_err(anyhow::Error::msg)
+    }
+}
+
+async fn run_writer<T, S: BatchSink<T>>(
+    sink: Arc<S>,
+    mut rx: mpsc::Receiver<Pending<T, S::Output>>,
+    max_batch: usize,
+    max_latency: Duration,
+) {
+    while let Some(first) = rx.recv().await {
+        let deadline = Instant::now() + max_latency;
+        let mut batch = vec![first];
+        while batch.len() < max_batch {
+            // Take what is queued already, then linger for more
+            match rx.try_recv() {
+                Ok(pending) => batch.push(pending),
+                Err(_) => match tokio::time::timeout_at(deadline, rx.recv()).await {
+                    Ok(Some(pending)) => batch.push(pending),
+                    _ => break,
+                },
+            }
+        }
+
+        let (items, replies): (Vec<T>, Vec<_>) =
+            batch.into_iter().map(|p| (p.item, p.reply)).unzip();
+        let count = items.len();
+        let mut results = sink.write(items).await.into_iter();
+        for reply in replies {
+            let result = results
+                .next()
+                .unwrap_or_else(|| Err(format!("batch of {} returned too few results", count)));
+            let _ = reply.send(result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Records batch sizes and numbers items in arrival order
+    #[derive(Default)]
+    struct Counter {
+        batches: Mutex<Vec<usize>>,
+    }
+
+    #[async_trait]
+    impl BatchSink<u32> for Counter {
+        type Output = u32;
+
+        async fn write(&self, items: Vec<u32>) -> Vec<Result<u32, String>> {
+            self.batches.lock().unwrap().push(items.len());
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            items
+                .into_iter()
+                .map(|i| if i == 13 { Err("unlucky".to_string()) } else { Ok(i * 2) })
+                .collect()
+        }
+    }
+
+    fn settings(max_batch: usize) -> BatchSettings {
+        BatchSettings {
+            max_batch,
+            max_latency: Duration::from_millis(2),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_submits_share_batches() {
+        let sink = Arc::new(Counter::default());
+        let writer = WriteBehind::spawn(sink.clone(), settings(8));
+
+        let submits = (0..20u32).map(|i| {
+            let writer = writer.clone();
+            async move { writer.submit(i).await }
+        });
+        let results = futures_util::future::join_all(submits).await;
+
+        for (i, result) in results.into_iter().enumerate() {
+            match i {
+                13 => assert_eq!(result.unwrap_err().to_string(), "unlucky"),
+                _ => assert_eq!(result.unwrap(), i as u32 * 2),
+            }
+        }
+        let batches = sink.batches.lock().unwrap().clone();
+        assert_eq!(batches.iter().sum::<usize>(), 20);
+        assert!(batches.iter().all(|size| *size <= 8));
+        assert!(batches.len() < 20);
+    }
+
+    #[tokio::test]
+    async fn test_lone_submit_is_written_after_latency() {
+        let sink = Arc::new(Counter::default());
+        let writer = WriteBehind::spawn(sink.clone(), settings(64));
+        assert_eq!(writer.submit(1).await.unwrap(), 2);
+        assert_eq!(*sink.batches.lock().unwrap(), vec![1]);
+    }
+}
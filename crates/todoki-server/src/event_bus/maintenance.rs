@@ -0,0 +1,26 @@
+use super::store::PgEventStore;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How many months of partitions to keep created ahead of time
+const MONTHS_AHEAD: u32 = 2;
+
+/// How often the maintenance worker runs
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Background worker keeping monthly event partitions created ahead of time
+///
+/// Runs once at startup and then periodically, so inserts never fall into
+/// the default partition when a new month begins.
+pub async fn run_partition_maintenance(store: Arc<PgEventStore>) {
+    let mut interval = tokio::time::interval(MAINTENANCE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        match store.ensure_partitions(MONTHS_AHEAD).await {
+            Ok(()) => tracing::debug!(months_ahead = MONTHS_AHEAD, "event partitions ensured"),
+            Err(e) => tracing::error!(error = %e, "failed to ensure event partitions"),
+        }
+    }
+}
@@ -0,0 +1,393 @@
+//! Event store kept in process memory
+//!
+//! Behaves like [`super::PgEventStore`] (cursors from 1, `*` wildcards in
+//! kinds, the same ordering and limits) but loses everything on exit. Used by
+//! tests of code built on the event bus, and by `todoki --memory-events` for
+//! demos that should leave the events table alone.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::RwLock;
+#[cfg(test)]
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::store::EventStore;
+use super::types::{AgentEventFilter, DataFilter, Event, EventCount, EventGroupBy, EventPage};
+#[cfg(test)]
+use super::{EventPublisher, EventSubscriber};
+
+/// Default and maximum page size of [`EventStore::query`], as in Postgres
+const DEFAULT_LIMIT: usize = 1000;
+const MAX_LIMIT: usize = 10000;
+
+#[derive(Default)]
+pub struct InMemoryEventStore {
+    /// Ordered by cursor
+    events: RwLock<Vec<Event>>,
+    /// Last cursor handed out; like a sequence it survives pruning
+    last_cursor: AtomicI64,
+}
+
+impl InMemoryEventStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Vec<Event>> {
+        self.events.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, Vec<Event>> {
+        self.events.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Publisher and subscriber sharing one in-memory store, for tests
+#[cfg(test)]
+pub fn bus() -> (
+    Arc<InMemoryEventStore>,
+    Arc<EventPublisher>,
+    Arc<EventSubscriber>,
+) {
+    let store = Arc::new(InMemoryEventStore::new());
+    let publisher = Arc::new(EventPublisher::new(store.clone()));
+    let subscriber = Arc::new(EventSubscriber::new(store.clone()));
+    (store, publisher, subscriber)
+}
+
+#[async_trait]
+impl EventStore for InMemoryEventStore {
+    async fn append(&self, event: &mut Event) -> Result<i64> {
+        let mut events = self.write();
+        let cursor = self.last_cursor.fetch_add(1, Ordering::SeqCst) + 1;
+        event.cursor = cursor;
+        events.push(event.clone());
+        Ok(cursor)
+    }
+
+    async fn query(
+        &self,
+        from_cursor: i64,
+        to_cursor: Option<i64>,
+        kinds: Option<&[String]>,
+        agent_id: Option<Uuid>,
+        task_id: Option<Uuid>,
+        correlation_id: Option<Uuid>,
+        data: Option<&DataFilter>,
+        limit: Option<usize>,
+    ) -> Result<Vec<Event>> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+        Ok(self
+            .read()
+            .iter()
+            .filter(|e| e.cursor > from_cursor)
+            .filter(|e| to_cursor.is_none_or(|to| e.cursor <= to))
+            .filter(|e| matches_kinds(kinds, &e.kind))
+            .filter(|e| agent_id.is_none_or(|id| e.agent_id == id))
+            .filter(|e| task_id.is_none_or(|id| e.task_id == Some(id)))
+            .filter(|e| correlation_id.is_none_or(|id| e.correlation_id == Some(id)))
+            .filter(|e| data.is_none_or(|filter| filter.matches(&e.data)))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn aggregate(
+        &self,
+        group_by: EventGroupBy,
+        from_cursor: i64,
+        since: Option<DateTime<Utc>>,
+        kinds: Option<&[String]>,
+        agent_id: Option<Uuid>,
+        task_id: Option<Uuid>,
+        data: Option<&DataFilter>,
+    ) -> Result<Vec<EventCount>> {
+        let mut counts: HashMap<String, i64> = HashMap::new();
+        for event in self.read().iter() {
+            if event.cursor <= from_cursor
+                || since.is_some_and(|since| event.time < since)
+                || !matches_kinds(kinds, &event.kind)
+                || agent_id.is_some_and(|id| event.agent_id != id)
+                || task_id.is_some_and(|id| event.task_id != Some(id))
+                || data.is_some_and(|filter| !filter.matches(&event.data))
+            {
+                continue;
+            }
+            let key = match group_by {
+                EventGroupBy::Kind => event.kind.clone(),
+                EventGroupBy::Agent => event.agent_id.to_string(),
+                EventGroupBy::Hour => hour_bucket(event.time),
+            };
+            *counts.entry(key).or_default() += 1;
+        }
+
+        let mut counts: Vec<EventCount> = counts
+            .into_iter()
+            .map(|(key, count)| EventCount { key, count })
+            .collect();
+        // Same order as the Postgres store
+        match group_by {
+            EventGroupBy::Hour => counts.sort_by(|a, b| a.key.cmp(&b.key)),
+            _ => counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key))),
+        }
+        Ok(counts)
+    }
+
+    async fn session_events(
+        &self,
+        agent_id: Uuid,
+        session_id: &str,
+        limit: usize,
+    ) -> Result<Vec<Event>> {
+        let events = self.read();
+        let mut matching: Vec<Event> = events
+            .iter()
+            .rev()
+            .filter(|e| e.agent_id == agent_id)
+            .filter(|e| e.data.get("session_id").and_then(|v| v.as_str()) == Some(session_id))
+            .take(limit.min(MAX_LIMIT))
+            .cloned()
+            .collect();
+        matching.reverse();
+        Ok(matching)
+    }
+
+    async fn agent_events(
+        &self,
+        agent_id: Uuid,
+        filter: &AgentEventFilter,
+        page: EventPage,
+        limit: usize,
+    ) -> Result<Vec<Event>> {
+        let session_id = filter.session_id.map(|id| id.to_string());
+        let matches = |e: &&Event| {
+            let payload = |field: &str| e.data.get(field).and_then(|v| v.as_str());
+            e.agent_id == agent_id
+                && matches_kinds(filter.kinds.as_deref(), &e.kind)
+                && filter.streams.as_ref().is_none_or(|streams| {
+                    payload("stream").is_some_and(|s| streams.iter().any(|want| want == s))
+                })
+                && session_id.as_deref().is_none_or(|id| {
+                    e.session_id.map(|s| s.to_string()).as_deref() == Some(id)
+                        || payload("session_id") == Some(id)
+                })
+                && filter.since.is_none_or(|since| e.time >= since)
+                && filter.until.is_none_or(|until| e.time < until)
+        };
+
+        let events = self.read();
+        let limit = limit.min(MAX_LIMIT);
+        Ok(match page {
+            EventPage::Latest => events.iter().rev().filter(matches).take(limit).cloned().collect(),
+            EventPage::Before(cursor) => events
+                .iter()
+                .rev()
+                .filter(|e| e.cursor < cursor)
+                .filter(matches)
+                .take(limit)
+                .cloned()
+                .collect(),
+            EventPage::After(cursor) => events
+                .iter()
+                .filter(|e| e.cursor > cursor)
+                .filter(matches)
+                .take(limit)
+                .cloned()
+                .collect(),
+        })
+    }
+
+    async fn latest_cursor(&self) -> Result<i64> {
+        Ok(self.read().last().map_or(0, |e| e.cursor))
+    }
+
+    async fn prune_before(&self, before: DateTime<Utc>) -> Result<u64> {
+        let mut events = self.write();
+        let count = events.len();
+        events.retain(|e| e.time >= before);
+        Ok((count - events.len()) as u64)
+    }
+}
+
+fn matches_kinds(kinds: Option<&[String]>, kind: &str) -> bool {
+    kinds.is_none_or(|patterns| patterns.iter().any(|p| wildcard_match(p, kind)))
+}
+
+/// `*` matches any run of characters, like `%` in the Postgres store's LIKE
+fn wildcard_match(pattern: &str, kind: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = kind.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Start of the UTC hour, formatted like the Postgres store's hour keys
+fn hour_bucket(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%dT%H:00:00Z").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_bus::EventScope;
+    use chrono::TimeZone;
+    use serde_json::json;
+    use todoki_protocol::event_bus::{BuiltinEvent, SystemConfigReloadedData};
+
+    fn event(kind: &str, agent_id: Uuid, data: serde_json::Value) -> Event {
+        Event {
+            cursor: 0,
+            kind: kind.to_string(),
+            time: Utc::now(),
+            agent_id,
+            session_id: None,
+            task_id: None,
+            correlation_id: None,
+            request_id: None,
+            data,
+            schema_version: 1,
+        }
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("task.*", "task.created"));
+        assert!(wildcard_match("task.created", "task.created"));
+        assert!(wildcard_match("*.completed", "relay.spawn_completed"));
+        assert!(wildcard_match("relay.*_completed", "relay.spawn_completed"));
+        assert!(wildcard_match("*", "anything"));
+        assert!(!wildcard_match("task.created", "task.created.later"));
+        assert!(!wildcard_match("agent.*", "task.created"));
+        assert!(!wildcard_match("relay.*_failed", "relay.spawn_completed"));
+    }
+
+    #[tokio::test]
+    async fn test_publish_and_query() {
+        let (store, publisher, subscriber) = bus();
+        let mut live = publisher.subscribe();
+        let agent = Uuid::new_v4();
+
+        assert_eq!(subscriber.latest_cursor().await.unwrap(), 0);
+        publisher.emit(event("task.created", agent, json!({}))).await.unwrap();
+        publisher.emit(event("task.updated", Uuid::nil(), json!({}))).await.unwrap();
+        let reloaded = BuiltinEvent::SystemConfigReloaded(SystemConfigReloadedData {
+            applied: vec!["log".to_string()],
+            restart_required: vec![],
+        });
+        let cursor = publisher.emit_builtin(reloaded, EventScope::system()).await.unwrap();
+        assert_eq!(cursor, 3);
+        assert_eq!(store.latest_cursor().await.unwrap(), 3);
+        assert_eq!(live.recv().await.unwrap().cursor, 1);
+
+        let tasks = vec!["task.*".to_string()];
+        let polled = subscriber
+            .poll(0, Some(&tasks), None, None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(polled.iter().map(|e| e.cursor).collect::<Vec<_>>(), vec![1, 2]);
+        let polled = subscriber.poll(1, None, Some(agent), None, None, None, None).await.unwrap();
+        assert!(polled.is_empty());
+        let polled = subscriber.poll(0, None, None, None, None, None, Some(1)).await.unwrap();
+        assert_eq!(polled.len(), 1);
+
+        let reloaded_log = DataFilter::parse(r#"data.applied=["log"]"#).unwrap();
+        let polled = subscriber
+            .poll(0, None, None, None, None, Some(&reloaded_log), None)
+            .await
+            .unwrap();
+        assert_eq!(polled.iter().map(|e| e.cursor).collect::<Vec<_>>(), vec![3]);
+
+        let counts = subscriber
+            .aggregate(EventGroupBy::Kind, 0, None, None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(counts.len(), 3);
+        assert!(counts.iter().all(|c| c.count == 1));
+        assert_eq!(counts[0].key, "system.config_reloaded");
+    }
+
+    #[tokio::test]
+    async fn test_session_events_and_prune() {
+        let store = InMemoryEventStore::new();
+        let agent = Uuid::new_v4();
+        for i in 0..5 {
+            let session = if i % 2 == 0 { "s1" } else { "s2" };
+            let mut e = event("agent.output_batch", agent, json!({ "session_id": session }));
+            e.time = Utc.with_ymd_and_hms(2025, 1, 1, i, 30, 0).unwrap();
+            store.append(&mut e).await.unwrap();
+        }
+
+        let events = store.session_events(agent, "s1", 2).await.unwrap();
+        assert_eq!(events.iter().map(|e| e.cursor).collect::<Vec<_>>(), vec![3, 5]);
+
+        let hours = store
+            .aggregate(EventGroupBy::Hour, 0, None, None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(hours[0].key, "2025-01-01T00:00:00Z");
+
+        let cutoff = Utc.with_ymd_and_hms(2025, 1, 1, 2, 0, 0).unwrap();
+        assert_eq!(store.prune_before(cutoff).await.unwrap(), 2);
+        let left = store.query(0, None, None, None, None, None, None, None).await.unwrap();
+        assert_eq!(left.iter().map(|e| e.cursor).collect::<Vec<_>>(), vec![3, 4, 5]);
+
+        // Cursors keep counting after pruning, like the Postgres sequence
+        store.prune_before(Utc::now()).await.unwrap();
+        let mut next = event("task.created", agent, json!({}));
+        assert_eq!(store.append(&mut next).await.unwrap(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_agent_events_pages_and_filters() {
+        let (store, _, subscriber) = bus();
+        let agent = Uuid::new_v4();
+        let session = Uuid::new_v4();
+        for i in 0..6i64 {
+            let stream = if i % 2 == 0 { "assistant" } else { "tool_use" };
+            let data = json!({ "session_id": session.to_string(), "stream": stream });
+            let mut e = event("agent.output_batch", agent, data);
+            e.time = Utc.with_ymd_and_hms(2025, 1, 1, i as u32, 0, 0).unwrap();
+            store.append(&mut e).await.unwrap();
+        }
+        let mut other = event("agent.output_batch", Uuid::new_v4(), json!({}));
+        store.append(&mut other).await.unwrap();
+
+        let all = AgentEventFilter::default();
+        let page = subscriber.agent_events(agent, &all, EventPage::Latest, 4).await.unwrap();
+        let cursors: Vec<i64> = page.events.iter().map(|e| e.cursor).collect();
+        assert_eq!(cursors, vec![6, 5, 4, 3]);
+        assert_eq!((page.next_cursor, page.prev_cursor), (Some(3), None));
+
+        let page = subscriber.agent_events(agent, &all, EventPage::Before(3), 4).await.unwrap();
+        let cursors: Vec<i64> = page.events.iter().map(|e| e.cursor).collect();
+        assert_eq!(cursors, vec![2, 1]);
+        assert_eq!((page.next_cursor, page.prev_cursor), (None, Some(2)));
+
+        let filter = AgentEventFilter {
+            streams: Some(vec!["assistant".to_string()]),
+            session_id: Some(session),
+            since: Some(Utc.with_ymd_and_hms(2025, 1, 1, 1, 0, 0).unwrap()),
+            ..AgentEventFilter::default()
+        };
+        let page = subscriber.agent_events(agent, &filter, EventPage::After(0), 10).await.unwrap();
+        let cursors: Vec<i64> = page.events.iter().map(|e| e.cursor).collect();
+        assert_eq!(cursors, vec![5, 3]);
+    }
+}
@@ -6,10 +6,18 @@
 pub mod types;
 pub mod kinds;
 pub mod store;
+pub mod batch;
+pub mod memory;
 pub mod publisher;
 pub mod subscriber;
+pub mod maintenance;
+pub mod outbox;
 
-pub use types::Event;
-pub use store::PgEventStore;
+pub use types::{
+    AgentEventFilter, AgentEventPage, DataFilter, Event, EventCount, EventGroupBy, EventPage,
+    EventScope,
+};
+pub use store::{EventStore, PgEventStore};
+pub use memory::InMemoryEventStore;
 pub use publisher::EventPublisher;
 pub use subscriber::EventSubscriber;
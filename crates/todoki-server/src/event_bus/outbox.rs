@@ -0,0 +1,157 @@
+//! Transactional outbox
+//!
+//! Domain changes that must announce themselves write their events to
+//! `event_outbox` in the same transaction as the change, so a crash between
+//! the write and the publish can no longer lose the event. The relay worker
+//! below publishes pending rows in order and marks them delivered.
+//!
+//! Delivery is at-least-once: if the process dies after publishing but
+//! before the row is marked, the event is published again on restart.
+
+use super::publisher::EventPublisher;
+use super::types::Event;
+use crate::net::request_id;
+use anyhow::Result;
+use conservator::{Executor, PooledConnection};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often pending outbox rows are picked up
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Max rows published per poll
+const BATCH_SIZE: i64 = 100;
+
+/// How often delivered rows are cleaned up
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How long delivered rows are kept around for inspection
+const DELIVERED_RETENTION_HOURS: i32 = 24;
+
+/// Queue `event` for publishing as part of the caller's transaction
+pub async fn enqueue<E: Executor>(executor: &E, event: &Event) -> Result<(), conservator::Error> {
+    // Published later by the worker, outside the request
+    let request_id = event.request_id.clone().or_else(request_id::current);
+    executor
+        .execute(
+            r#"
+            INSERT INTO event_outbox
+                (kind, time, agent_id, session_id, task_id, correlation_id, request_id, data,
+                 schema_version)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+            &[
+                &event.kind,
+                &event.time,
+                &event.agent_id,
+                &event.session_id,
+                &event.task_id,
+                &event.correlation_id,
+                &request_id,
+                &event.data,
+                &event.schema_version,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+/// Background worker publishing outbox rows to the event bus
+pub async fn run_outbox_relay(pool: Arc<PooledConnection>, publisher: Arc<EventPublisher>) {
+    let mut poll = tokio::time::interval(POLL_INTERVAL);
+    let mut prune = tokio::time::interval(PRUNE_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = poll.tick() => {
+                match publish_pending(&pool, &publisher).await {
+                    Ok(0) => {}
+                    Ok(n) => tracing::debug!(published = n, "outbox events published"),
+                    Err(e) => tracing::error!(error = %e, "failed to publish outbox events"),
+                }
+            }
+            _ = prune.tick() => {
+                if let Err(e) = prune_delivered(&pool).await {
+                    tracing::error!(error = %e, "failed to prune delivered outbox events");
+                }
+            }
+        }
+    }
+}
+
+/// Publish one batch of pending rows; returns how many were delivered
+async fn publish_pending(pool: &PooledConnection, publisher: &EventPublisher) -> Result<usize> {
+    let mut conn = pool.get().await?;
+    let tx = conn.begin().await?;
+
+    // SKIP LOCKED lets several server instances share the outbox without
+    // publishing the same row twice
+    let rows = tx
+        .query(
+            r#"
+            SELECT id, kind, time, agent_id, session_id, task_id, correlation_id, request_id,
+                   data, schema_version
+            FROM event_outbox
+            WHERE delivered_at IS NULL
+            ORDER BY id ASC
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+            "#,
+            &[&BATCH_SIZE],
+        )
+        .await?;
+
+    let mut delivered = 0;
+    for row in &rows {
+        let id: i64 = row.get("id");
+        let event = Event {
+            cursor: 0, // Assigned by the event store
+            kind: row.get("kind"),
+            time: row.get("time"),
+            agent_id: row.get("agent_id"),
+            session_id: row.get("session_id"),
+            task_id: row.get("task_id"),
+            correlation_id: row.get("correlation_id"),
+            request_id: row.get("request_id"),
+            data: row.get("data"),
+            schema_version: row.get("schema_version"),
+        };
+
+        // Stop at the first failure so events stay in order; the rest are
+        // retried on the next poll
+        let cursor = match publisher.emit(event).await {
+            Ok(cursor) => cursor,
+            Err(e) => {
+                tracing::warn!(outbox_id = id, error = %e, "failed to publish outbox event");
+                break;
+            }
+        };
+
+        tx.execute(
+            "UPDATE event_outbox SET delivered_at = NOW(), event_cursor = $2 WHERE id = $1",
+            &[&id, &cursor],
+        )
+        .await?;
+        delivered += 1;
+    }
+
+    tx.commit().await?;
+    Ok(delivered)
+}
+
+async fn prune_delivered(pool: &PooledConnection) -> Result<()> {
+    let conn = pool.get().await?;
+    let deleted = conn
+        .execute(
+            r#"
+            DELETE FROM event_outbox
+            WHERE delivered_at < NOW() - make_interval(hours => $1)
+            "#,
+            &[&DELIVERED_RETENTION_HOURS],
+        )
+        .await?;
+    if deleted > 0 {
+        tracing::debug!(rows = deleted, "pruned delivered outbox events");
+    }
+    Ok(())
+}
@@ -1,7 +1,9 @@
 use super::store::EventStore;
-use super::types::Event;
+use super::types::{Event, EventScope};
+use crate::net::request_id;
 use anyhow::Result;
 use std::sync::Arc;
+use todoki_protocol::event_bus::BuiltinEvent;
 use tokio::sync::broadcast;
 
 /// Event Publisher
@@ -30,6 +32,11 @@ impl EventPublisher {
     ///
     /// Returns the assigned cursor on success
     pub async fn emit(&self, mut event: Event) -> Result<i64> {
+        // Events emitted while handling an API request are tagged with it
+        if event.request_id.is_none() {
+            event.request_id = request_id::current();
+        }
+
         // Persist to store (assigns cursor)
         let cursor = self.store.append(&mut event).await?;
 
@@ -39,6 +46,14 @@ impl EventPublisher {
         Ok(cursor)
     }
 
+    /// Emit a typed protocol event
+    ///
+    /// Prefer this over `emit` with a hand-built `json!` payload: the data
+    /// shape is checked against the protocol structs at compile time.
+    pub async fn emit_builtin(&self, event: BuiltinEvent, scope: EventScope) -> Result<i64> {
+        self.emit(Event::builtin(event, scope)).await
+    }
+
     /// Subscribe to real-time events
     ///
     /// Returns a broadcast receiver that will receive all future events.
@@ -1,7 +1,10 @@
-use super::types::Event;
+use super::batch::{
+    BatchSettings, BatchSink, WriteBehind, ALLOCATE_CURSORS_SQL, BATCH_INSERT_SQL,
+};
+use super::types::{AgentEventFilter, DataFilter, Event, EventCount, EventGroupBy, EventPage};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
 use conservator::{Creatable, Domain, Executor, PooledConnection};
 use std::sync::Arc;
 use tracing::error;
@@ -21,9 +24,41 @@ pub trait EventStore: Send + Sync {
         kinds: Option<&[String]>,
         agent_id: Option<Uuid>,
         task_id: Option<Uuid>,
+        correlation_id: Option<Uuid>,
+        data: Option<&DataFilter>,
         limit: Option<usize>,
     ) -> Result<Vec<Event>>;
 
+    /// Count events matching the filters, grouped by `group_by`
+    async fn aggregate(
+        &self,
+        group_by: EventGroupBy,
+        from_cursor: i64,
+        since: Option<DateTime<Utc>>,
+        kinds: Option<&[String]>,
+        agent_id: Option<Uuid>,
+        task_id: Option<Uuid>,
+        data: Option<&DataFilter>,
+    ) -> Result<Vec<EventCount>>;
+
+    /// Most recent `limit` events of one agent session, oldest first
+    async fn session_events(
+        &self,
+        agent_id: Uuid,
+        session_id: &str,
+        limit: usize,
+    ) -> Result<Vec<Event>>;
+
+    /// Up to `limit` events of one agent from `page` on, in the order the
+    /// page walks: newest first, except oldest first after a cursor
+    async fn agent_events(
+        &self,
+        agent_id: Uuid,
+        filter: &AgentEventFilter,
+        page: EventPage,
+        limit: usize,
+    ) -> Result<Vec<Event>>;
+
     /// Get latest cursor
     async fn latest_cursor(&self) -> Result<i64>;
 
@@ -34,36 +69,202 @@ pub trait EventStore: Send + Sync {
 /// PostgreSQL implementation of Event Store
 pub struct PgEventStore {
     pool: Arc<PooledConnection>,
+    /// Pool used for cursor-range queries (may point at a read replica)
+    read_pool: Arc<PooledConnection>,
+    /// Batches appends when set; otherwise each is its own INSERT
+    writer: Option<WriteBehind<Event, i64>>,
 }
 
 impl PgEventStore {
     pub fn new(pool: Arc<PooledConnection>) -> Self {
-        Self { pool }
+        Self {
+            read_pool: pool.clone(),
+            pool,
+            writer: None,
+        }
+    }
+
+    /// Write appends in batches of up to `settings.max_batch` (see
+    /// [`super::batch`]); a batch of 1 keeps one INSERT per event
+    pub fn with_batched_inserts(mut self, settings: BatchSettings) -> Self {
+        if settings.max_batch > 1 {
+            let sink = Arc::new(PgEventSink {
+                pool: self.pool.clone(),
+            });
+            self.writer = Some(WriteBehind::spawn(sink, settings));
+        }
+        self
+    }
+
+    /// Route read queries to a separate (replica) pool
+    pub fn with_read_pool(mut self, read_pool: Arc<PooledConnection>) -> Self {
+        self.read_pool = read_pool;
+        self
+    }
+
+    /// Ensure monthly partitions exist from the current month up to `months_ahead`
+    pub async fn ensure_partitions(&self, months_ahead: u32) -> Result<()> {
+        let conn = self.pool.get().await?;
+        let current = month_start(Utc::now().date_naive());
+        for offset in 0..=months_ahead {
+            let month = current + Months::new(offset);
+            conn.execute("SELECT ensure_events_partition($1)", &[&month])
+                .await
+                .context(format!("Failed to ensure events partition for {}", month))?;
+        }
+        Ok(())
+    }
+
+    /// List monthly partitions as (table name, month start)
+    async fn monthly_partitions(&self) -> Result<Vec<(String, NaiveDate)>> {
+        let conn = self.pool.get().await?;
+        let rows = conn
+            .query(
+                r#"
+                SELECT c.relname AS name
+                FROM pg_inherits i
+                JOIN pg_class c ON c.oid = i.inhrelid
+                WHERE i.inhparent = 'events'::regclass
+                "#,
+                &[],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                let name: String = row.get("name");
+                parse_partition_month(&name).map(|month| (name, month))
+            })
+            .collect())
     }
 }
 
-#[async_trait]
-impl EventStore for PgEventStore {
-    async fn append(&self, event: &mut Event) -> Result<i64> {
-        let create_event = event.to_create();
-        let cursor = create_event
-            .insert::<Event>()
-            .returning_pk(&*self.pool)
+/// Inserts one event on its own, letting the database assign the cursor
+async fn insert_one(pool: &PooledConnection, event: &Event) -> Result<i64> {
+    event
+        .to_create()
+        .insert::<Event>()
+        .returning_pk(pool)
+        .await
+        .map_err(|e| {
+            error!(
+                error = %e,
+                kind = %event.kind,
+                agent_id = %event.agent_id,
+                task_id = ?event.task_id,
+                "Failed to insert event into database"
+            );
+            e
+        })
+        .context(format!(
+            "Failed to insert event kind={} agent_id={} task_id={:?}",
+            event.kind, event.agent_id, event.task_id
+        ))
+}
+
+/// Writes batches of events with one INSERT each
+struct PgEventSink {
+    pool: Arc<PooledConnection>,
+}
+
+impl PgEventSink {
+    /// Reserve cursors for the batch, lowest first, and insert it
+    async fn insert_batch(&self, events: &[Event]) -> Result<Vec<i64>> {
+        let conn = self.pool.get().await?;
+        let rows = conn
+            .query(ALLOCATE_CURSORS_SQL, &[&(events.len() as i32)])
             .await
-            .map_err(|e| {
-                error!(
+            .context("Failed to allocate event cursors")?;
+        let mut cursors: Vec<i64> = rows.iter().map(|row| row.get("cursor")).collect();
+        cursors.sort_unstable();
+
+        let kinds: Vec<&str> = events.iter().map(|e| e.kind.as_str()).collect();
+        let times: Vec<DateTime<Utc>> = events.iter().map(|e| e.time).collect();
+        let agent_ids: Vec<Uuid> = events.iter().map(|e| e.agent_id).collect();
+        let session_ids: Vec<Option<Uuid>> = events.iter().map(|e| e.session_id).collect();
+        let task_ids: Vec<Option<Uuid>> = events.iter().map(|e| e.task_id).collect();
+        let correlation_ids: Vec<Option<Uuid>> =
+            events.iter().map(|e| e.correlation_id).collect();
+        let request_ids: Vec<Option<&str>> =
+            events.iter().map(|e| e.request_id.as_deref()).collect();
+        let data: Vec<&serde_json::Value> = events.iter().map(|e| &e.data).collect();
+        let schema_versions: Vec<i32> = events.iter().map(|e| e.schema_version).collect();
+
+        conn.execute(
+            BATCH_INSERT_SQL,
+            &[
+                &cursors,
+                &kinds,
+                &times,
+                &agent_ids,
+                &session_ids,
+                &task_ids,
+                &correlation_ids,
+                &request_ids,
+                &data,
+                &schema_versions,
+            ],
+        )
+        .await
+        .context(format!("Failed to insert batch of {} events", events.len()))?;
+        Ok(cursors)
+    }
+}
+
+#[async_trait]
+impl BatchSink<Event> for PgEventSink {
+    type Output = i64;
+
+    async fn write(&self, events: Vec<Event>) -> Vec<Result<i64, String>> {
+        match self.insert_batch(&events).await {
+            Ok(cursors) => cursors.into_iter().map(Ok).collect(),
+            Err(e) => {
+                // One bad event must not fail the others: retry them one by
+                // one, so only the culprit gets the error
+                tracing::warn!(
                     error = %e,
-                    kind = %event.kind,
-                    agent_id = %event.agent_id,
-                    task_id = ?event.task_id,
-                    "Failed to insert event into database"
+                    count = events.len(),
+                    "batched event insert failed, inserting one by one"
                 );
-                e
-            })
-            .context(format!(
-                "Failed to insert event kind={} agent_id={} task_id={:?}",
-                event.kind, event.agent_id, event.task_id
-            ))?;
+                let mut results = Vec::with_capacity(events.len());
+                for event in &events {
+                    let result = insert_one(&self.pool, event).await;
+                    results.push(result.map_err(|e| format!("{:#}", e)));
+                }
+                results
+            }
+        }
+    }
+}
+
+/// Start of the event's UTC hour as RFC 3339
+const HOUR_BUCKET_KEY: &str =
+    r#"to_char(date_trunc('hour', time AT TIME ZONE 'UTC'), 'YYYY-MM-DD"T"HH24:00:00"Z"')"#;
+
+/// First day of the month containing `date`
+fn month_start(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+/// Parse the month from a partition name like `events_p202501`
+fn parse_partition_month(name: &str) -> Option<NaiveDate> {
+    let suffix = name.strip_prefix("events_p")?;
+    if suffix.len() != 6 {
+        return None;
+    }
+    let year: i32 = suffix[..4].parse().ok()?;
+    let month: u32 = suffix[4..].parse().ok()?;
+    NaiveDate::from_ymd_opt(year, month, 1)
+}
+
+#[async_trait]
+impl EventStore for PgEventStore {
+    async fn append(&self, event: &mut Event) -> Result<i64> {
+        let cursor = match &self.writer {
+            Some(writer) => writer.submit(event.clone()).await?,
+            None => insert_one(&self.pool, event).await?,
+        };
 
         event.cursor = cursor;
         Ok(cursor)
@@ -76,11 +277,13 @@ impl EventStore for PgEventStore {
         kinds: Option<&[String]>,
         agent_id: Option<Uuid>,
         task_id: Option<Uuid>,
+        correlation_id: Option<Uuid>,
+        data: Option<&DataFilter>,
         limit: Option<usize>,
     ) -> Result<Vec<Event>> {
         // For complex queries with wildcard kinds, use raw SQL
         // This is more efficient and supports LIKE patterns
-        let conn = self.pool.get().await?;
+        let conn = self.read_pool.get().await?;
         let limit_i64 = limit.unwrap_or(1000).min(10000) as i64;
 
         // Convert wildcard patterns (e.g., "agent.*") to SQL LIKE patterns (e.g., "agent.%")
@@ -93,7 +296,8 @@ impl EventStore for PgEventStore {
         let rows = conn
             .query(
                 r#"
-                SELECT cursor, kind, time, agent_id, session_id, task_id, data
+                SELECT cursor, kind, time, agent_id, session_id, task_id, correlation_id,
+                       request_id, data, schema_version
                 FROM events
                 WHERE cursor > $1
                   AND ($2::BIGINT IS NULL OR cursor <= $2)
@@ -103,8 +307,10 @@ impl EventStore for PgEventStore {
                   ))
                   AND ($4::UUID IS NULL OR agent_id = $4)
                   AND ($5::UUID IS NULL OR task_id = $5)
+                  AND ($6::UUID IS NULL OR correlation_id = $6)
+                  AND ($7::JSONB IS NULL OR data @> $7)
                 ORDER BY cursor ASC
-                LIMIT $6
+                LIMIT $8
                 "#,
                 &[
                     &from_cursor,
@@ -112,6 +318,190 @@ impl EventStore for PgEventStore {
                     &kinds_patterns,
                     &agent_id,
                     &task_id,
+                    &correlation_id,
+                    &data.map(DataFilter::as_json),
+                    &limit_i64,
+                ],
+            )
+            .await?;
+
+        let events = rows
+            .iter()
+            .map(|row| Event {
+                cursor: row.get("cursor"),
+                kind: row.get("kind"),
+                time: row.get("time"),
+                agent_id: row.get("agent_id"),
+                session_id: row.get("session_id"),
+                task_id: row.get("task_id"),
+                correlation_id: row.get("correlation_id"),
+                request_id: row.get("request_id"),
+                data: row.get("data"),
+                schema_version: row.get("schema_version"),
+            })
+            .collect();
+
+        Ok(events)
+    }
+
+    async fn aggregate(
+        &self,
+        group_by: EventGroupBy,
+        from_cursor: i64,
+        since: Option<DateTime<Utc>>,
+        kinds: Option<&[String]>,
+        agent_id: Option<Uuid>,
+        task_id: Option<Uuid>,
+        data: Option<&DataFilter>,
+    ) -> Result<Vec<EventCount>> {
+        let conn = self.read_pool.get().await?;
+
+        let kinds_patterns: Option<Vec<String>> =
+            kinds.map(|k| k.iter().map(|s| s.replace('*', "%")).collect());
+
+        // Volume charts read hours in order; kinds and agents busiest first
+        let (key, order) = match group_by {
+            EventGroupBy::Kind => ("kind", "count DESC, key ASC"),
+            EventGroupBy::Agent => ("agent_id::TEXT", "count DESC, key ASC"),
+            EventGroupBy::Hour => (HOUR_BUCKET_KEY, "key ASC"),
+        };
+
+        let rows = conn
+            .query(
+                &format!(
+                    r#"
+                    SELECT {key} AS key, COUNT(*) AS count
+                    FROM events
+                    WHERE cursor > $1
+                      AND ($2::TIMESTAMPTZ IS NULL OR time >= $2)
+                      AND ($3::TEXT[] IS NULL OR EXISTS (
+                          SELECT 1 FROM unnest($3::TEXT[]) AS pattern
+                          WHERE kind LIKE pattern
+                      ))
+                      AND ($4::UUID IS NULL OR agent_id = $4)
+                      AND ($5::UUID IS NULL OR task_id = $5)
+                      AND ($6::JSONB IS NULL OR data @> $6)
+                    GROUP BY 1
+                    ORDER BY {order}
+                    "#
+                ),
+                &[
+                    &from_cursor,
+                    &since,
+                    &kinds_patterns,
+                    &agent_id,
+                    &task_id,
+                    &data.map(DataFilter::as_json),
+                ],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| EventCount {
+                key: row.get("key"),
+                count: row.get("count"),
+            })
+            .collect())
+    }
+
+    /// Relay-emitted events carry the session in `data.session_id` rather
+    /// than the `session_id` column, so filter on the payload.
+    async fn session_events(
+        &self,
+        agent_id: Uuid,
+        session_id: &str,
+        limit: usize,
+    ) -> Result<Vec<Event>> {
+        let conn = self.read_pool.get().await?;
+        let limit_i64 = limit.min(10000) as i64;
+
+        let rows = conn
+            .query(
+                r#"
+                SELECT cursor, kind, time, agent_id, session_id, task_id, correlation_id,
+                       request_id, data, schema_version
+                FROM events
+                WHERE agent_id = $1 AND data->>'session_id' = $2
+                ORDER BY cursor DESC
+                LIMIT $3
+                "#,
+                &[&agent_id, &session_id, &limit_i64],
+            )
+            .await?;
+
+        let mut events: Vec<Event> = rows
+            .iter()
+            .map(|row| Event {
+                cursor: row.get("cursor"),
+                kind: row.get("kind"),
+                time: row.get("time"),
+                agent_id: row.get("agent_id"),
+                session_id: row.get("session_id"),
+                task_id: row.get("task_id"),
+                correlation_id: row.get("correlation_id"),
+                request_id: row.get("request_id"),
+                data: row.get("data"),
+                schema_version: row.get("schema_version"),
+            })
+            .collect();
+        events.reverse();
+        Ok(events)
+    }
+
+    async fn agent_events(
+        &self,
+        agent_id: Uuid,
+        filter: &AgentEventFilter,
+        page: EventPage,
+        limit: usize,
+    ) -> Result<Vec<Event>> {
+        let conn = self.read_pool.get().await?;
+        let limit_i64 = limit.min(10000) as i64;
+        let (before, after, order) = match page {
+            EventPage::Latest => (None, None, "DESC"),
+            EventPage::Before(cursor) => (Some(cursor), None, "DESC"),
+            EventPage::After(cursor) => (None, Some(cursor), "ASC"),
+        };
+        let kinds_patterns: Option<Vec<String>> = filter
+            .kinds
+            .as_ref()
+            .map(|k| k.iter().map(|s| s.replace('*', "%")).collect());
+        let session_id = filter.session_id.map(|id| id.to_string());
+
+        // Relay-emitted events carry the session in the payload only
+        let sql = format!(
+            r#"
+            SELECT cursor, kind, time, agent_id, session_id, task_id, correlation_id,
+                   request_id, data, schema_version
+            FROM events
+            WHERE agent_id = $1
+              AND ($2::BIGINT IS NULL OR cursor < $2)
+              AND ($3::BIGINT IS NULL OR cursor > $3)
+              AND ($4::TEXT[] IS NULL OR EXISTS (
+                  SELECT 1 FROM unnest($4::TEXT[]) AS pattern
+                  WHERE kind LIKE pattern
+              ))
+              AND ($5::TEXT[] IS NULL OR data->>'stream' = ANY($5))
+              AND ($6::TEXT IS NULL OR session_id::TEXT = $6 OR data->>'session_id' = $6)
+              AND ($7::TIMESTAMPTZ IS NULL OR time >= $7)
+              AND ($8::TIMESTAMPTZ IS NULL OR time < $8)
+            ORDER BY cursor {order}
+            LIMIT $9
+            "#
+        );
+        let rows = conn
+            .query(
+                &sql,
+                &[
+                    &agent_id,
+                    &before,
+                    &after,
+                    &kinds_patterns,
+                    &filter.streams,
+                    &session_id,
+                    &filter.since,
+                    &filter.until,
                     &limit_i64,
                 ],
             )
@@ -126,7 +516,10 @@ impl EventStore for PgEventStore {
                 agent_id: row.get("agent_id"),
                 session_id: row.get("session_id"),
                 task_id: row.get("task_id"),
+                correlation_id: row.get("correlation_id"),
+                request_id: row.get("request_id"),
                 data: row.get("data"),
+                schema_version: row.get("schema_version"),
             })
             .collect();
 
@@ -143,11 +536,32 @@ impl EventStore for PgEventStore {
     }
 
     async fn prune_before(&self, before: DateTime<Utc>) -> Result<u64> {
+        // Drop whole partitions that end at or before the cutoff, then
+        // delete the remaining rows from the partition straddling it
+        let cutoff = before.date_naive();
+        let mut dropped = 0u64;
+        for (name, month) in self.monthly_partitions().await? {
+            let end = month + Months::new(1);
+            if end > cutoff {
+                continue;
+            }
+            let conn = self.pool.get().await?;
+            let row = conn
+                .query_one(&format!("SELECT COUNT(*) AS count FROM \"{}\"", name), &[])
+                .await?;
+            let count: i64 = row.get("count");
+            conn.execute(&format!("DROP TABLE IF EXISTS \"{}\"", name), &[])
+                .await
+                .context(format!("Failed to drop events partition {}", name))?;
+            tracing::info!(partition = %name, rows = count, "dropped expired events partition");
+            dropped += count as u64;
+        }
+
         let deleted = Event::delete()
             .filter(Event::COLUMNS.time.lt(before))
             .execute(&*self.pool)
             .await?;
-        Ok(deleted)
+        Ok(dropped + deleted)
     }
 }
 
@@ -161,4 +575,25 @@ mod tests {
         fn _assert_impl<T: EventStore>() {}
         _assert_impl::<PgEventStore>();
     }
+
+    #[test]
+    fn test_parse_partition_month() {
+        assert_eq!(
+            parse_partition_month("events_p202501"),
+            NaiveDate::from_ymd_opt(2025, 1, 1)
+        );
+        assert_eq!(
+            parse_partition_month("events_p202412"),
+            NaiveDate::from_ymd_opt(2024, 12, 1)
+        );
+        assert_eq!(parse_partition_month("events_default"), None);
+        assert_eq!(parse_partition_month("events_p202513"), None);
+        assert_eq!(parse_partition_month("events_p2025"), None);
+    }
+
+    #[test]
+    fn test_month_start() {
+        let date = NaiveDate::from_ymd_opt(2025, 3, 17).unwrap();
+        assert_eq!(month_start(date), NaiveDate::from_ymd_opt(2025, 3, 1).unwrap());
+    }
 }
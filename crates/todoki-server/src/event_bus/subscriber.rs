@@ -1,5 +1,8 @@
 use super::store::EventStore;
-use super::types::Event;
+use super::types::{
+    AgentEventFilter, AgentEventPage, DataFilter, Event, EventCount, EventGroupBy, EventPage,
+};
+use chrono::{DateTime, Utc};
 use anyhow::Result;
 use std::sync::Arc;
 use uuid::Uuid;
@@ -26,13 +29,63 @@ impl EventSubscriber {
         kinds: Option<&[String]>,
         agent_id: Option<Uuid>,
         task_id: Option<Uuid>,
+        correlation_id: Option<Uuid>,
+        data: Option<&DataFilter>,
         limit: Option<usize>,
     ) -> Result<Vec<Event>> {
         self.store
-            .query(from_cursor, None, kinds, agent_id, task_id, limit)
+            .query(
+                from_cursor,
+                None,
+                kinds,
+                agent_id,
+                task_id,
+                correlation_id,
+                data,
+                limit,
+            )
             .await
     }
 
+    /// Event counts grouped by kind, agent or hour (for dashboards)
+    pub async fn aggregate(
+        &self,
+        group_by: EventGroupBy,
+        from_cursor: i64,
+        since: Option<DateTime<Utc>>,
+        kinds: Option<&[String]>,
+        agent_id: Option<Uuid>,
+        task_id: Option<Uuid>,
+        data: Option<&DataFilter>,
+    ) -> Result<Vec<EventCount>> {
+        self.store
+            .aggregate(group_by, from_cursor, since, kinds, agent_id, task_id, data)
+            .await
+    }
+
+    /// Most recent `limit` events of one agent session, oldest first
+    pub async fn session_events(
+        &self,
+        agent_id: Uuid,
+        session_id: &str,
+        limit: usize,
+    ) -> Result<Vec<Event>> {
+        self.store.session_events(agent_id, session_id, limit).await
+    }
+
+    /// A page of one agent's event history, newest first
+    pub async fn agent_events(
+        &self,
+        agent_id: Uuid,
+        filter: &AgentEventFilter,
+        page: EventPage,
+        limit: usize,
+    ) -> Result<AgentEventPage> {
+        // One more than asked for tells whether there is another page
+        let events = self.store.agent_events(agent_id, filter, page, limit + 1).await?;
+        Ok(AgentEventPage::new(events, page, limit))
+    }
+
     /// Get latest cursor (for initialization)
     pub async fn latest_cursor(&self) -> Result<i64> {
         self.store.latest_cursor().await
@@ -51,7 +104,16 @@ impl EventSubscriber {
         kinds: Option<&[String]>,
     ) -> Result<Vec<Event>> {
         self.store
-            .query(from_cursor, Some(to_cursor), kinds, None, None, None)
+            .query(
+                from_cursor,
+                Some(to_cursor),
+                kinds,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
             .await
     }
 }
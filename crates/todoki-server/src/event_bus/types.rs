@@ -2,6 +2,7 @@ use chrono::{DateTime, Utc};
 use conservator::{Creatable, Domain};
 use gotcha::Schematic;
 use serde::{Deserialize, Serialize};
+use todoki_protocol::event_bus::{BuiltinEvent, EVENT_SCHEMA_VERSION};
 use uuid::Uuid;
 
 /// Core event structure for the Event Bus
@@ -30,8 +31,227 @@ pub struct Event {
     /// Optional task ID (for task-related events)
     pub task_id: Option<Uuid>,
 
+    /// Optional correlation ID shared by every event of one execution
+    pub correlation_id: Option<Uuid>,
+
+    /// ID of the API request that caused this event, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+
     /// Event-specific data (JSON)
     pub data: serde_json::Value,
+
+    /// Payload schema version the event was written with
+    pub schema_version: i32,
+}
+
+/// Who a typed event is about; the default is a system event with no task or session
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EventScope {
+    pub agent_id: Uuid,
+    pub task_id: Option<Uuid>,
+    pub session_id: Option<Uuid>,
+    pub correlation_id: Option<Uuid>,
+}
+
+impl EventScope {
+    /// System event not tied to a task or session
+    pub fn system() -> Self {
+        Self::default()
+    }
+
+    /// Task-related event
+    pub fn task(task_id: Uuid) -> Self {
+        Self {
+            task_id: Some(task_id),
+            ..Self::default()
+        }
+    }
+
+    /// Event of one agent execution, correlated with the rest of its events
+    pub fn execution(session_id: Uuid, correlation_id: Uuid) -> Self {
+        Self {
+            session_id: Some(session_id),
+            correlation_id: Some(correlation_id),
+            ..Self::default()
+        }
+    }
+
+    /// Attach a task, if there is one
+    pub fn with_task(mut self, task_id: Option<Uuid>) -> Self {
+        self.task_id = task_id;
+        self
+    }
+
+    /// Attach a session, if there is one
+    pub fn with_session(mut self, session_id: Option<Uuid>) -> Self {
+        self.session_id = session_id;
+        self
+    }
+
+    /// Attach the correlation ID of the execution, if there is one
+    pub fn with_correlation(mut self, correlation_id: Option<Uuid>) -> Self {
+        self.correlation_id = correlation_id;
+        self
+    }
+}
+
+/// Dimension event counts are grouped by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Schematic)]
+#[serde(rename_all = "snake_case")]
+pub enum EventGroupBy {
+    Kind,
+    Agent,
+    /// Hour the event happened in (UTC)
+    Hour,
+}
+
+/// Number of events in one group
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct EventCount {
+    /// Event kind, agent ID, or hour start (RFC 3339) depending on the grouping
+    pub key: String,
+    pub count: i64,
+}
+
+/// Filters of an agent's event history
+#[derive(Debug, Clone, Default)]
+pub struct AgentEventFilter {
+    /// Event kinds; `*` matches any run of characters
+    pub kinds: Option<Vec<String>>,
+    /// Output stream types (`data.stream`); events without one are left out
+    pub streams: Option<Vec<String>>,
+    /// Session, in the `session_id` column or in the payload
+    pub session_id: Option<Uuid>,
+    /// Events at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Events before this time
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// Conditions on event data, e.g. `data.relay_id=x,data.success=false`
+///
+/// Each condition compares the value at a dotted path of `data` with a JSON
+/// literal (`false`, `3`, `null`, `"7"`) or, if the value is not one, with
+/// a string. They are combined into one object the data must contain, which
+/// the Postgres store checks with `@>` on the GIN-indexed column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataFilter(serde_json::Value);
+
+impl DataFilter {
+    /// Parse comma-separated `data.<path>=<value>` conditions
+    pub fn parse(conditions: &str) -> Result<Self, String> {
+        let mut contained = serde_json::Value::Object(Default::default());
+        for condition in conditions
+            .split(',')
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+        {
+            let (path, value) = condition
+                .split_once('=')
+                .ok_or_else(|| format!("`{}` is not `data.<path>=<value>`", condition))?;
+            let path = path
+                .trim()
+                .strip_prefix("data.")
+                .ok_or_else(|| format!("`{}` does not start with `data.`", path))?;
+            let value = value.trim();
+            let value = serde_json::from_str(value)
+                .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+
+            let mut slot = &mut contained;
+            for key in path.split('.') {
+                if key.is_empty() {
+                    return Err(format!("`data.{}` has an empty key", path));
+                }
+                let serde_json::Value::Object(object) = slot else {
+                    return Err(format!("`data.{}` conflicts with another condition", path));
+                };
+                slot = object
+                    .entry(key)
+                    .or_insert_with(|| serde_json::Value::Object(Default::default()));
+            }
+            if !slot.as_object().is_some_and(|o| o.is_empty()) {
+                return Err(format!("`data.{}` conflicts with another condition", path));
+            }
+            *slot = value;
+        }
+        if contained.as_object().is_some_and(|o| o.is_empty()) {
+            return Err("no data conditions given".to_string());
+        }
+        Ok(Self(contained))
+    }
+
+    /// The object event data must contain
+    pub fn as_json(&self) -> &serde_json::Value {
+        &self.0
+    }
+
+    /// Whether `data` contains the conditions, like Postgres `@>`
+    pub fn matches(&self, data: &serde_json::Value) -> bool {
+        contains(data, &self.0)
+    }
+}
+
+fn contains(data: &serde_json::Value, wanted: &serde_json::Value) -> bool {
+    use serde_json::Value;
+    match (data, wanted) {
+        (Value::Object(data), Value::Object(wanted)) => wanted
+            .iter()
+            .all(|(key, w)| data.get(key).is_some_and(|d| contains(d, w))),
+        (Value::Array(data), Value::Array(wanted)) => {
+            wanted.iter().all(|w| data.iter().any(|d| contains(d, w)))
+        }
+        (Value::Number(data), Value::Number(wanted)) => data.as_f64() == wanted.as_f64(),
+        _ => data == wanted,
+    }
+}
+
+/// Where a page of event history starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventPage {
+    /// The most recent events
+    Latest,
+    /// Events older than the cursor
+    Before(i64),
+    /// Events newer than the cursor
+    After(i64),
+}
+
+/// A page of event history, newest first
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct AgentEventPage {
+    pub events: Vec<Event>,
+    /// Pass as `before` to fetch older events; absent when there are none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<i64>,
+    /// Pass as `after` to fetch newer events; absent on the newest page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev_cursor: Option<i64>,
+}
+
+impl AgentEventPage {
+    /// Page of `events` as fetched for `page` with a limit of `limit + 1`;
+    /// the extra event only tells whether there are more in that direction
+    pub fn new(mut events: Vec<Event>, page: EventPage, limit: usize) -> Self {
+        let more = events.len() > limit;
+        events.truncate(limit);
+        // Newer events are fetched oldest first
+        if matches!(page, EventPage::After(_)) {
+            events.reverse();
+        }
+        let newest = events.first().map(|e| e.cursor);
+        let oldest = events.last().map(|e| e.cursor);
+        let (next_cursor, prev_cursor) = match page {
+            EventPage::Latest => (oldest.filter(|_| more), None),
+            EventPage::Before(_) => (oldest.filter(|_| more), newest),
+            EventPage::After(_) => (oldest, newest.filter(|_| more)),
+        };
+        Self {
+            events,
+            next_cursor,
+            prev_cursor,
+        }
+    }
 }
 
 /// Creatable struct for inserting new events
@@ -42,7 +262,10 @@ pub struct CreateEvent {
     pub agent_id: Uuid,
     pub session_id: Option<Uuid>,
     pub task_id: Option<Uuid>,
+    pub correlation_id: Option<Uuid>,
+    pub request_id: Option<String>,
     pub data: serde_json::Value,
+    pub schema_version: i32,
 }
 
 impl CreateEvent {
@@ -58,7 +281,10 @@ impl CreateEvent {
             agent_id,
             session_id: None,
             task_id: None,
+            correlation_id: None,
+            request_id: None,
             data,
+            schema_version: EVENT_SCHEMA_VERSION,
         }
     }
 
@@ -75,7 +301,10 @@ impl CreateEvent {
             agent_id,
             session_id: None,
             task_id: Some(task_id),
+            correlation_id: None,
+            request_id: None,
             data,
+            schema_version: EVENT_SCHEMA_VERSION,
         }
     }
 
@@ -92,7 +321,10 @@ impl CreateEvent {
             agent_id,
             session_id: Some(session_id),
             task_id: None,
+            correlation_id: None,
+            request_id: None,
             data,
+            schema_version: EVENT_SCHEMA_VERSION,
         }
     }
 }
@@ -111,7 +343,10 @@ impl Event {
             agent_id,
             session_id: None,
             task_id: None,
+            correlation_id: None,
+            request_id: None,
             data,
+            schema_version: EVENT_SCHEMA_VERSION,
         }
     }
 
@@ -129,7 +364,10 @@ impl Event {
             agent_id,
             session_id: None,
             task_id: Some(task_id),
+            correlation_id: None,
+            request_id: None,
             data,
+            schema_version: EVENT_SCHEMA_VERSION,
         }
     }
 
@@ -147,7 +385,30 @@ impl Event {
             agent_id,
             session_id: Some(session_id),
             task_id: None,
+            correlation_id: None,
+            request_id: None,
+            data,
+            schema_version: EVENT_SCHEMA_VERSION,
+        }
+    }
+
+    /// Create an event from a typed protocol payload
+    ///
+    /// Kind and data come from the protocol's serde representation, so the
+    /// payload always matches what consumers deserialize.
+    pub fn builtin(event: BuiltinEvent, scope: EventScope) -> Self {
+        let (kind, data) = event.into_parts();
+        Self {
+            cursor: 0,
+            kind,
+            time: Utc::now(),
+            agent_id: scope.agent_id,
+            session_id: scope.session_id,
+            task_id: scope.task_id,
+            correlation_id: scope.correlation_id,
+            request_id: None,
             data,
+            schema_version: EVENT_SCHEMA_VERSION,
         }
     }
 
@@ -159,7 +420,110 @@ impl Event {
             agent_id: self.agent_id,
             session_id: self.session_id,
             task_id: self.task_id,
+            correlation_id: self.correlation_id,
+            request_id: self.request_id.clone(),
             data: self.data.clone(),
+            schema_version: self.schema_version,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use todoki_protocol::event_bus::{EventKind, TaskUnsnoozedData};
+
+    use super::*;
+
+    #[test]
+    fn test_builtin_event_uses_protocol_shape() {
+        let task_id = Uuid::new_v4();
+        let event = Event::builtin(
+            BuiltinEvent::TaskUnsnoozed(TaskUnsnoozedData {
+                reason: "timer".to_string(),
+            }),
+            EventScope::task(task_id),
+        );
+
+        assert_eq!(event.kind, EventKind::TASK_UNSNOOZED);
+        assert_eq!(event.data, serde_json::json!({ "reason": "timer" }));
+        assert_eq!(event.task_id, Some(task_id));
+        assert_eq!(event.agent_id, Uuid::nil());
+        assert_eq!(event.session_id, None);
+    }
+
+    #[test]
+    fn test_execution_scope_carries_correlation() {
+        let session_id = Uuid::new_v4();
+        let correlation_id = Uuid::new_v4();
+        let event = Event::builtin(
+            BuiltinEvent::TaskUnsnoozed(TaskUnsnoozedData {
+                reason: "timer".to_string(),
+            }),
+            EventScope::execution(session_id, correlation_id),
+        );
+
+        assert_eq!(event.session_id, Some(session_id));
+        assert_eq!(event.correlation_id, Some(correlation_id));
+        assert_eq!(event.to_create().correlation_id, Some(correlation_id));
+    }
+
+    #[test]
+    fn test_data_filter() {
+        let filter =
+            DataFilter::parse("data.relay_id=r1, data.success=false,data.tool.exit=2").unwrap();
+        assert_eq!(
+            filter.as_json(),
+            &serde_json::json!({"relay_id": "r1", "success": false, "tool": {"exit": 2}})
+        );
+        assert!(filter.matches(&serde_json::json!({
+            "relay_id": "r1",
+            "success": false,
+            "tool": {"exit": 2.0, "name": "bash"},
+            "extra": [1],
+        })));
+        assert!(!filter.matches(&serde_json::json!({"relay_id": "r1", "success": false})));
+        assert!(!filter.matches(&serde_json::json!({
+            "relay_id": "r1",
+            "success": "false",
+            "tool": {"exit": 2},
+        })));
+
+        let quoted = DataFilter::parse(r#"data.session_id="7""#).unwrap();
+        assert_eq!(quoted.as_json(), &serde_json::json!({"session_id": "7"}));
+
+        assert!(DataFilter::parse("relay_id=r1").is_err());
+        assert!(DataFilter::parse("data.relay_id").is_err());
+        assert!(DataFilter::parse("data.tool=1,data.tool.exit=2").is_err());
+        assert!(DataFilter::parse("data.a..b=1").is_err());
+        assert!(DataFilter::parse(" , ").is_err());
+    }
+
+    #[test]
+    fn test_agent_event_page_cursors() {
+        let events = |cursors: &[i64]| -> Vec<Event> {
+            cursors
+                .iter()
+                .map(|cursor| Event {
+                    cursor: *cursor,
+                    ..Event::new("agent.output_batch", Uuid::nil(), serde_json::json!({}))
+                })
+                .collect()
+        };
+        let cursors =
+            |page: &AgentEventPage| page.events.iter().map(|e| e.cursor).collect::<Vec<_>>();
+
+        let page = AgentEventPage::new(events(&[9, 8, 7]), EventPage::Latest, 2);
+        assert_eq!(cursors(&page), vec![9, 8]);
+        assert_eq!((page.next_cursor, page.prev_cursor), (Some(8), None));
+
+        let page = AgentEventPage::new(events(&[7, 6]), EventPage::Before(8), 2);
+        assert_eq!((page.next_cursor, page.prev_cursor), (None, Some(7)));
+
+        let page = AgentEventPage::new(events(&[7, 8, 9]), EventPage::After(6), 2);
+        assert_eq!(cursors(&page), vec![8, 7]);
+        assert_eq!((page.next_cursor, page.prev_cursor), (Some(7), Some(8)));
+
+        let page = AgentEventPage::new(Vec::new(), EventPage::After(9), 2);
+        assert_eq!((page.next_cursor, page.prev_cursor), (None, None));
+    }
+}
@@ -0,0 +1,297 @@
+//! Background event handlers
+//!
+//! Each handler follows the event bus and applies the state transitions its
+//! events imply. When applying an event fails (a DB error, say) the event is
+//! written to the dead-letter table instead of only being logged, so it can
+//! be inspected and reprocessed through `/api/event-bus/dead-letters`.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use todoki_protocol::event_bus::{
+    AgentFollowupTaskData, AgentSubtaskDoneData, AgentTaskCommentData, EventKind, ResourceUsage,
+};
+use tokio::sync::broadcast;
+use tracing::error;
+
+use crate::db::DatabaseService;
+use crate::event_bus::{Event, EventPublisher};
+use crate::models;
+use crate::relay::RequestTracker;
+
+/// Background handlers whose failures are dead-lettered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventHandler {
+    RelayResponses,
+    AgentTaskTools,
+}
+
+impl EventHandler {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventHandler::RelayResponses => "relay_responses",
+            EventHandler::AgentTaskTools => "agent_task_tools",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "relay_responses" => Some(EventHandler::RelayResponses),
+            "agent_task_tools" => Some(EventHandler::AgentTaskTools),
+            _ => None,
+        }
+    }
+
+    /// Whether this handler acts on events of `kind`
+    pub fn handles(&self, kind: &str) -> bool {
+        match self {
+            EventHandler::RelayResponses => matches!(
+                kind,
+                EventKind::RELAY_SPAWN_COMPLETED
+                    | EventKind::RELAY_SPAWN_FAILED
+                    | EventKind::AGENT_SESSION_EXITED
+            ),
+            EventHandler::AgentTaskTools => matches!(
+                kind,
+                EventKind::AGENT_TASK_COMMENT
+                    | EventKind::AGENT_SUBTASK_DONE
+                    | EventKind::AGENT_FOLLOWUP_TASK
+            ),
+        }
+    }
+
+    /// Apply one event; used by the live loop and by reprocessing
+    pub async fn apply(
+        &self,
+        db: &DatabaseService,
+        tracker: &RequestTracker,
+        event: &Event,
+    ) -> Result<()> {
+        match self {
+            EventHandler::RelayResponses => apply_relay_response(db, tracker, event).await,
+            EventHandler::AgentTaskTools => apply_agent_task_tool(db, event).await,
+        }
+    }
+}
+
+/// Follow the event bus and apply `handler` to every event it handles
+pub async fn run(
+    handler: EventHandler,
+    publisher: Arc<EventPublisher>,
+    db: Arc<DatabaseService>,
+    tracker: Arc<RequestTracker>,
+) {
+    let mut rx = publisher.subscribe();
+
+    loop {
+        let event = match rx.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                tracing::warn!(
+                    handler = handler.as_str(),
+                    lagged_events = n,
+                    "event handler lagged, some events may be missed"
+                );
+                continue;
+            }
+            Err(_) => {
+                error!(handler = handler.as_str(), "event handler channel closed");
+                break;
+            }
+        };
+
+        if !handler.handles(&event.kind) {
+            continue;
+        }
+
+        if let Err(e) = handler.apply(&db, &tracker, &event).await {
+            error!(
+                handler = handler.as_str(),
+                kind = %event.kind,
+                cursor = event.cursor,
+                error = %e,
+                "failed to handle event, moving it to the dead-letter queue"
+            );
+            if let Err(e) = db
+                .record_dead_letter(handler.as_str(), &event, &e.to_string())
+                .await
+            {
+                error!(cursor = event.cursor, error = %e, "failed to record dead letter");
+            }
+        }
+    }
+}
+
+/// Handle relay response events
+///
+/// - relay.spawn_completed: Notifies waiting request trackers, marks the session running
+/// - relay.spawn_failed: Notifies waiting request trackers with error
+/// - agent.session_exited: Updates session status and final resource usage in database
+async fn apply_relay_response(
+    db: &DatabaseService,
+    tracker: &RequestTracker,
+    event: &Event,
+) -> Result<()> {
+    match event.kind.as_str() {
+        EventKind::RELAY_SPAWN_COMPLETED => {
+            if let Some(req_id) = event.data.get("request_id").and_then(|v| v.as_str()) {
+                let result = Ok(serde_json::json!({
+                    "session_id": event.data.get("session_id"),
+                }));
+                tracker.complete_request(req_id, result).await;
+            }
+
+            // Update session status
+            if let Some(session_id_str) = event.data.get("session_id").and_then(|v| v.as_str())
+                && let Ok(session_uuid) = uuid::Uuid::parse_str(session_id_str)
+            {
+                db.update_session_status(session_uuid, models::SessionStatus::Running)
+                    .await?;
+            }
+        }
+
+        EventKind::RELAY_SPAWN_FAILED => {
+            if let Some(req_id) = event.data.get("request_id").and_then(|v| v.as_str()) {
+                let error_msg = event
+                    .data
+                    .get("error")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown error");
+                // Relays with preflight checks say what kind of failure it was
+                let error = match event.data.get("code").and_then(|v| v.as_str()) {
+                    Some(code) => anyhow::anyhow!("{} ({})", error_msg, code),
+                    None => anyhow::anyhow!("{}", error_msg),
+                };
+                tracker.complete_request(req_id, Err(error)).await;
+            }
+        }
+
+        EventKind::AGENT_SESSION_EXITED => {
+            if let Some(session_id_str) = event.data.get("session_id").and_then(|v| v.as_str())
+                && let Ok(session_uuid) = uuid::Uuid::parse_str(session_id_str)
+            {
+                let status_str = event
+                    .data
+                    .get("status")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("completed");
+                let status = match status_str {
+                    "completed" => models::SessionStatus::Completed,
+                    "failed" => models::SessionStatus::Failed,
+                    "cancelled" => models::SessionStatus::Cancelled,
+                    _ => models::SessionStatus::Completed,
+                };
+                db.update_session_status(session_uuid, status).await?;
+
+                if let Some(usage) = event
+                    .data
+                    .get("usage")
+                    .and_then(|v| serde_json::from_value::<ResourceUsage>(v.clone()).ok())
+                {
+                    db.update_session_resource_usage(session_uuid, &usage).await?;
+                }
+            }
+        }
+
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Handle task tool calls made by agents through the relay
+///
+/// - agent.task_comment: Adds the comment to the event's task
+/// - agent.subtask_done: Records the finished subtask as a task comment
+/// - agent.followup_task: Creates a new backlog task in the same project
+async fn apply_agent_task_tool(db: &DatabaseService, event: &Event) -> Result<()> {
+    let Some(task_id) = event.task_id else {
+        tracing::warn!(kind = %event.kind, cursor = event.cursor, "agent task tool event without task_id");
+        return Ok(());
+    };
+
+    match event.kind.as_str() {
+        EventKind::AGENT_TASK_COMMENT => {
+            match serde_json::from_value::<AgentTaskCommentData>(event.data.clone()) {
+                Ok(data) => {
+                    db.add_task_comment(task_id, data.content).await?;
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, cursor = event.cursor, "invalid agent.task_comment data");
+                }
+            }
+        }
+        EventKind::AGENT_SUBTASK_DONE => {
+            match serde_json::from_value::<AgentSubtaskDoneData>(event.data.clone()) {
+                Ok(data) => {
+                    let content = match data.note {
+                        Some(note) => format!("✅ {}\n\n{}", data.subtask, note),
+                        None => format!("✅ {}", data.subtask),
+                    };
+                    db.add_task_comment(task_id, content).await?;
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, cursor = event.cursor, "invalid agent.subtask_done data");
+                }
+            }
+        }
+        EventKind::AGENT_FOLLOWUP_TASK => {
+            match serde_json::from_value::<AgentFollowupTaskData>(event.data.clone()) {
+                Ok(data) => create_followup_task(db, task_id, data).await?,
+                Err(e) => {
+                    tracing::warn!(error = %e, cursor = event.cursor, "invalid agent.followup_task data");
+                }
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+async fn create_followup_task(
+    db: &DatabaseService,
+    origin_task_id: uuid::Uuid,
+    data: AgentFollowupTaskData,
+) -> crate::Result<()> {
+    let Some(origin) = db.get_task_by_id(origin_task_id).await? else {
+        tracing::warn!(task_id = %origin_task_id, "follow-up requested for unknown task");
+        return Ok(());
+    };
+
+    let create = models::CreateTask::new(
+        data.content,
+        models::TaskStatus::Backlog,
+        data.priority,
+        origin.project_id,
+    );
+    let followup = db.create_task(create).await?;
+    db.add_task_comment(
+        origin_task_id,
+        format!("Follow-up task created: {}", followup.id),
+    )
+    .await?;
+    tracing::info!(task_id = %origin_task_id, followup_id = %followup.id, "agent created follow-up task");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handler_name_round_trip() {
+        for handler in [EventHandler::RelayResponses, EventHandler::AgentTaskTools] {
+            assert_eq!(EventHandler::parse(handler.as_str()), Some(handler));
+        }
+        assert_eq!(EventHandler::parse("unknown"), None);
+    }
+
+    #[test]
+    fn test_handlers_do_not_overlap() {
+        assert!(EventHandler::RelayResponses.handles(EventKind::RELAY_SPAWN_FAILED));
+        assert!(!EventHandler::AgentTaskTools.handles(EventKind::RELAY_SPAWN_FAILED));
+        assert!(EventHandler::AgentTaskTools.handles(EventKind::AGENT_FOLLOWUP_TASK));
+        assert!(!EventHandler::RelayResponses.handles(EventKind::AGENT_FOLLOWUP_TASK));
+    }
+}
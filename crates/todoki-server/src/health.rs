@@ -0,0 +1,175 @@
+//! Agent liveness tracking
+//!
+//! Relays probe their running ACP sessions and report `agent.unhealthy`
+//! while probes keep failing, then `agent.healthy` once one is answered
+//! again. The monitor records the latest state on the agent; a new session
+//! starts out healthy. With `health.restart_after_failures` set, an agent
+//! whose running session failed that many probes in a row is stopped and
+//! started again.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::de::DeserializeOwned;
+use todoki_protocol::event_bus::{
+    AgentHealthyData, AgentSessionStartedData, AgentUnhealthyData, EventKind,
+};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::api::agents::{start_agent_internal, stop_agent_internal};
+use crate::config::HealthSettings;
+use crate::db::DatabaseService;
+use crate::event_bus::{Event, EventPublisher};
+use crate::models::AgentHealth;
+use crate::relay::{RelayManager, RequestTracker};
+
+/// Pause between stopping an unhealthy session and spawning its
+/// replacement, so the relay has released the old process
+const RESTART_DELAY: Duration = Duration::from_secs(5);
+
+pub struct HealthMonitor {
+    settings: HealthSettings,
+    db: Arc<DatabaseService>,
+    relays: Arc<RelayManager>,
+    publisher: Arc<EventPublisher>,
+    tracker: Arc<RequestTracker>,
+}
+
+impl HealthMonitor {
+    pub fn new(
+        settings: HealthSettings,
+        db: Arc<DatabaseService>,
+        relays: Arc<RelayManager>,
+        publisher: Arc<EventPublisher>,
+        tracker: Arc<RequestTracker>,
+    ) -> Self {
+        Self {
+            settings,
+            db,
+            relays,
+            publisher,
+            tracker,
+        }
+    }
+
+    /// Follow the event bus and record probe results
+    pub async fn run(self: Arc<Self>) {
+        let mut rx = self.publisher.subscribe();
+
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    if let Err(e) = self.clone().handle(&event).await {
+                        tracing::warn!(
+                            kind = %event.kind,
+                            cursor = event.cursor,
+                            error = %e,
+                            "failed to record agent health"
+                        );
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!(
+                        lagged_events = n,
+                        "health monitor lagged, some events may be missed"
+                    );
+                }
+                Err(_) => {
+                    tracing::error!("health monitor channel closed");
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn handle(self: Arc<Self>, event: &Event) -> anyhow::Result<()> {
+        match event.kind.as_str() {
+            EventKind::AGENT_SESSION_STARTED => {
+                let data: AgentSessionStartedData = parse(event)?;
+                let agent_id = Uuid::parse_str(&data.agent_id)?;
+                self.db
+                    .update_agent_health(agent_id, AgentHealth::Healthy, 0)
+                    .await?;
+            }
+            EventKind::AGENT_HEALTHY => {
+                let data: AgentHealthyData = parse(event)?;
+                let agent_id = Uuid::parse_str(&data.agent_id)?;
+                self.db
+                    .update_agent_health(agent_id, AgentHealth::Healthy, 0)
+                    .await?;
+                tracing::info!(agent_id = %agent_id, "agent healthy again");
+            }
+            EventKind::AGENT_UNHEALTHY => {
+                let data: AgentUnhealthyData = parse(event)?;
+                let agent_id = Uuid::parse_str(&data.agent_id)?;
+                let session_id = Uuid::parse_str(&data.session_id)?;
+                let failures = i32::try_from(data.consecutive_failures).unwrap_or(i32::MAX);
+                self.db
+                    .update_agent_health(agent_id, AgentHealth::Unhealthy, failures)
+                    .await?;
+                tracing::warn!(
+                    agent_id = %agent_id,
+                    session_id = %session_id,
+                    consecutive_failures = data.consecutive_failures,
+                    error = %data.error,
+                    "agent unhealthy"
+                );
+
+                if self
+                    .settings
+                    .restart_after_failures
+                    .is_some_and(|max| data.consecutive_failures >= max)
+                {
+                    // Restarting waits for the relay; keep following events
+                    tokio::spawn(async move {
+                        if let Err(e) = self.restart(agent_id, session_id).await {
+                            tracing::error!(
+                                agent_id = %agent_id,
+                                error = %e,
+                                "failed to restart unhealthy agent"
+                            );
+                        }
+                    });
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Replace the agent's session if `session_id` is still the running one
+    async fn restart(&self, agent_id: Uuid, session_id: Uuid) -> anyhow::Result<()> {
+        let running = self.db.get_agent_running_session(agent_id).await?;
+        if running.is_none_or(|session| session.id != session_id) {
+            // Already stopped or restarted (probes keep failing meanwhile)
+            return Ok(());
+        }
+        let agent = self
+            .db
+            .get_agent(agent_id)
+            .await?
+            .with_context(|| format!("agent {} not found", agent_id))?;
+
+        tracing::warn!(agent_id = %agent_id, session_id = %session_id, "restarting unhealthy agent");
+        stop_agent_internal(&self.db, &self.relays, &self.publisher, agent_id).await?;
+        tokio::time::sleep(RESTART_DELAY).await;
+
+        let session = start_agent_internal(
+            &self.db,
+            &self.relays,
+            &self.publisher,
+            &self.tracker,
+            &agent,
+        )
+        .await?;
+        tracing::info!(agent_id = %agent_id, session_id = %session.id, "unhealthy agent restarted");
+        Ok(())
+    }
+}
+
+fn parse<T: DeserializeOwned>(event: &Event) -> anyhow::Result<T> {
+    serde_json::from_value(event.data.clone())
+        .with_context(|| format!("invalid {} data", event.kind))
+}
@@ -0,0 +1,178 @@
+//! Minimal Jira Cloud REST client (only the calls the sync makes)
+
+use anyhow::Context;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::CreatedIssue;
+use crate::config::IssueSyncSettings;
+use crate::models::StatusCategory;
+
+#[derive(Debug, Deserialize)]
+struct IssueRef {
+    id: String,
+    key: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Issue {
+    fields: IssueFields,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueFields {
+    status: Status,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Status {
+    #[serde(rename = "statusCategory")]
+    pub status_category: StatusCategoryRef,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatusCategoryRef {
+    /// `new`, `indeterminate` or `done`
+    pub key: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Transitions {
+    transitions: Vec<Transition>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Transition {
+    pub id: String,
+    pub to: Status,
+}
+
+/// The first transition leading into `category`
+fn pick_transition(transitions: &[Transition], category: StatusCategory) -> Option<&str> {
+    transitions
+        .iter()
+        .find(|t| t.to.status_category.key == category.jira_key())
+        .map(|t| t.id.as_str())
+}
+
+#[derive(Clone)]
+pub struct JiraClient {
+    http_client: reqwest::Client,
+    base_url: String,
+    email: String,
+    api_token: String,
+}
+
+impl JiraClient {
+    /// `None` unless the site and credentials are all set
+    pub fn from_settings(settings: &IssueSyncSettings) -> Option<Self> {
+        if settings.jira_url.is_empty()
+            || settings.jira_email.is_empty()
+            || settings.jira_api_token.is_empty()
+        {
+            return None;
+        }
+        Some(Self {
+            http_client: reqwest::Client::new(),
+            base_url: settings.jira_url.trim_end_matches('/').to_string(),
+            email: settings.jira_email.clone(),
+            api_token: settings.jira_api_token.clone(),
+        })
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.http_client
+            .request(method, format!("{}/rest/api/2{}", self.base_url, path))
+            .basic_auth(&self.email, Some(&self.api_token))
+            .header("Accept", "application/json")
+    }
+
+    pub async fn create_issue(
+        &self,
+        project_key: &str,
+        issue_type: &str,
+        summary: &str,
+        description: &str,
+    ) -> anyhow::Result<CreatedIssue> {
+        let body = json!({
+            "fields": {
+                "project": { "key": project_key },
+                "issuetype": { "name": issue_type },
+                "summary": summary,
+                "description": description,
+            }
+        });
+        let issue: IssueRef = self
+            .request(reqwest::Method::POST, "/issue")
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("unexpected Jira response")?;
+        Ok(CreatedIssue {
+            url: format!("{}/browse/{}", self.base_url, issue.key),
+            id: issue.id,
+            key: issue.key,
+        })
+    }
+
+    pub async fn status_category(&self, issue_id: &str) -> anyhow::Result<Option<StatusCategory>> {
+        let issue: Issue = self
+            .request(
+                reqwest::Method::GET,
+                &format!("/issue/{}?fields=status", issue_id),
+            )
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(StatusCategory::from_jira(
+            &issue.fields.status.status_category.key,
+        ))
+    }
+
+    /// Transition the issue into `category`; `false` when its workflow has
+    /// no such transition from where it is
+    pub async fn move_to(&self, issue_id: &str, category: StatusCategory) -> anyhow::Result<bool> {
+        let path = format!("/issue/{}/transitions", issue_id);
+        let available: Transitions = self
+            .request(reqwest::Method::GET, &path)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let Some(transition_id) = pick_transition(&available.transitions, category) else {
+            return Ok(false);
+        };
+        self.request(reqwest::Method::POST, &path)
+            .json(&json!({ "transition": { "id": transition_id } }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_transition() {
+        let transitions: Transitions = serde_json::from_value(json!({
+            "transitions": [
+                {"id": "11", "name": "Start", "to": {"statusCategory": {"key": "indeterminate"}}},
+                {"id": "31", "name": "Close", "to": {"statusCategory": {"key": "done"}}}
+            ]
+        }))
+        .unwrap();
+        let pick = |category| pick_transition(&transitions.transitions, category);
+        assert_eq!(pick(StatusCategory::Done), Some("31"));
+        assert_eq!(pick(StatusCategory::InProgress), Some("11"));
+        assert_eq!(pick(StatusCategory::Todo), None);
+    }
+}
@@ -0,0 +1,198 @@
+//! Minimal Linear GraphQL client (only the calls the sync makes)
+
+use anyhow::{anyhow, Context};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::CreatedIssue;
+use crate::config::IssueSyncSettings;
+use crate::models::StatusCategory;
+
+const CREATE_ISSUE: &str = "mutation($input: IssueCreateInput!) { \
+    issueCreate(input: $input) { issue { id identifier url } } }";
+
+const ISSUE_STATE: &str = "query($id: String!) { issue(id: $id) { state { type } } }";
+
+const TEAM_STATES: &str = "query($id: String!) { \
+    issue(id: $id) { team { states { nodes { id type position } } } } }";
+
+const UPDATE_STATE: &str = "mutation($id: String!, $stateId: String!) { \
+    issueUpdate(id: $id, input: { stateId: $stateId }) { success } }";
+
+#[derive(Debug, Deserialize)]
+struct GraphqlResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphqlError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphqlError {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IssueCreated {
+    issue_create: IssueCreatePayload,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueCreatePayload {
+    issue: CreatedIssueNode,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreatedIssueNode {
+    id: String,
+    identifier: String,
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueData<T> {
+    issue: T,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueState {
+    state: StateRef,
+}
+
+#[derive(Debug, Deserialize)]
+struct StateRef {
+    #[serde(rename = "type")]
+    state_type: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueTeam {
+    team: Team,
+}
+
+#[derive(Debug, Deserialize)]
+struct Team {
+    states: Nodes<WorkflowState>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Nodes<T> {
+    nodes: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WorkflowState {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub state_type: String,
+    #[serde(default)]
+    pub position: f64,
+}
+
+/// The team's first state of the type `category` maps to
+fn pick_state(states: &[WorkflowState], category: StatusCategory) -> Option<&str> {
+    states
+        .iter()
+        .filter(|s| s.state_type == category.linear_type())
+        .min_by(|a, b| a.position.total_cmp(&b.position))
+        .map(|s| s.id.as_str())
+}
+
+#[derive(Clone)]
+pub struct LinearClient {
+    http_client: reqwest::Client,
+    api_url: String,
+    api_key: String,
+}
+
+impl LinearClient {
+    /// `None` unless an API key is set
+    pub fn from_settings(settings: &IssueSyncSettings) -> Option<Self> {
+        if settings.linear_api_key.is_empty() {
+            return None;
+        }
+        Some(Self {
+            http_client: reqwest::Client::new(),
+            api_url: settings.linear_api_url.clone(),
+            api_key: settings.linear_api_key.clone(),
+        })
+    }
+
+    async fn query<T: DeserializeOwned>(&self, query: &str, variables: Value) -> anyhow::Result<T> {
+        let response: GraphqlResponse<T> = self
+            .http_client
+            .post(&self.api_url)
+            .header("Authorization", &self.api_key)
+            .json(&json!({ "query": query, "variables": variables }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("unexpected Linear response")?;
+        if let Some(error) = response.errors.first() {
+            return Err(anyhow!("Linear API error: {}", error.message));
+        }
+        response
+            .data
+            .ok_or_else(|| anyhow!("Linear API returned no data"))
+    }
+
+    pub async fn create_issue(
+        &self,
+        team_id: &str,
+        title: &str,
+        description: &str,
+    ) -> anyhow::Result<CreatedIssue> {
+        let input = json!({ "teamId": team_id, "title": title, "description": description });
+        let created: IssueCreated = self.query(CREATE_ISSUE, json!({ "input": input })).await?;
+        let issue = created.issue_create.issue;
+        Ok(CreatedIssue {
+            id: issue.id,
+            key: issue.identifier,
+            url: issue.url,
+        })
+    }
+
+    pub async fn status_category(&self, issue_id: &str) -> anyhow::Result<Option<StatusCategory>> {
+        let data: IssueData<IssueState> =
+            self.query(ISSUE_STATE, json!({ "id": issue_id })).await?;
+        Ok(StatusCategory::from_linear(&data.issue.state.state_type))
+    }
+
+    /// Move the issue to its team's first state of `category`; `false` when
+    /// the team has none
+    pub async fn move_to(&self, issue_id: &str, category: StatusCategory) -> anyhow::Result<bool> {
+        let data: IssueData<IssueTeam> = self.query(TEAM_STATES, json!({ "id": issue_id })).await?;
+        let Some(state_id) = pick_state(&data.issue.team.states.nodes, category) else {
+            return Ok(false);
+        };
+        let _: Value = self
+            .query(UPDATE_STATE, json!({ "id": issue_id, "stateId": state_id }))
+            .await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_state() {
+        let states: Vec<WorkflowState> = serde_json::from_value(json!([
+            {"id": "done", "type": "completed", "position": 3.0},
+            {"id": "review", "type": "started", "position": 2.0},
+            {"id": "doing", "type": "started", "position": 1.0},
+            {"id": "dropped", "type": "canceled", "position": 4.0}
+        ]))
+        .unwrap();
+        assert_eq!(
+            pick_state(&states, StatusCategory::InProgress),
+            Some("doing")
+        );
+        assert_eq!(pick_state(&states, StatusCategory::Done), Some("done"));
+        assert_eq!(pick_state(&states, StatusCategory::Todo), None);
+    }
+}
@@ -0,0 +1,311 @@
+//! Two-way sync of tasks with Jira or Linear issues
+//!
+//! Projects with an `issue_sync` get a tracker issue for every task created
+//! in them. Afterwards only moves between status categories (todo, in
+//! progress, done) are mirrored: each synced task remembers the category
+//! both sides agreed on last, so whichever side moved since wins (the task
+//! when both did). Tracker changes arrive through the webhooks in
+//! `api::trackers` or, without webhooks, by polling.
+
+mod jira;
+mod linear;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use todoki_protocol::event_bus::EventKind;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::config::IssueSyncSettings;
+use crate::db::DatabaseService;
+use crate::event_bus::EventPublisher;
+use crate::models::{IssueProvider, StatusCategory, SyncAction, Task, TaskRemoteIssue};
+
+use self::jira::JiraClient;
+use self::linear::LinearClient;
+
+/// Synced issues checked per poll
+const POLL_BATCH: i64 = 100;
+
+/// Longest issue title; the full task text goes into the description
+const MAX_TITLE_CHARS: usize = 200;
+
+const DEFAULT_JIRA_ISSUE_TYPE: &str = "Task";
+
+/// An issue created in a tracker
+#[derive(Debug, Clone)]
+pub struct CreatedIssue {
+    pub id: String,
+    pub key: String,
+    pub url: String,
+}
+
+/// Issue title for a task: its first line, shortened
+fn issue_title(task: &Task) -> String {
+    let first_line = task.content.lines().next().unwrap_or("").trim();
+    if first_line.chars().count() <= MAX_TITLE_CHARS {
+        return first_line.to_string();
+    }
+    let mut title: String = first_line.chars().take(MAX_TITLE_CHARS - 1).collect();
+    title.push('…');
+    title
+}
+
+pub struct IssueSyncService {
+    settings: IssueSyncSettings,
+    db: Arc<DatabaseService>,
+    jira: Option<JiraClient>,
+    linear: Option<LinearClient>,
+}
+
+impl IssueSyncService {
+    pub fn new(settings: IssueSyncSettings, db: Arc<DatabaseService>) -> Self {
+        Self {
+            jira: JiraClient::from_settings(&settings),
+            linear: LinearClient::from_settings(&settings),
+            settings,
+            db,
+        }
+    }
+
+    /// Create issues for new tasks and poll synced issues until the event
+    /// bus closes
+    pub async fn run(self: Arc<Self>, publisher: Arc<EventPublisher>) {
+        if self.settings.poll_interval_secs > 0 {
+            tokio::spawn(self.clone().poll());
+        }
+
+        let mut rx = publisher.subscribe();
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!(lagged_events = n, "issue sync lagged");
+                    continue;
+                }
+                Err(_) => break,
+            };
+            if event.kind != EventKind::TASK_CREATED {
+                continue;
+            }
+            let Some(task_id) = event.task_id else {
+                continue;
+            };
+
+            let service = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = service.create_issue(task_id).await {
+                    tracing::warn!(task_id = %task_id, error = %e, "failed to create issue");
+                }
+            });
+        }
+    }
+
+    async fn poll(self: Arc<Self>) {
+        let period = Duration::from_secs(self.settings.poll_interval_secs.max(30));
+        let mut interval = tokio::time::interval(period);
+
+        loop {
+            interval.tick().await;
+
+            let issues = match self.db.list_remote_issues(POLL_BATCH).await {
+                Ok(issues) => issues,
+                Err(e) => {
+                    tracing::error!(error = %e, "failed to list synced issues");
+                    continue;
+                }
+            };
+            for issue in issues {
+                if let Err(e) = self.reconcile(&issue, None).await {
+                    tracing::warn!(task_id = %issue.task_id, error = %e, "failed to sync issue");
+                }
+            }
+        }
+    }
+
+    /// Mirror a new task into its project's tracker, if the project syncs
+    async fn create_issue(&self, task_id: Uuid) -> anyhow::Result<()> {
+        if self.db.get_remote_issue(task_id).await?.is_some() {
+            return Ok(());
+        }
+        let Some(task) = self.db.get_task_by_id(task_id).await? else {
+            return Ok(());
+        };
+        let Some(sync) = self
+            .db
+            .get_project(task.project_id)
+            .await?
+            .and_then(|project| project.issue_sync())
+        else {
+            return Ok(());
+        };
+        let (Some(provider), Some(target)) = (sync.provider, sync.target.as_deref()) else {
+            return Ok(());
+        };
+
+        let title = issue_title(&task);
+        let created = match provider {
+            IssueProvider::Jira => {
+                let issue_type = sync
+                    .issue_type
+                    .as_deref()
+                    .unwrap_or(DEFAULT_JIRA_ISSUE_TYPE);
+                self.jira()?
+                    .create_issue(target, issue_type, &title, &task.content)
+                    .await?
+            }
+            IssueProvider::Linear => {
+                self.linear()?
+                    .create_issue(target, &title, &task.content)
+                    .await?
+            }
+        };
+
+        // New issues start in the tracker's first category; a task created
+        // further along is pushed right away
+        let issue = self
+            .db
+            .create_remote_issue(
+                task.id,
+                provider,
+                &created.id,
+                &created.key,
+                &created.url,
+                StatusCategory::Todo,
+            )
+            .await?;
+        tracing::info!(task_id = %task.id, key = %created.key, "tracker issue created");
+        self.reconcile(&issue, Some(StatusCategory::Todo)).await
+    }
+
+    /// A tracker reported an issue's category; returns `false` when no task
+    /// is synced with the issue
+    pub async fn remote_changed(
+        &self,
+        provider: IssueProvider,
+        remote_id: &str,
+        category: StatusCategory,
+    ) -> anyhow::Result<bool> {
+        let Some(issue) = self.db.find_remote_issue(provider, remote_id).await? else {
+            return Ok(false);
+        };
+        self.reconcile(&issue, Some(category)).await?;
+        Ok(true)
+    }
+
+    /// Bring a task and its issue back in line; the issue's category is
+    /// fetched unless given
+    async fn reconcile(
+        &self,
+        issue: &TaskRemoteIssue,
+        issue_category: Option<StatusCategory>,
+    ) -> anyhow::Result<()> {
+        let Some(task) = self.db.get_task_by_id(issue.task_id).await? else {
+            return Ok(());
+        };
+        let issue_category = match issue_category {
+            Some(category) => category,
+            None => match self.fetch_category(issue).await? {
+                Some(category) => category,
+                // Status the mapping does not know; leave both sides alone
+                None => return Ok(()),
+            },
+        };
+
+        let task_category = StatusCategory::of_task(task.status);
+        match SyncAction::plan(issue.synced_category, task_category, issue_category) {
+            SyncAction::None => {}
+            SyncAction::Record(category) => {
+                self.db.set_remote_issue_synced(task.id, category).await?;
+            }
+            SyncAction::Push(category) => {
+                if self.move_issue(issue, category).await? {
+                    self.db.set_remote_issue_synced(task.id, category).await?;
+                    tracing::info!(task_id = %task.id, key = %issue.remote_key, "tracker issue moved");
+                } else {
+                    tracing::warn!(
+                        task_id = %task.id,
+                        key = %issue.remote_key,
+                        category = ?category,
+                        "tracker workflow has no way to move the issue"
+                    );
+                }
+            }
+            SyncAction::Pull(category) => {
+                self.db
+                    .update_task_status(task.id, category.task_status())
+                    .await?;
+                self.db.set_remote_issue_synced(task.id, category).await?;
+                tracing::info!(task_id = %task.id, key = %issue.remote_key, "task moved after its issue");
+            }
+        }
+        Ok(())
+    }
+
+    async fn fetch_category(
+        &self,
+        issue: &TaskRemoteIssue,
+    ) -> anyhow::Result<Option<StatusCategory>> {
+        match issue.provider {
+            IssueProvider::Jira => self.jira()?.status_category(&issue.remote_id).await,
+            IssueProvider::Linear => self.linear()?.status_category(&issue.remote_id).await,
+        }
+    }
+
+    async fn move_issue(
+        &self,
+        issue: &TaskRemoteIssue,
+        category: StatusCategory,
+    ) -> anyhow::Result<bool> {
+        match issue.provider {
+            IssueProvider::Jira => self.jira()?.move_to(&issue.remote_id, category).await,
+            IssueProvider::Linear => self.linear()?.move_to(&issue.remote_id, category).await,
+        }
+    }
+
+    fn jira(&self) -> anyhow::Result<&JiraClient> {
+        self.jira.as_ref().ok_or_else(|| {
+            anyhow!("issue_sync.jira_url, jira_email and jira_api_token are not set")
+        })
+    }
+
+    fn linear(&self) -> anyhow::Result<&LinearClient> {
+        self.linear
+            .as_ref()
+            .ok_or_else(|| anyhow!("issue_sync.linear_api_key is not set"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TaskStatus;
+
+    fn task(content: &str) -> Task {
+        Task {
+            id: Uuid::new_v4(),
+            priority: 0,
+            content: content.to_string(),
+            project_id: Uuid::nil(),
+            status: TaskStatus::Todo,
+            create_at: chrono::Utc::now(),
+            archived: false,
+            agent_id: None,
+            due_at: None,
+            estimate_minutes: None,
+            workflow_state: None,
+            rank: None,
+        }
+    }
+
+    #[test]
+    fn test_issue_title() {
+        assert_eq!(issue_title(&task("Fix login\n\nSteps: ...")), "Fix login");
+
+        let title = issue_title(&task(&"x".repeat(300)));
+        assert_eq!(title.chars().count(), MAX_TITLE_CHARS);
+        assert!(title.ends_with('…'));
+    }
+}
@@ -0,0 +1,103 @@
+//! Minimal OpenAI-compatible chat completions client
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::config::AutoReviewSettings;
+
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatMessage {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct LlmClient {
+    http_client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+impl LlmClient {
+    /// Build a client from the shared OpenAI settings; `None` without an API key
+    pub fn from_settings(settings: &AutoReviewSettings) -> Option<Self> {
+        if settings.openai_api_key.is_empty() {
+            return None;
+        }
+
+        let http_client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(settings.timeout_secs))
+            .build()
+            .ok()?;
+        let base_url = settings
+            .openai_base_url
+            .clone()
+            .filter(|url| !url.is_empty())
+            .unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+
+        Some(Self {
+            http_client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: settings.openai_api_key.clone(),
+            model: settings.model.clone(),
+        })
+    }
+
+    /// Use a different model with the same endpoint and key
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Run a single system + user prompt and return the assistant's reply
+    pub async fn complete(&self, system: &str, user: &str) -> anyhow::Result<String> {
+        let resp = self
+            .http_client
+            .post(format!("{}/chat/completions", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({
+                "model": self.model,
+                "temperature": 0.2,
+                "messages": [
+                    { "role": "system", "content": system },
+                    { "role": "user", "content": user },
+                ],
+            }))
+            .send()
+            .await?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            anyhow::bail!("chat completion failed ({}): {}", status, body);
+        }
+
+        let completion: ChatCompletionResponse = resp.json().await?;
+        completion
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .map(|content| content.trim().to_string())
+            .filter(|content| !content.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("chat completion returned no content"))
+    }
+}
@@ -0,0 +1,153 @@
+//! Tracing setup
+//!
+//! Logs are written as text or, with `log.format = "json"`, as one JSON
+//! object per line carrying the fields of the enclosing spans, so every line
+//! logged while handling a request has its `request_id` and every line of a
+//! relay connection its `relay_id`. The filter sits behind a reload layer so
+//! `log.level` can change while the server runs; the format is fixed at
+//! startup.
+
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+
+use crate::config::LogSettings;
+
+/// How log lines are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// JSON lines, for Loki or ELK
+    Json,
+}
+
+/// Swaps the active log filter
+#[derive(Clone)]
+pub struct LogControl {
+    handle: reload::Handle<EnvFilter, Registry>,
+}
+
+/// Install the global subscriber; an invalid `log.level` falls back to the
+/// default filter and is returned as the error
+pub fn init(settings: &LogSettings) -> (LogControl, Result<(), String>) {
+    let (filter, result) = match filter(settings) {
+        Ok(filter) => (filter, Ok(())),
+        Err(e) => (default_filter(), Err(e)),
+    };
+    let (filter, handle) = reload::Layer::new(filter);
+    let (text, json) = match settings.format {
+        LogFormat::Text => (Some(fmt::layer()), None),
+        LogFormat::Json => {
+            let json = fmt::layer()
+                .json()
+                .flatten_event(true)
+                .with_current_span(false)
+                .with_span_list(true);
+            (None, Some(json))
+        }
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(text)
+        .with(json)
+        .init();
+    (LogControl { handle }, result)
+}
+
+impl LogControl {
+    /// Use the filter `settings` describe
+    pub fn apply(&self, settings: &LogSettings) -> Result<(), String> {
+        self.replace(filter(settings)?)
+    }
+
+    /// Use `directives` until the settings are applied again
+    pub fn set(&self, directives: &str) -> Result<(), String> {
+        self.replace(parse(directives)?)
+    }
+
+    fn replace(&self, filter: EnvFilter) -> Result<(), String> {
+        self.handle.reload(filter).map_err(|e| e.to_string())
+    }
+}
+
+/// `RUST_LOG` with everything at debug, as before `log.level` existed
+fn default_filter() -> EnvFilter {
+    EnvFilter::from_default_env().add_directive(tracing::Level::DEBUG.into())
+}
+
+/// The filter for `settings`; an invalid `level` is an error
+pub fn filter(settings: &LogSettings) -> Result<EnvFilter, String> {
+    match settings.level.as_deref().map(str::trim) {
+        Some(level) if !level.is_empty() => {
+            parse(level).map_err(|e| format!("invalid log.level: {}", e))
+        }
+        _ => Ok(default_filter()),
+    }
+}
+
+/// Filter directives in `RUST_LOG` syntax
+pub fn parse(directives: &str) -> Result<EnvFilter, String> {
+    EnvFilter::try_new(directives.trim()).map_err(|e| format!("{:?}: {}", directives, e))
+}
+
+/// Thins out a debug log written for every message of a busy loop
+///
+/// The first occurrence is logged, then one in every `every`; each logged
+/// occurrence reports how many were skipped since the last one.
+#[derive(Debug, Clone)]
+pub struct LogSampler {
+    every: u64,
+    seen: u64,
+}
+
+impl LogSampler {
+    /// `every` of 0 or 1 logs every occurrence
+    pub fn new(every: u64) -> Self {
+        Self {
+            every: every.max(1),
+            seen: 0,
+        }
+    }
+
+    /// Count an occurrence; `Some(skipped)` when this one should be logged
+    pub fn sample(&mut self) -> Option<u64> {
+        self.seen += 1;
+        if self.seen == 1 {
+            return Some(0);
+        }
+        ((self.seen - 1) % self.every == 0).then(|| self.every - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        let level = |level: &str| LogSettings {
+            level: Some(level.to_string()),
+            ..Default::default()
+        };
+        assert!(filter(&LogSettings::default()).is_ok());
+        assert!(filter(&level("info,todoki::relay=debug")).is_ok());
+        assert!(filter(&level("todoki=loud")).is_err());
+    }
+
+    #[test]
+    fn test_log_sampler() {
+        let mut sampler = LogSampler::new(3);
+        let logged: Vec<Option<u64>> = (0..7).map(|_| sampler.sample()).collect();
+        assert_eq!(
+            logged,
+            vec![Some(0), None, None, Some(2), None, None, Some(2)]
+        );
+
+        let mut every = LogSampler::new(0);
+        assert_eq!(every.sample(), Some(0));
+        assert_eq!(every.sample(), Some(0));
+    }
+}
@@ -1,14 +1,35 @@
 mod api;
 mod auth;
+mod ci;
 mod config;
 mod db;
+mod digest;
 mod event_bus;
+mod handlers;
+mod health;
+mod issue_sync;
+mod llm;
+mod logging;
 mod models;
+mod net;
+mod notification;
+mod permission;
+mod quota;
 mod relay;
+mod scheduler;
+mod summary;
+mod telegram;
+mod transcript;
+mod triage;
+mod trigger;
+mod vault;
+mod watch;
 
 use std::ops::Deref;
 use std::sync::Arc;
+use std::time::Duration;
 
+use clap::Parser;
 use gotcha::Gotcha;
 use gotcha::Json;
 use gotcha::axum::extract::FromRef;
@@ -17,10 +38,16 @@ use serde_json::json;
 use thiserror::Error;
 use tracing::{error, info};
 
-use crate::api::{agents, artifacts, projects, relays, report, tasks};
+use crate::api::{
+    activity, admin, agents, artifacts, calendar, debug, email, forges, notifications,
+    permissions, projects, quotas, relays, report, session_replay, status_page, task_context,
+    tasks, templates, time_entries, trackers, undo, views, watches,
+};
 use crate::auth::auth_middleware;
 use crate::config::Settings;
 use crate::db::DatabaseService;
+use crate::net::client_ip::{TrustedProxies, client_ip_middleware};
+use crate::net::request_id::request_id_middleware;
 use crate::relay::{RelayManager, RequestTracker};
 
 // ============================================================================
@@ -87,6 +114,54 @@ impl Deref for ReqTracker {
     }
 }
 
+/// Agent trigger engine wrapper for state extraction
+#[derive(Clone)]
+pub struct Triggers(pub Arc<trigger::TriggerEngine>);
+
+impl Deref for Triggers {
+    type Target = Arc<trigger::TriggerEngine>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Permission reviewer wrapper for state extraction
+#[derive(Clone)]
+pub struct Reviewer(pub Arc<permission::review::PermissionReviewer>);
+
+impl Deref for Reviewer {
+    type Target = Arc<permission::review::PermissionReviewer>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Log filter control wrapper for state extraction
+#[derive(Clone)]
+pub struct Logs(pub logging::LogControl);
+
+impl Deref for Logs {
+    type Target = logging::LogControl;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Watch registry wrapper for state extraction
+#[derive(Clone)]
+pub struct Watches(pub Arc<watch::WatchRegistry>);
+
+impl Deref for Watches {
+    type Target = Arc<watch::WatchRegistry>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 // ============================================================================
 // Error types
 // ============================================================================
@@ -99,6 +174,9 @@ pub enum TodokiError {
     #[error("Migration error: {0}")]
     Migration(#[from] conservator::MigrateError),
 
+    #[error("Migration check failed: {0}")]
+    MigrationCheck(String),
+
     #[error("Configuration error: {0}")]
     Config(String),
 
@@ -120,6 +198,7 @@ impl TodokiError {
         match self {
             TodokiError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
             TodokiError::Migration(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            TodokiError::MigrationCheck(_) => StatusCode::INTERNAL_SERVER_ERROR,
             TodokiError::Config(_) => StatusCode::INTERNAL_SERVER_ERROR,
             TodokiError::Auth(_) => StatusCode::UNAUTHORIZED,
             TodokiError::NotFound(_) => StatusCode::NOT_FOUND,
@@ -135,11 +214,17 @@ impl TodokiError {
 #[derive(Clone)]
 pub struct AppState {
     pub db: Db,
-    pub settings: Settings,
+    pub settings: config::reload::SharedSettings,
     pub relays: Arc<RelayManager>,
     pub event_publisher: Arc<event_bus::EventPublisher>,
     pub event_subscriber: Arc<event_bus::EventSubscriber>,
     pub request_tracker: Arc<RequestTracker>,
+    pub trigger_engine: Arc<trigger::TriggerEngine>,
+    pub reviewer: Arc<permission::review::PermissionReviewer>,
+    pub log_control: logging::LogControl,
+    pub watches: Arc<watch::WatchRegistry>,
+    #[cfg(feature = "graphql")]
+    pub graphql_schema: api::graphql::TodokiSchema,
 }
 
 impl Default for AppState {
@@ -158,7 +243,11 @@ impl FromRef<gotcha::GotchaContext<AppState, Settings>> for Db {
 // Allow extracting Settings from GotchaContext
 impl FromRef<gotcha::GotchaContext<AppState, Settings>> for Settings {
     fn from_ref(ctx: &gotcha::GotchaContext<AppState, Settings>) -> Self {
-        ctx.state.settings.clone()
+        ctx.state
+            .settings
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
     }
 }
 
@@ -190,6 +279,41 @@ impl FromRef<gotcha::GotchaContext<AppState, Settings>> for ReqTracker {
     }
 }
 
+// Allow extracting Triggers from GotchaContext
+impl FromRef<gotcha::GotchaContext<AppState, Settings>> for Triggers {
+    fn from_ref(ctx: &gotcha::GotchaContext<AppState, Settings>) -> Self {
+        Triggers(ctx.state.trigger_engine.clone())
+    }
+}
+
+// Allow extracting Reviewer from GotchaContext
+impl FromRef<gotcha::GotchaContext<AppState, Settings>> for Reviewer {
+    fn from_ref(ctx: &gotcha::GotchaContext<AppState, Settings>) -> Self {
+        Reviewer(ctx.state.reviewer.clone())
+    }
+}
+
+impl FromRef<gotcha::GotchaContext<AppState, Settings>> for Logs {
+    fn from_ref(ctx: &gotcha::GotchaContext<AppState, Settings>) -> Self {
+        Logs(ctx.state.log_control.clone())
+    }
+}
+
+// Allow extracting Watches from GotchaContext
+impl FromRef<gotcha::GotchaContext<AppState, Settings>> for Watches {
+    fn from_ref(ctx: &gotcha::GotchaContext<AppState, Settings>) -> Self {
+        Watches(ctx.state.watches.clone())
+    }
+}
+
+// Allow extracting the GraphQL schema from GotchaContext
+#[cfg(feature = "graphql")]
+impl FromRef<gotcha::GotchaContext<AppState, Settings>> for api::graphql::TodokiSchema {
+    fn from_ref(ctx: &gotcha::GotchaContext<AppState, Settings>) -> Self {
+        ctx.state.graphql_schema.clone()
+    }
+}
+
 // ============================================================================
 // Health check handler
 // ============================================================================
@@ -202,72 +326,387 @@ async fn health_check() -> Response {
 // Main
 // ============================================================================
 
+/// Todoki API server
+#[derive(Debug, Parser)]
+#[command(name = "todoki", version, about = "Todoki API server")]
+struct Args {
+    /// Apply pending database migrations and exit (for CI-driven deploys)
+    #[arg(long)]
+    migrate_only: bool,
+
+    /// With --migrate-only, list pending migrations without applying them
+    #[arg(long, requires = "migrate_only")]
+    dry_run: bool,
+
+    /// Validate the configuration, print a report and exit (non-zero on errors)
+    #[arg(long, conflicts_with = "migrate_only")]
+    check_config: bool,
+
+    /// With --check-config, also connect to the database
+    #[arg(long, requires = "check_config")]
+    check_database: bool,
+
+    /// Keep events in memory instead of the database (for demos; they are
+    /// lost on exit)
+    #[arg(long)]
+    memory_events: bool,
+}
+
 #[tokio::main]
 async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive(tracing::Level::DEBUG.into()),
-        )
-        .init();
+    let args = Args::parse();
+
+    if args.check_config {
+        let report = config::check::run(args.check_database).await;
+        report.print();
+        std::process::exit(if report.is_ok() { 0 } else { 1 });
+    }
+
+    let loaded = Settings::new();
+    let log = loaded
+        .as_ref()
+        .map(|settings| settings.application.log.clone())
+        .unwrap_or_default();
+    let (log_control, log_filter) = logging::init(&log);
+    if let Err(e) = log_filter {
+        error!("{}", e);
+    }
 
     info!("Starting Todoki API Server");
 
-    let settings = Settings::new().map_err(|e| {
+    let mut settings = loaded.map_err(|e| {
         error!("Failed to load configuration: {}", e);
         e
     })?;
+    let agent_tokens = &mut settings.application.agent_tokens;
+    if agent_tokens.secret.is_empty() {
+        agent_tokens.secret = auth::session::random_token();
+    }
 
     info!("Initializing database...");
-    let db_service = Arc::new(DatabaseService::new(&settings.application.database_url)?);
+    let db_service = Arc::new(DatabaseService::new(
+        &settings.application.database_url,
+        &settings.application.database,
+    )?);
+
+    let database = &settings.application.database;
+    let lock_timeout = Duration::from_secs(database.migration_lock_timeout_secs);
+    if args.migrate_only {
+        if args.dry_run {
+            let pending = db_service.pending_migrations().await?;
+            if pending.is_empty() {
+                println!("No pending migrations");
+            }
+            for migration in &pending {
+                println!("{:03} {}", migration.version, migration.description);
+            }
+        } else {
+            info!("Running database migrations...");
+            db_service.migrate(lock_timeout).await?;
+        }
+        return Ok(());
+    }
 
-    info!("Running database migrations...");
-    db_service.migrate().await?;
+    if database.migrate_on_startup {
+        info!("Running database migrations...");
+        db_service.migrate(lock_timeout).await?;
+    } else {
+        let pending = db_service.pending_migrations().await?;
+        if !pending.is_empty() {
+            error!(
+                pending = pending.len(),
+                "Database has pending migrations; apply them with `todoki --migrate-only`"
+            );
+            return Err("pending database migrations".into());
+        }
+    }
 
     let db = Db(db_service.clone());
-    let relay_manager = Arc::new(RelayManager::new());
+    let relay_manager = Arc::new(
+        RelayManager::with_limits(settings.application.concurrency.clone())
+            .with_agent_tokens(settings.application.agent_tokens.clone())
+            .with_permissions(settings.application.permissions.clone()),
+    );
+    if let Err(e) = quota::load_session_quotas(&db_service, &relay_manager).await {
+        tracing::warn!(error = %e, "failed to load project session quotas");
+    }
 
     // Initialize Event Bus
     info!("Initializing Event Bus...");
-    let event_store = Arc::new(event_bus::PgEventStore::new(db_service.pool()));
+    let event_store: Arc<dyn event_bus::EventStore> = if args.memory_events {
+        tracing::warn!("events are kept in memory and lost on exit");
+        Arc::new(event_bus::InMemoryEventStore::new())
+    } else {
+        let store = Arc::new(
+            event_bus::PgEventStore::new(db_service.pool())
+                .with_read_pool(db_service.read_pool())
+                .with_batched_inserts(settings.application.events.batch()),
+        );
+        // Keep monthly event partitions created ahead of time
+        tokio::spawn(event_bus::maintenance::run_partition_maintenance(
+            store.clone(),
+        ));
+        store
+    };
     let event_publisher = Arc::new(event_bus::EventPublisher::new(event_store.clone()));
     let event_subscriber = Arc::new(event_bus::EventSubscriber::new(event_store.clone()));
 
+    // Publish events queued in the transactional outbox
+    tokio::spawn(event_bus::outbox::run_outbox_relay(
+        db_service.pool(),
+        event_publisher.clone(),
+    ));
+
     // Initialize Request Tracker for async request-response pattern
     let request_tracker = Arc::new(RequestTracker::new());
 
+    // Spawn executions queued outside their project's execution window
+    tokio::spawn(scheduler::run_execution_scheduler(
+        db_service.clone(),
+        relay_manager.clone(),
+        event_publisher.clone(),
+    ));
+
+    // Return snoozed tasks to the inbox when their wake time passes
+    let snooze_waker = scheduler::snooze::SnoozeWaker::new(
+        &settings.application.snooze,
+        &settings.application.telegram,
+        db_service.clone(),
+        event_publisher.clone(),
+    );
+    tokio::spawn(snooze_waker.run());
+
+    // Cancel permission requests nobody answered in time
+    let permission_expiry = scheduler::permission_expiry::PermissionExpiry::new(
+        &settings.application.permissions,
+        db_service.clone(),
+        relay_manager.clone(),
+        event_publisher.clone(),
+    );
+    tokio::spawn(permission_expiry.run());
+
+    // Watched tasks and sessions; they route permission requests and
+    // watched-task notifications
+    let watch_registry = Arc::new(watch::WatchRegistry::new());
+    if let Err(e) = watch_registry.load(&db_service).await {
+        tracing::warn!("Failed to load watches: {}", e);
+    }
+
+    // Turn events into in-app notifications
+    let notification_center = Arc::new(
+        notification::NotificationCenter::new(
+            &settings.application.notifications,
+            db_service.clone(),
+            event_publisher.clone(),
+        )
+        .with_watches(watch_registry.clone()),
+    );
+    tokio::spawn(notification_center.clone().run());
+
     // Start relay response handler in background
-    {
-        let publisher = event_publisher.clone();
-        let db = db_service.clone();
-        let tracker = request_tracker.clone();
+    tokio::spawn(handlers::run(
+        handlers::EventHandler::RelayResponses,
+        event_publisher.clone(),
+        db_service.clone(),
+        request_tracker.clone(),
+    ));
+    info!("Relay response handler started");
+
+    // Apply agent task tool calls (comments, subtasks, follow-ups) in background
+    tokio::spawn(handlers::run(
+        handlers::EventHandler::AgentTaskTools,
+        event_publisher.clone(),
+        db_service.clone(),
+        request_tracker.clone(),
+    ));
+    info!("Agent task tool handler started");
+
+    // Start auto-trigger agents on their subscribed events
+    let trigger_engine = Arc::new(
+        trigger::TriggerEngine::new(
+            db_service.clone(),
+            relay_manager.clone(),
+            event_publisher.clone(),
+            event_subscriber.clone(),
+        )
+        .with_assignment(settings.application.assignment.clone()),
+    );
+    tokio::spawn(trigger_engine.clone().run());
+    info!("Agent trigger engine started");
+
+    // Record relay liveness probes and restart agents that stop answering
+    let health_monitor = Arc::new(health::HealthMonitor::new(
+        settings.application.health.clone(),
+        db_service.clone(),
+        relay_manager.clone(),
+        event_publisher.clone(),
+        request_tracker.clone(),
+    ));
+    tokio::spawn(health_monitor.run());
+    info!("Agent health monitor started");
+
+    // Telegram bot (task capture and permission responses)
+    if settings.application.telegram.enabled() {
+        let bot = Arc::new(telegram::TelegramBot::new(
+            settings.application.telegram.clone(),
+            db_service.clone(),
+            relay_manager.clone(),
+            event_publisher.clone(),
+        ));
+        tokio::spawn(bot.run());
+        info!("Telegram bot started");
+    }
+
+    // Daily digest worker
+    if settings.application.digest.enabled {
+        let worker = digest::DigestWorker::new(
+            settings.application.digest.clone(),
+            &settings.application.telegram,
+            db_service.clone(),
+        );
+        tokio::spawn(worker.run());
+        info!("Daily digest worker started");
+    }
+
+    // CI status of branches agents worked on
+    if settings.application.ci.enabled {
+        let watcher = ci::CiWatcher::new(
+            settings.application.ci.clone(),
+            db_service.clone(),
+            event_publisher.clone(),
+        );
+        tokio::spawn(watcher.run());
+        info!("CI watcher started");
+    }
+
+    // Jira / Linear issue sync
+    if settings.application.issue_sync.enabled {
+        let sync = Arc::new(issue_sync::IssueSyncService::new(
+            settings.application.issue_sync.clone(),
+            db_service.clone(),
+        ));
+        tokio::spawn(sync.run(event_publisher.clone()));
+        info!("Issue sync started");
+    }
+
+    // Scheduled Markdown vault export
+    if settings.application.vault.enabled {
+        let exporter =
+            vault::VaultExporter::new(settings.application.vault.clone(), db_service.clone());
+        tokio::spawn(exporter.run());
+        info!("Vault export scheduled");
+    }
+
+    // LLM session summaries after each completed prompt
+    if settings.application.session_summary.enabled {
+        match llm::LlmClient::from_settings(&settings.application.auto_review) {
+            Some(llm) => {
+                let summarizer = Arc::new(summary::SessionSummarizer::new(
+                    settings.application.session_summary.clone(),
+                    llm,
+                    db_service.clone(),
+                    event_store.clone(),
+                ));
+                tokio::spawn(summarizer.run(event_publisher.clone()));
+                info!("Session summarizer started");
+            }
+            None => tracing::warn!("session summaries enabled but auto_review.openai_api_key is not set"),
+        }
+    }
+
+    // LLM triage of newly created tasks
+    if settings.application.triage.enabled {
+        match llm::LlmClient::from_settings(&settings.application.auto_review) {
+            Some(llm) => {
+                let triage = Arc::new(triage::TriageService::new(
+                    settings.application.triage.clone(),
+                    llm,
+                    db_service.clone(),
+                    event_publisher.clone(),
+                ));
+                tokio::spawn(triage.run());
+                info!("Task triage service started");
+            }
+            None => tracing::warn!("task triage enabled but auto_review.openai_api_key is not set"),
+        }
+    }
 
-        tokio::spawn(async move {
-            handle_relay_responses(publisher, db, tracker).await;
-        });
-        info!("Relay response handler started");
+    // LLM review of permission requests, run as they are emitted
+    let reviewer = Arc::new(
+        permission::review::PermissionReviewer::new(
+            &settings.application.auto_review,
+            settings.application.permissions.clone(),
+            db_service.clone(),
+            relay_manager.clone(),
+            event_publisher.clone(),
+        )
+        .with_quotas(settings.application.quotas.clone()),
+    );
+    if reviewer.enabled() {
+        info!("Permission auto-review enabled");
+    } else if settings.application.auto_review.enabled {
+        tracing::warn!("permission auto-review enabled but auto_review.openai_api_key is not set");
     }
 
     let app_settings = settings.application.clone();
+    let shared_settings = Arc::new(std::sync::RwLock::new(app_settings.clone()));
     let app_state = AppState {
         db: db.clone(),
-        settings: app_settings.clone(),
+        settings: shared_settings.clone(),
         relays: relay_manager.clone(),
         event_publisher: event_publisher.clone(),
         event_subscriber: event_subscriber.clone(),
         request_tracker: request_tracker.clone(),
+        trigger_engine: trigger_engine.clone(),
+        reviewer: reviewer.clone(),
+        log_control: log_control.clone(),
+        watches: watch_registry,
+        #[cfg(feature = "graphql")]
+        graphql_schema: api::graphql::build_schema(
+            db_service.clone(),
+            event_publisher.clone(),
+            event_subscriber.clone(),
+        ),
     };
 
     info!("Relay manager initialized");
 
+    // Apply reloadable settings on SIGHUP or when the config files change
+    let reloader = config::reload::ConfigReloader::new(
+        shared_settings,
+        reviewer,
+        notification_center,
+        log_control,
+        event_publisher.clone(),
+    );
+    tokio::spawn(reloader.run());
+
+    let cors = net::cors::cors_layer(&app_settings.cors)?;
+    let trusted_proxies = TrustedProxies::from_settings(&app_settings.proxy)?;
+
     let addr = format!("{}:{}", &settings.basic.host, &settings.basic.port);
-    info!("Starting server on http://{}", addr);
+    let addr = if app_settings.tls.enabled() {
+        // Plain HTTP stays on loopback; TLS is terminated in front of it
+        let acceptor = net::tls::load_acceptor(&app_settings.tls)?;
+        let listener = tokio::net::TcpListener::bind(&addr).await?;
+        let backend = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+        tokio::spawn(net::tls::serve(listener, acceptor, backend));
+        info!("Starting server on https://{}", addr);
+        backend.to_string()
+    } else {
+        info!("Starting server on http://{}", addr);
+        addr
+    };
 
-    Gotcha::with_types::<AppState, Settings>()
+    let app = Gotcha::with_types::<AppState, Settings>()
         .state(app_state)
         .config(settings)
         // Health check
         .get("/api", health_check)
+        // Login routes
+        .get("/api/auth/config", auth::oidc::auth_config)
+        .get("/api/auth/oidc/login", auth::oidc::login)
+        .get("/api/auth/oidc/callback", auth::oidc::callback)
         // Task routes
         .get("/api/tasks", tasks::get_tasks)
         .get("/api/tasks/inbox", tasks::get_inbox_tasks)
@@ -275,16 +714,38 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
         .get("/api/tasks/in-progress", tasks::get_in_progress_tasks)
         .get("/api/tasks/done", tasks::get_done_tasks)
         .get("/api/tasks/done/today", tasks::get_today_done_tasks)
+        .get("/api/tasks/snoozed", tasks::get_snoozed_tasks)
         .post("/api/tasks", tasks::create_task)
         .get("/api/tasks/:task_id", tasks::get_task)
         .put("/api/tasks/:task_id", tasks::update_task)
         .post("/api/tasks/:task_id/status", tasks::update_task_status)
+        .post("/api/tasks/:task_id/move", tasks::move_task)
         .post("/api/tasks/:task_id/archive", tasks::archive_task)
         .post("/api/tasks/:task_id/unarchive", tasks::unarchive_task)
+        .post("/api/tasks/:task_id/merge", tasks::merge_task)
+        .post("/api/tasks/:task_id/snooze", tasks::snooze_task)
+        .delete("/api/tasks/:task_id/snooze", tasks::unsnooze_task)
         .delete("/api/tasks/:task_id", tasks::delete_task)
         .post("/api/tasks/:task_id/comments", tasks::add_comment)
         .post("/api/tasks/:task_id/execute", tasks::execute_task)
         .get("/api/tasks/:task_id/execution", tasks::get_task_execution)
+        .get("/api/tasks/:task_id/executions", tasks::get_task_executions)
+        .post("/api/tasks/:task_id/handoff", tasks::handoff_task)
+        .get("/api/tasks/:task_id/context", task_context::list_task_context)
+        .get("/api/tasks/:task_id/context/:key", task_context::get_task_context)
+        .put("/api/tasks/:task_id/context/:key", task_context::put_task_context)
+        .delete("/api/tasks/:task_id/context/:key", task_context::delete_task_context)
+        .get("/api/tasks/:task_id/activity", activity::task_activity)
+        .post("/api/tasks/:task_id/watch", watches::watch_task)
+        .delete("/api/tasks/:task_id/watch", watches::unwatch_task)
+        .get("/api/tasks/:task_id/time-entries", time_entries::list_time_entries)
+        .post("/api/tasks/:task_id/time-entries", time_entries::log_time)
+        .post("/api/tasks/:task_id/time-entries/start", time_entries::start_timer)
+        .post("/api/tasks/:task_id/time-entries/stop", time_entries::stop_timer)
+        .delete(
+            "/api/tasks/:task_id/time-entries/:entry_id",
+            time_entries::delete_time_entry,
+        )
         // Project routes
         .get("/api/projects", projects::list_projects)
         .post("/api/projects", projects::create_project)
@@ -292,150 +753,215 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
         .get("/api/projects/:project_id", projects::get_project)
         .put("/api/projects/:project_id", projects::update_project)
         .delete("/api/projects/:project_id", projects::delete_project)
+        .post("/api/projects/:project_id/clone", projects::clone_project)
+        .post("/api/projects/:project_id/archive", projects::archive_project)
+        .post("/api/projects/:project_id/restore", projects::restore_project)
         .get("/api/projects/:project_id/tasks/done", projects::get_project_done_tasks)
+        .get("/api/projects/:project_id/activity", activity::project_activity)
+        .get(
+            "/api/projects/:project_id/time-report",
+            time_entries::project_time_report,
+        )
+        .get(
+            "/api/projects/:project_id/status-page",
+            status_page::get_status_page,
+        )
+        .put(
+            "/api/projects/:project_id/status-page",
+            status_page::update_status_page,
+        )
+        .get(
+            "/api/projects/:project_id/quotas",
+            quotas::get_project_quotas,
+        )
+        .put(
+            "/api/projects/:project_id/quotas",
+            quotas::update_project_quotas,
+        )
+        // Public status pages, gated by their token instead of a login
+        .get("/api/public/status/:token", status_page::get_public_status)
+        .get("/status/:token", status_page::public_status_page)
+        // Admin routes
+        .get("/api/admin/backup", admin::backup)
+        .post("/api/admin/restore", admin::restore)
+        .put("/api/debug/log-level", debug::set_log_level)
+        .post("/api/export/vault", api::vault::export_vault)
+        // Notification center of the calling user
+        .get("/api/notifications", notifications::list_notifications)
+        .post(
+            "/api/notifications/read-all",
+            notifications::mark_all_notifications_read,
+        )
+        .get(
+            "/api/notifications/preferences",
+            notifications::get_notification_preferences,
+        )
+        .put(
+            "/api/notifications/preferences",
+            notifications::update_notification_preferences,
+        )
+        .post(
+            "/api/notifications/:notification_id/read",
+            notifications::mark_notification_read,
+        )
+        // Tasks and sessions the calling user watches
+        .get("/api/watches", watches::list_watches)
+        .post("/api/watches", watches::create_watch)
+        .delete("/api/watches/:watch_id", watches::delete_watch)
+        .get("/api/watched", activity::watched_activity)
+        // Undo of recent mutations by the calling token
+        .post("/api/undo", undo::undo)
         // Report route
         .get("/api/report", report::get_report)
+        // Calendar feed (iCalendar, token via query for calendar apps)
+        .get("/api/calendar.ics", calendar::calendar_feed)
         // Artifact routes
         .get(
             "/api/projects/:project_id/artifacts",
             artifacts::list_artifacts,
         )
         .get("/api/artifacts/:artifact_id", artifacts::get_artifact)
+        .patch("/api/artifacts/:artifact_id", artifacts::update_artifact)
+        .delete("/api/artifacts/:artifact_id", artifacts::delete_artifact)
+        .post(
+            "/api/artifacts/:artifact_id/links/:task_id",
+            artifacts::link_artifact,
+        )
+        .delete(
+            "/api/artifacts/:artifact_id/links/:task_id",
+            artifacts::unlink_artifact,
+        )
+        .post(
+            "/api/sessions/:session_id/artifacts",
+            artifacts::create_artifact,
+        )
+        // Prompt template routes
+        .get("/api/views", views::list_views)
+        .post("/api/views", views::create_view)
+        .get("/api/views/:view_id", views::get_view)
+        .put("/api/views/:view_id", views::update_view)
+        .delete("/api/views/:view_id", views::delete_view)
+        .get("/api/views/:view_id/tasks", views::get_view_tasks)
+        .get("/api/templates", templates::list_templates)
+        .post("/api/templates", templates::create_template)
+        .get("/api/templates/:template_id", templates::get_template)
+        .put("/api/templates/:template_id", templates::update_template)
+        .delete("/api/templates/:template_id", templates::delete_template)
+        .get(
+            "/api/templates/:template_id/versions",
+            templates::list_template_versions,
+        )
+        .post(
+            "/api/templates/:template_id/rollback",
+            templates::rollback_template,
+        )
+        .get(
+            "/api/projects/:project_id/templates",
+            templates::list_project_templates,
+        )
+        .put(
+            "/api/projects/:project_id/templates/:role",
+            templates::set_project_template,
+        )
+        // Integration routes
+        .post("/api/integrations/email", email::receive_email)
+        .post("/api/integrations/gitlab", forges::receive_gitlab_webhook)
+        .post("/api/integrations/gitea", forges::receive_gitea_webhook)
+        .post("/api/integrations/jira", trackers::receive_jira_webhook)
+        .post("/api/integrations/linear", trackers::receive_linear_webhook)
         // Agent routes
         .get("/api/agents", agents::list_agents)
         .post("/api/agents", agents::create_agent)
+        .get("/api/agents/leaderboard", agents::get_agent_leaderboard)
         .get("/api/agents/:agent_id", agents::get_agent)
         .delete("/api/agents/:agent_id", agents::delete_agent)
         .post("/api/agents/:agent_id/start", agents::start_agent)
         .post("/api/agents/:agent_id/stop", agents::stop_agent)
+        .put(
+            "/api/agents/:agent_id/subscriptions",
+            agents::update_subscriptions,
+        )
+        .post("/api/agents/:agent_id/replay", agents::start_replay)
+        .delete("/api/agents/:agent_id/replay", agents::cancel_replay)
+        .get("/api/agents/:agent_id/events", agents::get_agent_events)
         .get("/api/agents/:agent_id/sessions", agents::get_agent_sessions)
+        .get("/api/agents/:agent_id/stats", agents::get_agent_stats)
+        .get(
+            "/api/agents/:agent_id/sessions/:session_id/export",
+            agents::export_session,
+        )
+        .post(
+            "/api/agents/:agent_id/sessions/:session_id/share",
+            agents::share_session,
+        )
+        .get(
+            "/api/agents/:agent_id/sessions/:session_id/replay",
+            session_replay::replay_session,
+        )
         // Relay routes
-        // Note: Relay WebSocket connections now use /ws/event-bus with relay_id parameter
         .get("/api/relays", relays::list_relays)
+        .get("/api/relays/capacity", relays::get_capacity)
         .get("/api/relays/:relay_id", relays::get_relay)
         .get(
             "/api/projects/:project_id/relays",
             relays::list_relays_by_project,
         )
+        // Permission routes
+        .get(
+            "/api/permissions/pending",
+            permissions::list_pending_permissions,
+        )
+        .post(
+            "/api/permissions/:request_id/respond",
+            permissions::respond_permission,
+        )
+        .post(
+            "/api/permission-policies/simulate",
+            permissions::simulate_permission_policy,
+        )
         // Event Bus routes
         .get("/api/event-bus", api::event_bus::query_events)
         .get("/api/event-bus/latest", api::event_bus::get_latest_cursor)
         .post("/api/event-bus/replay", api::event_bus::replay_events)
         .post("/api/event-bus/emit", api::event_bus::emit_event)
-        // Event Bus WebSocket (for real-time event streaming)
+        .get("/api/event-bus/dead-letters", api::event_bus::list_dead_letters)
+        .post(
+            "/api/event-bus/dead-letters/:dead_letter_id/reprocess",
+            api::event_bus::reprocess_dead_letter,
+        )
+        // Event Bus WebSocket (for real-time event streaming to frontends)
         .get("/ws/event-bus", api::event_bus_ws::event_bus_websocket)
-        .layer(gotcha::axum::middleware::from_fn_with_state(
-            app_settings,
-            auth_middleware,
-        ))
-        .with_cors()
-        .with_openapi()
-        .listen(addr)
-        .await?;
+        // Relay WebSocket (relay token only)
+        .get("/ws/relay", api::event_bus_ws::relay_websocket);
+
+    // GraphQL API (optional feature)
+    #[cfg(feature = "graphql")]
+    let app = app
+        .post("/graphql", api::graphql::graphql_handler)
+        .get("/graphql/ws", api::graphql::graphql_ws_handler);
+
+    // Web UI, with every other GET path falling back to it
+    let app = if app_settings.web.enabled {
+        app.get("/", net::static_files::serve)
+            .get("/*path", net::static_files::serve)
+    } else {
+        app
+    };
 
-    Ok(())
-}
+    app.layer(gotcha::axum::middleware::from_fn_with_state(
+        app_settings,
+        auth_middleware,
+    ))
+    .layer(gotcha::axum::middleware::from_fn_with_state(
+        trusted_proxies,
+        client_ip_middleware,
+    ))
+    .layer(gotcha::axum::middleware::from_fn(api::error::problem_middleware))
+    .layer(gotcha::axum::middleware::from_fn(request_id_middleware))
+    .layer(cors)
+    .with_openapi()
+    .listen(addr)
+    .await?;
 
-// ============================================================================
-// Background Handlers
-// ============================================================================
-
-/// Handle relay response events from Event Bus
-///
-/// Listens for:
-/// - relay.spawn_completed: Notifies waiting request trackers
-/// - relay.spawn_failed: Notifies waiting request trackers with error
-/// - agent.session_exited: Updates session status in database
-async fn handle_relay_responses(
-    publisher: Arc<event_bus::EventPublisher>,
-    db: Arc<DatabaseService>,
-    tracker: Arc<RequestTracker>,
-) {
-    use tokio::sync::broadcast;
-
-    let mut rx = publisher.subscribe();
-
-    loop {
-        match rx.recv().await {
-            Ok(event) => {
-                match event.kind.as_str() {
-                    "relay.spawn_completed" => {
-                        if let Some(req_id) = event.data.get("request_id").and_then(|v| v.as_str())
-                        {
-                            let result = Ok(serde_json::json!({
-                                "session_id": event.data.get("session_id"),
-                            }));
-                            tracker.complete_request(req_id, result).await;
-                        }
-
-                        // Update session status
-                        if let Some(session_id_str) =
-                            event.data.get("session_id").and_then(|v| v.as_str())
-                        {
-                            if let Ok(session_uuid) = uuid::Uuid::parse_str(session_id_str) {
-                                if let Err(e) =
-                                    db.update_session_status(session_uuid, models::SessionStatus::Running)
-                                        .await
-                                {
-                                    error!(error = %e, session_id = %session_id_str, "failed to update session status");
-                                }
-                            }
-                        }
-                    }
-
-                    "relay.spawn_failed" => {
-                        if let Some(req_id) = event.data.get("request_id").and_then(|v| v.as_str())
-                        {
-                            let error_msg = event
-                                .data
-                                .get("error")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("unknown error");
-                            tracker
-                                .complete_request(req_id, Err(anyhow::anyhow!("{}", error_msg)))
-                                .await;
-                        }
-                    }
-
-                    "agent.session_exited" => {
-                        if let Some(session_id_str) =
-                            event.data.get("session_id").and_then(|v| v.as_str())
-                        {
-                            if let Ok(session_uuid) = uuid::Uuid::parse_str(session_id_str) {
-                                let status_str = event
-                                    .data
-                                    .get("status")
-                                    .and_then(|v| v.as_str())
-                                    .unwrap_or("completed");
-                                let status = match status_str {
-                                    "completed" => models::SessionStatus::Completed,
-                                    "failed" => models::SessionStatus::Failed,
-                                    "cancelled" => models::SessionStatus::Cancelled,
-                                    _ => models::SessionStatus::Completed,
-                                };
-                                if let Err(e) =
-                                    db.update_session_status(session_uuid, status)
-                                        .await
-                                {
-                                    error!(error = %e, session_id = %session_id_str, "failed to update session status");
-                                }
-                            }
-                        }
-                    }
-
-                    _ => {}
-                }
-            }
-            Err(broadcast::error::RecvError::Lagged(n)) => {
-                tracing::warn!(
-                    lagged_events = n,
-                    "relay response handler lagged, some events may be missed"
-                );
-            }
-            Err(_) => {
-                error!("relay response channel closed");
-                break;
-            }
-        }
-    }
+    Ok(())
 }
@@ -0,0 +1,264 @@
+use chrono::{DateTime, Utc};
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::task::TaskStatus;
+use super::workflow::status_key;
+
+/// Feed items per page unless the caller asks for fewer or more
+pub const DEFAULT_ACTIVITY_LIMIT: i64 = 50;
+/// Most feed items per page
+pub const MAX_ACTIVITY_LIMIT: i64 = 200;
+
+// ============================================================================
+// Activity Feed
+// ============================================================================
+
+/// What happened in a feed item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Schematic)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    Created,
+    StatusChange,
+    Archived,
+    Unarchived,
+    Reorder,
+    /// Another open task has a similar title
+    PossibleDuplicate,
+    /// A duplicate was merged into the task
+    MergedFrom,
+    /// The task was merged into another one
+    MergedInto,
+    Comment,
+    Artifact,
+    /// An agent session was spawned for the task
+    SessionStarted,
+    SessionExited,
+    /// The relay could not start the session
+    SpawnFailed,
+}
+
+impl ActivityKind {
+    /// Kind of a row of the feed query: a task event type, `comment`,
+    /// `artifact`, `session_started` or an event bus kind
+    pub fn from_source(source: &str) -> Option<Self> {
+        Some(match source {
+            "Create" => ActivityKind::Created,
+            "StatusChange" => ActivityKind::StatusChange,
+            "Archived" => ActivityKind::Archived,
+            "Unarchived" => ActivityKind::Unarchived,
+            "Reorder" => ActivityKind::Reorder,
+            "PossibleDuplicate" => ActivityKind::PossibleDuplicate,
+            "MergedFrom" => ActivityKind::MergedFrom,
+            "MergedInto" => ActivityKind::MergedInto,
+            "comment" => ActivityKind::Comment,
+            "artifact" => ActivityKind::Artifact,
+            "session_started" => ActivityKind::SessionStarted,
+            "agent.session_exited" => ActivityKind::SessionExited,
+            "relay.spawn_failed" => ActivityKind::SpawnFailed,
+            _ => return None,
+        })
+    }
+}
+
+/// One entry of a task or project activity feed
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct ActivityItem {
+    /// Unique within the feed
+    pub id: String,
+    pub kind: ActivityKind,
+    pub time: DateTime<Utc>,
+    pub task_id: Uuid,
+    pub project_id: Uuid,
+    /// Status after and before a status change
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<TaskStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_state: Option<TaskStatus>,
+    /// One line for display
+    pub summary: String,
+    /// Kind-specific details: comment `content`, `artifact_type`,
+    /// `session_id`, `agent_id`, `exit_code`, `error`, `rank`,
+    /// `related_task_id`, `similarity`
+    pub data: Value,
+}
+
+impl ActivityItem {
+    /// Display line for an item of `kind`
+    pub fn summarize(
+        kind: ActivityKind,
+        state: Option<TaskStatus>,
+        from_state: Option<TaskStatus>,
+        data: &Value,
+    ) -> String {
+        let field = |name: &str| data.get(name).and_then(Value::as_str).unwrap_or_default();
+        match kind {
+            ActivityKind::Created => "Task created".to_string(),
+            ActivityKind::StatusChange => match (from_state, state) {
+                (Some(from), Some(to)) => {
+                    format!("Status changed from {} to {}", status_key(from), status_key(to))
+                }
+                (None, Some(to)) => format!("Status changed to {}", status_key(to)),
+                _ => "Status changed".to_string(),
+            },
+            ActivityKind::Archived => "Task archived".to_string(),
+            ActivityKind::Unarchived => "Task unarchived".to_string(),
+            ActivityKind::Reorder => "Task moved on the board".to_string(),
+            ActivityKind::PossibleDuplicate => {
+                match data.get("similarity").and_then(Value::as_f64) {
+                    Some(similarity) => format!(
+                        "Possible duplicate of task {} ({:.0}% similar)",
+                        field("related_task_id"),
+                        similarity * 100.0
+                    ),
+                    None => format!("Possible duplicate of task {}", field("related_task_id")),
+                }
+            }
+            ActivityKind::MergedFrom => {
+                format!("Task {} merged into this task", field("related_task_id"))
+            }
+            ActivityKind::MergedInto => {
+                format!("Task merged into task {}", field("related_task_id"))
+            }
+            ActivityKind::Comment => {
+                let first_line = field("content").lines().next().unwrap_or_default();
+                let mut excerpt: String = first_line.chars().take(80).collect();
+                if excerpt.len() < field("content").len() {
+                    excerpt.push('…');
+                }
+                format!("Comment: {}", excerpt)
+            }
+            ActivityKind::Artifact => format!("Artifact {} created", field("artifact_type")),
+            ActivityKind::SessionStarted => "Agent session started".to_string(),
+            ActivityKind::SessionExited => match data.get("exit_code").and_then(Value::as_i64) {
+                Some(0) => "Agent session finished".to_string(),
+                Some(code) => format!("Agent session exited with code {}", code),
+                None => "Agent session exited".to_string(),
+            },
+            ActivityKind::SpawnFailed => {
+                format!("Agent session failed to start: {}", field("error"))
+            }
+        }
+    }
+}
+
+/// Position in a feed: the time and id of the last item seen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActivityCursor {
+    pub time: DateTime<Utc>,
+    pub id: String,
+}
+
+impl ActivityCursor {
+    pub fn of(item: &ActivityItem) -> Self {
+        Self {
+            time: item.time,
+            id: item.id.clone(),
+        }
+    }
+
+    /// Opaque form handed to clients, `<microseconds>.<id>`
+    pub fn encode(&self) -> String {
+        format!("{}.{}", self.time.timestamp_micros(), self.id)
+    }
+
+    pub fn decode(value: &str) -> Option<Self> {
+        let (micros, id) = value.split_once('.')?;
+        let time = DateTime::from_timestamp_micros(micros.parse().ok()?)?;
+        (!id.is_empty()).then(|| Self {
+            time,
+            id: id.to_string(),
+        })
+    }
+}
+
+/// `GET .../activity` query parameters
+#[derive(Debug, Clone, Default, Deserialize, Schematic)]
+pub struct ActivityQuery {
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    /// Items per page (default 50, at most 200)
+    pub limit: Option<i64>,
+}
+
+impl ActivityQuery {
+    pub fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_ACTIVITY_LIMIT)
+            .clamp(1, MAX_ACTIVITY_LIMIT)
+    }
+}
+
+/// A page of a feed, newest first
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct ActivityPage {
+    pub items: Vec<ActivityItem>,
+    /// Pass as `cursor` to fetch older items; absent on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl ActivityPage {
+    /// Page of `items` fetched with `limit`; a full page may have more after it
+    pub fn new(items: Vec<ActivityItem>, limit: i64) -> Self {
+        let next_cursor = (items.len() as i64 >= limit)
+            .then(|| items.last().map(|item| ActivityCursor::of(item).encode()))
+            .flatten();
+        Self { items, next_cursor }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_summarize() {
+        let summary = ActivityItem::summarize(
+            ActivityKind::StatusChange,
+            Some(TaskStatus::InProgress),
+            Some(TaskStatus::Todo),
+            &Value::Null,
+        );
+        assert_eq!(summary, "Status changed from todo to in-progress");
+
+        let data = json!({"content": "Looks good\nship it"});
+        let summary = ActivityItem::summarize(ActivityKind::Comment, None, None, &data);
+        assert_eq!(summary, "Comment: Looks good…");
+
+        let data = json!({"exit_code": 2});
+        let summary = ActivityItem::summarize(ActivityKind::SessionExited, None, None, &data);
+        assert_eq!(summary, "Agent session exited with code 2");
+
+        let data = json!({"related_task_id": "t-1", "similarity": 0.82});
+        let summary = ActivityItem::summarize(ActivityKind::PossibleDuplicate, None, None, &data);
+        assert_eq!(summary, "Possible duplicate of task t-1 (82% similar)");
+
+        assert_eq!(
+            ActivityKind::from_source("relay.spawn_failed"),
+            Some(ActivityKind::SpawnFailed)
+        );
+        assert_eq!(ActivityKind::from_source("CreateComment"), None);
+    }
+
+    #[test]
+    fn test_cursor() {
+        let cursor = ActivityCursor {
+            time: DateTime::from_timestamp_micros(1_760_000_000_123_456).unwrap(),
+            id: "agent.session_exited.42".to_string(),
+        };
+        assert_eq!(ActivityCursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(ActivityCursor::decode("garbage"), None);
+        assert_eq!(ActivityCursor::decode("12."), None);
+
+        assert_eq!(ActivityQuery::default().limit(), DEFAULT_ACTIVITY_LIMIT);
+        let query = ActivityQuery {
+            cursor: None,
+            limit: Some(10_000),
+        };
+        assert_eq!(query.limit(), MAX_ACTIVITY_LIMIT);
+    }
+}
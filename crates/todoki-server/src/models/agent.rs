@@ -2,8 +2,12 @@ use chrono::{DateTime, Utc};
 use conservator::{Creatable, Domain, TextEnum};
 use gotcha::Schematic;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use todoki_protocol::event_bus::ResourceUsage;
 use uuid::Uuid;
 
+use crate::relay::LabelSelector;
+
 // ============================================================================
 // Execution Mode
 // ============================================================================
@@ -13,7 +17,24 @@ use uuid::Uuid;
 pub enum ExecutionMode {
     #[default]
     Local,
+    /// ACP agent on a relay
     Remote,
+    /// Plain process on a relay for agents that do not speak ACP
+    Process,
+    /// Interactive CLI on a relay-side pseudo-terminal
+    Pty,
+}
+
+impl ExecutionMode {
+    /// Relay backend for this mode (`None` = not run on a relay)
+    pub fn session_mode(&self) -> Option<todoki_protocol::SessionMode> {
+        match self {
+            ExecutionMode::Local => None,
+            ExecutionMode::Remote => Some(todoki_protocol::SessionMode::Acp),
+            ExecutionMode::Process => Some(todoki_protocol::SessionMode::Process),
+            ExecutionMode::Pty => Some(todoki_protocol::SessionMode::Pty),
+        }
+    }
 }
 
 // ============================================================================
@@ -31,6 +52,21 @@ pub enum AgentStatus {
     Failed,
 }
 
+// ============================================================================
+// Agent Health
+// ============================================================================
+
+/// Liveness of the agent's running session, as last reported by its relay
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default, Schematic, TextEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentHealth {
+    /// No probe result since the current session started
+    #[default]
+    Unknown,
+    Healthy,
+    Unhealthy,
+}
+
 // ============================================================================
 // Session Status
 // ============================================================================
@@ -58,6 +94,17 @@ pub enum AgentRole {
     Qa,
 }
 
+impl AgentRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AgentRole::General => "general",
+            AgentRole::Business => "business",
+            AgentRole::Coding => "coding",
+            AgentRole::Qa => "qa",
+        }
+    }
+}
+
 impl From<AgentRole> for todoki_protocol::AgentRole {
     fn from(role: AgentRole) -> Self {
         match role {
@@ -88,12 +135,41 @@ pub struct Agent {
     pub status: AgentStatus,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Event kinds that trigger this agent (wildcards allowed, e.g. "task.*")
+    pub subscribed_events: Vec<String>,
+    /// Start a session automatically when a subscribed event is published
+    pub auto_trigger: bool,
+    /// Cursor of the last event that triggered this agent
+    pub last_cursor: i64,
+    /// Label selector the relay running this agent must match
+    pub relay_selector: Option<String>,
+    pub health: AgentHealth,
+    /// Liveness probes failed in a row
+    pub probe_failures: i32,
+    pub health_checked_at: Option<DateTime<Utc>>,
 }
 
 impl Agent {
     pub fn args_vec(&self) -> Vec<String> {
         serde_json::from_str(&self.args).unwrap_or_default()
     }
+
+    /// Whether an event of `kind` matches one of the agent's subscriptions
+    pub fn is_subscribed_to(&self, kind: &str) -> bool {
+        self.subscribed_events
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => kind.starts_with(prefix),
+                None => pattern == kind,
+            })
+    }
+
+    /// Parsed relay selector; invalid selectors are rejected when saved
+    pub fn label_selector(&self) -> Option<LabelSelector> {
+        self.relay_selector
+            .as_deref()
+            .and_then(|s| LabelSelector::parse(s).ok())
+    }
 }
 
 #[derive(Debug, Clone, Creatable)]
@@ -105,6 +181,9 @@ pub struct CreateAgent {
     pub execution_mode: ExecutionMode,
     pub role: AgentRole,
     pub project_id: Uuid,
+    pub subscribed_events: Vec<String>,
+    pub auto_trigger: bool,
+    pub relay_selector: Option<String>,
 }
 
 impl CreateAgent {
@@ -125,8 +204,28 @@ impl CreateAgent {
             execution_mode,
             role,
             project_id,
+            subscribed_events: Vec::new(),
+            auto_trigger: false,
+            relay_selector: None,
         }
     }
+
+    /// Trigger the agent on the given event kinds
+    pub fn with_subscriptions(
+        mut self,
+        subscribed_events: Vec<String>,
+        auto_trigger: bool,
+    ) -> Self {
+        self.subscribed_events = subscribed_events;
+        self.auto_trigger = auto_trigger;
+        self
+    }
+
+    /// Only run the agent on relays matching `selector`
+    pub fn with_relay_selector(mut self, selector: Option<String>) -> Self {
+        self.relay_selector = selector;
+        self
+    }
 }
 
 // ============================================================================
@@ -142,6 +241,19 @@ pub struct AgentSession {
     pub status: SessionStatus,
     pub started_at: DateTime<Utc>,
     pub ended_at: Option<DateTime<Utc>>,
+    /// Shared by every event of this execution
+    pub correlation_id: Uuid,
+    /// Latest resource usage reported by the relay (`ResourceUsage`)
+    pub resource_usage: Option<Value>,
+}
+
+impl AgentSession {
+    /// Parsed resource usage; `None` until the relay reported a sample
+    pub fn resource_usage(&self) -> Option<ResourceUsage> {
+        self.resource_usage
+            .clone()
+            .and_then(|v| serde_json::from_value(v).ok())
+    }
 }
 
 #[derive(Debug, Clone, Creatable)]
@@ -149,6 +261,17 @@ pub struct CreateAgentSession {
     pub agent_id: Uuid,
 }
 
+/// An agent's workload and past results, as the assignment engine sees them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AgentTrackRecord {
+    /// Unfinished, unarchived tasks assigned to the agent
+    pub open_tasks: i64,
+    /// Sessions it ran for the project's tasks that ended in the history window
+    pub finished_sessions: i64,
+    /// Those of them that completed
+    pub completed_sessions: i64,
+}
+
 // ============================================================================
 // API DTOs
 // ============================================================================
@@ -166,6 +289,14 @@ pub struct AgentResponse {
     pub status: AgentStatus,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub subscribed_events: Vec<String>,
+    pub auto_trigger: bool,
+    pub last_cursor: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relay_selector: Option<String>,
+    pub health: AgentHealth,
+    pub probe_failures: i32,
+    pub health_checked_at: Option<DateTime<Utc>>,
 }
 
 impl From<Agent> for AgentResponse {
@@ -182,6 +313,13 @@ impl From<Agent> for AgentResponse {
             status: a.status,
             created_at: a.created_at,
             updated_at: a.updated_at,
+            subscribed_events: a.subscribed_events.clone(),
+            auto_trigger: a.auto_trigger,
+            last_cursor: a.last_cursor,
+            relay_selector: a.relay_selector.clone(),
+            health: a.health,
+            probe_failures: a.probe_failures,
+            health_checked_at: a.health_checked_at,
         }
     }
 }
@@ -193,16 +331,22 @@ pub struct AgentSessionResponse {
     pub status: SessionStatus,
     pub started_at: DateTime<Utc>,
     pub ended_at: Option<DateTime<Utc>>,
+    pub correlation_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_usage: Option<ResourceUsage>,
 }
 
 impl From<AgentSession> for AgentSessionResponse {
     fn from(s: AgentSession) -> Self {
+        let resource_usage = s.resource_usage();
         Self {
             id: s.id,
             agent_id: s.agent_id,
             status: s.status,
             started_at: s.started_at,
             ended_at: s.ended_at,
+            correlation_id: s.correlation_id,
+            resource_usage,
         }
     }
 }
@@ -55,9 +55,47 @@ impl CreateArtifact {
 }
 
 // ============================================================================
-// Artifact Response
+// Artifact Request/Response
 // ============================================================================
 
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct CreateArtifactRequest {
+    /// Task the artifact belongs to (defaults to the agent token's task)
+    #[serde(default)]
+    pub task_id: Option<Uuid>,
+    pub artifact_type: String,
+    pub data: Value,
+}
+
+/// `PATCH /api/artifacts/:artifact_id` body
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Schematic)]
+pub struct UpdateArtifactRequest {
+    /// Merged into the artifact's data one key deep; keys set to null are
+    /// removed (e.g. `{"state": "merged"}`)
+    #[serde(default)]
+    pub data: Option<Value>,
+    /// Move the artifact to another task
+    #[serde(default)]
+    pub task_id: Option<Uuid>,
+}
+
+/// Apply `patch` to `data` one key deep: null removes a key, anything else
+/// replaces it. A patch that is not an object replaces the data entirely.
+pub fn merge_artifact_data(data: &mut Value, patch: Value) {
+    match (data.as_object_mut(), patch) {
+        (Some(fields), Value::Object(patch)) => {
+            for (key, value) in patch {
+                if value.is_null() {
+                    fields.remove(&key);
+                } else {
+                    fields.insert(key, value);
+                }
+            }
+        }
+        (_, patch) => *data = patch,
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
 pub struct ArtifactResponse {
     pub id: Uuid,
@@ -86,3 +124,21 @@ impl From<Artifact> for ArtifactResponse {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_artifact_data() {
+        let mut data = json!({"url": "https://example.com/pr/1", "state": "open", "draft": true});
+        merge_artifact_data(&mut data, json!({"state": "merged", "draft": null}));
+        assert_eq!(data, json!({"url": "https://example.com/pr/1", "state": "merged"}));
+
+        merge_artifact_data(&mut data, json!("revised"));
+        assert_eq!(data, json!("revised"));
+        merge_artifact_data(&mut data, json!({"content": "v2"}));
+        assert_eq!(data, json!({"content": "v2"}));
+    }
+}
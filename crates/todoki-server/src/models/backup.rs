@@ -0,0 +1,305 @@
+use chrono::{DateTime, Utc};
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Version of the backup line format; restores reject any other
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// Tables in a backup, parents before the tables referencing them
+///
+/// The undo log is left out: its entries only matter for a few minutes.
+pub const BACKUP_TABLES: &[&str] = &[
+    "projects",
+    "prompt_templates",
+    "prompt_template_versions",
+    "project_prompt_templates",
+    "project_status_pages",
+    "project_quotas",
+    "quota_usage",
+    "agents",
+    "agent_sessions",
+    "tasks",
+    "task_events",
+    "task_comments",
+    "task_snoozes",
+    "task_session_durations",
+    "task_time_entries",
+    "scheduled_executions",
+    "agent_events",
+    "agent_dispatches",
+    "artifacts",
+    "artifact_links",
+    "task_context",
+    "events",
+    "event_outbox",
+    "event_dead_letters",
+];
+
+/// One line of a backup, which is newline-delimited JSON
+///
+/// A backup is a header, then every table's rows followed by its
+/// `table_end`, then an `end` trailer that marks it as complete.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BackupLine {
+    Header {
+        version: u32,
+        backup_id: Uuid,
+        created_at: DateTime<Utc>,
+        tables: Vec<String>,
+    },
+    Row {
+        table: String,
+        row: Value,
+    },
+    TableEnd {
+        table: String,
+        rows: i64,
+    },
+    End {
+        rows: i64,
+    },
+}
+
+/// Rows handed to `DatabaseService::restore_backup` while a backup is read
+#[derive(Debug, Clone)]
+pub enum RestoreBatch {
+    Rows { table: String, rows: Vec<Value> },
+    /// The whole backup was read and checked; commit the restore
+    Commit,
+}
+
+/// Rows restored into one table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct RestoredTable {
+    pub table: String,
+    pub rows: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct RestoreResponse {
+    /// ID of the backup that was restored
+    pub backup_id: Uuid,
+    pub tables: Vec<RestoredTable>,
+    pub rows: i64,
+}
+
+/// Checks that a backup is complete and well-ordered while it is restored
+///
+/// Tables must come in `BACKUP_TABLES` order so parents are restored first,
+/// and each table's row count must match its `table_end`.
+#[derive(Debug, Default)]
+pub struct BackupReader {
+    backup_id: Option<Uuid>,
+    tables: Vec<String>,
+    /// Table whose rows are being read and its rows so far
+    current: Option<(String, i64)>,
+    finished: Vec<RestoredTable>,
+    ended: bool,
+}
+
+impl BackupReader {
+    pub fn backup_id(&self) -> Option<Uuid> {
+        self.backup_id
+    }
+
+    pub fn tables_total(&self) -> u32 {
+        self.tables.len() as u32
+    }
+
+    pub fn tables_done(&self) -> u32 {
+        self.finished.len() as u32
+    }
+
+    pub fn rows(&self) -> i64 {
+        let current = self.current.as_ref().map_or(0, |(_, rows)| *rows);
+        self.finished.iter().map(|t| t.rows).sum::<i64>() + current
+    }
+
+    /// Check the next line; returns the table it finished, if any
+    pub fn accept(&mut self, line: &BackupLine) -> Result<Option<RestoredTable>, String> {
+        if self.ended {
+            return Err("data after the end of the backup".to_string());
+        }
+        if self.backup_id.is_none() && !matches!(line, BackupLine::Header { .. }) {
+            return Err("backup does not start with a header".to_string());
+        }
+
+        match line {
+            BackupLine::Header {
+                version,
+                backup_id,
+                tables,
+                ..
+            } => {
+                if self.backup_id.is_some() {
+                    return Err("duplicate backup header".to_string());
+                }
+                if *version != BACKUP_FORMAT_VERSION {
+                    return Err(format!("unsupported backup format version {}", version));
+                }
+                if let Some(table) = tables.iter().find(|t| table_rank(t).is_none()) {
+                    return Err(format!("unknown table {} in backup", table));
+                }
+                self.backup_id = Some(*backup_id);
+                self.tables = tables.clone();
+                Ok(None)
+            }
+            BackupLine::Row { table, .. } => {
+                match &mut self.current {
+                    Some((current, rows)) if current == table => *rows += 1,
+                    Some((current, _)) => {
+                        return Err(format!("rows of {} before the end of {}", table, current));
+                    }
+                    None => {
+                        self.start_table(table)?;
+                        self.current = Some((table.clone(), 1));
+                    }
+                }
+                Ok(None)
+            }
+            BackupLine::TableEnd { table, rows } => {
+                let read = match self.current.take() {
+                    Some((current, read)) if current == *table => read,
+                    Some((current, _)) => {
+                        return Err(format!("end of {} before the end of {}", table, current));
+                    }
+                    None => {
+                        self.start_table(table)?;
+                        0
+                    }
+                };
+                if read != *rows {
+                    return Err(format!(
+                        "table {} has {} rows but its end says {}",
+                        table, read, rows
+                    ));
+                }
+                let done = RestoredTable {
+                    table: table.clone(),
+                    rows: read,
+                };
+                self.finished.push(done.clone());
+                Ok(Some(done))
+            }
+            BackupLine::End { rows } => {
+                if let Some((current, _)) = &self.current {
+                    return Err(format!("backup ends inside table {}", current));
+                }
+                if self.rows() != *rows {
+                    return Err(format!(
+                        "backup has {} rows but its end says {}",
+                        self.rows(),
+                        rows
+                    ));
+                }
+                self.ended = true;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Tables read, once the whole backup has been accepted
+    pub fn finish(&self) -> Result<Vec<RestoredTable>, String> {
+        if !self.ended {
+            return Err("backup is truncated".to_string());
+        }
+        Ok(self.finished.clone())
+    }
+
+    fn start_table(&self, table: &str) -> Result<(), String> {
+        if !self.tables.iter().any(|t| t == table) {
+            return Err(format!(
+                "table {} is not listed in the backup header",
+                table
+            ));
+        }
+        let previous = self.finished.last().and_then(|t| table_rank(&t.table));
+        if previous.is_some() && previous >= table_rank(table) {
+            return Err(format!("table {} is out of order", table));
+        }
+        Ok(())
+    }
+}
+
+fn table_rank(table: &str) -> Option<usize> {
+    BACKUP_TABLES.iter().position(|t| *t == table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(tables: &[&str]) -> BackupLine {
+        BackupLine::Header {
+            version: BACKUP_FORMAT_VERSION,
+            backup_id: Uuid::nil(),
+            created_at: Utc::now(),
+            tables: tables.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    fn row(table: &str) -> BackupLine {
+        BackupLine::Row {
+            table: table.to_string(),
+            row: serde_json::json!({}),
+        }
+    }
+
+    fn table_end(table: &str, rows: i64) -> BackupLine {
+        BackupLine::TableEnd {
+            table: table.to_string(),
+            rows,
+        }
+    }
+
+    #[test]
+    fn test_reader_accepts_complete_backup() {
+        let mut reader = BackupReader::default();
+        let lines = [
+            header(&["projects", "tasks"]),
+            row("projects"),
+            table_end("projects", 1),
+            table_end("tasks", 0),
+            BackupLine::End { rows: 1 },
+        ];
+        for line in &lines {
+            reader.accept(line).unwrap();
+        }
+        assert_eq!(
+            reader.finish().unwrap(),
+            vec![
+                RestoredTable {
+                    table: "projects".to_string(),
+                    rows: 1
+                },
+                RestoredTable {
+                    table: "tasks".to_string(),
+                    rows: 0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reader_rejects_truncated_and_misordered_backups() {
+        let mut reader = BackupReader::default();
+        reader.accept(&header(&["projects", "tasks"])).unwrap();
+        reader.accept(&row("projects")).unwrap();
+        assert!(reader.accept(&table_end("projects", 2)).is_err());
+
+        let mut reader = BackupReader::default();
+        reader.accept(&header(&["projects", "tasks"])).unwrap();
+        reader.accept(&table_end("tasks", 0)).unwrap();
+        assert!(reader.accept(&row("projects")).is_err());
+
+        let mut reader = BackupReader::default();
+        reader.accept(&header(&["projects"])).unwrap();
+        reader.accept(&table_end("projects", 0)).unwrap();
+        assert_eq!(reader.finish().unwrap_err(), "backup is truncated");
+
+        assert!(BackupReader::default().accept(&row("projects")).is_err());
+    }
+}
@@ -0,0 +1,25 @@
+use chrono::{DateTime, Utc};
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::event_bus::Event;
+
+/// An event a background handler failed to apply
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct DeadLetter {
+    pub id: Uuid,
+    pub event_cursor: i64,
+    pub event_kind: String,
+    /// Name of the handler that failed (e.g. "relay_responses")
+    pub handler: String,
+    /// Snapshot of the event as it was delivered to the handler
+    pub event: Event,
+    /// Error from the most recent attempt
+    pub error: String,
+    pub attempts: i32,
+    pub created_at: DateTime<Utc>,
+    pub last_attempt_at: DateTime<Utc>,
+    /// Set once a reprocess attempt succeeds
+    pub resolved_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,157 @@
+use std::collections::HashSet;
+
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::task::{TaskResponse, TaskStatus};
+
+// ============================================================================
+// Similarity
+// ============================================================================
+
+/// Trigrams of a text the way pg_trgm builds them: lowercased alphanumeric
+/// words, each padded with two spaces in front and one behind
+fn trigrams(text: &str) -> HashSet<[char; 3]> {
+    let mut grams = HashSet::new();
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        let padded: Vec<char> = "  "
+            .chars()
+            .chain(word.chars().flat_map(char::to_lowercase))
+            .chain(" ".chars())
+            .collect();
+        for window in padded.windows(3) {
+            grams.insert([window[0], window[1], window[2]]);
+        }
+    }
+    grams
+}
+
+/// Shared trigrams over all trigrams of both texts, from 0 to 1
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (trigrams(a), trigrams(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+/// First line of a task's content, the part compared for duplicates
+pub fn task_title(content: &str) -> &str {
+    content.lines().next().unwrap_or_default().trim()
+}
+
+// ============================================================================
+// Suggestions
+// ============================================================================
+
+/// An open task that may be the same as a new one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct DuplicateSuggestion {
+    pub task_id: Uuid,
+    pub title: String,
+    pub status: TaskStatus,
+    /// Title similarity, 0 to 1
+    pub similarity: f64,
+}
+
+/// Candidates whose title is at least `threshold` alike to `content`'s,
+/// most similar first, at most `limit`
+pub fn find_duplicates(
+    content: &str,
+    candidates: &[(Uuid, String, TaskStatus)],
+    threshold: f64,
+    limit: usize,
+) -> Vec<DuplicateSuggestion> {
+    let title = task_title(content);
+    if title.is_empty() {
+        return Vec::new();
+    }
+
+    let mut suggestions: Vec<DuplicateSuggestion> = candidates
+        .iter()
+        .filter_map(|(task_id, content, status)| {
+            let candidate = task_title(content);
+            let similarity = similarity(title, candidate);
+            (similarity >= threshold).then(|| DuplicateSuggestion {
+                task_id: *task_id,
+                title: candidate.to_string(),
+                status: *status,
+                similarity,
+            })
+        })
+        .collect();
+    suggestions.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    suggestions.truncate(limit);
+    suggestions
+}
+
+// ============================================================================
+// API DTOs
+// ============================================================================
+
+/// `POST /api/tasks` response: the task and open tasks it may duplicate
+#[derive(Debug, Clone, Serialize, Schematic)]
+pub struct TaskCreateResponse {
+    #[serde(flatten)]
+    #[schematic(flatten)]
+    pub task: TaskResponse,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub possible_duplicates: Vec<DuplicateSuggestion>,
+}
+
+#[derive(Debug, Clone, Deserialize, Schematic)]
+pub struct MergeTaskRequest {
+    /// Task to fold into this one; it is archived afterwards
+    pub duplicate_id: Uuid,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("Fix login bug", "fix LOGIN bug!"), 1.0);
+        assert_eq!(similarity("", ""), 0.0);
+        assert_eq!(similarity("abc", "xyz"), 0.0);
+
+        let close = similarity("Fix login redirect bug", "Fix the login redirect bug");
+        let far = similarity("Fix login redirect bug", "Write release notes");
+        assert!(close > 0.7, "{}", close);
+        assert!(far < 0.1, "{}", far);
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let candidates = vec![
+            (a, "Write release notes".to_string(), TaskStatus::Todo),
+            (
+                b,
+                "Fix login redirect\nsteps to reproduce".to_string(),
+                TaskStatus::Todo,
+            ),
+            (
+                c,
+                "Fix the login redirect bug".to_string(),
+                TaskStatus::InProgress,
+            ),
+        ];
+
+        let found = find_duplicates("Fix login redirect bug\n\ndetails", &candidates, 0.5, 3);
+        let ids: Vec<Uuid> = found.iter().map(|s| s.task_id).collect();
+        assert_eq!(ids, [c, b]);
+        assert_eq!(found[1].title, "Fix login redirect");
+
+        assert_eq!(
+            find_duplicates("Fix login redirect bug", &candidates, 0.5, 1).len(),
+            1
+        );
+        assert!(find_duplicates("\n", &candidates, 0.0, 3).is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+use chrono::{DateTime, Utc};
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use todoki_protocol::SessionMode;
+use todoki_protocol::event_bus::{RelaySpawnRequestedData, ResourceUsage};
+use uuid::Uuid;
+
+use super::agent::SessionStatus;
+
+/// Substrings marking a command-line flag as taking a secret
+const SECRET_MARKERS: &[&str] = &["token", "secret", "password", "passwd", "api_key", "apikey"];
+
+const REDACTED: &str = "[redacted]";
+
+/// What the relay was asked to run, with secrets removed
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct ExecutionSpawnParams {
+    pub command: String,
+    pub args: Vec<String>,
+    pub workdir: String,
+    pub mode: SessionMode,
+    /// Names of the extra environment variables; values are never returned
+    pub env_keys: Vec<String>,
+}
+
+impl From<&RelaySpawnRequestedData> for ExecutionSpawnParams {
+    fn from(spawn: &RelaySpawnRequestedData) -> Self {
+        let mut env_keys: Vec<String> = spawn.env.keys().cloned().collect();
+        env_keys.sort();
+        Self {
+            command: spawn.command.clone(),
+            args: redact_args(&spawn.args),
+            workdir: spawn.workdir.clone(),
+            mode: spawn.mode,
+            env_keys,
+        }
+    }
+}
+
+/// A permission request raised during an execution and how it was answered
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct ExecutionPermission {
+    pub request_id: String,
+    pub tool_title: String,
+    pub requested_at: DateTime<Utc>,
+    /// Name of the selected option, "cancelled", "expired", or `None` while
+    /// unanswered
+    pub decision: Option<String>,
+    /// Kind of the selected option (e.g. "allow_once", "reject_always")
+    pub decision_kind: Option<String>,
+    pub responded_at: Option<DateTime<Utc>>,
+}
+
+/// One session ever spawned for a task
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct TaskExecution {
+    pub session_id: Uuid,
+    pub agent_id: Uuid,
+    pub agent_name: Option<String>,
+    pub relay_id: String,
+    pub requested_at: DateTime<Utc>,
+    /// `None` when the session row no longer exists
+    pub status: Option<SessionStatus>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub duration_secs: Option<i64>,
+    pub exit_code: Option<i32>,
+    /// Why the relay could not spawn the session
+    pub spawn_error: Option<String>,
+    pub spawn: ExecutionSpawnParams,
+    pub artifact_count: i64,
+    pub permissions: Vec<ExecutionPermission>,
+    pub resource_usage: Option<ResourceUsage>,
+}
+
+/// Every execution of a task, oldest first
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct TaskExecutionHistory {
+    pub task_id: Uuid,
+    pub executions: Vec<TaskExecution>,
+}
+
+fn is_secret_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    SECRET_MARKERS.iter().any(|marker| name.contains(marker))
+}
+
+/// Hide the values of secret-looking flags (`--token x`, `--api-key=x`)
+fn redact_args(args: &[String]) -> Vec<String> {
+    let mut redacted = Vec::with_capacity(args.len());
+    let mut hide_next = false;
+    for arg in args {
+        if std::mem::take(&mut hide_next) && !arg.starts_with('-') {
+            redacted.push(REDACTED.to_string());
+            continue;
+        }
+        match arg.split_once('=') {
+            Some((flag, _)) if flag.starts_with('-') && is_secret_name(&flag.replace('-', "_")) => {
+                redacted.push(format!("{}={}", flag, REDACTED));
+            }
+            Some(_) => redacted.push(arg.clone()),
+            None => {
+                hide_next = arg.starts_with('-') && is_secret_name(&arg.replace('-', "_"));
+                redacted.push(arg.clone());
+            }
+        }
+    }
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_redact_secret_flags() {
+        let redacted = redact_args(&args(&[
+            "--model",
+            "opus",
+            "--api-key",
+            "sk-123",
+            "--github-token=ghp_abc",
+            "--verbose",
+            "notes.md",
+        ]));
+        assert_eq!(
+            redacted,
+            args(&[
+                "--model",
+                "opus",
+                "--api-key",
+                "[redacted]",
+                "--github-token=[redacted]",
+                "--verbose",
+                "notes.md",
+            ])
+        );
+    }
+
+    #[test]
+    fn test_secret_flag_without_value_keeps_next_flag() {
+        let redacted = redact_args(&args(&["--no-password", "--print"]));
+        assert_eq!(redacted, args(&["--no-password", "--print"]));
+    }
+}
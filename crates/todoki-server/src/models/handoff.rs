@@ -0,0 +1,99 @@
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use validator::Validate;
+
+use super::agent::{AgentResponse, AgentRole, AgentSessionResponse};
+use super::artifact::ArtifactResponse;
+
+/// Artifact type of stored handoff contexts
+pub const HANDOFF_ARTIFACT_TYPE: &str = "handoff";
+
+/// Placeholder a role template can use to place the handoff context itself
+pub const HANDOFF_PLACEHOLDER: &str = "{{handoff_context}}";
+
+/// `POST /api/tasks/:task_id/handoff` body
+#[derive(Debug, Clone, Deserialize, Validate, Schematic)]
+pub struct HandoffRequest {
+    /// Role of the agent taking over
+    pub role: AgentRole,
+    /// What the next stage needs to know, e.g. `{"summary": "...", "branch": "..."}`
+    #[validate(custom(function = "crate::api::validation::json_object"))]
+    pub context: Value,
+    /// Relay to run the next stage on; overrides the project's routing
+    pub relay_id: Option<String>,
+    /// Label selector the relay must match; overrides the project's routing
+    pub relay_selector: Option<String>,
+}
+
+#[derive(Debug, Serialize, Schematic)]
+pub struct HandoffResponse {
+    /// The stored `handoff` artifact
+    pub artifact: ArtifactResponse,
+    /// Agent running the next stage
+    pub agent: AgentResponse,
+    pub session: AgentSessionResponse,
+}
+
+/// Data of a `handoff` artifact
+pub fn handoff_artifact_data(
+    from_role: Option<AgentRole>,
+    to_role: AgentRole,
+    context: &Value,
+) -> Value {
+    json!({
+        "from_role": from_role,
+        "to_role": to_role,
+        "context": context,
+    })
+}
+
+/// Put `context` into a rendered stage prompt
+///
+/// The context goes where the template placed [`HANDOFF_PLACEHOLDER`], or
+/// into a section appended to the prompt.
+pub fn inject_handoff(prompt: &str, context: Option<&Value>) -> String {
+    let Some(context) = context else {
+        return prompt.replace(HANDOFF_PLACEHOLDER, "");
+    };
+    let context = serde_json::to_string_pretty(context).unwrap_or_default();
+    if prompt.contains(HANDOFF_PLACEHOLDER) {
+        prompt.replace(HANDOFF_PLACEHOLDER, &context)
+    } else {
+        format!(
+            "{}\n## Handoff From The Previous Stage\n```json\n{}\n```\n",
+            prompt, context
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inject_handoff() {
+        let context = json!({"summary": "tests pass"});
+
+        let appended = inject_handoff("# Task\nFix it\n", Some(&context));
+        assert!(appended.starts_with("# Task\nFix it\n\n## Handoff From The Previous Stage\n"));
+        assert!(appended.contains("\"summary\": \"tests pass\""));
+
+        let placed = inject_handoff("Before:\n{{handoff_context}}\nGo", Some(&context));
+        assert!(placed.starts_with("Before:\n{\n"));
+        assert!(!placed.contains("Handoff From"));
+
+        assert_eq!(inject_handoff("A{{handoff_context}}B", None), "AB");
+    }
+
+    #[test]
+    fn test_request_requires_object_context() {
+        let request: HandoffRequest =
+            serde_json::from_value(json!({"role": "qa", "context": "done"})).unwrap();
+        assert!(request.validate().is_err());
+
+        let request: HandoffRequest =
+            serde_json::from_value(json!({"role": "qa", "context": {"pr": 3}})).unwrap();
+        assert!(request.validate().is_ok());
+    }
+}
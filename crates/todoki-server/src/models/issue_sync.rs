@@ -0,0 +1,232 @@
+use chrono::{DateTime, Utc};
+use conservator::{Domain, TextEnum};
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::task::TaskStatus;
+
+/// Issue tracker tasks can be mirrored into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Schematic, TextEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueProvider {
+    Jira,
+    Linear,
+}
+
+/// Coarse status both sides of a sync can express
+///
+/// Tasks have many statuses and trackers have per-project workflows; only
+/// moves between categories are mirrored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Schematic, TextEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusCategory {
+    Todo,
+    InProgress,
+    Done,
+}
+
+impl StatusCategory {
+    pub fn of_task(status: TaskStatus) -> Self {
+        match status {
+            TaskStatus::Backlog | TaskStatus::Todo => Self::Todo,
+            TaskStatus::Done => Self::Done,
+            _ => Self::InProgress,
+        }
+    }
+
+    /// Status a task moves to when its issue enters this category
+    pub fn task_status(self) -> TaskStatus {
+        match self {
+            Self::Todo => TaskStatus::Todo,
+            Self::InProgress => TaskStatus::CodingInProgress,
+            Self::Done => TaskStatus::Done,
+        }
+    }
+
+    /// Category of a Jira status category key
+    pub fn from_jira(key: &str) -> Option<Self> {
+        match key {
+            "new" => Some(Self::Todo),
+            "indeterminate" => Some(Self::InProgress),
+            "done" => Some(Self::Done),
+            _ => None,
+        }
+    }
+
+    pub fn jira_key(self) -> &'static str {
+        match self {
+            Self::Todo => "new",
+            Self::InProgress => "indeterminate",
+            Self::Done => "done",
+        }
+    }
+
+    /// Category of a Linear workflow state type; canceled issues count as done
+    pub fn from_linear(state_type: &str) -> Option<Self> {
+        match state_type {
+            "triage" | "backlog" | "unstarted" => Some(Self::Todo),
+            "started" => Some(Self::InProgress),
+            "completed" | "canceled" => Some(Self::Done),
+            _ => None,
+        }
+    }
+
+    /// Linear state type an issue is moved to for this category
+    pub fn linear_type(self) -> &'static str {
+        match self {
+            Self::Todo => "unstarted",
+            Self::InProgress => "started",
+            Self::Done => "completed",
+        }
+    }
+}
+
+/// What to do to bring a task and its issue back in line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncAction {
+    /// Both sides are where they were at the last sync
+    None,
+    /// Both sides moved to the same category; only the record is stale
+    Record(StatusCategory),
+    /// The task moved; move the issue after it
+    Push(StatusCategory),
+    /// The issue moved; move the task after it
+    Pull(StatusCategory),
+}
+
+impl SyncAction {
+    /// Compare both sides with the category they agreed on last. When both
+    /// moved to different categories, the task wins.
+    pub fn plan(synced: StatusCategory, task: StatusCategory, issue: StatusCategory) -> Self {
+        match (task != synced, issue != synced) {
+            (false, false) => Self::None,
+            (_, _) if task == issue => Self::Record(task),
+            (true, _) => Self::Push(task),
+            (false, true) => Self::Pull(issue),
+        }
+    }
+}
+
+/// Project setting for mirroring tasks into an issue tracker
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct ProjectIssueSync {
+    /// Tracker to mirror into; unset turns the sync off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<IssueProvider>,
+    /// Jira project key (e.g. `OPS`) or Linear team id issues are created in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Jira issue type of created issues (default `Task`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issue_type: Option<String>,
+}
+
+impl ProjectIssueSync {
+    /// Whether nothing is configured
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Check the setting is usable; returns a message for the first problem
+    pub fn validate(&self) -> Result<(), String> {
+        let target = self.target.as_deref().unwrap_or("").trim();
+        match self.provider {
+            Some(IssueProvider::Jira) if target.is_empty() => {
+                Err("Jira sync needs the project key as target".to_string())
+            }
+            Some(IssueProvider::Linear) if target.is_empty() => {
+                Err("Linear sync needs the team id as target".to_string())
+            }
+            None if !self.is_empty() => Err("issue sync needs a provider".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The tracker issue a task is mirrored to
+#[derive(Debug, Clone, Serialize, Deserialize, Domain)]
+#[domain(table = "task_remote_issues")]
+pub struct TaskRemoteIssue {
+    #[domain(primary_key)]
+    pub task_id: Uuid,
+    pub provider: IssueProvider,
+    /// Tracker's internal id (Jira issue id, Linear issue UUID)
+    pub remote_id: String,
+    /// Human-readable key, e.g. `OPS-42` or `ENG-7`
+    pub remote_key: String,
+    pub url: String,
+    /// Category task and issue were both in at the last sync
+    pub synced_category: StatusCategory,
+    pub synced_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Tracker issue shown on a task
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct RemoteIssueResponse {
+    pub provider: IssueProvider,
+    pub key: String,
+    pub url: String,
+}
+
+impl From<TaskRemoteIssue> for RemoteIssueResponse {
+    fn from(issue: TaskRemoteIssue) -> Self {
+        Self {
+            provider: issue.provider,
+            key: issue.remote_key,
+            url: issue.url,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use StatusCategory::*;
+
+    #[test]
+    fn test_sync_plan() {
+        assert_eq!(SyncAction::plan(Todo, Todo, Todo), SyncAction::None);
+        assert_eq!(
+            SyncAction::plan(Todo, InProgress, Todo),
+            SyncAction::Push(InProgress)
+        );
+        assert_eq!(SyncAction::plan(Todo, Todo, Done), SyncAction::Pull(Done));
+        assert_eq!(SyncAction::plan(Todo, Done, Done), SyncAction::Record(Done));
+        // Conflicting moves: the task wins
+        assert_eq!(
+            SyncAction::plan(Todo, InProgress, Done),
+            SyncAction::Push(InProgress)
+        );
+    }
+
+    #[test]
+    fn test_status_categories() {
+        assert_eq!(StatusCategory::of_task(TaskStatus::Backlog), Todo);
+        assert_eq!(StatusCategory::of_task(TaskStatus::PlanReview), InProgress);
+        assert_eq!(StatusCategory::from_linear("canceled"), Some(Done));
+        assert_eq!(StatusCategory::from_jira(Done.jira_key()), Some(Done));
+        for category in [Todo, InProgress, Done] {
+            assert_eq!(StatusCategory::of_task(category.task_status()), category);
+            assert_eq!(
+                StatusCategory::from_linear(category.linear_type()),
+                Some(category)
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(ProjectIssueSync::default().validate().is_ok());
+        let mut sync = ProjectIssueSync {
+            provider: Some(IssueProvider::Jira),
+            ..Default::default()
+        };
+        assert!(sync.validate().is_err());
+        sync.target = Some("OPS".to_string());
+        assert!(sync.validate().is_ok());
+        sync.provider = None;
+        assert!(sync.validate().is_err());
+    }
+}
@@ -1,11 +1,50 @@
+pub mod activity;
 pub mod agent;
 pub mod artifact;
+pub mod backup;
+pub mod dead_letter;
+pub mod dedup;
+pub mod execution;
+pub mod handoff;
+pub mod issue_sync;
+pub mod notification;
 pub mod project;
+pub mod quota;
+pub mod rank;
 pub mod report;
+pub mod schedule;
+pub mod scoreboard;
+pub mod status_page;
 pub mod task;
+pub mod task_context;
+pub mod template;
+pub mod time_entry;
+pub mod undo;
+pub mod view;
+pub mod watch;
+pub mod workflow;
 
+pub use activity::*;
 pub use agent::*;
 pub use artifact::*;
+pub use backup::*;
+pub use dead_letter::*;
+pub use dedup::*;
+pub use execution::*;
+pub use handoff::*;
+pub use issue_sync::*;
+pub use notification::*;
 pub use project::*;
+pub use quota::*;
 pub use report::*;
+pub use schedule::*;
+pub use scoreboard::*;
+pub use status_page::*;
 pub use task::*;
+pub use task_context::*;
+pub use template::*;
+pub use time_entry::*;
+pub use undo::*;
+pub use view::*;
+pub use watch::*;
+pub use workflow::*;
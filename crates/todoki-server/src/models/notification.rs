@@ -0,0 +1,248 @@
+use chrono::{DateTime, Utc};
+use conservator::{Creatable, Domain, TextEnum};
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Most notifications listed at once
+pub const MAX_NOTIFICATION_LIMIT: i64 = 200;
+
+// ============================================================================
+// Notification Kind
+// ============================================================================
+
+/// What a notification is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Schematic, TextEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// `task.assigned`
+    TaskAssigned,
+    /// `permission.requested` left to a human
+    PermissionPending,
+    /// `agent.session_exited` with a failure, or `relay.spawn_failed`
+    SessionFailed,
+    /// `task.mention` naming the user
+    Mentioned,
+    /// A task the user watches changed status, was handed over or got an
+    /// agent comment
+    WatchedTaskChanged,
+}
+
+impl NotificationKind {
+    pub const ALL: [NotificationKind; 5] = [
+        NotificationKind::TaskAssigned,
+        NotificationKind::PermissionPending,
+        NotificationKind::SessionFailed,
+        NotificationKind::Mentioned,
+        NotificationKind::WatchedTaskChanged,
+    ];
+}
+
+// ============================================================================
+// Notification
+// ============================================================================
+
+/// An entry in a user's notification center
+#[derive(Debug, Clone, Domain)]
+#[domain(table = "notifications")]
+pub struct Notification {
+    #[domain(primary_key)]
+    pub id: Uuid,
+    /// Token ID of the recipient, e.g. `user` or `oidc:<subject>`
+    pub user_id: String,
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: String,
+    pub task_id: Option<Uuid>,
+    pub session_id: Option<Uuid>,
+    /// Cursor of the event that caused it
+    pub event_cursor: i64,
+    pub created_at: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Creatable)]
+pub struct CreateNotification {
+    pub user_id: String,
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: String,
+    pub task_id: Option<Uuid>,
+    pub session_id: Option<Uuid>,
+    pub event_cursor: i64,
+    pub created_at: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+/// A notification before it is addressed to its recipients
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotificationDraft {
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: String,
+    pub task_id: Option<Uuid>,
+    pub session_id: Option<Uuid>,
+    pub event_cursor: i64,
+    /// Only these users are notified (`None` = everyone who wants the kind)
+    pub addressees: Option<Vec<String>>,
+}
+
+impl NotificationDraft {
+    pub fn to(&self, user_id: &str) -> CreateNotification {
+        CreateNotification {
+            user_id: user_id.to_string(),
+            kind: self.kind,
+            title: self.title.clone(),
+            body: self.body.clone(),
+            task_id: self.task_id,
+            session_id: self.session_id,
+            event_cursor: self.event_cursor,
+            created_at: Utc::now(),
+            read_at: None,
+        }
+    }
+}
+
+// ============================================================================
+// Notification Preferences
+// ============================================================================
+
+/// Which kinds a user is notified of
+///
+/// Users get a row the first time they open their notification center; the
+/// shared-token user is notified without one.
+#[derive(Debug, Clone, Domain)]
+#[domain(table = "notification_preferences")]
+pub struct NotificationPreferences {
+    #[domain(primary_key)]
+    pub user_id: String,
+    /// Kinds turned off, as a JSON array; new kinds start out on
+    pub muted: Value,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl NotificationPreferences {
+    pub fn muted(&self) -> Vec<NotificationKind> {
+        serde_json::from_value(self.muted.clone()).unwrap_or_default()
+    }
+
+    pub fn allows(&self, kind: NotificationKind) -> bool {
+        !self.muted().contains(&kind)
+    }
+}
+
+/// Muted kinds for a user who wants exactly `enabled`
+pub fn muted_kinds(enabled: &[NotificationKind]) -> Value {
+    let muted: Vec<NotificationKind> = NotificationKind::ALL
+        .into_iter()
+        .filter(|kind| !enabled.contains(kind))
+        .collect();
+    serde_json::to_value(muted).unwrap_or_default()
+}
+
+// ============================================================================
+// API DTOs
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct NotificationResponse {
+    pub id: Uuid,
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<Uuid>,
+    pub event_cursor: i64,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+impl From<Notification> for NotificationResponse {
+    fn from(n: Notification) -> Self {
+        Self {
+            id: n.id,
+            kind: n.kind,
+            title: n.title,
+            body: n.body,
+            task_id: n.task_id,
+            session_id: n.session_id,
+            event_cursor: n.event_cursor,
+            created_at: n.created_at,
+            read_at: n.read_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct NotificationListResponse {
+    /// Newest first
+    pub items: Vec<NotificationResponse>,
+    pub unread: i64,
+}
+
+/// `GET /api/notifications` query parameters
+#[derive(Debug, Clone, Default, Deserialize, Schematic)]
+pub struct NotificationListQuery {
+    /// Only unread notifications
+    #[serde(default)]
+    pub unread: bool,
+    /// Default and maximum 200
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct NotificationReadResponse {
+    /// Notifications marked read by this request
+    pub marked: u64,
+    pub unread: i64,
+}
+
+/// Notification kinds a user receives
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct NotificationPreferencesResponse {
+    pub enabled: Vec<NotificationKind>,
+    /// Kinds the server generates at all (`notifications.kinds`)
+    pub available: Vec<NotificationKind>,
+}
+
+#[derive(Debug, Clone, Deserialize, Schematic)]
+pub struct NotificationPreferencesRequest {
+    /// Replaces the enabled kinds
+    pub enabled: Vec<NotificationKind>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preferences() {
+        let prefs = NotificationPreferences {
+            user_id: "oidc:alice".to_string(),
+            muted: muted_kinds(&[NotificationKind::SessionFailed]),
+            updated_at: Utc::now(),
+        };
+        assert_eq!(
+            prefs.muted,
+            serde_json::json!([
+                "task_assigned",
+                "permission_pending",
+                "mentioned",
+                "watched_task_changed"
+            ])
+        );
+        assert!(prefs.allows(NotificationKind::SessionFailed));
+        assert!(!prefs.allows(NotificationKind::TaskAssigned));
+
+        // Unreadable preferences mute nothing
+        let prefs = NotificationPreferences {
+            muted: serde_json::json!("garbage"),
+            ..prefs
+        };
+        assert!(prefs.allows(NotificationKind::TaskAssigned));
+    }
+}
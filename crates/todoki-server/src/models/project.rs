@@ -2,7 +2,16 @@ use chrono::{DateTime, Utc};
 use conservator::{Creatable, Domain};
 use gotcha::Schematic;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use todoki_protocol::event_bus::RiskLevel;
 use uuid::Uuid;
+use validator::Validate;
+
+use super::agent::{AgentResponse, AgentRole};
+use super::issue_sync::ProjectIssueSync;
+use super::schedule::ExecutionSchedule;
+use super::workflow::Workflow;
+use crate::relay::LabelSelector;
 
 // ============================================================================
 // Project
@@ -24,6 +33,75 @@ pub struct Project {
     pub business_template: Option<String>,
     pub coding_template: Option<String>,
     pub qa_template: Option<String>,
+    /// Execution windows as JSON (NULL = agents may run at any time)
+    pub execution_schedule: Option<Value>,
+    /// Per-role relay routing as JSON (NULL = any eligible relay)
+    pub relay_routing: Option<Value>,
+    /// Permission reviewer overrides as JSON (NULL = global settings)
+    pub review_config: Option<Value>,
+    /// Task states, transitions and priority scale as JSON (NULL = built-in)
+    pub workflow: Option<Value>,
+    /// Jira / Linear issue sync as JSON (NULL = not synced)
+    pub issue_sync: Option<Value>,
+}
+
+impl Project {
+    /// Parsed execution windows; `None` when unrestricted
+    pub fn schedule(&self) -> Option<ExecutionSchedule> {
+        self.execution_schedule
+            .clone()
+            .and_then(|v| serde_json::from_value(v).ok())
+    }
+
+    /// Parsed relay routing; `None` when not configured
+    pub fn relay_routing(&self) -> Option<RelayRouting> {
+        self.relay_routing
+            .clone()
+            .and_then(|v| serde_json::from_value(v).ok())
+    }
+
+    /// Parsed permission reviewer overrides; `None` when not configured
+    pub fn review_config(&self) -> Option<ProjectReviewConfig> {
+        self.review_config
+            .clone()
+            .and_then(|v| serde_json::from_value(v).ok())
+    }
+
+    /// Custom workflow; `None` when the project uses the built-in one
+    pub fn custom_workflow(&self) -> Option<Workflow> {
+        self.workflow
+            .clone()
+            .and_then(|v| serde_json::from_value(v).ok())
+    }
+
+    /// Parsed issue tracker sync; `None` when the project is not synced
+    pub fn issue_sync(&self) -> Option<ProjectIssueSync> {
+        self.issue_sync
+            .clone()
+            .and_then(|v| serde_json::from_value::<ProjectIssueSync>(v).ok())
+            .filter(|sync| sync.provider.is_some())
+    }
+
+    /// The workflow tasks of this project follow
+    pub fn workflow(&self) -> Workflow {
+        self.custom_workflow().unwrap_or_else(Workflow::builtin)
+    }
+
+    /// Where agents of `role` should be spawned for this project
+    pub fn relay_route(&self, role: AgentRole) -> Option<RelayRoute> {
+        self.relay_routing()
+            .and_then(|routing| routing.for_role(role).cloned())
+    }
+
+    /// Inline execution template for `role`, if the project sets one
+    pub fn template_for_role(&self, role: AgentRole) -> Option<&str> {
+        match role {
+            AgentRole::General => self.general_template.as_deref(),
+            AgentRole::Business => self.business_template.as_deref(),
+            AgentRole::Coding => self.coding_template.as_deref(),
+            AgentRole::Qa => self.qa_template.as_deref(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Creatable)]
@@ -38,6 +116,11 @@ pub struct CreateProject {
     pub business_template: Option<String>,
     pub coding_template: Option<String>,
     pub qa_template: Option<String>,
+    pub execution_schedule: Option<Value>,
+    pub relay_routing: Option<Value>,
+    pub review_config: Option<Value>,
+    pub workflow: Option<Value>,
+    pub issue_sync: Option<Value>,
 }
 
 impl CreateProject {
@@ -54,10 +137,134 @@ impl CreateProject {
             business_template: None,
             coding_template: None,
             qa_template: None,
+            execution_schedule: None,
+            relay_routing: None,
+            review_config: None,
+            workflow: None,
+            issue_sync: None,
         }
     }
 }
 
+/// Where to spawn agents of one role
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct RelayRoute {
+    /// Preferred relay; used when connected, eligible and not full
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relay_id: Option<String>,
+    /// Label selector every candidate relay must match (e.g. `gpu=true`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+}
+
+impl RelayRoute {
+    /// Parsed selector; invalid selectors are rejected when saved
+    pub fn label_selector(&self) -> Option<LabelSelector> {
+        self.selector
+            .as_deref()
+            .and_then(|s| LabelSelector::parse(s).ok())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.relay_id.is_none() && self.selector.is_none()
+    }
+}
+
+/// Per-role relay routing for a project
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct RelayRouting {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub general: Option<RelayRoute>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub business: Option<RelayRoute>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coding: Option<RelayRoute>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qa: Option<RelayRoute>,
+}
+
+impl RelayRouting {
+    pub fn for_role(&self, role: AgentRole) -> Option<&RelayRoute> {
+        match role {
+            AgentRole::General => self.general.as_ref(),
+            AgentRole::Business => self.business.as_ref(),
+            AgentRole::Coding => self.coding.as_ref(),
+            AgentRole::Qa => self.qa.as_ref(),
+        }
+    }
+
+    /// Whether no role has a route
+    pub fn is_empty(&self) -> bool {
+        [&self.general, &self.business, &self.coding, &self.qa]
+            .iter()
+            .all(|route| route.as_ref().is_none_or(RelayRoute::is_empty))
+    }
+
+    /// Check every selector parses; returns a message for the first problem
+    pub fn validate(&self) -> Result<(), String> {
+        let routes = [
+            ("general", &self.general),
+            ("business", &self.business),
+            ("coding", &self.coding),
+            ("qa", &self.qa),
+        ];
+        for (role, route) in routes {
+            if let Some(selector) = route.as_ref().and_then(|r| r.selector.as_deref()) {
+                LabelSelector::parse(selector)
+                    .map_err(|e| format!("invalid {} relay selector: {}", role, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Project overrides of the `auto_review` settings for permission reviews
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct ProjectReviewConfig {
+    /// Review this project's requests (default: `auto_review.enabled`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Model asked instead of `auto_review.model`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Extra rules appended to the reviewer's instructions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guidance: Option<String>,
+    /// Highest risk the reviewer may allow on its own; riskier allows are
+    /// left to a human
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_max_risk: Option<RiskLevel>,
+    /// Lowest risk the reviewer may deny on its own; denials of safer calls
+    /// are left to a human
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deny_min_risk: Option<RiskLevel>,
+}
+
+impl ProjectReviewConfig {
+    /// Whether nothing is overridden
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Check the overrides make sense; returns a message for the first problem
+    pub fn validate(&self) -> Result<(), String> {
+        if self.model.as_deref().is_some_and(|m| m.trim().is_empty()) {
+            return Err("review model must not be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// What archiving or restoring a project cascaded to
+#[derive(Debug, Clone)]
+pub struct ProjectCascade {
+    pub project: Project,
+    /// Tasks archived (or unarchived) along with the project
+    pub task_ids: Vec<Uuid>,
+    /// Artifacts hidden (or shown again)
+    pub artifacts: i64,
+}
+
 // ============================================================================
 // API DTOs
 // ============================================================================
@@ -79,10 +286,27 @@ pub struct ProjectResponse {
     pub coding_template: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub qa_template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution_schedule: Option<ExecutionSchedule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relay_routing: Option<RelayRouting>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub review_config: Option<ProjectReviewConfig>,
+    /// Custom workflow; absent when the project uses the built-in statuses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workflow: Option<Workflow>,
+    /// Jira / Linear issue sync; absent when the project is not synced
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issue_sync: Option<ProjectIssueSync>,
 }
 
 impl From<Project> for ProjectResponse {
     fn from(p: Project) -> Self {
+        let execution_schedule = p.schedule();
+        let relay_routing = p.relay_routing();
+        let review_config = p.review_config();
+        let workflow = p.custom_workflow();
+        let issue_sync = p.issue_sync();
         Self {
             id: p.id,
             name: p.name,
@@ -95,25 +319,94 @@ impl From<Project> for ProjectResponse {
             business_template: p.business_template,
             coding_template: p.coding_template,
             qa_template: p.qa_template,
+            execution_schedule,
+            relay_routing,
+            review_config,
+            workflow,
+            issue_sync,
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Schematic)]
+/// Result of archiving or restoring a project
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct ProjectCascadeResponse {
+    pub project: ProjectResponse,
+    /// Tasks archived or restored with the project
+    pub tasks: i64,
+    /// Running agents stopped (archiving only)
+    pub stopped_agents: i64,
+    /// Artifacts hidden or shown again
+    pub artifacts: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Schematic, Validate)]
 pub struct ProjectCreateRequest {
+    #[validate(
+        length(min = 1, max = "crate::api::validation::MAX_NAME_LEN"),
+        custom(function = "crate::api::validation::not_blank")
+    )]
     pub name: String,
+    #[validate(length(max = "crate::api::validation::MAX_DESCRIPTION_LEN"))]
     pub description: Option<String>,
+    #[validate(custom(function = "crate::api::validation::hex_color"))]
     pub color: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize, Schematic)]
+#[derive(Debug, Clone, Deserialize, Schematic, Validate)]
+pub struct ProjectCloneRequest {
+    /// Name of the new project
+    #[validate(
+        length(min = 1, max = "crate::api::validation::MAX_NAME_LEN"),
+        custom(function = "crate::api::validation::not_blank")
+    )]
+    pub name: String,
+    /// Defaults to the source project's description
+    #[validate(length(max = "crate::api::validation::MAX_DESCRIPTION_LEN"))]
+    pub description: Option<String>,
+    /// Defaults to the source project's color
+    #[validate(custom(function = "crate::api::validation::hex_color"))]
+    pub color: Option<String>,
+    /// Also copy the source project's agent definitions
+    #[serde(default)]
+    pub include_agents: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct ProjectCloneResponse {
+    pub project: ProjectResponse,
+    /// Agents copied into the new project
+    pub agents: Vec<AgentResponse>,
+}
+
+#[derive(Debug, Clone, Deserialize, Schematic, Validate)]
 pub struct ProjectUpdateRequest {
+    #[validate(
+        length(min = 1, max = "crate::api::validation::MAX_NAME_LEN"),
+        custom(function = "crate::api::validation::not_blank")
+    )]
     pub name: Option<String>,
+    #[validate(length(max = "crate::api::validation::MAX_DESCRIPTION_LEN"))]
     pub description: Option<String>,
+    #[validate(custom(function = "crate::api::validation::hex_color"))]
     pub color: Option<String>,
     pub archived: Option<bool>,
+    #[validate(length(max = "crate::api::validation::MAX_TEMPLATE_LEN"))]
     pub general_template: Option<String>,
+    #[validate(length(max = "crate::api::validation::MAX_TEMPLATE_LEN"))]
     pub business_template: Option<String>,
+    #[validate(length(max = "crate::api::validation::MAX_TEMPLATE_LEN"))]
     pub coding_template: Option<String>,
+    #[validate(length(max = "crate::api::validation::MAX_TEMPLATE_LEN"))]
     pub qa_template: Option<String>,
+    /// Execution windows; an empty `windows` list removes the restriction
+    pub execution_schedule: Option<ExecutionSchedule>,
+    /// Per-role relay routing; an object without routes removes it
+    pub relay_routing: Option<RelayRouting>,
+    /// Permission reviewer overrides; an empty object removes them
+    pub review_config: Option<ProjectReviewConfig>,
+    /// Task workflow; an empty object restores the built-in statuses
+    pub workflow: Option<Workflow>,
+    /// Jira / Linear issue sync; an empty object turns it off
+    pub issue_sync: Option<ProjectIssueSync>,
 }
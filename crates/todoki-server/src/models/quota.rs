@@ -0,0 +1,215 @@
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// ============================================================================
+// Quotas
+// ============================================================================
+
+/// A resource a project's use of is limited
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Schematic)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaMetric {
+    /// Agent sessions running at once
+    ConcurrentSessions,
+    /// Events emitted through the API in the last hour
+    EventsPerHour,
+    /// Bytes of artifact data, email attachments included
+    StorageBytes,
+    /// Permission auto-reviews this calendar month
+    AiReviewsPerMonth,
+}
+
+impl QuotaMetric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaMetric::ConcurrentSessions => "concurrent_sessions",
+            QuotaMetric::EventsPerHour => "events_per_hour",
+            QuotaMetric::StorageBytes => "storage_bytes",
+            QuotaMetric::AiReviewsPerMonth => "ai_reviews_per_month",
+        }
+    }
+}
+
+/// A limit for each metric; `None` is unlimited, or in overrides, the default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Schematic)]
+pub struct QuotaLimits {
+    #[serde(default)]
+    pub max_concurrent_sessions: Option<u64>,
+    #[serde(default)]
+    pub events_per_hour: Option<u64>,
+    #[serde(default)]
+    pub storage_bytes: Option<u64>,
+    #[serde(default)]
+    pub ai_reviews_per_month: Option<u64>,
+}
+
+impl QuotaLimits {
+    /// These limits with those set in `overrides` replacing them
+    pub fn overridden(self, overrides: &QuotaLimits) -> Self {
+        Self {
+            max_concurrent_sessions: overrides
+                .max_concurrent_sessions
+                .or(self.max_concurrent_sessions),
+            events_per_hour: overrides.events_per_hour.or(self.events_per_hour),
+            storage_bytes: overrides.storage_bytes.or(self.storage_bytes),
+            ai_reviews_per_month: overrides.ai_reviews_per_month.or(self.ai_reviews_per_month),
+        }
+    }
+
+    pub fn get(&self, metric: QuotaMetric) -> Option<u64> {
+        match metric {
+            QuotaMetric::ConcurrentSessions => self.max_concurrent_sessions,
+            QuotaMetric::EventsPerHour => self.events_per_hour,
+            QuotaMetric::StorageBytes => self.storage_bytes,
+            QuotaMetric::AiReviewsPerMonth => self.ai_reviews_per_month,
+        }
+    }
+}
+
+/// A project's overrides of the configured quota defaults
+#[derive(Debug, Clone)]
+pub struct ProjectQuotas {
+    pub project_id: Uuid,
+    pub overrides: QuotaLimits,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Use of one quota
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct QuotaUsage {
+    pub metric: QuotaMetric,
+    pub used: u64,
+    /// Absent when unlimited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+    /// Nothing more is allowed until use drops or the period ends
+    pub exceeded: bool,
+}
+
+impl QuotaUsage {
+    pub fn new(metric: QuotaMetric, used: u64, limit: Option<u64>) -> Self {
+        Self {
+            metric,
+            used,
+            limit,
+            exceeded: !allows(used, 1, limit),
+        }
+    }
+}
+
+/// Whether `added` more fits next to `used` under `limit`
+pub fn allows(used: u64, added: u64, limit: Option<u64>) -> bool {
+    limit.is_none_or(|limit| used.saturating_add(added) <= limit)
+}
+
+/// A request refused because it would go over a quota
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuotaExceeded {
+    pub metric: QuotaMetric,
+    pub used: u64,
+    pub limit: u64,
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "project quota {} exceeded ({} used of {})",
+            self.metric.as_str(),
+            self.used,
+            self.limit
+        )
+    }
+}
+
+/// Start of the calendar month (UTC) `time` falls in
+pub fn month_start(time: DateTime<Utc>) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(time.year(), time.month(), 1, 0, 0, 0)
+        .single()
+        .unwrap_or(time)
+}
+
+/// Start of the calendar month after the one `time` falls in
+pub fn next_month_start(time: DateTime<Utc>) -> DateTime<Utc> {
+    let (year, month) = match time.month() {
+        12 => (time.year() + 1, 1),
+        month => (time.year(), month + 1),
+    };
+    Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0)
+        .single()
+        .unwrap_or(time)
+}
+
+// ============================================================================
+// API DTOs
+// ============================================================================
+
+/// `GET /api/projects/:project_id/quotas` response
+#[derive(Debug, Clone, Serialize, Schematic)]
+pub struct QuotaStatus {
+    pub project_id: Uuid,
+    /// Limits set for this project; unset ones use the defaults
+    pub overrides: QuotaLimits,
+    /// One entry per metric, with the limit in effect
+    pub quotas: Vec<QuotaUsage>,
+    /// When the monthly AI review count starts over
+    pub ai_reviews_reset_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overridden() {
+        let defaults = QuotaLimits {
+            max_concurrent_sessions: Some(2),
+            events_per_hour: Some(1000),
+            storage_bytes: None,
+            ai_reviews_per_month: Some(100),
+        };
+        let overrides = QuotaLimits {
+            events_per_hour: Some(50),
+            storage_bytes: Some(1 << 20),
+            ..Default::default()
+        };
+
+        let limits = defaults.overridden(&overrides);
+        assert_eq!(limits.get(QuotaMetric::ConcurrentSessions), Some(2));
+        assert_eq!(limits.get(QuotaMetric::EventsPerHour), Some(50));
+        assert_eq!(limits.get(QuotaMetric::StorageBytes), Some(1 << 20));
+        assert_eq!(limits.get(QuotaMetric::AiReviewsPerMonth), Some(100));
+        assert_eq!(defaults.overridden(&QuotaLimits::default()), defaults);
+    }
+
+    #[test]
+    fn test_usage() {
+        assert!(allows(9, 1, Some(10)));
+        assert!(!allows(9, 2, Some(10)));
+        assert!(allows(u64::MAX, 1, None));
+
+        assert!(!QuotaUsage::new(QuotaMetric::EventsPerHour, 9, Some(10)).exceeded);
+        assert!(QuotaUsage::new(QuotaMetric::EventsPerHour, 10, Some(10)).exceeded);
+        assert!(QuotaUsage::new(QuotaMetric::StorageBytes, 0, Some(0)).exceeded);
+
+        let json =
+            serde_json::to_value(QuotaUsage::new(QuotaMetric::StorageBytes, 5, None)).unwrap();
+        assert_eq!(json["metric"], "storage_bytes");
+        assert!(json.get("limit").is_none());
+    }
+
+    #[test]
+    fn test_month_bounds() {
+        let time = Utc.with_ymd_and_hms(2025, 12, 17, 8, 30, 0).unwrap();
+        assert_eq!(
+            month_start(time),
+            Utc.with_ymd_and_hms(2025, 12, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            next_month_start(time),
+            Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()
+        );
+    }
+}
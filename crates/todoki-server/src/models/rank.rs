@@ -0,0 +1,166 @@
+//! Lexicographic ranks for manual ordering
+//!
+//! A rank is a string of base-36 digits (`0-9a-z`) that never ends in `0`,
+//! so there is always room for another rank before or between two ranks.
+//! Plain string comparison gives the order.
+
+const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+const BASE: u8 = 36;
+
+/// Ranks longer than this are respread with [`spread`]
+pub const MAX_RANK_LEN: usize = 24;
+
+fn value(digit: u8) -> u8 {
+    DIGITS.iter().position(|&d| d == digit).unwrap_or(0) as u8
+}
+
+fn encode(values: &[u8]) -> String {
+    values.iter().map(|&v| DIGITS[v as usize] as char).collect()
+}
+
+/// A rank after `before` and before `after` (`None` = open end)
+///
+/// `before` must sort before `after`.
+pub fn between(before: Option<&str>, after: Option<&str>) -> String {
+    let lo: Vec<u8> = before.unwrap_or_default().bytes().map(value).collect();
+    let hi: Option<Vec<u8>> = after.map(|a| a.bytes().map(value).collect());
+    encode(&midpoint(&lo, hi.as_deref()))
+}
+
+fn midpoint(lo: &[u8], hi: Option<&[u8]>) -> Vec<u8> {
+    if let Some(hi) = hi {
+        // Keep the shared prefix, padding `lo` with zeros
+        let shared = hi
+            .iter()
+            .enumerate()
+            .take_while(|&(i, &d)| lo.get(i).copied().unwrap_or(0) == d)
+            .count();
+        if shared > 0 {
+            let mut out = hi[..shared].to_vec();
+            out.extend(midpoint(lo.get(shared..).unwrap_or_default(), Some(&hi[shared..])));
+            return out;
+        }
+    }
+    let low = lo.first().copied().unwrap_or(0);
+    let high = hi.and_then(|h| h.first().copied()).unwrap_or(BASE);
+    if high - low > 1 {
+        return vec![(low + high) / 2];
+    }
+    match hi {
+        // The first digit of `hi` alone sorts between the two
+        Some(hi) if hi.len() > 1 => vec![hi[0]],
+        _ => {
+            let mut out = vec![low];
+            out.extend(midpoint(lo.get(1..).unwrap_or_default(), None));
+            out
+        }
+    }
+}
+
+/// `count` evenly spaced ranks in ascending order
+pub fn spread(count: usize) -> Vec<String> {
+    let mut width = 1;
+    while (BASE as u128).pow(width) <= count as u128 + 1 {
+        width += 1;
+    }
+    let space = (BASE as u128).pow(width);
+    (1..=count as u128)
+        .map(|i| {
+            let mut n = i * space / (count as u128 + 1);
+            let mut values = vec![0u8; width as usize];
+            for slot in values.iter_mut().rev() {
+                *slot = (n % BASE as u128) as u8;
+                n /= BASE as u128;
+            }
+            while values.last() == Some(&0) {
+                values.pop();
+            }
+            encode(&values)
+        })
+        .collect()
+}
+
+/// Rank for an item inserted at `position` of `column` (ranks in order)
+///
+/// Unranked items sort last. When the item before the slot is unranked or
+/// the ranks get too long, every item is respread: the second value holds
+/// new ranks for `column`, in the same order.
+pub fn place(column: &[Option<String>], position: usize) -> (String, Option<Vec<String>>) {
+    let position = position.min(column.len());
+    let before = match position.checked_sub(1).map(|i| column[i].as_deref()) {
+        None => Some(None),
+        Some(Some(rank)) => Some(Some(rank)),
+        Some(None) => None,
+    };
+    let after = column.get(position).and_then(|r| r.as_deref());
+    if let Some(before) = before {
+        let rank = between(before, after);
+        if rank.len() <= MAX_RANK_LEN {
+            return (rank, None);
+        }
+    }
+
+    let mut ranks = spread(column.len() + 1);
+    let rank = ranks.remove(position);
+    (rank, Some(ranks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_between() {
+        assert_eq!(between(None, None), "i");
+        assert_eq!(between(Some("a"), Some("c")), "b");
+        assert_eq!(between(Some("a"), Some("b")), "ai");
+        assert_eq!(between(None, Some("1")), "0i");
+        assert_eq!(between(Some("az"), Some("b")), "azi");
+
+        // Repeated inserts at the front, back and middle stay ordered
+        let mut ranks = vec![between(None, None)];
+        for i in 0..200 {
+            let rank = match i % 3 {
+                0 => between(None, Some(&ranks[0])),
+                1 => between(Some(ranks.last().unwrap()), None),
+                _ => {
+                    let mid = ranks.len() / 2;
+                    between(Some(&ranks[mid - 1]), Some(&ranks[mid]))
+                }
+            };
+            assert!(!rank.ends_with('0'));
+            ranks.push(rank);
+            ranks.sort();
+            ranks.dedup();
+        }
+        assert_eq!(ranks.len(), 201);
+    }
+
+    #[test]
+    fn test_place() {
+        let column = vec![Some("a".to_string()), Some("c".to_string()), None];
+        assert_eq!(place(&column, 0), ("5".to_string(), None));
+        assert_eq!(place(&column, 1), ("b".to_string(), None));
+        // After the last ranked task; unranked ones stay behind it
+        assert_eq!(place(&column, 2), ("o".to_string(), None));
+
+        // Behind an unranked task: the column is ranked afresh
+        let (rank, respread) = place(&column, 3);
+        let respread = respread.unwrap();
+        assert_eq!(respread.len(), 3);
+        assert!(respread.iter().all(|r| r.as_str() < rank.as_str()));
+        assert!(respread.windows(2).all(|w| w[0] < w[1]));
+
+        assert_eq!(place(&[], 5), ("i".to_string(), None));
+    }
+
+    #[test]
+    fn test_spread() {
+        assert_eq!(spread(0), Vec::<String>::new());
+        assert_eq!(spread(1), vec!["i"]);
+        let ranks = spread(1000);
+        assert_eq!(ranks.len(), 1000);
+        assert!(ranks.windows(2).all(|w| w[0] < w[1]));
+        assert!(ranks.iter().all(|r| !r.ends_with('0') && r.len() <= 2));
+    }
+}
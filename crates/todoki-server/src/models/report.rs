@@ -1,5 +1,11 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
 use gotcha::Schematic;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::artifact::Artifact;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Schematic, Default)]
 #[serde(rename_all = "lowercase")]
@@ -29,4 +35,171 @@ pub struct ReportResponse {
     pub archived_count: i64,
     pub state_changes_count: i64,
     pub comments_count: i64,
+    /// Estimate-vs-actual accuracy of tasks completed in the period
+    pub estimation: EstimationReport,
+}
+
+// ============================================================================
+// Estimation
+// ============================================================================
+
+/// A completed task with an estimate and recorded agent session time
+#[derive(Debug, Clone)]
+pub struct EstimateSample {
+    pub project_name: String,
+    /// Role of the task's most recent session
+    pub role: String,
+    pub estimate_minutes: i32,
+    pub actual_secs: i64,
+}
+
+impl EstimateSample {
+    fn actual_minutes(&self) -> f64 {
+        self.actual_secs as f64 / 60.0
+    }
+}
+
+/// Estimate accuracy for one project or agent role
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct EstimateAccuracy {
+    /// Project name or agent role
+    pub key: String,
+    pub task_count: i64,
+    pub estimated_minutes: f64,
+    pub actual_minutes: f64,
+    /// Total actual / total estimated (> 1 means underestimated)
+    pub ratio: Option<f64>,
+    /// Mean per-task accuracy in percent (100 = exact, 0 = off by 100% or more)
+    pub accuracy_pct: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct EstimationReport {
+    pub by_project: Vec<EstimateAccuracy>,
+    pub by_role: Vec<EstimateAccuracy>,
+}
+
+impl EstimationReport {
+    pub fn from_samples(samples: &[EstimateSample]) -> Self {
+        Self {
+            by_project: group_accuracy(samples, |s| &s.project_name),
+            by_role: group_accuracy(samples, |s| &s.role),
+        }
+    }
+}
+
+fn group_accuracy(
+    samples: &[EstimateSample],
+    key: impl Fn(&EstimateSample) -> &str,
+) -> Vec<EstimateAccuracy> {
+    let mut groups: BTreeMap<&str, Vec<&EstimateSample>> = BTreeMap::new();
+    for sample in samples {
+        groups.entry(key(sample)).or_default().push(sample);
+    }
+
+    groups
+        .into_iter()
+        .map(|(key, samples)| {
+            let estimated: f64 = samples.iter().map(|s| s.estimate_minutes as f64).sum();
+            let actual: f64 = samples.iter().map(|s| s.actual_minutes()).sum();
+            let accuracy: f64 = samples
+                .iter()
+                .map(|s| {
+                    let estimate = s.estimate_minutes.max(1) as f64;
+                    let error = (s.actual_minutes() - estimate).abs() / estimate;
+                    (1.0 - error).max(0.0) * 100.0
+                })
+                .sum::<f64>()
+                / samples.len() as f64;
+
+            EstimateAccuracy {
+                key: key.to_string(),
+                task_count: samples.len() as i64,
+                estimated_minutes: estimated,
+                actual_minutes: actual,
+                ratio: (estimated > 0.0).then(|| actual / estimated),
+                accuracy_pct: accuracy,
+            }
+        })
+        .collect()
+}
+
+// ============================================================================
+// Daily Digest
+// ============================================================================
+
+/// A task whose status changed to done within the digest window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestCompletedTask {
+    pub task_id: Uuid,
+    pub content: String,
+    pub project_name: String,
+    pub completed_at: DateTime<Utc>,
+}
+
+/// An agent session that ended in the failed state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestFailedSession {
+    pub session_id: Uuid,
+    pub agent_name: String,
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+/// A permission request that was rejected or cancelled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestPermissionDenial {
+    pub session_id: String,
+    pub tool_title: String,
+    /// Name of the selected reject option, or "cancelled"
+    pub choice: String,
+    pub responded_at: DateTime<Utc>,
+}
+
+/// Activity aggregated for one digest window
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DigestActivity {
+    pub completed_tasks: Vec<DigestCompletedTask>,
+    pub failed_sessions: Vec<DigestFailedSession>,
+    /// `github_pr` artifacts created or merged within the window
+    pub pull_requests: Vec<Artifact>,
+    pub permission_denials: Vec<DigestPermissionDenial>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(project: &str, role: &str, estimate_minutes: i32, actual_secs: i64) -> EstimateSample {
+        EstimateSample {
+            project_name: project.to_string(),
+            role: role.to_string(),
+            estimate_minutes,
+            actual_secs,
+        }
+    }
+
+    #[test]
+    fn test_estimation_report_groups() {
+        let report = EstimationReport::from_samples(&[
+            sample("web", "coding", 30, 30 * 60),
+            sample("web", "qa", 10, 20 * 60),
+            sample("ops", "coding", 60, 30 * 60),
+        ]);
+
+        assert_eq!(report.by_project.len(), 2);
+        let web = &report.by_project[1];
+        assert_eq!(web.key, "web");
+        assert_eq!(web.task_count, 2);
+        assert_eq!(web.estimated_minutes, 40.0);
+        assert_eq!(web.actual_minutes, 50.0);
+        assert_eq!(web.ratio, Some(1.25));
+        // exact (100%) and off by 100% (0%)
+        assert_eq!(web.accuracy_pct, 50.0);
+
+        let coding = &report.by_role[0];
+        assert_eq!(coding.key, "coding");
+        assert_eq!(coding.task_count, 2);
+        assert_eq!(coding.ratio, Some(60.0 / 90.0));
+        assert_eq!(coding.accuracy_pct, 75.0);
+    }
 }
@@ -0,0 +1,281 @@
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveTime, Utc};
+use conservator::{Creatable, Domain};
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// ============================================================================
+// Execution Schedule
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Schematic)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl From<chrono::Weekday> for Weekday {
+    fn from(day: chrono::Weekday) -> Self {
+        match day {
+            chrono::Weekday::Mon => Weekday::Monday,
+            chrono::Weekday::Tue => Weekday::Tuesday,
+            chrono::Weekday::Wed => Weekday::Wednesday,
+            chrono::Weekday::Thu => Weekday::Thursday,
+            chrono::Weekday::Fri => Weekday::Friday,
+            chrono::Weekday::Sat => Weekday::Saturday,
+            chrono::Weekday::Sun => Weekday::Sunday,
+        }
+    }
+}
+
+/// A recurring time range in which agents may be spawned
+///
+/// When `end` is not after `start` the window runs past midnight into the
+/// next day (e.g. 22:00-06:00); `00:00`-`00:00` covers the whole day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct ExecutionWindow {
+    /// Days on which the window starts (empty = every day)
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    /// Local start time ("HH:MM")
+    pub start: String,
+    /// Local end time ("HH:MM")
+    pub end: String,
+}
+
+impl ExecutionWindow {
+    fn bounds(&self) -> Option<(NaiveTime, NaiveTime)> {
+        let start = NaiveTime::parse_from_str(&self.start, "%H:%M").ok()?;
+        let end = NaiveTime::parse_from_str(&self.end, "%H:%M").ok()?;
+        Some((start, end))
+    }
+
+    fn starts_on(&self, date: NaiveDate) -> bool {
+        self.days.is_empty() || self.days.contains(&date.weekday().into())
+    }
+
+    /// The (start, end) instants of this window when it starts on `date`
+    fn occurrence(
+        &self,
+        date: NaiveDate,
+        offset: FixedOffset,
+    ) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        if !self.starts_on(date) {
+            return None;
+        }
+        let (start, end) = self.bounds()?;
+        let end_date = if end > start {
+            date
+        } else {
+            date + Duration::days(1)
+        };
+        let to_utc = |date: NaiveDate, time: NaiveTime| {
+            date.and_time(time)
+                .and_local_timezone(offset)
+                .single()
+                .map(|t| t.with_timezone(&Utc))
+        };
+        Some((to_utc(date, start)?, to_utc(end_date, end)?))
+    }
+}
+
+/// Project-level execution windows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct ExecutionSchedule {
+    /// Offset of the window times from UTC (hours)
+    #[serde(default = "default_utc_offset_hours")]
+    pub utc_offset_hours: i32,
+    pub windows: Vec<ExecutionWindow>,
+}
+
+fn default_utc_offset_hours() -> i32 {
+    8
+}
+
+impl ExecutionSchedule {
+    /// Check offset and window times; returns a message for the first problem
+    pub fn validate(&self) -> Result<(), String> {
+        if self.offset().is_none() {
+            return Err(format!(
+                "invalid utc_offset_hours {}",
+                self.utc_offset_hours
+            ));
+        }
+        for window in &self.windows {
+            if window.bounds().is_none() {
+                return Err(format!(
+                    "invalid window {}-{}, expected HH:MM",
+                    window.start, window.end
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn offset(&self) -> Option<FixedOffset> {
+        FixedOffset::east_opt(self.utc_offset_hours * 3600)
+    }
+
+    /// Whether an agent may be spawned at `now`
+    pub fn is_open(&self, now: DateTime<Utc>) -> bool {
+        let Some(offset) = self.offset() else {
+            return true;
+        };
+        if self.windows.is_empty() {
+            return true;
+        }
+        let today = now.with_timezone(&offset).date_naive();
+        // A window that started yesterday may still be running past midnight
+        [today - Duration::days(1), today].into_iter().any(|date| {
+            self.windows.iter().any(|window| {
+                window
+                    .occurrence(date, offset)
+                    .is_some_and(|(start, end)| start <= now && now < end)
+            })
+        })
+    }
+
+    /// The next time the schedule opens (`now` if it is open already)
+    pub fn next_open(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if self.is_open(now) {
+            return Some(now);
+        }
+        let offset = self.offset()?;
+        let today = now.with_timezone(&offset).date_naive();
+        (0..=7)
+            .map(|days| today + Duration::days(days))
+            .flat_map(|date| {
+                self.windows
+                    .iter()
+                    .filter_map(move |window| window.occurrence(date, offset))
+            })
+            .map(|(start, _)| start)
+            .filter(|start| *start > now)
+            .min()
+    }
+}
+
+// ============================================================================
+// Scheduled Execution
+// ============================================================================
+
+/// A task execution deferred until its project's window opens
+#[derive(Debug, Clone, Serialize, Deserialize, Domain)]
+#[domain(table = "scheduled_executions")]
+pub struct ScheduledExecution {
+    #[domain(primary_key)]
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub relay_id: Option<String>,
+    pub scheduled_for: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub relay_selector: Option<String>,
+}
+
+#[derive(Debug, Clone, Creatable)]
+pub struct CreateScheduledExecution {
+    pub task_id: Uuid,
+    pub relay_id: Option<String>,
+    pub scheduled_for: DateTime<Utc>,
+    pub relay_selector: Option<String>,
+}
+
+// ============================================================================
+// Task Snooze
+// ============================================================================
+
+/// A task hidden from the inbox until `wake_at`
+#[derive(Debug, Clone, Serialize, Deserialize, Domain)]
+#[domain(table = "task_snoozes")]
+pub struct TaskSnooze {
+    #[domain(primary_key)]
+    pub task_id: Uuid,
+    pub wake_at: DateTime<Utc>,
+    /// Send a notification when the task wakes up
+    pub notify: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Schematic)]
+pub struct TaskSnoozeRequest {
+    /// When the task returns to the inbox
+    pub until: DateTime<Utc>,
+    #[serde(default)]
+    pub notify: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn schedule(windows: Vec<ExecutionWindow>) -> ExecutionSchedule {
+        ExecutionSchedule {
+            utc_offset_hours: 0,
+            windows,
+        }
+    }
+
+    fn window(days: Vec<Weekday>, start: &str, end: &str) -> ExecutionWindow {
+        ExecutionWindow {
+            days,
+            start: start.to_string(),
+            end: end.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_overnight_window() {
+        let nightly = schedule(vec![window(vec![], "22:00", "06:00")]);
+
+        // 2024-03-05 is a Tuesday
+        let late = Utc.with_ymd_and_hms(2024, 3, 5, 23, 0, 0).unwrap();
+        let early = Utc.with_ymd_and_hms(2024, 3, 6, 5, 59, 0).unwrap();
+        let noon = Utc.with_ymd_and_hms(2024, 3, 6, 12, 0, 0).unwrap();
+        assert!(nightly.is_open(late));
+        assert!(nightly.is_open(early));
+        assert!(!nightly.is_open(noon));
+        assert_eq!(
+            nightly.next_open(noon),
+            Some(Utc.with_ymd_and_hms(2024, 3, 6, 22, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_weekend_window() {
+        let weekends = schedule(vec![window(
+            vec![Weekday::Saturday, Weekday::Sunday],
+            "00:00",
+            "00:00",
+        )]);
+
+        let tuesday = Utc.with_ymd_and_hms(2024, 3, 5, 12, 0, 0).unwrap();
+        let sunday = Utc.with_ymd_and_hms(2024, 3, 10, 23, 30, 0).unwrap();
+        assert!(!weekends.is_open(tuesday));
+        assert!(weekends.is_open(sunday));
+        assert_eq!(
+            weekends.next_open(tuesday),
+            Some(Utc.with_ymd_and_hms(2024, 3, 9, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_times() {
+        assert!(
+            schedule(vec![window(vec![], "22:00", "06:00")])
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            schedule(vec![window(vec![], "25:00", "06:00")])
+                .validate()
+                .is_err()
+        );
+    }
+}
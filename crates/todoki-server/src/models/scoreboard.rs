@@ -0,0 +1,237 @@
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::agent::AgentRole;
+
+/// Default days of history stats cover
+pub const DEFAULT_STATS_DAYS: u32 = 30;
+
+/// Most days of history stats may cover
+pub const MAX_STATS_DAYS: u32 = 365;
+
+/// Sessions an agent needs in the window to be ranked on the leaderboard
+pub const DEFAULT_MIN_SESSIONS: i64 = 3;
+
+// ============================================================================
+// Agent Stats
+// ============================================================================
+
+/// Raw counts behind an agent's stats
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AgentStatCounts {
+    pub agent_id: Uuid,
+    pub name: String,
+    pub role: AgentRole,
+    pub project_id: Uuid,
+    /// Sessions that ended in the window
+    pub sessions: i64,
+    pub completed_sessions: i64,
+    pub failed_sessions: i64,
+    /// Mean start-to-end time of those sessions
+    pub avg_session_secs: Option<f64>,
+    /// Tasks the agent worked on that got a QA verdict
+    pub qa_tasks: i64,
+    /// Those of them whose first verdict was a pass
+    pub qa_first_pass: i64,
+    pub permission_requests: i64,
+    /// Requests cancelled or answered with a reject/deny option
+    pub permission_denials: i64,
+}
+
+/// Performance of one agent over a window of days
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct AgentStats {
+    pub agent_id: Uuid,
+    pub name: String,
+    pub role: AgentRole,
+    pub project_id: Uuid,
+    pub sessions: i64,
+    pub completed_sessions: i64,
+    pub failed_sessions: i64,
+    /// Completed share of ended sessions; `None` without sessions
+    pub success_rate: Option<f64>,
+    pub avg_session_secs: Option<f64>,
+    pub qa_tasks: i64,
+    pub qa_first_pass: i64,
+    /// Share of QA-checked tasks that passed on the first verdict
+    pub qa_first_pass_rate: Option<f64>,
+    pub permission_requests: i64,
+    pub permission_denials: i64,
+    /// Denied share of permission requests
+    pub denial_rate: Option<f64>,
+}
+
+fn ratio(part: i64, whole: i64) -> Option<f64> {
+    (whole > 0).then(|| part as f64 / whole as f64)
+}
+
+impl From<AgentStatCounts> for AgentStats {
+    fn from(counts: AgentStatCounts) -> Self {
+        Self {
+            success_rate: ratio(counts.completed_sessions, counts.sessions),
+            qa_first_pass_rate: ratio(counts.qa_first_pass, counts.qa_tasks),
+            denial_rate: ratio(counts.permission_denials, counts.permission_requests),
+            agent_id: counts.agent_id,
+            name: counts.name,
+            role: counts.role,
+            project_id: counts.project_id,
+            sessions: counts.sessions,
+            completed_sessions: counts.completed_sessions,
+            failed_sessions: counts.failed_sessions,
+            avg_session_secs: counts.avg_session_secs,
+            qa_tasks: counts.qa_tasks,
+            qa_first_pass: counts.qa_first_pass,
+            permission_requests: counts.permission_requests,
+            permission_denials: counts.permission_denials,
+        }
+    }
+}
+
+// ============================================================================
+// Leaderboard
+// ============================================================================
+
+/// What the leaderboard ranks by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, Schematic)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardSort {
+    /// Highest success rate first
+    #[default]
+    SuccessRate,
+    /// Highest first-attempt QA pass rate first
+    QaFirstPass,
+    /// Fewest permission denials first
+    DenialRate,
+    /// Shortest sessions first
+    AvgDuration,
+}
+
+impl LeaderboardSort {
+    /// The stat ranked by, oriented so that larger is better
+    fn key(self, stats: &AgentStats) -> Option<f64> {
+        match self {
+            Self::SuccessRate => stats.success_rate,
+            Self::QaFirstPass => stats.qa_first_pass_rate,
+            Self::DenialRate => stats.denial_rate.map(|r| -r),
+            Self::AvgDuration => stats.avg_session_secs.map(|s| -s),
+        }
+    }
+}
+
+/// Agents with at least `min_sessions` sessions, best first; agents without
+/// the stat ranked by come last
+pub fn rank_leaderboard(
+    stats: Vec<AgentStats>,
+    sort: LeaderboardSort,
+    min_sessions: i64,
+) -> Vec<AgentStats> {
+    let mut ranked: Vec<AgentStats> = stats
+        .into_iter()
+        .filter(|s| s.sessions >= min_sessions.max(1))
+        .collect();
+    ranked.sort_by(|a, b| match (sort.key(a), sort.key(b)) {
+        (Some(a_key), Some(b_key)) => b_key.total_cmp(&a_key).then(b.sessions.cmp(&a.sessions)),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => b.sessions.cmp(&a.sessions),
+    });
+    ranked
+}
+
+// ============================================================================
+// API DTOs
+// ============================================================================
+
+/// `GET /api/agents/:agent_id/stats` query parameters
+#[derive(Debug, Clone, Default, Deserialize, Schematic)]
+pub struct AgentStatsQuery {
+    /// Days of history to cover (default 30, at most 365)
+    pub days: Option<u32>,
+}
+
+/// `GET /api/agents/leaderboard` query parameters
+#[derive(Debug, Clone, Default, Deserialize, Schematic)]
+pub struct LeaderboardQuery {
+    /// Days of history to cover (default 30, at most 365)
+    pub days: Option<u32>,
+    /// Only agents of this project
+    pub project_id: Option<Uuid>,
+    #[serde(default)]
+    pub sort: LeaderboardSort,
+    /// Leave out agents with fewer sessions in the window (default 3)
+    pub min_sessions: Option<i64>,
+}
+
+/// Clamp a requested window to `1..=MAX_STATS_DAYS`
+pub fn stats_days(days: Option<u32>) -> u32 {
+    days.unwrap_or(DEFAULT_STATS_DAYS).clamp(1, MAX_STATS_DAYS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(name: &str, sessions: i64, completed: i64, denials: i64) -> AgentStats {
+        AgentStatCounts {
+            agent_id: Uuid::new_v4(),
+            name: name.to_string(),
+            sessions,
+            completed_sessions: completed,
+            permission_requests: 10,
+            permission_denials: denials,
+            ..Default::default()
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_rates() {
+        let s = stats("coder", 4, 3, 2);
+        assert_eq!(s.success_rate, Some(0.75));
+        assert_eq!(s.denial_rate, Some(0.2));
+        assert_eq!(s.qa_first_pass_rate, None);
+    }
+
+    #[test]
+    fn test_rank_leaderboard() {
+        let all = vec![
+            stats("steady", 10, 9, 1),
+            stats("new", 1, 1, 0),
+            stats("flaky", 10, 4, 5),
+            stats("sharp", 5, 5, 3),
+        ];
+
+        let names = |ranked: Vec<AgentStats>| -> Vec<String> {
+            ranked.into_iter().map(|s| s.name).collect()
+        };
+        assert_eq!(
+            names(rank_leaderboard(
+                all.clone(),
+                LeaderboardSort::SuccessRate,
+                3
+            )),
+            ["sharp", "steady", "flaky"]
+        );
+        assert_eq!(
+            names(rank_leaderboard(
+                all.clone(),
+                LeaderboardSort::DenialRate,
+                1
+            )),
+            ["new", "steady", "sharp", "flaky"]
+        );
+        // Nobody has QA verdicts: most sessions first
+        assert_eq!(
+            names(rank_leaderboard(all, LeaderboardSort::QaFirstPass, 5)),
+            ["steady", "flaky", "sharp"]
+        );
+    }
+
+    #[test]
+    fn test_stats_days() {
+        assert_eq!(stats_days(None), DEFAULT_STATS_DAYS);
+        assert_eq!(stats_days(Some(0)), 1);
+        assert_eq!(stats_days(Some(10_000)), MAX_STATS_DAYS);
+    }
+}
@@ -0,0 +1,364 @@
+use chrono::{DateTime, Utc};
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::project::Project;
+use super::task::TaskStatus;
+use super::workflow::{status_key, Workflow};
+
+/// Completed tasks a status page lists
+pub const STATUS_PAGE_COMPLETED_LIMIT: i64 = 10;
+
+// ============================================================================
+// Status Page
+// ============================================================================
+
+/// Part of a status page that can be left out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Schematic)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusPageField {
+    /// The project description
+    Description,
+    /// Task counts per workflow state
+    StatusCounts,
+    /// Recently completed tasks
+    RecentlyCompleted,
+    /// Titles of recently completed tasks (only their completion time shows)
+    TaskTitles,
+    /// Number of agents with a running session
+    ActiveAgents,
+}
+
+/// A project's public status page; whoever has the token can read it
+#[derive(Debug, Clone)]
+pub struct StatusPage {
+    pub project_id: Uuid,
+    pub token: String,
+    pub enabled: bool,
+    pub redact: Vec<StatusPageField>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl StatusPage {
+    pub fn shows(&self, field: StatusPageField) -> bool {
+        !self.redact.contains(&field)
+    }
+}
+
+/// What a status page is built from
+#[derive(Debug, Clone, Default)]
+pub struct ProjectSnapshot {
+    /// Unarchived tasks by built-in status and custom state
+    pub counts: Vec<(TaskStatus, Option<String>, i64)>,
+    /// Latest completed tasks: content and completion time
+    pub completed: Vec<(String, DateTime<Utc>)>,
+    pub active_agents: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct StatusCount {
+    /// Workflow state key
+    pub state: String,
+    pub name: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct CompletedItem {
+    /// First line of the task; absent when titles are redacted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub completed_at: DateTime<Utc>,
+}
+
+/// `GET /api/public/status/:token` response; redacted parts are absent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct PublicProjectStatus {
+    pub name: String,
+    pub color: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// In board order; states without tasks are left out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_counts: Option<Vec<StatusCount>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recently_completed: Option<Vec<CompletedItem>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_agents: Option<i64>,
+    pub generated_at: DateTime<Utc>,
+}
+
+impl PublicProjectStatus {
+    pub fn build(page: &StatusPage, project: &Project, snapshot: ProjectSnapshot) -> Self {
+        let workflow = project.workflow();
+        let status_counts = page
+            .shows(StatusPageField::StatusCounts)
+            .then(|| status_counts(&workflow, &snapshot.counts));
+        let show_titles = page.shows(StatusPageField::TaskTitles);
+        let recently_completed = page.shows(StatusPageField::RecentlyCompleted).then(|| {
+            snapshot
+                .completed
+                .into_iter()
+                .map(|(content, completed_at)| CompletedItem {
+                    title: show_titles.then(|| {
+                        content
+                            .lines()
+                            .next()
+                            .unwrap_or_default()
+                            .trim()
+                            .to_string()
+                    }),
+                    completed_at,
+                })
+                .collect()
+        });
+
+        Self {
+            name: project.name.clone(),
+            color: project.color.clone(),
+            description: project
+                .description
+                .clone()
+                .filter(|_| page.shows(StatusPageField::Description)),
+            status_counts,
+            recently_completed,
+            active_agents: page
+                .shows(StatusPageField::ActiveAgents)
+                .then_some(snapshot.active_agents),
+            generated_at: Utc::now(),
+        }
+    }
+
+    /// Self-contained HTML page, for embedding in an iframe
+    pub fn render_html(&self) -> String {
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{0}</title>\n<style>{1}</style>\n</head>\n<body>\n\
+             <h1 style=\"border-color: {2}\">{0}</h1>\n",
+            escape(&self.name),
+            HTML_STYLE,
+            escape(&self.color)
+        );
+        if let Some(description) = &self.description {
+            out.push_str(&format!("<p>{}</p>\n", escape(description)));
+        }
+        if let Some(counts) = &self.status_counts {
+            out.push_str("<h2>Tasks</h2>\n<table>\n");
+            for count in counts {
+                out.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td></tr>\n",
+                    escape(&count.name),
+                    count.count
+                ));
+            }
+            out.push_str("</table>\n");
+        }
+        if let Some(agents) = self.active_agents {
+            out.push_str(&format!("<p>Active agents: {}</p>\n", agents));
+        }
+        if let Some(completed) = &self.recently_completed {
+            out.push_str("<h2>Recently completed</h2>\n<ul>\n");
+            for item in completed {
+                let date = item.completed_at.format("%Y-%m-%d");
+                match &item.title {
+                    Some(title) => out.push_str(&format!(
+                        "<li>{} <small>{}</small></li>\n",
+                        escape(title),
+                        date
+                    )),
+                    None => out.push_str(&format!("<li><small>{}</small></li>\n", date)),
+                }
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str(&format!(
+            "<footer>Updated {}</footer>\n</body>\n</html>\n",
+            self.generated_at.format("%Y-%m-%d %H:%M UTC")
+        ));
+        out
+    }
+}
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;margin:1rem;color:#222}\
+h1{border-left:6px solid;padding-left:.5rem}\
+td{padding:.2rem 1rem .2rem 0}footer{color:#888;font-size:.8rem;margin-top:1rem}";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Counts of the workflow states that have tasks, in board order; tasks in
+/// states the workflow no longer has come last, under their key
+fn status_counts(
+    workflow: &Workflow,
+    counts: &[(TaskStatus, Option<String>, i64)],
+) -> Vec<StatusCount> {
+    let mut out: Vec<StatusCount> = workflow
+        .states
+        .iter()
+        .map(|state| StatusCount {
+            state: state.key.clone(),
+            name: state.name.clone(),
+            count: 0,
+        })
+        .collect();
+    for (status, workflow_state, count) in counts {
+        let key = workflow_state
+            .clone()
+            .unwrap_or_else(|| status_key(*status));
+        match out.iter_mut().find(|c| c.state == key) {
+            Some(entry) => entry.count += count,
+            None => out.push(StatusCount {
+                name: key.clone(),
+                state: key,
+                count: *count,
+            }),
+        }
+    }
+    out.retain(|c| c.count > 0);
+    out
+}
+
+// ============================================================================
+// API DTOs
+// ============================================================================
+
+/// `PUT /api/projects/:project_id/status-page` body
+#[derive(Debug, Clone, Deserialize, Schematic)]
+pub struct StatusPageRequest {
+    pub enabled: bool,
+    /// Parts to leave out of the page
+    #[serde(default)]
+    pub redact: Vec<StatusPageField>,
+    /// Issue a new token; links with the old one stop working
+    #[serde(default)]
+    pub rotate_token: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Schematic)]
+pub struct StatusPageResponse {
+    pub project_id: Uuid,
+    pub enabled: bool,
+    pub redact: Vec<StatusPageField>,
+    pub token: String,
+    /// HTML page, relative to the server
+    pub page_path: String,
+    /// JSON API, relative to the server
+    pub api_path: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<StatusPage> for StatusPageResponse {
+    fn from(page: StatusPage) -> Self {
+        Self {
+            page_path: format!("/status/{}", page.token),
+            api_path: format!("/api/public/status/{}", page.token),
+            project_id: page.project_id,
+            enabled: page.enabled,
+            redact: page.redact,
+            token: page.token,
+            updated_at: page.updated_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> Project {
+        Project {
+            id: Uuid::new_v4(),
+            name: "Website <beta>".to_string(),
+            description: Some("Internal roadmap".to_string()),
+            color: "#336699".to_string(),
+            archived: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            general_template: None,
+            business_template: None,
+            coding_template: None,
+            qa_template: None,
+            execution_schedule: None,
+            relay_routing: None,
+            review_config: None,
+            workflow: None,
+            issue_sync: None,
+        }
+    }
+
+    fn page(redact: Vec<StatusPageField>) -> StatusPage {
+        StatusPage {
+            project_id: Uuid::new_v4(),
+            token: "token".to_string(),
+            enabled: true,
+            redact,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn snapshot() -> ProjectSnapshot {
+        ProjectSnapshot {
+            counts: vec![
+                (TaskStatus::Todo, None, 3),
+                (TaskStatus::Done, None, 5),
+                (TaskStatus::InProgress, Some("blocked".to_string()), 1),
+            ],
+            completed: vec![("Ship <it>\nsecret details".to_string(), Utc::now())],
+            active_agents: 2,
+        }
+    }
+
+    #[test]
+    fn test_build() {
+        let status = PublicProjectStatus::build(&page(vec![]), &project(), snapshot());
+        let counts = status.status_counts.unwrap();
+        let todo = counts.iter().find(|c| c.state == "todo").unwrap();
+        assert_eq!(todo.count, 3);
+        assert!(counts.iter().all(|c| c.state != "backlog"));
+        assert_eq!(counts.last().unwrap().state, "blocked");
+        let completed = status.recently_completed.unwrap();
+        assert_eq!(completed[0].title.as_deref(), Some("Ship <it>"));
+        assert_eq!(status.active_agents, Some(2));
+        assert_eq!(status.description.as_deref(), Some("Internal roadmap"));
+    }
+
+    #[test]
+    fn test_build_redacts() {
+        let redact = vec![
+            StatusPageField::Description,
+            StatusPageField::TaskTitles,
+            StatusPageField::ActiveAgents,
+        ];
+        let status = PublicProjectStatus::build(&page(redact), &project(), snapshot());
+        assert_eq!(status.description, None);
+        assert_eq!(status.active_agents, None);
+        assert_eq!(status.recently_completed.unwrap()[0].title, None);
+
+        let json = serde_json::to_value(PublicProjectStatus::build(
+            &page(vec![
+                StatusPageField::RecentlyCompleted,
+                StatusPageField::StatusCounts,
+            ]),
+            &project(),
+            snapshot(),
+        ))
+        .unwrap();
+        assert!(json.get("recently_completed").is_none());
+        assert!(json.get("status_counts").is_none());
+    }
+
+    #[test]
+    fn test_render_html_escapes() {
+        let html = PublicProjectStatus::build(&page(vec![]), &project(), snapshot()).render_html();
+        assert!(html.contains("<title>Website &lt;beta&gt;</title>"));
+        assert!(html.contains("Ship &lt;it&gt;"));
+        assert!(!html.contains("secret details"));
+    }
+}
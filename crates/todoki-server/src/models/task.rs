@@ -3,9 +3,11 @@ use conservator::{Creatable, Domain, TextEnum};
 use gotcha::Schematic;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use validator::Validate;
 
 use super::agent::AgentBriefResponse;
 use super::artifact::ArtifactResponse;
+use super::issue_sync::RemoteIssueResponse;
 
 // ============================================================================
 // Task Status
@@ -129,6 +131,10 @@ pub enum TaskEventType {
     Unarchived,
     Archived,
     CreateComment,
+    Reorder,
+    PossibleDuplicate,
+    MergedFrom,
+    MergedInto,
 }
 
 // ============================================================================
@@ -148,6 +154,24 @@ pub struct Task {
     pub archived: bool,
     /// Agent ID if this task is being executed by an agent
     pub agent_id: Option<Uuid>,
+    /// Optional due date
+    pub due_at: Option<DateTime<Utc>>,
+    /// Optional effort estimate (minutes)
+    pub estimate_minutes: Option<i32>,
+    /// Custom workflow state; `None` when the task is in the state named by
+    /// its built-in status
+    pub workflow_state: Option<String>,
+    /// Manual position within the board column (`None` = after ranked tasks)
+    pub rank: Option<String>,
+}
+
+impl Task {
+    /// Key of the workflow state the task is in
+    pub fn state(&self) -> String {
+        self.workflow_state
+            .clone()
+            .unwrap_or_else(|| super::workflow::status_key(self.status))
+    }
 }
 
 #[derive(Debug, Clone, Creatable)]
@@ -159,6 +183,10 @@ pub struct CreateTask {
     pub create_at: DateTime<Utc>,
     pub archived: bool,
     pub agent_id: Option<Uuid>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub estimate_minutes: Option<i32>,
+    pub workflow_state: Option<String>,
+    pub rank: Option<String>,
 }
 
 impl CreateTask {
@@ -171,8 +199,27 @@ impl CreateTask {
             create_at: Utc::now(),
             archived: false,
             agent_id: None,
+            due_at: None,
+            estimate_minutes: None,
+            workflow_state: None,
+            rank: None,
         }
     }
+
+    pub fn with_due_at(mut self, due_at: Option<DateTime<Utc>>) -> Self {
+        self.due_at = due_at;
+        self
+    }
+
+    pub fn with_estimate_minutes(mut self, estimate_minutes: Option<i32>) -> Self {
+        self.estimate_minutes = estimate_minutes;
+        self
+    }
+
+    pub fn with_workflow_state(mut self, workflow_state: Option<String>) -> Self {
+        self.workflow_state = workflow_state;
+        self
+    }
 }
 
 // ============================================================================
@@ -189,6 +236,13 @@ pub struct TaskEvent {
     pub datetime: DateTime<Utc>,
     pub state: Option<TaskStatus>,
     pub from_state: Option<TaskStatus>,
+    /// Ranks before and after a reorder
+    pub rank: Option<String>,
+    pub from_rank: Option<String>,
+    /// The other task of a possible duplicate or a merge
+    pub related_task_id: Option<Uuid>,
+    /// Title similarity of a possible duplicate
+    pub similarity: Option<f64>,
 }
 
 #[derive(Debug, Clone, Creatable)]
@@ -198,6 +252,10 @@ pub struct CreateTaskEvent {
     pub datetime: DateTime<Utc>,
     pub state: Option<TaskStatus>,
     pub from_state: Option<TaskStatus>,
+    pub rank: Option<String>,
+    pub from_rank: Option<String>,
+    pub related_task_id: Option<Uuid>,
+    pub similarity: Option<f64>,
 }
 
 impl CreateTaskEvent {
@@ -208,6 +266,10 @@ impl CreateTaskEvent {
             datetime: Utc::now(),
             state: None,
             from_state: None,
+            rank: None,
+            from_rank: None,
+            related_task_id: None,
+            similarity: None,
         }
     }
 
@@ -218,6 +280,30 @@ impl CreateTaskEvent {
             datetime: Utc::now(),
             state: Some(to_status),
             from_state: Some(from_status),
+            rank: None,
+            from_rank: None,
+            related_task_id: None,
+            similarity: None,
+        }
+    }
+
+    /// Manual move within a board column
+    pub fn reorder(
+        task_id: Uuid,
+        status: TaskStatus,
+        from_rank: Option<String>,
+        rank: String,
+    ) -> Self {
+        Self {
+            task_id,
+            event_type: TaskEventType::Reorder,
+            datetime: Utc::now(),
+            state: Some(status),
+            from_state: None,
+            rank: Some(rank),
+            from_rank,
+            related_task_id: None,
+            similarity: None,
         }
     }
 
@@ -228,6 +314,10 @@ impl CreateTaskEvent {
             datetime: Utc::now(),
             state: None,
             from_state: None,
+            rank: None,
+            from_rank: None,
+            related_task_id: None,
+            similarity: None,
         }
     }
 
@@ -238,6 +328,10 @@ impl CreateTaskEvent {
             datetime: Utc::now(),
             state: None,
             from_state: None,
+            rank: None,
+            from_rank: None,
+            related_task_id: None,
+            similarity: None,
         }
     }
 
@@ -248,6 +342,55 @@ impl CreateTaskEvent {
             datetime: Utc::now(),
             state: None,
             from_state: None,
+            rank: None,
+            from_rank: None,
+            related_task_id: None,
+            similarity: None,
+        }
+    }
+
+    /// `related_task_id` is an open task whose title is `similarity` alike
+    pub fn possible_duplicate(task_id: Uuid, related_task_id: Uuid, similarity: f64) -> Self {
+        Self {
+            task_id,
+            event_type: TaskEventType::PossibleDuplicate,
+            datetime: Utc::now(),
+            state: None,
+            from_state: None,
+            rank: None,
+            from_rank: None,
+            related_task_id: Some(related_task_id),
+            similarity: Some(similarity),
+        }
+    }
+
+    /// `duplicate_id` was merged into this task
+    pub fn merged_from(task_id: Uuid, duplicate_id: Uuid) -> Self {
+        Self {
+            task_id,
+            event_type: TaskEventType::MergedFrom,
+            datetime: Utc::now(),
+            state: None,
+            from_state: None,
+            rank: None,
+            from_rank: None,
+            related_task_id: Some(duplicate_id),
+            similarity: None,
+        }
+    }
+
+    /// This task was merged into `target_id`
+    pub fn merged_into(task_id: Uuid, target_id: Uuid) -> Self {
+        Self {
+            task_id,
+            event_type: TaskEventType::MergedInto,
+            datetime: Utc::now(),
+            state: None,
+            from_state: None,
+            rank: None,
+            from_rank: None,
+            related_task_id: Some(target_id),
+            similarity: None,
         }
     }
 }
@@ -295,6 +438,14 @@ pub struct TaskEventResponse {
     pub datetime: DateTime<Utc>,
     pub state: Option<TaskStatus>,
     pub from_state: Option<TaskStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_rank: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub related_task_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f64>,
 }
 
 impl From<TaskEvent> for TaskEventResponse {
@@ -306,6 +457,10 @@ impl From<TaskEvent> for TaskEventResponse {
             datetime: e.datetime,
             state: e.state,
             from_state: e.from_state,
+            rank: e.rank,
+            from_rank: e.from_rank,
+            related_task_id: e.related_task_id,
+            similarity: e.similarity,
         }
     }
 }
@@ -336,8 +491,20 @@ pub struct TaskResponse {
     pub content: String,
     pub project_id: Uuid,
     pub status: TaskStatus,
+    /// Workflow state key; the status unless the project has custom states
+    pub state: String,
+    /// Manual position within the board column
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank: Option<String>,
     pub create_at: DateTime<Utc>,
     pub archived: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate_minutes: Option<i32>,
+    /// Hidden from the inbox until this time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snoozed_until: Option<DateTime<Utc>>,
     pub events: Vec<TaskEventResponse>,
     pub comments: Vec<TaskCommentResponse>,
     /// Agent executing this task, if any
@@ -346,6 +513,9 @@ pub struct TaskResponse {
     /// Artifacts created by the agent (e.g., GitHub PRs)
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub artifacts: Vec<ArtifactResponse>,
+    /// Jira / Linear issue the task is mirrored to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_issue: Option<RemoteIssueResponse>,
 }
 
 impl TaskResponse {
@@ -356,14 +526,21 @@ impl TaskResponse {
         agent: Option<AgentBriefResponse>,
         artifacts: Vec<ArtifactResponse>,
     ) -> Self {
+        let state = task.state();
         Self {
             id: task.id,
             priority: task.priority,
             content: task.content,
             project_id: task.project_id,
             status: task.status,
+            state,
+            rank: task.rank,
             create_at: task.create_at,
             archived: task.archived,
+            due_at: task.due_at,
+            estimate_minutes: task.estimate_minutes,
+            snoozed_until: None,
+            remote_issue: None,
             events: events.into_iter().map(Into::into).collect(),
             comments: comments.into_iter().map(Into::into).collect(),
             agent,
@@ -372,29 +549,72 @@ impl TaskResponse {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Schematic)]
+#[derive(Debug, Clone, Deserialize, Schematic, Validate)]
 pub struct TaskCreateRequest {
     #[serde(default)]
     pub priority: i32,
+    #[validate(
+        length(min = 1, max = "crate::api::validation::MAX_CONTENT_LEN"),
+        custom(function = "crate::api::validation::not_blank")
+    )]
     pub content: String,
     pub project_id: Uuid,
     #[serde(default)]
     pub status: TaskStatus,
+    /// Initial workflow state; takes precedence over `status`
+    #[serde(default)]
+    pub state: Option<String>,
+    #[serde(default)]
+    pub due_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub estimate_minutes: Option<i32>,
 }
 
-#[derive(Debug, Clone, Deserialize, Schematic)]
+#[derive(Debug, Clone, Deserialize, Schematic, Validate)]
 pub struct TaskUpdateRequest {
     pub priority: i32,
+    #[validate(
+        length(min = 1, max = "crate::api::validation::MAX_CONTENT_LEN"),
+        custom(function = "crate::api::validation::not_blank")
+    )]
     pub content: String,
     pub project_id: Uuid,
+    #[serde(default)]
+    pub due_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub estimate_minutes: Option<i32>,
 }
 
+/// Move a task to position `position` of a board column
 #[derive(Debug, Clone, Deserialize, Schematic)]
-pub struct TaskStatusUpdateRequest {
-    pub status: TaskStatus,
+pub struct TaskMoveRequest {
+    /// Column to move to; defaults to the task's current one
+    #[serde(default)]
+    pub status: Option<TaskStatus>,
+    /// Workflow state of the column; takes precedence over `status`
+    #[serde(default)]
+    pub state: Option<String>,
+    /// Zero-based position among the column's other tasks
+    pub position: usize,
 }
 
+/// Move a task to a built-in `status` or to a workflow `state`
 #[derive(Debug, Clone, Deserialize, Schematic)]
+pub struct TaskStatusUpdateRequest {
+    #[serde(default)]
+    pub status: Option<TaskStatus>,
+    /// Workflow state key; takes precedence over `status`
+    #[serde(default)]
+    pub state: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Schematic, Validate)]
 pub struct TaskCommentCreateRequest {
+    #[validate(
+        length(min = 1, max = "crate::api::validation::MAX_CONTENT_LEN"),
+        custom(function = "crate::api::validation::not_blank")
+    )]
     pub content: String,
 }
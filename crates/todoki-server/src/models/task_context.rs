@@ -0,0 +1,113 @@
+use chrono::{DateTime, Utc};
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Longest context key (characters)
+pub const MAX_CONTEXT_KEY_LEN: usize = 200;
+
+/// A key may use letters, digits, `.`, `_`, `-` and `:`, e.g. `qa.findings`
+pub fn valid_context_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.chars().count() <= MAX_CONTEXT_KEY_LEN
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | ':'))
+}
+
+// ============================================================================
+// Task Context Entry
+// ============================================================================
+
+/// A JSON document in a task's shared context
+///
+/// The agents of every stage read and write the same keys, so analysis,
+/// decisions and findings outlive the session that produced them.
+#[derive(Debug, Clone)]
+pub struct TaskContextEntry {
+    pub task_id: Uuid,
+    pub key: String,
+    pub value: Value,
+    /// Starts at 1 and grows with every write
+    pub version: i32,
+    /// Token ID of the last writer
+    pub updated_by: String,
+    pub agent_id: Option<Uuid>,
+    pub session_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Who writes a context entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextWriter {
+    pub token_id: String,
+    pub agent_id: Option<Uuid>,
+    pub session_id: Option<Uuid>,
+}
+
+// ============================================================================
+// API DTOs
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct TaskContextEntryResponse {
+    pub key: String,
+    pub value: Value,
+    pub version: i32,
+    pub updated_by: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<TaskContextEntry> for TaskContextEntryResponse {
+    fn from(entry: TaskContextEntry) -> Self {
+        Self {
+            key: entry.key,
+            value: entry.value,
+            version: entry.version,
+            updated_by: entry.updated_by,
+            agent_id: entry.agent_id,
+            session_id: entry.session_id,
+            created_at: entry.created_at,
+            updated_at: entry.updated_at,
+        }
+    }
+}
+
+/// `GET /api/tasks/:task_id/context` query parameters
+#[derive(Debug, Clone, Default, Deserialize, Schematic)]
+pub struct TaskContextListQuery {
+    /// Only keys starting with this, e.g. `qa.`
+    pub prefix: Option<String>,
+}
+
+/// `PUT /api/tasks/:task_id/context/:key` body
+#[derive(Debug, Clone, Deserialize, Validate, Schematic)]
+pub struct TaskContextWriteRequest {
+    pub value: Value,
+    /// Only write if the entry is at this version (`0` = does not exist yet)
+    #[validate(range(min = 0))]
+    pub expected_version: Option<i32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_context_key() {
+        assert!(valid_context_key("ba.analysis"));
+        assert!(valid_context_key("qa:findings_v2-final"));
+        assert!(!valid_context_key(""));
+        assert!(!valid_context_key("coding/decisions"));
+        assert!(!valid_context_key("with space"));
+        assert!(!valid_context_key(&"k".repeat(MAX_CONTEXT_KEY_LEN + 1)));
+    }
+}
@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::agent::AgentRole;
+
+// ============================================================================
+// Prompt Template
+// ============================================================================
+
+/// A shared prompt template with its latest version
+#[derive(Debug, Clone)]
+pub struct PromptTemplate {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    /// Role the template is written for (None = any role)
+    pub role: Option<AgentRole>,
+    pub version: i32,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One saved revision of a prompt template
+#[derive(Debug, Clone)]
+pub struct PromptTemplateVersion {
+    pub id: Uuid,
+    pub template_id: Uuid,
+    pub version: i32,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// ============================================================================
+// API DTOs
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct PromptTemplateResponse {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<AgentRole>,
+    pub version: i32,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<PromptTemplate> for PromptTemplateResponse {
+    fn from(t: PromptTemplate) -> Self {
+        Self {
+            id: t.id,
+            name: t.name,
+            description: t.description,
+            role: t.role,
+            version: t.version,
+            content: t.content,
+            created_at: t.created_at,
+            updated_at: t.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct PromptTemplateVersionResponse {
+    pub id: Uuid,
+    pub template_id: Uuid,
+    pub version: i32,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<PromptTemplateVersion> for PromptTemplateVersionResponse {
+    fn from(v: PromptTemplateVersion) -> Self {
+        Self {
+            id: v.id,
+            template_id: v.template_id,
+            version: v.version,
+            content: v.content,
+            created_at: v.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Schematic)]
+pub struct PromptTemplateCreateRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub role: Option<AgentRole>,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Schematic)]
+pub struct PromptTemplateUpdateRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// New content; saved as a new version when it differs from the latest
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Schematic)]
+pub struct PromptTemplateRollbackRequest {
+    /// Version whose content becomes the new latest version
+    pub version: i32,
+}
+
+/// A role template referenced by a project
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct ProjectTemplateRef {
+    pub role: AgentRole,
+    pub template_id: Uuid,
+    pub template_name: String,
+    pub version: i32,
+}
+
+#[derive(Debug, Clone, Deserialize, Schematic)]
+pub struct ProjectTemplateAssignRequest {
+    /// Template to use for the role; null falls back to the project's inline template
+    pub template_id: Option<Uuid>,
+}
@@ -0,0 +1,407 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Longest span a single entry may cover
+pub const MAX_ENTRY_HOURS: i64 = 24;
+
+/// Who spent the time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Schematic)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeSource {
+    /// Logged by a person, by hand or with a timer
+    Human,
+    /// An agent session run for the task, recorded when it ended
+    Agent,
+}
+
+// ============================================================================
+// Time Entry
+// ============================================================================
+
+/// Time a person logged on a task
+#[derive(Debug, Clone)]
+pub struct TaskTimeEntry {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub project_id: Uuid,
+    /// Token ID of the person, e.g. `user` or `oidc:<subject>`
+    pub user_id: String,
+    pub started_at: DateTime<Utc>,
+    /// `None` while the timer runs
+    pub ended_at: Option<DateTime<Utc>>,
+    pub note: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A recorded agent session of a task
+#[derive(Debug, Clone)]
+pub struct AgentSessionTime {
+    pub session_id: Uuid,
+    pub task_id: Uuid,
+    pub role: String,
+    pub spawned_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+    pub duration_secs: i64,
+}
+
+/// Check a span someone logs by hand
+pub fn check_span(
+    started_at: DateTime<Utc>,
+    ended_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    if ended_at <= started_at {
+        return Err("ended_at must be after started_at".to_string());
+    }
+    if ended_at > now + Duration::minutes(1) {
+        return Err("time entries cannot end in the future".to_string());
+    }
+    if ended_at - started_at > Duration::hours(MAX_ENTRY_HOURS) {
+        return Err(format!(
+            "a time entry covers at most {} hours",
+            MAX_ENTRY_HOURS
+        ));
+    }
+    Ok(())
+}
+
+// ============================================================================
+// API DTOs
+// ============================================================================
+
+/// A human or agent time entry of a task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct TimeEntryResponse {
+    /// Entry id, or the session id for agent time
+    pub id: Uuid,
+    pub source: TimeSource,
+    /// Person who logged human time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// Role of the agent for agent time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    /// Zero while a timer runs
+    pub duration_secs: i64,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub note: String,
+    pub running: bool,
+}
+
+impl From<TaskTimeEntry> for TimeEntryResponse {
+    fn from(entry: TaskTimeEntry) -> Self {
+        Self {
+            id: entry.id,
+            source: TimeSource::Human,
+            user_id: Some(entry.user_id),
+            role: None,
+            started_at: entry.started_at,
+            duration_secs: entry
+                .ended_at
+                .map_or(0, |ended| (ended - entry.started_at).num_seconds()),
+            ended_at: entry.ended_at,
+            note: entry.note,
+            running: entry.ended_at.is_none(),
+        }
+    }
+}
+
+impl From<AgentSessionTime> for TimeEntryResponse {
+    fn from(session: AgentSessionTime) -> Self {
+        Self {
+            id: session.session_id,
+            source: TimeSource::Agent,
+            user_id: None,
+            role: Some(session.role),
+            started_at: session.spawned_at,
+            ended_at: Some(session.completed_at),
+            duration_secs: session.duration_secs,
+            note: String::new(),
+            running: false,
+        }
+    }
+}
+
+/// `GET /api/tasks/:task_id/time-entries` response
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct TaskTimeResponse {
+    /// Newest first
+    pub entries: Vec<TimeEntryResponse>,
+    /// Finished human entries; running timers are not counted
+    pub human_secs: i64,
+    pub agent_secs: i64,
+    /// The task's estimate, for comparison
+    pub estimate_minutes: Option<i32>,
+}
+
+impl TaskTimeResponse {
+    pub fn new(
+        human: Vec<TaskTimeEntry>,
+        agent: Vec<AgentSessionTime>,
+        estimate_minutes: Option<i32>,
+    ) -> Self {
+        let mut entries: Vec<TimeEntryResponse> = human
+            .into_iter()
+            .map(Into::into)
+            .chain(agent.into_iter().map(Into::into))
+            .collect();
+        entries.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+
+        let total = |source| {
+            entries
+                .iter()
+                .filter(|e| e.source == source)
+                .map(|e| e.duration_secs)
+                .sum()
+        };
+        Self {
+            human_secs: total(TimeSource::Human),
+            agent_secs: total(TimeSource::Agent),
+            entries,
+            estimate_minutes,
+        }
+    }
+}
+
+/// `POST /api/tasks/:task_id/time-entries` body: a finished span
+#[derive(Debug, Clone, Deserialize, Validate, Schematic)]
+pub struct LogTimeRequest {
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    #[serde(default)]
+    #[validate(length(max = 1000))]
+    pub note: String,
+}
+
+/// `POST /api/tasks/:task_id/time-entries/start` body
+#[derive(Debug, Clone, Default, Deserialize, Validate, Schematic)]
+pub struct StartTimerRequest {
+    #[serde(default)]
+    #[validate(length(max = 1000))]
+    pub note: String,
+}
+
+/// `GET /api/projects/:project_id/time-report` query parameters
+#[derive(Debug, Clone, Default, Deserialize, Schematic)]
+pub struct TimeReportQuery {
+    /// Only time started at or after this (default: 30 days ago)
+    pub since: Option<DateTime<Utc>>,
+    /// Only time started before this (default: now)
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// Human and agent time of one task, person or role
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct TimeTotals {
+    pub human_secs: i64,
+    pub agent_secs: i64,
+}
+
+impl TimeTotals {
+    fn add(&mut self, source: TimeSource, secs: i64) {
+        match source {
+            TimeSource::Human => self.human_secs += secs,
+            TimeSource::Agent => self.agent_secs += secs,
+        }
+    }
+}
+
+/// Time spent on one task in the report window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct TaskTimeTotals {
+    pub task_id: Uuid,
+    /// First line of the task
+    pub title: String,
+    pub estimate_minutes: Option<i32>,
+    #[serde(flatten)]
+    #[schematic(flatten)]
+    pub totals: TimeTotals,
+}
+
+/// Time spent by one person (human time) or agent role (agent time)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct ContributorTime {
+    pub source: TimeSource,
+    /// Token ID of the person or the agent role
+    pub key: String,
+    pub secs: i64,
+}
+
+/// One finished span counted in a project report
+#[derive(Debug, Clone)]
+pub struct TimeSample {
+    pub task_id: Uuid,
+    pub title: String,
+    pub estimate_minutes: Option<i32>,
+    pub source: TimeSource,
+    /// Token ID for human time, role for agent time
+    pub key: String,
+    pub secs: i64,
+}
+
+/// `GET /api/projects/:project_id/time-report` response
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct ProjectTimeReport {
+    pub project_id: Uuid,
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    #[serde(flatten)]
+    #[schematic(flatten)]
+    pub totals: TimeTotals,
+    /// Most time first
+    pub tasks: Vec<TaskTimeTotals>,
+    /// Most time first
+    pub contributors: Vec<ContributorTime>,
+}
+
+impl ProjectTimeReport {
+    pub fn from_samples(
+        project_id: Uuid,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        samples: Vec<TimeSample>,
+    ) -> Self {
+        let mut totals = TimeTotals::default();
+        let mut tasks: BTreeMap<Uuid, TaskTimeTotals> = BTreeMap::new();
+        let mut contributors: BTreeMap<(TimeSource, String), i64> = BTreeMap::new();
+
+        for sample in samples {
+            totals.add(sample.source, sample.secs);
+            tasks
+                .entry(sample.task_id)
+                .or_insert_with(|| TaskTimeTotals {
+                    task_id: sample.task_id,
+                    title: sample.title.lines().next().unwrap_or("").trim().to_string(),
+                    estimate_minutes: sample.estimate_minutes,
+                    totals: TimeTotals::default(),
+                })
+                .totals
+                .add(sample.source, sample.secs);
+            *contributors.entry((sample.source, sample.key)).or_default() += sample.secs;
+        }
+
+        let mut tasks: Vec<TaskTimeTotals> = tasks.into_values().collect();
+        tasks.sort_by_key(|t| -(t.totals.human_secs + t.totals.agent_secs));
+        let mut contributors: Vec<ContributorTime> = contributors
+            .into_iter()
+            .map(|((source, key), secs)| ContributorTime { source, key, secs })
+            .collect();
+        contributors.sort_by_key(|c| -c.secs);
+
+        Self {
+            project_id,
+            since,
+            until,
+            totals,
+            tasks,
+            contributors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(&format!("2026-03-02T{:02}:00:00Z", hour))
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_check_span() {
+        assert!(check_span(at(9), at(10), at(12)).is_ok());
+        assert!(check_span(at(10), at(10), at(12)).is_err());
+        assert!(check_span(at(9), at(13), at(12)).is_err());
+        assert!(check_span(at(9) - Duration::days(2), at(10), at(12)).is_err());
+    }
+
+    #[test]
+    fn test_task_time_totals() {
+        let task_id = Uuid::new_v4();
+        let human = |started: u32, ended: Option<u32>| TaskTimeEntry {
+            id: Uuid::new_v4(),
+            task_id,
+            project_id: Uuid::nil(),
+            user_id: "user".to_string(),
+            started_at: at(started),
+            ended_at: ended.map(at),
+            note: String::new(),
+            created_at: at(started),
+        };
+        let agent = AgentSessionTime {
+            session_id: Uuid::new_v4(),
+            task_id,
+            role: "coding".to_string(),
+            spawned_at: at(11),
+            completed_at: at(12),
+            duration_secs: 3600,
+        };
+
+        let response = TaskTimeResponse::new(
+            vec![human(8, Some(10)), human(13, None)],
+            vec![agent],
+            Some(60),
+        );
+        assert_eq!(response.human_secs, 2 * 3600);
+        assert_eq!(response.agent_secs, 3600);
+        let sources: Vec<(TimeSource, bool)> = response
+            .entries
+            .iter()
+            .map(|e| (e.source, e.running))
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                (TimeSource::Human, true),
+                (TimeSource::Agent, false),
+                (TimeSource::Human, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_project_report() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let sample = |task_id, source, key: &str, secs| TimeSample {
+            task_id,
+            title: "Fix login\n\ndetails".to_string(),
+            estimate_minutes: None,
+            source,
+            key: key.to_string(),
+            secs,
+        };
+        let report = ProjectTimeReport::from_samples(
+            Uuid::nil(),
+            at(0),
+            at(23),
+            vec![
+                sample(a, TimeSource::Human, "user", 600),
+                sample(b, TimeSource::Agent, "coding", 3000),
+                sample(a, TimeSource::Agent, "coding", 1200),
+                sample(a, TimeSource::Human, "oidc:ann", 300),
+            ],
+        );
+        assert_eq!(
+            report.totals,
+            TimeTotals {
+                human_secs: 900,
+                agent_secs: 4200
+            }
+        );
+        assert_eq!(report.tasks[0].task_id, b);
+        assert_eq!(report.tasks[1].title, "Fix login");
+        assert_eq!(report.tasks[1].totals.human_secs, 900);
+        assert_eq!(report.contributors[0].key, "coding");
+        assert_eq!(report.contributors[0].secs, 4200);
+        assert_eq!(report.contributors.len(), 3);
+    }
+}
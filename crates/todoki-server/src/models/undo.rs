@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use conservator::TextEnum;
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// A mutation that `POST /api/undo` can revert
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Schematic, TextEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum UndoAction {
+    /// Reverted from the task's latest status-change event
+    TaskStatus,
+    TaskArchive,
+    TaskUnarchive,
+    /// Recreated from the snapshot taken before deleting
+    TaskDelete,
+    /// Reverted like `POST /api/projects/:id/restore`
+    ProjectArchive,
+}
+
+/// A recorded mutation in the undo log
+#[derive(Debug, Clone)]
+pub struct UndoEntry {
+    pub id: Uuid,
+    pub token_id: String,
+    pub action: UndoAction,
+    pub target_id: Uuid,
+    pub snapshot: Option<Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+// ============================================================================
+// API DTOs
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Schematic)]
+pub struct UndoRequest {
+    /// Mutations to revert, newest first (default 1)
+    #[serde(default)]
+    pub count: Option<usize>,
+}
+
+/// Outcome of reverting one logged mutation
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct UndoResult {
+    pub id: Uuid,
+    pub action: UndoAction,
+    pub target_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub undone: bool,
+    /// Why the mutation could not be reverted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct UndoResponse {
+    pub results: Vec<UndoResult>,
+}
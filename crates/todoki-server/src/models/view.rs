@@ -0,0 +1,221 @@
+use chrono::{DateTime, Utc};
+use conservator::{Creatable, Domain};
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::task::TaskStatus;
+
+/// Longest text query of a view (characters)
+pub const MAX_VIEW_QUERY_LEN: usize = 200;
+/// Most tasks a view returns
+pub const VIEW_TASK_LIMIT: i64 = 500;
+
+// ============================================================================
+// Saved View
+// ============================================================================
+
+/// A named task filter shared by everyone using the instance
+#[derive(Debug, Clone, Domain)]
+#[domain(table = "saved_views")]
+pub struct SavedView {
+    #[domain(primary_key)]
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    /// [`ViewFilter`] as JSON
+    pub filter: Value,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SavedView {
+    /// Parsed filter; an unreadable one matches like an empty filter
+    pub fn filter(&self) -> ViewFilter {
+        serde_json::from_value(self.filter.clone()).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Creatable)]
+pub struct CreateSavedView {
+    pub name: String,
+    pub description: Option<String>,
+    pub filter: Value,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl CreateSavedView {
+    pub fn new(name: String, description: Option<String>, filter: &ViewFilter) -> Self {
+        let now = Utc::now();
+        Self {
+            name,
+            description,
+            filter: serde_json::to_value(filter).unwrap_or_default(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Order of the tasks in a view
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, Schematic)]
+#[serde(rename_all = "kebab-case")]
+pub enum ViewSort {
+    /// Manual board rank, then priority
+    #[default]
+    Board,
+    /// Highest priority first
+    Priority,
+    Newest,
+    Oldest,
+    /// Earliest due date first, undated last
+    Due,
+}
+
+impl ViewSort {
+    pub fn order_by(&self) -> &'static str {
+        match self {
+            ViewSort::Board => "t.rank ASC NULLS LAST, t.priority DESC, t.create_at DESC",
+            ViewSort::Priority => "t.priority DESC, t.create_at DESC",
+            ViewSort::Newest => "t.create_at DESC",
+            ViewSort::Oldest => "t.create_at ASC",
+            ViewSort::Due => "t.due_at ASC NULLS LAST, t.priority DESC",
+        }
+    }
+}
+
+/// Which tasks a view shows; empty lists and absent fields match anything
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct ViewFilter {
+    /// Built-in statuses
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub statuses: Vec<TaskStatus>,
+    /// Custom workflow states; a task matches either list
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub states: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub project_ids: Vec<Uuid>,
+    /// Agent executing the task
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<Uuid>,
+    /// Case-insensitive substring of the task content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    #[serde(default)]
+    pub include_archived: bool,
+    #[serde(default)]
+    pub sort: ViewSort,
+}
+
+impl ViewFilter {
+    /// Check the text query; returns a message for the first problem
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(query) = &self.query
+            && query.chars().count() > MAX_VIEW_QUERY_LEN
+        {
+            return Err(format!(
+                "query must be at most {} characters",
+                MAX_VIEW_QUERY_LEN
+            ));
+        }
+        Ok(())
+    }
+
+    /// `ILIKE` pattern for the text query, with wildcards escaped
+    pub fn like_pattern(&self) -> Option<String> {
+        let query = self.query.as_deref().map(str::trim).filter(|q| !q.is_empty())?;
+        let escaped = query
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        Some(format!("%{}%", escaped))
+    }
+}
+
+// ============================================================================
+// API DTOs
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct SavedViewResponse {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub filter: ViewFilter,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<SavedView> for SavedViewResponse {
+    fn from(v: SavedView) -> Self {
+        let filter = v.filter();
+        Self {
+            id: v.id,
+            name: v.name,
+            description: v.description,
+            filter,
+            created_at: v.created_at,
+            updated_at: v.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Schematic, Validate)]
+pub struct SavedViewCreateRequest {
+    #[validate(
+        length(min = 1, max = "crate::api::validation::MAX_NAME_LEN"),
+        custom(function = "crate::api::validation::not_blank")
+    )]
+    pub name: String,
+    #[validate(length(max = "crate::api::validation::MAX_DESCRIPTION_LEN"))]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub filter: ViewFilter,
+}
+
+#[derive(Debug, Clone, Deserialize, Schematic, Validate)]
+pub struct SavedViewUpdateRequest {
+    #[validate(
+        length(min = 1, max = "crate::api::validation::MAX_NAME_LEN"),
+        custom(function = "crate::api::validation::not_blank")
+    )]
+    pub name: Option<String>,
+    #[validate(length(max = "crate::api::validation::MAX_DESCRIPTION_LEN"))]
+    pub description: Option<String>,
+    /// Replaces the whole filter
+    pub filter: Option<ViewFilter>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        let filter: ViewFilter = serde_json::from_value(serde_json::json!({
+            "statuses": ["todo", "in-progress"],
+            "query": " 50%_off ",
+            "sort": "due"
+        }))
+        .unwrap();
+        assert_eq!(filter.statuses, vec![TaskStatus::Todo, TaskStatus::InProgress]);
+        assert_eq!(filter.like_pattern().as_deref(), Some("%50\\%\\_off%"));
+        assert_eq!(filter.sort.order_by(), "t.due_at ASC NULLS LAST, t.priority DESC");
+        assert!(filter.validate().is_ok());
+
+        let empty = ViewFilter::default();
+        assert_eq!(empty.like_pattern(), None);
+        assert_eq!(empty.sort, ViewSort::Board);
+        assert_eq!(serde_json::to_value(&empty).unwrap()["sort"], "board");
+
+        let long = ViewFilter {
+            query: Some("x".repeat(MAX_VIEW_QUERY_LEN + 1)),
+            ..Default::default()
+        };
+        assert!(long.validate().is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use chrono::{DateTime, Utc};
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// ============================================================================
+// Watch
+// ============================================================================
+
+/// A task or agent session someone watches
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct Watch {
+    pub id: Uuid,
+    /// Token ID of the watcher, e.g. `user` or `oidc:<subject>`
+    pub user_id: String,
+    /// Set when a task is watched
+    pub task_id: Option<Uuid>,
+    /// Set when an agent session is watched
+    pub session_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Watch {
+    pub fn target(&self) -> Option<WatchTarget> {
+        match (self.task_id, self.session_id) {
+            (Some(task_id), None) => Some(WatchTarget::Task(task_id)),
+            (None, Some(session_id)) => Some(WatchTarget::Session(session_id)),
+            _ => None,
+        }
+    }
+}
+
+/// What a watch is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchTarget {
+    Task(Uuid),
+    Session(Uuid),
+}
+
+impl WatchTarget {
+    /// `(task_id, session_id)` as stored in a watch
+    pub fn ids(self) -> (Option<Uuid>, Option<Uuid>) {
+        match self {
+            WatchTarget::Task(task_id) => (Some(task_id), None),
+            WatchTarget::Session(session_id) => (None, Some(session_id)),
+        }
+    }
+}
+
+// ============================================================================
+// API DTOs
+// ============================================================================
+
+/// Request to watch a task or an agent session; exactly one is given
+#[derive(Debug, Clone, Deserialize, Schematic)]
+pub struct CreateWatchRequest {
+    pub task_id: Option<Uuid>,
+    pub session_id: Option<Uuid>,
+}
+
+impl CreateWatchRequest {
+    pub fn target(&self) -> Result<WatchTarget, String> {
+        match (self.task_id, self.session_id) {
+            (Some(task_id), None) => Ok(WatchTarget::Task(task_id)),
+            (None, Some(session_id)) => Ok(WatchTarget::Session(session_id)),
+            _ => Err("give either task_id or session_id".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watch_request_needs_one_target() {
+        let id = Uuid::new_v4();
+        let request = |task_id, session_id| CreateWatchRequest {
+            task_id,
+            session_id,
+        };
+
+        assert_eq!(request(Some(id), None).target(), Ok(WatchTarget::Task(id)));
+        assert_eq!(
+            request(None, Some(id)).target(),
+            Ok(WatchTarget::Session(id))
+        );
+        assert!(request(None, None).target().is_err());
+        assert!(request(Some(id), Some(id)).target().is_err());
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::{BTreeMap, HashSet};
+
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+
+use super::task::TaskStatus;
+
+// ============================================================================
+// Project Workflow
+// ============================================================================
+
+/// Board column group a workflow state belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Schematic)]
+#[serde(rename_all = "lowercase")]
+pub enum StateCategory {
+    Backlog,
+    Active,
+    Done,
+}
+
+impl StateCategory {
+    /// Built-in status of tasks in a custom state of this category
+    pub fn default_status(&self) -> TaskStatus {
+        match self {
+            StateCategory::Backlog => TaskStatus::Backlog,
+            StateCategory::Active => TaskStatus::InProgress,
+            StateCategory::Done => TaskStatus::Done,
+        }
+    }
+}
+
+impl From<TaskStatus> for StateCategory {
+    fn from(status: TaskStatus) -> Self {
+        match status {
+            TaskStatus::Backlog | TaskStatus::Todo => StateCategory::Backlog,
+            s if s.is_terminal() => StateCategory::Done,
+            _ => StateCategory::Active,
+        }
+    }
+}
+
+/// One state of a project workflow
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct WorkflowState {
+    /// Stable identifier, e.g. `blocked`; a built-in status (`todo`) keeps
+    /// its meaning for agents and reports
+    pub key: String,
+    /// Display name
+    pub name: String,
+    pub category: StateCategory,
+    /// Built-in status tasks in this state report; defaults to the status
+    /// named by `key`, else the category's (backlog, in-progress, done)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<TaskStatus>,
+}
+
+impl WorkflowState {
+    fn builtin(status: TaskStatus) -> Self {
+        let key = status_key(status);
+        Self {
+            name: key.replace('-', " "),
+            key,
+            category: status.into(),
+            status: None,
+        }
+    }
+
+    /// Built-in status of tasks in this state
+    pub fn task_status(&self) -> TaskStatus {
+        self.status
+            .or_else(|| builtin_status(&self.key))
+            .unwrap_or_else(|| self.category.default_status())
+    }
+}
+
+/// A named priority value
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct PriorityLevel {
+    pub value: i32,
+    pub name: String,
+}
+
+/// Per-project task states, allowed transitions and priority scale
+///
+/// Projects without one use [`Workflow::builtin`]: every built-in status,
+/// any transition and any integer priority.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct Workflow {
+    /// States in board order
+    #[serde(default)]
+    pub states: Vec<WorkflowState>,
+    /// Allowed next states by state key; states not listed may move anywhere
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub transitions: BTreeMap<String, Vec<String>>,
+    /// Allowed priorities, lowest first (empty = any integer)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub priorities: Vec<PriorityLevel>,
+}
+
+/// Where a task ends up after a move: its built-in status and, for states
+/// that are not a built-in status, the custom state key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateTarget {
+    pub status: TaskStatus,
+    pub workflow_state: Option<String>,
+}
+
+const BUILTIN_STATUSES: [TaskStatus; 17] = [
+    TaskStatus::Backlog,
+    TaskStatus::Todo,
+    TaskStatus::PlanPending,
+    TaskStatus::PlanInProgress,
+    TaskStatus::PlanReview,
+    TaskStatus::PlanDone,
+    TaskStatus::CodingPending,
+    TaskStatus::CodingInProgress,
+    TaskStatus::CodingReview,
+    TaskStatus::CodingDone,
+    TaskStatus::CrossReviewPending,
+    TaskStatus::CrossReviewInProgress,
+    TaskStatus::CrossReviewPass,
+    TaskStatus::CrossReviewFail,
+    TaskStatus::Done,
+    TaskStatus::InProgress,
+    TaskStatus::InReview,
+];
+
+/// API name of a built-in status, e.g. `in-progress`
+pub fn status_key(status: TaskStatus) -> String {
+    serde_json::to_value(status)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn builtin_status(key: &str) -> Option<TaskStatus> {
+    serde_json::from_value(serde_json::Value::String(key.to_string())).ok()
+}
+
+impl Workflow {
+    /// The default workflow: the built-in statuses, unrestricted
+    pub fn builtin() -> Self {
+        Self {
+            states: BUILTIN_STATUSES.into_iter().map(WorkflowState::builtin).collect(),
+            transitions: BTreeMap::new(),
+            priorities: Vec::new(),
+        }
+    }
+
+    /// Nothing configured; saving it resets the project to the default
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty() && self.transitions.is_empty() && self.priorities.is_empty()
+    }
+
+    /// Check keys, names, transitions and priorities; returns a message for
+    /// the first problem
+    pub fn validate(&self) -> Result<(), String> {
+        if self.states.is_empty() && !(self.transitions.is_empty() && self.priorities.is_empty()) {
+            return Err("a workflow needs at least one state".to_string());
+        }
+        let mut keys = HashSet::new();
+        for state in &self.states {
+            let valid_key = !state.key.is_empty()
+                && state.key.len() <= 64
+                && state
+                    .key
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+            if !valid_key {
+                return Err(format!(
+                    "invalid state key '{}', use lowercase letters, digits, '-' and '_'",
+                    state.key
+                ));
+            }
+            if state.name.trim().is_empty() {
+                return Err(format!("state '{}' needs a name", state.key));
+            }
+            if !keys.insert(state.key.as_str()) {
+                return Err(format!("duplicate state '{}'", state.key));
+            }
+        }
+        for (from, targets) in &self.transitions {
+            for key in std::iter::once(from).chain(targets) {
+                if !keys.contains(key.as_str()) {
+                    return Err(format!("transition refers to unknown state '{}'", key));
+                }
+            }
+        }
+        let mut values = HashSet::new();
+        for level in &self.priorities {
+            if level.name.trim().is_empty() {
+                return Err(format!("priority {} needs a name", level.value));
+            }
+            if !values.insert(level.value) {
+                return Err(format!("duplicate priority {}", level.value));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn state(&self, key: &str) -> Option<&WorkflowState> {
+        self.states.iter().find(|s| s.key == key)
+    }
+
+    /// Check that a task in state `from` may move to `to`
+    ///
+    /// A task in a state the workflow no longer has may move anywhere.
+    pub fn transition(&self, from: &str, to: &str) -> Result<StateTarget, String> {
+        let target = self
+            .state(to)
+            .ok_or_else(|| format!("'{}' is not a state of this project's workflow", to))?;
+        if from != to
+            && self.state(from).is_some()
+            && let Some(allowed) = self.transitions.get(from)
+            && !allowed.iter().any(|k| k == to)
+        {
+            return Err(format!("cannot move a task from '{}' to '{}'", from, to));
+        }
+        Ok(self.target(target))
+    }
+
+    /// Where a task in state `state` lands
+    pub fn target(&self, state: &WorkflowState) -> StateTarget {
+        let status = state.task_status();
+        let custom = builtin_status(&state.key) != Some(status);
+        let workflow_state = custom.then(|| state.key.clone());
+        StateTarget {
+            status,
+            workflow_state,
+        }
+    }
+
+    /// Check `priority` against the priority scale
+    pub fn check_priority(&self, priority: i32) -> Result<(), String> {
+        if self.priorities.is_empty() || self.priorities.iter().any(|p| p.value == priority) {
+            return Ok(());
+        }
+        let allowed: Vec<String> = self.priorities.iter().map(|p| p.value.to_string()).collect();
+        Err(format!(
+            "priority {} is not on this project's scale ({})",
+            priority,
+            allowed.join(", ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(key: &str, category: StateCategory) -> WorkflowState {
+        WorkflowState {
+            key: key.to_string(),
+            name: key.to_string(),
+            category,
+            status: None,
+        }
+    }
+
+    fn workflow() -> Workflow {
+        Workflow {
+            states: vec![
+                state("todo", StateCategory::Backlog),
+                state("in-progress", StateCategory::Active),
+                state("blocked", StateCategory::Active),
+                state("in-qa", StateCategory::Active),
+                state("done", StateCategory::Done),
+            ],
+            transitions: BTreeMap::from([(
+                "blocked".to_string(),
+                vec!["in-progress".to_string()],
+            )]),
+            priorities: vec![
+                PriorityLevel {
+                    value: 0,
+                    name: "low".to_string(),
+                },
+                PriorityLevel {
+                    value: 5,
+                    name: "high".to_string(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_builtin() {
+        let builtin = Workflow::builtin();
+        assert!(builtin.validate().is_ok());
+        assert_eq!(builtin.states[0].key, "backlog");
+        assert_eq!(
+            builtin.transition("backlog", "in-review").unwrap(),
+            StateTarget {
+                status: TaskStatus::InReview,
+                workflow_state: None,
+            }
+        );
+        assert_eq!(builtin.state("done").unwrap().category, StateCategory::Done);
+        assert!(builtin.check_priority(42).is_ok());
+    }
+
+    #[test]
+    fn test_transition() {
+        let workflow = workflow();
+        assert!(workflow.validate().is_ok());
+
+        // Custom states report their category's built-in status
+        assert_eq!(
+            workflow.transition("todo", "blocked").unwrap(),
+            StateTarget {
+                status: TaskStatus::InProgress,
+                workflow_state: Some("blocked".to_string()),
+            }
+        );
+        assert_eq!(workflow.transition("blocked", "in-progress").unwrap().workflow_state, None);
+        assert!(workflow.transition("blocked", "done").is_err());
+        assert!(workflow.transition("todo", "plan-pending").is_err());
+        // Unknown current state: anything goes
+        assert!(workflow.transition("plan-pending", "done").is_ok());
+
+        assert!(workflow.check_priority(5).is_ok());
+        assert!(workflow.check_priority(3).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut invalid = workflow();
+        invalid.states.push(state("blocked", StateCategory::Active));
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = workflow();
+        invalid.states[0].key = "To Do".to_string();
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = workflow();
+        invalid
+            .transitions
+            .insert("todo".to_string(), vec!["shipped".to_string()]);
+        assert!(invalid.validate().is_err());
+
+        assert!(Workflow::default().is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+//! Client address of a request, taking trusted reverse proxies into account
+//!
+//! Behind a proxy the TCP peer is the proxy itself, so the client is read
+//! from `proxy.client_ip_header` (`X-Forwarded-For` by default) - but only
+//! when the peer is a trusted proxy, since anyone can send the header.
+//! The peer comes from the connection info the server records; without it
+//! the client address is unknown.
+
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+use gotcha::axum::extract::{ConnectInfo, Request, State};
+use gotcha::axum::middleware::Next;
+use gotcha::axum::response::Response;
+
+use crate::config::ProxySettings;
+
+/// Address of the client that sent a request, when it is known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+/// An address or CIDR block, e.g. `10.0.0.0/8` or `::1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpRange {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpRange {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid address {}", s))?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max)
+                .ok_or_else(|| format!("invalid prefix length in {}", s))?,
+            None => max,
+        };
+        Ok(Self { addr, prefix })
+    }
+}
+
+/// Proxies whose forwarding header is believed
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    ranges: Vec<IpRange>,
+    header: String,
+}
+
+impl TrustedProxies {
+    pub fn from_settings(settings: &ProxySettings) -> Result<Self, String> {
+        let ranges = settings
+            .trusted_proxies
+            .iter()
+            .map(|range| range.parse())
+            .collect::<Result<Vec<IpRange>, _>>()?;
+        Ok(Self {
+            ranges,
+            header: settings.client_ip_header.to_ascii_lowercase(),
+        })
+    }
+
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.ranges.iter().any(|range| range.contains(ip))
+    }
+
+    /// The client behind `peer`, given the values of the forwarding header
+    ///
+    /// Forwarded-for lists are walked from the right, skipping trusted
+    /// proxies; the first other hop is the client. Entries left of it were
+    /// supplied by the client and are not believed.
+    pub fn client_ip<'a>(
+        &self,
+        peer: Option<IpAddr>,
+        forwarded: impl DoubleEndedIterator<Item = &'a str>,
+    ) -> Option<IpAddr> {
+        let peer = peer?;
+        if !self.is_trusted(peer) {
+            return Some(peer);
+        }
+
+        let mut client = peer;
+        for hop in forwarded.rev().flat_map(|value| value.rsplit(',')) {
+            match hop.trim().parse::<IpAddr>() {
+                Ok(ip) => {
+                    client = ip;
+                    if !self.is_trusted(ip) {
+                        break;
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+        Some(client)
+    }
+}
+
+/// Records the [`ClientIp`] of each request
+pub async fn client_ip_middleware(
+    State(proxies): State<TrustedProxies>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let forwarded = request
+        .headers()
+        .get_all(proxies.header.as_str())
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<_>>();
+    let client = proxies.client_ip(peer, forwarded.into_iter());
+
+    request.extensions_mut().insert(ClientIp(client));
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxies(ranges: &[&str]) -> TrustedProxies {
+        TrustedProxies::from_settings(&ProxySettings {
+            trusted_proxies: ranges.iter().map(|r| r.to_string()).collect(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_ip_range() {
+        let range: IpRange = "10.0.0.0/8".parse().unwrap();
+        assert!(range.contains(ip("10.1.2.3")));
+        assert!(range.contains(ip("::ffff:10.1.2.3")));
+        assert!(!range.contains(ip("11.0.0.1")));
+
+        let range: IpRange = "fd00::/8".parse().unwrap();
+        assert!(range.contains(ip("fd12::1")));
+        assert!(!range.contains(ip("10.0.0.1")));
+
+        assert!(
+            "0.0.0.0/0"
+                .parse::<IpRange>()
+                .unwrap()
+                .contains(ip("1.2.3.4"))
+        );
+        assert!("10.0.0.0/33".parse::<IpRange>().is_err());
+        assert!("proxy".parse::<IpRange>().is_err());
+    }
+
+    #[test]
+    fn test_client_ip_only_trusts_configured_proxies() {
+        let proxies = proxies(&["10.0.0.0/8"]);
+        let forwarded = ["6.6.6.6, 1.2.3.4", "10.0.0.2"];
+
+        // The header of an untrusted peer is ignored
+        let client = proxies.client_ip(Some(ip("5.5.5.5")), forwarded.into_iter());
+        assert_eq!(client, Some(ip("5.5.5.5")));
+
+        // Behind trusted proxies the right-most untrusted hop is the client
+        let client = proxies.client_ip(Some(ip("10.0.0.1")), forwarded.into_iter());
+        assert_eq!(client, Some(ip("1.2.3.4")));
+
+        let client = proxies.client_ip(Some(ip("10.0.0.1")), std::iter::empty());
+        assert_eq!(client, Some(ip("10.0.0.1")));
+        assert_eq!(proxies.client_ip(None, forwarded.into_iter()), None);
+    }
+}
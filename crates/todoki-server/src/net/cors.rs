@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+use gotcha::axum::http::{HeaderName, HeaderValue};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+use crate::config::CorsSettings;
+use crate::net::request_id::REQUEST_ID_HEADER;
+
+/// CORS policy for the API; "*" among the origins allows any origin
+pub fn cors_layer(settings: &CorsSettings) -> Result<CorsLayer, String> {
+    let layer = CorsLayer::new()
+        .allow_methods(Any)
+        .allow_headers(Any)
+        // Lets the web UI show the request ID of a failed call
+        .expose_headers([HeaderName::from_static(REQUEST_ID_HEADER)])
+        .max_age(Duration::from_secs(settings.max_age_secs));
+
+    if settings.allowed_origins.iter().any(|origin| origin == "*") {
+        return Ok(layer.allow_origin(Any));
+    }
+    let origins = settings
+        .allowed_origins
+        .iter()
+        .map(|origin| {
+            HeaderValue::from_str(origin).map_err(|_| format!("invalid CORS origin {}", origin))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(layer.allow_origin(AllowOrigin::list(origins)))
+}
@@ -0,0 +1,5 @@
+pub mod client_ip;
+pub mod cors;
+pub mod request_id;
+pub mod static_files;
+pub mod tls;
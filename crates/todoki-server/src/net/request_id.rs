@@ -0,0 +1,92 @@
+//! Per-request IDs for correlating responses with server logs
+//!
+//! A request keeps the `x-request-id` it arrives with (set by a proxy or the
+//! client) when that looks sane, and gets a fresh UUID otherwise. The ID is
+//! stored in the request extensions and, while the request is handled, in a
+//! task-local so error responses, emitted events and undo log rows can carry
+//! it without threading it through every handler. Everything logged while
+//! handling the request is inside a span with the ID, and the response
+//! echoes it in `x-request-id`.
+
+use gotcha::axum::extract::Request;
+use gotcha::axum::http::HeaderValue;
+use gotcha::axum::middleware::Next;
+use gotcha::axum::response::Response;
+use tracing::Instrument;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest incoming ID that is kept
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// ID of the request being handled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+tokio::task_local! {
+    static CURRENT: RequestId;
+}
+
+/// ID of the request handled by the current task, if any
+pub fn current() -> Option<String> {
+    CURRENT.try_with(|id| id.0.clone()).ok()
+}
+
+/// Accept a client-sent ID only if it is short printable ASCII
+fn accept(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_REQUEST_ID_LEN
+        && value.bytes().all(|b| b.is_ascii_graphic())
+}
+
+pub async fn request_id_middleware(mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| accept(v))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let span = tracing::info_span!(
+        "request",
+        request_id = %id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+    let header = HeaderValue::from_str(&id).ok();
+    let id = RequestId(id);
+
+    request.extensions_mut().insert(id.clone());
+    let mut response = CURRENT
+        .scope(id, next.run(request))
+        .instrument(span)
+        .await;
+    if let Some(header) = header {
+        response.headers_mut().insert(REQUEST_ID_HEADER, header);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept() {
+        assert!(accept("3f6c1f0e-8f4b-4a53-9d2e-1b7f2a7c9e10"));
+        assert!(accept("req_abc.123"));
+        assert!(!accept(""));
+        assert!(!accept("has space"));
+        assert!(!accept(&"a".repeat(MAX_REQUEST_ID_LEN + 1)));
+    }
+
+    #[tokio::test]
+    async fn test_current() {
+        assert_eq!(current(), None);
+        let id = RequestId("req-1".to_string());
+        assert_eq!(
+            CURRENT.scope(id, async { current() }).await.as_deref(),
+            Some("req-1")
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Web UI serving
+//!
+//! `GET /` and every path no API route claims serve the web UI bundle. Files
+//! come from `web.dir` when it is set, otherwise from the bundle embedded at
+//! build time (`web/dist`, built with `pnpm build` before `cargo build`).
+//! Paths without a file of their own get `index.html`, so client-side routes
+//! survive a reload; paths that look like files (`/logo.png`) stay 404.
+
+use std::path::{Component, Path, PathBuf};
+
+use gotcha::axum::extract::State;
+use gotcha::axum::http::{header, HeaderValue, StatusCode, Uri};
+use gotcha::axum::response::{IntoResponse, Response};
+use rust_embed::RustEmbed;
+
+use crate::api::error::ApiError;
+use crate::config::Settings;
+
+/// The web UI bundle, embedded when `web/dist` exists at build time
+#[derive(RustEmbed)]
+#[folder = "../../web/dist"]
+#[allow_missing = true]
+struct Bundle;
+
+const INDEX: &str = "index.html";
+
+/// Path prefixes owned by the server; unknown paths under them are API 404s
+const RESERVED_PREFIXES: &[&str] = &["api", "ws", "graphql"];
+
+/// GET /*path - Web UI asset, or `index.html` for client-side routes
+pub async fn serve(State(settings): State<Settings>, uri: Uri) -> Response {
+    let path = uri.path().trim_start_matches('/');
+    if is_reserved(path) {
+        return ApiError::not_found("route not found").into_response();
+    }
+    let Some(relative) = sanitize(path) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let dir = settings.web.dir.as_deref().map(Path::new);
+    if let Some(file) = load(dir, &relative).await {
+        return file_response(&relative, file);
+    }
+    if looks_like_file(&relative) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    match load(dir, Path::new(INDEX)).await {
+        Some(index) => file_response(Path::new(INDEX), index),
+        None => (StatusCode::NOT_FOUND, "web UI is not bundled with this server").into_response(),
+    }
+}
+
+async fn load(dir: Option<&Path>, relative: &Path) -> Option<Vec<u8>> {
+    match dir {
+        Some(dir) => tokio::fs::read(dir.join(relative)).await.ok(),
+        None => Bundle::get(relative.to_str()?).map(|file| file.data.into_owned()),
+    }
+}
+
+fn file_response(relative: &Path, body: Vec<u8>) -> Response {
+    let mime = mime_guess::from_path(relative).first_or_octet_stream();
+    let mut response = body.into_response();
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(mime.as_ref()) {
+        headers.insert(header::CONTENT_TYPE, value);
+    }
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(cache_control(relative)),
+    );
+    response
+}
+
+fn is_reserved(path: &str) -> bool {
+    let first = path.split('/').next().unwrap_or_default();
+    RESERVED_PREFIXES.contains(&first)
+}
+
+/// Request path as a relative file path; `None` when it tries to leave the
+/// bundle
+fn sanitize(path: &str) -> Option<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    if relative.as_os_str().is_empty() {
+        relative.push(INDEX);
+    }
+    Some(relative)
+}
+
+fn looks_like_file(relative: &Path) -> bool {
+    relative.extension().is_some()
+}
+
+/// Vite fingerprints everything under `assets/`; the rest must be revalidated
+fn cache_control(relative: &Path) -> &'static str {
+    if relative.starts_with("assets") {
+        "public, max-age=31536000, immutable"
+    } else {
+        "no-cache"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize(""), Some(PathBuf::from("index.html")));
+        assert_eq!(sanitize("assets/app.js"), Some(PathBuf::from("assets/app.js")));
+        assert_eq!(sanitize("./tasks/today"), Some(PathBuf::from("tasks/today")));
+        assert_eq!(sanitize("../etc/passwd"), None);
+        assert_eq!(sanitize("assets/../../secret"), None);
+        assert_eq!(sanitize("/etc/passwd"), None);
+    }
+
+    #[test]
+    fn test_routing_decisions() {
+        assert!(is_reserved("api/unknown"));
+        assert!(is_reserved("ws"));
+        assert!(!is_reserved("apis"));
+        assert!(!is_reserved("tasks/123"));
+
+        assert!(looks_like_file(Path::new("favicon.ico")));
+        assert!(!looks_like_file(Path::new("projects/abc")));
+
+        assert_eq!(
+            cache_control(Path::new("assets/index-3f2a.js")),
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(cache_control(Path::new("index.html")), "no-cache");
+    }
+}
@@ -0,0 +1,74 @@
+//! Direct HTTPS serving
+//!
+//! The web framework only listens on plain HTTP, so with TLS configured the
+//! app listens on a loopback port and connections to the public address are
+//! decrypted here and piped through to it. The app then sees every request
+//! coming from loopback, so loopback must not be a trusted proxy as well.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::net::{TcpListener, TcpStream};
+use tokio_native_tls::native_tls::{self, Identity};
+use tokio_native_tls::TlsAcceptor;
+use tracing::{debug, warn};
+
+use crate::config::TlsSettings;
+
+/// Connections that have not finished the handshake by then are dropped
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Load the PEM certificate chain and PKCS#8 key from `settings`
+pub fn load_acceptor(settings: &TlsSettings) -> Result<TlsAcceptor, String> {
+    let (Some(cert_path), Some(key_path)) = (&settings.cert_path, &settings.key_path) else {
+        return Err("tls.cert_path and tls.key_path must both be set".to_string());
+    };
+    let cert = std::fs::read(cert_path).map_err(|e| format!("cannot read {}: {}", cert_path, e))?;
+    let key = std::fs::read(key_path).map_err(|e| format!("cannot read {}: {}", key_path, e))?;
+
+    let identity = Identity::from_pkcs8(&cert, &key)
+        .map_err(|e| format!("invalid TLS certificate or key: {}", e))?;
+    let acceptor = native_tls::TlsAcceptor::new(identity)
+        .map_err(|e| format!("failed to set up TLS: {}", e))?;
+    Ok(TlsAcceptor::from(acceptor))
+}
+
+/// Accept TLS connections on `listener` and pipe them to `backend`
+pub async fn serve(listener: TcpListener, acceptor: TlsAcceptor, backend: SocketAddr) {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                warn!(error = %e, "Failed to accept TLS connection");
+                continue;
+            }
+        };
+
+        let acceptor = acceptor.clone();
+        tokio::spawn(async move {
+            let mut tls =
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(tls)) => tls,
+                    Ok(Err(e)) => {
+                        debug!(peer = %peer, error = %e, "TLS handshake failed");
+                        return;
+                    }
+                    Err(_) => {
+                        debug!(peer = %peer, "TLS handshake timed out");
+                        return;
+                    }
+                };
+
+            let mut upstream = match TcpStream::connect(backend).await {
+                Ok(upstream) => upstream,
+                Err(e) => {
+                    warn!(error = %e, "Failed to reach the HTTP listener behind TLS");
+                    return;
+                }
+            };
+            if let Err(e) = tokio::io::copy_bidirectional(&mut tls, &mut upstream).await {
+                debug!(peer = %peer, error = %e, "TLS connection closed with error");
+            }
+        });
+    }
+}
@@ -0,0 +1,207 @@
+//! `@` mentions in task comments
+//!
+//! `@handle` names an agent when it matches the agent's name with spaces
+//! written as `-` (case-insensitive), preferring agents of the task's
+//! project. Otherwise it names a user: `@user` is the shared-token user and
+//! `@alice` the OIDC user `oidc:alice`. A comment with mentions emits
+//! `task.mention`; mentioned users are notified and mentioned agents with
+//! `auto_trigger` are prompted with the comment (see `crate::trigger`).
+
+use todoki_protocol::event_bus::{BuiltinEvent, TaskMentionData};
+use uuid::Uuid;
+
+use crate::auth::USER_TOKEN_ID;
+use crate::db::DatabaseService;
+use crate::event_bus::{EventPublisher, EventScope};
+use crate::models::{Agent, Task, TaskComment};
+
+/// Who a comment mentions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Mentions {
+    /// Token IDs
+    pub users: Vec<String>,
+    pub agent_ids: Vec<Uuid>,
+}
+
+impl Mentions {
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty() && self.agent_ids.is_empty()
+    }
+}
+
+/// Lowercased handles mentioned in `content`, in order, without duplicates
+///
+/// A handle starts after `@` at the start of a word, so addresses like
+/// `me@example.com` are not mentions, and ends before trailing punctuation.
+pub fn handles(content: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    let mut prev: Option<char> = None;
+    for (i, c) in content.char_indices() {
+        let at_word_start = prev.is_none_or(|p| !(p.is_alphanumeric() || p == '@'));
+        prev = Some(c);
+        if c != '@' || !at_word_start {
+            continue;
+        }
+        let rest = &content[i + 1..];
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | ':')))
+            .unwrap_or(rest.len());
+        let handle = rest[..len].trim_end_matches(['.', ':', '-']).to_lowercase();
+        if !handle.is_empty() && !out.contains(&handle) {
+            out.push(handle);
+        }
+    }
+    out
+}
+
+/// An agent as seen by mention resolution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MentionableAgent {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    /// Name with spaces written as `-`, lowercased
+    pub handle: String,
+}
+
+impl From<&Agent> for MentionableAgent {
+    fn from(agent: &Agent) -> Self {
+        Self {
+            id: agent.id,
+            project_id: agent.project_id,
+            handle: agent
+                .name
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join("-")
+                .to_lowercase(),
+        }
+    }
+}
+
+/// Resolve `handles` for a comment on a task of `project_id` against
+/// `agents` and the known `users` (token IDs)
+pub fn resolve(
+    handles: &[String],
+    project_id: Uuid,
+    agents: &[MentionableAgent],
+    users: &[String],
+) -> Mentions {
+    let mut mentions = Mentions::default();
+    for handle in handles {
+        let named: Vec<&MentionableAgent> = agents.iter().filter(|a| a.handle == *handle).collect();
+        let in_project: Vec<&MentionableAgent> = named
+            .iter()
+            .copied()
+            .filter(|a| a.project_id == project_id)
+            .collect();
+        let matched = if in_project.is_empty() { named } else { in_project };
+        if !matched.is_empty() {
+            mentions.agent_ids.extend(matched.iter().map(|a| a.id));
+            continue;
+        }
+
+        let user = if handle == USER_TOKEN_ID {
+            Some(USER_TOKEN_ID.to_string())
+        } else {
+            users
+                .iter()
+                .find(|u| u.to_lowercase() == format!("oidc:{}", handle))
+                .cloned()
+        };
+        if let Some(user) = user
+            && !mentions.users.contains(&user)
+        {
+            mentions.users.push(user);
+        }
+    }
+    mentions
+}
+
+/// Emit `task.mention` for the mentions in `comment`, if it has any
+///
+/// `author` is left out of the mentioned agents so an agent cannot prompt
+/// itself. Failures are logged; the comment is stored either way.
+pub async fn publish(
+    db: &DatabaseService,
+    publisher: &EventPublisher,
+    task: &Task,
+    comment: &TaskComment,
+    author: Option<Uuid>,
+) {
+    let handles = handles(&comment.content);
+    if handles.is_empty() {
+        return;
+    }
+    let known = async {
+        let agents: Vec<MentionableAgent> =
+            db.list_agents().await?.iter().map(Into::into).collect();
+        let users: Vec<String> = db
+            .list_notification_preferences()
+            .await?
+            .into_iter()
+            .map(|p| p.user_id)
+            .collect();
+        crate::Result::Ok((agents, users))
+    };
+    let (agents, users) = match known.await {
+        Ok(known) => known,
+        Err(e) => {
+            tracing::warn!(task_id = %task.id, error = %e, "failed to resolve mentions");
+            return;
+        }
+    };
+
+    let mut mentions = resolve(&handles, task.project_id, &agents, &users);
+    mentions.agent_ids.retain(|id| Some(*id) != author);
+    if mentions.is_empty() {
+        return;
+    }
+
+    let event = BuiltinEvent::TaskMention(TaskMentionData {
+        comment_id: comment.id.to_string(),
+        content: comment.content.clone(),
+        users: mentions.users,
+        agent_ids: mentions.agent_ids.iter().map(Uuid::to_string).collect(),
+    });
+    if let Err(e) = publisher.emit_builtin(event, EventScope::task(task.id)).await {
+        tracing::warn!(task_id = %task.id, error = %e, "failed to emit task.mention");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent(name: &str, project_id: Uuid) -> MentionableAgent {
+        MentionableAgent {
+            id: Uuid::new_v4(),
+            project_id,
+            handle: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_handles() {
+        assert_eq!(
+            handles("@Reviewer please check, cc @alice. Mail me@example.com @alice"),
+            vec!["reviewer", "alice"]
+        );
+        assert_eq!(handles("@ nothing @@x"), Vec::<String>::new());
+        assert_eq!(handles("(@code-bot)"), vec!["code-bot"]);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let project = Uuid::new_v4();
+        let ours = agent("code-bot", project);
+        let theirs = agent("code-bot", Uuid::new_v4());
+        let other = agent("reviewer", Uuid::new_v4());
+        let agents = vec![ours.clone(), theirs, other.clone()];
+        let users = vec!["oidc:Alice".to_string()];
+
+        let handles = handles("@code-bot @reviewer @alice @user @bob");
+        let mentions = resolve(&handles, project, &agents, &users);
+        assert_eq!(mentions.agent_ids, vec![ours.id, other.id]);
+        assert_eq!(mentions.users, vec!["oidc:Alice".to_string(), "user".to_string()]);
+    }
+}
@@ -0,0 +1,381 @@
+//! In-app notification center
+//!
+//! Follows the event bus and turns the events listed in
+//! `notifications.kinds` into notifications for every user who has not
+//! muted the kind: tasks assigned, permission requests left to a human,
+//! failed agent sessions and, for the users named, comment mentions (see
+//! [`mention`]). Status changes, handoffs and agent comments of a task only
+//! notify the users watching it. After storing them it emits
+//! `notification.unread` with each recipient's new unread count, which the
+//! event-bus WebSocket only delivers to that user.
+
+pub mod mention;
+
+use std::sync::{Arc, RwLock};
+
+use todoki_protocol::event_bus::{
+    AgentSessionExitedData, AgentTaskCommentData, BuiltinEvent, EventKind, NotificationUnreadData,
+    PermissionRequestedData, RelaySpawnFailedData, ReviewDecision, TaskHandoffData,
+    TaskMentionData, TaskStatusChangedData,
+};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::auth::USER_TOKEN_ID;
+use crate::config::NotificationSettings;
+use crate::db::DatabaseService;
+use crate::event_bus::{Event, EventPublisher, EventScope};
+use crate::models::{NotificationDraft, NotificationKind, NotificationPreferences};
+use crate::watch::WatchRegistry;
+
+/// Longest task excerpt in a notification title (characters)
+const TITLE_EXCERPT_LEN: usize = 120;
+
+pub struct NotificationCenter {
+    db: Arc<DatabaseService>,
+    publisher: Arc<EventPublisher>,
+    /// `notifications.kinds`, replaced when the configuration is reloaded
+    kinds: RwLock<Vec<NotificationKind>>,
+    /// Who watches which task; without it watched-task changes reach nobody
+    watches: Option<Arc<WatchRegistry>>,
+}
+
+impl NotificationCenter {
+    pub fn new(
+        settings: &NotificationSettings,
+        db: Arc<DatabaseService>,
+        publisher: Arc<EventPublisher>,
+    ) -> Self {
+        Self {
+            db,
+            publisher,
+            kinds: RwLock::new(settings.kinds.clone()),
+            watches: None,
+        }
+    }
+
+    pub fn with_watches(mut self, watches: Arc<WatchRegistry>) -> Self {
+        self.watches = Some(watches);
+        self
+    }
+
+    /// Generate the kinds of reloaded `settings` from now on
+    pub fn reconfigure(&self, settings: &NotificationSettings) {
+        *self.kinds.write().unwrap_or_else(|e| e.into_inner()) = settings.kinds.clone();
+    }
+
+    fn generates(&self, kind: NotificationKind) -> bool {
+        self.kinds
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(&kind)
+    }
+
+    pub async fn run(self: Arc<Self>) {
+        let mut rx = self.publisher.subscribe();
+
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!(lagged_events = n, "notification center lagged");
+                    continue;
+                }
+                Err(_) => break,
+            };
+
+            let Some(draft) = draft(&event).filter(|d| self.generates(d.kind)) else {
+                continue;
+            };
+            if let Err(e) = self.notify(draft).await {
+                tracing::warn!(
+                    kind = %event.kind,
+                    cursor = event.cursor,
+                    error = %e,
+                    "failed to create notifications"
+                );
+            }
+        }
+    }
+
+    async fn notify(&self, mut draft: NotificationDraft) -> anyhow::Result<()> {
+        if draft.kind == NotificationKind::WatchedTaskChanged {
+            let watchers = match &self.watches {
+                Some(watches) => watches.watchers(draft.task_id, None),
+                None => Default::default(),
+            };
+            draft.addressees = Some(watchers.into_iter().collect());
+        }
+        let preferences = self.db.list_notification_preferences().await?;
+        let users = recipients(&draft, &preferences);
+        if users.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(task_id) = draft.task_id
+            && let Some(task) = self.db.get_task_by_id(task_id).await?
+        {
+            draft.title = format!("{}: {}", draft.title, excerpt(&task.content));
+        }
+        self.db
+            .create_notifications(users.iter().map(|user| draft.to(user)).collect())
+            .await?;
+
+        for user_id in users {
+            push_unread(&self.db, &self.publisher, &user_id).await;
+        }
+        Ok(())
+    }
+}
+
+/// Emit `notification.unread` with the current unread count of `user_id`
+pub async fn push_unread(db: &DatabaseService, publisher: &EventPublisher, user_id: &str) {
+    let unread = match db.unread_notification_count(user_id).await {
+        Ok(unread) => unread,
+        Err(e) => {
+            tracing::warn!(user_id, error = %e, "failed to count unread notifications");
+            return;
+        }
+    };
+    let event = BuiltinEvent::NotificationUnread(NotificationUnreadData {
+        user_id: user_id.to_string(),
+        unread,
+    });
+    if let Err(e) = publisher.emit_builtin(event, EventScope::system()).await {
+        tracing::warn!(user_id, error = %e, "failed to emit notification.unread");
+    }
+}
+
+/// The notification an event calls for, if any
+pub fn draft(event: &Event) -> Option<NotificationDraft> {
+    let mut addressees = None;
+    let (kind, title, body, session_id) = match event.kind.as_str() {
+        EventKind::TASK_ASSIGNED => (
+            NotificationKind::TaskAssigned,
+            "Task assigned",
+            String::new(),
+            event.session_id,
+        ),
+        EventKind::PERMISSION_REQUESTED => {
+            let data: PermissionRequestedData = serde_json::from_value(event.data.clone()).ok()?;
+            // Requests the reviewer allowed or denied are already answered
+            if data.review.is_some_and(|r| r.decision != ReviewDecision::Manual) {
+                return None;
+            }
+            (
+                NotificationKind::PermissionPending,
+                "Permission needed",
+                data.tool_call.title,
+                Uuid::parse_str(&data.session_id).ok(),
+            )
+        }
+        EventKind::AGENT_SESSION_EXITED => {
+            let data: AgentSessionExitedData = serde_json::from_value(event.data.clone()).ok()?;
+            let failed = match data.status.as_deref() {
+                Some(status) => status == "failed",
+                None => data.exit_code.is_some_and(|code| code != 0),
+            };
+            if !failed {
+                return None;
+            }
+            let body = match data.exit_code {
+                Some(code) => format!("Exited with code {}", code),
+                None => "Session failed".to_string(),
+            };
+            (
+                NotificationKind::SessionFailed,
+                "Agent session failed",
+                body,
+                Uuid::parse_str(&data.session_id).ok(),
+            )
+        }
+        EventKind::RELAY_SPAWN_FAILED => {
+            let data: RelaySpawnFailedData = serde_json::from_value(event.data.clone()).ok()?;
+            (
+                NotificationKind::SessionFailed,
+                "Agent session failed to start",
+                data.error,
+                Uuid::parse_str(&data.session_id).ok(),
+            )
+        }
+        EventKind::TASK_STATUS_CHANGED => {
+            event.task_id?;
+            let data: TaskStatusChangedData = serde_json::from_value(event.data.clone()).ok()?;
+            (
+                NotificationKind::WatchedTaskChanged,
+                "Task status changed",
+                format!("{} → {}", data.old_status, data.new_status),
+                event.session_id,
+            )
+        }
+        EventKind::TASK_HANDOFF => {
+            event.task_id?;
+            let data: TaskHandoffData = serde_json::from_value(event.data.clone()).ok()?;
+            (
+                NotificationKind::WatchedTaskChanged,
+                "Task handed over",
+                format!("Handed over to {}", data.to_role),
+                Uuid::parse_str(&data.to_session_id).ok(),
+            )
+        }
+        EventKind::AGENT_TASK_COMMENT => {
+            event.task_id?;
+            let data: AgentTaskCommentData = serde_json::from_value(event.data.clone()).ok()?;
+            (
+                NotificationKind::WatchedTaskChanged,
+                "Agent commented",
+                data.content,
+                Uuid::parse_str(&data.session_id).ok(),
+            )
+        }
+        EventKind::TASK_MENTION => {
+            let data: TaskMentionData = serde_json::from_value(event.data.clone()).ok()?;
+            if data.users.is_empty() {
+                return None;
+            }
+            addressees = Some(data.users);
+            (
+                NotificationKind::Mentioned,
+                "You were mentioned",
+                data.content,
+                event.session_id,
+            )
+        }
+        _ => return None,
+    };
+
+    Some(NotificationDraft {
+        kind,
+        title: title.to_string(),
+        body,
+        task_id: event.task_id,
+        session_id,
+        event_cursor: event.cursor,
+        addressees,
+    })
+}
+
+/// Users notified of `draft`: the shared-token user unless they muted its
+/// kind, and every other user with preferences allowing it, narrowed down to
+/// the draft's addressees
+pub fn recipients(
+    draft: &NotificationDraft,
+    preferences: &[NotificationPreferences],
+) -> Vec<String> {
+    let mut users = Vec::new();
+    if !preferences.iter().any(|p| p.user_id == USER_TOKEN_ID) {
+        users.push(USER_TOKEN_ID.to_string());
+    }
+    users.extend(
+        preferences
+            .iter()
+            .filter(|p| p.allows(draft.kind))
+            .map(|p| p.user_id.clone()),
+    );
+    if let Some(addressees) = &draft.addressees {
+        users.retain(|user| addressees.contains(user));
+    }
+    users
+}
+
+fn excerpt(content: &str) -> String {
+    let line = content.lines().next().unwrap_or_default().trim();
+    let mut excerpt: String = line.chars().take(TITLE_EXCERPT_LEN).collect();
+    if excerpt.len() < line.len() {
+        excerpt.push('…');
+    }
+    excerpt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn event(kind: &str, data: serde_json::Value) -> Event {
+        let mut event = Event::new(kind, Uuid::nil(), data);
+        event.cursor = 7;
+        event
+    }
+
+    #[test]
+    fn test_draft() {
+        let session_id = Uuid::new_v4();
+        let exited = event(
+            EventKind::AGENT_SESSION_EXITED,
+            json!({"agent_id": "a", "session_id": session_id.to_string(), "exit_code": 1}),
+        );
+        let draft = draft(&exited).unwrap();
+        assert_eq!(draft.kind, NotificationKind::SessionFailed);
+        assert_eq!(draft.body, "Exited with code 1");
+        assert_eq!(draft.session_id, Some(session_id));
+        assert_eq!(draft.event_cursor, 7);
+
+        let cancelled = event(
+            EventKind::AGENT_SESSION_EXITED,
+            json!({"agent_id": "a", "session_id": "s", "exit_code": 1, "status": "cancelled"}),
+        );
+        assert_eq!(super::draft(&cancelled), None);
+
+        let request = json!({
+            "session_id": session_id.to_string(),
+            "request_id": "r1",
+            "tool_call_id": "t1",
+            "tool_call": {"title": "rm -rf target", "raw_input": {}, "tool_call_id": null},
+            "options": [],
+        });
+        let pending = super::draft(&event(EventKind::PERMISSION_REQUESTED, request.clone()));
+        assert_eq!(pending.unwrap().body, "rm -rf target");
+
+        let mut reviewed = request;
+        reviewed["review"] = json!({"decision": "allow", "reason": "safe", "risk_level": "low"});
+        assert_eq!(super::draft(&event(EventKind::PERMISSION_REQUESTED, reviewed)), None);
+
+        assert_eq!(super::draft(&event(EventKind::TASK_CREATED, json!({}))), None);
+
+        let mut moved = event(
+            EventKind::TASK_STATUS_CHANGED,
+            json!({"old_status": "todo", "new_status": "in-progress"}),
+        );
+        assert_eq!(super::draft(&moved), None, "status changes need a task");
+        moved.task_id = Some(Uuid::new_v4());
+        let watched = super::draft(&moved).unwrap();
+        assert_eq!(watched.kind, NotificationKind::WatchedTaskChanged);
+        assert_eq!(watched.body, "todo → in-progress");
+        assert_eq!(watched.task_id, moved.task_id);
+    }
+
+    #[test]
+    fn test_recipients() {
+        let prefs = |user_id: &str, muted: serde_json::Value| NotificationPreferences {
+            user_id: user_id.to_string(),
+            muted,
+            updated_at: Utc::now(),
+        };
+
+        let draft = |kind, addressees: Option<&[&str]>| NotificationDraft {
+            kind,
+            title: String::new(),
+            body: String::new(),
+            task_id: None,
+            session_id: None,
+            event_cursor: 1,
+            addressees: addressees.map(|a| a.iter().map(|u| u.to_string()).collect()),
+        };
+
+        let assigned = draft(NotificationKind::TaskAssigned, None);
+        assert_eq!(recipients(&assigned, &[]), vec!["user"]);
+
+        let preferences = vec![
+            prefs("user", json!(["session_failed"])),
+            prefs("oidc:alice", json!([])),
+        ];
+        let failed = draft(NotificationKind::SessionFailed, None);
+        assert_eq!(recipients(&failed, &preferences), vec!["oidc:alice"]);
+        assert_eq!(recipients(&assigned, &preferences), vec!["user", "oidc:alice"]);
+
+        let mention = draft(NotificationKind::Mentioned, Some(&["oidc:alice", "oidc:bob"]));
+        assert_eq!(recipients(&mention, &preferences), vec!["oidc:alice"]);
+    }
+}
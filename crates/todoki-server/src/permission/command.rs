@@ -0,0 +1,441 @@
+//! Tool call normalization and shell command parsing
+//!
+//! Agents describe tool calls loosely: a Bash call carries its command line
+//! in `raw_input.command`, file tools a `file_path` or `path`, fetches a
+//! `url`. [`NormalizedToolCall`] pulls these out, and command lines are
+//! split into [`ParsedCommand`]s (binary, subcommand, flags, arguments,
+//! paths) so rules can say "git push, unless --force" instead of matching
+//! raw strings.
+//!
+//! The parser understands quoting, escapes, `&&`/`||`/`;`/`|` chains and
+//! redirects; it does not expand variables or substitutions.
+
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use todoki_protocol::event_bus::ToolCall;
+
+/// Binaries whose first argument names a subcommand
+const SUBCOMMAND_BINARIES: &[&str] = &[
+    "apt", "brew", "cargo", "docker", "gh", "git", "go", "helm", "kubectl", "npm", "pip",
+    "pnpm", "podman", "systemctl", "terraform", "yarn",
+];
+
+/// Prefixes that run the rest of the line as another command
+const WRAPPERS: &[&str] = &["sudo", "env", "nohup", "time", "nice", "xargs"];
+
+/// What kind of action a tool call performs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolKind {
+    /// Runs a shell command line
+    Shell,
+    /// Reads or writes files
+    File,
+    /// Fetches a URL
+    Fetch,
+    Other,
+}
+
+/// A tool call reduced to what permission rules look at
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedToolCall {
+    pub kind: ToolKind,
+    /// Commands of a shell call, in order
+    pub commands: Vec<ParsedCommand>,
+    /// Files touched: file tool targets and paths named by commands
+    pub paths: Vec<String>,
+    pub url: Option<String>,
+}
+
+impl NormalizedToolCall {
+    pub fn new(tool_call: &ToolCall) -> Self {
+        let input = &tool_call.raw_input;
+        let field = |name: &str| input.get(name).and_then(Value::as_str).map(str::to_string);
+
+        if let Some(command) = field("command").or_else(|| field("cmd")) {
+            let commands = parse_command_line(&command);
+            let paths = commands
+                .iter()
+                .flat_map(|c| c.paths.iter().cloned())
+                .collect();
+            return Self {
+                kind: ToolKind::Shell,
+                commands,
+                paths,
+                url: None,
+            };
+        }
+        if let Some(path) = ["file_path", "path", "notebook_path"]
+            .iter()
+            .find_map(|name| field(name))
+        {
+            return Self {
+                kind: ToolKind::File,
+                commands: Vec::new(),
+                paths: vec![path],
+                url: None,
+            };
+        }
+        if let Some(url) = field("url") {
+            return Self {
+                kind: ToolKind::Fetch,
+                commands: Vec::new(),
+                paths: Vec::new(),
+                url: Some(url),
+            };
+        }
+        Self {
+            kind: ToolKind::Other,
+            commands: Vec::new(),
+            paths: Vec::new(),
+            url: None,
+        }
+    }
+}
+
+/// One simple command of a command line
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct ParsedCommand {
+    /// Program name without its directory (`/usr/bin/git` → `git`)
+    pub binary: String,
+    /// First argument of tools like git or cargo (`git push` → `push`)
+    pub subcommand: Option<String>,
+    /// Options as written (`-rf`, `--force`, `--output=x`)
+    pub flags: Vec<String>,
+    /// Non-option arguments after the subcommand
+    pub args: Vec<String>,
+    /// Arguments and redirect targets that look like file paths
+    pub paths: Vec<String>,
+    /// Wrappers the command ran under (`sudo`, `env`, ...)
+    pub wrappers: Vec<String>,
+}
+
+impl ParsedCommand {
+    /// Whether the command passes `flag` (`--force`, `-f`)
+    ///
+    /// Long flags also match their `--flag=value` form; short flags match
+    /// inside bundles like `-rf`.
+    pub fn has_flag(&self, flag: &str) -> bool {
+        if let Some(long) = flag.strip_prefix("--") {
+            return self.flags.iter().any(|f| {
+                f.strip_prefix("--")
+                    .is_some_and(|f| f == long || f.starts_with(&format!("{}=", long)))
+            });
+        }
+        let Some(short) = flag.strip_prefix('-') else {
+            return false;
+        };
+        self.flags.iter().any(|f| {
+            !f.starts_with("--")
+                && f.strip_prefix('-').is_some_and(|bundle| {
+                    bundle == short || (short.len() == 1 && bundle.contains(short))
+                })
+        })
+    }
+
+    /// Whether this command is an instance of `pattern`
+    ///
+    /// `pattern` is itself a command (`git push --force`): binary and
+    /// subcommand must be equal and every flag and argument of the pattern
+    /// present, in any order.
+    pub fn matches(&self, pattern: &ParsedCommand) -> bool {
+        self.binary == pattern.binary
+            && (pattern.subcommand.is_none() || self.subcommand == pattern.subcommand)
+            && pattern.flags.iter().all(|flag| self.has_flag(flag))
+            && pattern.args.iter().all(|arg| self.args.contains(arg))
+    }
+}
+
+/// Split a command line into its simple commands
+pub fn parse_command_line(line: &str) -> Vec<ParsedCommand> {
+    let mut commands = Vec::new();
+    let mut words = Vec::new();
+    let mut redirects = Vec::new();
+    let mut tokens = tokenize(line).into_iter().peekable();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Operator => {
+                commands.extend(build_command(std::mem::take(&mut words), &mut redirects));
+            }
+            Token::Redirect => {
+                if let Some(Token::Word(target)) = tokens.peek() {
+                    // `2>&1` and the like name a descriptor, not a file
+                    if !target.starts_with('&') {
+                        redirects.push(target.clone());
+                    }
+                    tokens.next();
+                }
+            }
+            Token::Word(word) => words.push(word),
+        }
+    }
+    commands.extend(build_command(words, &mut redirects));
+    commands
+}
+
+fn build_command(words: Vec<String>, redirects: &mut Vec<String>) -> Option<ParsedCommand> {
+    let mut command = ParsedCommand {
+        paths: std::mem::take(redirects),
+        ..Default::default()
+    };
+    let mut words = words.into_iter().peekable();
+
+    // Leading `VAR=value` assignments and wrappers
+    while let Some(word) = words.peek() {
+        if is_assignment(word) {
+            words.next();
+        } else if WRAPPERS.contains(&word.as_str()) {
+            command.wrappers.push(words.next()?);
+            // Wrapper flags (`sudo -E`) are not the command
+            while words.peek().is_some_and(|w| w.starts_with('-')) {
+                words.next();
+            }
+        } else {
+            break;
+        }
+    }
+
+    let binary = words.next()?;
+    command.binary = binary.rsplit('/').next().unwrap_or(&binary).to_string();
+    let takes_subcommand = SUBCOMMAND_BINARIES.contains(&command.binary.as_str());
+
+    for word in words {
+        if word.len() > 1 && word.starts_with('-') {
+            command.flags.push(word);
+        } else if takes_subcommand && command.subcommand.is_none() && command.args.is_empty() {
+            command.subcommand = Some(word);
+        } else {
+            if looks_like_path(&word) {
+                command.paths.push(word.clone());
+            }
+            command.args.push(word);
+        }
+    }
+    Some(command)
+}
+
+fn is_assignment(word: &str) -> bool {
+    match word.split_once('=') {
+        Some((name, _)) => {
+            !name.is_empty()
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+fn looks_like_path(word: &str) -> bool {
+    (word.contains('/') && !word.contains("://"))
+        || word.starts_with('.')
+        || word.starts_with('~')
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    /// `&&`, `||`, `;`, `|`, `&` or a newline
+    Operator,
+    /// `>`, `>>`, `<` (with an optional descriptor number)
+    Redirect,
+}
+
+fn tokenize(line: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    // Quoted empty strings are still words
+    let mut in_word = false;
+    let mut chars = line.chars().peekable();
+
+    let flush = |tokens: &mut Vec<Token>, word: &mut String, in_word: &mut bool| {
+        if *in_word {
+            tokens.push(Token::Word(std::mem::take(word)));
+            *in_word = false;
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                for c in chars.by_ref() {
+                    if c == '\'' {
+                        break;
+                    }
+                    word.push(c);
+                }
+            }
+            '"' => {
+                in_word = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => {
+                            if let Some(&next) = chars.peek()
+                                && matches!(next, '"' | '\\' | '$' | '`')
+                            {
+                                word.push(next);
+                                chars.next();
+                                continue;
+                            }
+                            word.push(c);
+                        }
+                        _ => word.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                match chars.next() {
+                    // Line continuation
+                    Some('\n') => {}
+                    Some(c) => word.push(c),
+                    None => {}
+                }
+            }
+            '#' if !in_word => {
+                // Comment until the end of the line
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            ';' | '|' | '&' | '\n' => {
+                // `&>` redirects both streams
+                if c == '&' && chars.peek() == Some(&'>') {
+                    flush(&mut tokens, &mut word, &mut in_word);
+                    chars.next();
+                    if chars.peek() == Some(&'>') {
+                        chars.next();
+                    }
+                    tokens.push(Token::Redirect);
+                    continue;
+                }
+                flush(&mut tokens, &mut word, &mut in_word);
+                if matches!(c, '|' | '&') && chars.peek() == Some(&c) {
+                    chars.next();
+                }
+                tokens.push(Token::Operator);
+            }
+            '>' | '<' => {
+                // A bare descriptor number before the redirect belongs to it
+                if in_word && word.chars().all(|c| c.is_ascii_digit()) {
+                    word.clear();
+                    in_word = false;
+                }
+                flush(&mut tokens, &mut word, &mut in_word);
+                if chars.peek() == Some(&c) {
+                    chars.next();
+                }
+                tokens.push(Token::Redirect);
+                // `>&2` duplicates a descriptor; keep it as one `&` word
+                if chars.peek() == Some(&'&') {
+                    chars.next();
+                    let mut target = String::from("&");
+                    while let Some(&c) = chars.peek() {
+                        if !(c.is_ascii_digit() || c == '-') {
+                            break;
+                        }
+                        target.push(c);
+                        chars.next();
+                    }
+                    tokens.push(Token::Word(target));
+                }
+            }
+            c if c.is_whitespace() => flush(&mut tokens, &mut word, &mut in_word),
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    flush(&mut tokens, &mut word, &mut in_word);
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_one(line: &str) -> ParsedCommand {
+        let mut commands = parse_command_line(line);
+        assert_eq!(commands.len(), 1, "{:?}", commands);
+        commands.remove(0)
+    }
+
+    #[test]
+    fn test_parse_command() {
+        let command = parse_one("sudo /usr/bin/git push --force origin 'main branch'");
+        assert_eq!(command.binary, "git");
+        assert_eq!(command.wrappers, vec!["sudo".to_string()]);
+        assert_eq!(command.subcommand.as_deref(), Some("push"));
+        assert_eq!(command.flags, vec!["--force".to_string()]);
+        assert_eq!(
+            command.args,
+            vec!["origin".to_string(), "main branch".to_string()]
+        );
+
+        let command = parse_one("RUST_LOG=debug cargo test -p todoki > ./out.log 2>&1");
+        assert_eq!(command.binary, "cargo");
+        assert_eq!(command.subcommand.as_deref(), Some("test"));
+        assert_eq!(command.args, vec!["todoki".to_string()]);
+        assert_eq!(command.paths, vec!["./out.log".to_string()]);
+
+        let command = parse_one(r#"rm -rf "build dir/" ~/.cache"#);
+        assert_eq!(command.subcommand, None);
+        assert_eq!(
+            command.paths,
+            vec!["build dir/".to_string(), "~/.cache".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_parse_chains() {
+        let commands = parse_command_line("cd web && npm install; cat a.txt | grep -v x # done");
+        let binaries: Vec<&str> = commands.iter().map(|c| c.binary.as_str()).collect();
+        assert_eq!(binaries, vec!["cd", "npm", "cat", "grep"]);
+        assert_eq!(commands[1].subcommand.as_deref(), Some("install"));
+        assert_eq!(commands[3].args, vec!["x".to_string()]);
+    }
+
+    #[test]
+    fn test_flags_and_patterns() {
+        let command = parse_one("git push origin main --force-with-lease=main");
+        assert!(command.has_flag("--force-with-lease"));
+        assert!(!command.has_flag("--force"));
+        assert!(parse_one("rm -rf /tmp/x").has_flag("-f"));
+
+        let pattern = parse_one("git push --force");
+        assert!(parse_one("git push origin main --force").matches(&pattern));
+        assert!(!parse_one("git push origin main").matches(&pattern));
+        assert!(!parse_one("git fetch --force").matches(&pattern));
+    }
+
+    #[test]
+    fn test_normalize_tool_call() {
+        let tool_call = |raw_input: Value| ToolCall {
+            title: String::new(),
+            raw_input,
+            tool_call_id: None,
+        };
+
+        let shell = NormalizedToolCall::new(&tool_call(serde_json::json!({
+            "command": "cp src/a.rs /tmp/b.rs"
+        })));
+        assert_eq!(shell.kind, ToolKind::Shell);
+        assert_eq!(
+            shell.paths,
+            vec!["src/a.rs".to_string(), "/tmp/b.rs".to_string()]
+        );
+
+        let edit = NormalizedToolCall::new(&tool_call(serde_json::json!({
+            "file_path": "/repo/Cargo.toml",
+            "old_string": "a"
+        })));
+        assert_eq!(edit.kind, ToolKind::File);
+        assert_eq!(edit.paths, vec!["/repo/Cargo.toml".to_string()]);
+
+        let other = NormalizedToolCall::new(&tool_call(Value::Null));
+        assert_eq!(other.kind, ToolKind::Other);
+    }
+}
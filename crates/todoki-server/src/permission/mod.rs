@@ -0,0 +1,307 @@
+//! Permission requests waiting for a human answer
+//!
+//! Requests whose tool call matches `permissions.two_person_patterns` (force
+//! pushes, production database access, ...) are only allowed once two
+//! distinct people approved them. Rejections and cancellations decide a
+//! request right away. Partial approvals are kept with the pending request
+//! and listed by `GET /api/permissions/pending`.
+//!
+//! With `auto_review` enabled, [`review`] asks a model about each request
+//! first and answers the clear cases itself.
+
+pub mod command;
+pub mod review;
+
+use chrono::{DateTime, Utc};
+use gotcha::Schematic;
+use serde::{Deserialize, Serialize};
+use todoki_protocol::event_bus::{
+    BuiltinEvent, CommandPreview, PermissionOption, PermissionOutcome, PermissionRequestedData,
+    PermissionRespondedData, PermissionReview, ToolCall,
+};
+use uuid::Uuid;
+
+use crate::config::PermissionSettings;
+use crate::db::DatabaseService;
+use crate::event_bus::{EventPublisher, EventScope};
+
+/// One approval of a request that needs several
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct PermissionApproval {
+    /// Token ID of the approver (e.g. "oidc:<sub>", "telegram:<user id>")
+    pub approved_by: String,
+    pub option_id: String,
+    pub approved_at: DateTime<Utc>,
+}
+
+/// A request the relay is still waiting on
+#[derive(Debug, Clone)]
+pub struct PendingPermission {
+    pub request: PermissionRequestedData,
+    pub requested_at: DateTime<Utc>,
+    /// Distinct approvals needed before an allow option is forwarded
+    pub required_approvals: usize,
+    pub approvals: Vec<PermissionApproval>,
+}
+
+/// A pending request (for API responses)
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct PendingPermissionInfo {
+    pub request_id: String,
+    pub session_id: String,
+    /// Relay running the session; `None` once it disconnected
+    pub relay_id: Option<String>,
+    pub tool_call: ToolCall,
+    pub options: Vec<PermissionOption>,
+    pub requested_at: DateTime<Utc>,
+    pub required_approvals: usize,
+    pub approvals: Vec<PermissionApproval>,
+    /// The automatic review, when the request was left to a human
+    pub review: Option<PermissionReview>,
+    /// Output of the relay's dry run of the command
+    pub preview: Option<CommandPreview>,
+}
+
+impl PendingPermissionInfo {
+    pub fn new(pending: PendingPermission, relay_id: Option<String>) -> Self {
+        Self {
+            request_id: pending.request.request_id,
+            session_id: pending.request.session_id,
+            relay_id,
+            tool_call: pending.request.tool_call,
+            options: pending.request.options,
+            requested_at: pending.requested_at,
+            required_approvals: pending.required_approvals,
+            approvals: pending.approvals,
+            review: pending.request.review,
+            preview: pending.request.preview,
+        }
+    }
+}
+
+/// Result of answering a pending request
+#[derive(Debug, Clone, PartialEq)]
+pub enum PermissionAnswer {
+    /// Forward this outcome to the relay
+    Decided(PermissionOutcome),
+    /// The approval was recorded; more are needed
+    Waiting { approvals: usize, required: usize },
+}
+
+impl PendingPermission {
+    pub fn new(request: PermissionRequestedData, required_approvals: usize) -> Self {
+        Self {
+            request,
+            requested_at: Utc::now(),
+            required_approvals: required_approvals.max(1),
+            approvals: Vec::new(),
+        }
+    }
+
+    /// Record `answered_by`'s answer
+    ///
+    /// The last approver's option is forwarded once enough people approved.
+    pub fn answer(
+        &mut self,
+        answered_by: &str,
+        outcome: PermissionOutcome,
+    ) -> Result<PermissionAnswer, String> {
+        let option_id = match &outcome {
+            PermissionOutcome::Cancelled { .. } => return Ok(PermissionAnswer::Decided(outcome)),
+            PermissionOutcome::Selected { selected } => selected.option_id.clone(),
+        };
+        let option = self
+            .request
+            .options
+            .iter()
+            .find(|option| option.option_id == option_id)
+            .ok_or_else(|| format!("unknown option {}", option_id))?;
+        if !is_allow(&option.kind) {
+            return Ok(PermissionAnswer::Decided(outcome));
+        }
+
+        if self
+            .approvals
+            .iter()
+            .any(|approval| approval.approved_by == answered_by)
+        {
+            return Err("you already approved this request".to_string());
+        }
+        self.approvals.push(PermissionApproval {
+            approved_by: answered_by.to_string(),
+            option_id,
+            approved_at: Utc::now(),
+        });
+        if self.approvals.len() >= self.required_approvals {
+            return Ok(PermissionAnswer::Decided(outcome));
+        }
+        Ok(PermissionAnswer::Waiting {
+            approvals: self.approvals.len(),
+            required: self.required_approvals,
+        })
+    }
+}
+
+/// Whether an option kind (e.g. "allow_once", "reject_always") lets the
+/// tool call run
+fn is_allow(kind: &str) -> bool {
+    kind.starts_with("allow")
+}
+
+/// A configured rule a tool call matched
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schematic)]
+pub struct MatchedRule {
+    /// Setting the rule comes from (e.g. "two_person_patterns")
+    pub rule: String,
+    pub pattern: String,
+    /// "text" when the call's text contains the pattern, "command" when one
+    /// of its commands is an instance of it
+    pub matched_by: String,
+}
+
+/// Two-person patterns matching a tool call
+///
+/// A pattern matches when the call's text contains it, or when one of its
+/// shell commands is an instance of the pattern parsed as a command, so
+/// `git push --force` also catches `git push origin main --force`.
+pub fn matched_rules(settings: &PermissionSettings, tool_call: &ToolCall) -> Vec<MatchedRule> {
+    let text = format!("{}\n{}", tool_call.title, tool_call.raw_input).to_lowercase();
+    let commands = command::NormalizedToolCall::new(tool_call).commands;
+    settings
+        .two_person_patterns
+        .iter()
+        .filter(|pattern| !pattern.is_empty())
+        .filter_map(|pattern| {
+            let matched_by = if text.contains(&pattern.to_lowercase()) {
+                "text"
+            } else if command::parse_command_line(pattern)
+                .first()
+                .is_some_and(|pattern| commands.iter().any(|c| c.matches(pattern)))
+            {
+                "command"
+            } else {
+                return None;
+            };
+            Some(MatchedRule {
+                rule: "two_person_patterns".to_string(),
+                pattern: pattern.clone(),
+                matched_by: matched_by.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Approvals a tool call needs: two when it matches a two-person pattern
+pub fn required_approvals(settings: &PermissionSettings, tool_call: &ToolCall) -> usize {
+    if matched_rules(settings, tool_call).is_empty() { 1 } else { 2 }
+}
+
+/// Emit `permission.responded`, which the relay delivers to the agent
+pub async fn send_response(
+    db: &DatabaseService,
+    publisher: &EventPublisher,
+    relay_id: String,
+    request_id: &str,
+    session_id: &str,
+    outcome: PermissionOutcome,
+) -> anyhow::Result<()> {
+    let session_uuid = Uuid::parse_str(session_id).ok();
+    let correlation_id = match session_uuid {
+        Some(session_uuid) => match db.get_session_correlation(session_uuid).await {
+            Ok(correlation_id) => correlation_id,
+            Err(e) => {
+                tracing::warn!(session_id = %session_id, error = %e, "failed to load session correlation");
+                None
+            }
+        },
+        None => None,
+    };
+    let scope = EventScope::system()
+        .with_session(session_uuid)
+        .with_correlation(correlation_id);
+
+    let event = BuiltinEvent::PermissionResponded(PermissionRespondedData {
+        relay_id,
+        request_id: request_id.to_string(),
+        session_id: session_id.to_string(),
+        outcome,
+    });
+    publisher.emit_builtin(event, scope).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(title: &str) -> PermissionRequestedData {
+        let option = |kind: &str| PermissionOption {
+            kind: kind.to_string(),
+            name: kind.to_string(),
+            option_id: kind.to_string(),
+        };
+        PermissionRequestedData {
+            session_id: "session-1".to_string(),
+            request_id: "req-1".to_string(),
+            tool_call_id: "call-1".to_string(),
+            tool_call: ToolCall {
+                title: title.to_string(),
+                raw_input: serde_json::json!({ "command": title }),
+                tool_call_id: None,
+            },
+            options: vec![option("allow_once"), option("reject_once")],
+            review: None,
+            preview: None,
+        }
+    }
+
+    #[test]
+    fn test_required_approvals() {
+        let settings = PermissionSettings {
+            two_person_patterns: vec!["git push --force".to_string(), "PROD_DB".to_string()],
+            ..Default::default()
+        };
+        let tool_call = |title: &str| request(title).tool_call;
+        assert_eq!(required_approvals(&settings, &tool_call("git push --force origin")), 2);
+        assert_eq!(required_approvals(&settings, &tool_call("psql $prod_db_url")), 2);
+        assert_eq!(required_approvals(&settings, &tool_call("git push origin")), 1);
+        assert_eq!(
+            required_approvals(&settings, &tool_call("git push origin main --force")),
+            2
+        );
+
+        let matched = matched_rules(&settings, &tool_call("git push origin main --force"));
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].pattern, "git push --force");
+        assert_eq!(matched[0].matched_by, "command");
+    }
+
+    #[test]
+    fn test_two_person_approval() {
+        let mut pending = PendingPermission::new(request("git push --force"), 2);
+        let allow = || PermissionOutcome::selected("allow_once");
+
+        assert_eq!(
+            pending.answer("oidc:alice", allow()),
+            Ok(PermissionAnswer::Waiting {
+                approvals: 1,
+                required: 2
+            })
+        );
+        // The same person cannot approve twice
+        assert!(pending.answer("oidc:alice", allow()).is_err());
+        assert!(pending.answer("oidc:bob", PermissionOutcome::selected("nope")).is_err());
+        assert_eq!(
+            pending.answer("oidc:bob", allow()),
+            Ok(PermissionAnswer::Decided(allow()))
+        );
+
+        // One rejection is enough
+        let mut pending = PendingPermission::new(request("git push --force"), 2);
+        let reject = PermissionOutcome::selected("reject_once");
+        assert_eq!(
+            pending.answer("oidc:alice", reject.clone()),
+            Ok(PermissionAnswer::Decided(reject))
+        );
+    }
+}
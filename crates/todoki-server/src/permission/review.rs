@@ -0,0 +1,569 @@
+//! Automatic review of permission requests
+//!
+//! With `auto_review.enabled`, every `permission.requested` is sent to the
+//! configured model together with the task it belongs to. The model answers
+//! allow, deny or manual, with a reason and a risk level. The review is
+//! attached to the event; allow and deny are answered by the server right
+//! away, manual requests wait for a human who sees the model's assessment
+//! next to the tool call. Requests needing two approvals are never allowed
+//! automatically.
+//!
+//! With `auto_review.consensus_model`, a second model reviews every request
+//! as well. Only a decision both models share is acted on; when they
+//! disagree the request is left to a human with both rationales.
+//!
+//! The instructions come in the language set by `auto_review.language`, so
+//! reasons read naturally to the operators, or are replaced entirely by
+//! `auto_review.system_prompt`; the reply format is always appended.
+//!
+//! Projects may override these settings in their `review_config`: turn
+//! reviews on or off, ask another model, add domain-specific rules to the
+//! instructions, and bound the risk the reviewer may allow or deny on its
+//! own. Overrides are resolved for each request from the task's project.
+//!
+//! Each review counts against the project's monthly AI review quota; once
+//! it is used up, requests wait for a human until the month is over.
+
+use std::sync::{Arc, RwLock};
+
+use serde_json::Value;
+use todoki_protocol::event_bus::{
+    PermissionOption, PermissionOutcome, PermissionRequestedData, PermissionReview,
+    ReviewDecision,
+};
+use uuid::Uuid;
+
+use super::{is_allow, required_approvals, send_response, PermissionAnswer};
+use crate::config::{AutoReviewSettings, PermissionSettings, QuotaSettings};
+use crate::db::DatabaseService;
+use crate::event_bus::EventPublisher;
+use crate::llm::LlmClient;
+use crate::models::project::ProjectReviewConfig;
+use crate::quota;
+use crate::relay::RelayManager;
+
+/// Approver ID recorded for automatic answers
+const REVIEWER_ID: &str = "auto_review";
+
+const INSTRUCTIONS_EN: &str = "You review tool calls that an AI agent wants to run while \
+working on a task. Allow actions that are routine and clearly serve the task, deny \
+destructive or unrelated ones and leave anything uncertain to a human.";
+
+const INSTRUCTIONS_ZH: &str = "你负责审核 AI 代理在执行任务时想要调用的工具。\
+对常规且明显服务于任务的操作予以允许，拒绝具有破坏性或与任务无关的操作，\
+拿不准的交给人工处理。reason 请用中文撰写。";
+
+/// Appended to every prompt; the values are parsed, so they stay English
+const REPLY_FORMAT: &str = "Reply with a single JSON object and nothing else:\n\
+{\"decision\": <\"allow\" | \"deny\" | \"manual\">, \
+\"risk_level\": <\"low\" | \"medium\" | \"high\">, \
+\"reason\": <one sentence>}";
+
+/// The models and instructions `auto_review` configures; replaced as a
+/// whole when the configuration is reloaded
+struct ReviewModels {
+    /// `None` without an API key
+    llm: Option<LlmClient>,
+    /// Second opinion, with `consensus_model`
+    consensus: Option<LlmClient>,
+    /// `auto_review.enabled`; projects may override it
+    enabled: bool,
+    /// Instructions for the configured language or the custom prompt
+    instructions: String,
+}
+
+impl ReviewModels {
+    fn new(settings: &AutoReviewSettings) -> Self {
+        let llm = LlmClient::from_settings(settings);
+        let consensus = llm
+            .as_ref()
+            .zip(settings.consensus_model.as_ref())
+            .filter(|(_, model)| !model.is_empty())
+            .map(|(llm, model)| llm.clone().with_model(model.clone()));
+        Self {
+            llm,
+            consensus,
+            enabled: settings.enabled,
+            instructions: instructions(settings),
+        }
+    }
+}
+
+pub struct PermissionReviewer {
+    models: RwLock<Arc<ReviewModels>>,
+    permissions: PermissionSettings,
+    /// Monthly review quotas of projects
+    quotas: QuotaSettings,
+    db: Arc<DatabaseService>,
+    relays: Arc<RelayManager>,
+    publisher: Arc<EventPublisher>,
+}
+
+impl PermissionReviewer {
+    pub fn new(
+        settings: &AutoReviewSettings,
+        permissions: PermissionSettings,
+        db: Arc<DatabaseService>,
+        relays: Arc<RelayManager>,
+        publisher: Arc<EventPublisher>,
+    ) -> Self {
+        Self {
+            models: RwLock::new(Arc::new(ReviewModels::new(settings))),
+            permissions,
+            quotas: QuotaSettings::default(),
+            db,
+            relays,
+            publisher,
+        }
+    }
+
+    pub fn with_quotas(mut self, quotas: QuotaSettings) -> Self {
+        self.quotas = quotas;
+        self
+    }
+
+    /// Apply reloaded `auto_review` settings to the reviews started from now on
+    pub fn reconfigure(&self, settings: &AutoReviewSettings) {
+        let models = Arc::new(ReviewModels::new(settings));
+        *self.models.write().unwrap_or_else(|e| e.into_inner()) = models;
+    }
+
+    fn models(&self) -> Arc<ReviewModels> {
+        self.models.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled_for(None)
+    }
+
+    /// Whether requests of a project with `config` are reviewed
+    pub fn enabled_for(&self, config: Option<&ProjectReviewConfig>) -> bool {
+        let models = self.models();
+        models.llm.is_some() && config.and_then(|c| c.enabled).unwrap_or(models.enabled)
+    }
+
+    /// Review `permission.requested` data and attach the review to it
+    ///
+    /// A `review` sent by the emitter is dropped either way. Returns the
+    /// request and its review for [`Self::apply`].
+    pub async fn annotate(
+        &self,
+        task_id: Option<Uuid>,
+        data: &mut Value,
+    ) -> Option<(PermissionRequestedData, PermissionReview)> {
+        data.as_object_mut()?.remove("review");
+        self.models().llm.as_ref()?;
+
+        let request: PermissionRequestedData = serde_json::from_value(data.clone()).ok()?;
+        let review = match self.review(task_id, &request).await {
+            Ok(Some(review)) => review,
+            Ok(None) => return None,
+            Err(e) => {
+                tracing::warn!(request_id = %request.request_id, error = %e, "permission review failed, leaving it to a human");
+                return None;
+            }
+        };
+
+        data.as_object_mut()?
+            .insert("review".to_string(), serde_json::to_value(&review).ok()?);
+        if review.decision == ReviewDecision::Manual {
+            self.relays
+                .set_permission_review(&request.request_id, review.clone())
+                .await;
+        }
+        Some((request, review))
+    }
+
+    /// Answer a request reviewed as allow or deny
+    pub async fn apply(&self, request: &PermissionRequestedData, review: &PermissionReview) {
+        let request_id = &request.request_id;
+        let Some(outcome) = review_outcome(review.decision, &request.options) else {
+            return;
+        };
+        let Some((relay_id, session_id)) = self.relays.get_pending_permission(request_id).await
+        else {
+            tracing::debug!(request_id = %request_id, "reviewed permission request is no longer pending");
+            return;
+        };
+        let outcome = match self
+            .relays
+            .answer_permission(request_id, REVIEWER_ID, outcome)
+            .await
+        {
+            Some(Ok(PermissionAnswer::Decided(outcome))) => outcome,
+            _ => return,
+        };
+
+        let sent = send_response(
+            &self.db,
+            &self.publisher,
+            relay_id,
+            request_id,
+            &session_id,
+            outcome,
+        )
+        .await;
+        if let Err(e) = sent {
+            tracing::error!(request_id = %request_id, error = %e, "failed to send reviewed permission response");
+            return;
+        }
+        self.relays.remove_pending_permission(request_id).await;
+        tracing::info!(request_id = %request_id, decision = ?review.decision, risk_level = ?review.risk_level, "permission request answered by review");
+    }
+
+    /// Review `request` with the overrides of the task's project; `None`
+    /// when the project turned reviews off or used up its monthly quota
+    async fn review(
+        &self,
+        task_id: Option<Uuid>,
+        request: &PermissionRequestedData,
+    ) -> anyhow::Result<Option<PermissionReview>> {
+        let task = match task_id {
+            Some(task_id) => self.db.get_task_by_id(task_id).await?,
+            None => None,
+        };
+        let config = match &task {
+            Some(task) => self
+                .db
+                .get_project(task.project_id)
+                .await?
+                .and_then(|project| project.review_config()),
+            None => None,
+        };
+        if !self.enabled_for(config.as_ref()) {
+            return Ok(None);
+        }
+        if let Some(task) = &task
+            && !quota::consume_ai_review(&self.db, &self.quotas, task.project_id).await?
+        {
+            tracing::info!(
+                request_id = %request.request_id,
+                project_id = %task.project_id,
+                "AI review quota used up, leaving the request to a human"
+            );
+            return Ok(None);
+        }
+        let goal = task.as_ref().map(|t| t.content.as_str());
+        self.review_for_goal(goal, config.as_ref(), request)
+            .await
+            .map(Some)
+    }
+
+    /// Ask the model about `request` made while working towards `goal`,
+    /// with a project's `config` overrides
+    pub async fn review_for_goal(
+        &self,
+        goal: Option<&str>,
+        config: Option<&ProjectReviewConfig>,
+        request: &PermissionRequestedData,
+    ) -> anyhow::Result<PermissionReview> {
+        let models = self.models();
+        let llm = models
+            .llm
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("auto review is not configured"))?;
+        let llm = match config.and_then(|c| c.model.as_deref()) {
+            Some(model) => llm.clone().with_model(model),
+            None => llm.clone(),
+        };
+        let system = system_prompt(
+            &models.instructions,
+            config.and_then(|c| c.guidance.as_deref()),
+        );
+
+        let options = request
+            .options
+            .iter()
+            .map(|option| option.kind.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut prompt = format!(
+            "Task:\n{}\n\nTool call: {}\n\nInput:\n{}\n\nOptions: {}",
+            goal.unwrap_or("(unknown)"),
+            request.tool_call.title,
+            serde_json::to_string_pretty(&request.tool_call.raw_input).unwrap_or_default(),
+            options
+        );
+        if let Some(preview) = &request.preview {
+            prompt.push_str(&format!(
+                "\n\nPreview ({}, exit code {:?}):\n{}",
+                preview.command, preview.exit_code, preview.output
+            ));
+        }
+
+        let mut review = match &models.consensus {
+            None => ask(&llm, &system, &prompt).await?,
+            Some(second) => {
+                let (first_review, second_review) = tokio::join!(
+                    ask(&llm, &system, &prompt),
+                    ask(second, &system, &prompt)
+                );
+                consensus(
+                    (llm.model(), first_review),
+                    (second.model(), second_review),
+                )?
+            }
+        };
+        // Two-person requests are for people to approve
+        if review.decision == ReviewDecision::Allow
+            && required_approvals(&self.permissions, &request.tool_call) > 1
+        {
+            review.decision = ReviewDecision::Manual;
+        }
+        if let Some(config) = config {
+            apply_thresholds(&mut review, config);
+        }
+        Ok(review)
+    }
+}
+
+async fn ask(llm: &LlmClient, system: &str, prompt: &str) -> anyhow::Result<PermissionReview> {
+    let reply = llm.complete(system, prompt).await?;
+    Ok(parse_review(&reply)?)
+}
+
+/// Reviewer instructions for `settings`: the custom prompt, or the built-in
+/// one for the configured language
+fn instructions(settings: &AutoReviewSettings) -> String {
+    if let Some(prompt) = settings
+        .system_prompt
+        .as_deref()
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+    {
+        return prompt.to_string();
+    }
+    let language = settings.language.trim();
+    match language.to_ascii_lowercase().as_str() {
+        "" | "en" | "english" => INSTRUCTIONS_EN.to_string(),
+        "zh" | "zh-cn" | "chinese" => INSTRUCTIONS_ZH.to_string(),
+        _ => format!(
+            "{} Write the reason in {}, using its usual terminology.",
+            INSTRUCTIONS_EN, language
+        ),
+    }
+}
+
+/// The full system prompt, with a project's extra rules
+fn system_prompt(instructions: &str, guidance: Option<&str>) -> String {
+    match guidance.map(str::trim).filter(|g| !g.is_empty()) {
+        Some(guidance) => format!(
+            "{}\n\nRules for this project:\n{}\n\n{}",
+            instructions, guidance, REPLY_FORMAT
+        ),
+        None => format!("{}\n\n{}", instructions, REPLY_FORMAT),
+    }
+}
+
+/// Leave decisions outside the project's risk thresholds to a human
+fn apply_thresholds(review: &mut PermissionReview, config: &ProjectReviewConfig) {
+    let outside = match review.decision {
+        ReviewDecision::Allow => config
+            .allow_max_risk
+            .is_some_and(|max| review.risk_level > max),
+        ReviewDecision::Deny => config
+            .deny_min_risk
+            .is_some_and(|min| review.risk_level < min),
+        ReviewDecision::Manual => false,
+    };
+    if outside {
+        review.decision = ReviewDecision::Manual;
+    }
+}
+
+/// Combine the reviews of two models
+///
+/// The shared decision when both agree, manual otherwise (also when one of
+/// them failed). The reason lists both rationales; the risk is the higher.
+fn consensus(
+    first: (&str, anyhow::Result<PermissionReview>),
+    second: (&str, anyhow::Result<PermissionReview>),
+) -> anyhow::Result<PermissionReview> {
+    let rationale = |model: &str, review: &anyhow::Result<PermissionReview>| match review {
+        Ok(review) => format!("{}: {}", model, review.reason),
+        Err(e) => format!("{}: review failed ({})", model, e),
+    };
+    let reason = format!(
+        "{}\n{}",
+        rationale(first.0, &first.1),
+        rationale(second.0, &second.1)
+    );
+
+    let (decision, risk_level) = match (first.1, second.1) {
+        (Ok(a), Ok(b)) => {
+            let decision = if a.decision == b.decision {
+                a.decision
+            } else {
+                ReviewDecision::Manual
+            };
+            (decision, a.risk_level.max(b.risk_level))
+        }
+        (Ok(review), Err(_)) | (Err(_), Ok(review)) => (ReviewDecision::Manual, review.risk_level),
+        (Err(e), Err(_)) => return Err(e),
+    };
+    Ok(PermissionReview {
+        decision,
+        reason,
+        risk_level,
+    })
+}
+
+/// Parse the model reply, tolerating a surrounding Markdown code fence
+fn parse_review(reply: &str) -> serde_json::Result<PermissionReview> {
+    let trimmed = reply.trim();
+    let body = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .unwrap_or(trimmed);
+    serde_json::from_str(body.trim())
+}
+
+/// Outcome sent for an allow or deny review; `None` leaves it to a human
+///
+/// Prefers the one-off options so a review never grants more than this call.
+fn review_outcome(
+    decision: ReviewDecision,
+    options: &[PermissionOption],
+) -> Option<PermissionOutcome> {
+    let pick = |preferred: &str, matches: fn(&str) -> bool| {
+        options
+            .iter()
+            .find(|option| option.kind == preferred)
+            .or_else(|| options.iter().find(|option| matches(&option.kind)))
+            .map(|option| PermissionOutcome::selected(option.option_id.clone()))
+    };
+    match decision {
+        ReviewDecision::Allow => pick("allow_once", is_allow),
+        ReviewDecision::Deny => Some(
+            pick("reject_once", |kind| kind.starts_with("reject"))
+                .unwrap_or_else(PermissionOutcome::cancelled),
+        ),
+        ReviewDecision::Manual => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use todoki_protocol::event_bus::RiskLevel;
+
+    fn option(kind: &str) -> PermissionOption {
+        PermissionOption {
+            kind: kind.to_string(),
+            name: kind.to_string(),
+            option_id: format!("{}-id", kind),
+        }
+    }
+
+    #[test]
+    fn test_parse_review() {
+        let reply = "```json\n{\"decision\": \"manual\", \"risk_level\": \"high\", \"reason\": \"Deletes the build cache\"}\n```";
+        let review = parse_review(reply).unwrap();
+        assert_eq!(review.decision, ReviewDecision::Manual);
+        assert_eq!(review.risk_level, RiskLevel::High);
+        assert_eq!(review.reason, "Deletes the build cache");
+    }
+
+    #[test]
+    fn test_consensus() {
+        let review = |decision, risk_level, reason: &str| {
+            Ok(PermissionReview {
+                decision,
+                reason: reason.to_string(),
+                risk_level,
+            })
+        };
+
+        let agreed = consensus(
+            ("a", review(ReviewDecision::Allow, RiskLevel::Low, "read-only")),
+            ("b", review(ReviewDecision::Allow, RiskLevel::Medium, "harmless")),
+        )
+        .unwrap();
+        assert_eq!(agreed.decision, ReviewDecision::Allow);
+        assert_eq!(agreed.risk_level, RiskLevel::Medium);
+
+        let disagreed = consensus(
+            ("a", review(ReviewDecision::Allow, RiskLevel::Low, "read-only")),
+            ("b", review(ReviewDecision::Deny, RiskLevel::High, "leaks secrets")),
+        )
+        .unwrap();
+        assert_eq!(disagreed.decision, ReviewDecision::Manual);
+        assert_eq!(disagreed.risk_level, RiskLevel::High);
+        assert_eq!(disagreed.reason, "a: read-only\nb: leaks secrets");
+
+        let failed = consensus(
+            ("a", review(ReviewDecision::Allow, RiskLevel::Low, "read-only")),
+            ("b", Err(anyhow::anyhow!("timeout"))),
+        )
+        .unwrap();
+        assert_eq!(failed.decision, ReviewDecision::Manual);
+    }
+
+    #[test]
+    fn test_instructions() {
+        let mut settings = AutoReviewSettings::default();
+        assert_eq!(instructions(&settings), INSTRUCTIONS_EN);
+
+        settings.language = "zh".to_string();
+        assert_eq!(instructions(&settings), INSTRUCTIONS_ZH);
+
+        settings.language = "Japanese".to_string();
+        assert!(
+            instructions(&settings)
+                .ends_with("Write the reason in Japanese, using its usual terminology.")
+        );
+
+        // A custom prompt wins over the language
+        settings.system_prompt = Some("Review tool calls for the payments team.".to_string());
+        assert_eq!(instructions(&settings), "Review tool calls for the payments team.");
+        assert!(system_prompt(&instructions(&settings), None).ends_with(REPLY_FORMAT));
+    }
+
+    #[test]
+    fn test_apply_thresholds() {
+        let config = ProjectReviewConfig {
+            allow_max_risk: Some(RiskLevel::Low),
+            deny_min_risk: Some(RiskLevel::Medium),
+            ..Default::default()
+        };
+        let reviewed = |decision, risk_level| {
+            let mut review = PermissionReview {
+                decision,
+                reason: String::new(),
+                risk_level,
+            };
+            apply_thresholds(&mut review, &config);
+            review.decision
+        };
+
+        assert_eq!(reviewed(ReviewDecision::Allow, RiskLevel::Low), ReviewDecision::Allow);
+        assert_eq!(reviewed(ReviewDecision::Allow, RiskLevel::Medium), ReviewDecision::Manual);
+        assert_eq!(reviewed(ReviewDecision::Deny, RiskLevel::High), ReviewDecision::Deny);
+        assert_eq!(reviewed(ReviewDecision::Deny, RiskLevel::Low), ReviewDecision::Manual);
+    }
+
+    #[test]
+    fn test_review_outcome() {
+        let options = vec![
+            option("allow_always"),
+            option("allow_once"),
+            option("reject_once"),
+        ];
+        assert_eq!(
+            review_outcome(ReviewDecision::Allow, &options),
+            Some(PermissionOutcome::selected("allow_once-id"))
+        );
+        assert_eq!(
+            review_outcome(ReviewDecision::Deny, &options),
+            Some(PermissionOutcome::selected("reject_once-id"))
+        );
+        assert_eq!(review_outcome(ReviewDecision::Manual, &options), None);
+
+        // Without a reject option a denial cancels
+        assert_eq!(
+            review_outcome(ReviewDecision::Deny, &options[..2]),
+            Some(PermissionOutcome::cancelled())
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! Per-project quotas
+//!
+//! Defaults come from `[application.quotas]`; the concurrent session quota
+//! defaults to `concurrency.max_sessions_per_project`. Projects may override
+//! each limit. The relay manager holds the session quotas, so spawns over one
+//! wait in the spawn queue like spawns over any other cap. Events emitted
+//! through the API and uploaded artifacts (email attachments included) are
+//! refused over their quota, and permission requests over the monthly AI
+//! review quota are left to a human.
+
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::config::QuotaSettings;
+use crate::db::DatabaseService;
+use crate::models::{
+    allows, month_start, next_month_start, QuotaExceeded, QuotaLimits, QuotaMetric, QuotaStatus,
+    QuotaUsage,
+};
+use crate::relay::RelayManager;
+
+/// The configured defaults; sessions are left to the relay manager
+pub fn default_limits(settings: &QuotaSettings) -> QuotaLimits {
+    QuotaLimits {
+        max_concurrent_sessions: None,
+        events_per_hour: settings.events_per_hour,
+        storage_bytes: settings.storage_bytes,
+        ai_reviews_per_month: settings.ai_reviews_per_month,
+    }
+}
+
+/// Limits in effect for a project
+pub async fn limits(
+    db: &DatabaseService,
+    settings: &QuotaSettings,
+    project_id: Uuid,
+) -> crate::Result<QuotaLimits> {
+    let defaults = default_limits(settings);
+    Ok(match db.get_project_quotas(project_id).await? {
+        Some(quotas) => defaults.overridden(&quotas.overrides),
+        None => defaults,
+    })
+}
+
+/// The quota one more event of a project would go over, if any
+pub async fn check_event(
+    db: &DatabaseService,
+    settings: &QuotaSettings,
+    project_id: Uuid,
+) -> crate::Result<Option<QuotaExceeded>> {
+    let Some(limit) = limits(db, settings, project_id).await?.events_per_hour else {
+        return Ok(None);
+    };
+    let since = Utc::now() - Duration::hours(1);
+    let used = db.count_project_events(project_id, since).await?.max(0) as u64;
+    Ok(exceeded(QuotaMetric::EventsPerHour, used, 1, limit))
+}
+
+/// The quota storing `bytes` more for a project would go over, if any
+pub async fn check_storage(
+    db: &DatabaseService,
+    settings: &QuotaSettings,
+    project_id: Uuid,
+    bytes: u64,
+) -> crate::Result<Option<QuotaExceeded>> {
+    let Some(limit) = limits(db, settings, project_id).await?.storage_bytes else {
+        return Ok(None);
+    };
+    let used = db.project_storage_bytes(project_id).await?.max(0) as u64;
+    Ok(exceeded(QuotaMetric::StorageBytes, used, bytes, limit))
+}
+
+/// Count an AI review of a project's permission request; false when the
+/// month's quota is used up
+pub async fn consume_ai_review(
+    db: &DatabaseService,
+    settings: &QuotaSettings,
+    project_id: Uuid,
+) -> crate::Result<bool> {
+    let limit = limits(db, settings, project_id).await?.ai_reviews_per_month;
+    db.consume_quota(
+        project_id,
+        QuotaMetric::AiReviewsPerMonth,
+        month_start(Utc::now()),
+        limit,
+    )
+    .await
+}
+
+/// Use of every quota of a project against the limits in effect
+pub async fn status(
+    db: &DatabaseService,
+    relays: &RelayManager,
+    settings: &QuotaSettings,
+    project_id: Uuid,
+) -> crate::Result<QuotaStatus> {
+    let overrides = db
+        .get_project_quotas(project_id)
+        .await?
+        .map(|quotas| quotas.overrides)
+        .unwrap_or_default();
+    let limits = default_limits(settings).overridden(&overrides);
+    let now = Utc::now();
+
+    let sessions = relays.project_session_count(project_id).await as u64;
+    let events = db
+        .count_project_events(project_id, now - Duration::hours(1))
+        .await?;
+    let storage = db.project_storage_bytes(project_id).await?;
+    let reviews = db
+        .quota_usage(project_id, QuotaMetric::AiReviewsPerMonth, month_start(now))
+        .await?;
+
+    Ok(QuotaStatus {
+        project_id,
+        overrides,
+        quotas: vec![
+            QuotaUsage::new(
+                QuotaMetric::ConcurrentSessions,
+                sessions,
+                relays
+                    .project_session_limit(project_id)
+                    .map(|max| max as u64),
+            ),
+            QuotaUsage::new(
+                QuotaMetric::EventsPerHour,
+                events.max(0) as u64,
+                limits.events_per_hour,
+            ),
+            QuotaUsage::new(
+                QuotaMetric::StorageBytes,
+                storage.max(0) as u64,
+                limits.storage_bytes,
+            ),
+            QuotaUsage::new(
+                QuotaMetric::AiReviewsPerMonth,
+                reviews.max(0) as u64,
+                limits.ai_reviews_per_month,
+            ),
+        ],
+        ai_reviews_reset_at: next_month_start(now),
+    })
+}
+
+/// Hand the session quotas of all projects to the relay manager
+pub async fn load_session_quotas(db: &DatabaseService, relays: &RelayManager) -> crate::Result<()> {
+    for quotas in db.list_project_quotas().await? {
+        if let Some(max) = quotas.overrides.max_concurrent_sessions {
+            relays.set_session_quota(quotas.project_id, Some(max as usize));
+        }
+    }
+    Ok(())
+}
+
+fn exceeded(metric: QuotaMetric, used: u64, added: u64, limit: u64) -> Option<QuotaExceeded> {
+    (!allows(used, added, Some(limit))).then_some(QuotaExceeded {
+        metric,
+        used,
+        limit,
+    })
+}
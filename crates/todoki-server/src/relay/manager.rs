@@ -1,29 +1,39 @@
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
-use chrono::Utc;
-use serde_json::Value;
+use chrono::{DateTime, Utc};
+use todoki_protocol::event_bus::{
+    BuiltinEvent, PermissionOutcome, PermissionRequestedData, PermissionReview,
+};
+use todoki_protocol::relay_version::UNVERSIONED_RELAY_PROTOCOL;
 use tokio::sync::{Mutex, RwLock};
 use uuid::Uuid;
 
-use super::{RelayInfo, AgentRole};
+use super::{AgentRole, CapacityInfo, LabelSelector, ProjectCapacity, RelayInfo};
+use crate::auth::agent_token;
+use crate::config::{AgentTokenSettings, ConcurrencySettings, PermissionSettings};
+use crate::event_bus::{Event, EventScope};
+use crate::permission::{self, PendingPermission, PendingPermissionInfo, PermissionAnswer};
 
 /// A set of project UUIDs for efficient lookup
 pub type ProjectSet = HashSet<Uuid>;
 
-/// Pending permission request info - only stores session_id, relay_id is derived from active_sessions
-#[derive(Clone)]
-struct PendingPermission {
-    session_id: String,
-}
-
 /// Relay connection manager (in-memory)
 #[derive(Clone)]
 pub struct RelayManager {
     /// relay_id -> RelayConnection
     relays: Arc<RwLock<HashMap<String, RelayConnection>>>,
     /// Pending permission requests: request_id -> PendingPermission
+    /// (relay_id is derived from active_sessions)
     pending_permissions: Arc<Mutex<HashMap<String, PendingPermission>>>,
+    /// Decides how many approvals a request needs
+    permissions: PermissionSettings,
+    /// Concurrent session caps
+    limits: ConcurrencySettings,
+    /// Project quotas replacing `limits.max_sessions_per_project`
+    session_quotas: Arc<std::sync::RwLock<HashMap<Uuid, usize>>>,
+    /// Signs the tokens handed to spawned agents
+    agent_tokens: AgentTokenSettings,
 }
 
 pub struct RelayConnection {
@@ -35,22 +45,63 @@ pub struct RelayConnection {
     pub projects: ProjectSet,
     pub setup_script: Option<String>,
     pub connected_at: i64,
-    pub active_sessions: HashSet<String>,
+    /// session_id -> project the session runs for
+    pub active_sessions: HashMap<String, Option<Uuid>>,
+    /// Relay protocol version agreed on in `relay.up`
+    pub protocol_version: u16,
+    /// WebSocket connection the relay registered on
+    pub connection_id: Uuid,
+}
+
+impl RelayConnection {
+    fn info(&self, max_sessions: usize) -> RelayInfo {
+        RelayInfo {
+            relay_id: self.relay_id.clone(),
+            name: self.name.clone(),
+            role: self.role.as_str().to_string(),
+            safe_paths: self.safe_paths.clone(),
+            labels: self.labels.clone(),
+            projects: self.projects.iter().copied().collect(),
+            setup_script: self.setup_script.clone(),
+            connected_at: self.connected_at,
+            active_session_count: self.active_sessions.len(),
+            max_sessions,
+            protocol_version: self.protocol_version,
+        }
+    }
 }
 
 impl RelayManager {
     pub fn new() -> Self {
+        Self::with_limits(ConcurrencySettings::default())
+    }
+
+    pub fn with_limits(limits: ConcurrencySettings) -> Self {
         Self {
             relays: Arc::new(RwLock::new(HashMap::new())),
             pending_permissions: Arc::new(Mutex::new(HashMap::new())),
+            limits,
+            session_quotas: Arc::new(std::sync::RwLock::new(HashMap::new())),
+            agent_tokens: AgentTokenSettings::default(),
+            permissions: PermissionSettings::default(),
         }
     }
 
+    pub fn with_agent_tokens(mut self, agent_tokens: AgentTokenSettings) -> Self {
+        self.agent_tokens = agent_tokens;
+        self
+    }
+
+    pub fn with_permissions(mut self, permissions: PermissionSettings) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
     /// Register a relay connection with a stable ID provided by the relay
     /// If a relay with the same ID is already connected, it will be replaced (reconnect scenario)
     ///
-    /// In the new Event Bus architecture, relays communicate via the Event Bus WebSocket
-    /// instead of a dedicated channel. The tx channel has been removed.
+    /// Relays communicate via the `/ws/relay` WebSocket; the returned ID
+    /// identifies this connection so a replaced one can tell it was superseded.
     pub async fn register(
         &self,
         relay_id: String,
@@ -60,7 +111,7 @@ impl RelayManager {
         labels: HashMap<String, String>,
         projects: Vec<Uuid>,
         setup_script: Option<String>,
-    ) -> String {
+    ) -> Uuid {
         let mut relays = self.relays.write().await;
 
         // Check if this relay was previously connected (reconnect scenario)
@@ -73,10 +124,11 @@ impl RelayManager {
             // Preserve active sessions from old connection
             old_conn.active_sessions
         } else {
-            HashSet::new()
+            HashMap::new()
         };
 
         let projects_set: ProjectSet = projects.iter().copied().collect();
+        let connection_id = Uuid::new_v4();
 
         let connection = RelayConnection {
             relay_id: relay_id.clone(),
@@ -88,6 +140,8 @@ impl RelayManager {
             setup_script,
             connected_at: Utc::now().timestamp(),
             active_sessions: previous_sessions,
+            protocol_version: UNVERSIONED_RELAY_PROTOCOL,
+            connection_id,
         };
 
         relays.insert(relay_id.clone(), connection);
@@ -99,12 +153,34 @@ impl RelayManager {
             "relay registered"
         );
 
-        relay_id
+        connection_id
+    }
+
+    /// Record the protocol version agreed on with a relay
+    pub async fn set_protocol_version(&self, relay_id: &str, version: u16) {
+        if let Some(conn) = self.relays.write().await.get_mut(relay_id) {
+            conn.protocol_version = version;
+        }
     }
 
-    /// Unregister a relay (on disconnect)
-    pub async fn unregister(&self, relay_id: &str) -> Vec<String> {
+    /// Whether `connection_id` is still the relay's registered connection
+    pub async fn is_current_connection(&self, relay_id: &str, connection_id: Uuid) -> bool {
+        let relays = self.relays.read().await;
+        relays
+            .get(relay_id)
+            .is_some_and(|conn| conn.connection_id == connection_id)
+    }
+
+    /// Unregister a relay (on disconnect); nothing happens when the
+    /// connection was already replaced by a newer one
+    pub async fn unregister(&self, relay_id: &str, connection_id: Uuid) -> Vec<String> {
         let mut relays = self.relays.write().await;
+        if relays
+            .get(relay_id)
+            .is_some_and(|conn| conn.connection_id != connection_id)
+        {
+            return Vec::new();
+        }
         let active_sessions = if let Some(conn) = relays.remove(relay_id) {
             tracing::info!(
                 relay_id = %relay_id,
@@ -112,7 +188,7 @@ impl RelayManager {
                 sessions = ?conn.active_sessions,
                 "relay unregistered"
             );
-            conn.active_sessions.into_iter().collect()
+            conn.active_sessions.into_keys().collect()
         } else {
             Vec::new()
         };
@@ -120,17 +196,35 @@ impl RelayManager {
     }
 
     /// Select an available relay based on role, project and availability
-    /// - If preferred_id is specified and the relay is idle, use it
-    /// - Otherwise, find an idle relay that matches the required_role (or is General)
-    ///   and the required_project (or has no project restrictions)
+    /// - Nothing is selected while the global or project session cap is reached
+    /// - If preferred_id is specified and the relay has a free slot, use it
+    /// - Otherwise, find a relay with a free slot that matches the required_role
+    ///   (or is General) and the required_project (or has no project restrictions)
     pub async fn select_relay(
         &self,
         preferred_id: Option<&str>,
         required_role: Option<AgentRole>,
         required_project: Option<Uuid>,
+    ) -> Option<String> {
+        self.select_relay_matching(preferred_id, required_role, required_project, None)
+            .await
+    }
+
+    /// Like [`select_relay`](Self::select_relay), additionally requiring the
+    /// relay's labels to match `selector` (the preferred relay included)
+    pub async fn select_relay_matching(
+        &self,
+        preferred_id: Option<&str>,
+        required_role: Option<AgentRole>,
+        required_project: Option<Uuid>,
+        selector: Option<&LabelSelector>,
     ) -> Option<String> {
         let relays = self.relays.read().await;
 
+        if self.over_shared_limits(&relays, required_project) {
+            return None;
+        }
+
         // Helper to check if relay matches role requirement
         let role_matches = |conn: &RelayConnection, required: Option<AgentRole>| -> bool {
             match required {
@@ -148,12 +242,22 @@ impl RelayManager {
             }
         };
 
-        // Helper to check if relay is idle (single-task mode)
-        let is_idle = |conn: &RelayConnection| -> bool { conn.active_sessions.is_empty() };
+        // Helper to check if relay has a free session slot
+        let has_slot = |conn: &RelayConnection| -> bool {
+            conn.active_sessions.len() < self.limits.max_sessions_per_relay
+        };
+
+        let labels_match = |conn: &RelayConnection| -> bool {
+            selector.is_none_or(|selector| selector.matches(&conn.labels))
+        };
 
         // Combined check
-        let matches_all =
-            |conn: &RelayConnection| -> bool { is_idle(conn) && role_matches(conn, required_role) && project_matches(conn, required_project) };
+        let matches_all = |conn: &RelayConnection| -> bool {
+            has_slot(conn)
+                && role_matches(conn, required_role)
+                && project_matches(conn, required_project)
+                && labels_match(conn)
+        };
 
         // If preferred relay is specified, check if it's available
         if let Some(id) = preferred_id {
@@ -164,15 +268,143 @@ impl RelayManager {
             }
         }
 
-        // Find first idle relay that matches all requirements
-        relays.values().find(|conn| matches_all(conn)).map(|conn| conn.relay_id.clone())
+        // Find the least loaded relay that matches all requirements
+        relays
+            .values()
+            .filter(|conn| matches_all(conn))
+            .min_by_key(|conn| conn.active_sessions.len())
+            .map(|conn| conn.relay_id.clone())
     }
 
-    /// Add active session to relay
-    pub async fn add_active_session(&self, relay_id: &str, session_id: &str) {
+    /// Whether a spawn for this role/project should wait for a free slot
+    ///
+    /// True when the global or project cap is reached, or when relays that
+    /// could take the spawn are connected but all of them are full. False when
+    /// no matching relay is connected at all, since waiting would not help.
+    pub async fn at_capacity(
+        &self,
+        required_role: Option<AgentRole>,
+        required_project: Option<Uuid>,
+    ) -> bool {
+        let relays = self.relays.read().await;
+
+        if self.over_shared_limits(&relays, required_project) {
+            return true;
+        }
+
+        let candidates: Vec<&RelayConnection> = relays
+            .values()
+            .filter(|conn| {
+                let role_ok = match required_role {
+                    None => true,
+                    Some(req) => conn.role == req || conn.role == AgentRole::General,
+                };
+                let project_ok = match required_project {
+                    None => true,
+                    Some(project_id) => conn.projects.is_empty() || conn.projects.contains(&project_id),
+                };
+                role_ok && project_ok
+            })
+            .collect();
+        !candidates.is_empty()
+            && candidates
+                .iter()
+                .all(|conn| conn.active_sessions.len() >= self.limits.max_sessions_per_relay)
+    }
+
+    /// Whether the global or the project session cap is reached
+    fn over_shared_limits(
+        &self,
+        relays: &HashMap<String, RelayConnection>,
+        project_id: Option<Uuid>,
+    ) -> bool {
+        if let Some(max) = self.limits.max_sessions_global {
+            let total: usize = relays.values().map(|conn| conn.active_sessions.len()).sum();
+            if total >= max {
+                return true;
+            }
+        }
+        if let Some(project_id) = project_id
+            && let Some(max) = self.project_session_limit(project_id)
+            && count_project_sessions(relays, project_id) >= max
+        {
+            return true;
+        }
+        false
+    }
+
+    /// Set a project's concurrent session quota; `None` falls back to
+    /// `max_sessions_per_project`
+    pub fn set_session_quota(&self, project_id: Uuid, max_sessions: Option<usize>) {
+        let mut quotas = self
+            .session_quotas
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        match max_sessions {
+            Some(max) => quotas.insert(project_id, max),
+            None => quotas.remove(&project_id),
+        };
+    }
+
+    /// Sessions a project may run at once
+    pub fn project_session_limit(&self, project_id: Uuid) -> Option<usize> {
+        let quotas = self
+            .session_quotas
+            .read()
+            .unwrap_or_else(|e| e.into_inner());
+        quotas
+            .get(&project_id)
+            .copied()
+            .or(self.limits.max_sessions_per_project)
+    }
+
+    /// Sessions running for a project
+    pub async fn project_session_count(&self, project_id: Uuid) -> usize {
+        let relays = self.relays.read().await;
+        count_project_sessions(&relays, project_id)
+    }
+
+    /// Current session utilization against the configured caps
+    pub async fn capacity(&self) -> CapacityInfo {
+        let relays = self.relays.read().await;
+
+        let mut per_project: HashMap<Uuid, usize> = HashMap::new();
+        for project_id in relays
+            .values()
+            .flat_map(|conn| conn.active_sessions.values())
+            .flatten()
+        {
+            *per_project.entry(*project_id).or_default() += 1;
+        }
+        let mut projects: Vec<ProjectCapacity> = per_project
+            .into_iter()
+            .map(|(project_id, active_sessions)| ProjectCapacity {
+                project_id,
+                active_sessions,
+                max_sessions: self.project_session_limit(project_id),
+            })
+            .collect();
+        projects.sort_by_key(|p| p.project_id);
+
+        CapacityInfo {
+            active_sessions: relays.values().map(|conn| conn.active_sessions.len()).sum(),
+            max_sessions_global: self.limits.max_sessions_global,
+            max_sessions_per_relay: self.limits.max_sessions_per_relay,
+            max_sessions_per_project: self.limits.max_sessions_per_project,
+            projects,
+        }
+    }
+
+    /// Add active session to relay, counted against `project_id`'s cap
+    pub async fn add_active_session(
+        &self,
+        relay_id: &str,
+        session_id: &str,
+        project_id: Option<Uuid>,
+    ) {
         let mut relays = self.relays.write().await;
         if let Some(conn) = relays.get_mut(relay_id) {
-            conn.active_sessions.insert(session_id.to_string());
+            conn.active_sessions.insert(session_id.to_string(), project_id);
         }
     }
 
@@ -189,34 +421,14 @@ impl RelayManager {
         let relays = self.relays.read().await;
         relays
             .values()
-            .map(|conn| RelayInfo {
-                relay_id: conn.relay_id.clone(),
-                name: conn.name.clone(),
-                role: conn.role.as_str().to_string(),
-                safe_paths: conn.safe_paths.clone(),
-                labels: conn.labels.clone(),
-                projects: conn.projects.iter().copied().collect(),
-                setup_script: conn.setup_script.clone(),
-                connected_at: conn.connected_at,
-                active_session_count: conn.active_sessions.len(),
-            })
+            .map(|conn| conn.info(self.limits.max_sessions_per_relay))
             .collect()
     }
 
     /// Get relay info by ID
     pub async fn get_relay(&self, relay_id: &str) -> Option<RelayInfo> {
         let relays = self.relays.read().await;
-        relays.get(relay_id).map(|conn| RelayInfo {
-            relay_id: conn.relay_id.clone(),
-            name: conn.name.clone(),
-            role: conn.role.as_str().to_string(),
-            safe_paths: conn.safe_paths.clone(),
-            labels: conn.labels.clone(),
-            projects: conn.projects.iter().copied().collect(),
-            setup_script: conn.setup_script.clone(),
-            connected_at: conn.connected_at,
-            active_session_count: conn.active_sessions.len(),
-        })
+        relays.get(relay_id).map(|conn| conn.info(self.limits.max_sessions_per_relay))
     }
 
     /// Check if relay is connected
@@ -240,17 +452,7 @@ impl RelayManager {
         relays
             .values()
             .filter(|conn| conn.projects.is_empty() || conn.projects.contains(&project_id))
-            .map(|conn| RelayInfo {
-                relay_id: conn.relay_id.clone(),
-                name: conn.name.clone(),
-                role: conn.role.as_str().to_string(),
-                safe_paths: conn.safe_paths.clone(),
-                labels: conn.labels.clone(),
-                projects: conn.projects.iter().copied().collect(),
-                setup_script: conn.setup_script.clone(),
-                connected_at: conn.connected_at,
-                active_session_count: conn.active_sessions.len(),
-            })
+            .map(|conn| conn.info(self.limits.max_sessions_per_relay))
             .collect()
     }
 
@@ -258,7 +460,7 @@ impl RelayManager {
     pub async fn get_relay_for_session(&self, session_id: &str) -> Option<String> {
         let relays = self.relays.read().await;
         for conn in relays.values() {
-            if conn.active_sessions.contains(session_id) {
+            if conn.active_sessions.contains_key(session_id) {
                 return Some(conn.relay_id.clone());
             }
         }
@@ -267,25 +469,21 @@ impl RelayManager {
 
     /// Store a pending permission request
     /// Note: relay_id is kept in signature for logging but not stored (derived from active_sessions)
-    pub async fn store_permission_request(
-        &self,
-        relay_id: &str,
-        request_id: &str,
-        session_id: &str,
-    ) {
-        let mut pending = self.pending_permissions.lock().await;
-        pending.insert(
-            request_id.to_string(),
-            PendingPermission {
-                session_id: session_id.to_string(),
-            },
-        );
+    pub async fn store_permission_request(&self, relay_id: &str, request: PermissionRequestedData) {
+        let required_approvals =
+            permission::required_approvals(&self.permissions, &request.tool_call);
         tracing::debug!(
-            request_id = %request_id,
+            request_id = %request.request_id,
             relay_id = %relay_id,
-            session_id = %session_id,
+            session_id = %request.session_id,
+            required_approvals = required_approvals,
             "stored pending permission request"
         );
+        let mut pending = self.pending_permissions.lock().await;
+        pending.insert(
+            request.request_id.clone(),
+            PendingPermission::new(request, required_approvals),
+        );
     }
 
     /// Get pending permission info without removing it
@@ -293,7 +491,7 @@ impl RelayManager {
     pub async fn get_pending_permission(&self, request_id: &str) -> Option<(String, String)> {
         let pending = self.pending_permissions.lock().await;
         if let Some(p) = pending.get(request_id) {
-            let session_id = p.session_id.clone();
+            let session_id = p.request.session_id.clone();
             drop(pending); // Release lock before calling get_relay_for_session
             if let Some(relay_id) = self.get_relay_for_session(&session_id).await {
                 return Some((relay_id, session_id));
@@ -308,50 +506,140 @@ impl RelayManager {
         pending.remove(request_id);
     }
 
+    /// Record an answer to a pending request; `None` if it is not pending
+    pub async fn answer_permission(
+        &self,
+        request_id: &str,
+        answered_by: &str,
+        outcome: PermissionOutcome,
+    ) -> Option<Result<PermissionAnswer, String>> {
+        let mut pending = self.pending_permissions.lock().await;
+        let p = pending.get_mut(request_id)?;
+        Some(p.answer(answered_by, outcome))
+    }
+
+    /// Attach the automatic review to a pending request
+    pub async fn set_permission_review(&self, request_id: &str, review: PermissionReview) -> bool {
+        let mut pending = self.pending_permissions.lock().await;
+        match pending.get_mut(request_id) {
+            Some(p) => {
+                p.request.review = Some(review);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Approvals a pending request needs
+    pub async fn required_approvals(&self, request_id: &str) -> Option<usize> {
+        let pending = self.pending_permissions.lock().await;
+        pending.get(request_id).map(|p| p.required_approvals)
+    }
+
+    /// Pending requests with their approvals, oldest first
+    pub async fn list_pending_permissions(&self) -> Vec<PendingPermissionInfo> {
+        let pending: Vec<PendingPermission> = {
+            let pending = self.pending_permissions.lock().await;
+            pending.values().cloned().collect()
+        };
+        let mut list = Vec::with_capacity(pending.len());
+        for p in pending {
+            let relay_id = self.get_relay_for_session(&p.request.session_id).await;
+            list.push(PendingPermissionInfo::new(p, relay_id));
+        }
+        list.sort_by_key(|info| info.requested_at);
+        list
+    }
+
+    /// Remove and return requests pending since before `cutoff`
+    /// Returns (request_id, session_id) pairs
+    pub async fn take_expired_permissions(&self, cutoff: DateTime<Utc>) -> Vec<(String, String)> {
+        let mut pending = self.pending_permissions.lock().await;
+        let expired: Vec<String> = pending
+            .iter()
+            .filter(|(_, p)| p.requested_at < cutoff)
+            .map(|(request_id, _)| request_id.clone())
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|request_id| {
+                let p = pending.remove(&request_id)?;
+                Some((request_id, p.request.session_id))
+            })
+            .collect()
+    }
+
     /// Emit a relay command event to Event Bus
     ///
     /// This replaces the old RPC-based approach. The relay will receive the event
-    /// through its Event Bus WebSocket subscription.
+    /// through its Event Bus WebSocket subscription. The command payload carries
+    /// its own relay_id and request_id; `relay_id` is used to check the relay
+    /// is connected before emitting. `scope` ties the command to its task and
+    /// execution.
     pub async fn emit_relay_command(
         &self,
         publisher: &crate::event_bus::EventPublisher,
         relay_id: &str,
-        kind: &str,
-        request_id: String,
-        mut data: Value,
-        task_id: Option<Uuid>,
-    ) -> anyhow::Result<String> {
+        command: BuiltinEvent,
+        scope: EventScope,
+    ) -> anyhow::Result<()> {
         // Check if relay is connected
         if !self.is_connected(relay_id).await {
             anyhow::bail!("relay {} not connected", relay_id);
         }
 
-        // Inject relay_id and request_id into event data
-        if let Some(obj) = data.as_object_mut() {
-            obj.insert("relay_id".to_string(), Value::String(relay_id.to_string()));
-            obj.insert(
-                "request_id".to_string(),
-                Value::String(request_id.clone()),
-            );
-        }
-
-        // Create and emit event with task_id
-        let mut event = crate::event_bus::Event::new(kind.to_string(), Uuid::nil(), data);
-        event.task_id = task_id;
+        let event = Event::builtin(self.with_agent_token(command), scope);
+        let kind = event.kind.clone();
         publisher.emit(event).await?;
 
         tracing::debug!(
             kind = %kind,
             relay_id = %relay_id,
-            request_id = %request_id,
-            task_id = ?task_id,
+            task_id = ?scope.task_id,
+            correlation_id = ?scope.correlation_id,
             "emitted relay command event"
         );
 
-        Ok(request_id)
+        Ok(())
+    }
+
+    /// Add a scoped token for the agent to a spawn command
+    ///
+    /// Without a signing key (tests) the command is sent unchanged.
+    fn with_agent_token(&self, command: BuiltinEvent) -> BuiltinEvent {
+        if self.agent_tokens.secret.is_empty() {
+            return command;
+        }
+        match command {
+            BuiltinEvent::RelaySpawnRequested(mut spawn) => {
+                let ids = (
+                    Uuid::parse_str(&spawn.target_agent_id),
+                    Uuid::parse_str(&spawn.session_id),
+                );
+                if let (Ok(agent_id), Ok(session_id)) = ids {
+                    let task_id = spawn.task_id.as_deref().and_then(|id| Uuid::parse_str(id).ok());
+                    spawn.env.extend(agent_token::spawn_env(
+                        &self.agent_tokens,
+                        agent_id,
+                        session_id,
+                        task_id,
+                    ));
+                }
+                BuiltinEvent::RelaySpawnRequested(spawn)
+            }
+            command => command,
+        }
     }
 }
 
+fn count_project_sessions(relays: &HashMap<String, RelayConnection>, project_id: Uuid) -> usize {
+    relays
+        .values()
+        .flat_map(|conn| conn.active_sessions.values())
+        .filter(|p| **p == Some(project_id))
+        .count()
+}
+
 impl Default for RelayManager {
     fn default() -> Self {
         Self::new()
@@ -431,7 +719,7 @@ mod tests {
             .await;
 
         // Mark it as busy
-        manager.add_active_session("coding-1", "session-1").await;
+        manager.add_active_session("coding-1", "session-1", None).await;
 
         // Try to select - should fail (no idle relay)
         let selected = manager
@@ -485,7 +773,7 @@ mod tests {
         assert_eq!(selected, Some("coding-2".to_string()));
 
         // Mark preferred as busy, should fall back to other
-        manager.add_active_session("coding-2", "session-1").await;
+        manager.add_active_session("coding-2", "session-1", None).await;
         let selected = manager
             .select_relay(Some("coding-2"), Some(AgentRole::Coding), None)
             .await;
@@ -536,7 +824,7 @@ mod tests {
         );
 
         // Mark relay-a as busy
-        manager.add_active_session("relay-a", "session-1").await;
+        manager.add_active_session("relay-a", "session-1", None).await;
 
         // Now only relay-universal should match for project_a
         let selected = manager
@@ -551,7 +839,7 @@ mod tests {
         assert_eq!(selected, Some("relay-universal".to_string()));
 
         // Mark relay-universal as busy
-        manager.add_active_session("relay-universal", "session-2").await;
+        manager.add_active_session("relay-universal", "session-2", None).await;
 
         // Now no relay should match for project_b
         let selected = manager
@@ -560,6 +848,39 @@ mod tests {
         assert_eq!(selected, None);
     }
 
+    #[tokio::test]
+    async fn test_select_relay_matching_labels() {
+        let manager = RelayManager::new();
+
+        let gpu = HashMap::from([("gpu".to_string(), "true".to_string())]);
+        for (id, labels) in [("relay-cpu", HashMap::new()), ("relay-gpu", gpu)] {
+            manager
+                .register(
+                    id.to_string(),
+                    id.to_string(),
+                    AgentRole::General,
+                    vec![],
+                    labels,
+                    vec![],
+                    None,
+                )
+                .await;
+        }
+        let selector = LabelSelector::parse("gpu=true").unwrap();
+
+        // A preferred relay that does not match the selector is passed over
+        let selected = manager
+            .select_relay_matching(Some("relay-cpu"), None, None, Some(&selector))
+            .await;
+        assert_eq!(selected, Some("relay-gpu".to_string()));
+
+        manager.add_active_session("relay-gpu", "session-1", None).await;
+        let selected = manager
+            .select_relay_matching(None, None, None, Some(&selector))
+            .await;
+        assert_eq!(selected, None);
+    }
+
     #[tokio::test]
     async fn test_list_relays_by_project() {
         let manager = RelayManager::new();
@@ -626,4 +947,160 @@ mod tests {
         assert_eq!(relays_for_c.len(), 1);
         assert_eq!(relays_for_c[0].relay_id, "relay-universal");
     }
+
+    #[tokio::test]
+    async fn test_concurrency_limits() {
+        let manager = RelayManager::with_limits(ConcurrencySettings {
+            max_sessions_per_relay: 2,
+            max_sessions_per_project: Some(2),
+            max_sessions_global: Some(3),
+        });
+
+        let project_a = Uuid::new_v4();
+        let project_b = Uuid::new_v4();
+
+        for id in ["relay-1", "relay-2"] {
+            manager
+                .register(
+                    id.to_string(),
+                    id.to_string(),
+                    AgentRole::General,
+                    vec![],
+                    HashMap::new(),
+                    vec![],
+                    None,
+                )
+                .await;
+        }
+
+        // A relay takes a second session while under its cap
+        manager.add_active_session("relay-1", "session-1", Some(project_a)).await;
+        assert_eq!(
+            manager.select_relay(Some("relay-1"), None, Some(project_b)).await,
+            Some("relay-1".to_string())
+        );
+
+        // Project cap reached: project_a waits, project_b still fits
+        manager.add_active_session("relay-1", "session-2", Some(project_a)).await;
+        assert_eq!(manager.select_relay(None, None, Some(project_a)).await, None);
+        assert!(manager.at_capacity(None, Some(project_a)).await);
+        assert_eq!(
+            manager.select_relay(None, None, Some(project_b)).await,
+            Some("relay-2".to_string())
+        );
+
+        // Global cap reached: nothing is selected
+        manager.add_active_session("relay-2", "session-3", Some(project_b)).await;
+        assert_eq!(manager.select_relay(None, None, Some(project_b)).await, None);
+        assert!(manager.at_capacity(None, Some(project_b)).await);
+
+        let capacity = manager.capacity().await;
+        assert_eq!(capacity.active_sessions, 3);
+        let relay_1 = manager.get_relay("relay-1").await.unwrap();
+        assert_eq!(relay_1.active_session_count, 2);
+        assert_eq!(relay_1.max_sessions, 2);
+
+        // Freeing a slot lets spawns through again
+        manager.remove_active_session("relay-1", "session-1").await;
+        assert!(!manager.at_capacity(None, Some(project_a)).await);
+    }
+
+    #[tokio::test]
+    async fn test_session_quota() {
+        let manager = RelayManager::with_limits(ConcurrencySettings {
+            max_sessions_per_relay: 4,
+            max_sessions_per_project: Some(2),
+            max_sessions_global: None,
+        });
+        let project = Uuid::new_v4();
+        manager
+            .register(
+                "relay-1".to_string(),
+                "relay-1".to_string(),
+                AgentRole::General,
+                vec![],
+                HashMap::new(),
+                vec![],
+                None,
+            )
+            .await;
+        manager.add_active_session("relay-1", "session-1", Some(project)).await;
+
+        // A quota below the project cap holds further spawns back
+        manager.set_session_quota(project, Some(1));
+        assert_eq!(manager.project_session_limit(project), Some(1));
+        assert!(manager.at_capacity(None, Some(project)).await);
+        assert_eq!(manager.capacity().await.projects[0].max_sessions, Some(1));
+
+        // Without the quota the configured cap applies again
+        manager.set_session_quota(project, None);
+        assert_eq!(manager.project_session_limit(project), Some(2));
+        assert_eq!(manager.project_session_count(project).await, 1);
+        assert!(!manager.at_capacity(None, Some(project)).await);
+    }
+
+    #[tokio::test]
+    async fn test_superseded_connection() {
+        let manager = RelayManager::new();
+        let register = || {
+            manager.register(
+                "relay-1".to_string(),
+                "relay-1".to_string(),
+                AgentRole::General,
+                vec![],
+                HashMap::new(),
+                vec![],
+                None,
+            )
+        };
+        let old = register().await;
+        manager.add_active_session("relay-1", "session-1", None).await;
+        let new = register().await;
+        assert!(!manager.is_current_connection("relay-1", old).await);
+        assert!(manager.is_current_connection("relay-1", new).await);
+
+        // The replaced connection closing leaves the new one registered
+        assert!(manager.unregister("relay-1", old).await.is_empty());
+        assert!(manager.get_relay("relay-1").await.is_some());
+        assert_eq!(manager.unregister("relay-1", new).await, vec!["session-1"]);
+        assert!(manager.get_relay("relay-1").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_take_expired_permissions() {
+        let manager = RelayManager::new();
+        for (request_id, session_id) in [("req-1", "session-1"), ("req-2", "session-2")] {
+            let request = PermissionRequestedData {
+                session_id: session_id.to_string(),
+                request_id: request_id.to_string(),
+                tool_call_id: "call-1".to_string(),
+                tool_call: todoki_protocol::event_bus::ToolCall {
+                    title: "ls".to_string(),
+                    raw_input: serde_json::Value::Null,
+                    tool_call_id: None,
+                },
+                options: vec![],
+                review: None,
+                preview: None,
+            };
+            manager.store_permission_request("relay-1", request).await;
+        }
+
+        // Nothing is older than a cutoff in the past
+        let past = Utc::now() - chrono::Duration::minutes(1);
+        assert!(manager.take_expired_permissions(past).await.is_empty());
+
+        let future = Utc::now() + chrono::Duration::minutes(1);
+        let mut expired = manager.take_expired_permissions(future).await;
+        expired.sort();
+        assert_eq!(
+            expired,
+            vec![
+                ("req-1".to_string(), "session-1".to_string()),
+                ("req-2".to_string(), "session-2".to_string()),
+            ]
+        );
+        // Expired requests are gone
+        assert!(manager.take_expired_permissions(future).await.is_empty());
+    }
 }
@@ -1,10 +1,12 @@
 mod manager;
 mod request_tracker;
+mod selector;
 
 use std::collections::HashMap;
 
 pub use manager::RelayManager;
 pub use request_tracker::RequestTracker;
+pub use selector::LabelSelector;
 
 use gotcha::Schematic;
 use serde::{Deserialize, Serialize};
@@ -29,4 +31,26 @@ pub struct RelayInfo {
     pub setup_script: Option<String>,
     pub connected_at: i64,
     pub active_session_count: usize,
+    /// Session cap for this relay
+    pub max_sessions: usize,
+    /// Relay protocol version agreed on at registration
+    pub protocol_version: u16,
+}
+
+/// Session utilization against the configured concurrency caps
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct CapacityInfo {
+    pub active_sessions: usize,
+    pub max_sessions_global: Option<usize>,
+    pub max_sessions_per_relay: usize,
+    pub max_sessions_per_project: Option<usize>,
+    /// Projects with at least one running session
+    pub projects: Vec<ProjectCapacity>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Schematic)]
+pub struct ProjectCapacity {
+    pub project_id: Uuid,
+    pub active_sessions: usize,
+    pub max_sessions: Option<usize>,
 }
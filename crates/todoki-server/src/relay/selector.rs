@@ -0,0 +1,239 @@
+//! Relay label selectors
+//!
+//! Relays register free-form `key=value` labels. A selector is a
+//! comma-separated list of requirements that must all hold for a relay to be
+//! eligible:
+//!
+//! - `key=value` / `key!=value`: the label equals (or does not equal) a value
+//! - `key in (a,b)` / `key notin (a,b)`: the label is (or is not) one of a set
+//! - `key` / `!key`: the label is present (or absent)
+//!
+//! e.g. `gpu=true,region in (eu,us),!experimental`.
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// A parsed label selector; every requirement must match
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelSelector {
+    requirements: Vec<Requirement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Requirement {
+    Equals(String, String),
+    NotEquals(String, String),
+    In(String, Vec<String>),
+    NotIn(String, Vec<String>),
+    Exists(String),
+    NotExists(String),
+}
+
+impl Requirement {
+    fn parse(term: &str) -> Result<Self, String> {
+        if let Some(key) = term.strip_prefix('!') {
+            return Ok(Requirement::NotExists(parse_key(key, term)?));
+        }
+        if let Some((key, values)) = split_set_op(term, "notin") {
+            return Ok(Requirement::NotIn(
+                parse_key(key, term)?,
+                parse_set(values, term)?,
+            ));
+        }
+        if let Some((key, values)) = split_set_op(term, "in") {
+            return Ok(Requirement::In(
+                parse_key(key, term)?,
+                parse_set(values, term)?,
+            ));
+        }
+        if let Some((key, value)) = term.split_once("!=") {
+            return Ok(Requirement::NotEquals(
+                parse_key(key, term)?,
+                value.trim().to_string(),
+            ));
+        }
+        if let Some((key, value)) = term.split_once('=') {
+            // Accept `==` as an alias of `=`
+            let value = value.strip_prefix('=').unwrap_or(value);
+            return Ok(Requirement::Equals(
+                parse_key(key, term)?,
+                value.trim().to_string(),
+            ));
+        }
+        Ok(Requirement::Exists(parse_key(term, term)?))
+    }
+
+    fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        match self {
+            Requirement::Equals(key, value) => labels.get(key) == Some(value),
+            // A missing label is not equal to anything
+            Requirement::NotEquals(key, value) => labels.get(key) != Some(value),
+            Requirement::In(key, values) => labels.get(key).is_some_and(|v| values.contains(v)),
+            Requirement::NotIn(key, values) => labels.get(key).is_none_or(|v| !values.contains(v)),
+            Requirement::Exists(key) => labels.contains_key(key),
+            Requirement::NotExists(key) => !labels.contains_key(key),
+        }
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Requirement::Equals(key, value) => write!(f, "{}={}", key, value),
+            Requirement::NotEquals(key, value) => write!(f, "{}!={}", key, value),
+            Requirement::In(key, values) => write!(f, "{} in ({})", key, values.join(",")),
+            Requirement::NotIn(key, values) => write!(f, "{} notin ({})", key, values.join(",")),
+            Requirement::Exists(key) => write!(f, "{}", key),
+            Requirement::NotExists(key) => write!(f, "!{}", key),
+        }
+    }
+}
+
+impl LabelSelector {
+    /// Parse a selector; an empty string selects every relay
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let requirements = split_terms(input)?
+            .into_iter()
+            .map(Requirement::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { requirements })
+    }
+
+    /// Whether a relay with `labels` satisfies the selector
+    pub fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        self.requirements.iter().all(|req| req.matches(labels))
+    }
+}
+
+impl fmt::Display for LabelSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let terms: Vec<String> = self.requirements.iter().map(ToString::to_string).collect();
+        write!(f, "{}", terms.join(","))
+    }
+}
+
+/// Split on the commas that are not inside a `(...)` value set
+fn split_terms(input: &str) -> Result<Vec<&str>, String> {
+    let mut terms = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in input.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Err(format!("unbalanced ')' in '{}'", input)),
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                terms.push(&input[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err(format!("unbalanced '(' in '{}'", input));
+    }
+    terms.push(&input[start..]);
+    Ok(terms
+        .into_iter()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .collect())
+}
+
+/// Split `key <op> (values)` into key and the parenthesized values
+fn split_set_op<'a>(term: &'a str, op: &str) -> Option<(&'a str, &'a str)> {
+    let (key, rest) = term.split_once(char::is_whitespace)?;
+    let values = rest.trim_start().strip_prefix(op)?;
+    values
+        .trim_start()
+        .starts_with('(')
+        .then_some((key, values.trim()))
+}
+
+fn parse_key(key: &str, term: &str) -> Result<String, String> {
+    let key = key.trim();
+    if key.is_empty() || key.contains(|c: char| c.is_whitespace() || "!=(),".contains(c)) {
+        return Err(format!("invalid label key in '{}'", term));
+    }
+    Ok(key.to_string())
+}
+
+fn parse_set(values: &str, term: &str) -> Result<Vec<String>, String> {
+    let inner = values
+        .strip_prefix('(')
+        .and_then(|v| v.strip_suffix(')'))
+        .ok_or_else(|| format!("expected a (a,b) value set in '{}'", term))?;
+    let values: Vec<String> = inner
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .collect();
+    if values.is_empty() {
+        return Err(format!("empty value set in '{}'", term));
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_equality_terms_must_all_match() {
+        let selector = LabelSelector::parse("gpu=true, region=eu").unwrap();
+        assert!(selector.matches(&labels(&[
+            ("gpu", "true"),
+            ("region", "eu"),
+            ("os", "linux")
+        ])));
+        assert!(!selector.matches(&labels(&[("gpu", "true"), ("region", "us")])));
+        assert!(!selector.matches(&labels(&[("region", "eu")])));
+        assert_eq!(selector.to_string(), "gpu=true,region=eu");
+    }
+
+    #[test]
+    fn test_set_and_existence_terms() {
+        let selector = LabelSelector::parse("region in (eu, us),!experimental,os").unwrap();
+        assert!(selector.matches(&labels(&[("region", "us"), ("os", "linux")])));
+        assert!(!selector.matches(&labels(&[("region", "ap"), ("os", "linux")])));
+        assert!(!selector.matches(&labels(&[("region", "eu")])));
+        assert!(!selector.matches(&labels(&[
+            ("region", "eu"),
+            ("os", "linux"),
+            ("experimental", "yes")
+        ])));
+        assert_eq!(selector.to_string(), "region in (eu,us),!experimental,os");
+    }
+
+    #[test]
+    fn test_negated_terms_match_missing_labels() {
+        let selector = LabelSelector::parse("tier!=prod,region notin (cn)").unwrap();
+        assert!(selector.matches(&HashMap::new()));
+        assert!(selector.matches(&labels(&[("tier", "dev"), ("region", "eu")])));
+        assert!(!selector.matches(&labels(&[("tier", "prod")])));
+        assert!(!selector.matches(&labels(&[("region", "cn")])));
+    }
+
+    #[test]
+    fn test_empty_selector_matches_everything() {
+        let selector = LabelSelector::parse("").unwrap();
+        assert!(selector.matches(&HashMap::new()));
+    }
+
+    #[test]
+    fn test_invalid_terms_are_rejected() {
+        assert!(LabelSelector::parse("=true").is_err());
+        assert!(LabelSelector::parse("!").is_err());
+        assert!(LabelSelector::parse("region in (eu").is_err());
+        assert!(LabelSelector::parse("region in ()").is_err());
+        assert!(LabelSelector::parse("two words").is_err());
+    }
+}
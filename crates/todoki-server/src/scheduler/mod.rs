@@ -0,0 +1,140 @@
+//! Execution window scheduler
+//!
+//! Executions requested while a project's execution window is closed, or
+//! while a concurrency cap is reached, are stored in `scheduled_executions`.
+//! This worker spawns them once their time comes and a session slot is free,
+//! re-queuing entries whose window has moved or that could not be started
+//! (e.g. no relay connected yet).
+
+pub mod permission_expiry;
+pub mod snooze;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+
+use crate::api::tasks::start_task_execution;
+use crate::db::DatabaseService;
+use crate::event_bus::EventPublisher;
+use crate::models::{AgentRole, AgentStatus, ScheduledExecution, TaskStatus};
+use crate::relay::RelayManager;
+
+/// How often due executions are checked
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Delay before retrying an execution that failed to start
+const RETRY_DELAY_MINUTES: i64 = 5;
+
+pub async fn run_execution_scheduler(
+    db: Arc<DatabaseService>,
+    relays: Arc<RelayManager>,
+    publisher: Arc<EventPublisher>,
+) {
+    let mut interval = tokio::time::interval(SCHEDULER_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let due = match db.list_due_executions(Utc::now()).await {
+            Ok(due) => due,
+            Err(e) => {
+                tracing::error!(error = %e, "failed to list scheduled executions");
+                continue;
+            }
+        };
+
+        for execution in due {
+            if let Err(e) = run_scheduled(&db, &relays, &publisher, &execution).await {
+                tracing::error!(
+                    task_id = %execution.task_id,
+                    error = %e,
+                    "failed to process scheduled execution"
+                );
+            }
+        }
+    }
+}
+
+async fn run_scheduled(
+    db: &DatabaseService,
+    relays: &RelayManager,
+    publisher: &EventPublisher,
+    execution: &ScheduledExecution,
+) -> crate::Result<()> {
+    let Some(task) = db.get_task_by_id(execution.task_id).await? else {
+        return db.delete_scheduled_execution(execution.id).await;
+    };
+    let Some(project) = db.get_project(task.project_id).await? else {
+        return db.delete_scheduled_execution(execution.id).await;
+    };
+
+    // The task may have moved on or been started manually since it was queued
+    let executable = !task.archived
+        && matches!(
+            task.status,
+            TaskStatus::Todo | TaskStatus::InProgress | TaskStatus::InReview
+        );
+    let running = match task.agent_id {
+        Some(agent_id) => db
+            .get_agent(agent_id)
+            .await?
+            .is_some_and(|agent| agent.status == AgentStatus::Running),
+        None => false,
+    };
+    if !executable || running {
+        tracing::info!(task_id = %task.id, "dropping scheduled execution, task no longer executable");
+        return db.delete_scheduled_execution(execution.id).await;
+    }
+
+    // The project's windows may have changed since the execution was queued
+    let now = Utc::now();
+    if let Some(schedule) = project.schedule() {
+        if !schedule.is_open(now) {
+            return match schedule.next_open(now) {
+                Some(next) => db.reschedule_execution(execution.id, next).await,
+                None => db.delete_scheduled_execution(execution.id).await,
+            };
+        }
+    }
+
+    // Wait for a free session slot; due entries are retried on the next tick
+    if relays
+        .at_capacity(Some(AgentRole::Coding.into()), Some(project.id))
+        .await
+    {
+        return Ok(());
+    }
+
+    match start_task_execution(
+        db,
+        relays,
+        publisher,
+        &task,
+        &project,
+        execution.relay_id.as_deref(),
+        execution.relay_selector.as_deref(),
+    )
+    .await
+    {
+        Ok((agent, session)) => {
+            tracing::info!(
+                task_id = %task.id,
+                agent_id = %agent.id,
+                session_id = %session.id,
+                "scheduled execution started"
+            );
+            db.delete_scheduled_execution(execution.id).await
+        }
+        Err(e) => {
+            let retry_at = now + chrono::Duration::minutes(RETRY_DELAY_MINUTES);
+            tracing::warn!(
+                task_id = %task.id,
+                error = %e.message,
+                retry_at = %retry_at,
+                "scheduled execution failed to start, retrying later"
+            );
+            db.reschedule_execution(execution.id, retry_at).await
+        }
+    }
+}
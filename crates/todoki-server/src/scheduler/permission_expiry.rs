@@ -0,0 +1,102 @@
+//! Permission request expiry
+//!
+//! Relays wait for a human (or Telegram) to answer each permission request.
+//! Requests still pending after `permissions.expire_after_secs` are taken
+//! from the relay manager, recorded as `permission.expired` and answered
+//! with a cancelled outcome, so the agent sees the tool call refused instead
+//! of waiting forever.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use todoki_protocol::event_bus::{BuiltinEvent, PermissionOutcome};
+use uuid::Uuid;
+
+use crate::config::PermissionSettings;
+use crate::db::DatabaseService;
+use crate::event_bus::{EventPublisher, EventScope};
+use crate::permission;
+use crate::relay::RelayManager;
+
+/// How often pending requests are checked
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(15);
+
+pub struct PermissionExpiry {
+    expire_after: chrono::Duration,
+    db: Arc<DatabaseService>,
+    relays: Arc<RelayManager>,
+    publisher: Arc<EventPublisher>,
+}
+
+impl PermissionExpiry {
+    pub fn new(
+        settings: &PermissionSettings,
+        db: Arc<DatabaseService>,
+        relays: Arc<RelayManager>,
+        publisher: Arc<EventPublisher>,
+    ) -> Self {
+        Self {
+            expire_after: chrono::Duration::seconds(settings.expire_after_secs as i64),
+            db,
+            relays,
+            publisher,
+        }
+    }
+
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(EXPIRY_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let cutoff = Utc::now() - self.expire_after;
+            for (request_id, session_id) in self.relays.take_expired_permissions(cutoff).await {
+                self.expire(&request_id, &session_id).await;
+            }
+        }
+    }
+
+    async fn expire(&self, request_id: &str, session_id: &str) {
+        tracing::info!(request_id = %request_id, session_id = %session_id, "permission request expired");
+
+        let session_uuid = Uuid::parse_str(session_id).ok();
+        let correlation_id = match session_uuid {
+            Some(session_uuid) => match self.db.get_session_correlation(session_uuid).await {
+                Ok(correlation_id) => correlation_id,
+                Err(e) => {
+                    tracing::warn!(session_id = %session_id, error = %e, "failed to load session correlation");
+                    None
+                }
+            },
+            None => None,
+        };
+        let scope = EventScope::system()
+            .with_session(session_uuid)
+            .with_correlation(correlation_id);
+
+        let expired = BuiltinEvent::PermissionExpired {
+            request_id: request_id.to_string(),
+        };
+        if let Err(e) = self.publisher.emit_builtin(expired, scope).await {
+            tracing::error!(request_id = %request_id, error = %e, "failed to emit permission.expired");
+        }
+
+        // The session may have ended meanwhile; then nobody is waiting
+        let Some(relay_id) = self.relays.get_relay_for_session(session_id).await else {
+            return;
+        };
+        let sent = permission::send_response(
+            &self.db,
+            &self.publisher,
+            relay_id,
+            request_id,
+            session_id,
+            PermissionOutcome::cancelled(),
+        )
+        .await;
+        if let Err(e) = sent {
+            tracing::error!(request_id = %request_id, error = %e, "failed to cancel expired permission request");
+        }
+    }
+}
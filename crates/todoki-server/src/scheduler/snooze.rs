@@ -0,0 +1,101 @@
+//! Snooze wake-ups
+//!
+//! Snoozed tasks are hidden from the inbox until their wake time. This worker
+//! removes due snoozes, emits `task.unsnoozed` and, for snoozes created with
+//! `notify`, announces the task on the configured notifiers.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use todoki_protocol::event_bus::{BuiltinEvent, TaskUnsnoozedData};
+
+use crate::config::{SnoozeSettings, TelegramSettings};
+use crate::db::DatabaseService;
+use crate::digest::notifier::Notifier;
+use crate::event_bus::{EventPublisher, EventScope};
+use crate::models::TaskSnooze;
+
+/// How often due snoozes are checked
+const SNOOZE_INTERVAL: Duration = Duration::from_secs(30);
+
+pub struct SnoozeWaker {
+    db: Arc<DatabaseService>,
+    publisher: Arc<EventPublisher>,
+    notifiers: Vec<Notifier>,
+}
+
+impl SnoozeWaker {
+    pub fn new(
+        settings: &SnoozeSettings,
+        telegram: &TelegramSettings,
+        db: Arc<DatabaseService>,
+        publisher: Arc<EventPublisher>,
+    ) -> Self {
+        let notifiers = settings
+            .notifiers
+            .iter()
+            .filter_map(|config| Notifier::from_config(config, telegram))
+            .collect();
+
+        Self {
+            db,
+            publisher,
+            notifiers,
+        }
+    }
+
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(SNOOZE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let due = match self.db.take_due_snoozes(Utc::now()).await {
+                Ok(due) => due,
+                Err(e) => {
+                    tracing::error!(error = %e, "failed to take due snoozes");
+                    continue;
+                }
+            };
+
+            for snooze in due {
+                self.wake(&snooze).await;
+            }
+        }
+    }
+
+    async fn wake(&self, snooze: &TaskSnooze) {
+        let event = BuiltinEvent::TaskUnsnoozed(TaskUnsnoozedData {
+            reason: "timer".to_string(),
+        });
+        if let Err(e) = self.publisher.emit_builtin(event, EventScope::task(snooze.task_id)).await {
+            tracing::warn!(task_id = %snooze.task_id, error = %e, "failed to emit task.unsnoozed");
+        }
+
+        if !snooze.notify || self.notifiers.is_empty() {
+            return;
+        }
+
+        let content = match self.db.get_task_by_id(snooze.task_id).await {
+            Ok(Some(task)) => task.content,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!(task_id = %snooze.task_id, error = %e, "failed to load snoozed task");
+                return;
+            }
+        };
+        let markdown = format!("⏰ Back in your inbox: {}", content);
+
+        for notifier in &self.notifiers {
+            if let Err(e) = notifier.send("Task unsnoozed", &markdown).await {
+                tracing::warn!(
+                    notifier = notifier.name(),
+                    task_id = %snooze.task_id,
+                    error = %e,
+                    "failed to send snooze notification"
+                );
+            }
+        }
+    }
+}
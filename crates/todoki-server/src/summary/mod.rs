@@ -0,0 +1,250 @@
+//! LLM-generated session summaries
+//!
+//! After each `relay.prompt_completed`, the session's recent timeline (agent
+//! output, artifacts, permission requests) is sent to the configured model,
+//! and the resulting "what the agent did / files touched / open questions"
+//! summary is attached to the agent's task as a comment and a
+//! `session_summary` artifact.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use todoki_protocol::event_bus::{EventKind, RelayPromptCompletedData};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::config::SessionSummarySettings;
+use crate::db::DatabaseService;
+use crate::event_bus::{Event, EventPublisher, EventStore};
+use crate::llm::LlmClient;
+
+/// Wait for the relay's final output batch to land before reading the timeline
+const SETTLE_DELAY: Duration = Duration::from_secs(2);
+const TIMELINE_EVENT_LIMIT: usize = 500;
+
+const SYSTEM_PROMPT: &str = "You summarize coding agent sessions for the task's owner. \
+Reply in Markdown with exactly three sections:\n\
+### What the agent did\n\
+### Files touched\n\
+### Open questions\n\
+Be concise: short bullet points, no preamble. Write \"None\" for an empty section.";
+
+pub struct SessionSummarizer {
+    settings: SessionSummarySettings,
+    llm: LlmClient,
+    db: Arc<DatabaseService>,
+    store: Arc<dyn EventStore>,
+}
+
+impl SessionSummarizer {
+    pub fn new(
+        settings: SessionSummarySettings,
+        llm: LlmClient,
+        db: Arc<DatabaseService>,
+        store: Arc<dyn EventStore>,
+    ) -> Self {
+        let llm = if settings.model.is_empty() {
+            llm
+        } else {
+            llm.with_model(settings.model.clone())
+        };
+        Self {
+            settings,
+            llm,
+            db,
+            store,
+        }
+    }
+
+    /// Summarize every completed prompt until the event bus closes
+    pub async fn run(self: Arc<Self>, publisher: Arc<EventPublisher>) {
+        let mut rx = publisher.subscribe();
+
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!(lagged_events = n, "session summarizer lagged");
+                    continue;
+                }
+                Err(_) => break,
+            };
+
+            if event.kind != EventKind::RELAY_PROMPT_COMPLETED {
+                continue;
+            }
+            let data: RelayPromptCompletedData = match serde_json::from_value(event.data) {
+                Ok(data) => data,
+                Err(e) => {
+                    tracing::warn!(error = %e, cursor = event.cursor, "invalid relay.prompt_completed data");
+                    continue;
+                }
+            };
+
+            let summarizer = self.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(SETTLE_DELAY).await;
+                if let Err(e) = summarizer.summarize(&data).await {
+                    tracing::warn!(session_id = %data.session_id, error = %e, "failed to summarize session");
+                }
+            });
+        }
+    }
+
+    async fn summarize(&self, data: &RelayPromptCompletedData) -> anyhow::Result<()> {
+        let session_uuid = Uuid::parse_str(&data.session_id)?;
+        let Some(session) = self.db.get_agent_session(session_uuid).await? else {
+            return Ok(());
+        };
+        let Some(task) = self.db.get_task_by_agent_id(session.agent_id).await? else {
+            tracing::debug!(session_id = %data.session_id, "session has no task, skipping summary");
+            return Ok(());
+        };
+
+        let events = self
+            .store
+            .session_events(session.agent_id, &data.session_id, TIMELINE_EVENT_LIMIT)
+            .await?;
+        let timeline = render_timeline(&events, self.settings.max_timeline_chars);
+        if timeline.is_empty() {
+            return Ok(());
+        }
+
+        let outcome = match (data.success, &data.error) {
+            (true, _) => "completed successfully".to_string(),
+            (false, Some(error)) => format!("failed: {}", error),
+            (false, None) => "failed".to_string(),
+        };
+        let prompt = format!(
+            "Task:\n{}\n\nThe prompt {}.\n\nSession timeline:\n{}",
+            task.content, outcome, timeline
+        );
+        let summary = self.llm.complete(SYSTEM_PROMPT, &prompt).await?;
+
+        self.db
+            .add_task_comment(task.id, format!("Session summary\n\n{}", summary))
+            .await?;
+        self.db
+            .create_artifact(
+                task.id,
+                task.project_id,
+                Some(session.agent_id),
+                Some(session_uuid),
+                "session_summary",
+                serde_json::json!({
+                    "session_id": data.session_id,
+                    "success": data.success,
+                    "model": self.llm.model(),
+                    "summary": summary,
+                }),
+            )
+            .await?;
+
+        tracing::info!(task_id = %task.id, session_id = %data.session_id, "session summary stored");
+        Ok(())
+    }
+}
+
+/// Flatten session events into plain text, keeping the most recent
+/// `max_chars` characters
+fn render_timeline(events: &[Event], max_chars: usize) -> String {
+    let mut lines = Vec::new();
+    for event in events {
+        let data = &event.data;
+        match event.kind.as_str() {
+            EventKind::AGENT_OUTPUT_BATCH => {
+                if let Some(messages) = data.get("messages").and_then(|v| v.as_array()) {
+                    lines.extend(
+                        messages
+                            .iter()
+                            .filter_map(|m| m.as_str())
+                            .map(str::to_string),
+                    );
+                }
+            }
+            EventKind::ARTIFACT_CREATED => {
+                let artifact_type = data
+                    .get("artifact_type")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("");
+                let detail = data.get("data").map(|v| v.to_string()).unwrap_or_default();
+                lines.push(format!("[artifact {}] {}", artifact_type, detail));
+            }
+            EventKind::PERMISSION_REQUESTED => {
+                let title = data
+                    .get("tool_call")
+                    .and_then(|t| t.get("title"))
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("");
+                lines.push(format!("[permission requested] {}", title));
+            }
+            _ => {}
+        }
+    }
+
+    let timeline = lines.join("\n");
+    let total = timeline.chars().count();
+    if total <= max_chars {
+        timeline
+    } else {
+        let tail: String = timeline.chars().skip(total - max_chars).collect();
+        format!("[earlier output truncated]\n{}", tail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn event(kind: &str, data: serde_json::Value) -> Event {
+        Event {
+            cursor: 0,
+            kind: kind.to_string(),
+            time: Utc::now(),
+            agent_id: Uuid::nil(),
+            session_id: None,
+            task_id: None,
+            correlation_id: None,
+            request_id: None,
+            data,
+            schema_version: todoki_protocol::EVENT_SCHEMA_VERSION,
+        }
+    }
+
+    #[test]
+    fn test_render_timeline() {
+        let events = vec![
+            event(
+                EventKind::AGENT_OUTPUT_BATCH,
+                serde_json::json!({ "session_id": "s", "stream": "stdout", "messages": ["edit src/main.rs", "done"], "ts": 0 }),
+            ),
+            event(
+                EventKind::PERMISSION_REQUESTED,
+                serde_json::json!({ "session_id": "s", "tool_call": { "title": "cargo test" } }),
+            ),
+            event(
+                "relay.prompt_completed",
+                serde_json::json!({ "session_id": "s" }),
+            ),
+        ];
+
+        assert_eq!(
+            render_timeline(&events, 1000),
+            "edit src/main.rs\ndone\n[permission requested] cargo test"
+        );
+    }
+
+    #[test]
+    fn test_render_timeline_keeps_tail() {
+        let events = vec![event(
+            EventKind::AGENT_OUTPUT_BATCH,
+            serde_json::json!({ "messages": ["0123456789"] }),
+        )];
+
+        assert_eq!(
+            render_timeline(&events, 4),
+            "[earlier output truncated]\n6789"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! Minimal Telegram Bot API client (only the methods the bot uses)
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const API_BASE: &str = "https://api.telegram.org";
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Update {
+    pub update_id: i64,
+    pub message: Option<Message>,
+    pub callback_query: Option<CallbackQuery>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Message {
+    pub message_id: i64,
+    pub chat: Chat,
+    pub from: Option<User>,
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Chat {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct User {
+    pub id: i64,
+    pub username: Option<String>,
+    pub first_name: String,
+}
+
+impl User {
+    pub fn display_name(&self) -> String {
+        match &self.username {
+            Some(username) => format!("@{}", username),
+            None => self.first_name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CallbackQuery {
+    pub id: String,
+    pub from: User,
+    pub message: Option<Message>,
+    pub data: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InlineKeyboardButton {
+    pub text: String,
+    pub callback_data: String,
+}
+
+#[derive(Clone)]
+pub struct BotApi {
+    http_client: reqwest::Client,
+    base_url: String,
+}
+
+impl BotApi {
+    pub fn new(token: &str) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            base_url: format!("{}/bot{}", API_BASE, token),
+        }
+    }
+
+    /// Long-poll for updates after `offset`
+    pub async fn get_updates(&self, offset: i64, timeout_secs: u64) -> anyhow::Result<Vec<Update>> {
+        self.call(
+            "getUpdates",
+            &serde_json::json!({
+                "offset": offset,
+                "timeout": timeout_secs,
+                "allowed_updates": ["message", "callback_query"],
+            }),
+        )
+        .await
+    }
+
+    /// Send a text message, optionally with one row of inline buttons per entry
+    pub async fn send_message(
+        &self,
+        chat_id: i64,
+        text: &str,
+        keyboard: Option<Vec<Vec<InlineKeyboardButton>>>,
+    ) -> anyhow::Result<Message> {
+        let mut body = serde_json::json!({
+            "chat_id": chat_id,
+            "text": text,
+            "disable_web_page_preview": true,
+        });
+        if let Some(keyboard) = keyboard {
+            body["reply_markup"] = serde_json::json!({ "inline_keyboard": keyboard });
+        }
+        self.call("sendMessage", &body).await
+    }
+
+    /// Replace a message's text and drop its inline keyboard
+    pub async fn edit_message_text(
+        &self,
+        chat_id: i64,
+        message_id: i64,
+        text: &str,
+    ) -> anyhow::Result<()> {
+        let _: Value = self
+            .call(
+                "editMessageText",
+                &serde_json::json!({
+                    "chat_id": chat_id,
+                    "message_id": message_id,
+                    "text": text,
+                }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn answer_callback_query(
+        &self,
+        callback_query_id: &str,
+        text: &str,
+    ) -> anyhow::Result<()> {
+        let _: Value = self
+            .call(
+                "answerCallbackQuery",
+                &serde_json::json!({
+                    "callback_query_id": callback_query_id,
+                    "text": text,
+                }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, body: &Value) -> anyhow::Result<T> {
+        let resp: ApiResponse<T> = self
+            .http_client
+            .post(format!("{}/{}", self.base_url, method))
+            .json(body)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        match (resp.ok, resp.result) {
+            (true, Some(result)) => Ok(result),
+            _ => anyhow::bail!(
+                "telegram {} failed: {}",
+                method,
+                resp.description
+                    .unwrap_or_else(|| "unknown error".to_string())
+            ),
+        }
+    }
+}
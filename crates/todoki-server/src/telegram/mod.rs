@@ -0,0 +1,537 @@
+//! Telegram bot: task capture, today's work, and inline permission responses
+//!
+//! The bot long-polls the Bot API for chat messages and button presses, and
+//! watches the event bus for `permission.requested` so every authorized chat
+//! gets approve/deny buttons. Button presses become `permission.responded`
+//! events, routed to the owning relay exactly like responses from the web UI.
+
+pub mod api;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use todoki_protocol::event_bus::{
+    BuiltinEvent, EventKind, PermissionOutcome, PermissionRequestedData, ReviewDecision,
+    TaskCreatedData,
+};
+use tokio::sync::{Mutex, broadcast};
+use uuid::Uuid;
+
+use crate::config::TelegramSettings;
+use crate::db::DatabaseService;
+use crate::event_bus::{EventPublisher, EventScope};
+use crate::models::{CreateTask, Task, TaskStatus};
+use crate::permission::{self, PermissionAnswer};
+use crate::relay::RelayManager;
+use api::{BotApi, CallbackQuery, InlineKeyboardButton, Message, User};
+
+const POLL_ERROR_DELAY: Duration = Duration::from_secs(5);
+const CALLBACK_PREFIX: &str = "perm";
+const LIST_LIMIT: usize = 30;
+const LINE_MAX_CHARS: usize = 80;
+/// Command preview output shown in a permission prompt
+const PREVIEW_MAX_CHARS: usize = 1000;
+
+const HELP_TEXT: &str = "todoki bot\n\n\
+/new <text> - create a task (plain messages work too)\n\
+/today - list today's work\n\
+/help - show this message\n\n\
+Permission requests from agents show up here with buttons to answer them.";
+
+/// A chat command parsed from message text
+#[derive(Debug, PartialEq)]
+enum Command {
+    Help,
+    NewTask(String),
+    Today,
+    Unknown(String),
+}
+
+fn parse_command(text: &str) -> Command {
+    let text = text.trim();
+    if !text.starts_with('/') {
+        return Command::NewTask(text.to_string());
+    }
+
+    let (head, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    // Commands may be addressed as /cmd@botname in group chats
+    let name = head.split('@').next().unwrap_or(head);
+    match name {
+        "/start" | "/help" => Command::Help,
+        "/today" => Command::Today,
+        "/new" | "/task" if !rest.trim().is_empty() => Command::NewTask(rest.trim().to_string()),
+        other => Command::Unknown(other.to_string()),
+    }
+}
+
+fn callback_data(request_id: &str, option_index: usize) -> String {
+    format!("{}:{}:{}", CALLBACK_PREFIX, request_id, option_index)
+}
+
+fn parse_callback_data(data: &str) -> Option<(&str, usize)> {
+    let rest = data.strip_prefix(CALLBACK_PREFIX)?.strip_prefix(':')?;
+    let (request_id, index) = rest.rsplit_once(':')?;
+    Some((request_id, index.parse().ok()?))
+}
+
+/// First line of `text`, truncated for chat listings
+fn one_line(text: &str) -> String {
+    let line = text.lines().next().unwrap_or("").trim();
+    if line.chars().count() > LINE_MAX_CHARS {
+        format!("{}…", line.chars().take(LINE_MAX_CHARS).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+/// A permission request shown in one or more chats
+struct PermissionPrompt {
+    session_id: String,
+    text: String,
+    /// (option_id, display name) in button order
+    options: Vec<(String, String)>,
+    /// (chat_id, message_id) of every message carrying the buttons
+    messages: Vec<(i64, i64)>,
+}
+
+pub struct TelegramBot {
+    api: BotApi,
+    settings: TelegramSettings,
+    db: Arc<DatabaseService>,
+    relays: Arc<RelayManager>,
+    publisher: Arc<EventPublisher>,
+    /// Open permission prompts keyed by request_id
+    prompts: Mutex<HashMap<String, PermissionPrompt>>,
+}
+
+impl TelegramBot {
+    pub fn new(
+        settings: TelegramSettings,
+        db: Arc<DatabaseService>,
+        relays: Arc<RelayManager>,
+        publisher: Arc<EventPublisher>,
+    ) -> Self {
+        Self {
+            api: BotApi::new(&settings.bot_token),
+            settings,
+            db,
+            relays,
+            publisher,
+            prompts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Run the bot until the event bus closes
+    pub async fn run(self: Arc<Self>) {
+        tokio::spawn(self.clone().poll_updates());
+        self.watch_permissions().await;
+    }
+
+    fn is_allowed(&self, chat_id: i64) -> bool {
+        self.settings.allowed_chat_ids.contains(&chat_id)
+    }
+
+    // ========================================================================
+    // Chat updates
+    // ========================================================================
+
+    async fn poll_updates(self: Arc<Self>) {
+        let mut offset = 0;
+        loop {
+            let updates = match self
+                .api
+                .get_updates(offset, self.settings.poll_timeout_secs)
+                .await
+            {
+                Ok(updates) => updates,
+                Err(e) => {
+                    tracing::warn!(error = %e, "telegram getUpdates failed");
+                    tokio::time::sleep(POLL_ERROR_DELAY).await;
+                    continue;
+                }
+            };
+
+            for update in updates {
+                offset = offset.max(update.update_id + 1);
+                if let Some(message) = update.message {
+                    self.handle_message(message).await;
+                } else if let Some(callback) = update.callback_query {
+                    self.handle_callback(callback).await;
+                }
+            }
+        }
+    }
+
+    async fn handle_message(&self, message: Message) {
+        let chat_id = message.chat.id;
+        if !self.is_allowed(chat_id) {
+            tracing::warn!(
+                chat_id = chat_id,
+                "ignoring telegram message from unauthorized chat"
+            );
+            return;
+        }
+        let Some(text) = message.text.as_deref() else {
+            return;
+        };
+
+        let reply = match parse_command(text) {
+            Command::Help => HELP_TEXT.to_string(),
+            Command::Today => match self.today_summary().await {
+                Ok(summary) => summary,
+                Err(e) => format!("Failed to load tasks: {}", e),
+            },
+            Command::NewTask(content) if content.is_empty() => HELP_TEXT.to_string(),
+            Command::NewTask(content) => {
+                let sender = message
+                    .from
+                    .as_ref()
+                    .map(|u| u.display_name())
+                    .unwrap_or_else(|| chat_id.to_string());
+                match self.create_task(content, &sender).await {
+                    Ok(task) => format!(
+                        "Created task {}: {}",
+                        &task.id.to_string()[..8],
+                        one_line(&task.content)
+                    ),
+                    Err(e) => format!("Failed to create task: {}", e),
+                }
+            }
+            Command::Unknown(name) => format!("Unknown command {}\n\n{}", name, HELP_TEXT),
+        };
+
+        if let Err(e) = self.api.send_message(chat_id, &reply, None).await {
+            tracing::warn!(chat_id = chat_id, error = %e, "failed to send telegram reply");
+        }
+    }
+
+    async fn create_task(&self, content: String, sender: &str) -> crate::Result<Task> {
+        let project = self
+            .db
+            .get_or_create_project(&self.settings.project, "Tasks created from Telegram")
+            .await?;
+        let task = self
+            .db
+            .create_task(CreateTask::new(content, TaskStatus::Todo, 0, project.id))
+            .await?;
+
+        let event = BuiltinEvent::TaskCreated(TaskCreatedData {
+            title: one_line(&task.content),
+            description: Some(task.content.clone()),
+            parent_task_id: None,
+            source: Some("telegram".to_string()),
+            sender: Some(sender.to_string()),
+        });
+        if let Err(e) = self.publisher.emit_builtin(event, EventScope::task(task.id)).await {
+            tracing::warn!(task_id = %task.id, error = %e, "failed to emit task.created for telegram task");
+        }
+
+        Ok(task)
+    }
+
+    async fn today_summary(&self) -> crate::Result<String> {
+        let open = self.db.get_inbox_tasks().await?;
+        let done = self.db.get_today_done_tasks().await?;
+
+        let mut lines = vec![format!("📋 Today ({} open)", open.len())];
+        for task in open.iter().take(LIST_LIMIT) {
+            let status = serde_json::to_value(task.status)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default();
+            lines.push(format!("• [{}] {}", status, one_line(&task.content)));
+        }
+        if open.len() > LIST_LIMIT {
+            lines.push(format!("… and {} more", open.len() - LIST_LIMIT));
+        }
+        if !done.is_empty() {
+            lines.push(String::new());
+            lines.push(format!("✅ Done today ({})", done.len()));
+            for task in done.iter().take(LIST_LIMIT) {
+                lines.push(format!("• {}", one_line(&task.content)));
+            }
+        }
+        Ok(lines.join("\n"))
+    }
+
+    // ========================================================================
+    // Permission requests
+    // ========================================================================
+
+    async fn watch_permissions(&self) {
+        let mut rx = self.publisher.subscribe();
+        loop {
+            match rx.recv().await {
+                Ok(event) if event.kind == EventKind::PERMISSION_REQUESTED => {
+                    match serde_json::from_value::<PermissionRequestedData>(event.data.clone()) {
+                        Ok(data) => self.announce_permission(data, event.task_id).await,
+                        Err(e) => tracing::warn!(error = %e, "invalid permission.requested data"),
+                    }
+                }
+                Ok(event) if event.kind == EventKind::PERMISSION_RESPONDED => {
+                    // Answered elsewhere (web UI, another chat): retire our buttons
+                    if let Some(request_id) = event.data.get("request_id").and_then(|v| v.as_str())
+                    {
+                        self.close_prompt(request_id, "✔️ Answered").await;
+                    }
+                }
+                Ok(event) if event.kind == EventKind::PERMISSION_EXPIRED => {
+                    if let Some(request_id) = event.data.get("request_id").and_then(|v| v.as_str())
+                    {
+                        self.close_prompt(request_id, "⌛ Expired").await;
+                    }
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!(
+                        lagged_events = n,
+                        "telegram bot lagged, some permission requests may be missed"
+                    );
+                }
+                Err(_) => {
+                    tracing::error!("telegram bot event channel closed");
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn announce_permission(&self, data: PermissionRequestedData, task_id: Option<Uuid>) {
+        // Requests the review allowed or denied are answered already
+        if data
+            .review
+            .as_ref()
+            .is_some_and(|review| review.decision != ReviewDecision::Manual)
+        {
+            return;
+        }
+        let mut text = format!("🔐 Permission requested\n{}", data.tool_call.title);
+        if let Some(command) = data
+            .tool_call
+            .raw_input
+            .get("command")
+            .and_then(|v| v.as_str())
+        {
+            text.push_str(&format!("\n\n$ {}", command));
+        }
+        if let Some(preview) = &data.preview {
+            let full = preview.output.trim();
+            let output: String = full.chars().take(PREVIEW_MAX_CHARS).collect();
+            let cut = preview.truncated || full.chars().count() > PREVIEW_MAX_CHARS;
+            text.push_str(&format!(
+                "\n\n🔎 {}\n{}{}",
+                preview.command,
+                output,
+                if cut { "\n…" } else { "" }
+            ));
+        }
+        if let Some(review) = &data.review {
+            text.push_str(&format!(
+                "\n\n🤖 Risk: {:?}\n{}",
+                review.risk_level, review.reason
+            ));
+        }
+        if let Some(task_id) = task_id {
+            if let Ok(Some(task)) = self.db.get_task_by_id(task_id).await {
+                text.push_str(&format!("\n\nTask: {}", one_line(&task.content)));
+            }
+        }
+
+        let keyboard: Vec<Vec<InlineKeyboardButton>> = data
+            .options
+            .iter()
+            .enumerate()
+            .map(|(i, option)| {
+                vec![InlineKeyboardButton {
+                    text: option.name.clone(),
+                    callback_data: callback_data(&data.request_id, i),
+                }]
+            })
+            .collect();
+
+        let mut messages = Vec::new();
+        for &chat_id in &self.settings.allowed_chat_ids {
+            match self
+                .api
+                .send_message(chat_id, &text, Some(keyboard.clone()))
+                .await
+            {
+                Ok(message) => messages.push((chat_id, message.message_id)),
+                Err(e) => {
+                    tracing::warn!(chat_id = chat_id, error = %e, "failed to send permission prompt")
+                }
+            }
+        }
+
+        self.prompts.lock().await.insert(
+            data.request_id.clone(),
+            PermissionPrompt {
+                session_id: data.session_id,
+                text,
+                options: data
+                    .options
+                    .into_iter()
+                    .map(|o| (o.option_id, o.name))
+                    .collect(),
+                messages,
+            },
+        );
+    }
+
+    async fn handle_callback(&self, callback: CallbackQuery) {
+        let chat_id = callback
+            .message
+            .as_ref()
+            .map(|m| m.chat.id)
+            .unwrap_or_default();
+        if !self.is_allowed(chat_id) {
+            tracing::warn!(
+                chat_id = chat_id,
+                "ignoring telegram callback from unauthorized chat"
+            );
+            return;
+        }
+
+        let reply = match callback.data.as_deref().and_then(parse_callback_data) {
+            Some((request_id, index)) => {
+                self.respond_permission(request_id, index, &callback.from)
+                    .await
+            }
+            None => "Unknown action".to_string(),
+        };
+
+        if let Err(e) = self.api.answer_callback_query(&callback.id, &reply).await {
+            tracing::warn!(error = %e, "failed to answer telegram callback");
+        }
+    }
+
+    /// Answer a permission request with the chosen option; returns the
+    /// toast text. Two-person requests are forwarded on the second approval.
+    async fn respond_permission(&self, request_id: &str, index: usize, from: &User) -> String {
+        let Some(prompt) = self.prompts.lock().await.remove(request_id) else {
+            return "This request was already answered".to_string();
+        };
+        let Some((option_id, option_name)) = prompt.options.get(index).cloned() else {
+            return "Unknown option".to_string();
+        };
+        let responder = from.display_name();
+
+        let Some((relay_id, _)) = self.relays.get_pending_permission(request_id).await else {
+            self.edit_prompt(&prompt, "⚠️ Agent session is no longer connected")
+                .await;
+            return "Agent session is no longer connected".to_string();
+        };
+
+        let answered_by = format!("telegram:{}", from.id);
+        let outcome = PermissionOutcome::selected(option_id);
+        let answer = match self
+            .relays
+            .answer_permission(request_id, &answered_by, outcome)
+            .await
+        {
+            Some(Ok(answer)) => answer,
+            Some(Err(e)) => {
+                // Keep the buttons for the other approver
+                self.prompts
+                    .lock()
+                    .await
+                    .insert(request_id.to_string(), prompt);
+                return e;
+            }
+            None => {
+                self.edit_prompt(&prompt, "⚠️ Request is no longer pending")
+                    .await;
+                return "Request is no longer pending".to_string();
+            }
+        };
+        let outcome = match answer {
+            PermissionAnswer::Decided(outcome) => outcome,
+            PermissionAnswer::Waiting {
+                approvals,
+                required,
+            } => {
+                tracing::info!(request_id = %request_id, responder = %responder, approvals = approvals, required = required, "permission approved via telegram, waiting for another approval");
+                // Leave the message alone: editing it would drop the buttons
+                self.prompts
+                    .lock()
+                    .await
+                    .insert(request_id.to_string(), prompt);
+                return "Approved, waiting for a second approver".to_string();
+            }
+        };
+
+        if let Err(e) = permission::send_response(
+            &self.db,
+            &self.publisher,
+            relay_id,
+            request_id,
+            &prompt.session_id,
+            outcome,
+        )
+        .await
+        {
+            tracing::error!(request_id = %request_id, error = %e, "failed to emit permission response");
+            // Keep the buttons usable so the user can retry
+            self.prompts
+                .lock()
+                .await
+                .insert(request_id.to_string(), prompt);
+            return "Failed to send response, try again".to_string();
+        }
+        self.relays.remove_pending_permission(request_id).await;
+
+        tracing::info!(request_id = %request_id, responder = %responder, option = %option_name, "permission answered via telegram");
+        self.edit_prompt(&prompt, &format!("→ {} by {}", option_name, responder))
+            .await;
+        format!("Sent: {}", option_name)
+    }
+
+    async fn close_prompt(&self, request_id: &str, note: &str) {
+        let prompt = self.prompts.lock().await.remove(request_id);
+        if let Some(prompt) = prompt {
+            self.edit_prompt(&prompt, note).await;
+        }
+    }
+
+    async fn edit_prompt(&self, prompt: &PermissionPrompt, note: &str) {
+        let text = format!("{}\n\n{}", prompt.text, note);
+        for &(chat_id, message_id) in &prompt.messages {
+            if let Err(e) = self.api.edit_message_text(chat_id, message_id, &text).await {
+                tracing::debug!(chat_id = chat_id, error = %e, "failed to edit permission prompt");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("/help"), Command::Help);
+        assert_eq!(parse_command("/today@todoki_bot"), Command::Today);
+        assert_eq!(
+            parse_command("/new  Buy milk "),
+            Command::NewTask("Buy milk".to_string())
+        );
+        assert_eq!(
+            parse_command("Call the plumber"),
+            Command::NewTask("Call the plumber".to_string())
+        );
+        assert_eq!(parse_command("/new"), Command::Unknown("/new".to_string()));
+    }
+
+    #[test]
+    fn test_callback_data_roundtrip() {
+        let request_id = "2b1c6a52-0d4e-4b8f-9a51-7f3e2d1c0b9a";
+        let data = callback_data(request_id, 1);
+        assert!(
+            data.len() <= 64,
+            "telegram limits callback_data to 64 bytes"
+        );
+        assert_eq!(parse_callback_data(&data), Some((request_id, 1)));
+        assert_eq!(parse_callback_data("other:x:1"), None);
+    }
+}
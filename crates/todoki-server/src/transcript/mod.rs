@@ -0,0 +1,617 @@
+//! Shareable session transcripts
+//!
+//! Turns the events of an agent session into a document: the agent's
+//! messages, optionally its thoughts, plans, tool calls with their input and
+//! output folded away, permission requests and artifacts. Documents are
+//! rendered as Markdown or as a standalone HTML page.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use gotcha::Schematic;
+use pulldown_cmark::{html, Event as MdEvent, Parser};
+use serde::Deserialize;
+use serde_json::Value;
+use todoki_protocol::event_bus::EventKind;
+
+use crate::event_bus::Event;
+
+/// Most events read for one transcript
+pub const TRANSCRIPT_EVENT_LIMIT: usize = 10_000;
+/// Longest tool input or output kept in a transcript (characters)
+const MAX_TOOL_OUTPUT_CHARS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Schematic)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptFormat {
+    #[default]
+    Markdown,
+    Html,
+}
+
+impl TranscriptFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TranscriptFormat::Markdown => "markdown",
+            TranscriptFormat::Html => "html",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            TranscriptFormat::Markdown => "text/markdown; charset=utf-8",
+            TranscriptFormat::Html => "text/html; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TranscriptFormat::Markdown => "md",
+            TranscriptFormat::Html => "html",
+        }
+    }
+}
+
+/// What a transcript is about
+#[derive(Debug, Clone)]
+pub struct TranscriptHeader {
+    pub agent_name: String,
+    pub session_id: String,
+    pub status: String,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    /// Content of the session's task
+    pub task: Option<String>,
+}
+
+/// One block of a transcript
+#[derive(Debug, Clone, PartialEq)]
+pub enum Entry {
+    Message(String),
+    Thought(String),
+    /// Input sent to the agent
+    User(String),
+    /// Plan entries as (status, content)
+    Plan(Vec<(String, String)>),
+    ToolCall {
+        id: String,
+        title: String,
+        status: String,
+        input: Option<String>,
+        output: Option<String>,
+    },
+    Permission {
+        title: String,
+        outcome: Option<String>,
+    },
+    Artifact {
+        artifact_type: String,
+        data: String,
+    },
+    /// Raw process output (stdout, stderr, terminal)
+    Output(String),
+}
+
+/// Transcript entries of a session's events, oldest first
+///
+/// Streamed text chunks are joined and tool call updates are folded into
+/// the call they update. Thoughts are left out unless `thoughts` is set.
+pub fn entries(events: &[Event], thoughts: bool) -> Vec<Entry> {
+    let mut out: Vec<Entry> = Vec::new();
+    let mut tool_calls: HashMap<String, usize> = HashMap::new();
+    let mut permissions: HashMap<String, usize> = HashMap::new();
+
+    for event in events {
+        let data = &event.data;
+        match event.kind.as_str() {
+            EventKind::AGENT_OUTPUT_BATCH => {
+                let stream = data.get("stream").and_then(Value::as_str).unwrap_or("");
+                let messages = data.get("messages").and_then(Value::as_array);
+                for message in messages.into_iter().flatten().filter_map(Value::as_str) {
+                    let parsed: Value = serde_json::from_str(message)
+                        .unwrap_or_else(|_| Value::String(message.to_string()));
+                    push_output(&mut out, &mut tool_calls, stream, &parsed, thoughts);
+                }
+            }
+            EventKind::PERMISSION_REQUESTED => {
+                let title = data
+                    .pointer("/tool_call/title")
+                    .and_then(Value::as_str)
+                    .unwrap_or("tool call")
+                    .to_string();
+                if let Some(request_id) = data.get("request_id").and_then(Value::as_str) {
+                    permissions.insert(request_id.to_string(), out.len());
+                }
+                out.push(Entry::Permission {
+                    title,
+                    outcome: None,
+                });
+            }
+            EventKind::PERMISSION_RESPONDED | EventKind::PERMISSION_EXPIRED => {
+                let answer = if event.kind == EventKind::PERMISSION_EXPIRED {
+                    "expired".to_string()
+                } else {
+                    data.pointer("/outcome/option_id")
+                        .and_then(Value::as_str)
+                        .map(|option| format!("answered: {}", option))
+                        .unwrap_or_else(|| "answered".to_string())
+                };
+                let index = data
+                    .get("request_id")
+                    .and_then(Value::as_str)
+                    .and_then(|id| permissions.get(id));
+                if let Some(Entry::Permission { outcome, .. }) =
+                    index.and_then(|i| out.get_mut(*i))
+                {
+                    *outcome = Some(answer);
+                }
+            }
+            EventKind::ARTIFACT_CREATED => out.push(Entry::Artifact {
+                artifact_type: data
+                    .get("artifact_type")
+                    .and_then(Value::as_str)
+                    .unwrap_or("artifact")
+                    .to_string(),
+                data: data
+                    .get("data")
+                    .map(|d| serde_json::to_string_pretty(d).unwrap_or_default())
+                    .unwrap_or_default(),
+            }),
+            _ => {}
+        }
+    }
+    out
+}
+
+fn push_output(
+    out: &mut Vec<Entry>,
+    tool_calls: &mut HashMap<String, usize>,
+    stream: &str,
+    message: &Value,
+    thoughts: bool,
+) {
+    let text = || match message {
+        Value::String(raw) => Some(raw.clone()),
+        other => other.get("text").and_then(Value::as_str).map(str::to_string),
+    };
+    match stream {
+        "assistant" => {
+            if let Some(text) = text() {
+                match out.last_mut() {
+                    Some(Entry::Message(last)) => last.push_str(&text),
+                    _ => out.push(Entry::Message(text)),
+                }
+            }
+        }
+        "thinking" if thoughts => {
+            if let Some(text) = text() {
+                match out.last_mut() {
+                    Some(Entry::Thought(last)) => last.push_str(&text),
+                    _ => out.push(Entry::Thought(text)),
+                }
+            }
+        }
+        "user" => {
+            if let Some(text) = text() {
+                out.push(Entry::User(text));
+            }
+        }
+        "plan" => {
+            let plan = message
+                .pointer("/plan/entries")
+                .and_then(Value::as_array)
+                .map(|entries| {
+                    entries
+                        .iter()
+                        .map(|e| (field(e, "status"), field(e, "content")))
+                        .collect()
+                });
+            if let Some(plan) = plan {
+                out.push(Entry::Plan(plan));
+            }
+        }
+        "tool_use" | "tool_result" => {
+            let id = field(message, "id");
+            let title = message.get("title").and_then(Value::as_str);
+            let status = message.get("status").and_then(Value::as_str);
+            let input = message.get("raw_input").filter(|v| !v.is_null()).map(pretty);
+            let output = tool_output(message);
+            if let Some(index) = tool_calls.get(&id)
+                && let Some(Entry::ToolCall {
+                    title: t,
+                    status: s,
+                    input: i,
+                    output: o,
+                    ..
+                }) = out.get_mut(*index)
+            {
+                if let Some(title) = title {
+                    *t = title.to_string();
+                }
+                if let Some(status) = status {
+                    *s = status.to_string();
+                }
+                *i = input.or(i.take());
+                *o = output.or(o.take());
+                return;
+            }
+            tool_calls.insert(id.clone(), out.len());
+            out.push(Entry::ToolCall {
+                id,
+                title: title.unwrap_or("tool call").to_string(),
+                status: status.unwrap_or("pending").to_string(),
+                input,
+                output,
+            });
+        }
+        "stdout" | "stderr" | "terminal" => {
+            if let Some(text) = text() {
+                match out.last_mut() {
+                    Some(Entry::Output(last)) => {
+                        last.push('\n');
+                        last.push_str(&text);
+                    }
+                    _ => out.push(Entry::Output(text)),
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn field(value: &Value, name: &str) -> String {
+    match value.get(name) {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn pretty(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => serde_json::to_string_pretty(other).unwrap_or_default(),
+    }
+}
+
+/// `raw_output`, or else the text blocks of `content`
+fn tool_output(message: &Value) -> Option<String> {
+    if let Some(raw) = message.get("raw_output").filter(|v| !v.is_null()) {
+        return Some(pretty(raw));
+    }
+    let texts: Vec<&str> = message
+        .get("content")
+        .and_then(Value::as_array)?
+        .iter()
+        .filter_map(|block| {
+            block
+                .pointer("/content/text")
+                .or_else(|| block.get("text"))
+                .and_then(Value::as_str)
+        })
+        .collect();
+    (!texts.is_empty()).then(|| texts.join("\n"))
+}
+
+fn clip(text: &str) -> String {
+    if text.chars().count() <= MAX_TOOL_OUTPUT_CHARS {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(MAX_TOOL_OUTPUT_CHARS).collect();
+    format!("{}\n[truncated]", kept)
+}
+
+/// Render a transcript in `format`
+pub fn render(format: TranscriptFormat, header: &TranscriptHeader, entries: &[Entry]) -> String {
+    match format {
+        TranscriptFormat::Markdown => render_markdown(header, entries),
+        TranscriptFormat::Html => render_html(header, entries),
+    }
+}
+
+fn header_lines(header: &TranscriptHeader) -> Vec<(&'static str, String)> {
+    let mut lines = vec![
+        ("Session", header.session_id.clone()),
+        ("Status", header.status.clone()),
+        ("Started", header.started_at.to_rfc3339()),
+    ];
+    if let Some(ended_at) = header.ended_at {
+        lines.push(("Ended", ended_at.to_rfc3339()));
+    }
+    lines
+}
+
+/// A code fence longer than any backtick run in `text`
+fn fence(text: &str) -> String {
+    let longest = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+fn code_block(lang: &str, text: &str) -> String {
+    let fence = fence(text);
+    format!("{}{}\n{}\n{}\n", fence, lang, text, fence)
+}
+
+fn render_markdown(header: &TranscriptHeader, entries: &[Entry]) -> String {
+    let mut out = format!("# Transcript: {}\n\n", header.agent_name);
+    for (name, value) in header_lines(header) {
+        out.push_str(&format!("- **{}:** {}\n", name, value));
+    }
+    if let Some(task) = &header.task {
+        out.push_str(&format!("\n## Task\n\n{}\n", task.trim()));
+    }
+    out.push_str("\n---\n");
+
+    for entry in entries {
+        out.push('\n');
+        match entry {
+            Entry::Message(text) => out.push_str(&format!("**Agent**\n\n{}\n", text.trim())),
+            Entry::Thought(text) => {
+                out.push_str("> _Thinking_\n>\n");
+                for line in text.trim().lines() {
+                    out.push_str(&format!("> {}\n", line));
+                }
+            }
+            Entry::User(text) => out.push_str(&format!("**User**\n\n{}\n", text.trim())),
+            Entry::Plan(items) => {
+                out.push_str("**Plan**\n\n");
+                for (status, content) in items {
+                    let mark = if status == "completed" { "x" } else { " " };
+                    out.push_str(&format!("- [{}] {}\n", mark, content));
+                }
+            }
+            Entry::ToolCall {
+                title,
+                status,
+                input,
+                output,
+                ..
+            } => {
+                out.push_str(&format!(
+                    "<details>\n<summary>Tool: {} ({})</summary>\n\n",
+                    title, status
+                ));
+                if let Some(input) = input {
+                    out.push_str(&format!("Input:\n\n{}\n", code_block("", &clip(input))));
+                }
+                if let Some(output) = output {
+                    out.push_str(&format!("Output:\n\n{}\n", code_block("", &clip(output))));
+                }
+                out.push_str("</details>\n");
+            }
+            Entry::Permission { title, outcome } => out.push_str(&format!(
+                "**Permission requested:** {} ({})\n",
+                title,
+                outcome.as_deref().unwrap_or("pending")
+            )),
+            Entry::Artifact {
+                artifact_type,
+                data,
+            } => out.push_str(&format!(
+                "**Artifact:** `{}`\n\n{}",
+                artifact_type,
+                code_block("json", data)
+            )),
+            Entry::Output(text) => out.push_str(&code_block("", &clip(text))),
+        }
+    }
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Markdown written by the agent as HTML, with any raw HTML in it escaped
+fn markdown_html(text: &str) -> String {
+    let parser = Parser::new(text).map(|event| match event {
+        MdEvent::Html(raw) | MdEvent::InlineHtml(raw) => MdEvent::Text(raw),
+        other => other,
+    });
+    let mut out = String::new();
+    html::push_html(&mut out, parser);
+    out
+}
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:860px;margin:2rem auto;\
+padding:0 1rem;color:#1e293b;line-height:1.5}pre{background:#f1f5f9;padding:.75rem;\
+overflow-x:auto;white-space:pre-wrap}details{border:1px solid #e2e8f0;border-radius:6px;\
+padding:.5rem .75rem}summary{cursor:pointer;font-weight:600}.thought{color:#64748b;\
+border-left:3px solid #cbd5e1;padding-left:.75rem}.entry{margin:1rem 0}";
+
+fn render_html(header: &TranscriptHeader, entries: &[Entry]) -> String {
+    let title = escape(&format!("Transcript: {}", header.agent_name));
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{0}</title>\n<style>{1}</style>\n</head>\n<body>\n<h1>{0}</h1>\n<ul>\n",
+        title, HTML_STYLE
+    );
+    for (name, value) in header_lines(header) {
+        out.push_str(&format!("<li><strong>{}:</strong> {}</li>\n", name, escape(&value)));
+    }
+    out.push_str("</ul>\n");
+    if let Some(task) = &header.task {
+        out.push_str(&format!("<h2>Task</h2>\n{}", markdown_html(task)));
+    }
+    out.push_str("<hr>\n");
+
+    for entry in entries {
+        out.push_str("<div class=\"entry\">\n");
+        match entry {
+            Entry::Message(text) => {
+                out.push_str(&format!("<strong>Agent</strong>\n{}", markdown_html(text)));
+            }
+            Entry::Thought(text) => out.push_str(&format!(
+                "<div class=\"thought\"><em>Thinking</em>\n{}</div>\n",
+                markdown_html(text)
+            )),
+            Entry::User(text) => {
+                out.push_str(&format!("<strong>User</strong>\n{}", markdown_html(text)));
+            }
+            Entry::Plan(items) => {
+                out.push_str("<strong>Plan</strong>\n<ul>\n");
+                for (status, content) in items {
+                    let checked = if status == "completed" { " checked" } else { "" };
+                    out.push_str(&format!(
+                        "<li><input type=\"checkbox\" disabled{}> {}</li>\n",
+                        checked,
+                        escape(content)
+                    ));
+                }
+                out.push_str("</ul>\n");
+            }
+            Entry::ToolCall {
+                title,
+                status,
+                input,
+                output,
+                ..
+            } => {
+                out.push_str(&format!(
+                    "<details>\n<summary>Tool: {} ({})</summary>\n",
+                    escape(title),
+                    escape(status)
+                ));
+                for (label, text) in [("Input", input), ("Output", output)] {
+                    if let Some(text) = text {
+                        out.push_str(&format!(
+                            "<p>{}:</p>\n<pre>{}</pre>\n",
+                            label,
+                            escape(&clip(text))
+                        ));
+                    }
+                }
+                out.push_str("</details>\n");
+            }
+            Entry::Permission { title, outcome } => out.push_str(&format!(
+                "<p><strong>Permission requested:</strong> {} ({})</p>\n",
+                escape(title),
+                escape(outcome.as_deref().unwrap_or("pending"))
+            )),
+            Entry::Artifact {
+                artifact_type,
+                data,
+            } => out.push_str(&format!(
+                "<p><strong>Artifact:</strong> <code>{}</code></p>\n<pre>{}</pre>\n",
+                escape(artifact_type),
+                escape(data)
+            )),
+            Entry::Output(text) => out.push_str(&format!("<pre>{}</pre>\n", escape(&clip(text)))),
+        }
+        out.push_str("</div>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn event(kind: &str, data: Value) -> Event {
+        Event::new(kind, Uuid::nil(), data)
+    }
+
+    fn batch(stream: &str, messages: &[Value]) -> Event {
+        let messages: Vec<String> = messages.iter().map(Value::to_string).collect();
+        event(
+            EventKind::AGENT_OUTPUT_BATCH,
+            json!({"session_id": "s", "stream": stream, "messages": messages, "ts": 0}),
+        )
+    }
+
+    fn header() -> TranscriptHeader {
+        TranscriptHeader {
+            agent_name: "coder".to_string(),
+            session_id: "s".to_string(),
+            status: "completed".to_string(),
+            started_at: Utc::now(),
+            ended_at: None,
+            task: Some("Fix the build".to_string()),
+        }
+    }
+
+    fn events() -> Vec<Event> {
+        vec![
+            batch("thinking", &[json!({"type": "agent_thought", "text": "hmm"})]),
+            batch(
+                "assistant",
+                &[
+                    json!({"type": "agent_message", "chunk": true, "text": "Running "}),
+                    json!({"type": "agent_message", "chunk": true, "text": "tests"}),
+                ],
+            ),
+            batch(
+                "tool_use",
+                &[json!({
+                    "type": "tool_call",
+                    "id": "t1",
+                    "title": "cargo test",
+                    "status": "pending",
+                    "raw_input": {"command": "cargo test"},
+                })],
+            ),
+            batch(
+                "tool_result",
+                &[json!({
+                    "type": "tool_call_update",
+                    "id": "t1",
+                    "status": "completed",
+                    "raw_output": "ok. 3 passed",
+                })],
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_entries() {
+        let entries = entries(&events(), false);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], Entry::Message("Running tests".to_string()));
+        assert_eq!(
+            entries[1],
+            Entry::ToolCall {
+                id: "t1".to_string(),
+                title: "cargo test".to_string(),
+                status: "completed".to_string(),
+                input: Some("{\n  \"command\": \"cargo test\"\n}".to_string()),
+                output: Some("ok. 3 passed".to_string()),
+            }
+        );
+
+        let with_thoughts = super::entries(&events(), true);
+        assert_eq!(with_thoughts[0], Entry::Thought("hmm".to_string()));
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        let doc = render(TranscriptFormat::Markdown, &header(), &entries(&events(), false));
+        assert!(doc.starts_with("# Transcript: coder\n"));
+        assert!(doc.contains("## Task\n\nFix the build\n"));
+        assert!(doc.contains("**Agent**\n\nRunning tests\n"));
+        assert!(doc.contains("<summary>Tool: cargo test (completed)</summary>"));
+        assert!(doc.contains("Output:\n\n```\nok. 3 passed\n```\n"));
+        assert_eq!(fence("a ```` b"), "`````");
+    }
+
+    #[test]
+    fn test_render_html_escapes_agent_output() {
+        let entries = vec![
+            Entry::Message("**Done** <script>alert(1)</script>".to_string()),
+            Entry::Output("<b>".to_string()),
+        ];
+        let doc = render(TranscriptFormat::Html, &header(), &entries);
+        assert!(doc.contains("<strong>Done</strong>"));
+        assert!(!doc.contains("<script>"));
+        assert!(doc.contains("<pre>&lt;b&gt;</pre>"));
+    }
+}
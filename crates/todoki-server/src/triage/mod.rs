@@ -0,0 +1,329 @@
+//! Natural-language task triage
+//!
+//! On `task.created`, the task text and the list of active projects are sent
+//! to the configured model, which suggests a project, a priority, which agent
+//! role should handle the task and an optional split into subtasks. The
+//! suggestion is posted as a task comment; with `auto_apply` it is also
+//! applied (project/priority updated, subtasks created in the backlog).
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use todoki_protocol::event_bus::{BuiltinEvent, EventKind, TaskCreatedData};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::config::TriageSettings;
+use crate::db::DatabaseService;
+use crate::event_bus::{EventPublisher, EventScope};
+use crate::llm::LlmClient;
+use crate::models::{AgentRole, CreateTask, Project, Task, TaskStatus};
+
+/// `task.created` events from triage itself carry this source and are skipped
+const TRIAGE_SOURCE: &str = "triage";
+const MAX_PRIORITY: i32 = 3;
+const MAX_SUBTASKS: usize = 8;
+
+const SYSTEM_PROMPT: &str = "You triage tasks for a personal task manager whose tasks \
+may be executed by AI agents. Given a task and the available projects, reply with a \
+single JSON object and nothing else:\n\
+{\"project\": <name of the best matching project or null>, \
+\"priority\": <0 (none) to 3 (urgent)>, \
+\"role\": <\"business\" | \"coding\" | \"qa\" | \"general\">, \
+\"subtasks\": [<short subtask descriptions, empty unless the task clearly needs splitting>], \
+\"reasoning\": <one sentence>}";
+
+/// A triage suggestion as returned by the model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TriageSuggestion {
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub priority: Option<i32>,
+    #[serde(default)]
+    pub role: Option<AgentRole>,
+    #[serde(default)]
+    pub subtasks: Vec<String>,
+    #[serde(default)]
+    pub reasoning: String,
+}
+
+impl TriageSuggestion {
+    /// Parse the model reply, tolerating a surrounding Markdown code fence
+    fn parse(reply: &str) -> serde_json::Result<Self> {
+        let trimmed = reply.trim();
+        let body = trimmed
+            .strip_prefix("```json")
+            .or_else(|| trimmed.strip_prefix("```"))
+            .and_then(|rest| rest.strip_suffix("```"))
+            .unwrap_or(trimmed);
+        serde_json::from_str(body.trim())
+    }
+
+    /// Drop unknown projects, clamp the priority and cap the subtask list
+    fn normalize(mut self, projects: &[Project]) -> Self {
+        self.project = self.project.and_then(|name| {
+            projects
+                .iter()
+                .find(|p| p.name.eq_ignore_ascii_case(name.trim()))
+                .map(|p| p.name.clone())
+        });
+        self.priority = self.priority.map(|p| p.clamp(0, MAX_PRIORITY));
+        self.subtasks = self
+            .subtasks
+            .into_iter()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .take(MAX_SUBTASKS)
+            .collect();
+        self
+    }
+
+    /// Comment body: readable summary plus the raw suggestion as JSON
+    fn to_comment(&self, applied: bool) -> String {
+        let mut out = String::from(if applied {
+            "Triage (applied)\n"
+        } else {
+            "Triage suggestion\n"
+        });
+        if let Some(project) = &self.project {
+            out.push_str(&format!("\n- Project: {}", project));
+        }
+        if let Some(priority) = self.priority {
+            out.push_str(&format!("\n- Priority: {}", priority));
+        }
+        if let Some(role) = self.role {
+            out.push_str(&format!(
+                "\n- Role: {}",
+                serde_json::to_value(role)
+                    .ok()
+                    .and_then(|v| v.as_str().map(str::to_string))
+                    .unwrap_or_default()
+            ));
+        }
+        if !self.subtasks.is_empty() {
+            out.push_str("\n- Subtasks:");
+            for subtask in &self.subtasks {
+                out.push_str(&format!("\n  - {}", subtask));
+            }
+        }
+        if !self.reasoning.is_empty() {
+            out.push_str(&format!("\n\n{}", self.reasoning));
+        }
+        out.push_str(&format!(
+            "\n\n```json\n{}\n```",
+            serde_json::to_string_pretty(self).unwrap_or_default()
+        ));
+        out
+    }
+}
+
+pub struct TriageService {
+    settings: TriageSettings,
+    llm: LlmClient,
+    db: Arc<DatabaseService>,
+    publisher: Arc<EventPublisher>,
+}
+
+impl TriageService {
+    pub fn new(
+        settings: TriageSettings,
+        llm: LlmClient,
+        db: Arc<DatabaseService>,
+        publisher: Arc<EventPublisher>,
+    ) -> Self {
+        let llm = if settings.model.is_empty() {
+            llm
+        } else {
+            llm.with_model(settings.model.clone())
+        };
+        Self {
+            settings,
+            llm,
+            db,
+            publisher,
+        }
+    }
+
+    /// Triage every newly created task until the event bus closes
+    pub async fn run(self: Arc<Self>) {
+        let mut rx = self.publisher.subscribe();
+
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!(lagged_events = n, "triage service lagged");
+                    continue;
+                }
+                Err(_) => break,
+            };
+
+            if event.kind != EventKind::TASK_CREATED {
+                continue;
+            }
+            if event.data.get("source").and_then(|v| v.as_str()) == Some(TRIAGE_SOURCE) {
+                continue;
+            }
+            let Some(task_id) = event.task_id else {
+                continue;
+            };
+
+            let service = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = service.triage(task_id).await {
+                    tracing::warn!(task_id = %task_id, error = %e, "failed to triage task");
+                }
+            });
+        }
+    }
+
+    async fn triage(&self, task_id: Uuid) -> anyhow::Result<()> {
+        let Some(task) = self.db.get_task_by_id(task_id).await? else {
+            return Ok(());
+        };
+        let projects = self.db.list_projects(false).await?;
+        let current_project = projects
+            .iter()
+            .find(|p| p.id == task.project_id)
+            .map(|p| p.name.as_str())
+            .unwrap_or("");
+
+        let project_list = projects
+            .iter()
+            .map(|p| match &p.description {
+                Some(desc) if !desc.is_empty() => format!("- {}: {}", p.name, desc),
+                _ => format!("- {}", p.name),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let prompt = format!(
+            "Task:\n{}\n\nCurrent project: {}\nCurrent priority: {}\n\nProjects:\n{}",
+            task.content, current_project, task.priority, project_list
+        );
+
+        let reply = self.llm.complete(SYSTEM_PROMPT, &prompt).await?;
+        let suggestion = TriageSuggestion::parse(&reply)?.normalize(&projects);
+
+        if self.settings.auto_apply {
+            self.apply(&task, &suggestion, &projects).await?;
+        }
+        self.db
+            .add_task_comment(task.id, suggestion.to_comment(self.settings.auto_apply))
+            .await?;
+
+        tracing::info!(task_id = %task.id, auto_apply = self.settings.auto_apply, "task triaged");
+        Ok(())
+    }
+
+    async fn apply(
+        &self,
+        task: &Task,
+        suggestion: &TriageSuggestion,
+        projects: &[Project],
+    ) -> anyhow::Result<()> {
+        let project_id = suggestion
+            .project
+            .as_ref()
+            .and_then(|name| projects.iter().find(|p| &p.name == name))
+            .map(|p| p.id)
+            .unwrap_or(task.project_id);
+        let priority = suggestion.priority.unwrap_or(task.priority);
+
+        if project_id != task.project_id || priority != task.priority {
+            self.db
+                .update_task(
+                    task.id,
+                    priority,
+                    task.content.clone(),
+                    project_id,
+                    task.due_at,
+                    task.estimate_minutes,
+                )
+                .await?;
+        }
+
+        for content in &suggestion.subtasks {
+            let subtask = self
+                .db
+                .create_task(CreateTask::new(
+                    content.clone(),
+                    TaskStatus::Backlog,
+                    priority,
+                    project_id,
+                ))
+                .await?;
+            let event = BuiltinEvent::TaskCreated(TaskCreatedData {
+                title: content.clone(),
+                description: None,
+                parent_task_id: Some(task.id.to_string()),
+                source: Some(TRIAGE_SOURCE.to_string()),
+                sender: None,
+            });
+            if let Err(e) = self.publisher.emit_builtin(event, EventScope::task(subtask.id)).await {
+                tracing::warn!(task_id = %subtask.id, error = %e, "failed to emit task.created for subtask");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn project(name: &str) -> Project {
+        Project {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            description: None,
+            color: String::new(),
+            archived: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            general_template: None,
+            business_template: None,
+            coding_template: None,
+            qa_template: None,
+            execution_schedule: None,
+            relay_routing: None,
+            review_config: None,
+            workflow: None,
+            issue_sync: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_fenced_reply() {
+        let reply = "```json\n{\"project\": \"web\", \"priority\": 2, \"role\": \"coding\", \"subtasks\": [], \"reasoning\": \"UI bug\"}\n```";
+        let suggestion = TriageSuggestion::parse(reply).unwrap();
+        assert_eq!(suggestion.project.as_deref(), Some("web"));
+        assert_eq!(suggestion.role, Some(AgentRole::Coding));
+    }
+
+    #[test]
+    fn test_normalize_suggestion() {
+        let projects = vec![project("Web"), project("Ops")];
+        let suggestion = TriageSuggestion {
+            project: Some("web".to_string()),
+            priority: Some(9),
+            role: None,
+            subtasks: vec!["  write tests ".to_string(), "".to_string()],
+            reasoning: String::new(),
+        }
+        .normalize(&projects);
+
+        assert_eq!(suggestion.project.as_deref(), Some("Web"));
+        assert_eq!(suggestion.priority, Some(3));
+        assert_eq!(suggestion.subtasks, vec!["write tests".to_string()]);
+
+        let unknown = TriageSuggestion {
+            project: Some("Marketing".to_string()),
+            ..suggestion
+        }
+        .normalize(&projects);
+        assert_eq!(unknown.project, None);
+    }
+}
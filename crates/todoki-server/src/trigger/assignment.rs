@@ -0,0 +1,246 @@
+//! Choosing one agent for an unassigned task
+//!
+//! With `assignment.enabled`, an event about a task that has no agent yet
+//! does not start every auto-trigger agent that wants it. The candidates
+//! are ranked instead, and only the best one that can start is triggered.
+//! Ranking looks at four factors:
+//!
+//! - how well the agent's role fits the task's stage
+//! - how often its sessions for the project's tasks completed
+//! - how many unfinished tasks it already holds
+//! - how busy the relay it would run on is
+//!
+//! Busy agents and agents without a relay slot are not picked. The winner
+//! becomes the task's agent, and `task.assigned` records the ranking.
+
+use todoki_protocol::event_bus::{AssignmentCandidate, AssignmentRationale};
+
+use crate::models::{Agent, AgentRole, AgentTrackRecord, TaskStatus};
+
+const ROLE_WEIGHT: f64 = 0.4;
+const SUCCESS_WEIGHT: f64 = 0.3;
+const OPEN_TASKS_WEIGHT: f64 = 0.2;
+const RELAY_WEIGHT: f64 = 0.1;
+
+/// Fit of a general-purpose agent for a stage that wants a specialist
+const GENERAL_ROLE_FIT: f64 = 0.5;
+
+/// Role whose agents work on a task in this status; `None` in the simple
+/// flow, where every role fits
+pub fn stage_role(status: TaskStatus) -> Option<AgentRole> {
+    match status {
+        TaskStatus::PlanPending
+        | TaskStatus::PlanInProgress
+        | TaskStatus::PlanReview
+        | TaskStatus::PlanDone => Some(AgentRole::Business),
+        TaskStatus::CodingPending
+        | TaskStatus::CodingInProgress
+        | TaskStatus::CodingReview
+        | TaskStatus::CodingDone => Some(AgentRole::Coding),
+        TaskStatus::CrossReviewPending
+        | TaskStatus::CrossReviewInProgress
+        | TaskStatus::CrossReviewPass
+        | TaskStatus::CrossReviewFail => Some(AgentRole::Qa),
+        _ => None,
+    }
+}
+
+/// An agent an event would trigger, with what ranking needs to know
+pub struct Candidate<'a> {
+    pub agent: &'a Agent,
+    pub record: AgentTrackRecord,
+    /// Relay with a free slot the agent would run on, and the share of its
+    /// slots in use
+    pub relay: Option<(String, f64)>,
+    /// Set when the agent cannot be picked
+    pub excluded: Option<String>,
+}
+
+fn role_fit(stage: Option<AgentRole>, role: AgentRole) -> f64 {
+    match stage {
+        None => 1.0,
+        Some(stage) if stage == role => 1.0,
+        Some(_) if role == AgentRole::General => GENERAL_ROLE_FIT,
+        Some(_) => 0.0,
+    }
+}
+
+/// Completed share of finished sessions, smoothed so an agent without
+/// history starts at one half
+fn success_rate(record: &AgentTrackRecord) -> f64 {
+    (record.completed_sessions as f64 + 1.0) / (record.finished_sessions as f64 + 2.0)
+}
+
+/// Score every candidate; pickable ones come first, best first
+pub fn rank(stage: Option<AgentRole>, candidates: &[Candidate<'_>]) -> Vec<AssignmentCandidate> {
+    let mut ranked: Vec<AssignmentCandidate> = candidates
+        .iter()
+        .map(|candidate| {
+            let role_fit = role_fit(stage, candidate.agent.role);
+            let success_rate = success_rate(&candidate.record);
+            let relay_load = candidate.relay.as_ref().map_or(1.0, |(_, load)| *load);
+            let score = ROLE_WEIGHT * role_fit
+                + SUCCESS_WEIGHT * success_rate
+                + OPEN_TASKS_WEIGHT / (1.0 + candidate.record.open_tasks as f64)
+                + RELAY_WEIGHT * (1.0 - relay_load);
+            let excluded = candidate.excluded.clone().or_else(|| {
+                candidate
+                    .relay
+                    .is_none()
+                    .then(|| "no relay with a free session slot".to_string())
+            });
+            AssignmentCandidate {
+                agent_id: candidate.agent.id.to_string(),
+                agent_name: candidate.agent.name.clone(),
+                role: candidate.agent.role.as_str().to_string(),
+                score,
+                role_fit,
+                success_rate,
+                open_tasks: candidate.record.open_tasks,
+                relay_id: candidate.relay.as_ref().map(|(id, _)| id.clone()),
+                relay_load,
+                excluded,
+            }
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| {
+        a.excluded
+            .is_some()
+            .cmp(&b.excluded.is_some())
+            .then(b.score.total_cmp(&a.score))
+            .then(a.open_tasks.cmp(&b.open_tasks))
+            .then(a.agent_id.cmp(&b.agent_id))
+    });
+    ranked
+}
+
+/// The rationale recorded when `picked` got the task
+pub fn rationale(
+    picked: &AssignmentCandidate,
+    ranked: Vec<AssignmentCandidate>,
+) -> AssignmentRationale {
+    AssignmentRationale {
+        summary: format!(
+            "{} ({}) scored {:.2}, best of {} candidate(s): role fit {:.1}, success rate {:.2}, \
+             {} open task(s), relay {:.0}% busy",
+            picked.agent_name,
+            picked.role,
+            picked.score,
+            ranked.len(),
+            picked.role_fit,
+            picked.success_rate,
+            picked.open_tasks,
+            picked.relay_load * 100.0
+        ),
+        candidates: ranked,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AgentHealth, AgentStatus, ExecutionMode};
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn agent(name: &str, role: AgentRole) -> Agent {
+        Agent {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            workdir: "/work".to_string(),
+            command: "claude-code-acp".to_string(),
+            args: "[]".to_string(),
+            execution_mode: ExecutionMode::Remote,
+            role,
+            project_id: Uuid::nil(),
+            status: AgentStatus::Created,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            subscribed_events: vec!["task.created".to_string()],
+            auto_trigger: true,
+            last_cursor: 0,
+            relay_selector: None,
+            health: AgentHealth::Unknown,
+            probe_failures: 0,
+            health_checked_at: None,
+        }
+    }
+
+    fn record(open_tasks: i64, finished: i64, completed: i64) -> AgentTrackRecord {
+        AgentTrackRecord {
+            open_tasks,
+            finished_sessions: finished,
+            completed_sessions: completed,
+        }
+    }
+
+    #[test]
+    fn test_stage_role() {
+        assert_eq!(stage_role(TaskStatus::Todo), None);
+        assert_eq!(
+            stage_role(TaskStatus::PlanPending),
+            Some(AgentRole::Business)
+        );
+        assert_eq!(
+            stage_role(TaskStatus::CodingReview),
+            Some(AgentRole::Coding)
+        );
+        assert_eq!(
+            stage_role(TaskStatus::CrossReviewPending),
+            Some(AgentRole::Qa)
+        );
+    }
+
+    #[test]
+    fn test_rank() {
+        let coder = agent("coder", AgentRole::Coding);
+        let general = agent("general", AgentRole::General);
+        let flaky = agent("flaky", AgentRole::Coding);
+        let offline = agent("offline", AgentRole::Coding);
+        let relay = |load: f64| Some(("relay-1".to_string(), load));
+        let candidates = [
+            Candidate {
+                agent: &general,
+                record: record(0, 0, 0),
+                relay: relay(0.0),
+                excluded: None,
+            },
+            Candidate {
+                agent: &flaky,
+                record: record(0, 10, 1),
+                relay: relay(0.0),
+                excluded: None,
+            },
+            Candidate {
+                agent: &offline,
+                record: record(0, 10, 10),
+                relay: None,
+                excluded: None,
+            },
+            Candidate {
+                agent: &coder,
+                record: record(1, 4, 4),
+                relay: relay(0.5),
+                excluded: None,
+            },
+        ];
+
+        let ranked = rank(Some(AgentRole::Coding), &candidates);
+        let names: Vec<&str> = ranked.iter().map(|c| c.agent_name.as_str()).collect();
+        assert_eq!(names, ["coder", "flaky", "general", "offline"]);
+        assert_eq!(ranked[0].role_fit, 1.0);
+        assert_eq!(ranked[2].role_fit, GENERAL_ROLE_FIT);
+        assert!(ranked[3].excluded.is_some());
+
+        let rationale = rationale(&ranked[0].clone(), ranked);
+        assert!(rationale.summary.starts_with("coder (coding) scored"));
+        assert_eq!(rationale.candidates.len(), 4);
+    }
+
+    #[test]
+    fn test_success_rate_is_smoothed() {
+        assert_eq!(success_rate(&record(0, 0, 0)), 0.5);
+        assert_eq!(success_rate(&record(0, 2, 2)), 0.75);
+    }
+}
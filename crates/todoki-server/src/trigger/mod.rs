@@ -0,0 +1,566 @@
+//! Event-driven agent triggering
+//!
+//! Agents with `auto_trigger` enabled are started whenever an event matching
+//! one of their `subscribed_events` is published. The agent's role template
+//! (shared template, then the project's inline one, then a default) is
+//! rendered with the triggering event, a session is created and
+//! `relay.spawn_requested` is emitted, followed by the rendered prompt.
+//!
+//! `task.mention` is the exception: it triggers exactly the `auto_trigger`
+//! agents it names, subscribed or not, with [`MENTION_TEMPLATE`].
+//!
+//! Each (agent, event cursor) pair is claimed in `agent_dispatches` before
+//! spawning, so an event never triggers the same agent twice.
+//!
+//! With auto-assignment on, events about tasks without an agent trigger only
+//! one agent, picked by [`assignment`].
+
+mod assignment;
+mod replay;
+
+pub use replay::ReplayRange;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Context;
+use serde_json::Value;
+use todoki_protocol::event_bus::{
+    AgentTaskContext, BuiltinEvent, EventKind, RelayInputRequestedData, RelaySpawnRequestedData,
+    TaskAssignedData, TaskMentionData,
+};
+use tokio::sync::{broadcast, Mutex};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::config::AssignmentSettings;
+use crate::db::DatabaseService;
+use crate::event_bus::{Event, EventPublisher, EventScope, EventSubscriber};
+use crate::models::{Agent, AgentSession, AgentStatus, Project, SessionStatus, Task};
+use crate::relay::RelayManager;
+
+use self::assignment::Candidate;
+
+/// Prompt used when neither a shared nor an inline role template is set
+const DEFAULT_TRIGGER_TEMPLATE: &str = r#"# Triggered by {{event_kind}}
+
+## Project: {{project_name}}
+{{project_description}}
+
+## Event
+```json
+{{event_data}}
+```
+
+{{task_content}}
+"#;
+
+/// Prompt for an agent mentioned in a task comment
+pub const MENTION_TEMPLATE: &str = r#"# You were mentioned in a comment
+
+## Project: {{project_name}}
+{{project_description}}
+
+## Task
+{{task_content}}
+
+## Comment
+{{event.content}}
+"#;
+
+pub struct TriggerEngine {
+    db: Arc<DatabaseService>,
+    relays: Arc<RelayManager>,
+    publisher: Arc<EventPublisher>,
+    subscriber: Arc<EventSubscriber>,
+    assignment: AssignmentSettings,
+    /// Running replays by agent
+    replays: Mutex<HashMap<Uuid, JoinHandle<()>>>,
+}
+
+impl TriggerEngine {
+    pub fn new(
+        db: Arc<DatabaseService>,
+        relays: Arc<RelayManager>,
+        publisher: Arc<EventPublisher>,
+        subscriber: Arc<EventSubscriber>,
+    ) -> Self {
+        Self {
+            db,
+            relays,
+            publisher,
+            subscriber,
+            assignment: AssignmentSettings::default(),
+            replays: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_assignment(mut self, assignment: AssignmentSettings) -> Self {
+        self.assignment = assignment;
+        self
+    }
+
+    /// Follow the event bus and trigger subscribed agents
+    pub async fn run(self: Arc<Self>) {
+        let mut rx = self.publisher.subscribe();
+
+        loop {
+            match rx.recv().await {
+                Ok(event) => self.dispatch(&event).await,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!(
+                        lagged_events = n,
+                        "trigger engine lagged, some events may be missed"
+                    );
+                }
+                Err(_) => {
+                    tracing::error!("trigger engine channel closed");
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn dispatch(&self, event: &Event) {
+        let agents = match self.db.list_auto_trigger_agents().await {
+            Ok(agents) => agents,
+            Err(e) => {
+                tracing::error!(error = %e, "failed to list auto-trigger agents");
+                return;
+            }
+        };
+
+        let mentioned = (event.kind == EventKind::TASK_MENTION).then(|| {
+            serde_json::from_value::<TaskMentionData>(event.data.clone())
+                .map(|data| data.agent_ids)
+                .unwrap_or_default()
+        });
+
+        let agents: Vec<Agent> = agents
+            .into_iter()
+            .filter(|agent| {
+                let wanted = match &mentioned {
+                    Some(agent_ids) => agent_ids.contains(&agent.id.to_string()),
+                    None => agent.is_subscribed_to(&event.kind),
+                };
+                // An agent is never triggered by its own events
+                agent.id != event.agent_id && event.cursor > agent.last_cursor && wanted
+            })
+            .collect();
+        if agents.is_empty() {
+            return;
+        }
+
+        if mentioned.is_none()
+            && let Some(task) = self.unassigned_task(event).await
+        {
+            if let Err(e) = self.assign(&task, &agents, event).await {
+                tracing::warn!(
+                    task_id = %task.id,
+                    cursor = event.cursor,
+                    error = %e,
+                    "failed to assign task"
+                );
+            }
+            return;
+        }
+
+        for agent in agents {
+            if let Err(e) = self.trigger(&agent, event).await {
+                tracing::warn!(
+                    agent_id = %agent.id,
+                    kind = %event.kind,
+                    cursor = event.cursor,
+                    error = %e,
+                    "failed to trigger agent"
+                );
+            }
+        }
+    }
+
+    /// The task `event` is about, when auto-assignment is on and the task
+    /// has no agent yet
+    async fn unassigned_task(&self, event: &Event) -> Option<Task> {
+        if !self.assignment.enabled {
+            return None;
+        }
+        match self.db.get_task_by_id(event.task_id?).await {
+            Ok(task) => task.filter(|task| task.agent_id.is_none() && !task.archived),
+            Err(e) => {
+                tracing::warn!(cursor = event.cursor, error = %e, "failed to load event task");
+                None
+            }
+        }
+    }
+
+    /// Trigger the best-placed of `agents` for `event` and assign the task
+    /// to it
+    async fn assign(&self, task: &Task, agents: &[Agent], event: &Event) -> anyhow::Result<()> {
+        let agent_ids: Vec<Uuid> = agents.iter().map(|a| a.id).collect();
+        let mut records = self
+            .db
+            .list_agent_track_records(&agent_ids, task.project_id, self.assignment.history_days)
+            .await?;
+
+        let mut candidates = Vec::with_capacity(agents.len());
+        for agent in agents {
+            let mut candidate = Candidate {
+                agent,
+                record: records.remove(&agent.id).unwrap_or_default(),
+                relay: None,
+                excluded: None,
+            };
+            if agent.status == AgentStatus::Running {
+                candidate.excluded = Some("agent is busy".to_string());
+            } else if let Some(project) = self.db.get_project(agent.project_id).await? {
+                if let Some(relay_id) = self.select_relay(agent, &project).await {
+                    let load = match self.relays.get_relay(&relay_id).await {
+                        Some(info) if info.max_sessions > 0 => {
+                            info.active_session_count as f64 / info.max_sessions as f64
+                        }
+                        _ => 0.0,
+                    };
+                    candidate.relay = Some((relay_id, load));
+                }
+            }
+            candidates.push(candidate);
+        }
+
+        let ranked = assignment::rank(assignment::stage_role(task.status), &candidates);
+        for picked in ranked.iter().filter(|c| c.excluded.is_none()) {
+            let Some(agent) = agents.iter().find(|a| a.id.to_string() == picked.agent_id) else {
+                continue;
+            };
+            let session = match self.trigger(agent, event).await {
+                Ok(Some(session)) => session,
+                Ok(None) => continue,
+                Err(e) => {
+                    tracing::warn!(agent_id = %agent.id, error = %e, "failed to trigger agent");
+                    continue;
+                }
+            };
+
+            self.db
+                .update_task_agent_id(task.id, Some(agent.id))
+                .await?;
+            let rationale = assignment::rationale(picked, ranked.clone());
+            tracing::info!(
+                task_id = %task.id,
+                agent_id = %agent.id,
+                rationale = %rationale.summary,
+                "task assigned"
+            );
+            let assigned = BuiltinEvent::TaskAssigned(TaskAssignedData {
+                assigned_agent_id: agent.id.to_string(),
+                rationale: Some(rationale),
+            });
+            let scope =
+                EventScope::execution(session.id, session.correlation_id).with_task(Some(task.id));
+            self.publisher.emit_builtin(assigned, scope).await?;
+            return Ok(());
+        }
+
+        tracing::info!(
+            task_id = %task.id,
+            candidates = ranked.len(),
+            "no agent could take the task"
+        );
+        Ok(())
+    }
+
+    /// A relay with a free slot for `agent`, honouring the project's routing
+    async fn select_relay(&self, agent: &Agent, project: &Project) -> Option<String> {
+        let route = project.relay_route(agent.role).unwrap_or_default();
+        let selector = agent.label_selector().or_else(|| route.label_selector());
+        self.relays
+            .select_relay_matching(
+                route.relay_id.as_deref(),
+                Some(agent.role.into()),
+                Some(agent.project_id),
+                selector.as_ref(),
+            )
+            .await
+    }
+
+    /// Start a session of `agent` for `event`; returns the session, or
+    /// `None` when the agent was not started (busy, archived project or
+    /// event already dispatched)
+    pub async fn trigger(
+        &self,
+        agent: &Agent,
+        event: &Event,
+    ) -> anyhow::Result<Option<AgentSession>> {
+        let Some(mode) = agent.execution_mode.session_mode() else {
+            anyhow::bail!("local execution not implemented");
+        };
+        if agent.status == AgentStatus::Running {
+            tracing::info!(
+                agent_id = %agent.id,
+                cursor = event.cursor,
+                "agent busy, skipping trigger"
+            );
+            return Ok(None);
+        }
+        let project = self
+            .db
+            .get_project(agent.project_id)
+            .await?
+            .with_context(|| format!("project {} not found", agent.project_id))?;
+        if project.archived {
+            tracing::debug!(
+                agent_id = %agent.id,
+                project_id = %project.id,
+                "project archived, skipping trigger"
+            );
+            return Ok(None);
+        }
+
+        if !self.db.claim_agent_dispatch(agent.id, event.cursor).await? {
+            tracing::debug!(
+                agent_id = %agent.id,
+                cursor = event.cursor,
+                "event already dispatched"
+            );
+            return Ok(None);
+        }
+        let task = match event.task_id {
+            Some(task_id) => self.db.get_task_by_id(task_id).await?,
+            None => None,
+        };
+
+        let relay_id = self.select_relay(agent, &project).await.with_context(|| {
+            format!(
+                "no relay with a free session slot for role {:?} and project {}",
+                agent.role, agent.project_id
+            )
+        })?;
+
+        let session = self.db.create_agent_session(agent.id).await?;
+        self.db
+            .update_agent_status(agent.id, AgentStatus::Running)
+            .await?;
+        self.relays
+            .add_active_session(&relay_id, &session.id.to_string(), Some(project.id))
+            .await;
+
+        let spawn = BuiltinEvent::RelaySpawnRequested(RelaySpawnRequestedData {
+            relay_id: relay_id.clone(),
+            request_id: Uuid::new_v4().to_string(),
+            target_agent_id: agent.id.to_string(),
+            session_id: session.id.to_string(),
+            workdir: agent.workdir.clone(),
+            command: agent.command.clone(),
+            args: agent.args_vec(),
+            env: HashMap::new(),
+            task_id: event.task_id.map(|id| id.to_string()),
+            task: task.as_ref().map(|task| task_context(task, &project)),
+            mode,
+        });
+        let scope =
+            EventScope::execution(session.id, session.correlation_id).with_task(event.task_id);
+        if let Err(e) = self
+            .relays
+            .emit_relay_command(&self.publisher, &relay_id, spawn, scope)
+            .await
+        {
+            // Rollback on emit failure
+            let _ = self
+                .db
+                .update_agent_status(agent.id, AgentStatus::Failed)
+                .await;
+            let _ = self
+                .db
+                .update_session_status(session.id, SessionStatus::Failed)
+                .await;
+            self.relays
+                .remove_active_session(&relay_id, &session.id.to_string())
+                .await;
+            anyhow::bail!("failed to emit spawn command: {}", e);
+        }
+
+        self.db
+            .set_dispatch_session(agent.id, event.cursor, session.id)
+            .await?;
+        self.db
+            .update_agent_last_cursor(agent.id, event.cursor)
+            .await?;
+
+        // A shared template referenced by the project wins over the inline one
+        let shared_template = match self
+            .db
+            .get_project_template_content(project.id, agent.role)
+            .await
+        {
+            Ok(content) => content,
+            Err(e) => {
+                tracing::warn!(
+                    project_id = %project.id,
+                    error = %e,
+                    "failed to load shared prompt template"
+                );
+                None
+            }
+        };
+        let template = if event.kind == EventKind::TASK_MENTION {
+            MENTION_TEMPLATE
+        } else {
+            shared_template
+                .as_deref()
+                .or_else(|| project.template_for_role(agent.role))
+                .unwrap_or(DEFAULT_TRIGGER_TEMPLATE)
+        };
+        let prompt = render_trigger_prompt(template, event, &project, task.as_ref());
+
+        let input = BuiltinEvent::RelayInputRequested(RelayInputRequestedData {
+            relay_id: relay_id.clone(),
+            request_id: Some(Uuid::new_v4().to_string()),
+            session_id: session.id.to_string(),
+            input: prompt,
+        });
+        if let Err(e) = self
+            .relays
+            .emit_relay_command(&self.publisher, &relay_id, input, scope)
+            .await
+        {
+            tracing::warn!(session_id = %session.id, error = %e, "failed to emit trigger prompt");
+        }
+
+        tracing::info!(
+            agent_id = %agent.id,
+            session_id = %session.id,
+            kind = %event.kind,
+            cursor = event.cursor,
+            "agent triggered by event"
+        );
+        Ok(Some(session))
+    }
+}
+
+fn task_context(task: &Task, project: &Project) -> AgentTaskContext {
+    AgentTaskContext {
+        task_id: task.id.to_string(),
+        content: task.content.clone(),
+        status: serde_json::to_value(task.status)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default(),
+        priority: task.priority,
+        project_id: project.id.to_string(),
+        project_name: project.name.clone(),
+    }
+}
+
+/// Render a role template with the triggering event
+///
+/// Besides the task placeholders (`{{task_content}}`, `{{project_name}}`,
+/// `{{project_description}}`), templates can use `{{event_kind}}`,
+/// `{{event_cursor}}`, `{{event_data}}` (pretty JSON) and
+/// `{{event.<field>}}` for top-level payload fields.
+fn render_trigger_prompt(
+    template: &str,
+    event: &Event,
+    project: &Project,
+    task: Option<&Task>,
+) -> String {
+    let mut prompt = template.to_string();
+    if let Some(fields) = event.data.as_object() {
+        for (key, value) in fields {
+            let text = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            prompt = prompt.replace(&format!("{{{{event.{}}}}}", key), &text);
+        }
+    }
+
+    prompt
+        .replace("{{event_kind}}", &event.kind)
+        .replace("{{event_cursor}}", &event.cursor.to_string())
+        .replace(
+            "{{event_data}}",
+            &serde_json::to_string_pretty(&event.data).unwrap_or_default(),
+        )
+        .replace(
+            "{{task_content}}",
+            task.map(|t| t.content.as_str()).unwrap_or(""),
+        )
+        .replace("{{project_name}}", &project.name)
+        .replace(
+            "{{project_description}}",
+            project.description.as_deref().unwrap_or(""),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn project() -> Project {
+        Project {
+            id: Uuid::new_v4(),
+            name: "todoki".to_string(),
+            description: Some("Task manager".to_string()),
+            color: "#6B7280".to_string(),
+            archived: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            general_template: None,
+            business_template: None,
+            coding_template: None,
+            qa_template: None,
+            execution_schedule: None,
+            relay_routing: None,
+            review_config: None,
+            workflow: None,
+            issue_sync: None,
+        }
+    }
+
+    fn event(data: Value) -> Event {
+        Event {
+            cursor: 42,
+            kind: "artifact.github_pr_opened".to_string(),
+            time: Utc::now(),
+            agent_id: Uuid::nil(),
+            session_id: None,
+            task_id: None,
+            correlation_id: None,
+            request_id: None,
+            data,
+            schema_version: todoki_protocol::EVENT_SCHEMA_VERSION,
+        }
+    }
+
+    #[test]
+    fn test_render_event_fields() {
+        let event = event(serde_json::json!({"url": "https://example.com/pr/1", "number": 1}));
+        let prompt = render_trigger_prompt(
+            "Review {{event.url}} (#{{event.number}}) in {{project_name}} after {{event_kind}}",
+            &event,
+            &project(),
+            None,
+        );
+        assert_eq!(
+            prompt,
+            "Review https://example.com/pr/1 (#1) in todoki after artifact.github_pr_opened"
+        );
+    }
+
+    #[test]
+    fn test_default_template_includes_payload() {
+        let event = event(serde_json::json!({"url": "https://example.com/pr/1"}));
+        let prompt = render_trigger_prompt(DEFAULT_TRIGGER_TEMPLATE, &event, &project(), None);
+        assert!(prompt.contains("# Triggered by artifact.github_pr_opened"));
+        assert!(prompt.contains("\"url\": \"https://example.com/pr/1\""));
+        assert!(!prompt.contains("{{"));
+    }
+
+    #[test]
+    fn test_mention_template_includes_comment() {
+        let event = event(serde_json::json!({"comment_id": "c1", "content": "@bot retry please"}));
+        let prompt = render_trigger_prompt(MENTION_TEMPLATE, &event, &project(), None);
+        assert!(prompt.contains("## Comment\n@bot retry please"));
+        assert!(!prompt.contains("{{"));
+    }
+}
@@ -0,0 +1,162 @@
+//! Backfill of historical events to a subscribed agent
+//!
+//! A replay walks the events matching the agent's subscriptions from a start
+//! cursor up to the latest cursor at the time it was started, triggering the
+//! agent for each one in turn. It waits for the previous session to end and
+//! paces triggers to a bounded rate, advancing `last_cursor` as it goes;
+//! events published after the replay started are left to live dispatch.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use gotcha::Schematic;
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::TriggerEngine;
+use crate::models::{Agent, AgentStatus};
+
+/// How often a busy agent is checked while the replay waits for it
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Upper bound on the replay rate
+const MAX_EVENTS_PER_MINUTE: u32 = 60;
+
+/// Cursor range a replay covers
+#[derive(Debug, Clone, Copy, Serialize, Schematic)]
+pub struct ReplayRange {
+    /// Events with a cursor above this one are replayed
+    pub from_cursor: i64,
+    /// Last cursor included in the replay
+    pub to_cursor: i64,
+}
+
+impl TriggerEngine {
+    /// Start replaying events to an agent in the background
+    ///
+    /// `from_cursor` defaults to the agent's `last_cursor`. Fails when a
+    /// replay for the agent is already running.
+    pub async fn start_replay(
+        self: &Arc<Self>,
+        agent_id: Uuid,
+        from_cursor: Option<i64>,
+        events_per_minute: u32,
+    ) -> anyhow::Result<ReplayRange> {
+        let agent = self
+            .db
+            .get_agent(agent_id)
+            .await?
+            .with_context(|| format!("agent {} not found", agent_id))?;
+        let range = ReplayRange {
+            from_cursor: from_cursor.unwrap_or(agent.last_cursor),
+            to_cursor: self.subscriber.latest_cursor().await?,
+        };
+        let pace = Duration::from_secs(60) / events_per_minute.clamp(1, MAX_EVENTS_PER_MINUTE);
+
+        let mut replays = self.replays.lock().await;
+        if replays
+            .get(&agent_id)
+            .is_some_and(|handle| !handle.is_finished())
+        {
+            anyhow::bail!("a replay is already running for agent {}", agent_id);
+        }
+
+        let engine = self.clone();
+        let handle = tokio::spawn(async move {
+            match engine.run_replay(agent_id, range, pace).await {
+                Ok(triggered) => tracing::info!(
+                    agent_id = %agent_id,
+                    to_cursor = range.to_cursor,
+                    triggered,
+                    "event replay finished"
+                ),
+                Err(e) => tracing::warn!(agent_id = %agent_id, error = %e, "event replay failed"),
+            }
+        });
+        replays.insert(agent_id, handle);
+
+        tracing::info!(
+            agent_id = %agent_id,
+            from_cursor = range.from_cursor,
+            to_cursor = range.to_cursor,
+            events_per_minute,
+            "event replay started"
+        );
+        Ok(range)
+    }
+
+    /// Stop a running replay; returns false when none was running
+    pub async fn cancel_replay(&self, agent_id: Uuid) -> bool {
+        match self.replays.lock().await.remove(&agent_id) {
+            Some(handle) if !handle.is_finished() => {
+                handle.abort();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns how many times the agent was triggered
+    async fn run_replay(
+        &self,
+        agent_id: Uuid,
+        range: ReplayRange,
+        pace: Duration,
+    ) -> anyhow::Result<usize> {
+        let mut cursor = range.from_cursor;
+        let mut triggered = 0;
+
+        while cursor < range.to_cursor {
+            // Subscriptions may change (or the agent be deleted) mid-replay
+            let Some(agent) = self.db.get_agent(agent_id).await? else {
+                break;
+            };
+            if agent.subscribed_events.is_empty() {
+                break;
+            }
+
+            let events = self
+                .subscriber
+                .replay(cursor, range.to_cursor, Some(&agent.subscribed_events))
+                .await?;
+            if events.is_empty() {
+                break;
+            }
+
+            for event in events {
+                cursor = event.cursor;
+                if event.agent_id == agent_id {
+                    continue;
+                }
+
+                let agent = self.wait_until_idle(agent_id).await?;
+                self.trigger(&agent, &event).await?;
+                self.db.update_agent_last_cursor(agent_id, cursor).await?;
+                triggered += 1;
+
+                tokio::time::sleep(pace).await;
+            }
+        }
+
+        self.db
+            .update_agent_last_cursor(agent_id, range.to_cursor)
+            .await?;
+        Ok(triggered)
+    }
+
+    /// Wait for the agent's current session to end; returns the fresh agent
+    async fn wait_until_idle(&self, agent_id: Uuid) -> anyhow::Result<Agent> {
+        loop {
+            let agent = self
+                .db
+                .get_agent(agent_id)
+                .await?
+                .with_context(|| format!("agent {} was deleted", agent_id))?;
+            if agent.status != AgentStatus::Running {
+                return Ok(agent);
+            }
+            tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+        }
+    }
+}
@@ -0,0 +1,447 @@
+//! Markdown vault export of project knowledge
+//!
+//! Writes every project as a folder of Markdown notes, one per task, that
+//! Obsidian or any Markdown viewer can browse: YAML frontmatter with the
+//! task's metadata, then its text, comments, session summaries and
+//! artifacts. Each project folder also gets an `index.md` linking its tasks
+//! by status. Files are only rewritten when their content changed, so a
+//! vault kept in git or a sync service only sees real changes.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use gotcha::Schematic;
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::config::VaultSettings;
+use crate::db::DatabaseService;
+use crate::models::{Artifact, Project, Task, TaskComment};
+
+/// Longest file or folder name derived from a title
+const MAX_SLUG_CHARS: usize = 60;
+
+/// Artifact type whose summaries get their own section
+const SUMMARY_ARTIFACT_TYPE: &str = "session_summary";
+
+/// Comments the summarizer posts alongside each `session_summary` artifact
+const SUMMARY_COMMENT_PREFIX: &str = "Session summary\n";
+
+/// What one export wrote
+#[derive(Debug, Clone, Default, Serialize, Schematic)]
+pub struct VaultExportSummary {
+    /// Folder the vault was written to
+    pub dir: String,
+    pub projects: usize,
+    pub tasks: usize,
+    /// Files created or changed; unchanged files are left alone
+    pub written: usize,
+}
+
+/// A task with everything its note shows
+pub struct TaskNote {
+    pub task: Task,
+    pub project: String,
+    pub agent: Option<String>,
+    pub remote_issue: Option<String>,
+    pub comments: Vec<TaskComment>,
+    pub artifacts: Vec<Artifact>,
+}
+
+impl TaskNote {
+    fn title(&self) -> &str {
+        let title = self.task.content.lines().next().unwrap_or("").trim();
+        if title.is_empty() {
+            "Untitled task"
+        } else {
+            title
+        }
+    }
+
+    /// File name without extension: the title's slug plus a short id, so
+    /// renaming a task does not collide with another one
+    fn file_stem(&self) -> String {
+        let id = self.task.id.simple().to_string();
+        match slug(self.title()) {
+            Some(slug) => format!("{}-{}", slug, &id[..8]),
+            None => id[..8].to_string(),
+        }
+    }
+}
+
+/// Lowercase title with runs of other characters turned into `-`; `None`
+/// when nothing usable is left
+fn slug(title: &str) -> Option<String> {
+    let mut slug = String::new();
+    for c in title.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.chars().count() >= MAX_SLUG_CHARS {
+            break;
+        }
+    }
+    let slug = slug.trim_end_matches('-').to_string();
+    (!slug.is_empty()).then_some(slug)
+}
+
+/// A YAML scalar; JSON strings are valid double-quoted YAML
+fn yaml_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m-%d %H:%M").to_string()
+}
+
+fn status_key(task: &Task) -> String {
+    crate::models::status_key(task.status)
+}
+
+/// One bullet describing an artifact
+fn artifact_line(artifact: &Artifact) -> String {
+    let field = |key: &str| artifact.data.get(key).and_then(Value::as_str);
+    let number = artifact.data.get("number").and_then(Value::as_i64);
+    let label = match (artifact.artifact_type.as_str(), field("url")) {
+        ("github_pr" | "gitea_pr", Some(url)) => format!(
+            "[{}/{}#{}]({})",
+            field("owner").unwrap_or(""),
+            field("repo").unwrap_or(""),
+            number.unwrap_or_default(),
+            url
+        ),
+        ("gitlab_mr", Some(url)) => format!(
+            "[{}!{}]({})",
+            field("project").unwrap_or(""),
+            number.unwrap_or_default(),
+            url
+        ),
+        ("branch", _) => format!(
+            "`{}` @ `{}`",
+            field("name").unwrap_or(""),
+            field("head").map(|h| &h[..h.len().min(7)]).unwrap_or("")
+        ),
+        ("commit", _) => format!(
+            "`{}` {}",
+            field("short_hash").unwrap_or(""),
+            field("message")
+                .and_then(|m| m.lines().next())
+                .unwrap_or("")
+        ),
+        (_, Some(url)) => format!("<{}>", url),
+        _ => timestamp(artifact.created_at),
+    };
+    let state = field("state")
+        .map(|s| format!(" ({})", s))
+        .unwrap_or_default();
+    format!("- **{}** {}{}", artifact.artifact_type, label, state)
+}
+
+/// The task's note: frontmatter, text, comments, summaries and artifacts
+pub fn render_task(note: &TaskNote) -> String {
+    let task = &note.task;
+    let mut out = String::from("---\n");
+    out.push_str(&format!("id: {}\n", task.id));
+    out.push_str(&format!("title: {}\n", yaml_string(note.title())));
+    out.push_str(&format!("project: {}\n", yaml_string(&note.project)));
+    out.push_str(&format!("status: {}\n", status_key(task)));
+    if task.workflow_state.is_some() {
+        out.push_str(&format!("state: {}\n", yaml_string(&task.state())));
+    }
+    out.push_str(&format!("priority: {}\n", task.priority));
+    out.push_str(&format!("created: {}\n", task.create_at.to_rfc3339()));
+    if let Some(due) = task.due_at {
+        out.push_str(&format!("due: {}\n", due.to_rfc3339()));
+    }
+    if let Some(minutes) = task.estimate_minutes {
+        out.push_str(&format!("estimate_minutes: {}\n", minutes));
+    }
+    if let Some(agent) = &note.agent {
+        out.push_str(&format!("agent: {}\n", yaml_string(agent)));
+    }
+    if let Some(key) = &note.remote_issue {
+        out.push_str(&format!("remote_issue: {}\n", yaml_string(key)));
+    }
+    if task.archived {
+        out.push_str("archived: true\n");
+    }
+    out.push_str("tags: [todoki]\n---\n\n");
+
+    out.push_str(&format!("# {}\n", note.title()));
+    let body = task.content.split_once('\n').map(|(_, rest)| rest.trim());
+    if let Some(body) = body.filter(|b| !b.is_empty()) {
+        out.push_str(&format!("\n{}\n", body));
+    }
+
+    let comments: Vec<&TaskComment> = note
+        .comments
+        .iter()
+        .filter(|c| !c.content.starts_with(SUMMARY_COMMENT_PREFIX))
+        .collect();
+    if !comments.is_empty() {
+        out.push_str("\n## Comments\n");
+        for comment in comments {
+            out.push_str(&format!(
+                "\n### {}\n\n{}\n",
+                timestamp(comment.create_at),
+                comment.content.trim()
+            ));
+        }
+    }
+
+    let (summaries, artifacts): (Vec<&Artifact>, Vec<&Artifact>) = note
+        .artifacts
+        .iter()
+        .partition(|a| a.artifact_type == SUMMARY_ARTIFACT_TYPE);
+    if !summaries.is_empty() {
+        out.push_str("\n## Session summaries\n");
+        for summary in summaries {
+            let text = summary.data.get("summary").and_then(Value::as_str);
+            out.push_str(&format!(
+                "\n### {}\n\n{}\n",
+                timestamp(summary.created_at),
+                text.unwrap_or("").trim()
+            ));
+        }
+    }
+    if !artifacts.is_empty() {
+        out.push_str("\n## Artifacts\n\n");
+        for artifact in artifacts {
+            out.push_str(&artifact_line(artifact));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// The project's `index.md`: its tasks as wiki links, grouped by status
+pub fn render_index(project: &Project, notes: &[TaskNote]) -> String {
+    let mut out = format!("# {}\n", project.name);
+    if let Some(description) = project.description.as_deref().filter(|d| !d.is_empty()) {
+        out.push_str(&format!("\n{}\n", description));
+    }
+
+    let mut by_status: BTreeMap<String, Vec<&TaskNote>> = BTreeMap::new();
+    for note in notes {
+        by_status.entry(note.task.state()).or_default().push(note);
+    }
+    for (state, notes) in by_status {
+        out.push_str(&format!("\n## {} ({})\n\n", state, notes.len()));
+        for note in notes {
+            out.push_str(&format!("- [[{}|{}]]\n", note.file_stem(), note.title()));
+        }
+    }
+    out
+}
+
+pub struct VaultExporter {
+    settings: VaultSettings,
+    db: Arc<DatabaseService>,
+}
+
+impl VaultExporter {
+    pub fn new(settings: VaultSettings, db: Arc<DatabaseService>) -> Self {
+        Self { settings, db }
+    }
+
+    /// Export every `interval_hours`, starting right away
+    pub async fn run(self) {
+        let period = Duration::from_secs(self.settings.interval_hours.max(1) * 3600);
+        let mut interval = tokio::time::interval(period);
+
+        loop {
+            interval.tick().await;
+            match self.export(None).await {
+                Ok(summary) => tracing::info!(
+                    dir = %summary.dir,
+                    tasks = summary.tasks,
+                    written = summary.written,
+                    "vault exported"
+                ),
+                Err(e) => tracing::error!(error = %e, "failed to export vault"),
+            }
+        }
+    }
+
+    /// Write all projects, or only `project_id`, into the vault folder
+    pub async fn export(&self, project_id: Option<Uuid>) -> anyhow::Result<VaultExportSummary> {
+        let projects = self
+            .db
+            .list_projects(self.settings.include_archived)
+            .await?;
+        let dir = PathBuf::from(&self.settings.dir);
+        let mut summary = VaultExportSummary {
+            dir: dir.display().to_string(),
+            ..Default::default()
+        };
+
+        for project in projects {
+            if project_id.is_some_and(|id| id != project.id) {
+                continue;
+            }
+            let folder = slug(&project.name)
+                .unwrap_or_else(|| format!("project-{}", &project.id.simple().to_string()[..8]));
+            let project_dir = dir.join(folder);
+            tokio::fs::create_dir_all(&project_dir).await?;
+
+            let notes = self.task_notes(&project).await?;
+            for note in &notes {
+                let path = project_dir.join(format!("{}.md", note.file_stem()));
+                if write_if_changed(&path, &render_task(note)).await? {
+                    summary.written += 1;
+                }
+            }
+            let index = render_index(&project, &notes);
+            if write_if_changed(&project_dir.join("index.md"), &index).await? {
+                summary.written += 1;
+            }
+
+            summary.projects += 1;
+            summary.tasks += notes.len();
+        }
+        Ok(summary)
+    }
+
+    async fn task_notes(&self, project: &Project) -> crate::Result<Vec<TaskNote>> {
+        let tasks = self
+            .db
+            .list_project_tasks(project.id, self.settings.include_archived)
+            .await?;
+
+        let mut notes = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            let agent = match task.agent_id {
+                Some(agent_id) => self.db.get_agent(agent_id).await?.map(|a| a.name),
+                None => None,
+            };
+            notes.push(TaskNote {
+                agent,
+                remote_issue: self
+                    .db
+                    .get_remote_issue(task.id)
+                    .await?
+                    .map(|issue| issue.remote_key),
+                comments: self.db.get_task_comments(task.id).await?,
+                artifacts: self.db.list_artifacts_by_task(task.id).await?,
+                project: project.name.clone(),
+                task,
+            });
+        }
+        Ok(notes)
+    }
+}
+
+/// Write `content` unless the file already holds it; returns whether it wrote
+async fn write_if_changed(path: &Path, content: &str) -> std::io::Result<bool> {
+    if let Ok(existing) = tokio::fs::read_to_string(path).await
+        && existing == content
+    {
+        return Ok(false);
+    }
+    tokio::fs::write(path, content).await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TaskStatus;
+    use serde_json::json;
+
+    fn note(content: &str) -> TaskNote {
+        let at = DateTime::parse_from_rfc3339("2026-03-01T09:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let task_id = Uuid::parse_str("0193f1a2-0000-7000-8000-000000000001").unwrap();
+        let artifact = |artifact_type: &str, data: Value| Artifact {
+            id: Uuid::new_v4(),
+            task_id,
+            project_id: Uuid::nil(),
+            agent_id: None,
+            session_id: None,
+            artifact_type: artifact_type.to_string(),
+            data,
+            created_at: at,
+            updated_at: at,
+        };
+        TaskNote {
+            task: Task {
+                id: task_id,
+                priority: 2,
+                content: content.to_string(),
+                project_id: Uuid::nil(),
+                status: TaskStatus::Done,
+                create_at: at,
+                archived: false,
+                agent_id: None,
+                due_at: None,
+                estimate_minutes: None,
+                workflow_state: None,
+                rank: None,
+            },
+            project: "Web \"app\"".to_string(),
+            agent: Some("coder".to_string()),
+            remote_issue: None,
+            comments: vec![
+                TaskComment {
+                    id: Uuid::new_v4(),
+                    task_id,
+                    content: "Looks good".to_string(),
+                    create_at: at,
+                },
+                TaskComment {
+                    id: Uuid::new_v4(),
+                    task_id,
+                    content: "Session summary\n\nduplicate".to_string(),
+                    create_at: at,
+                },
+            ],
+            artifacts: vec![
+                artifact(
+                    "github_pr",
+                    json!({"url": "https://github.com/o/r/pull/3", "owner": "o", "repo": "r", "number": 3}),
+                ),
+                artifact(
+                    "session_summary",
+                    json!({"summary": "### What the agent did\nFixed it"}),
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_slug() {
+        assert_eq!(
+            slug("Fix: login (SSO) redirect!").as_deref(),
+            Some("fix-login-sso-redirect")
+        );
+        assert_eq!(slug("修复 登录").as_deref(), Some("修复-登录"));
+        assert_eq!(slug("?!").as_deref(), None);
+        assert_eq!(slug(&"a".repeat(100)).unwrap().len(), MAX_SLUG_CHARS);
+    }
+
+    #[test]
+    fn test_render_task() {
+        let note = note("Fix login redirect\n\nUsers land on /404 after SSO.");
+        assert_eq!(note.file_stem(), "fix-login-redirect-0193f1a2");
+
+        let md = render_task(&note);
+        assert!(md.starts_with("---\nid: 0193f1a2-0000-7000-8000-000000000001\n"));
+        assert!(md.contains("project: \"Web \\\"app\\\"\"\n"));
+        assert!(md.contains("status: done\n"));
+        assert!(md.contains("agent: \"coder\"\n"));
+        assert!(md.contains("# Fix login redirect\n\nUsers land on /404 after SSO.\n"));
+        assert!(md.contains("### 2026-03-01 09:30\n\nLooks good\n"));
+        assert!(!md.contains("duplicate"));
+        assert!(
+            md.contains("## Session summaries\n\n### 2026-03-01 09:30\n\n### What the agent did")
+        );
+        assert!(md.contains("- **github_pr** [o/r#3](https://github.com/o/r/pull/3)\n"));
+    }
+}
@@ -0,0 +1,151 @@
+//! Who watches which tasks and agent sessions
+//!
+//! Mirrors the `watches` table so events can be routed without a query per
+//! event. Permission requests about a watched task or session are only
+//! pushed to the clients of its watchers; requests nobody watches still
+//! reach every client, so none goes unanswered. Starting a task or agent
+//! through the API makes the caller watch it. The notification center
+//! addresses changes of a task to its watchers, and `watched_only`
+//! event-bus clients only get the events of what their user watches.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+
+use todoki_protocol::event_bus::EventKind;
+use uuid::Uuid;
+
+use crate::db::DatabaseService;
+use crate::event_bus::Event;
+use crate::models::{Watch, WatchTarget};
+
+#[derive(Default)]
+pub struct WatchRegistry {
+    index: RwLock<WatchIndex>,
+}
+
+#[derive(Default)]
+struct WatchIndex {
+    tasks: HashMap<Uuid, HashSet<String>>,
+    sessions: HashMap<Uuid, HashSet<String>>,
+}
+
+impl WatchIndex {
+    fn users(&mut self, target: WatchTarget) -> &mut HashSet<String> {
+        match target {
+            WatchTarget::Task(id) => self.tasks.entry(id).or_default(),
+            WatchTarget::Session(id) => self.sessions.entry(id).or_default(),
+        }
+    }
+}
+
+impl WatchRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fill the registry from the database
+    pub async fn load(&self, db: &DatabaseService) -> crate::Result<()> {
+        for watch in db.list_watches().await? {
+            self.insert(&watch);
+        }
+        Ok(())
+    }
+
+    pub fn insert(&self, watch: &Watch) {
+        let Some(target) = watch.target() else {
+            return;
+        };
+        let mut index = self.index.write().unwrap_or_else(|e| e.into_inner());
+        index.users(target).insert(watch.user_id.clone());
+    }
+
+    pub fn remove(&self, watch: &Watch) {
+        let Some(target) = watch.target() else {
+            return;
+        };
+        let mut index = self.index.write().unwrap_or_else(|e| e.into_inner());
+        index.users(target).remove(&watch.user_id);
+    }
+
+    /// Users watching the task or the session
+    pub fn watchers(&self, task_id: Option<Uuid>, session_id: Option<Uuid>) -> HashSet<String> {
+        let index = self.index.read().unwrap_or_else(|e| e.into_inner());
+        let task = task_id.and_then(|id| index.tasks.get(&id));
+        let session = session_id.and_then(|id| index.sessions.get(&id));
+        task.into_iter().chain(session).flatten().cloned().collect()
+    }
+
+    /// Whether `user_id` watches the task or the session of `event`
+    pub fn watched_by(&self, event: &Event, user_id: &str) -> bool {
+        self.watchers(event.task_id, event_session(event))
+            .contains(user_id)
+    }
+
+    /// Whether a client of `user_id` gets `event`; permission requests
+    /// somebody watches only go to their watchers
+    pub fn delivers(&self, event: &Event, user_id: Option<&str>) -> bool {
+        if event.kind != EventKind::PERMISSION_REQUESTED {
+            return true;
+        }
+        let watchers = self.watchers(event.task_id, event_session(event));
+        watchers.is_empty() || user_id.is_some_and(|user| watchers.contains(user))
+    }
+}
+
+/// Session of an event, in the `session_id` column or in the payload
+fn event_session(event: &Event) -> Option<Uuid> {
+    event.session_id.or_else(|| {
+        event
+            .data
+            .get("session_id")
+            .and_then(|v| v.as_str())
+            .and_then(|s| Uuid::parse_str(s).ok())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn watch(user_id: &str, task_id: Option<Uuid>, session_id: Option<Uuid>) -> Watch {
+        Watch {
+            id: Uuid::new_v4(),
+            user_id: user_id.to_string(),
+            task_id,
+            session_id,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_permission_requests_reach_watchers_only() {
+        let registry = WatchRegistry::new();
+        let (task_id, session_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut request = Event::new(
+            EventKind::PERMISSION_REQUESTED,
+            Uuid::nil(),
+            serde_json::json!({ "session_id": session_id.to_string() }),
+        );
+        request.task_id = Some(task_id);
+
+        // Nobody watches yet, so everyone gets it
+        assert!(registry.delivers(&request, Some("oidc:alice")));
+        assert!(registry.delivers(&request, None));
+
+        let by_task = watch("oidc:alice", Some(task_id), None);
+        registry.insert(&by_task);
+        registry.insert(&watch("oidc:bob", None, Some(session_id)));
+        assert!(registry.delivers(&request, Some("oidc:alice")));
+        assert!(registry.delivers(&request, Some("oidc:bob")));
+        assert!(!registry.delivers(&request, Some("user")));
+        assert!(!registry.delivers(&request, None));
+
+        // Other kinds are not routed
+        let created = Event::new("task.created", Uuid::nil(), request.data.clone());
+        assert!(registry.delivers(&created, Some("user")));
+
+        registry.remove(&by_task);
+        assert!(!registry.delivers(&request, Some("oidc:alice")));
+    }
+}
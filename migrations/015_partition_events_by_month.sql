@@ -0,0 +1,69 @@
+-- Partition events table by month
+-- Cursor-range queries slow down as the events table grows; native range
+-- partitioning on time lets retention drop whole partitions instead of
+-- deleting rows, and keeps per-partition indexes small.
+
+ALTER TABLE events RENAME TO events_legacy;
+ALTER SEQUENCE events_cursor_seq OWNED BY NONE;
+
+CREATE TABLE events (
+    cursor BIGINT NOT NULL DEFAULT nextval('events_cursor_seq'),
+    kind VARCHAR(64) NOT NULL,
+    time TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    agent_id UUID NOT NULL,
+    session_id UUID,
+    task_id UUID,
+    data JSONB NOT NULL,
+
+    PRIMARY KEY (cursor, time),
+    FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE,
+    FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE SET NULL
+) PARTITION BY RANGE (time);
+
+-- Create the monthly partition containing the given date (idempotent).
+-- Partitions are named events_pYYYYMM.
+CREATE OR REPLACE FUNCTION ensure_events_partition(month DATE) RETURNS TEXT AS $$
+DECLARE
+    start_date DATE := date_trunc('month', month)::date;
+    end_date DATE := (date_trunc('month', month) + INTERVAL '1 month')::date;
+    partition_name TEXT := 'events_p' || to_char(start_date, 'YYYYMM');
+BEGIN
+    IF to_regclass(partition_name) IS NULL THEN
+        EXECUTE format(
+            'CREATE TABLE %I PARTITION OF events FOR VALUES FROM (%L) TO (%L)',
+            partition_name, start_date, end_date
+        );
+    END IF;
+    RETURN partition_name;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Catch-all for rows outside any monthly partition
+CREATE TABLE events_default PARTITION OF events DEFAULT;
+
+-- Partitions for existing data plus the current and next month
+SELECT ensure_events_partition(m::date)
+FROM generate_series(
+    date_trunc('month', LEAST(COALESCE((SELECT MIN(time) FROM events_legacy), NOW()), NOW())),
+    date_trunc('month', NOW() + INTERVAL '1 month'),
+    INTERVAL '1 month'
+) AS m;
+
+INSERT INTO events (cursor, kind, time, agent_id, session_id, task_id, data)
+SELECT cursor, kind, time, agent_id, session_id, task_id, data FROM events_legacy;
+
+DROP TABLE events_legacy;
+
+ALTER SEQUENCE events_cursor_seq OWNED BY events.cursor;
+
+-- Indexes (created on every partition automatically)
+CREATE INDEX idx_events_cursor ON events(cursor);
+CREATE INDEX idx_events_time ON events(time);
+CREATE INDEX idx_events_kind ON events(kind);
+CREATE INDEX idx_events_kind_time ON events(kind, time);
+CREATE INDEX idx_events_agent_cursor ON events(agent_id, cursor);
+CREATE INDEX idx_events_task ON events(task_id) WHERE task_id IS NOT NULL;
+CREATE INDEX idx_events_session ON events(session_id) WHERE session_id IS NOT NULL;
+
+COMMENT ON TABLE events IS 'Event Bus storage, range-partitioned by month on time. Partitions are created ahead of time by the server maintenance worker.';
+COMMENT ON COLUMN events.cursor IS 'Global monotonic sequence number for event ordering and incremental consumption';
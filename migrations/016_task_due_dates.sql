@@ -0,0 +1,3 @@
+-- Optional due date for tasks (used by the calendar feed)
+ALTER TABLE tasks ADD COLUMN due_at TIMESTAMPTZ;
+CREATE INDEX idx_tasks_due_at ON tasks(due_at) WHERE due_at IS NOT NULL;
@@ -0,0 +1,14 @@
+-- Per-project execution windows (NULL = agents may run at any time)
+ALTER TABLE projects ADD COLUMN execution_schedule JSONB;
+
+-- Executions requested outside their project's window, spawned when it opens
+CREATE TABLE scheduled_executions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    relay_id VARCHAR(255),
+    scheduled_for TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_scheduled_executions_task ON scheduled_executions(task_id);
+CREATE INDEX idx_scheduled_executions_due ON scheduled_executions(scheduled_for);
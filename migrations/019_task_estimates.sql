@@ -0,0 +1,16 @@
+-- Optional effort estimate for tasks
+ALTER TABLE tasks ADD COLUMN estimate_minutes INTEGER;
+
+-- Spawn-to-completion duration of every agent session run for a task
+CREATE TABLE task_session_durations (
+    session_id UUID PRIMARY KEY REFERENCES agent_sessions(id) ON DELETE CASCADE,
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    role VARCHAR(50) NOT NULL,
+    spawned_at TIMESTAMPTZ NOT NULL,
+    completed_at TIMESTAMPTZ NOT NULL,
+    duration_secs BIGINT NOT NULL
+);
+
+CREATE INDEX idx_task_session_durations_task ON task_session_durations(task_id);
+CREATE INDEX idx_task_session_durations_project ON task_session_durations(project_id);
@@ -0,0 +1,9 @@
+-- Snoozed tasks are hidden from the inbox until wake_at
+CREATE TABLE task_snoozes (
+    task_id UUID PRIMARY KEY REFERENCES tasks(id) ON DELETE CASCADE,
+    wake_at TIMESTAMPTZ NOT NULL,
+    notify BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_task_snoozes_wake_at ON task_snoozes(wake_at);
@@ -0,0 +1,9 @@
+-- agent_events.stream holds an OutputStreamKind name (assistant, thinking,
+-- tool_use, tool_result, user, plan, system, stdout, stderr, terminal).
+-- Unknown kinds are preserved verbatim, so the column stays free-form text;
+-- only legacy spellings are normalized to the canonical snake_case names.
+UPDATE agent_events
+SET stream = replace(lower(trim(stream)), '-', '_')
+WHERE stream <> replace(lower(trim(stream)), '-', '_');
+
+COMMENT ON COLUMN agent_events.stream IS 'OutputStreamKind name; unknown kinds are kept as-is';
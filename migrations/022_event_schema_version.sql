@@ -0,0 +1,4 @@
+-- Version of the builtin payload schema an event was written with
+-- (todoki_protocol::EVENT_SCHEMA_VERSION). Existing rows predate versioning
+-- and are treated as version 1.
+ALTER TABLE events ADD COLUMN schema_version INTEGER NOT NULL DEFAULT 1;
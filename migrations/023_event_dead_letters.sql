@@ -0,0 +1,20 @@
+-- Events whose background handler failed (e.g. a DB error while applying a
+-- relay response). The full event is kept so it can be reprocessed even after
+-- the events partition it came from has been pruned.
+CREATE TABLE event_dead_letters (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    event_cursor BIGINT NOT NULL,
+    event_kind VARCHAR(255) NOT NULL,
+    handler VARCHAR(100) NOT NULL,
+    event JSONB NOT NULL,
+    error TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 1,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    resolved_at TIMESTAMPTZ,
+    UNIQUE (event_cursor, handler)
+);
+
+CREATE INDEX idx_event_dead_letters_unresolved
+    ON event_dead_letters(created_at)
+    WHERE resolved_at IS NULL;
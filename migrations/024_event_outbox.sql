@@ -0,0 +1,21 @@
+-- Transactional outbox: events written in the same transaction as the
+-- domain change they describe, published to the event bus by a background
+-- worker and then marked delivered.
+CREATE TABLE event_outbox (
+    id BIGSERIAL PRIMARY KEY,
+    kind VARCHAR(255) NOT NULL,
+    time TIMESTAMPTZ NOT NULL,
+    agent_id UUID NOT NULL,
+    session_id UUID,
+    task_id UUID,
+    data JSONB NOT NULL,
+    schema_version INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    delivered_at TIMESTAMPTZ,
+    -- Cursor the event got once published
+    event_cursor BIGINT
+);
+
+CREATE INDEX idx_event_outbox_pending ON event_outbox(id) WHERE delivered_at IS NULL;
+CREATE INDEX idx_event_outbox_delivered_at ON event_outbox(delivered_at)
+    WHERE delivered_at IS NOT NULL;
@@ -0,0 +1,10 @@
+-- Which events have already triggered which agent. A dispatcher claims
+-- (agent_id, event_cursor) before spawning, so replays and restarts can't
+-- trigger the same agent twice for one event.
+CREATE TABLE agent_dispatches (
+    agent_id UUID NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    event_cursor BIGINT NOT NULL,
+    session_id UUID REFERENCES agent_sessions(id) ON DELETE SET NULL,
+    dispatched_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (agent_id, event_cursor)
+);
@@ -0,0 +1,8 @@
+-- Event subscriptions for agents, persisted again so the trigger engine can
+-- start sessions from events (they were dropped as runtime-only in 013)
+ALTER TABLE agents
+ADD COLUMN subscribed_events TEXT[] NOT NULL DEFAULT '{}',
+ADD COLUMN auto_trigger BOOLEAN NOT NULL DEFAULT false,
+ADD COLUMN last_cursor BIGINT NOT NULL DEFAULT 0;
+
+CREATE INDEX idx_agents_auto_trigger ON agents(auto_trigger) WHERE auto_trigger = true;
@@ -0,0 +1,11 @@
+-- Correlation IDs tie together every event of one execution (spawn, prompt,
+-- output, permission round-trips, completion). Each session gets its own;
+-- events inherit it from the session they belong to.
+ALTER TABLE agent_sessions
+ADD COLUMN correlation_id UUID NOT NULL DEFAULT uuid_generate_v4();
+
+ALTER TABLE events ADD COLUMN correlation_id UUID;
+ALTER TABLE event_outbox ADD COLUMN correlation_id UUID;
+
+CREATE INDEX idx_events_correlation ON events(correlation_id, cursor)
+    WHERE correlation_id IS NOT NULL;
@@ -0,0 +1,7 @@
+-- Archiving a project archives its open tasks and hides its artifacts; the
+-- flags below record what the cascade touched so restoring can undo exactly that
+ALTER TABLE tasks ADD COLUMN archived_with_project BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE artifacts ADD COLUMN hidden BOOLEAN NOT NULL DEFAULT false;
+
+CREATE INDEX idx_tasks_archived_with_project ON tasks(project_id)
+    WHERE archived_with_project = true;
@@ -0,0 +1,3 @@
+-- Per-role relay routing for a project: a preferred relay and/or a label
+-- selector constraining which relays may run the project's agents.
+ALTER TABLE projects ADD COLUMN relay_routing JSONB;
@@ -0,0 +1,4 @@
+-- Label selectors (e.g. `gpu=true,region in (eu,us)`) restricting which
+-- relays may run an agent or a queued task execution.
+ALTER TABLE agents ADD COLUMN relay_selector TEXT;
+ALTER TABLE scheduled_executions ADD COLUMN relay_selector TEXT;
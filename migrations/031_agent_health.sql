@@ -0,0 +1,5 @@
+-- Liveness of an agent's running session as reported by relay probes
+ALTER TABLE agents
+ADD COLUMN health VARCHAR(50) NOT NULL DEFAULT 'unknown',
+ADD COLUMN probe_failures INTEGER NOT NULL DEFAULT 0,
+ADD COLUMN health_checked_at TIMESTAMPTZ;
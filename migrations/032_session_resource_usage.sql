@@ -0,0 +1,3 @@
+-- Latest resource usage of a session's process tree as sampled by its relay
+ALTER TABLE agent_sessions
+ADD COLUMN resource_usage JSONB;
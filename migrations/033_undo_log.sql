@@ -0,0 +1,17 @@
+-- Recent destructive mutations per API token, so they can be reverted with
+-- POST /api/undo. Entries only matter within the undo window and are pruned
+-- once they fall out of it.
+CREATE TABLE undo_log (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    token_id VARCHAR(255) NOT NULL,
+    action VARCHAR(50) NOT NULL,
+    target_id UUID NOT NULL,
+    -- Rows needed to recreate a deleted target
+    snapshot JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    undone_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_undo_log_token_pending
+    ON undo_log(token_id, created_at DESC)
+    WHERE undone_at IS NULL;
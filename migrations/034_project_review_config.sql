@@ -0,0 +1,3 @@
+-- Per-project overrides of the permission reviewer settings: enablement,
+-- model, extra guidance and the risk thresholds for automatic answers.
+ALTER TABLE projects ADD COLUMN review_config JSONB;
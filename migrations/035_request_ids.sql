@@ -0,0 +1,7 @@
+-- ID of the API request behind an event or an undo log entry, for
+-- correlating them with the server logs and with each other.
+ALTER TABLE events ADD COLUMN request_id VARCHAR(128);
+ALTER TABLE event_outbox ADD COLUMN request_id VARCHAR(128);
+ALTER TABLE undo_log ADD COLUMN request_id VARCHAR(128);
+
+CREATE INDEX idx_events_request ON events(request_id) WHERE request_id IS NOT NULL;
@@ -0,0 +1,6 @@
+-- Per-project workflows: ordered task states with a category, allowed
+-- transitions and a priority scale. NULL keeps the built-in statuses.
+ALTER TABLE projects ADD COLUMN workflow JSONB;
+
+-- Custom state of a task; NULL when it is in the state named by its status.
+ALTER TABLE tasks ADD COLUMN workflow_state VARCHAR(64);
@@ -0,0 +1,8 @@
+-- Manual order of tasks within a board column: a lexicographic rank that
+-- sorts before priority. Reorders are kept as task events with the old
+-- and new rank.
+ALTER TABLE tasks ADD COLUMN rank TEXT;
+ALTER TABLE task_events ADD COLUMN rank TEXT;
+ALTER TABLE task_events ADD COLUMN from_rank TEXT;
+
+CREATE INDEX idx_tasks_project_rank ON tasks(project_id, rank);
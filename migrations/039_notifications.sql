@@ -0,0 +1,24 @@
+-- In-app notification center: one row per recipient, addressed by token ID
+-- ("user" or "oidc:<subject>").
+CREATE TABLE notifications (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id VARCHAR(255) NOT NULL,
+    kind VARCHAR(50) NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    body TEXT NOT NULL DEFAULT '',
+    task_id UUID REFERENCES tasks(id) ON DELETE CASCADE,
+    session_id UUID,
+    event_cursor BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    read_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_notifications_user ON notifications(user_id, created_at DESC);
+CREATE INDEX idx_notifications_unread ON notifications(user_id) WHERE read_at IS NULL;
+
+-- Kinds each user turned off; users without a row get every kind
+CREATE TABLE notification_preferences (
+    user_id VARCHAR(255) PRIMARY KEY,
+    muted JSONB NOT NULL DEFAULT '[]',
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,16 @@
+-- Shared memory of the agents working on a task: JSON documents by key.
+-- Each write bumps the key's version so writers can detect lost updates.
+CREATE TABLE task_context (
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    key VARCHAR(200) NOT NULL,
+    value JSONB NOT NULL,
+    version INTEGER NOT NULL DEFAULT 1,
+    -- Token ID of the last writer ("user", "oidc:<subject>" or "agent:<session>")
+    updated_by VARCHAR(255) NOT NULL,
+    -- Agent session of the last writer, if an agent wrote it
+    agent_id UUID,
+    session_id UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (task_id, key)
+);
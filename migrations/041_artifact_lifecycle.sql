@@ -0,0 +1,13 @@
+-- Artifacts can be revised, deleted and shared with other tasks. Deleting
+-- only stamps deleted_at, so history and events keep pointing at something.
+ALTER TABLE artifacts ADD COLUMN deleted_at TIMESTAMPTZ;
+
+-- Tasks an artifact is linked to besides the one it belongs to
+CREATE TABLE artifact_links (
+    artifact_id UUID NOT NULL REFERENCES artifacts(id) ON DELETE CASCADE,
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (artifact_id, task_id)
+);
+
+CREATE INDEX idx_artifact_links_task ON artifact_links(task_id);
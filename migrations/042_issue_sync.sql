@@ -0,0 +1,17 @@
+-- Projects can mirror their tasks into Jira or Linear. Each synced task
+-- keeps the remote issue's key and the status category both sides agreed
+-- on last, which tells which side changed since.
+ALTER TABLE projects ADD COLUMN issue_sync JSONB;
+
+CREATE TABLE task_remote_issues (
+    task_id UUID PRIMARY KEY REFERENCES tasks(id) ON DELETE CASCADE,
+    provider TEXT NOT NULL,
+    remote_id TEXT NOT NULL,
+    remote_key TEXT NOT NULL,
+    url TEXT NOT NULL,
+    synced_category TEXT NOT NULL,
+    synced_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_task_remote_issues_remote ON task_remote_issues(provider, remote_id);
@@ -0,0 +1,23 @@
+-- Time people log on tasks, either as a finished span or with a running
+-- timer (ended_at NULL). Agent time is not stored here: it is recorded per
+-- session in task_session_durations.
+CREATE TABLE task_time_entries (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    user_id VARCHAR(255) NOT NULL,
+    started_at TIMESTAMPTZ NOT NULL,
+    ended_at TIMESTAMPTZ,
+    note TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (ended_at IS NULL OR ended_at >= started_at)
+);
+
+CREATE INDEX idx_task_time_entries_task ON task_time_entries(task_id);
+CREATE INDEX idx_task_time_entries_project ON task_time_entries(project_id, started_at);
+
+-- Each user has at most one running timer
+CREATE UNIQUE INDEX idx_task_time_entries_running ON task_time_entries(user_id)
+    WHERE ended_at IS NULL;
+
+CREATE INDEX idx_task_session_durations_spawned ON task_session_durations(project_id, spawned_at);
@@ -0,0 +1,7 @@
+-- Possible duplicates flagged on task creation and merges of duplicates are
+-- kept as task events pointing at the other task.
+ALTER TABLE task_events ADD COLUMN related_task_id UUID;
+ALTER TABLE task_events ADD COLUMN similarity DOUBLE PRECISION;
+
+CREATE INDEX idx_tasks_project_open ON tasks(project_id, create_at DESC)
+    WHERE archived = false AND status <> 'done';
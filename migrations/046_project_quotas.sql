@@ -0,0 +1,19 @@
+-- Per-project overrides of the configured quota defaults; NULL keeps the
+-- default.
+CREATE TABLE project_quotas (
+    project_id UUID PRIMARY KEY REFERENCES projects(id) ON DELETE CASCADE,
+    max_concurrent_sessions BIGINT,
+    events_per_hour BIGINT,
+    storage_bytes BIGINT,
+    ai_reviews_per_month BIGINT,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Usage of quotas counted per period, e.g. AI reviews per month.
+CREATE TABLE quota_usage (
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    metric TEXT NOT NULL,
+    period_start TIMESTAMPTZ NOT NULL,
+    used BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (project_id, metric, period_start)
+);
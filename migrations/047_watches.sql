@@ -0,0 +1,16 @@
+-- Tasks and agent sessions people watch. Permission requests about a
+-- watched task or session are only pushed to the watchers' WebSocket
+-- clients.
+CREATE TABLE watches (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id VARCHAR(255) NOT NULL,
+    task_id UUID REFERENCES tasks(id) ON DELETE CASCADE,
+    session_id UUID REFERENCES agent_sessions(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((task_id IS NULL) <> (session_id IS NULL))
+);
+
+CREATE UNIQUE INDEX idx_watches_task ON watches(user_id, task_id)
+    WHERE task_id IS NOT NULL;
+CREATE UNIQUE INDEX idx_watches_session ON watches(user_id, session_id)
+    WHERE session_id IS NOT NULL;
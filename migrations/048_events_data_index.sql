@@ -0,0 +1,4 @@
+-- Filtering events by their data (GET /api/event-bus?filter=data.relay_id=x)
+-- checks containment with @>; jsonb_path_ops indexes exactly that. Created
+-- on the partitioned table, so every monthly partition gets one.
+CREATE INDEX idx_events_data ON events USING GIN (data jsonb_path_ops);
@@ -2,13 +2,19 @@ import { useEffect, useState } from "react";
 import RouteList from "@/router/RouteList";
 import { Toaster } from "./components/ui/toaster";
 import TokenInput from "./pages/TokenInput";
-import { getToken, validateToken, clearToken } from "./lib/auth";
+import {
+  getToken,
+  validateToken,
+  clearToken,
+  consumeLoginToken,
+} from "./lib/auth";
 
 function App() {
   const [isAuthenticated, setIsAuthenticated] = useState<boolean | null>(null);
 
   useEffect(() => {
     const checkAuth = async () => {
+      consumeLoginToken();
       const token = getToken();
       if (!token) {
         setIsAuthenticated(false);
@@ -0,0 +1,6 @@
+import { fetcher } from "./fetcher";
+
+// Permission API endpoints using openapi-typescript-fetch
+// Note: These paths need to be added to schema.d.ts when running npm run api
+export const fetchPendingPermissions = fetcher.path("/api/permissions/pending" as any).method("get").create();
+export const respondPermission = fetcher.path("/api/permissions/{request_id}/respond" as any).method("post").create();
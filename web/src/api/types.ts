@@ -40,4 +40,6 @@ export interface RelayInfo {
     setup_script: string | null;
     connected_at: number;
     active_session_count: number;
+    max_sessions: number;
+    protocol_version: number;
 }
\ No newline at end of file
@@ -110,10 +110,17 @@ export default function TaskDetailPanel({
     setIsExecuting(true);
     try {
       const { data } = await executeTask({ task_id: selectedTaskId });
-      toast({
-        title: "Task execution started",
-        description: `Agent ${data.agent.name} is now running`,
-      });
+      toast(
+        data.agent
+          ? {
+              title: "Task execution started",
+              description: `Agent ${data.agent.name} is now running`,
+            }
+          : {
+              title: "Task execution scheduled",
+              description: `Outside the project's execution window, starts at ${new Date(data.scheduled_for!).toLocaleString()}`,
+            },
+      );
       refresh();
     } catch (e) {
       toast({
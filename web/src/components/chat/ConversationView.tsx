@@ -5,7 +5,7 @@ import { AssistantMessage } from "./AssistantMessage";
 import { ToolCallMessage } from "./ToolCallMessage";
 import { PlanMessage } from "./PlanMessage";
 import { SystemMessage } from "./SystemMessage";
-import { PermissionMessage } from "./PermissionMessage";
+import { PermissionMessage, type CommandPreview, type PermissionReview } from "./PermissionMessage";
 import {
   type OutputBatchData,
   type AgentMessage,
@@ -40,6 +40,8 @@ interface PermissionRequestData {
     title?: string;
     kind?: string;
   };
+  review?: PermissionReview;
+  preview?: CommandPreview;
 }
 
 interface PermissionResponseData {
@@ -55,7 +57,10 @@ interface PermissionState {
   request_id: string;
   session_id: string;
   tool_call?: { title?: string; kind?: string };
+  review?: PermissionReview;
+  preview?: CommandPreview;
   outcome?: { selected?: { option_id: string }; cancelled?: boolean };
+  expired?: boolean;
 }
 
 interface HumanMessageData {
@@ -106,6 +111,8 @@ export function ConversationView({
             request_id: data.request_id,
             session_id: data.session_id,
             tool_call: data.tool_call,
+            review: data.review,
+            preview: data.preview,
           });
         }
         continue;
@@ -125,6 +132,17 @@ export function ConversationView({
         continue;
       }
 
+      // The server cancels requests nobody answered in time
+      if (event.kind === "permission.expired") {
+        const data = event.data as unknown as { request_id: string };
+        const key = `${data.request_id}-${event.session_id}`;
+        const existing = permissionStates.get(key);
+        if (existing) {
+          permissionStates.set(key, { ...existing, expired: true });
+        }
+        continue;
+      }
+
       // Build tool states (tool_result updates tool_use)
       if (event.kind !== "agent.output_batch") continue;
       const data = event.data as unknown as OutputBatchData;
@@ -189,8 +207,8 @@ export function ConversationView({
         continue;
       }
 
-      // Skip permission.responded - merged into permission.requested above
-      if (event.kind === "permission.responded") continue;
+      // Skip permission.responded/expired - merged into permission.requested above
+      if (event.kind === "permission.responded" || event.kind === "permission.expired") continue;
 
       // Handle human.message events
       if (event.kind === "human.message") {
@@ -379,7 +397,10 @@ export function ConversationView({
                   agentId={msg.agentId}
                   taskId={msg.taskId}
                   toolCall={msg.permissionData?.tool_call}
+                  review={msg.permissionData?.review}
+                  preview={msg.permissionData?.preview}
                   outcome={msg.permissionData?.outcome}
+                  expired={msg.permissionData?.expired}
                   timestamp={msg.timestamp}
                 />
               );
@@ -3,7 +3,29 @@ import { Button } from "@/components/ui/button";
 import { Badge } from "@/components/ui/badge";
 import { cn } from "@/lib/utils";
 import { useState } from "react";
-import { emitEvent } from "@/api/eventBus";
+import { respondPermission } from "@/api/permissions";
+
+/** The server's automatic review of the request */
+export interface PermissionReview {
+  decision: "allow" | "deny" | "manual";
+  reason: string;
+  risk_level: "low" | "medium" | "high";
+}
+
+/** Output of the relay's dry run of the command */
+export interface CommandPreview {
+  mode: "dry_run" | "sandbox";
+  command: string;
+  exit_code?: number;
+  output: string;
+  truncated?: boolean;
+}
+
+const riskStyles: Record<PermissionReview["risk_level"], string> = {
+  low: "text-green-600 border-green-300",
+  medium: "text-orange-600 border-orange-300",
+  high: "text-red-600 border-red-300",
+};
 
 interface PermissionMessageProps {
   requestId: string;
@@ -14,21 +36,25 @@ interface PermissionMessageProps {
     title?: string;
     kind?: string;
   };
+  review?: PermissionReview;
+  preview?: CommandPreview;
   outcome?: {
     selected?: { option_id: string };
     cancelled?: boolean;
   };
+  /** Nobody answered in time and the server cancelled the request */
+  expired?: boolean;
   timestamp?: number;
   className?: string;
 }
 
 export function PermissionMessage({
   requestId,
-  sessionId,
-  agentId,
-  taskId,
   toolCall,
+  review,
+  preview,
   outcome,
+  expired,
   timestamp,
   className,
 }: PermissionMessageProps) {
@@ -37,34 +63,33 @@ export function PermissionMessage({
   const [error, setError] = useState<string | null>(null);
 
   // Use outcome from props (merged state) or local state
-  const isResponded = !!outcome || !!localOutcome;
-  const responseLabel = outcome?.cancelled
-    ? "Rejected"
-    : outcome?.selected?.option_id === "allow_always"
-      ? "Always Allowed"
-      : outcome?.selected?.option_id === "allow"
-        ? "Allowed"
-        : localOutcome;
+  const isResponded = !!outcome || !!expired || !!localOutcome;
+  const responseLabel = expired
+    ? "Expired"
+    : outcome?.cancelled
+      ? "Rejected"
+      : outcome?.selected?.option_id === "allow_always"
+        ? "Always Allowed"
+        : outcome?.selected?.option_id === "allow"
+          ? "Allowed"
+          : localOutcome;
 
   const handleRespond = async (action: "allow" | "allow_always" | "reject") => {
     setIsLoading(true);
     setError(null);
 
     try {
-      await emitEvent({
-        agent_id: agentId,
-        session_id: sessionId,
-        task_id: taskId,
-        kind: "permission.responded",
-        data: {
-          relay_id: "",
-          request_id: requestId,
-          session_id: sessionId,
-          outcome: action === "reject" ? { cancelled: true } : { selected: { option_id: action } },
-        },
-      });
-
-      setLocalOutcome(action === "reject" ? "Rejected" : action === "allow_always" ? "Always Allowed" : "Allowed");
+      const response = await respondPermission({
+        request_id: requestId,
+        outcome: action === "reject" ? { cancelled: true } : { selected: { option_id: action } },
+      } as any);
+
+      // High-risk requests wait for a second person to approve
+      if (!(response.data as any).decided) {
+        setLocalOutcome("Approved, waiting for a second approver");
+      } else {
+        setLocalOutcome(action === "reject" ? "Rejected" : action === "allow_always" ? "Always Allowed" : "Allowed");
+      }
     } catch (err) {
       setError(err instanceof Error ? err.message : "Failed to respond");
     } finally {
@@ -72,7 +97,7 @@ export function PermissionMessage({
     }
   };
 
-  const isRejected = outcome?.cancelled || localOutcome === "Rejected";
+  const isRejected = expired || outcome?.cancelled || localOutcome === "Rejected";
 
   return (
     <div className={cn("flex gap-3 py-3", className)}>
@@ -122,6 +147,30 @@ export function PermissionMessage({
           </div>
         )}
 
+        {/* Dry run output */}
+        {preview && (
+          <details className="mb-3 text-xs">
+            <summary className="cursor-pointer text-slate-500">
+              {preview.mode === "sandbox" ? "Sandbox run" : "Dry run"}: <span className="font-mono">{preview.command}</span>
+              {preview.exit_code !== undefined && ` (exit ${preview.exit_code})`}
+            </summary>
+            <pre className="mt-1 max-h-60 overflow-auto whitespace-pre-wrap bg-slate-50 p-2 rounded border border-slate-200 font-mono text-slate-700">
+              {preview.output || "(no output)"}
+              {preview.truncated && "\n…"}
+            </pre>
+          </details>
+        )}
+
+        {/* Reviewer's assessment */}
+        {review && (
+          <div className="flex items-start gap-2 mb-3 text-xs text-slate-600">
+            <Badge variant="outline" className={cn("shrink-0", riskStyles[review.risk_level])}>
+              {review.risk_level} risk
+            </Badge>
+            <span>{review.reason}</span>
+          </div>
+        )}
+
         {/* Action buttons or responded state */}
         {isResponded ? (
           <Badge
@@ -7,7 +7,19 @@ export interface OutputBatchData {
   ts: number;
 }
 
-export type StreamType = "system" | "assistant" | "plan" | "tool_use" | "tool_result";
+// Mirrors OutputStreamKind in todoki-protocol; unknown kinds are passed through as-is
+export type StreamType =
+  | "assistant"
+  | "thinking"
+  | "tool_use"
+  | "tool_result"
+  | "user"
+  | "plan"
+  | "system"
+  | "stdout"
+  | "stderr"
+  | "terminal"
+  | (string & {});
 
 export interface AgentMessage {
   type: "agent_message";
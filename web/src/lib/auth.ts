@@ -24,3 +24,37 @@ export async function validateToken(token: string): Promise<boolean> {
     return false;
   }
 }
+
+/**
+ * Store the session token an OIDC login hands back in the URL fragment
+ * (`#token=...`) and remove it from the address bar.
+ */
+export function consumeLoginToken(): void {
+  const params = new URLSearchParams(window.location.hash.slice(1));
+  const token = params.get("token");
+  if (!token) return;
+
+  setToken(token);
+  window.history.replaceState(
+    null,
+    "",
+    window.location.pathname + window.location.search
+  );
+}
+
+export async function isOidcEnabled(): Promise<boolean> {
+  try {
+    const response = await fetch(
+      `${import.meta.env.VITE_API_URL}/api/auth/config`
+    );
+    if (!response.ok) return false;
+    const config: { oidc_enabled: boolean } = await response.json();
+    return config.oidc_enabled;
+  } catch {
+    return false;
+  }
+}
+
+export function oidcLoginUrl(): string {
+  return `${import.meta.env.VITE_API_URL}/api/auth/oidc/login`;
+}
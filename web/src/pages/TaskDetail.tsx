@@ -12,6 +12,7 @@ import {
   Circle,
   Clock,
   ExternalLink,
+  GitBranch,
   GitPullRequest,
   Inbox,
   Link as LinkIcon,
@@ -36,6 +37,24 @@ import { executeTask } from "../api/tasks";
 import type { TaskEvent, TaskStatus } from "../api/types";
 import { EventTimeline } from "@/components/EventTimeline";
 
+/** Pull and merge request artifacts, linked to the forge */
+const REVIEW_ARTIFACT_TYPES = ["github_pr", "gitlab_mr", "gitea_pr"];
+
+const CI_BADGE: Record<string, { label: string; className: string }> = {
+  pending: { label: "CI running", className: "bg-amber-50 text-amber-700 border-amber-200" },
+  passed: { label: "CI passed", className: "bg-green-50 text-green-700 border-green-200" },
+  failed: { label: "CI failed", className: "bg-red-50 text-red-700 border-red-200" },
+};
+
+function CiBadge({ status }: { status?: string }) {
+  const badge = CI_BADGE[status ?? "pending"] ?? CI_BADGE.pending;
+  return (
+    <Badge variant="outline" className={cn("text-xs shrink-0", badge.className)}>
+      {badge.label}
+    </Badge>
+  );
+}
+
 const STATUS_CONFIG: Record<
   string,
   { label: string; color: string }
@@ -124,10 +143,17 @@ export default function TaskDetail() {
     setIsExecuting(true);
     try {
       const { data } = await executeTask({ task_id: id! });
-      toast({
-        title: "Task execution started",
-        description: `Agent ${data.agent.name} is now running`,
-      });
+      toast(
+        data.agent
+          ? {
+              title: "Task execution started",
+              description: `Agent ${data.agent.name} is now running`,
+            }
+          : {
+              title: "Task execution scheduled",
+              description: `Outside the project's execution window, starts at ${new Date(data.scheduled_for!).toLocaleString()}`,
+            },
+      );
       refresh();
     } catch (e) {
       toast({
@@ -371,7 +397,7 @@ export default function TaskDetail() {
                 key={artifact.id}
                 className="flex items-center gap-3 p-3 border border-slate-200 rounded-lg bg-white hover:border-slate-300 transition-colors"
               >
-                {artifact.artifact_type === "github_pr" ? (
+                {REVIEW_ARTIFACT_TYPES.includes(artifact.artifact_type) ? (
                   <>
                     <GitPullRequest className="h-5 w-5 text-purple-500" />
                     <div className="flex-1 min-w-0">
@@ -381,13 +407,36 @@ export default function TaskDetail() {
                         rel="noopener noreferrer"
                         className="text-sm font-medium text-slate-700 hover:text-purple-600 flex items-center gap-1"
                       >
-                        {artifact.data.owner}/{artifact.data.repo}#{artifact.data.number}
+                        {artifact.artifact_type === "gitlab_mr"
+                          ? `${artifact.data.project}!${artifact.data.number}`
+                          : `${artifact.data.owner}/${artifact.data.repo}#${artifact.data.number}`}
                         <ExternalLink className="h-3 w-3" />
                       </a>
                       <p className="text-xs text-slate-400 truncate">
                         {artifact.data.url as string}
                       </p>
                     </div>
+                    {typeof artifact.data.state === "string" && (
+                      <span className="text-xs text-slate-500">{artifact.data.state}</span>
+                    )}
+                  </>
+                ) : artifact.artifact_type === "branch" ? (
+                  <>
+                    <GitBranch className="h-5 w-5 text-slate-500" />
+                    <div className="flex-1 min-w-0">
+                      <span className="text-sm font-medium text-slate-700 font-mono">
+                        {artifact.data.name as string}
+                      </span>
+                      <p className="text-xs text-slate-400 truncate">
+                        {artifact.data.owner
+                          ? `${artifact.data.owner}/${artifact.data.repo}`
+                          : (artifact.data.remote_url as string) || "local"}{" "}
+                        @ {String(artifact.data.head ?? "").slice(0, 7)}
+                      </p>
+                    </div>
+                    {artifact.data.owner && (
+                      <CiBadge status={(artifact.data.ci as { status?: string })?.status} />
+                    )}
                   </>
                 ) : (
                   <>
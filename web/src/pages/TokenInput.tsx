@@ -8,8 +8,13 @@ import {
   CardHeader,
   CardTitle,
 } from "@/components/ui/card";
-import { useState } from "react";
-import { setToken, validateToken } from "@/lib/auth";
+import { useEffect, useState } from "react";
+import {
+  isOidcEnabled,
+  oidcLoginUrl,
+  setToken,
+  validateToken,
+} from "@/lib/auth";
 import { Loader2 } from "lucide-react";
 
 interface Props {
@@ -20,6 +25,11 @@ export default function TokenInput({ onSuccess }: Props) {
   const [token, setTokenValue] = useState("");
   const [isLoading, setIsLoading] = useState(false);
   const [error, setError] = useState("");
+  const [oidcEnabled, setOidcEnabled] = useState(false);
+
+  useEffect(() => {
+    isOidcEnabled().then(setOidcEnabled);
+  }, []);
 
   const handleSubmit = async (e: React.FormEvent) => {
     e.preventDefault();
@@ -58,7 +68,7 @@ export default function TokenInput({ onSuccess }: Props) {
             />
             {error && <p className="text-sm text-red-500 mt-2">{error}</p>}
           </CardContent>
-          <CardFooter>
+          <CardFooter className="flex-col gap-2">
             <Button
               type="submit"
               className="w-full"
@@ -73,6 +83,11 @@ export default function TokenInput({ onSuccess }: Props) {
                 "确认"
               )}
             </Button>
+            {oidcEnabled && (
+              <Button asChild variant="outline" className="w-full">
+                <a href={oidcLoginUrl()}>使用 SSO 登录</a>
+              </Button>
+            )}
           </CardFooter>
         </form>
       </Card>